@@ -0,0 +1,311 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::net::IpAddr;
+use std::sync::Arc;
+
+use cheetah_string::CheetahString;
+use parking_lot::RwLock;
+use rocketmq_common::common::base::plain_access_config::PlainAccessConfig;
+use rocketmq_common::common::broker::broker_config::BrokerConfig;
+use rocketmq_common::common::config_manager::ConfigManager;
+use rocketmq_remoting::code::request_code::RequestCode;
+use rocketmq_remoting::code::response_code::ResponseCode;
+use rocketmq_remoting::protocol::remoting_command::RemotingCommand;
+use serde::Deserialize;
+use serde::Serialize;
+use tracing::warn;
+
+use crate::broker_path_config_helper::get_admin_acl_path;
+
+pub(crate) const ACCESS_KEY: &str = "AccessKey";
+const ALL_ADMIN_PERMS: &str = "*";
+
+/// Admin operations that change broker state and need an explicit grant, as opposed to the
+/// data-plane PUB/SUB permissions of an account.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum AdminOperation {
+    UpdateTopic,
+    DeleteTopic,
+    UpdateSubscriptionGroup,
+    DeleteSubscriptionGroup,
+    ResetOffset,
+    UpdateBrokerConfig,
+    UpdateStaticTopic,
+    Maintenance,
+    Snapshot,
+    FlushStore,
+    ResumeQueue,
+    ResolveTransaction,
+}
+
+impl AdminOperation {
+    /// Returns `None` for read-only and data-plane requests.
+    pub fn of(request_code: RequestCode) -> Option<Self> {
+        match request_code {
+            RequestCode::UpdateAndCreateTopic | RequestCode::UpdateAndCreateTopicList => {
+                Some(AdminOperation::UpdateTopic)
+            }
+            RequestCode::DeleteTopicInBroker => Some(AdminOperation::DeleteTopic),
+            RequestCode::UpdateAndCreateSubscriptionGroup
+            | RequestCode::CreateReplaySubscription => {
+                Some(AdminOperation::UpdateSubscriptionGroup)
+            }
+            RequestCode::DeleteSubscriptionGroup | RequestCode::DeleteReplaySubscription => {
+                Some(AdminOperation::DeleteSubscriptionGroup)
+            }
+            RequestCode::InvokeBrokerToResetOffset | RequestCode::ResetConsumerOffsetInBroker => {
+                Some(AdminOperation::ResetOffset)
+            }
+            RequestCode::UpdateBrokerConfig => Some(AdminOperation::UpdateBrokerConfig),
+            RequestCode::UpdateQueueWriteFence => Some(AdminOperation::UpdateStaticTopic),
+            RequestCode::UpdateMaintenanceMode | RequestCode::UpdateBrokerDrain => {
+                Some(AdminOperation::Maintenance)
+            }
+            RequestCode::CreateStoreSnapshot => Some(AdminOperation::Snapshot),
+            RequestCode::FlushStore | RequestCode::DeleteExpiredCommitlog => {
+                Some(AdminOperation::FlushStore)
+            }
+            RequestCode::ResumePausedQueue => Some(AdminOperation::ResumeQueue),
+            RequestCode::ResolveOrphanedTransaction => Some(AdminOperation::ResolveTransaction),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            AdminOperation::UpdateTopic => "updateTopic",
+            AdminOperation::DeleteTopic => "deleteTopic",
+            AdminOperation::UpdateSubscriptionGroup => "updateSubscriptionGroup",
+            AdminOperation::DeleteSubscriptionGroup => "deleteSubscriptionGroup",
+            AdminOperation::ResetOffset => "resetOffset",
+            AdminOperation::UpdateBrokerConfig => "updateBrokerConfig",
+            AdminOperation::UpdateStaticTopic => "updateStaticTopic",
+            AdminOperation::Maintenance => "maintenance",
+            AdminOperation::Snapshot => "snapshot",
+            AdminOperation::FlushStore => "flushStore",
+            AdminOperation::ResumeQueue => "resumeQueue",
+            AdminOperation::ResolveTransaction => "resolveTransaction",
+        }
+    }
+}
+
+impl Display for AdminOperation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AdminAclWrapper {
+    accounts: Vec<PlainAccessConfig>,
+}
+
+/// Checks admin requests against the accounts in `config/adminAcl.json`. Accounts with the
+/// `admin` flag may run every operation, other accounts only those listed in `adminPerms`.
+pub(crate) struct AdminAccessValidator {
+    broker_config: Arc<BrokerConfig>,
+    accounts: RwLock<HashMap<CheetahString, PlainAccessConfig>>,
+}
+
+impl AdminAccessValidator {
+    pub fn new(broker_config: Arc<BrokerConfig>) -> Self {
+        AdminAccessValidator {
+            broker_config,
+            accounts: RwLock::new(HashMap::new()),
+        }
+    }
+
+    pub fn update_account(&self, account: PlainAccessConfig) {
+        if let Some(access_key) = account.access_key.clone() {
+            self.accounts.write().insert(access_key, account);
+        }
+    }
+
+    pub fn remove_account(&self, access_key: &str) -> Option<PlainAccessConfig> {
+        self.accounts.write().remove(access_key)
+    }
+
+    /// Returns the rejection response when the caller may not run the admin request.
+    pub fn check(
+        &self,
+        request_code: RequestCode,
+        request: &RemotingCommand,
+        remote_ip: IpAddr,
+    ) -> Option<RemotingCommand> {
+        if !self.broker_config.admin_acl_enable {
+            return None;
+        }
+        let operation = AdminOperation::of(request_code)?;
+        let access_key = request
+            .ext_fields()
+            .and_then(|fields| fields.get(ACCESS_KEY))
+            .cloned()
+            .unwrap_or_default();
+        let denied = match self.accounts.read().get(&access_key) {
+            None => Some(format!("no account for AccessKey [{}]", access_key)),
+            Some(account) if !is_remote_address_allowed(account, remote_ip) => Some(format!(
+                "AccessKey [{}] is not allowed from {}",
+                access_key, remote_ip
+            )),
+            Some(account) if !is_operation_granted(account, operation) => Some(format!(
+                "AccessKey [{}] has no permission for admin operation [{}]",
+                access_key, operation
+            )),
+            Some(_) => None,
+        };
+        denied.map(|remark| {
+            warn!("reject admin request {:?}: {}", request_code, remark);
+            RemotingCommand::create_response_command_with_code_remark(
+                ResponseCode::NoPermission,
+                remark,
+            )
+        })
+    }
+}
+
+fn is_operation_granted(account: &PlainAccessConfig, operation: AdminOperation) -> bool {
+    account.admin
+        || account
+            .admin_perms
+            .iter()
+            .any(|perm| perm == ALL_ADMIN_PERMS || perm == operation.name())
+}
+
+/// `whiteRemoteAddress` is a comma separated list of IPs, `*` wildcards match a whole segment.
+fn is_remote_address_allowed(account: &PlainAccessConfig, remote_ip: IpAddr) -> bool {
+    let white_remote_address = match account.white_remote_address.as_ref() {
+        Some(address) if !address.trim().is_empty() => address,
+        _ => return true,
+    };
+    let remote_ip = remote_ip.to_string();
+    white_remote_address
+        .split(',')
+        .map(str::trim)
+        .any(|pattern| {
+            pattern == ALL_ADMIN_PERMS || {
+                let segments = pattern.split('.').collect::<Vec<_>>();
+                let remote_segments = remote_ip.split('.').collect::<Vec<_>>();
+                segments.len() == remote_segments.len()
+                    && segments
+                        .iter()
+                        .zip(remote_segments.iter())
+                        .all(|(pattern, segment)| *pattern == "*" || pattern == segment)
+            }
+        })
+}
+
+impl ConfigManager for AdminAccessValidator {
+    fn config_file_path(&self) -> String {
+        get_admin_acl_path(self.broker_config.store_path_root_dir.as_str())
+    }
+
+    fn encode_pretty(&self, pretty_format: bool) -> String {
+        let mut accounts = self.accounts.read().values().cloned().collect::<Vec<_>>();
+        accounts.sort_by(|a, b| a.access_key.cmp(&b.access_key));
+        let wrapper = AdminAclWrapper { accounts };
+        match pretty_format {
+            true => serde_json::to_string_pretty(&wrapper),
+            false => serde_json::to_string(&wrapper),
+        }
+        .expect("encode admin acl failed")
+    }
+
+    fn decode(&self, json_string: &str) {
+        if json_string.is_empty() {
+            return;
+        }
+        let wrapper = serde_json::from_str::<AdminAclWrapper>(json_string).unwrap_or_default();
+        let mut accounts = self.accounts.write();
+        accounts.clear();
+        for account in wrapper.accounts {
+            if let Some(access_key) = account.access_key.clone() {
+                accounts.insert(access_key, account);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn validator() -> AdminAccessValidator {
+        let broker_config = BrokerConfig {
+            admin_acl_enable: true,
+            ..Default::default()
+        };
+        let validator = AdminAccessValidator::new(Arc::new(broker_config));
+        validator.decode(
+            r#"{"accounts":[
+                {"accessKey":"ops","admin":false,"topicPerms":[],"groupPerms":[],
+                 "adminPerms":["updateTopic","resetOffset"],"whiteRemoteAddress":"10.0.*.*"},
+                {"accessKey":"root","admin":true,"topicPerms":[],"groupPerms":[]}
+            ]}"#,
+        );
+        validator
+    }
+
+    fn request(access_key: &str) -> RemotingCommand {
+        RemotingCommand::create_remoting_command(RequestCode::UpdateAndCreateTopic)
+            .set_ext_fields(HashMap::from([(ACCESS_KEY.into(), access_key.into())]))
+    }
+
+    #[test]
+    fn classifies_admin_operations() {
+        assert_eq!(
+            AdminOperation::of(RequestCode::DeleteSubscriptionGroup),
+            Some(AdminOperation::DeleteSubscriptionGroup)
+        );
+        assert_eq!(AdminOperation::of(RequestCode::SendMessage), None);
+        assert_eq!(AdminOperation::of(RequestCode::GetBrokerConfig), None);
+    }
+
+    #[test]
+    fn checks_admin_perms_per_operation() {
+        let validator = validator();
+        let ip: IpAddr = "10.0.1.2".parse().unwrap();
+        assert!(validator
+            .check(RequestCode::UpdateAndCreateTopic, &request("ops"), ip)
+            .is_none());
+        assert!(validator
+            .check(RequestCode::InvokeBrokerToResetOffset, &request("ops"), ip)
+            .is_none());
+        let response = validator
+            .check(RequestCode::DeleteSubscriptionGroup, &request("ops"), ip)
+            .unwrap();
+        assert_eq!(response.code(), ResponseCode::NoPermission as i32);
+        assert!(validator
+            .check(RequestCode::DeleteSubscriptionGroup, &request("root"), ip)
+            .is_none());
+        assert!(validator
+            .check(
+                RequestCode::UpdateAndCreateTopic,
+                &request("ops"),
+                "192.168.0.1".parse().unwrap()
+            )
+            .is_some());
+        assert!(validator
+            .check(RequestCode::UpdateAndCreateTopic, &request("unknown"), ip)
+            .is_some());
+        assert!(validator
+            .check(RequestCode::GetBrokerConfig, &request("unknown"), ip)
+            .is_none());
+    }
+}
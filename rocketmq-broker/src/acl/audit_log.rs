@@ -0,0 +1,311 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::fs;
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use bytes::Bytes;
+use cheetah_string::CheetahString;
+use parking_lot::Mutex;
+use rocketmq_common::common::broker::broker_config::BrokerConfig;
+use rocketmq_common::common::message::message_ext_broker_inner::MessageExtBrokerInner;
+use rocketmq_common::common::message::MessageTrait;
+use rocketmq_common::MessageDecoder;
+use rocketmq_common::TimeUtils::get_current_millis;
+use rocketmq_remoting::code::request_code::RequestCode;
+use rocketmq_remoting::protocol::remoting_command::RemotingCommand;
+use serde::Serialize;
+use tracing::error;
+
+use crate::acl::admin_access_validator::AdminOperation;
+use crate::acl::admin_access_validator::ACCESS_KEY;
+use crate::broker_path_config_helper::get_audit_log_dir;
+
+const AUDIT_LOG_FILE_NAME: &str = "audit.log";
+const AUDIT_MESSAGE_TAG: &str = "AUDIT";
+const ANONYMOUS_PRINCIPAL: &str = "anonymous";
+
+/// Ext fields naming the resource an admin request works on, in lookup order.
+const RESOURCE_FIELDS: [&str; 5] = ["topic", "consumerGroup", "group", "groupName", "key"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub(crate) enum AuditOutcome {
+    Success,
+    Failure,
+    Denied,
+}
+
+/// One line of the audit log, serialized as JSON.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct AuditRecord {
+    pub timestamp: u64,
+    pub principal: String,
+    pub remote_address: String,
+    pub request_code: i32,
+    pub operation: String,
+    pub resource: String,
+    pub outcome: AuditOutcome,
+    pub response_code: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remark: Option<String>,
+}
+
+impl AuditRecord {
+    /// Starts the record of an admin mutation, `None` for read-only and data-plane requests.
+    /// The outcome is filled in by [`AuditRecord::complete`] once the request was handled.
+    pub fn of(
+        request_code: RequestCode,
+        request: &RemotingCommand,
+        remote_address: SocketAddr,
+    ) -> Option<Self> {
+        let operation = AdminOperation::of(request_code)?;
+        let ext_fields = request.ext_fields();
+        let principal = ext_fields
+            .and_then(|fields| fields.get(ACCESS_KEY))
+            .filter(|access_key| !access_key.is_empty())
+            .map(|access_key| access_key.to_string())
+            .unwrap_or_else(|| ANONYMOUS_PRINCIPAL.to_string());
+        let resource = ext_fields
+            .and_then(|fields| {
+                RESOURCE_FIELDS
+                    .iter()
+                    .find_map(|name| fields.get(*name))
+                    .map(|value| value.to_string())
+            })
+            .unwrap_or_default();
+        Some(AuditRecord {
+            timestamp: get_current_millis(),
+            principal,
+            remote_address: remote_address.to_string(),
+            request_code: request_code.to_i32(),
+            operation: operation.name().to_string(),
+            resource,
+            outcome: AuditOutcome::Success,
+            response_code: 0,
+            remark: None,
+        })
+    }
+
+    pub fn complete(mut self, response: Option<&RemotingCommand>, denied: bool) -> Self {
+        self.response_code = response.map(|response| response.code()).unwrap_or_default();
+        self.outcome = if denied {
+            AuditOutcome::Denied
+        } else if self.response_code == 0 {
+            AuditOutcome::Success
+        } else {
+            AuditOutcome::Failure
+        };
+        self.remark = response
+            .and_then(|response| response.remark())
+            .map(|remark| remark.to_string());
+        self
+    }
+}
+
+struct AuditFile {
+    file: File,
+    size: u64,
+}
+
+/// Appends admin mutations and admin ACL denials to `audit/audit.log` under the store root,
+/// separately from the broker log. The file is rolled to `audit.log.1`, `audit.log.2`, ...
+/// once it reaches `auditLogMaxFileSize`, keeping at most `auditLogMaxFiles` rolled files.
+pub(crate) struct AuditLog {
+    broker_config: Arc<BrokerConfig>,
+    dir: PathBuf,
+    current: Mutex<Option<AuditFile>>,
+}
+
+impl AuditLog {
+    pub fn new(broker_config: Arc<BrokerConfig>) -> Self {
+        let dir = PathBuf::from(get_audit_log_dir(
+            broker_config.store_path_root_dir.as_str(),
+        ));
+        AuditLog {
+            broker_config,
+            dir,
+            current: Mutex::new(None),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.broker_config.audit_log_enable
+    }
+
+    pub fn is_export_enabled(&self) -> bool {
+        self.broker_config.audit_log_enable && self.broker_config.audit_log_export_trace_topic
+    }
+
+    pub fn append(&self, record: &AuditRecord) {
+        if !self.is_enabled() {
+            return;
+        }
+        let mut line = match serde_json::to_string(record) {
+            Ok(line) => line,
+            Err(e) => {
+                error!("encode audit record failed: {}", e);
+                return;
+            }
+        };
+        line.push('\n');
+        if let Err(e) = self.write_line(line.as_bytes()) {
+            error!("write audit log failed: {}, record: {}", e, line.trim_end());
+        }
+    }
+
+    fn write_line(&self, line: &[u8]) -> std::io::Result<()> {
+        let mut current = self.current.lock();
+        if let Some(audit_file) = current.as_ref() {
+            if audit_file.size > 0
+                && audit_file.size + line.len() as u64 > self.broker_config.audit_log_max_file_size
+            {
+                *current = None;
+                self.roll()?;
+            }
+        }
+        if current.is_none() {
+            fs::create_dir_all(&self.dir)?;
+            let file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(self.dir.join(AUDIT_LOG_FILE_NAME))?;
+            let size = file.metadata()?.len();
+            *current = Some(AuditFile { file, size });
+        }
+        let audit_file = current.as_mut().unwrap();
+        audit_file.file.write_all(line)?;
+        audit_file.size += line.len() as u64;
+        Ok(())
+    }
+
+    /// Shifts every rolled file up by one and drops the oldest beyond the retention.
+    fn roll(&self) -> std::io::Result<()> {
+        let max_files = self.broker_config.audit_log_max_files.max(1);
+        let rolled = |index: u32| self.dir.join(format!("{}.{}", AUDIT_LOG_FILE_NAME, index));
+        remove_if_exists(&rolled(max_files))?;
+        for index in (1..max_files).rev() {
+            let from = rolled(index);
+            if from.exists() {
+                fs::rename(&from, rolled(index + 1))?;
+            }
+        }
+        fs::rename(self.dir.join(AUDIT_LOG_FILE_NAME), rolled(1))
+    }
+
+    /// Builds the message exporting `record` to the trace topic, keyed by principal so it can
+    /// be found with a query by key.
+    pub fn build_export_message(
+        &self,
+        record: &AuditRecord,
+        store_host: SocketAddr,
+    ) -> Option<MessageExtBrokerInner> {
+        let body = serde_json::to_vec(record).ok()?;
+        let mut msg_inner = MessageExtBrokerInner::default();
+        msg_inner.set_topic(self.broker_config.msg_trace_topic_name.clone());
+        msg_inner.set_body(Bytes::from(body));
+        msg_inner.set_tags(CheetahString::from_static_str(AUDIT_MESSAGE_TAG));
+        msg_inner.set_keys(CheetahString::from_string(record.principal.clone()));
+        msg_inner.properties_string =
+            MessageDecoder::message_properties_to_string(msg_inner.get_properties());
+        msg_inner.tags_code = MessageExtBrokerInner::tags_string_to_tags_code(AUDIT_MESSAGE_TAG);
+        msg_inner.message_ext_inner.born_timestamp = record.timestamp as i64;
+        msg_inner.message_ext_inner.born_host = store_host;
+        msg_inner.message_ext_inner.store_host = store_host;
+        Some(msg_inner)
+    }
+}
+
+fn remove_if_exists(path: &Path) -> std::io::Result<()> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    fn request(fields: &[(&str, &str)]) -> RemotingCommand {
+        let ext_fields = fields
+            .iter()
+            .map(|(k, v)| (CheetahString::from(*k), CheetahString::from(*v)))
+            .collect::<HashMap<_, _>>();
+        RemotingCommand::create_remoting_command(RequestCode::DeleteTopicInBroker)
+            .set_ext_fields(ext_fields)
+    }
+
+    #[test]
+    fn builds_records_for_admin_mutations_only() {
+        let remote: SocketAddr = "10.0.0.1:5000".parse().unwrap();
+        let delete_request = request(&[(ACCESS_KEY, "ops"), ("topic", "orders")]);
+        let record = AuditRecord::of(RequestCode::DeleteTopicInBroker, &delete_request, remote)
+            .unwrap()
+            .complete(None, true);
+        assert_eq!(record.principal, "ops");
+        assert_eq!(record.resource, "orders");
+        assert_eq!(record.operation, "deleteTopic");
+        assert_eq!(record.outcome, AuditOutcome::Denied);
+
+        let response = RemotingCommand::create_response_command();
+        let record = AuditRecord::of(RequestCode::DeleteTopicInBroker, &request(&[]), remote)
+            .unwrap()
+            .complete(Some(&response), false);
+        assert_eq!(record.principal, ANONYMOUS_PRINCIPAL);
+        assert_eq!(record.outcome, AuditOutcome::Success);
+
+        assert!(AuditRecord::of(RequestCode::GetBrokerConfig, &delete_request, remote).is_none());
+    }
+
+    #[test]
+    fn rolls_audit_file_when_full() {
+        let root = std::env::temp_dir().join(format!("audit_log_test_{}", get_current_millis()));
+        let broker_config = BrokerConfig {
+            audit_log_enable: true,
+            audit_log_max_file_size: 64,
+            audit_log_max_files: 2,
+            store_path_root_dir: root.to_string_lossy().to_string().into(),
+            ..BrokerConfig::default()
+        };
+        let audit_log = AuditLog::new(Arc::new(broker_config));
+        let remote: SocketAddr = "10.0.0.1:5000".parse().unwrap();
+        let record = AuditRecord::of(
+            RequestCode::DeleteTopicInBroker,
+            &request(&[("topic", "orders")]),
+            remote,
+        )
+        .unwrap()
+        .complete(None, true);
+        for _ in 0..4 {
+            audit_log.append(&record);
+        }
+        let dir = PathBuf::from(get_audit_log_dir(root.to_string_lossy().as_ref()));
+        assert!(dir.join(AUDIT_LOG_FILE_NAME).exists());
+        assert!(dir.join("audit.log.1").exists());
+        assert!(dir.join("audit.log.2").exists());
+        assert!(!dir.join("audit.log.3").exists());
+        let _ = fs::remove_dir_all(root);
+    }
+}
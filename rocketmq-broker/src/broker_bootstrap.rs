@@ -15,12 +15,22 @@
  * limitations under the License.
  */
 
+use std::sync::Arc;
+use std::time::Duration;
+
 use rocketmq_common::common::broker::broker_config::BrokerConfig;
 use rocketmq_common::common::server::config::ServerConfig;
+use rocketmq_common::common::server::lifecycle::start_health_server;
+use rocketmq_common::common::server::lifecycle::Lifecycle;
+use rocketmq_common::common::server::lifecycle::LifecycleState;
+use rocketmq_rust::wait_for_signal;
 use rocketmq_store::config::message_store_config::MessageStoreConfig;
+use tokio::runtime::Handle;
 use tracing::error;
+use tracing::info;
 
 use crate::broker_runtime::BrokerRuntime;
+use crate::hook::message_body_validator::MessageBodyValidator;
 
 pub struct BrokerBootstrap {
     broker_runtime: BrokerRuntime,
@@ -28,11 +38,37 @@ pub struct BrokerBootstrap {
 
 impl BrokerBootstrap {
     pub async fn boot(mut self) {
+        let lifecycle = Arc::new(Lifecycle::new());
+        let health_listen_port = self.broker_runtime.broker_config().health_listen_port;
+        if health_listen_port > 0 {
+            if let Err(err) = start_health_server(health_listen_port, lifecycle.clone()).await {
+                error!(
+                    "start health endpoint on port {} failed: {}",
+                    health_listen_port, err
+                );
+            }
+        }
+
+        lifecycle.transition(LifecycleState::Recovering);
         if !self.initialize().await {
             error!("initialize fail");
+            lifecycle.transition(LifecycleState::Stopped);
             return;
         }
-        let (_start_result, _ctrl_c) = tokio::join!(self.start(), tokio::signal::ctrl_c());
+        self.start().await;
+        lifecycle.transition(LifecycleState::Serving);
+
+        wait_for_signal().await;
+        // reject writes so producers move to other brokers while consumers finish pulling
+        lifecycle.transition(LifecycleState::Draining);
+        self.broker_runtime.begin_drain();
+        let grace_period = self
+            .broker_runtime
+            .broker_config()
+            .shutdown_grace_period_millis;
+        info!("broker draining, shutting down in {}ms", grace_period);
+        tokio::time::sleep(Duration::from_millis(grace_period)).await;
+        lifecycle.transition(LifecycleState::Stopped);
     }
 
     async fn initialize(&mut self) -> bool {
@@ -48,6 +84,9 @@ pub struct Builder {
     broker_config: BrokerConfig,
     message_store_config: MessageStoreConfig,
     server_config: ServerConfig,
+    runtime_handle: Option<Handle>,
+    store_runtime_handle: Option<Handle>,
+    message_body_validators: Vec<(String, Arc<dyn MessageBodyValidator>)>,
 }
 
 impl Builder {
@@ -56,6 +95,9 @@ impl Builder {
             broker_config: Default::default(),
             message_store_config: MessageStoreConfig::default(),
             server_config: Default::default(),
+            runtime_handle: None,
+            store_runtime_handle: None,
+            message_body_validators: Vec::new(),
         }
     }
 
@@ -74,14 +116,45 @@ impl Builder {
         self
     }
 
+    /// Runs the broker's scheduled tasks on `handle` instead of a dedicated `broker-thread`
+    /// runtime. The embedder keeps ownership of the runtime and shuts it down.
+    pub fn set_runtime_handle(mut self, handle: Handle) -> Self {
+        self.runtime_handle = Some(handle);
+        self
+    }
+
+    /// Runs the message store's flush, reput and cleaning services on `handle`, isolating the
+    /// IO-heavy store work from request processing.
+    pub fn set_store_runtime_handle(mut self, handle: Handle) -> Self {
+        self.store_runtime_handle = Some(handle);
+        self
+    }
+
+    /// Registers a body validator topics can refer to by `id` in their `body.validators`
+    /// attribute, e.g. one checking a JSON schema or a protobuf descriptor.
+    pub fn register_message_body_validator(
+        mut self,
+        id: impl Into<String>,
+        validator: Arc<dyn MessageBodyValidator>,
+    ) -> Self {
+        self.message_body_validators.push((id.into(), validator));
+        self
+    }
+
     pub fn build(self) -> BrokerBootstrap {
-        BrokerBootstrap {
-            broker_runtime: BrokerRuntime::new(
-                self.broker_config,
-                self.message_store_config,
-                self.server_config,
-            ),
+        let broker_runtime = BrokerRuntime::new(
+            self.broker_config,
+            self.message_store_config,
+            self.server_config,
+            self.runtime_handle,
+            self.store_runtime_handle,
+        );
+        for (id, validator) in self.message_body_validators {
+            broker_runtime
+                .message_body_validators()
+                .register(id, validator);
         }
+        BrokerBootstrap { broker_runtime }
     }
 }
 
@@ -44,6 +44,23 @@ pub fn get_topic_queue_mapping_path(root_dir: &str) -> String {
         .into_owned()
 }
 
+// Admin ACL accounts path
+pub fn get_admin_acl_path(root_dir: &str) -> String {
+    PathBuf::from(root_dir)
+        .join("config")
+        .join("adminAcl.json")
+        .to_string_lossy()
+        .into_owned()
+}
+
+// Audit log directory
+pub fn get_audit_log_dir(root_dir: &str) -> String {
+    PathBuf::from(root_dir)
+        .join("audit")
+        .to_string_lossy()
+        .into_owned()
+}
+
 // Consumer offset path
 pub fn get_consumer_offset_path(root_dir: &str) -> String {
     PathBuf::from(root_dir)
@@ -125,6 +142,15 @@ pub fn get_message_request_mode_path(root_dir: &str) -> String {
         .into_owned()
 }
 
+// Pop consumer kv store path
+pub fn get_pop_consumer_store_path(root_dir: &str) -> String {
+    PathBuf::from(root_dir)
+        .join("pop")
+        .join("consumer")
+        .to_string_lossy()
+        .into_owned()
+}
+
 #[cfg(test)]
 mod test {
     use std::path::PathBuf;
@@ -15,6 +15,7 @@
  * limitations under the License.
  */
 use std::collections::HashMap;
+use std::path::Path;
 use std::sync::atomic::AtomicBool;
 use std::sync::atomic::AtomicU64;
 use std::sync::atomic::Ordering;
@@ -30,56 +31,83 @@ use rocketmq_common::common::server::config::ServerConfig;
 use rocketmq_common::common::statistics::state_getter::StateGetter;
 use rocketmq_common::TimeUtils::get_current_millis;
 use rocketmq_common::UtilAll::compute_next_morning_time_millis;
+use rocketmq_remoting::auth::authentication_rpc_hook::AuthenticationRPCHook;
+use rocketmq_remoting::auth::jwt_authentication_provider::JwtAuthenticationConfig;
+use rocketmq_remoting::auth::jwt_authentication_provider::JwtAuthenticationProvider;
 use rocketmq_remoting::protocol::body::broker_body::broker_member_group::BrokerMemberGroup;
 use rocketmq_remoting::protocol::body::topic_info_wrapper::topic_config_wrapper::TopicConfigAndMappingSerializeWrapper;
 use rocketmq_remoting::protocol::body::topic_info_wrapper::topic_config_wrapper::TopicConfigSerializeWrapper;
 use rocketmq_remoting::protocol::namespace_util::NamespaceUtil;
+use rocketmq_remoting::protocol::route::broker_feature::BrokerFeature;
 use rocketmq_remoting::protocol::static_topic::topic_queue_mapping_detail::TopicQueueMappingDetail;
 use rocketmq_remoting::protocol::DataVersion;
 use rocketmq_remoting::remoting_server::server::RocketMQServer;
 use rocketmq_remoting::runtime::config::client_config::TokioClientConfig;
+use rocketmq_remoting::runtime::RPCHook;
 use rocketmq_runtime::RocketMQRuntime;
 use rocketmq_rust::ArcMut;
 use rocketmq_store::base::store_enum::StoreType;
+use rocketmq_store::base::store_event_bus::StoreEvent;
+use rocketmq_store::base::store_snapshot;
+use rocketmq_store::config::broker_role::BrokerRole;
 use rocketmq_store::config::message_store_config::MessageStoreConfig;
 use rocketmq_store::log_file::MessageStore;
 use rocketmq_store::message_store::default_message_store::DefaultMessageStore;
 use rocketmq_store::stats::broker_stats::BrokerStats;
 use rocketmq_store::stats::broker_stats_manager::BrokerStatsManager;
 use rocketmq_store::timer::timer_message_store::TimerMessageStore;
+use tokio::runtime::Handle;
+use tokio::sync::broadcast::error::RecvError;
+use tracing::error;
 use tracing::info;
 use tracing::warn;
 
+use crate::acl::admin_access_validator::AdminAccessValidator;
+use crate::acl::audit_log::AuditLog;
 use crate::broker::broker_hook::BrokerShutdownHook;
+#[cfg(feature = "rocksdb")]
+use crate::broker_path_config_helper::get_pop_consumer_store_path;
 use crate::client::default_consumer_ids_change_listener::DefaultConsumerIdsChangeListener;
 use crate::client::manager::consumer_manager::ConsumerManager;
 use crate::client::manager::producer_manager::ProducerManager;
 use crate::client::net::broker_to_client::Broker2Client;
 use crate::client::rebalance::rebalance_lock_manager::RebalanceLockManager;
+use crate::failover::broker_drain::BrokerDrain;
+use crate::failover::escape_bridge::EscapeBridge;
 use crate::filter::manager::consumer_filter_manager::ConsumerFilterManager;
 use crate::hook::batch_check_before_put_message::BatchCheckBeforePutMessageHook;
 use crate::hook::check_before_put_message::CheckBeforePutMessageHook;
+use crate::hook::handle_schedule_message::HandleScheduleMessageHook;
+use crate::hook::message_body_validator::MessageBodyValidatorRegistry;
 use crate::long_polling::long_polling_service::pull_request_hold_service::PullRequestHoldService;
 use crate::long_polling::notify_message_arriving_listener::NotifyMessageArrivingListener;
 use crate::offset::manager::broadcast_offset_manager::BroadcastOffsetManager;
 use crate::offset::manager::consumer_offset_manager::ConsumerOffsetManager;
 use crate::offset::manager::consumer_order_info_manager::ConsumerOrderInfoManager;
 use crate::out_api::broker_outer_api::BrokerOuterAPI;
+#[cfg(feature = "rocksdb")]
+use crate::pop::pop_consumer_rocksdb_store::PopConsumerRocksdbStore;
+use crate::pop::pop_consumer_service::PopConsumerService;
 use crate::processor::admin_broker_processor::AdminBrokerProcessor;
 use crate::processor::change_invisible_time_processor::ChangeInvisibleTimeProcessor;
 use crate::processor::client_manage_processor::ClientManageProcessor;
 use crate::processor::consumer_manage_processor::ConsumerManageProcessor;
 use crate::processor::default_pull_message_result_handler::DefaultPullMessageResultHandler;
 use crate::processor::end_transaction_processor::EndTransactionProcessor;
+use crate::processor::maintenance_mode::MaintenanceMode;
+use crate::processor::poison_message_tracker::PoisonMessageTracker;
 use crate::processor::pull_message_processor::PullMessageProcessor;
 use crate::processor::pull_message_result_handler::PullMessageResultHandler;
 use crate::processor::query_assignment_processor::QueryAssignmentProcessor;
 use crate::processor::query_message_processor::QueryMessageProcessor;
 use crate::processor::reply_message_processor::ReplyMessageProcessor;
+use crate::processor::request_priority_dispatcher::RequestPriorityDispatcher;
 use crate::processor::send_message_processor::SendMessageProcessor;
 use crate::processor::BrokerRequestProcessor;
 use crate::schedule::schedule_message_service::ScheduleMessageService;
 use crate::subscription::manager::subscription_group_manager::SubscriptionGroupManager;
+use crate::subscription::replay_subscription_manager::release_replay_subscription;
+use crate::subscription::replay_subscription_manager::ReplaySubscriptionManager;
 use crate::topic::manager::topic_config_manager::TopicConfigManager;
 use crate::topic::manager::topic_queue_mapping_manager::TopicQueueMappingManager;
 use crate::topic::manager::topic_route_info_manager::TopicRouteInfoManager;
@@ -89,6 +117,7 @@ use crate::transaction::queue::default_transactional_message_service::DefaultTra
 use crate::transaction::queue::transactional_message_bridge::TransactionalMessageBridge;
 use crate::transaction::transaction_metrics_flush_service::TransactionMetricsFlushService;
 use crate::transaction::transactional_message_check_service::TransactionalMessageCheckService;
+use crate::transaction::transactional_message_service::TransactionalMessageService;
 
 pub(crate) struct BrokerRuntime {
     broker_config: Arc<BrokerConfig>,
@@ -136,6 +165,20 @@ pub(crate) struct BrokerRuntime {
     transactional_message_check_service: Option<Arc<TransactionalMessageCheckService>>,
     transaction_metrics_flush_service: Option<Arc<TransactionMetricsFlushService>>,
     topic_route_info_manager: Arc<TopicRouteInfoManager>,
+    #[cfg(feature = "local_file_store")]
+    pop_consumer_service: Option<PopConsumerService<DefaultMessageStore>>,
+    request_priority_dispatcher: Arc<RequestPriorityDispatcher>,
+    maintenance_mode: Arc<MaintenanceMode>,
+    admin_access_validator: Arc<AdminAccessValidator>,
+    audit_log: Arc<AuditLog>,
+    broker_drain: Arc<BrokerDrain>,
+    poison_message_tracker: Arc<PoisonMessageTracker>,
+    message_body_validators: Arc<MessageBodyValidatorRegistry>,
+    replay_subscription_manager: Arc<ReplaySubscriptionManager>,
+    // runtime the message store spawns its services on, `None` for the ambient one
+    store_runtime_handle: Option<Handle>,
+    #[cfg(feature = "local_file_store")]
+    escape_bridge: Option<ArcMut<EscapeBridge<DefaultMessageStore>>>,
 }
 
 impl Clone for BrokerRuntime {
@@ -175,6 +218,17 @@ impl Clone for BrokerRuntime {
             transactional_message_check_service: None,
             transaction_metrics_flush_service: None,
             topic_route_info_manager: self.topic_route_info_manager.clone(),
+            pop_consumer_service: self.pop_consumer_service.clone(),
+            request_priority_dispatcher: self.request_priority_dispatcher.clone(),
+            maintenance_mode: self.maintenance_mode.clone(),
+            admin_access_validator: self.admin_access_validator.clone(),
+            audit_log: self.audit_log.clone(),
+            broker_drain: self.broker_drain.clone(),
+            poison_message_tracker: self.poison_message_tracker.clone(),
+            message_body_validators: self.message_body_validators.clone(),
+            replay_subscription_manager: self.replay_subscription_manager.clone(),
+            store_runtime_handle: self.store_runtime_handle.clone(),
+            escape_bridge: self.escape_bridge.clone(),
         }
     }
 }
@@ -184,9 +238,14 @@ impl BrokerRuntime {
         broker_config: BrokerConfig,
         message_store_config: MessageStoreConfig,
         server_config: ServerConfig,
+        runtime_handle: Option<Handle>,
+        store_runtime_handle: Option<Handle>,
     ) -> Self {
         let broker_config = Arc::new(broker_config);
-        let runtime = RocketMQRuntime::new_multi(10, "broker-thread");
+        let runtime = match runtime_handle {
+            Some(handle) => RocketMQRuntime::from_handle(handle),
+            None => RocketMQRuntime::new_multi(10, "broker-thread"),
+        };
         let broker_outer_api =
             Arc::new(BrokerOuterAPI::new(Arc::new(TokioClientConfig::default())));
         let server_config = Arc::new(server_config);
@@ -226,6 +285,11 @@ impl BrokerRuntime {
             broker_config.broker_identity.broker_id,
             broker_config.get_broker_addr().into(),
         );
+        let request_priority_dispatcher = Arc::new(RequestPriorityDispatcher::new(&broker_config));
+        let maintenance_mode = Arc::new(MaintenanceMode::new(&broker_config));
+        let admin_access_validator = Arc::new(AdminAccessValidator::new(broker_config.clone()));
+        let audit_log = Arc::new(AuditLog::new(broker_config.clone()));
+        let poison_message_tracker = Arc::new(PoisonMessageTracker::new(&broker_config));
         Self {
             broker_config: broker_config.clone(),
             message_store_config,
@@ -267,9 +331,24 @@ impl BrokerRuntime {
                 broker_outer_api,
                 broker_config,
             )),
+            pop_consumer_service: None,
+            request_priority_dispatcher,
+            maintenance_mode,
+            admin_access_validator,
+            audit_log,
+            broker_drain: Arc::new(BrokerDrain::default()),
+            poison_message_tracker,
+            message_body_validators: Arc::new(MessageBodyValidatorRegistry::default()),
+            replay_subscription_manager: Arc::new(ReplaySubscriptionManager::default()),
+            store_runtime_handle,
+            escape_bridge: None,
         }
     }
 
+    pub(crate) fn message_body_validators(&self) -> &Arc<MessageBodyValidatorRegistry> {
+        &self.message_body_validators
+    }
+
     pub(crate) fn broker_config(&self) -> &BrokerConfig {
         &self.broker_config
     }
@@ -279,7 +358,17 @@ impl BrokerRuntime {
     }
 
     pub fn shutdown(&mut self) {
+        if self.broker_drain.is_draining() {
+            info!(
+                "[Broker shutdown]broker drain state: {:?}",
+                self.broker_drain.state()
+            );
+            self.broker_drain.stop();
+        }
         self.broker_out_api.shutdown();
+        if let Some(pop_consumer_service) = &self.pop_consumer_service {
+            pop_consumer_service.shutdown();
+        }
         if let Some(message_store) = &mut self.message_store {
             message_store.shutdown()
         }
@@ -292,11 +381,30 @@ impl BrokerRuntime {
             pull_request_hold_service.shutdown();
         }
 
+        if let Some(escape_bridge) = self.escape_bridge.as_mut() {
+            escape_bridge.shutdown();
+        }
+
+        if let Some(check_service) = self.transactional_message_check_service.as_ref() {
+            check_service.shutdown();
+        }
+        if let Some(transactional_message_service) = self.transactional_message_service.as_ref() {
+            transactional_message_service.close();
+        }
+
         if let Some(runtime) = self.broker_runtime.take() {
             runtime.shutdown();
         }
     }
 
+    /// Starts draining all non-system topics ahead of a shutdown, producers are answered with
+    /// `NO_PERMISSION` while pulls keep working.
+    pub(crate) fn begin_drain(&self) {
+        if !self.broker_drain.is_draining() {
+            self.broker_drain.start(Vec::new(), false);
+        }
+    }
+
     pub(crate) fn shutdown_basic_service(&mut self) {
         self.shutdown.store(true, Ordering::SeqCst);
 
@@ -320,6 +428,9 @@ impl Drop for BrokerRuntime {
 
 impl BrokerRuntime {
     pub(crate) async fn initialize(&mut self) -> bool {
+        if !self.bootstrap_from_snapshot() {
+            return false;
+        }
         let mut result = self.initialize_metadata();
         if !result {
             warn!("Initialize metadata failed");
@@ -333,6 +444,42 @@ impl BrokerRuntime {
         self.recover_initialize_service().await
     }
 
+    /// Populates a fresh slave store from `snapshotBootstrapDir` so that HA only has to
+    /// replicate what was written after the snapshot.
+    fn bootstrap_from_snapshot(&self) -> bool {
+        let snapshot_dir = match self.message_store_config.snapshot_bootstrap_dir.as_ref() {
+            Some(dir) if !dir.is_empty() => dir,
+            _ => return true,
+        };
+        if self.message_store_config.broker_role != BrokerRole::Slave {
+            warn!("snapshot bootstrap is only supported by slave brokers, ignore it");
+            return true;
+        }
+        match store_snapshot::bootstrap_from_snapshot(
+            &self.message_store_config,
+            Path::new(snapshot_dir),
+        ) {
+            Ok(manifest) => {
+                info!(
+                    "bootstrapped store from snapshot {}, HA catches up from {}",
+                    snapshot_dir, manifest.confirm_offset
+                );
+                true
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                info!("store already holds data, skip snapshot bootstrap: {}", e);
+                true
+            }
+            Err(e) => {
+                error!(
+                    "bootstrap store from snapshot {} failed: {}",
+                    snapshot_dir, e
+                );
+                false
+            }
+        }
+    }
+
     ///Load the original configuration data from the corresponding configuration files located
     /// under the ${HOME}\config directory.
     fn initialize_metadata(&self) -> bool {
@@ -343,22 +490,25 @@ impl BrokerRuntime {
             && self.subscription_group_manager.load()
             && self.consumer_filter_manager.load()
             && self.consumer_order_info_manager.load()
+            && (!self.broker_config.admin_acl_enable || self.admin_access_validator.load())
     }
 
     async fn initialize_message_store(&mut self) -> bool {
         if self.message_store_config.store_type == StoreType::LocalFile {
             info!("Use local file as message store");
-            let mut message_store = ArcMut::new(DefaultMessageStore::new(
+            let mut message_store = ArcMut::new(DefaultMessageStore::new_with_runtime(
                 self.message_store_config.clone(),
                 self.broker_config.clone(),
                 self.topic_config_manager.topic_config_table(),
                 Some(self.broker_stats_manager.clone()),
                 false,
+                self.store_runtime_handle.clone(),
             ));
             let message_store_clone = message_store.clone();
             message_store.set_message_store_arc(Some(message_store_clone));
             if self.message_store_config.is_timer_wheel_enable() {
                 let time_message_store = TimerMessageStore::new(Some(message_store.clone()));
+                self.timer_message_store = Some(time_message_store.clone());
                 message_store.set_timer_message_store(Arc::new(time_message_store));
             }
             self.consumer_offset_manager
@@ -388,8 +538,8 @@ impl BrokerRuntime {
             self.message_store.as_mut().unwrap().load().await;
         }
 
-        if self.broker_config.timer_wheel_config.timer_wheel_enable {
-            result &= self.timer_message_store.as_mut().unwrap().load();
+        if let Some(timer_message_store) = self.timer_message_store.as_ref() {
+            result &= timer_message_store.load();
         }
         result &= self.schedule_message_service.load();
 
@@ -398,6 +548,7 @@ impl BrokerRuntime {
             self.initialize_resources();
             self.initialize_scheduled_tasks().await;
             self.initial_transaction();
+            self.initial_pop_consumer_service();
             self.initial_acl();
             self.initial_rpc_hooks();
             self.initial_request_pipeline();
@@ -414,6 +565,11 @@ impl BrokerRuntime {
             message_store.set_put_message_hook(Box::new(BatchCheckBeforePutMessageHook::new(
                 self.topic_config_manager.topic_config_table(),
             )));
+            message_store.set_put_message_hook(Box::new(HandleScheduleMessageHook::new(
+                message_store.get_timer_message_store(),
+                self.schedule_message_service.clone(),
+                self.message_store_config.clone(),
+            )));
         }
     }
 
@@ -441,6 +597,8 @@ impl BrokerRuntime {
             self.transactional_message_service.as_ref().unwrap().clone(),
             self.rebalance_lock_manager.clone(),
             self.broker_stats_manager.clone(),
+            self.poison_message_tracker.clone(),
+            self.message_body_validators.clone(),
         );
         let reply_message_processor = ReplyMessageProcessor::new(
             self.topic_queue_mapping_manager.clone(),
@@ -452,6 +610,8 @@ impl BrokerRuntime {
             self.broker_stats_manager.clone(),
             Some(self.producer_manager.clone()),
             self.transactional_message_service.as_ref().unwrap().clone(),
+            self.poison_message_tracker.clone(),
+            self.message_body_validators.clone(),
         );
         let mut pull_message_result_handler =
             ArcMut::new(Box::new(DefaultPullMessageResultHandler::new(
@@ -477,6 +637,8 @@ impl BrokerRuntime {
             Arc::new(BroadcastOffsetManager::default()),
             message_store.clone(),
             self.broker_out_api.clone(),
+            self.poison_message_tracker.clone(),
+            self.replay_subscription_manager.clone(),
         ));
 
         let consumer_manage_processor = ConsumerManageProcessor::new(
@@ -510,6 +672,13 @@ impl BrokerRuntime {
         let query_message_processor =
             QueryMessageProcessor::new(self.message_store_config.clone(), message_store.clone());
 
+        let escape_bridge = ArcMut::new(EscapeBridge::new(
+            self.broker_config.clone(),
+            self.message_store.clone().unwrap(),
+            self.topic_route_info_manager.clone(),
+            self.broker_out_api.clone(),
+        ));
+        self.escape_bridge = Some(escape_bridge.clone());
         let admin_broker_processor = AdminBrokerProcessor::new(
             self.broker_config.clone(),
             self.server_config.clone(),
@@ -525,6 +694,15 @@ impl BrokerRuntime {
             self.broker_stats_manager.clone(),
             self.rebalance_lock_manager.clone(),
             self.broker_member_group.clone(),
+            self.request_priority_dispatcher.clone(),
+            self.maintenance_mode.clone(),
+            self.admin_access_validator.clone(),
+            self.broker_drain.clone(),
+            escape_bridge,
+            self.subscription_group_manager.clone(),
+            self.audit_log.clone(),
+            self.poison_message_tracker.clone(),
+            self.replay_subscription_manager.clone(),
         );
 
         BrokerRequestProcessor {
@@ -565,7 +743,11 @@ impl BrokerRuntime {
                 self.broker_config.clone(),
                 self.transactional_message_service.as_ref().unwrap().clone(),
                 self.message_store.as_ref().unwrap().clone(),
+                self.admin_access_validator.clone(),
             )),
+            request_priority_dispatcher: self.request_priority_dispatcher.clone(),
+            maintenance_mode: self.maintenance_mode.clone(),
+            broker_drain: self.broker_drain.clone(),
         }
     }
 
@@ -651,6 +833,29 @@ impl BrokerRuntime {
                 }
             });
 
+        let replay_subscription_manager = self.replay_subscription_manager.clone();
+        let consumer_offset_manager = self.consumer_offset_manager.clone();
+        let subscription_group_manager = self.subscription_group_manager.clone();
+        self.broker_runtime
+            .as_ref()
+            .unwrap()
+            .get_handle()
+            .spawn(async move {
+                info!("Replay subscription expiry Start scheduled task");
+                loop {
+                    tokio::time::sleep(Duration::from_secs(10)).await;
+                    for subscription in
+                        replay_subscription_manager.take_expired(get_current_millis() as i64)
+                    {
+                        release_replay_subscription(
+                            &subscription,
+                            &consumer_offset_manager,
+                            &subscription_group_manager,
+                        );
+                    }
+                }
+            });
+
         let message_store = self.message_store.clone();
         self.broker_runtime
             .as_ref()
@@ -706,7 +911,8 @@ impl BrokerRuntime {
                     self.broker_stats_manager.clone(),
                     self.consumer_offset_manager.clone(),
                     self.broker_config.clone(),
-                     self.topic_config_manager.clone()
+                    self.message_store_config.clone(),
+                    self.topic_config_manager.clone()
                 );
                 let service = DefaultTransactionalMessageService::new(bridge);
                 self.transactional_message_service = Some(ArcMut::new(service));
@@ -720,10 +926,39 @@ impl BrokerRuntime {
                 self.topic_config_manager.clone(),
                 self.message_store.as_ref().cloned().unwrap(),
             )));
-        self.transactional_message_check_service = Some(Arc::new(TransactionalMessageCheckService));
+        self.transactional_message_check_service = Some(Arc::new(
+            TransactionalMessageCheckService::new(self.broker_config.clone()),
+        ));
         self.transaction_metrics_flush_service = Some(Arc::new(TransactionMetricsFlushService));
     }
 
+    fn initial_pop_consumer_service(&mut self) {
+        if !self.broker_config.pop_consumer_kv_service_enable
+            && !self.broker_config.pop_consumer_kv_service_init
+        {
+            return;
+        }
+        cfg_if::cfg_if! {
+            if #[cfg(feature = "rocksdb")] {
+                let kv_store = Arc::new(PopConsumerRocksdbStore::new(
+                    get_pop_consumer_store_path(self.broker_config.store_path_root_dir.as_str()),
+                ));
+                self.pop_consumer_service = Some(PopConsumerService::new(
+                    self.broker_config.clone(),
+                    self.message_store.as_ref().unwrap().clone(),
+                    kv_store,
+                    self.topic_config_manager.clone(),
+                    self.subscription_group_manager.clone(),
+                ));
+            } else {
+                warn!(
+                    "Pop consumer kv service requires the rocksdb feature, fall back to revive \
+                     topic"
+                );
+            }
+        }
+    }
+
     fn initial_acl(&mut self) {}
 
     fn initial_rpc_hooks(&mut self) {}
@@ -732,6 +967,30 @@ impl BrokerRuntime {
 
     fn protect_broker(&mut self) {}
 
+    fn build_authentication_hook(&self) -> Option<Arc<dyn RPCHook>> {
+        if !self.broker_config.auth_jwt_enable {
+            return None;
+        }
+        let non_empty = |value: &CheetahString| {
+            if value.is_empty() {
+                None
+            } else {
+                Some(value.to_string())
+            }
+        };
+        let provider = Arc::new(JwtAuthenticationProvider::new(JwtAuthenticationConfig {
+            issuer: non_empty(&self.broker_config.auth_jwt_issuer),
+            audience: non_empty(&self.broker_config.auth_jwt_audience),
+            jwks_url: non_empty(&self.broker_config.auth_jwt_jwks_url),
+            hmac_secret: non_empty(&self.broker_config.auth_jwt_hmac_secret),
+            jwks_refresh_interval_millis: self.broker_config.auth_jwt_jwks_refresh_interval_millis,
+            clock_skew_seconds: self.broker_config.auth_jwt_clock_skew_seconds,
+        }));
+        provider.start_jwks_refresh();
+        info!("jwt authentication enabled for broker remoting servers");
+        Some(Arc::new(AuthenticationRPCHook::new(provider)))
+    }
+
     fn start_basic_service(&mut self) {
         let request_processor = self.init_processor();
         let fast_request_processor = request_processor.clone();
@@ -740,14 +999,37 @@ impl BrokerRuntime {
             .unwrap()
             .start()
             .expect("Message store start error");
+        if let Some(timer_message_store) = self.timer_message_store.as_ref() {
+            timer_message_store.start();
+        }
+        if let Some(transactional_message_service) = self.transactional_message_service.clone() {
+            DefaultTransactionalMessageService::start_op_batch_service(
+                transactional_message_service.clone(),
+            );
+            if self.message_store_config.broker_role != BrokerRole::Slave {
+                if let (Some(check_service), Some(check_listener)) = (
+                    self.transactional_message_check_service.as_ref(),
+                    self.transactional_message_check_listener.clone(),
+                ) {
+                    check_service.start(transactional_message_service, check_listener);
+                }
+            }
+        }
 
-        let server = RocketMQServer::new(self.server_config.clone());
+        let authentication_hook = self.build_authentication_hook();
+        let mut server = RocketMQServer::new(self.server_config.clone());
+        if let Some(hook) = authentication_hook.clone() {
+            server.register_rpc_hook(hook);
+        }
         //start nomarl broker remoting_server
         tokio::spawn(async move { server.run(request_processor).await });
         //start fast broker remoting_server
         let mut fast_server_config = (*self.server_config).clone();
         fast_server_config.listen_port = self.server_config.listen_port - 2;
-        let fast_server = RocketMQServer::new(Arc::new(fast_server_config));
+        let mut fast_server = RocketMQServer::new(Arc::new(fast_server_config));
+        if let Some(hook) = authentication_hook {
+            fast_server.register_rpc_hook(hook);
+        }
         tokio::spawn(async move { fast_server.run(fast_request_processor).await });
 
         if let Some(pull_request_hold_service) = self.pull_request_hold_service.as_mut() {
@@ -787,6 +1069,13 @@ impl BrokerRuntime {
         self.broker_out_api.start().await;
         self.start_basic_service();
 
+        if let Some(pop_consumer_service) = self.pop_consumer_service.clone() {
+            if !pop_consumer_service.start().await {
+                warn!("Pop consumer service start failed, fall back to revive topic");
+                self.pop_consumer_service = None;
+            }
+        }
+
         if !self.is_isolated.load(Ordering::Acquire)
             && !self.message_store_config.enable_dledger_commit_log
             && !self.broker_config.duplication_enable
@@ -834,6 +1123,8 @@ impl BrokerRuntime {
                 }
             });
 
+        self.listen_store_events();
+
         if self.broker_config.enable_slave_acting_master {
             self.schedule_send_heartbeat();
         }
@@ -875,6 +1166,62 @@ impl BrokerRuntime {
         );
     }
 
+    /// Re-registers to the name servers as soon as the store changes role and reports disk
+    /// watermark transitions.
+    fn listen_store_events(&mut self) {
+        let Some(event_bus) = self
+            .message_store
+            .as_ref()
+            .and_then(|message_store| message_store.event_bus())
+        else {
+            return;
+        };
+        let mut store_events = event_bus.subscribe();
+        let mut cloned_broker_runtime = self.clone();
+        self.broker_runtime
+            .as_ref()
+            .unwrap()
+            .get_handle()
+            .spawn(async move {
+                loop {
+                    match store_events.recv().await {
+                        Ok(StoreEvent::RoleChanged { role, epoch }) => {
+                            info!(
+                                "store changed role to {:?} at epoch {}, register to namesrv",
+                                role, epoch
+                            );
+                            cloned_broker_runtime
+                                .register_broker_all(true, false, true)
+                                .await;
+                        }
+                        Ok(StoreEvent::DiskWarning {
+                            kind,
+                            used_ratio,
+                            disk_full,
+                        }) => {
+                            if disk_full {
+                                warn!(
+                                    "{:?} disk used ratio {:.2} crossed the warning level, puts \
+                                     are refused",
+                                    kind, used_ratio
+                                );
+                            } else {
+                                info!(
+                                    "{:?} disk used ratio {:.2} back to normal",
+                                    kind, used_ratio
+                                );
+                            }
+                        }
+                        Ok(_) => {}
+                        Err(RecvError::Lagged(skipped)) => {
+                            warn!("broker lagged behind store events, {} skipped", skipped);
+                        }
+                        Err(RecvError::Closed) => break,
+                    }
+                }
+            });
+    }
+
     pub(crate) fn schedule_send_heartbeat(&mut self) {}
 
     pub(crate) fn start_service_without_condition(&mut self) {}
@@ -1008,6 +1355,7 @@ impl BrokerRuntime {
                 false,
                 false,
                 None,
+                reported_feature_bitmap(&self.broker_config),
                 Default::default(),
                 weak,
             )
@@ -1127,6 +1475,7 @@ impl BrokerRuntimeInner {
                 false,
                 false,
                 None,
+                reported_feature_bitmap(&self.broker_config),
                 Default::default(),
                 weak,
             )
@@ -1134,6 +1483,12 @@ impl BrokerRuntimeInner {
     }
 }
 
+/// Returns the features reported to the name server, leaving out the ones held back by
+/// `disabled_feature_bitmap` until every broker of a rolling upgrade runs the new version.
+fn reported_feature_bitmap(broker_config: &BrokerConfig) -> u64 {
+    BrokerFeature::SUPPORTED & !broker_config.disabled_feature_bitmap
+}
+
 struct ProducerStateGetter {
     topic_config_manager: TopicConfigManager,
     producer_manager: Arc<ProducerManager>,
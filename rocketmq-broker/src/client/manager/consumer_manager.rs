@@ -190,6 +190,29 @@ impl ConsumerManager {
         )
     }
 
+    /// Refreshes the channel of a consumer whose heartbeat carried no subscriptions, keeping the
+    /// subscriptions registered by its last full heartbeat.
+    pub fn register_consumer_without_sub(
+        &self,
+        group: &CheetahString,
+        client_channel_info: ClientChannelInfo,
+        consume_type: ConsumeType,
+        message_model: MessageModel,
+        consume_from_where: ConsumeFromWhere,
+        is_notify_consumer_ids_changed_enable: bool,
+    ) -> bool {
+        self.register_consumer_ext(
+            group,
+            client_channel_info,
+            consume_type,
+            message_model,
+            consume_from_where,
+            HashSet::new(),
+            is_notify_consumer_ids_changed_enable,
+            false,
+        )
+    }
+
     fn register_consumer_ext(
         &self,
         group: &CheetahString,
@@ -0,0 +1,428 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::net::SocketAddr;
+use std::sync::atomic::AtomicI64;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+
+use cheetah_string::CheetahString;
+use parking_lot::RwLock;
+use rocketmq_common::common::message::message_ext::MessageExt;
+use rocketmq_common::common::message::message_ext_broker_inner::MessageExtBrokerInner;
+use rocketmq_common::common::message::MessageTrait;
+use rocketmq_common::MessageAccessor::MessageAccessor;
+use rocketmq_common::MessageDecoder;
+use rocketmq_common::TimeUtils::get_current_millis;
+use rocketmq_remoting::code::request_code::RequestCode;
+use rocketmq_remoting::code::response_code::ResponseCode;
+use rocketmq_remoting::protocol::remoting_command::RemotingCommand;
+use rocketmq_store::base::message_status_enum::GetMessageStatus;
+use rocketmq_store::log_file::MessageStore;
+use serde::Deserialize;
+use serde::Serialize;
+use tracing::info;
+use tracing::warn;
+
+use crate::failover::escape_bridge::EscapeBridge;
+use crate::offset::manager::consumer_offset_manager::ConsumerOffsetManager;
+
+const REPUBLISH_BATCH_NUMS: i32 = 32;
+const REPUBLISH_BATCH_SIZE: i32 = 4 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DrainState {
+    #[default]
+    Idle,
+    /// New writes are rejected, waiting for consumers to catch up.
+    Draining,
+    /// Remaining messages of the selected topics are being forwarded to other brokers.
+    Republishing,
+    /// Nothing is left to consume on the drained topics.
+    Drained,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TopicDrainProgress {
+    pub topic: String,
+    /// Messages the slowest subscribed group still has to consume.
+    pub remaining_messages: i64,
+    pub republished_messages: u64,
+    pub failed_messages: u64,
+}
+
+/// Snapshot of the drain state returned by `UPDATE_BROKER_DRAIN`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BrokerDrainStatus {
+    pub state: DrainState,
+    pub since_timestamp: i64,
+    pub republish: bool,
+    pub rejected_writes: u64,
+    pub remaining_messages: i64,
+    pub republished_messages: u64,
+    pub failed_messages: u64,
+    pub topics: Vec<TopicDrainProgress>,
+}
+
+/// Takes the broker out of the write path before it is removed from the cluster.
+///
+/// While draining, producer sends are answered with `NO_PERMISSION` so clients retry on other
+/// brokers, pulls keep working so consumers can finish the backlog, and the remaining messages
+/// of selected topics may be republished to the other brokers of the topic through the
+/// [`EscapeBridge`].
+#[derive(Default)]
+pub(crate) struct BrokerDrain {
+    state: RwLock<DrainState>,
+    since_timestamp: AtomicI64,
+    republish: RwLock<bool>,
+    topics: RwLock<Vec<CheetahString>>,
+    republished: RwLock<HashMap<CheetahString, (u64, u64)>>,
+    rejected_writes: AtomicU64,
+}
+
+impl BrokerDrain {
+    /// Starts draining `topics`, all non-system topics when empty.
+    pub fn start(&self, topics: Vec<CheetahString>, republish: bool) {
+        *self.topics.write() = topics;
+        *self.republish.write() = republish;
+        self.republished.write().clear();
+        self.rejected_writes.store(0, Ordering::Relaxed);
+        self.since_timestamp
+            .store(get_current_millis() as i64, Ordering::Release);
+        *self.state.write() = DrainState::Draining;
+        info!(
+            "broker drain started, topics: {:?}, republish: {}",
+            self.topics.read(),
+            republish
+        );
+    }
+
+    pub fn stop(&self) {
+        let previous = std::mem::take(&mut *self.state.write());
+        if previous != DrainState::Idle {
+            info!(
+                "broker drain stopped in state {:?}, {} writes were rejected",
+                previous,
+                self.rejected_writes.load(Ordering::Relaxed)
+            );
+        }
+        self.since_timestamp.store(0, Ordering::Release);
+    }
+
+    #[inline]
+    pub fn is_draining(&self) -> bool {
+        *self.state.read() != DrainState::Idle
+    }
+
+    pub fn state(&self) -> DrainState {
+        *self.state.read()
+    }
+
+    /// Moves between the active states, a stopped drain stays stopped.
+    pub fn set_state(&self, state: DrainState) {
+        let mut current = self.state.write();
+        if *current != DrainState::Idle {
+            *current = state;
+        }
+    }
+
+    pub fn topics(&self) -> Vec<CheetahString> {
+        self.topics.read().clone()
+    }
+
+    pub fn is_republish(&self) -> bool {
+        *self.republish.read()
+    }
+
+    /// Returns the rejection response for producer writes while the broker is draining.
+    pub fn check(&self, request_code: RequestCode) -> Option<RemotingCommand> {
+        if !self.is_draining() {
+            return None;
+        }
+        match request_code {
+            RequestCode::SendMessage
+            | RequestCode::SendMessageV2
+            | RequestCode::SendBatchMessage
+            | RequestCode::SendReplyMessage
+            | RequestCode::SendReplyMessageV2 => {
+                self.rejected_writes.fetch_add(1, Ordering::Relaxed);
+                Some(RemotingCommand::create_response_command_with_code_remark(
+                    ResponseCode::NoPermission,
+                    "[BROKER_DRAINING] broker is draining for removal, send to another broker",
+                ))
+            }
+            _ => None,
+        }
+    }
+
+    pub fn record_republish(&self, topic: &CheetahString, republished: u64, failed: u64) {
+        let mut table = self.republished.write();
+        let entry = table.entry(topic.clone()).or_default();
+        entry.0 += republished;
+        entry.1 += failed;
+    }
+
+    /// Builds the status from the current backlog of each drained topic and marks the drain
+    /// as finished once nothing is left.
+    pub fn status(&self, backlogs: Vec<(CheetahString, i64)>) -> BrokerDrainStatus {
+        let republished = self.republished.read();
+        let topics = backlogs
+            .into_iter()
+            .map(|(topic, remaining_messages)| {
+                let (republished_messages, failed_messages) =
+                    republished.get(&topic).copied().unwrap_or_default();
+                TopicDrainProgress {
+                    topic: topic.to_string(),
+                    remaining_messages,
+                    republished_messages,
+                    failed_messages,
+                }
+            })
+            .collect::<Vec<_>>();
+        let remaining_messages = topics.iter().map(|t| t.remaining_messages).sum::<i64>();
+        if remaining_messages == 0 && self.state() == DrainState::Draining {
+            self.set_state(DrainState::Drained);
+            info!("broker drain finished, no messages left on the drained topics");
+        }
+        BrokerDrainStatus {
+            state: self.state(),
+            since_timestamp: self.since_timestamp.load(Ordering::Acquire),
+            republish: self.is_republish(),
+            rejected_writes: self.rejected_writes.load(Ordering::Relaxed),
+            remaining_messages,
+            republished_messages: topics.iter().map(|t| t.republished_messages).sum(),
+            failed_messages: topics.iter().map(|t| t.failed_messages).sum(),
+            topics,
+        }
+    }
+
+    pub fn build_running_stats(&self, stats: &mut HashMap<String, String>) {
+        stats.insert(
+            "brokerDrainState".to_string(),
+            format!("{:?}", self.state()),
+        );
+        stats.insert(
+            "brokerDrainRejectedWrites".to_string(),
+            self.rejected_writes.load(Ordering::Relaxed).to_string(),
+        );
+    }
+}
+
+/// Returns the number of messages the slowest group subscribed to `topic` still has to consume.
+pub(crate) fn topic_backlog<MS: MessageStore>(
+    message_store: &MS,
+    consumer_offset_manager: &ConsumerOffsetManager,
+    topic: &CheetahString,
+    queue_nums: i32,
+) -> i64 {
+    let groups = consumer_offset_manager.which_group_by_topic(topic);
+    let mut backlog = 0;
+    for queue_id in 0..queue_nums {
+        let max_offset = message_store.get_max_offset_in_queue(topic, queue_id);
+        let consumed_offset = slowest_consumed_offset(
+            message_store,
+            consumer_offset_manager,
+            &groups,
+            topic,
+            queue_id,
+        );
+        if let Some(consumed_offset) = consumed_offset {
+            backlog += (max_offset - consumed_offset).max(0);
+        }
+    }
+    backlog
+}
+
+fn slowest_consumed_offset<MS: MessageStore>(
+    message_store: &MS,
+    consumer_offset_manager: &ConsumerOffsetManager,
+    groups: &HashSet<CheetahString>,
+    topic: &CheetahString,
+    queue_id: i32,
+) -> Option<i64> {
+    let min_offset = message_store.get_min_offset_in_queue(topic, queue_id);
+    groups
+        .iter()
+        .map(|group| {
+            let offset = consumer_offset_manager.query_offset(group, topic, queue_id);
+            if offset < 0 {
+                min_offset
+            } else {
+                offset.max(min_offset)
+            }
+        })
+        .min()
+}
+
+/// Forwards the messages of `topic` that subscribed groups have not consumed yet to other
+/// brokers, then moves those groups past them so nothing is delivered twice.
+///
+/// A queue stops at the first message that cannot be forwarded and keeps its offsets, so the
+/// remainder is still consumable here or can be republished again.
+pub(crate) async fn republish_topic<MS: MessageStore>(
+    drain: &BrokerDrain,
+    escape_bridge: &mut EscapeBridge<MS>,
+    message_store: &MS,
+    consumer_offset_manager: &ConsumerOffsetManager,
+    topic: &CheetahString,
+    queue_nums: i32,
+) {
+    let groups = consumer_offset_manager.which_group_by_topic(topic);
+    let group = escape_bridge.inner_consumer_group_name().clone();
+    let client_host = SocketAddr::from(([127, 0, 0, 1], 0));
+    for queue_id in 0..queue_nums {
+        let Some(mut offset) = slowest_consumed_offset(
+            message_store,
+            consumer_offset_manager,
+            &groups,
+            topic,
+            queue_id,
+        ) else {
+            continue;
+        };
+        let max_offset = message_store.get_max_offset_in_queue(topic, queue_id);
+        let mut queue_failed = false;
+        while offset < max_offset && drain.is_draining() && !queue_failed {
+            let result = message_store
+                .get_message(
+                    &group,
+                    topic,
+                    queue_id,
+                    offset,
+                    REPUBLISH_BATCH_NUMS,
+                    REPUBLISH_BATCH_SIZE,
+                    None,
+                )
+                .await;
+            let Some(result) = result else {
+                break;
+            };
+            if result.status() != Some(GetMessageStatus::Found) {
+                warn!(
+                    "republish {}:{} stopped at offset {}, status: {:?}",
+                    topic,
+                    queue_id,
+                    offset,
+                    result.status()
+                );
+                break;
+            }
+            let mut republished = 0;
+            for buffer in result.message_mapped_list() {
+                let Some(mut bytes) = buffer.get_bytes() else {
+                    continue;
+                };
+                let Some(msg_ext) =
+                    MessageDecoder::decode(&mut bytes, true, false, false, false, false)
+                else {
+                    continue;
+                };
+                let queue_offset = msg_ext.queue_offset;
+                if escape_bridge
+                    .put_message_to_remote_broker(to_escape_message(&msg_ext), None)
+                    .await
+                    .is_none()
+                {
+                    warn!(
+                        "republish {}:{} failed at offset {}, msgId={}",
+                        topic,
+                        queue_id,
+                        queue_offset,
+                        msg_ext.msg_id()
+                    );
+                    queue_failed = true;
+                    offset = queue_offset;
+                    break;
+                }
+                republished += 1;
+            }
+            drain.record_republish(topic, republished, queue_failed as u64);
+            if !queue_failed {
+                offset = result.next_begin_offset();
+            }
+        }
+        for group in &groups {
+            if consumer_offset_manager.query_offset(group, topic, queue_id) < offset {
+                consumer_offset_manager.commit_offset(client_host, group, topic, queue_id, offset);
+            }
+        }
+    }
+}
+
+fn to_escape_message(msg_ext: &MessageExt) -> MessageExtBrokerInner {
+    let mut inner = MessageExtBrokerInner::default();
+    inner.set_topic(msg_ext.get_topic().clone());
+    if let Some(body) = msg_ext.get_body() {
+        inner.set_body(body.clone());
+    }
+    inner.set_flag(msg_ext.get_flag());
+    MessageAccessor::set_properties(&mut inner, msg_ext.get_properties().clone());
+    inner.properties_string =
+        MessageDecoder::message_properties_to_string(msg_ext.get_properties());
+    inner.tags_code = MessageExtBrokerInner::tags_string_to_tags_code(
+        msg_ext.get_tags().unwrap_or_default().as_str(),
+    );
+    inner.message_ext_inner.queue_id = msg_ext.queue_id;
+    inner.message_ext_inner.sys_flag = msg_ext.sys_flag();
+    inner.message_ext_inner.born_timestamp = msg_ext.born_timestamp;
+    inner.message_ext_inner.born_host = msg_ext.born_host;
+    inner.message_ext_inner.store_timestamp = msg_ext.store_timestamp;
+    inner.message_ext_inner.store_host = msg_ext.store_host;
+    inner.message_ext_inner.msg_id = msg_ext.msg_id().clone();
+    inner.message_ext_inner.reconsume_times = msg_ext.reconsume_times();
+    inner
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_only_producer_writes_while_draining() {
+        let drain = BrokerDrain::default();
+        assert!(drain.check(RequestCode::SendMessageV2).is_none());
+
+        drain.start(vec![CheetahString::from_static_str("TopicA")], false);
+        let response = drain.check(RequestCode::SendMessageV2).unwrap();
+        assert_eq!(response.code(), ResponseCode::NoPermission as i32);
+        assert!(drain.check(RequestCode::PullMessage).is_none());
+        assert!(drain.check(RequestCode::ConsumerSendMsgBack).is_none());
+
+        drain.stop();
+        assert!(drain.check(RequestCode::SendMessageV2).is_none());
+    }
+
+    #[test]
+    fn status_reports_drained_once_backlog_is_empty() {
+        let drain = BrokerDrain::default();
+        let topic = CheetahString::from_static_str("TopicA");
+        drain.start(vec![topic.clone()], true);
+        drain.record_republish(&topic, 3, 1);
+
+        let status = drain.status(vec![(topic.clone(), 5)]);
+        assert_eq!(status.state, DrainState::Draining);
+        assert_eq!(status.remaining_messages, 5);
+        assert_eq!(status.republished_messages, 3);
+        assert_eq!(status.failed_messages, 1);
+
+        let status = drain.status(vec![(topic, 0)]);
+        assert_eq!(status.state, DrainState::Drained);
+    }
+}
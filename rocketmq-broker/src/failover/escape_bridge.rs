@@ -80,7 +80,7 @@ const DEFAULT_PULL_TIMEOUT_MILLIS: u64 = 10_000;
 pub(crate) struct EscapeBridge<MS> {
     inner_producer_group_name: CheetahString,
     inner_consumer_group_name: CheetahString,
-    escape_bridge_runtime: RocketMQRuntime,
+    escape_bridge_runtime: Option<RocketMQRuntime>,
     message_store: ArcMut<MS>,
     broker_config: Arc<BrokerConfig>,
     topic_route_info_manager: Arc<TopicRouteInfoManager>,
@@ -91,6 +91,44 @@ impl<MS> EscapeBridge<MS>
 where
     MS: MessageStore,
 {
+    pub fn new(
+        broker_config: Arc<BrokerConfig>,
+        message_store: ArcMut<MS>,
+        topic_route_info_manager: Arc<TopicRouteInfoManager>,
+        broker_outer_api: Arc<BrokerOuterAPI>,
+    ) -> Self {
+        let broker_name = broker_config.broker_identity.broker_name.as_str();
+        let broker_id = broker_config.broker_identity.broker_id;
+        EscapeBridge {
+            inner_producer_group_name: CheetahString::from_string(format!(
+                "InnerProducerGroup_{}_{}",
+                broker_name, broker_id
+            )),
+            inner_consumer_group_name: CheetahString::from_string(format!(
+                "InnerConsumerGroup_{}_{}",
+                broker_name, broker_id
+            )),
+            escape_bridge_runtime: Some(RocketMQRuntime::new_multi(
+                num_cpus::get().min(4),
+                "AsyncEscapeBridgeExecutor_",
+            )),
+            message_store,
+            broker_config,
+            topic_route_info_manager,
+            broker_outer_api,
+        }
+    }
+
+    pub fn inner_consumer_group_name(&self) -> &CheetahString {
+        &self.inner_consumer_group_name
+    }
+
+    pub fn shutdown(&mut self) {
+        if let Some(runtime) = self.escape_bridge_runtime.take() {
+            runtime.shutdown();
+        }
+    }
+
     pub async fn put_message(
         &mut self,
         mut message_ext: MessageExtBrokerInner,
@@ -232,6 +270,9 @@ fn transform_send_result2put_result(send_result: Option<SendResult>) -> PutMessa
             SendStatus::SlaveNotAvailable => {
                 PutMessageResult::new(PutMessageStatus::SlaveNotAvailable, None, true)
             }
+            SendStatus::Spooled => {
+                PutMessageResult::new(PutMessageStatus::PutToRemoteBrokerFail, None, true)
+            }
         },
     }
 }
@@ -81,7 +81,31 @@ impl MessageFilter for ExpressionMessageFilter {
                 .code_set
                 .contains(&(tags_code.unwrap() as i32))
         } else {
-            unimplemented!("SQL92 expression type is not supported yet.")
+            // pre-filter by the bloom bitmap stored in the consume queue extension, the
+            // expression itself is evaluated against the commit log
+            let Some(consumer_filter_data) = self.consumer_filter_data.as_ref() else {
+                return true;
+            };
+            let Some(bloom_filter_data) = consumer_filter_data.bloom_filter_data() else {
+                return true;
+            };
+            // message is before consumer
+            let Some(cq_ext_unit) = cq_ext_unit else {
+                return true;
+            };
+            if cq_ext_unit.msg_store_time() < consumer_filter_data.born_time() as i64 {
+                return true;
+            }
+            let Some(bit_map) = cq_ext_unit.filter_bit_map() else {
+                return true;
+            };
+            if !self.bloom_data_valid || bit_map.len() * 8 != bloom_filter_data.bit_num() as usize {
+                return true;
+            }
+            match self.consumer_filter_manager.get_bloom_filter() {
+                Some(bloom_filter) => bloom_filter.is_hit(bloom_filter_data, bit_map),
+                None => true,
+            }
         }
     }
 
@@ -16,3 +16,5 @@
  */
 pub(crate) mod batch_check_before_put_message;
 pub(crate) mod check_before_put_message;
+pub(crate) mod handle_schedule_message;
+pub(crate) mod message_body_validator;
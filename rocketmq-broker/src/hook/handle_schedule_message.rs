@@ -0,0 +1,75 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::sync::Arc;
+
+use rocketmq_common::common::message::message_batch::MessageExtBatch;
+use rocketmq_common::common::message::message_ext_broker_inner::MessageExtBrokerInner;
+use rocketmq_store::base::message_result::PutMessageResult;
+use rocketmq_store::config::message_store_config::MessageStoreConfig;
+use rocketmq_store::hook::put_message_hook::PutMessageHook;
+use rocketmq_store::timer::timer_message_store::TimerMessageStore;
+
+use crate::schedule::schedule_message_service::ScheduleMessageService;
+use crate::util::hook_utils::HookUtils;
+
+/// Redirects timer messages to the timer topic and delay level messages to the schedule topic
+/// before they are stored.
+pub struct HandleScheduleMessageHook {
+    timer_message_store: Arc<TimerMessageStore>,
+    schedule_message_service: ScheduleMessageService,
+    message_store_config: Arc<MessageStoreConfig>,
+}
+
+impl HandleScheduleMessageHook {
+    pub fn new(
+        timer_message_store: Arc<TimerMessageStore>,
+        schedule_message_service: ScheduleMessageService,
+        message_store_config: Arc<MessageStoreConfig>,
+    ) -> Self {
+        Self {
+            timer_message_store,
+            schedule_message_service,
+            message_store_config,
+        }
+    }
+}
+
+impl PutMessageHook for HandleScheduleMessageHook {
+    fn hook_name(&self) -> String {
+        "handleScheduleMessage".to_string()
+    }
+
+    fn execute_before_put_message(
+        &self,
+        msg: &mut MessageExtBrokerInner,
+    ) -> Option<PutMessageResult> {
+        HookUtils::handle_schedule_message(
+            &self.timer_message_store,
+            &self.schedule_message_service,
+            &self.message_store_config,
+            msg,
+        )
+    }
+
+    fn execute_before_put_messages(
+        &self,
+        _msg_batch: &mut MessageExtBatch,
+    ) -> Option<PutMessageResult> {
+        // the commit log rejects delayed batches itself
+        None
+    }
+}
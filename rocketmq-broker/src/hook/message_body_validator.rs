@@ -0,0 +1,256 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use bytes::Buf;
+use parking_lot::RwLock;
+use rocketmq_common::common::attribute::attribute_list::ListAttribute;
+use rocketmq_common::common::compression::compressor_factory::CompressorFactory;
+use rocketmq_common::common::config::TopicConfig;
+use rocketmq_common::common::sys_flag::message_sys_flag::MessageSysFlag;
+use rocketmq_common::TopicAttributes;
+
+/// Validator id of [`JsonBodyValidator`].
+pub const JSON_VALIDATOR_ID: &str = "json";
+/// Validator id of [`Utf8BodyValidator`].
+pub const UTF8_VALIDATOR_ID: &str = "utf8";
+
+/// Checks the payload of a message before it is stored.
+///
+/// Topics opt in through the `body.validators` attribute, which lists validator ids. Schema
+/// aware validators, e.g. a JSON schema or a protobuf descriptor, are registered on the
+/// [`MessageBodyValidatorRegistry`] under an id of their own.
+pub trait MessageBodyValidator: Send + Sync {
+    /// Returns the reason the body is rejected, the body is already decompressed.
+    fn validate(&self, topic: &str, body: &[u8]) -> Result<(), String>;
+}
+
+/// Accepts bodies that are a well formed JSON document.
+pub struct JsonBodyValidator;
+
+impl MessageBodyValidator for JsonBodyValidator {
+    fn validate(&self, _topic: &str, body: &[u8]) -> Result<(), String> {
+        serde_json::from_slice::<serde_json::de::IgnoredAny>(body)
+            .map(|_| ())
+            .map_err(|e| format!("body is not valid json, {}", e))
+    }
+}
+
+/// Accepts bodies that are valid UTF-8 text.
+pub struct Utf8BodyValidator;
+
+impl MessageBodyValidator for Utf8BodyValidator {
+    fn validate(&self, _topic: &str, body: &[u8]) -> Result<(), String> {
+        std::str::from_utf8(body)
+            .map(|_| ())
+            .map_err(|e| format!("body is not valid utf-8, {}", e))
+    }
+}
+
+/// Validators by id, shared by the send processors.
+pub struct MessageBodyValidatorRegistry {
+    validators: RwLock<HashMap<String, Arc<dyn MessageBodyValidator>>>,
+}
+
+impl Default for MessageBodyValidatorRegistry {
+    fn default() -> Self {
+        let registry = Self {
+            validators: RwLock::new(HashMap::new()),
+        };
+        registry.register(JSON_VALIDATOR_ID, Arc::new(JsonBodyValidator));
+        registry.register(UTF8_VALIDATOR_ID, Arc::new(Utf8BodyValidator));
+        registry
+    }
+}
+
+impl MessageBodyValidatorRegistry {
+    /// Registers `validator` under `id`, replacing the one registered before.
+    pub fn register(&self, id: impl Into<String>, validator: Arc<dyn MessageBodyValidator>) {
+        self.validators.write().insert(id.into(), validator);
+    }
+
+    pub fn unregister(&self, id: &str) -> bool {
+        self.validators.write().remove(id).is_some()
+    }
+
+    /// Runs the validators the topic declared against the body of a single message.
+    ///
+    /// A validator id nothing is registered under rejects the message, so a misconfigured
+    /// topic does not silently let malformed payloads through.
+    pub fn validate_message(
+        &self,
+        topic_config: &TopicConfig,
+        sys_flag: i32,
+        body: Option<&[u8]>,
+    ) -> Result<(), String> {
+        let validator_ids = Self::validator_ids(topic_config);
+        if validator_ids.is_empty() {
+            return Ok(());
+        }
+        let body = body.unwrap_or_default();
+        if MessageSysFlag::check(sys_flag, MessageSysFlag::COMPRESSED_FLAG) {
+            let compressor =
+                CompressorFactory::get_compressor(MessageSysFlag::get_compression_type(sys_flag));
+            let body = compressor
+                .decompress(body)
+                .map_err(|e| format!("decompress body failed, {}", e))?;
+            return self.run(topic_config, &validator_ids, &body);
+        }
+        self.run(topic_config, &validator_ids, body)
+    }
+
+    /// Runs the validators the topic declared against every message of an encoded batch.
+    pub fn validate_batch(
+        &self,
+        topic_config: &TopicConfig,
+        body: Option<&[u8]>,
+    ) -> Result<(), String> {
+        let validator_ids = Self::validator_ids(topic_config);
+        if validator_ids.is_empty() {
+            return Ok(());
+        }
+        let mut buffer = body.unwrap_or_default();
+        let mut index = 0;
+        while buffer.has_remaining() {
+            let inner_body = next_batch_body(&mut buffer)
+                .ok_or_else(|| format!("batch message {} is truncated", index))?;
+            self.run(topic_config, &validator_ids, inner_body)
+                .map_err(|e| format!("batch message {}: {}", index, e))?;
+            index += 1;
+        }
+        Ok(())
+    }
+
+    fn validator_ids(topic_config: &TopicConfig) -> Vec<String> {
+        topic_config
+            .attributes
+            .get(TopicAttributes::BODY_VALIDATORS_ATTRIBUTE.get_name())
+            .map(|value| ListAttribute::parse(value.as_str()))
+            .unwrap_or_default()
+    }
+
+    fn run(
+        &self,
+        topic_config: &TopicConfig,
+        validator_ids: &[String],
+        body: &[u8],
+    ) -> Result<(), String> {
+        let topic = topic_config
+            .topic_name
+            .as_ref()
+            .map(|topic| topic.as_str())
+            .unwrap_or_default();
+        let validators = self.validators.read();
+        for id in validator_ids {
+            let Some(validator) = validators.get(id) else {
+                return Err(format!("validator {} is not registered", id));
+            };
+            validator
+                .validate(topic, body)
+                .map_err(|e| format!("rejected by validator {}, {}", id, e))?;
+        }
+        Ok(())
+    }
+}
+
+/// Splits the body off the next message of a batch, see `MessageDecoder::encode_message`.
+fn next_batch_body<'a>(buffer: &mut &'a [u8]) -> Option<&'a [u8]> {
+    let data: &'a [u8] = *buffer;
+    if data.len() < 4 {
+        return None;
+    }
+    let total_size = (&data[..4]).get_i32();
+    if total_size < 20 || total_size as usize > data.len() {
+        return None;
+    }
+    let (message, rest) = data.split_at(total_size as usize);
+    *buffer = rest;
+    // TOTALSIZE, MAGICCODE, BODYCRC, FLAG
+    let mut message = &message[16..];
+    let body_len = message.get_i32();
+    if body_len < 0 || body_len as usize > message.remaining() {
+        return None;
+    }
+    Some(&message[..body_len as usize])
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use cheetah_string::CheetahString;
+    use rocketmq_common::common::message::message_single::Message;
+    use rocketmq_common::MessageDecoder;
+
+    use super::*;
+
+    fn topic_config(validators: &str) -> TopicConfig {
+        TopicConfig {
+            topic_name: Some(CheetahString::from_static_str("orders")),
+            attributes: HashMap::from_iter([(
+                TopicAttributes::BODY_VALIDATORS_ATTRIBUTE
+                    .get_name()
+                    .to_string()
+                    .into(),
+                validators.to_string().into(),
+            )]),
+            ..TopicConfig::default()
+        }
+    }
+
+    #[test]
+    fn validate_message_runs_declared_validators() {
+        let registry = MessageBodyValidatorRegistry::default();
+        assert!(registry
+            .validate_message(&TopicConfig::default(), 0, Some(b"not json"))
+            .is_ok());
+
+        let config = topic_config("utf8,json");
+        assert!(registry
+            .validate_message(&config, 0, Some(br#"{"id":1}"#))
+            .is_ok());
+        assert!(registry
+            .validate_message(&config, 0, Some(b"not json"))
+            .is_err());
+        assert!(registry
+            .validate_message(&topic_config("missing"), 0, Some(b"{}"))
+            .is_err());
+    }
+
+    #[test]
+    fn validate_batch_checks_every_message() {
+        let registry = MessageBodyValidatorRegistry::default();
+        let config = topic_config("json");
+        let message = |body: &'static [u8]| Message {
+            body: Some(bytes::Bytes::from_static(body)),
+            ..Message::default()
+        };
+
+        let good = MessageDecoder::encode_messages(&[message(b"{}"), message(b"[1,2]")]);
+        assert!(registry.validate_batch(&config, Some(&good[..])).is_ok());
+
+        let bad = MessageDecoder::encode_messages(&[message(b"{}"), message(b"{")]);
+        let error = registry
+            .validate_batch(&config, Some(&bad[..]))
+            .unwrap_err();
+        assert!(error.starts_with("batch message 1"));
+        assert!(registry
+            .validate_batch(&config, Some(&good[..good.len() - 1]))
+            .is_err());
+    }
+}
@@ -21,11 +21,13 @@
 
 pub use broker_bootstrap::BrokerBootstrap;
 pub use broker_bootstrap::Builder;
+pub use hook::message_body_validator::MessageBodyValidator;
 
 use crate::broker_error::BrokerError;
 
 pub mod command;
 
+pub(crate) mod acl;
 pub(crate) mod broker;
 pub(crate) mod broker_bootstrap;
 pub(crate) mod broker_error;
@@ -42,6 +44,7 @@ pub(crate) mod long_polling;
 pub(crate) mod mqtrace;
 pub(crate) mod offset;
 pub(crate) mod out_api;
+pub(crate) mod pop;
 pub(crate) mod processor;
 pub(crate) mod schedule;
 pub(crate) mod subscription;
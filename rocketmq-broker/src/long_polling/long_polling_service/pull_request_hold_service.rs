@@ -151,6 +151,7 @@ where
                 }
                 drop(table);
                 let mut replay_list = Vec::new();
+                let mut wakeup_list = Vec::new();
 
                 for request in request_list {
                     let mut newest_offset = max_offset;
@@ -176,13 +177,7 @@ where
                         }
 
                         if match_by_commit_log {
-                            let pull_message_this = self.pull_message_processor.clone();
-                            self.pull_message_processor.execute_request_when_wakeup(
-                                pull_message_this,
-                                request.client_channel().clone(),
-                                request.connection_handler_context().clone(),
-                                request.request_command().clone(),
-                            );
+                            wakeup_list.push(request);
                             continue;
                         }
                     }
@@ -203,6 +198,8 @@ where
                     replay_list.push(request);
                 }
 
+                self.wakeup_arrived(topic, queue_id, wakeup_list);
+
                 if !replay_list.is_empty() {
                     let mut table = self.pull_request_table.write();
                     let mpr = table.entry(key).or_insert_with(ManyPullRequest::new);
@@ -212,6 +209,47 @@ where
         }
     }
 
+    fn wakeup_arrived(&self, topic: &CheetahString, queue_id: i32, request_list: Vec<PullRequest>) {
+        if request_list.is_empty() {
+            return;
+        }
+        if !self.broker_config.long_polling_prefetch_enable {
+            for request in request_list {
+                let pull_message_this = self.pull_message_processor.clone();
+                self.pull_message_processor.execute_request_when_wakeup(
+                    pull_message_this,
+                    request.client_channel().clone(),
+                    request.connection_handler_context().clone(),
+                    request.request_command().clone(),
+                );
+            }
+            return;
+        }
+        let offset = request_list
+            .iter()
+            .map(PullRequest::pull_from_this_offset)
+            .min()
+            .unwrap_or_default();
+        let requests = request_list
+            .into_iter()
+            .map(|request| {
+                (
+                    request.client_channel().clone(),
+                    request.connection_handler_context().clone(),
+                    request.request_command().clone(),
+                )
+            })
+            .collect();
+        self.pull_message_processor
+            .execute_requests_when_wakeup_with_prefetch(
+                self.pull_message_processor.clone(),
+                topic.clone(),
+                queue_id,
+                offset,
+                requests,
+            );
+    }
+
     pub async fn notify_master_online(&self) {
         for (_, mpr) in self.pull_request_table.read().iter() {
             if let Some(request_list) = mpr.clone_list_and_clear() {
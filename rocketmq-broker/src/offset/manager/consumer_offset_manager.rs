@@ -192,6 +192,28 @@ impl ConsumerOffsetManager {
         -1
     }
 
+    /// Drops every committed, pull and reset offset of `group`.
+    pub fn remove_offset(&self, group: &CheetahString) {
+        let matches_group = |key: &CheetahString| {
+            key.split(TOPIC_GROUP_SEPARATOR)
+                .nth(1)
+                .is_some_and(|value| value == group.as_str())
+        };
+        for table in [
+            &self.consumer_offset_wrapper.offset_table,
+            &self.consumer_offset_wrapper.pull_offset_table,
+            &self.consumer_offset_wrapper.reset_offset_table,
+        ] {
+            table.write().retain(|key, _| {
+                let matched = matches_group(key);
+                if matched {
+                    warn!("clean group offset {}", key);
+                }
+                !matched
+            });
+        }
+    }
+
     pub fn which_topic_by_consumer(&self, group: &CheetahString) -> HashSet<CheetahString> {
         let read_guard = self.consumer_offset_wrapper.offset_table.read();
         let mut topics = HashSet::new();
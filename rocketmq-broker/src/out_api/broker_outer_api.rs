@@ -165,6 +165,7 @@ impl BrokerOuterAPI {
         enable_acting_master: bool,
         compressed: bool,
         heartbeat_timeout_millis: Option<i64>,
+        feature_bitmap: u64,
         _broker_identity: BrokerIdentity,
         this: Weak<Self>,
     ) -> Vec<RegisterBrokerResult> {
@@ -181,6 +182,7 @@ impl BrokerOuterAPI {
                 compressed: false,
                 heartbeat_timeout_millis,
                 body_crc32: 0,
+                feature_bitmap: Some(feature_bitmap),
             };
 
             //build request body
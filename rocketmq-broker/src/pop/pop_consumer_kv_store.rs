@@ -0,0 +1,47 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use crate::pop::pop_consumer_record::PopConsumerRecord;
+
+/// Buffer for pop checkpoints and acks that bypasses the revive topic.
+///
+/// Implementations must keep records ordered by their visibility timeout so that
+/// `scan_expired_records` can be served by a single range scan.
+pub(crate) trait PopConsumerKVStore: Send + Sync + 'static {
+    /// Opens the underlying store, returns `false` if it could not be opened.
+    fn start(&self) -> bool;
+
+    /// Flushes and closes the underlying store.
+    fn shutdown(&self) -> bool;
+
+    /// Directory holding the store files.
+    fn get_file_path(&self) -> &str;
+
+    /// Writes (or overwrites) the given records in one batch.
+    fn write_records(&self, records: &[PopConsumerRecord]);
+
+    /// Removes the given records in one batch.
+    fn delete_records(&self, records: &[PopConsumerRecord]);
+
+    /// Returns at most `max_count` records whose visibility timeout lies in
+    /// `[lower_time, upper_time)`, ordered by visibility timeout.
+    fn scan_expired_records(
+        &self,
+        lower_time: i64,
+        upper_time: i64,
+        max_count: usize,
+    ) -> Vec<PopConsumerRecord>;
+}
@@ -0,0 +1,156 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::fmt::Display;
+
+use cheetah_string::CheetahString;
+use serde::Deserialize;
+use serde::Serialize;
+
+/// A single popped message tracked by the pop consumer kv store.
+///
+/// Records are keyed by their visibility timeout first, so a range scan over the
+/// store returns them in the order in which they have to be revived.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct PopConsumerRecord {
+    pub pop_time: i64,
+    pub group_id: CheetahString,
+    pub topic_id: CheetahString,
+    pub queue_id: i32,
+    pub retry_flag: i32,
+    pub invisible_time: i64,
+    pub offset: i64,
+    pub attempt_times: i32,
+    pub attempt_id: Option<CheetahString>,
+}
+
+impl PopConsumerRecord {
+    pub const KEY_SEPARATOR: &'static str = "@";
+
+    pub fn new(
+        pop_time: i64,
+        group_id: CheetahString,
+        topic_id: CheetahString,
+        queue_id: i32,
+        retry_flag: i32,
+        invisible_time: i64,
+        offset: i64,
+    ) -> Self {
+        PopConsumerRecord {
+            pop_time,
+            group_id,
+            topic_id,
+            queue_id,
+            retry_flag,
+            invisible_time,
+            offset,
+            attempt_times: 0,
+            attempt_id: None,
+        }
+    }
+
+    #[inline]
+    pub fn get_visibility_timeout(&self) -> i64 {
+        self.pop_time + self.invisible_time
+    }
+
+    #[inline]
+    pub fn is_retry(&self) -> bool {
+        self.retry_flag != 0
+    }
+
+    /// Key layout: 8 bytes big-endian visibility timeout followed by
+    /// `group@topic@queueId@offset`.
+    pub fn get_key_bytes(&self) -> Vec<u8> {
+        let suffix = format!(
+            "{}{}{}{}{}{}{}",
+            self.group_id,
+            Self::KEY_SEPARATOR,
+            self.topic_id,
+            Self::KEY_SEPARATOR,
+            self.queue_id,
+            Self::KEY_SEPARATOR,
+            self.offset
+        );
+        let mut key = Vec::with_capacity(8 + suffix.len());
+        key.extend_from_slice(&self.get_visibility_timeout().max(0).to_be_bytes());
+        key.extend_from_slice(suffix.as_bytes());
+        key
+    }
+
+    pub fn get_value_bytes(&self) -> Vec<u8> {
+        serde_json::to_vec(self).unwrap_or_default()
+    }
+
+    pub fn decode(value: &[u8]) -> Option<PopConsumerRecord> {
+        serde_json::from_slice(value).ok()
+    }
+}
+
+impl Display for PopConsumerRecord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "PopConsumerRecord [pop_time={}, group_id={}, topic_id={}, queue_id={}, \
+             retry_flag={}, invisible_time={}, offset={}, attempt_times={}, attempt_id={}]",
+            self.pop_time,
+            self.group_id,
+            self.topic_id,
+            self.queue_id,
+            self.retry_flag,
+            self.invisible_time,
+            self.offset,
+            self.attempt_times,
+            self.attempt_id.as_deref().unwrap_or("None")
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(pop_time: i64, invisible_time: i64) -> PopConsumerRecord {
+        PopConsumerRecord::new(
+            pop_time,
+            CheetahString::from_static_str("group"),
+            CheetahString::from_static_str("topic"),
+            3,
+            0,
+            invisible_time,
+            100,
+        )
+    }
+
+    #[test]
+    fn key_bytes_are_ordered_by_visibility_timeout() {
+        let early = record(1_000, 500);
+        let late = record(1_000, 70_000);
+        assert!(early.get_key_bytes() < late.get_key_bytes());
+        assert_eq!(&early.get_key_bytes()[..8], &1_500i64.to_be_bytes());
+        assert_eq!(&early.get_key_bytes()[8..], b"group@topic@3@100");
+    }
+
+    #[test]
+    fn value_bytes_round_trip() {
+        let mut original = record(1_000, 500);
+        original.attempt_id = Some(CheetahString::from_static_str("attempt"));
+        let decoded = PopConsumerRecord::decode(&original.get_value_bytes()).unwrap();
+        assert_eq!(decoded, original);
+        assert!(PopConsumerRecord::decode(b"not json").is_none());
+    }
+}
@@ -0,0 +1,154 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::fs;
+
+use parking_lot::RwLock;
+use rocksdb::Direction;
+use rocksdb::IteratorMode;
+use rocksdb::Options;
+use rocksdb::ReadOptions;
+use rocksdb::WriteBatch;
+use rocksdb::DB;
+use tracing::error;
+use tracing::info;
+use tracing::warn;
+
+use crate::pop::pop_consumer_kv_store::PopConsumerKVStore;
+use crate::pop::pop_consumer_record::PopConsumerRecord;
+
+/// RocksDB backed [`PopConsumerKVStore`], enabled by the `rocksdb` feature.
+pub(crate) struct PopConsumerRocksdbStore {
+    file_path: String,
+    db: RwLock<Option<DB>>,
+}
+
+impl PopConsumerRocksdbStore {
+    pub fn new(file_path: impl Into<String>) -> Self {
+        PopConsumerRocksdbStore {
+            file_path: file_path.into(),
+            db: RwLock::new(None),
+        }
+    }
+}
+
+impl PopConsumerKVStore for PopConsumerRocksdbStore {
+    fn start(&self) -> bool {
+        let mut db = self.db.write();
+        if db.is_some() {
+            return true;
+        }
+        if let Err(e) = fs::create_dir_all(&self.file_path) {
+            error!(
+                "create pop consumer store dir {} failed: {}",
+                self.file_path, e
+            );
+            return false;
+        }
+        let mut options = Options::default();
+        options.create_if_missing(true);
+        match DB::open(&options, &self.file_path) {
+            Ok(opened) => {
+                info!("pop consumer rocksdb store opened, path={}", self.file_path);
+                *db = Some(opened);
+                true
+            }
+            Err(e) => {
+                error!(
+                    "open pop consumer rocksdb store {} failed: {}",
+                    self.file_path, e
+                );
+                false
+            }
+        }
+    }
+
+    fn shutdown(&self) -> bool {
+        if let Some(db) = self.db.write().take() {
+            if let Err(e) = db.flush() {
+                warn!("flush pop consumer rocksdb store failed: {}", e);
+            }
+            info!("pop consumer rocksdb store closed, path={}", self.file_path);
+        }
+        true
+    }
+
+    fn get_file_path(&self) -> &str {
+        self.file_path.as_str()
+    }
+
+    fn write_records(&self, records: &[PopConsumerRecord]) {
+        let db = self.db.read();
+        let Some(db) = db.as_ref() else {
+            warn!("pop consumer rocksdb store is not started, skip write");
+            return;
+        };
+        let mut batch = WriteBatch::default();
+        for record in records {
+            batch.put(record.get_key_bytes(), record.get_value_bytes());
+        }
+        if let Err(e) = db.write(batch) {
+            error!("write pop consumer records failed: {}", e);
+        }
+    }
+
+    fn delete_records(&self, records: &[PopConsumerRecord]) {
+        let db = self.db.read();
+        let Some(db) = db.as_ref() else {
+            warn!("pop consumer rocksdb store is not started, skip delete");
+            return;
+        };
+        let mut batch = WriteBatch::default();
+        for record in records {
+            batch.delete(record.get_key_bytes());
+        }
+        if let Err(e) = db.write(batch) {
+            error!("delete pop consumer records failed: {}", e);
+        }
+    }
+
+    fn scan_expired_records(
+        &self,
+        lower_time: i64,
+        upper_time: i64,
+        max_count: usize,
+    ) -> Vec<PopConsumerRecord> {
+        let db = self.db.read();
+        let Some(db) = db.as_ref() else {
+            return Vec::new();
+        };
+        let lower = lower_time.max(0).to_be_bytes();
+        let mut read_options = ReadOptions::default();
+        read_options.set_iterate_upper_bound(upper_time.max(0).to_be_bytes().to_vec());
+        let mut records = Vec::new();
+        for item in db.iterator_opt(IteratorMode::From(&lower, Direction::Forward), read_options) {
+            if records.len() >= max_count {
+                break;
+            }
+            match item {
+                Ok((_, value)) => match PopConsumerRecord::decode(&value) {
+                    Some(record) => records.push(record),
+                    None => warn!("skip undecodable pop consumer record"),
+                },
+                Err(e) => {
+                    error!("scan pop consumer records failed: {}", e);
+                    break;
+                }
+            }
+        }
+        records
+    }
+}
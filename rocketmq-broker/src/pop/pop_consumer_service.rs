@@ -0,0 +1,397 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use bytes::Bytes;
+use cheetah_string::CheetahString;
+use rocketmq_common::common::broker::broker_config::BrokerConfig;
+use rocketmq_common::common::constant::PermName;
+use rocketmq_common::common::message::message_ext::MessageExt;
+use rocketmq_common::common::message::message_ext_broker_inner::MessageExtBrokerInner;
+use rocketmq_common::common::message::MessageConst;
+use rocketmq_common::common::message::MessageTrait;
+use rocketmq_common::common::pop_ack_constants::PopAckConstants;
+use rocketmq_common::MessageDecoder;
+use rocketmq_common::TimeUtils::get_current_millis;
+use rocketmq_rust::ArcMut;
+use rocketmq_store::base::message_status_enum::GetMessageStatus;
+use rocketmq_store::base::message_status_enum::PutMessageStatus;
+use rocketmq_store::log_file::MessageStore;
+use rocketmq_store::pop::pop_check_point::PopCheckPoint;
+use tokio::sync::Notify;
+use tracing::error;
+use tracing::info;
+use tracing::warn;
+
+use crate::pop::pop_consumer_kv_store::PopConsumerKVStore;
+use crate::pop::pop_consumer_record::PopConsumerRecord;
+use crate::pop::pop_retry;
+use crate::pop::pop_retry::PopRetryTarget;
+use crate::subscription::manager::subscription_group_manager::SubscriptionGroupManager;
+use crate::topic::manager::topic_config_manager::TopicConfigManager;
+
+const REVIVE_INTERVAL_MILLIS: u64 = PopAckConstants::SECOND as u64;
+const DEFAULT_MAX_RECONSUME_TIMES: i32 = 16;
+const DLQ_NUMS_PER_GROUP: i32 = 1;
+
+/// Buffers pop checkpoints in a [`PopConsumerKVStore`] instead of writing them to the
+/// revive topic.
+///
+/// Records whose invisible time elapsed are re-enqueued into the pop retry topic of their group,
+/// or into its DLQ once the group's retry budget is spent. When the kv service is switched off
+/// but `popConsumerKVServiceInit` is set, all records left in the store are replayed to the
+/// revive topic as regular checkpoint messages on start so no in-flight message is lost while
+/// migrating back.
+pub(crate) struct PopConsumerService<MS> {
+    broker_config: Arc<BrokerConfig>,
+    message_store: ArcMut<MS>,
+    kv_store: Arc<dyn PopConsumerKVStore>,
+    topic_config_manager: TopicConfigManager,
+    subscription_group_manager: Arc<SubscriptionGroupManager<MS>>,
+    store_host: SocketAddr,
+    shutdown_notify: Arc<Notify>,
+}
+
+impl<MS> Clone for PopConsumerService<MS> {
+    fn clone(&self) -> Self {
+        PopConsumerService {
+            broker_config: self.broker_config.clone(),
+            message_store: self.message_store.clone(),
+            kv_store: self.kv_store.clone(),
+            topic_config_manager: self.topic_config_manager.clone(),
+            subscription_group_manager: self.subscription_group_manager.clone(),
+            store_host: self.store_host,
+            shutdown_notify: self.shutdown_notify.clone(),
+        }
+    }
+}
+
+impl<MS> PopConsumerService<MS>
+where
+    MS: MessageStore + Send + Sync,
+{
+    pub fn new(
+        broker_config: Arc<BrokerConfig>,
+        message_store: ArcMut<MS>,
+        kv_store: Arc<dyn PopConsumerKVStore>,
+        topic_config_manager: TopicConfigManager,
+        subscription_group_manager: Arc<SubscriptionGroupManager<MS>>,
+    ) -> Self {
+        let store_host = broker_config
+            .get_broker_addr()
+            .parse::<SocketAddr>()
+            .expect("parse store host failed");
+        PopConsumerService {
+            broker_config,
+            message_store,
+            kv_store,
+            topic_config_manager,
+            subscription_group_manager,
+            store_host,
+            shutdown_notify: Arc::new(Notify::new()),
+        }
+    }
+
+    #[inline]
+    pub fn is_enabled(&self) -> bool {
+        self.broker_config.pop_consumer_kv_service_enable
+    }
+
+    /// Records popped messages, called instead of appending checkpoints to the revive topic.
+    pub fn write_records(&self, records: &[PopConsumerRecord]) {
+        if records.is_empty() {
+            return;
+        }
+        self.kv_store.write_records(records);
+    }
+
+    /// Removes acked messages, called instead of appending acks to the revive topic.
+    pub fn delete_records(&self, records: &[PopConsumerRecord]) {
+        if records.is_empty() {
+            return;
+        }
+        self.kv_store.delete_records(records);
+    }
+
+    pub async fn start(&self) -> bool {
+        if !self.kv_store.start() {
+            error!(
+                "pop consumer kv store start failed, path={}",
+                self.kv_store.get_file_path()
+            );
+            return false;
+        }
+        let replayed = self.replay_on_restart().await;
+        info!(
+            "pop consumer service started, enable={}, replayed {} records",
+            self.is_enabled(),
+            replayed
+        );
+        if !self.is_enabled() {
+            return true;
+        }
+
+        let this = self.clone();
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    _ = this.shutdown_notify.notified() => {
+                        break;
+                    }
+                    _ = tokio::time::sleep(Duration::from_millis(REVIVE_INTERVAL_MILLIS)) => {
+                        this.revive_expired_records(get_current_millis() as i64).await;
+                    }
+                }
+            }
+            info!("pop consumer revive service end");
+        });
+        true
+    }
+
+    pub fn shutdown(&self) {
+        self.shutdown_notify.notify_waiters();
+        self.kv_store.shutdown();
+    }
+
+    /// Replays buffered records after a restart.
+    ///
+    /// With the kv service enabled only records that became visible while the broker was down
+    /// are revived; otherwise every record is migrated to the revive topic.
+    async fn replay_on_restart(&self) -> usize {
+        let upper_time = if self.is_enabled() {
+            get_current_millis() as i64
+        } else {
+            i64::MAX
+        };
+        self.revive_expired_records(upper_time).await
+    }
+
+    /// Moves records visible before `upper_time` to the revive topic, returns how many were
+    /// moved. Records are only deleted from the kv store once the checkpoint is stored.
+    async fn revive_expired_records(&self, upper_time: i64) -> usize {
+        let batch_size = self.broker_config.pop_consumer_kv_service_batch_size.max(1);
+        let mut revived = 0;
+        loop {
+            let records = self
+                .kv_store
+                .scan_expired_records(0, upper_time, batch_size);
+            if records.is_empty() {
+                break;
+            }
+            let scanned = records.len();
+            let mut done = Vec::with_capacity(scanned);
+            for record in records {
+                let revived = if self.is_enabled() {
+                    self.revive_retry(&record).await
+                } else {
+                    self.put_check_point(&record).await
+                };
+                if revived {
+                    done.push(record);
+                } else {
+                    break;
+                }
+            }
+            self.kv_store.delete_records(&done);
+            revived += done.len();
+            if done.len() < scanned || scanned < batch_size {
+                break;
+            }
+        }
+        revived
+    }
+
+    /// Re-enqueues the message of an expired record into the pop retry topic or the DLQ.
+    /// Returns `false` only when the message should be tried again later.
+    async fn revive_retry(&self, record: &PopConsumerRecord) -> bool {
+        let Some(msg_ext) = self.get_message(record).await else {
+            warn!("pop revive skipped, message no longer in store, {}", record);
+            return true;
+        };
+        let max_reconsume_times = self
+            .subscription_group_manager
+            .find_subscription_group_config(&record.group_id)
+            .map(|config| config.retry_max_times())
+            .unwrap_or(DEFAULT_MAX_RECONSUME_TIMES);
+        let target = pop_retry::pop_retry_target(
+            record.topic_id.as_str(),
+            record.group_id.as_str(),
+            msg_ext.reconsume_times(),
+            max_reconsume_times,
+            self.broker_config.enable_retry_topic_v2,
+        );
+        let queue_nums = match target {
+            PopRetryTarget::Retry(_) => PopAckConstants::RETRY_QUEUE_NUM,
+            PopRetryTarget::DeadLetter(_) => DLQ_NUMS_PER_GROUP,
+        };
+        if self
+            .topic_config_manager
+            .clone()
+            .create_topic_in_send_message_back_method(
+                target.topic(),
+                queue_nums,
+                PermName::PERM_WRITE | PermName::PERM_READ,
+                false,
+                0,
+            )
+            .is_none()
+        {
+            warn!("pop revive failed, create topic {} failed", target.topic());
+            return false;
+        }
+        let msg_inner =
+            pop_retry::build_pop_retry_message(&msg_ext, &target, record.pop_time, self.store_host);
+        let result = self
+            .message_store
+            .mut_from_ref()
+            .put_message(msg_inner)
+            .await;
+        match result.put_message_status() {
+            PutMessageStatus::PutOk
+            | PutMessageStatus::FlushDiskTimeout
+            | PutMessageStatus::FlushSlaveTimeout
+            | PutMessageStatus::SlaveNotAvailable => {
+                if let PopRetryTarget::DeadLetter(topic) = &target {
+                    info!(
+                        "pop message reached max reconsume times {}, moved to {}, {}",
+                        max_reconsume_times, topic, record
+                    );
+                }
+                true
+            }
+            status => {
+                warn!(
+                    "pop revive put message to {} failed, status={:?}, {}",
+                    target.topic(),
+                    status,
+                    record
+                );
+                false
+            }
+        }
+    }
+
+    async fn get_message(&self, record: &PopConsumerRecord) -> Option<MessageExt> {
+        let result = self
+            .message_store
+            .get_message(
+                &record.group_id,
+                &record.topic_id,
+                record.queue_id,
+                record.offset,
+                1,
+                i32::MAX,
+                None,
+            )
+            .await?;
+        if result.status() != Some(GetMessageStatus::Found) {
+            return None;
+        }
+        let buffer = result.message_mapped_list().first()?;
+        let mut bytes = buffer.get_bytes()?;
+        MessageDecoder::decode(&mut bytes, true, false, false, false, false)
+    }
+
+    async fn put_check_point(&self, record: &PopConsumerRecord) -> bool {
+        let msg_inner = self.build_check_point_message(record);
+        let result = self
+            .message_store
+            .mut_from_ref()
+            .put_message(msg_inner)
+            .await;
+        match result.put_message_status() {
+            PutMessageStatus::PutOk
+            | PutMessageStatus::FlushDiskTimeout
+            | PutMessageStatus::FlushSlaveTimeout
+            | PutMessageStatus::SlaveNotAvailable => true,
+            status => {
+                warn!(
+                    "put pop checkpoint to revive topic failed, status={:?}, {}",
+                    status, record
+                );
+                false
+            }
+        }
+    }
+
+    fn build_check_point_message(&self, record: &PopConsumerRecord) -> MessageExtBrokerInner {
+        let broker_name = self.broker_config.broker_identity.broker_name.clone();
+        let check_point = PopCheckPoint {
+            start_offset: record.offset,
+            pop_time: record.pop_time,
+            invisible_time: record.invisible_time,
+            bit_map: 0,
+            num: 1,
+            queue_id: record.queue_id,
+            topic: record.topic_id.to_string(),
+            cid: record.group_id.to_string(),
+            revive_offset: 0,
+            queue_offset_diff: vec![0],
+            broker_name: Some(broker_name.to_string()),
+            re_put_times: None,
+        };
+        let revive_topic = PopAckConstants::build_cluster_revive_topic(
+            self.broker_config
+                .broker_identity
+                .broker_cluster_name
+                .as_str(),
+        );
+        let revive_queue_num = self.broker_config.revive_queue_num.max(1) as i32;
+
+        let mut msg_inner = MessageExtBrokerInner::default();
+        msg_inner.set_topic(CheetahString::from_string(revive_topic));
+        msg_inner.set_body(Bytes::from(
+            serde_json::to_vec(&check_point).unwrap_or_default(),
+        ));
+        msg_inner
+            .message_ext_inner
+            .set_queue_id(record.queue_id.rem_euclid(revive_queue_num));
+        msg_inner.set_tags(CheetahString::from_static_str(PopAckConstants::CK_TAG));
+        msg_inner.tags_code =
+            MessageExtBrokerInner::tags_string_to_tags_code(PopAckConstants::CK_TAG);
+        msg_inner.message_ext_inner.born_timestamp = get_current_millis() as i64;
+        msg_inner.message_ext_inner.born_host = self.store_host;
+        msg_inner.message_ext_inner.store_host = self.store_host;
+        msg_inner.set_deliver_time_ms(
+            (record.get_visibility_timeout() - PopAckConstants::ACK_TIME_INTERVAL).max(0) as u64,
+        );
+        msg_inner.put_property(
+            CheetahString::from_static_str(MessageConst::PROPERTY_UNIQ_CLIENT_MESSAGE_ID_KEYIDX),
+            CheetahString::from_string(format!(
+                "{}{}{}{}{}{}{}{}{}{}{}{}{}",
+                record.topic_id,
+                PopAckConstants::SPLIT,
+                record.queue_id,
+                PopAckConstants::SPLIT,
+                record.offset,
+                PopAckConstants::SPLIT,
+                record.group_id,
+                PopAckConstants::SPLIT,
+                record.pop_time,
+                PopAckConstants::SPLIT,
+                broker_name,
+                PopAckConstants::SPLIT,
+                PopAckConstants::CK_TAG
+            )),
+        );
+        msg_inner.properties_string =
+            MessageDecoder::message_properties_to_string(msg_inner.get_properties());
+        msg_inner
+    }
+}
@@ -0,0 +1,182 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::net::SocketAddr;
+
+use cheetah_string::CheetahString;
+use rocketmq_common::common::key_builder::KeyBuilder;
+use rocketmq_common::common::message::message_ext::MessageExt;
+use rocketmq_common::common::message::message_ext_broker_inner::MessageExtBrokerInner;
+use rocketmq_common::common::message::MessageConst;
+use rocketmq_common::common::message::MessageTrait;
+use rocketmq_common::common::mix_all;
+use rocketmq_common::MessageAccessor::MessageAccessor;
+use rocketmq_common::MessageDecoder;
+
+/// Where a popped message goes once its invisible time elapsed without an ack.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum PopRetryTarget {
+    /// Re-enqueue into the pop retry topic of the group.
+    Retry(CheetahString),
+    /// The group ran out of retries, move the message to its dead letter topic.
+    DeadLetter(CheetahString),
+}
+
+impl PopRetryTarget {
+    pub fn topic(&self) -> &CheetahString {
+        match self {
+            PopRetryTarget::Retry(topic) | PopRetryTarget::DeadLetter(topic) => topic,
+        }
+    }
+}
+
+/// Picks the retry or dead letter topic for a message of `topic` consumed by `group`.
+///
+/// Messages popped from a retry topic stay in it, so the retry topic of a retry topic is never
+/// built. A message goes to the DLQ once it was already retried `max_reconsume_times` times,
+/// the same rule `CONSUMER_SEND_MSG_BACK` applies for push consumers.
+pub(crate) fn pop_retry_target(
+    topic: &str,
+    group: &str,
+    reconsume_times: i32,
+    max_reconsume_times: i32,
+    enable_retry_topic_v2: bool,
+) -> PopRetryTarget {
+    if reconsume_times >= max_reconsume_times {
+        return PopRetryTarget::DeadLetter(CheetahString::from_string(mix_all::get_dlq_topic(
+            group,
+        )));
+    }
+    if topic.starts_with(mix_all::RETRY_GROUP_TOPIC_PREFIX) {
+        PopRetryTarget::Retry(CheetahString::from_string(topic.to_string()))
+    } else {
+        PopRetryTarget::Retry(CheetahString::from_string(
+            KeyBuilder::build_pop_retry_topic(topic, group, enable_retry_topic_v2),
+        ))
+    }
+}
+
+/// Builds the message re-enqueued for `msg_ext`, counting one more reconsume and keeping the
+/// time of the first pop so the client can report the end-to-end latency.
+pub(crate) fn build_pop_retry_message(
+    msg_ext: &MessageExt,
+    target: &PopRetryTarget,
+    pop_time: i64,
+    store_host: SocketAddr,
+) -> MessageExtBrokerInner {
+    let mut msg_inner = MessageExtBrokerInner::default();
+    msg_inner.set_topic(target.topic().clone());
+    if let Some(body) = msg_ext.get_body() {
+        msg_inner.set_body(body.clone());
+    }
+    msg_inner.set_flag(msg_ext.get_flag());
+    MessageAccessor::set_properties(&mut msg_inner, msg_ext.get_properties().clone());
+    if msg_ext.reconsume_times() == 0
+        || msg_inner
+            .get_property(&CheetahString::from_static_str(
+                MessageConst::PROPERTY_FIRST_POP_TIME,
+            ))
+            .is_none()
+    {
+        msg_inner.put_property(
+            CheetahString::from_static_str(MessageConst::PROPERTY_FIRST_POP_TIME),
+            CheetahString::from_string(pop_time.to_string()),
+        );
+    }
+    if msg_inner
+        .get_property(&CheetahString::from_static_str(
+            MessageConst::PROPERTY_ORIGIN_MESSAGE_ID,
+        ))
+        .is_none()
+    {
+        msg_inner.put_property(
+            CheetahString::from_static_str(MessageConst::PROPERTY_ORIGIN_MESSAGE_ID),
+            msg_ext.msg_id().clone(),
+        );
+    }
+    // the original delay already elapsed, the retry must be visible right away
+    msg_inner.clear_property(MessageConst::PROPERTY_DELAY_TIME_LEVEL);
+    msg_inner.properties_string =
+        MessageDecoder::message_properties_to_string(msg_inner.get_properties());
+    msg_inner.tags_code = MessageExtBrokerInner::tags_string_to_tags_code(
+        msg_ext.get_tags().unwrap_or_default().as_str(),
+    );
+    msg_inner.message_ext_inner.queue_id = 0;
+    msg_inner.message_ext_inner.sys_flag = msg_ext.sys_flag();
+    msg_inner.message_ext_inner.born_timestamp = msg_ext.born_timestamp;
+    msg_inner.message_ext_inner.born_host = store_host;
+    msg_inner.message_ext_inner.store_host = store_host;
+    msg_inner.message_ext_inner.reconsume_times = msg_ext.reconsume_times() + 1;
+    msg_inner
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pop_retry_target_uses_retry_topic_until_max_reconsume_times() {
+        let target = pop_retry_target("TopicA", "GroupA", 0, 16, false);
+        assert_eq!(
+            target,
+            PopRetryTarget::Retry(CheetahString::from_string(
+                KeyBuilder::build_pop_retry_topic("TopicA", "GroupA", false)
+            ))
+        );
+
+        let retry_topic = target.topic().to_string();
+        assert_eq!(
+            pop_retry_target(&retry_topic, "GroupA", 3, 16, false),
+            PopRetryTarget::Retry(CheetahString::from_string(retry_topic))
+        );
+
+        assert_eq!(
+            pop_retry_target("TopicA", "GroupA", 16, 16, false),
+            PopRetryTarget::DeadLetter(CheetahString::from_string(mix_all::get_dlq_topic(
+                "GroupA"
+            )))
+        );
+    }
+
+    #[test]
+    fn build_pop_retry_message_counts_reconsume_and_keeps_first_pop_time() {
+        let mut msg_ext = MessageExt::default();
+        msg_ext.set_topic(CheetahString::from_static_str("TopicA"));
+        msg_ext.set_reconsume_times(2);
+        msg_ext.put_property(
+            CheetahString::from_static_str(MessageConst::PROPERTY_FIRST_POP_TIME),
+            CheetahString::from_static_str("100"),
+        );
+        let store_host: SocketAddr = "127.0.0.1:10911".parse().unwrap();
+        let target = pop_retry_target("TopicA", "GroupA", 2, 16, true);
+
+        let msg_inner = build_pop_retry_message(&msg_ext, &target, 200, store_host);
+        assert_eq!(msg_inner.get_topic(), target.topic());
+        assert_eq!(msg_inner.reconsume_times(), 3);
+        assert_eq!(msg_inner.queue_id(), 0);
+        assert_eq!(
+            msg_inner
+                .get_property(&CheetahString::from_static_str(
+                    MessageConst::PROPERTY_FIRST_POP_TIME
+                ))
+                .unwrap(),
+            "100"
+        );
+        assert!(msg_inner
+            .properties_string
+            .contains(MessageConst::PROPERTY_ORIGIN_MESSAGE_ID));
+    }
+}
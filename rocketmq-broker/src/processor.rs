@@ -14,6 +14,8 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
+use std::sync::Arc;
+
 use rocketmq_remoting::code::request_code::RequestCode;
 use rocketmq_remoting::net::channel::Channel;
 use rocketmq_remoting::protocol::remoting_command::RemotingCommand;
@@ -25,11 +27,13 @@ use rocketmq_store::log_file::MessageStore;
 use tracing::info;
 
 use self::client_manage_processor::ClientManageProcessor;
+use crate::failover::broker_drain::BrokerDrain;
 use crate::processor::ack_message_processor::AckMessageProcessor;
 use crate::processor::admin_broker_processor::AdminBrokerProcessor;
 use crate::processor::change_invisible_time_processor::ChangeInvisibleTimeProcessor;
 use crate::processor::consumer_manage_processor::ConsumerManageProcessor;
 use crate::processor::end_transaction_processor::EndTransactionProcessor;
+use crate::processor::maintenance_mode::MaintenanceMode;
 use crate::processor::notification_processor::NotificationProcessor;
 use crate::processor::peek_message_processor::PeekMessageProcessor;
 use crate::processor::polling_info_processor::PollingInfoProcessor;
@@ -38,6 +42,7 @@ use crate::processor::pull_message_processor::PullMessageProcessor;
 use crate::processor::query_assignment_processor::QueryAssignmentProcessor;
 use crate::processor::query_message_processor::QueryMessageProcessor;
 use crate::processor::reply_message_processor::ReplyMessageProcessor;
+use crate::processor::request_priority_dispatcher::RequestPriorityDispatcher;
 use crate::processor::send_message_processor::SendMessageProcessor;
 use crate::transaction::transactional_message_service::TransactionalMessageService;
 
@@ -48,8 +53,10 @@ pub(crate) mod client_manage_processor;
 pub(crate) mod consumer_manage_processor;
 pub(crate) mod default_pull_message_result_handler;
 pub(crate) mod end_transaction_processor;
+pub(crate) mod maintenance_mode;
 pub(crate) mod notification_processor;
 pub(crate) mod peek_message_processor;
+pub(crate) mod poison_message_tracker;
 pub(crate) mod polling_info_processor;
 pub(crate) mod pop_inflight_message_counter;
 pub(crate) mod pop_message_processor;
@@ -58,6 +65,8 @@ pub(crate) mod pull_message_result_handler;
 pub(crate) mod query_assignment_processor;
 pub(crate) mod query_message_processor;
 pub(crate) mod reply_message_processor;
+pub(crate) mod request_priority_dispatcher;
+pub(crate) mod response_body_compressor;
 pub(crate) mod send_message_processor;
 
 pub struct BrokerRequestProcessor<MS, TS> {
@@ -76,6 +85,9 @@ pub struct BrokerRequestProcessor<MS, TS> {
     pub(crate) query_assignment_processor: ArcMut<QueryAssignmentProcessor>,
     pub(crate) end_transaction_processor: ArcMut<EndTransactionProcessor<TS, MS>>,
     pub(crate) admin_broker_processor: ArcMut<AdminBrokerProcessor>,
+    pub(crate) request_priority_dispatcher: Arc<RequestPriorityDispatcher>,
+    pub(crate) maintenance_mode: Arc<MaintenanceMode>,
+    pub(crate) broker_drain: Arc<BrokerDrain>,
 }
 impl<MS, TS> Clone for BrokerRequestProcessor<MS, TS> {
     fn clone(&self) -> Self {
@@ -95,6 +107,9 @@ impl<MS, TS> Clone for BrokerRequestProcessor<MS, TS> {
             query_assignment_processor: self.query_assignment_processor.clone(),
             query_message_processor: self.query_message_processor.clone(),
             end_transaction_processor: self.end_transaction_processor.clone(),
+            request_priority_dispatcher: self.request_priority_dispatcher.clone(),
+            maintenance_mode: self.maintenance_mode.clone(),
+            broker_drain: self.broker_drain.clone(),
         }
     }
 }
@@ -112,6 +127,17 @@ where
     ) -> Result<Option<RemotingCommand>> {
         let request_code = RequestCode::from(request.code());
         info!("process_request: {:?}", request_code);
+        if let Some(response) = self
+            .maintenance_mode
+            .check(request_code, channel.remote_address().ip())
+        {
+            return Ok(Some(response));
+        }
+        if let Some(response) = self.broker_drain.check(request_code) {
+            return Ok(Some(response));
+        }
+        let request_priority_dispatcher = self.request_priority_dispatcher.clone();
+        let _permit = request_priority_dispatcher.acquire(request_code).await;
         let result = match request_code {
             RequestCode::SendMessage
             | RequestCode::SendMessageV2
@@ -162,6 +188,22 @@ where
                     .await
             }
 
+            RequestCode::EndTransactionBatch => {
+                self.end_transaction_processor
+                    .process_batch_request(channel, ctx, request_code, request)
+                    .await
+            }
+
+            RequestCode::GetOrphanedTransactions => self
+                .end_transaction_processor
+                .get_orphaned_transactions(request_code, request),
+
+            RequestCode::ResolveOrphanedTransaction => {
+                self.end_transaction_processor
+                    .resolve_orphaned_transaction(channel, request_code, request)
+                    .await
+            }
+
             RequestCode::QueryConsumeQueue | RequestCode::SetMessageRequestMode => {
                 self.query_assignment_processor
                     .process_request(channel, ctx, request_code, request)
@@ -14,6 +14,7 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
+use std::net::SocketAddr;
 use std::sync::Arc;
 
 use rocketmq_common::common::broker::broker_config::BrokerConfig;
@@ -26,13 +27,20 @@ use rocketmq_remoting::protocol::remoting_command::RemotingCommand;
 use rocketmq_remoting::runtime::connection_handler_context::ConnectionHandlerContext;
 use rocketmq_rust::ArcMut;
 use rocketmq_store::config::message_store_config::MessageStoreConfig;
+use rocketmq_store::log_file::MessageStore;
 use rocketmq_store::message_store::default_message_store::DefaultMessageStore;
 use rocketmq_store::stats::broker_stats::BrokerStats;
 use rocketmq_store::stats::broker_stats_manager::BrokerStatsManager;
+use tracing::error;
 use tracing::warn;
 
+use crate::acl::admin_access_validator::AdminAccessValidator;
+use crate::acl::audit_log::AuditLog;
+use crate::acl::audit_log::AuditRecord;
 use crate::client::manager::consumer_manager::ConsumerManager;
 use crate::client::rebalance::rebalance_lock_manager::RebalanceLockManager;
+use crate::failover::broker_drain::BrokerDrain;
+use crate::failover::escape_bridge::EscapeBridge;
 use crate::offset::manager::consumer_offset_manager::ConsumerOffsetManager;
 use crate::out_api::broker_outer_api::BrokerOuterAPI;
 use crate::processor::admin_broker_processor::batch_mq_handler::BatchMqHandler;
@@ -40,8 +48,13 @@ use crate::processor::admin_broker_processor::broker_config_request_handler::Bro
 use crate::processor::admin_broker_processor::consumer_request_handler::ConsumerRequestHandler;
 use crate::processor::admin_broker_processor::offset_request_handler::OffsetRequestHandler;
 use crate::processor::admin_broker_processor::topic_request_handler::TopicRequestHandler;
+use crate::processor::maintenance_mode::MaintenanceMode;
+use crate::processor::poison_message_tracker::PoisonMessageTracker;
 use crate::processor::pop_inflight_message_counter::PopInflightMessageCounter;
+use crate::processor::request_priority_dispatcher::RequestPriorityDispatcher;
 use crate::schedule::schedule_message_service::ScheduleMessageService;
+use crate::subscription::manager::subscription_group_manager::SubscriptionGroupManager;
+use crate::subscription::replay_subscription_manager::ReplaySubscriptionManager;
 use crate::topic::manager::topic_config_manager::TopicConfigManager;
 use crate::topic::manager::topic_queue_mapping_manager::TopicQueueMappingManager;
 
@@ -57,6 +70,10 @@ pub struct AdminBrokerProcessor {
     consumer_request_handler: ConsumerRequestHandler,
     offset_request_handler: OffsetRequestHandler,
     batch_mq_handler: BatchMqHandler,
+    admin_access_validator: Arc<AdminAccessValidator>,
+    audit_log: Arc<AuditLog>,
+    default_message_store: ArcMut<DefaultMessageStore>,
+    store_host: SocketAddr,
 }
 
 impl AdminBrokerProcessor {
@@ -75,7 +92,20 @@ impl AdminBrokerProcessor {
         broker_stats_manager: Arc<BrokerStatsManager>,
         rebalance_lock_manager: Arc<RebalanceLockManager>,
         broker_member_group: Arc<BrokerMemberGroup>,
+        request_priority_dispatcher: Arc<RequestPriorityDispatcher>,
+        maintenance_mode: Arc<MaintenanceMode>,
+        admin_access_validator: Arc<AdminAccessValidator>,
+        broker_drain: Arc<BrokerDrain>,
+        escape_bridge: ArcMut<EscapeBridge<DefaultMessageStore>>,
+        subscription_group_manager: Arc<SubscriptionGroupManager<DefaultMessageStore>>,
+        audit_log: Arc<AuditLog>,
+        poison_message_tracker: Arc<PoisonMessageTracker>,
+        replay_subscription_manager: Arc<ReplaySubscriptionManager>,
     ) -> Self {
+        let store_host = broker_config
+            .get_broker_addr()
+            .parse::<SocketAddr>()
+            .expect("parse store host failed");
         let inner = Inner {
             broker_config,
             server_config,
@@ -83,7 +113,7 @@ impl AdminBrokerProcessor {
             topic_config_manager,
             consumer_offset_manager,
             topic_queue_mapping_manager,
-            default_message_store,
+            default_message_store: default_message_store.clone(),
             pop_inflight_message_counter: Arc::new(PopInflightMessageCounter),
             schedule_message_service,
             broker_stats,
@@ -92,6 +122,13 @@ impl AdminBrokerProcessor {
             broker_stats_manager,
             rebalance_lock_manager,
             broker_member_group,
+            request_priority_dispatcher,
+            maintenance_mode,
+            broker_drain,
+            escape_bridge,
+            subscription_group_manager,
+            poison_message_tracker,
+            replay_subscription_manager,
         };
         let topic_request_handler = TopicRequestHandler::new(inner.clone());
         let broker_config_request_handler = BrokerConfigRequestHandler::new(inner.clone());
@@ -104,6 +141,10 @@ impl AdminBrokerProcessor {
             consumer_request_handler,
             offset_request_handler,
             batch_mq_handler,
+            admin_access_validator,
+            audit_log,
+            default_message_store,
+            store_host,
         }
     }
 }
@@ -115,6 +156,60 @@ impl AdminBrokerProcessor {
         ctx: ConnectionHandlerContext,
         request_code: RequestCode,
         request: RemotingCommand,
+    ) -> Option<RemotingCommand> {
+        let remote_address = channel.remote_address();
+        let audit_record = if self.audit_log.is_enabled() {
+            AuditRecord::of(request_code, &request, remote_address)
+        } else {
+            None
+        };
+        let (response, denied) =
+            match self
+                .admin_access_validator
+                .check(request_code, &request, remote_address.ip())
+            {
+                Some(response) => (Some(response), true),
+                None => (
+                    self.dispatch_request(channel, ctx, request_code, request)
+                        .await,
+                    false,
+                ),
+            };
+        if let Some(record) = audit_record {
+            self.audit(record.complete(response.as_ref(), denied)).await;
+        }
+        response
+    }
+
+    async fn audit(&self, record: AuditRecord) {
+        self.audit_log.append(&record);
+        if !self.audit_log.is_export_enabled() {
+            return;
+        }
+        if let Some(msg_inner) = self
+            .audit_log
+            .build_export_message(&record, self.store_host)
+        {
+            let result = self
+                .default_message_store
+                .mut_from_ref()
+                .put_message(msg_inner)
+                .await;
+            if !result.is_ok() {
+                error!(
+                    "export audit record to trace topic failed, status: {:?}",
+                    result.put_message_status()
+                );
+            }
+        }
+    }
+
+    async fn dispatch_request(
+        &mut self,
+        channel: Channel,
+        ctx: ConnectionHandlerContext,
+        request_code: RequestCode,
+        request: RemotingCommand,
     ) -> Option<RemotingCommand> {
         match request_code {
             RequestCode::UpdateAndCreateTopic => {
@@ -162,6 +257,11 @@ impl AdminBrokerProcessor {
                     .get_consumer_connection_list(channel, ctx, request_code, request)
                     .await
             }
+            RequestCode::ConsumeMessageDirectly => {
+                self.consumer_request_handler
+                    .consume_message_directly(channel, ctx, request_code, request)
+                    .await
+            }
             RequestCode::GetConsumeStats => {
                 self.consumer_request_handler
                     .get_consume_stats(channel, ctx, request_code, request)
@@ -182,6 +282,76 @@ impl AdminBrokerProcessor {
                     .get_broker_runtime_info(channel, ctx, request_code, request)
                     .await
             }
+            RequestCode::GetStoreHealth => {
+                self.broker_config_request_handler
+                    .get_store_health(channel, ctx, request_code, request)
+                    .await
+            }
+            RequestCode::GetDiskUsageInfo => {
+                self.broker_config_request_handler
+                    .get_disk_usage_info(channel, ctx, request_code, request)
+                    .await
+            }
+            RequestCode::UpdateMaintenanceMode => {
+                self.broker_config_request_handler
+                    .update_maintenance_mode(channel, ctx, request_code, request)
+                    .await
+            }
+            RequestCode::UpdateBrokerDrain => {
+                self.broker_config_request_handler
+                    .update_broker_drain(channel, ctx, request_code, request)
+                    .await
+            }
+            RequestCode::GetPoisonMessageReport => {
+                self.consumer_request_handler
+                    .get_poison_message_report(channel, ctx, request_code, request)
+                    .await
+            }
+            RequestCode::ResumePausedQueue => {
+                self.consumer_request_handler
+                    .resume_paused_queue(channel, ctx, request_code, request)
+                    .await
+            }
+            RequestCode::CreateReplaySubscription => {
+                self.consumer_request_handler
+                    .create_replay_subscription(channel, ctx, request_code, request)
+                    .await
+            }
+            RequestCode::DeleteReplaySubscription => {
+                self.consumer_request_handler
+                    .delete_replay_subscription(channel, ctx, request_code, request)
+                    .await
+            }
+            RequestCode::FlushStore => {
+                self.broker_config_request_handler
+                    .flush_store(channel, ctx, request_code, request)
+                    .await
+            }
+            RequestCode::DeleteExpiredCommitlog => {
+                self.broker_config_request_handler
+                    .delete_expired_commit_log(channel, ctx, request_code, request)
+                    .await
+            }
+            RequestCode::CreateStoreSnapshot => {
+                self.broker_config_request_handler
+                    .create_store_snapshot(channel, ctx, request_code, request)
+                    .await
+            }
+            RequestCode::UpdateQueueWriteFence => {
+                self.topic_request_handler
+                    .update_queue_write_fence(channel, ctx, request_code, request)
+                    .await
+            }
+            RequestCode::GetHotMappedFiles => {
+                self.broker_config_request_handler
+                    .get_hot_mapped_files(channel, ctx, request_code, request)
+                    .await
+            }
+            RequestCode::GetTimerMetrics => {
+                self.broker_config_request_handler
+                    .get_timer_metrics(channel, ctx, request_code, request)
+                    .await
+            }
             RequestCode::QueryTopicConsumeByWho => {
                 self.topic_request_handler
                     .query_topic_consume_by_who(channel, ctx, request_code, request)
@@ -202,6 +372,16 @@ impl AdminBrokerProcessor {
                     .get_min_offset(channel, ctx, request_code, request)
                     .await
             }
+            RequestCode::CheckConfirmOffset => {
+                self.offset_request_handler
+                    .check_confirm_offset(channel, ctx, request_code, request)
+                    .await
+            }
+            RequestCode::ExamineConsumeQueue => {
+                self.offset_request_handler
+                    .examine_consume_queue(channel, ctx, request_code, request)
+                    .await
+            }
 
             RequestCode::LockBatchMq => {
                 self.batch_mq_handler
@@ -248,4 +428,11 @@ struct Inner {
     broker_stats_manager: Arc<BrokerStatsManager>,
     rebalance_lock_manager: Arc<RebalanceLockManager>,
     broker_member_group: Arc<BrokerMemberGroup>,
+    request_priority_dispatcher: Arc<RequestPriorityDispatcher>,
+    maintenance_mode: Arc<MaintenanceMode>,
+    broker_drain: Arc<BrokerDrain>,
+    escape_bridge: ArcMut<EscapeBridge<DefaultMessageStore>>,
+    subscription_group_manager: Arc<SubscriptionGroupManager<DefaultMessageStore>>,
+    poison_message_tracker: Arc<PoisonMessageTracker>,
+    replay_subscription_manager: Arc<ReplaySubscriptionManager>,
 }
@@ -16,20 +16,29 @@
  */
 
 use std::collections::HashMap;
+use std::path::Path;
 
 use cheetah_string::CheetahString;
+use rocketmq_common::common::config_manager::ConfigManager;
 use rocketmq_common::common::mix_all;
 use rocketmq_common::common::mq_version::RocketMqVersion;
+use rocketmq_common::common::topic::TopicValidator;
 use rocketmq_remoting::code::request_code::RequestCode;
+use rocketmq_remoting::code::response_code::ResponseCode;
 use rocketmq_remoting::net::channel::Channel;
 use rocketmq_remoting::protocol::body::kv_table::KVTable;
 use rocketmq_remoting::protocol::remoting_command::RemotingCommand;
 use rocketmq_remoting::runtime::connection_handler_context::ConnectionHandlerContext;
 use rocketmq_store::log_file::MessageStore;
 use sysinfo::Disks;
+use tracing::warn;
 
+use crate::failover::broker_drain;
+use crate::failover::broker_drain::DrainState;
 use crate::processor::admin_broker_processor::Inner;
 
+const DEFAULT_HOT_MAPPED_FILES_TOP_N: usize = 10;
+
 #[derive(Clone)]
 pub(super) struct BrokerConfigRequestHandler {
     inner: Inner,
@@ -99,11 +108,326 @@ impl BrokerConfigRequestHandler {
         Some(response)
     }
 
+    pub async fn get_store_health(
+        &mut self,
+        _channel: Channel,
+        _ctx: ConnectionHandlerContext,
+        _request_code: RequestCode,
+        _request: RemotingCommand,
+    ) -> Option<RemotingCommand> {
+        let health = self.inner.default_message_store.health();
+        let mut response = if health.healthy {
+            RemotingCommand::create_response_command()
+        } else {
+            RemotingCommand::create_response_command_with_code_remark(
+                ResponseCode::SystemError,
+                "message store is unhealthy",
+            )
+        };
+        response.set_body_mut_ref(serde_json::to_vec(&health).unwrap());
+        Some(response)
+    }
+
+    pub async fn get_disk_usage_info(
+        &mut self,
+        _channel: Channel,
+        _ctx: ConnectionHandlerContext,
+        _request_code: RequestCode,
+        _request: RemotingCommand,
+    ) -> Option<RemotingCommand> {
+        let disk_usage_info = self.inner.default_message_store.disk_usage_info();
+        Some(
+            RemotingCommand::create_response_command()
+                .set_body(serde_json::to_vec(&disk_usage_info).unwrap()),
+        )
+    }
+
+    pub async fn get_hot_mapped_files(
+        &mut self,
+        _channel: Channel,
+        _ctx: ConnectionHandlerContext,
+        _request_code: RequestCode,
+        request: RemotingCommand,
+    ) -> Option<RemotingCommand> {
+        let top_n = request
+            .ext_fields()
+            .and_then(|fields| fields.get("topN"))
+            .and_then(|top_n| top_n.parse::<usize>().ok())
+            .unwrap_or(DEFAULT_HOT_MAPPED_FILES_TOP_N);
+        let hot_files = self.inner.default_message_store.hot_mapped_files(top_n);
+        Some(
+            RemotingCommand::create_response_command()
+                .set_body(serde_json::to_vec(&hot_files).unwrap()),
+        )
+    }
+
+    /// Enters or leaves maintenance mode when `enable` is given and returns the current state.
+    pub async fn update_maintenance_mode(
+        &mut self,
+        _channel: Channel,
+        _ctx: ConnectionHandlerContext,
+        _request_code: RequestCode,
+        request: RemotingCommand,
+    ) -> Option<RemotingCommand> {
+        let maintenance_mode = &self.inner.maintenance_mode;
+        if let Some(fields) = request.ext_fields() {
+            if let Some(white_list) = fields.get("whiteList") {
+                maintenance_mode.update_white_list(white_list.as_str());
+            }
+            match fields.get("enable").map(|enable| enable.parse::<bool>()) {
+                Some(Ok(true)) => {
+                    let reason = fields.get("reason").map(|r| r.as_str()).unwrap_or_default();
+                    maintenance_mode.enter(reason);
+                }
+                Some(Ok(false)) => maintenance_mode.exit(),
+                Some(Err(_)) => {
+                    return Some(RemotingCommand::create_response_command_with_code_remark(
+                        ResponseCode::InvalidParameter,
+                        "enable must be true or false",
+                    ));
+                }
+                None => {}
+            }
+        }
+        Some(
+            RemotingCommand::create_response_command()
+                .set_body(serde_json::to_vec(&maintenance_mode.status()).unwrap()),
+        )
+    }
+
+    /// Starts or stops draining the broker when `enable` is given and returns the drain
+    /// progress. `topics` limits the drain to a comma separated list of topics and
+    /// `republish` forwards their unconsumed messages to other brokers.
+    pub async fn update_broker_drain(
+        &mut self,
+        _channel: Channel,
+        _ctx: ConnectionHandlerContext,
+        _request_code: RequestCode,
+        request: RemotingCommand,
+    ) -> Option<RemotingCommand> {
+        let broker_drain = self.inner.broker_drain.clone();
+        if let Some(fields) = request.ext_fields() {
+            let republish = match fields.get("republish").map(|value| value.parse::<bool>()) {
+                Some(Ok(republish)) => republish,
+                Some(Err(_)) => {
+                    return Some(RemotingCommand::create_response_command_with_code_remark(
+                        ResponseCode::InvalidParameter,
+                        "republish must be true or false",
+                    ));
+                }
+                None => false,
+            };
+            match fields.get("enable").map(|enable| enable.parse::<bool>()) {
+                Some(Ok(true)) => {
+                    let topics = fields
+                        .get("topics")
+                        .map(|topics| {
+                            topics
+                                .split(',')
+                                .map(str::trim)
+                                .filter(|topic| !topic.is_empty())
+                                .map(CheetahString::from)
+                                .collect::<Vec<_>>()
+                        })
+                        .unwrap_or_default();
+                    broker_drain.start(topics, republish);
+                    if republish {
+                        self.spawn_republish();
+                    }
+                }
+                Some(Ok(false)) => broker_drain.stop(),
+                Some(Err(_)) => {
+                    return Some(RemotingCommand::create_response_command_with_code_remark(
+                        ResponseCode::InvalidParameter,
+                        "enable must be true or false",
+                    ));
+                }
+                None => {}
+            }
+        }
+        let backlogs = if broker_drain.is_draining() {
+            self.drain_targets()
+                .into_iter()
+                .map(|(topic, queue_nums)| {
+                    let backlog = broker_drain::topic_backlog(
+                        self.inner.default_message_store.as_ref(),
+                        &self.inner.consumer_offset_manager,
+                        &topic,
+                        queue_nums,
+                    );
+                    (topic, backlog)
+                })
+                .collect()
+        } else {
+            vec![]
+        };
+        Some(
+            RemotingCommand::create_response_command()
+                .set_body(serde_json::to_vec(&broker_drain.status(backlogs)).unwrap()),
+        )
+    }
+
+    /// Topics covered by the running drain with their read queue numbers.
+    fn drain_targets(&self) -> Vec<(CheetahString, i32)> {
+        let selected = self.inner.broker_drain.topics();
+        let topic_config_table = self.inner.topic_config_manager.topic_config_table();
+        let topic_config_table = topic_config_table.lock();
+        topic_config_table
+            .iter()
+            .filter(|(topic, _)| {
+                if selected.is_empty() {
+                    !TopicValidator::is_system_topic(topic.as_str())
+                } else {
+                    selected.contains(topic)
+                }
+            })
+            .map(|(topic, config)| (topic.clone(), config.read_queue_nums as i32))
+            .collect()
+    }
+
+    fn spawn_republish(&self) {
+        let targets = self.drain_targets();
+        let broker_drain = self.inner.broker_drain.clone();
+        let mut escape_bridge = self.inner.escape_bridge.clone();
+        let message_store = self.inner.default_message_store.clone();
+        let consumer_offset_manager = self.inner.consumer_offset_manager.clone();
+        tokio::spawn(async move {
+            broker_drain.set_state(DrainState::Republishing);
+            for (topic, queue_nums) in targets {
+                if !broker_drain.is_draining() {
+                    break;
+                }
+                broker_drain::republish_topic(
+                    broker_drain.as_ref(),
+                    &mut escape_bridge,
+                    message_store.as_ref(),
+                    &consumer_offset_manager,
+                    &topic,
+                    queue_nums,
+                )
+                .await;
+            }
+            broker_drain.set_state(DrainState::Draining);
+        });
+    }
+
+    /// Persists the store and every JSON config manager immediately, e.g. before planned host
+    /// maintenance or a snapshot.
+    pub async fn flush_store(
+        &mut self,
+        _channel: Channel,
+        _ctx: ConnectionHandlerContext,
+        _request_code: RequestCode,
+        _request: RemotingCommand,
+    ) -> Option<RemotingCommand> {
+        let result = match self.inner.default_message_store.flush_all() {
+            Ok(result) => result,
+            Err(e) => {
+                return Some(RemotingCommand::create_response_command_with_code_remark(
+                    ResponseCode::SystemError,
+                    format!("flush store failed: {}", e),
+                ));
+            }
+        };
+        self.inner.topic_config_manager.persist();
+        self.inner.topic_queue_mapping_manager.persist();
+        self.inner.consumer_offset_manager.persist();
+        self.inner.subscription_group_manager.persist();
+        self.inner.schedule_message_service.persist();
+        Some(
+            RemotingCommand::create_response_command()
+                .set_body(serde_json::to_vec(&result).unwrap()),
+        )
+    }
+
+    pub async fn delete_expired_commit_log(
+        &mut self,
+        _channel: Channel,
+        _ctx: ConnectionHandlerContext,
+        _request_code: RequestCode,
+        _request: RemotingCommand,
+    ) -> Option<RemotingCommand> {
+        warn!("invoke deleteExpiredCommitLog start.");
+        self.inner
+            .default_message_store
+            .execute_delete_files_manually();
+        warn!("invoke deleteExpiredCommitLog end.");
+        Some(RemotingCommand::create_response_command())
+    }
+
+    pub async fn create_store_snapshot(
+        &mut self,
+        _channel: Channel,
+        _ctx: ConnectionHandlerContext,
+        _request_code: RequestCode,
+        request: RemotingCommand,
+    ) -> Option<RemotingCommand> {
+        let snapshot_dir = match request
+            .ext_fields()
+            .and_then(|fields| fields.get("snapshotDir"))
+        {
+            Some(dir) if !dir.is_empty() => dir.to_string(),
+            _ => {
+                return Some(RemotingCommand::create_response_command_with_code_remark(
+                    ResponseCode::InvalidParameter,
+                    "snapshotDir is required",
+                ));
+            }
+        };
+        match self
+            .inner
+            .default_message_store
+            .create_snapshot(Path::new(&snapshot_dir))
+        {
+            Ok(manifest) => Some(
+                RemotingCommand::create_response_command()
+                    .set_body(serde_json::to_vec(&manifest).unwrap()),
+            ),
+            Err(e) => Some(RemotingCommand::create_response_command_with_code_remark(
+                ResponseCode::SystemError,
+                format!("create store snapshot failed: {}", e),
+            )),
+        }
+    }
+
+    pub async fn get_timer_metrics(
+        &mut self,
+        _channel: Channel,
+        _ctx: ConnectionHandlerContext,
+        _request_code: RequestCode,
+        _request: RemotingCommand,
+    ) -> Option<RemotingCommand> {
+        if !self.inner.message_store_config.is_timer_wheel_enable() {
+            return Some(RemotingCommand::create_response_command_with_code_remark(
+                ResponseCode::SystemError,
+                "timer wheel is not enabled",
+            ));
+        }
+        let timer_status = self
+            .inner
+            .default_message_store
+            .get_timer_message_store()
+            .timer_status();
+        Some(
+            RemotingCommand::create_response_command()
+                .set_body(serde_json::to_vec(&timer_status).unwrap()),
+        )
+    }
+
     fn prepare_runtime_info(&self) -> HashMap<CheetahString, CheetahString> {
         let mut runtime_info = self.inner.default_message_store.get_runtime_info();
         self.inner
             .schedule_message_service
             .build_running_stats(&mut runtime_info);
+        self.inner
+            .request_priority_dispatcher
+            .build_running_stats(&mut runtime_info);
+        self.inner
+            .maintenance_mode
+            .build_running_stats(&mut runtime_info);
+        self.inner
+            .broker_drain
+            .build_running_stats(&mut runtime_info);
         runtime_info.insert(
             "brokerActive".to_string(),
             self.is_special_service_running().to_string(),
@@ -232,12 +556,26 @@ impl BrokerConfigRequestHandler {
                     .get_dequeue_tps()
                     .to_string(),
             );
+            let timer_message_store = self.inner.default_message_store.get_timer_message_store();
+            runtime_info.insert(
+                "timerCongested".to_string(),
+                timer_message_store.is_congested().to_string(),
+            );
+            runtime_info.insert(
+                "timerPendingNum".to_string(),
+                timer_message_store
+                    .timer_metrics()
+                    .pending_total()
+                    .to_string(),
+            );
         } else {
             runtime_info.insert("timerReadBehind".to_string(), "0".to_string());
             runtime_info.insert("timerOffsetBehind".to_string(), "0".to_string());
             runtime_info.insert("timerCongestNum".to_string(), "0".to_string());
             runtime_info.insert("timerEnqueueTps".to_string(), "0.0".to_string());
             runtime_info.insert("timerDequeueTps".to_string(), "0.0".to_string());
+            runtime_info.insert("timerCongested".to_string(), "false".to_string());
+            runtime_info.insert("timerPendingNum".to_string(), "0".to_string());
         }
         let default_message_store = self.inner.default_message_store.clone();
         runtime_info.insert(
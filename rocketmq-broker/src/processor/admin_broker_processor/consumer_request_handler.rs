@@ -15,10 +15,13 @@
  * limitations under the License.
  */
 
+use std::collections::HashMap;
 use std::collections::HashSet;
 
 use rocketmq_common::common::config_manager::ConfigManager;
+use rocketmq_common::common::message::message_decoder;
 use rocketmq_common::common::message::message_queue::MessageQueue;
+use rocketmq_common::TimeUtils::get_current_millis;
 use rocketmq_remoting::code::request_code::RequestCode;
 use rocketmq_remoting::code::response_code::ResponseCode;
 use rocketmq_remoting::net::channel::Channel;
@@ -26,15 +29,25 @@ use rocketmq_remoting::protocol::admin::consume_stats::ConsumeStats;
 use rocketmq_remoting::protocol::admin::offset_wrapper::OffsetWrapper;
 use rocketmq_remoting::protocol::body::connection::Connection;
 use rocketmq_remoting::protocol::body::consumer_connection::ConsumerConnection;
+use rocketmq_remoting::protocol::body::replay_subscription_body::CreateReplaySubscriptionRequestBody;
+use rocketmq_remoting::protocol::body::replay_subscription_body::ReplaySubscription;
+use rocketmq_remoting::protocol::header::consume_message_directly_result_request_header::ConsumeMessageDirectlyResultRequestHeader;
 use rocketmq_remoting::protocol::header::get_consume_stats_request_header::GetConsumeStatsRequestHeader;
 use rocketmq_remoting::protocol::header::get_consumer_connection_list_request_header::GetConsumerConnectionListRequestHeader;
 use rocketmq_remoting::protocol::remoting_command::RemotingCommand;
+use rocketmq_remoting::protocol::subscription::subscription_group_config::SubscriptionGroupConfig;
+use rocketmq_remoting::protocol::RemotingDeserializable;
 use rocketmq_remoting::protocol::RemotingSerializable;
 use rocketmq_remoting::runtime::connection_handler_context::ConnectionHandlerContext;
 use rocketmq_store::log_file::MessageStore;
+use tracing::info;
 use tracing::warn;
 
+use crate::client::net::broker_to_client::Broker2Client;
 use crate::processor::admin_broker_processor::Inner;
+use crate::subscription::replay_subscription_manager::release_replay_subscription;
+use crate::subscription::replay_subscription_manager::search_offset_by_timestamp;
+use crate::subscription::replay_subscription_manager::ReplaySubscriptionManager;
 
 #[derive(Clone)]
 pub(super) struct ConsumerRequestHandler {
@@ -100,6 +113,89 @@ impl ConsumerRequestHandler {
         }
     }
 
+    /// Forwards a message of this broker to one consumer instance so it is consumed right
+    /// away, bypassing the normal pull path. Used by the admin tools to debug listeners.
+    pub async fn consume_message_directly(
+        &mut self,
+        _channel: Channel,
+        _ctx: ConnectionHandlerContext,
+        _request_code: RequestCode,
+        mut request: RemotingCommand,
+    ) -> Option<RemotingCommand> {
+        let Ok(request_header) =
+            request.decode_command_custom_header::<ConsumeMessageDirectlyResultRequestHeader>()
+        else {
+            return Some(RemotingCommand::create_response_command_with_code_remark(
+                ResponseCode::InvalidParameter,
+                "decode ConsumeMessageDirectlyResultRequestHeader failed",
+            ));
+        };
+        request.add_ext_field("brokerName", self.inner.broker_config.broker_name.clone());
+        if let Some(message_id) = request_header
+            .msg_id
+            .as_ref()
+            .and_then(|msg_id| message_decoder::try_decode_message_id(msg_id))
+        {
+            if let Some(body) = self
+                .inner
+                .default_message_store
+                .select_one_message_by_offset(message_id.offset)
+                .await
+                .and_then(|result| result.get_bytes())
+            {
+                request.set_body_mut_ref(body);
+            }
+        }
+
+        let client_channel_info = self
+            .inner
+            .consume_manager
+            .get_consumer_group_info(&request_header.consumer_group)
+            .and_then(|group_info| {
+                group_info.find_channel_by_client_id(
+                    request_header.client_id.as_deref().unwrap_or_default(),
+                )
+            });
+        let Some(client_channel_info) = client_channel_info else {
+            return Some(RemotingCommand::create_response_command_with_code_remark(
+                ResponseCode::SystemError,
+                format!(
+                    "The Consumer <{}> <{}> not online",
+                    request_header.consumer_group,
+                    request_header.client_id.unwrap_or_default()
+                ),
+            ));
+        };
+        let mut new_request =
+            RemotingCommand::create_remoting_command(RequestCode::ConsumeMessageDirectly);
+        if let Some(ext_fields) = request.ext_fields() {
+            new_request = new_request.set_ext_fields(ext_fields.clone());
+        }
+        if let Some(body) = request.body().clone() {
+            new_request.set_body_mut_ref(body);
+        }
+        let mut channel = client_channel_info.channel().clone();
+        match Broker2Client
+            .call_client(
+                &mut channel,
+                new_request,
+                self.inner.broker_config.forward_timeout,
+            )
+            .await
+        {
+            Ok(response) => Some(response),
+            Err(e) => Some(RemotingCommand::create_response_command_with_code_remark(
+                ResponseCode::SystemError,
+                format!(
+                    "invoke consumer <{}> <{}> Exception: {}",
+                    request_header.consumer_group,
+                    request_header.client_id.unwrap_or_default(),
+                    e
+                ),
+            )),
+        }
+    }
+
     pub async fn get_consume_stats(
         &mut self,
         _channel: Channel,
@@ -240,4 +336,244 @@ impl ConsumerRequestHandler {
             )
         }
     }
+
+    /// Returns the send back rates and poison messages of `consumerGroup`, or of every group
+    /// when the field is absent.
+    pub async fn get_poison_message_report(
+        &mut self,
+        _channel: Channel,
+        _ctx: ConnectionHandlerContext,
+        _request_code: RequestCode,
+        request: RemotingCommand,
+    ) -> Option<RemotingCommand> {
+        let group = request
+            .ext_fields()
+            .and_then(|fields| fields.get("consumerGroup"))
+            .cloned();
+        let report = self.inner.poison_message_tracker.report(group.as_ref());
+        Some(
+            RemotingCommand::create_response_command()
+                .set_body(serde_json::to_vec(&report).unwrap()),
+        )
+    }
+
+    /// Resumes queues paused for `consumerGroup` after a poison message was detected. Without
+    /// `topic` and `queueId` every paused queue of the group is resumed.
+    pub async fn resume_paused_queue(
+        &mut self,
+        _channel: Channel,
+        _ctx: ConnectionHandlerContext,
+        _request_code: RequestCode,
+        request: RemotingCommand,
+    ) -> Option<RemotingCommand> {
+        let Some(fields) = request.ext_fields() else {
+            return Some(RemotingCommand::create_response_command_with_code_remark(
+                ResponseCode::InvalidParameter,
+                "consumerGroup is required",
+            ));
+        };
+        let Some(group) = fields.get("consumerGroup") else {
+            return Some(RemotingCommand::create_response_command_with_code_remark(
+                ResponseCode::InvalidParameter,
+                "consumerGroup is required",
+            ));
+        };
+        let queue_id = match fields
+            .get("queueId")
+            .map(|queue_id| queue_id.parse::<i32>())
+        {
+            Some(Ok(queue_id)) => Some(queue_id),
+            Some(Err(_)) => {
+                return Some(RemotingCommand::create_response_command_with_code_remark(
+                    ResponseCode::InvalidParameter,
+                    "queueId must be a number",
+                ));
+            }
+            None => None,
+        };
+        let resumed =
+            self.inner
+                .poison_message_tracker
+                .resume(group, fields.get("topic"), queue_id);
+        Some(
+            RemotingCommand::create_response_command()
+                .set_remark(format!("{} paused queue(s) resumed", resumed)),
+        )
+    }
+
+    /// Creates a temporary consumer group on `topic`, positioned at `startTimestamp` or at the
+    /// given start offsets, that is dropped again once its ttl expires.
+    pub async fn create_replay_subscription(
+        &mut self,
+        channel: Channel,
+        _ctx: ConnectionHandlerContext,
+        _request_code: RequestCode,
+        request: RemotingCommand,
+    ) -> Option<RemotingCommand> {
+        let Some(body) = request
+            .get_body()
+            .and_then(|body| CreateReplaySubscriptionRequestBody::decode(body).ok())
+        else {
+            return Some(RemotingCommand::create_response_command_with_code_remark(
+                ResponseCode::InvalidParameter,
+                "invalid replay subscription request body",
+            ));
+        };
+        if body.ttl_millis <= 0
+            || body.ttl_millis > self.inner.broker_config.replay_subscription_max_ttl_millis
+        {
+            return Some(RemotingCommand::create_response_command_with_code_remark(
+                ResponseCode::InvalidParameter,
+                format!(
+                    "ttlMillis must be in (0, {}]",
+                    self.inner.broker_config.replay_subscription_max_ttl_millis
+                ),
+            ));
+        }
+        if body.start_timestamp.is_none() == body.start_offsets.is_none() {
+            return Some(RemotingCommand::create_response_command_with_code_remark(
+                ResponseCode::InvalidParameter,
+                "exactly one of startTimestamp and startOffsets is required",
+            ));
+        }
+        let Some(topic_config) = self
+            .inner
+            .topic_config_manager
+            .select_topic_config(&body.topic)
+        else {
+            return Some(RemotingCommand::create_response_command_with_code_remark(
+                ResponseCode::TopicNotExist,
+                format!("topic {} not exist", body.topic),
+            ));
+        };
+        let now = get_current_millis() as i64;
+        let group = body
+            .consumer_group
+            .clone()
+            .unwrap_or_else(|| ReplaySubscriptionManager::generate_group_name(&body.topic, now));
+        if self
+            .inner
+            .subscription_group_manager
+            .contains_subscription_group(&group)
+            || !self
+                .inner
+                .consumer_offset_manager
+                .which_topic_by_consumer(&group)
+                .is_empty()
+        {
+            return Some(RemotingCommand::create_response_command_with_code_remark(
+                ResponseCode::InvalidParameter,
+                format!(
+                    "consumer group {} already exists, replay needs a fresh group",
+                    group
+                ),
+            ));
+        }
+
+        let store = &self.inner.default_message_store;
+        let mut start_offsets = HashMap::new();
+        let mut end_offsets = HashMap::new();
+        for queue_id in 0..topic_config.get_read_queue_nums() as i32 {
+            let min_offset = store.get_min_offset_in_queue(&body.topic, queue_id);
+            let max_offset = store.get_max_offset_in_queue(&body.topic, queue_id);
+            let start_offset = match (body.start_timestamp, &body.start_offsets) {
+                (Some(timestamp), _) => {
+                    search_offset_by_timestamp(store.as_ref(), &body.topic, queue_id, timestamp)
+                }
+                (None, Some(offsets)) => offsets
+                    .get(&queue_id)
+                    .copied()
+                    .unwrap_or(max_offset)
+                    .clamp(min_offset, max_offset),
+                (None, None) => unreachable!(),
+            };
+            start_offsets.insert(queue_id, start_offset);
+            if let Some(end_offset) = body
+                .end_offsets
+                .as_ref()
+                .and_then(|offsets| offsets.get(&queue_id))
+            {
+                end_offsets.insert(queue_id, (*end_offset).max(start_offset));
+            }
+        }
+
+        let subscription = ReplaySubscription {
+            consumer_group: group.clone(),
+            topic: body.topic.clone(),
+            start_offsets,
+            end_offsets,
+            expire_at_millis: now + body.ttl_millis,
+        };
+        if !self
+            .inner
+            .replay_subscription_manager
+            .register(subscription.clone())
+        {
+            return Some(RemotingCommand::create_response_command_with_code_remark(
+                ResponseCode::InvalidParameter,
+                format!("replay subscription {} already exists", group),
+            ));
+        }
+        let mut group_config = SubscriptionGroupConfig::default();
+        group_config.set_group_name(group.clone());
+        group_config.set_retry_max_times(0);
+        group_config.set_notify_consumer_ids_changed_enable(false);
+        self.inner
+            .subscription_group_manager
+            .update_subscription_group_config(group_config);
+        for (queue_id, offset) in subscription.start_offsets.iter() {
+            self.inner.consumer_offset_manager.commit_offset(
+                channel.remote_address(),
+                &group,
+                &body.topic,
+                *queue_id,
+                *offset,
+            );
+        }
+        info!(
+            "create replay subscription {} on {}, expire at {}",
+            group, body.topic, subscription.expire_at_millis
+        );
+        Some(
+            RemotingCommand::create_response_command().set_body(
+                subscription
+                    .encode()
+                    .expect("encode ReplaySubscription failed"),
+            ),
+        )
+    }
+
+    /// Drops a replay subscription before its ttl expires.
+    pub async fn delete_replay_subscription(
+        &mut self,
+        _channel: Channel,
+        _ctx: ConnectionHandlerContext,
+        _request_code: RequestCode,
+        request: RemotingCommand,
+    ) -> Option<RemotingCommand> {
+        let Some(group) = request
+            .ext_fields()
+            .and_then(|fields| fields.get("consumerGroup"))
+            .cloned()
+        else {
+            return Some(RemotingCommand::create_response_command_with_code_remark(
+                ResponseCode::InvalidParameter,
+                "consumerGroup is required",
+            ));
+        };
+        match self.inner.replay_subscription_manager.remove(&group) {
+            Some(subscription) => {
+                release_replay_subscription(
+                    &subscription,
+                    &self.inner.consumer_offset_manager,
+                    &self.inner.subscription_group_manager,
+                );
+                Some(RemotingCommand::create_response_command())
+            }
+            None => Some(RemotingCommand::create_response_command_with_code_remark(
+                ResponseCode::SubscriptionGroupNotExist,
+                format!("replay subscription {} not exist", group),
+            )),
+        }
+    }
 }
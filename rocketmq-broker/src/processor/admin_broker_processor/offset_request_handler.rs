@@ -17,6 +17,10 @@
 use rocketmq_remoting::code::request_code::RequestCode;
 use rocketmq_remoting::code::response_code::ResponseCode;
 use rocketmq_remoting::net::channel::Channel;
+use rocketmq_remoting::protocol::body::examine_consume_queue_body::ConsumeQueueUnitData;
+use rocketmq_remoting::protocol::body::examine_consume_queue_body::ExamineConsumeQueueBody;
+use rocketmq_remoting::protocol::header::check_confirm_offset_response_header::CheckConfirmOffsetResponseHeader;
+use rocketmq_remoting::protocol::header::examine_consume_queue_request_header::ExamineConsumeQueueRequestHeader;
 use rocketmq_remoting::protocol::header::get_max_offset_request_header::GetMaxOffsetRequestHeader;
 use rocketmq_remoting::protocol::header::get_max_offset_response_header::GetMaxOffsetResponseHeader;
 use rocketmq_remoting::protocol::header::get_min_offset_request_header::GetMinOffsetRequestHeader;
@@ -32,6 +36,8 @@ use rocketmq_store::log_file::MessageStore;
 
 use crate::processor::admin_broker_processor::Inner;
 
+const MAX_EXAMINE_CONSUME_QUEUE_COUNT: i32 = 1000;
+
 #[derive(Clone)]
 pub(super) struct OffsetRequestHandler {
     inner: Inner,
@@ -110,6 +116,76 @@ impl OffsetRequestHandler {
             response_header,
         ))
     }
+
+    pub async fn check_confirm_offset(
+        &mut self,
+        _channel: Channel,
+        _ctx: ConnectionHandlerContext,
+        _request_code: RequestCode,
+        _request: RemotingCommand,
+    ) -> Option<RemotingCommand> {
+        let confirm_offset = self.inner.default_message_store.get_confirm_offset();
+        let response_header = CheckConfirmOffsetResponseHeader { confirm_offset };
+        Some(RemotingCommand::create_response_command_with_header(
+            response_header,
+        ))
+    }
+
+    /// Returns raw consume queue units, at most `MAX_EXAMINE_CONSUME_QUEUE_COUNT` per request.
+    pub async fn examine_consume_queue(
+        &mut self,
+        _channel: Channel,
+        _ctx: ConnectionHandlerContext,
+        _request_code: RequestCode,
+        request: RemotingCommand,
+    ) -> Option<RemotingCommand> {
+        let Ok(request_header) =
+            request.decode_command_custom_header::<ExamineConsumeQueueRequestHeader>()
+        else {
+            return Some(RemotingCommand::create_response_command_with_code_remark(
+                ResponseCode::InvalidParameter,
+                "decode ExamineConsumeQueueRequestHeader failed",
+            ));
+        };
+        let count = request_header
+            .count
+            .clamp(0, MAX_EXAMINE_CONSUME_QUEUE_COUNT) as usize;
+        let message_store = &self.inner.default_message_store;
+        let Some(units) = message_store.examine_consume_queue(
+            &request_header.topic,
+            request_header.queue_id,
+            request_header.index,
+            count,
+        ) else {
+            return Some(RemotingCommand::create_response_command_with_code_remark(
+                ResponseCode::QueryNotFound,
+                format!(
+                    "consume queue {}-{} not found",
+                    request_header.topic, request_header.queue_id
+                ),
+            ));
+        };
+        let body = ExamineConsumeQueueBody {
+            min_offset: message_store
+                .get_min_offset_in_queue(&request_header.topic, request_header.queue_id),
+            max_offset: message_store
+                .get_max_offset_in_queue(&request_header.topic, request_header.queue_id),
+            topic: request_header.topic,
+            queue_id: request_header.queue_id,
+            units: units
+                .into_iter()
+                .map(|unit| ConsumeQueueUnitData {
+                    queue_offset: unit.queue_offset,
+                    phy_offset: unit.pos,
+                    size: unit.size,
+                    tags_code: unit.tags_code,
+                })
+                .collect(),
+        };
+        Some(
+            RemotingCommand::create_response_command().set_body(serde_json::to_vec(&body).unwrap()),
+        )
+    }
     /*
     async fn handle_get_min_offset(
         &mut self,
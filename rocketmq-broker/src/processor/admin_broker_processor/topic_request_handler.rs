@@ -47,6 +47,7 @@ use rocketmq_remoting::protocol::static_topic::topic_queue_mapping_detail::Topic
 use rocketmq_remoting::protocol::RemotingDeserializable;
 use rocketmq_remoting::protocol::RemotingSerializable;
 use rocketmq_remoting::runtime::connection_handler_context::ConnectionHandlerContext;
+use rocketmq_store::config::topic_flush_policy::TopicFlushPolicy;
 use rocketmq_store::log_file::MessageStore;
 use tracing::info;
 
@@ -144,6 +145,9 @@ impl TopicRequestHandler {
                     .set_remark("MIXED message type is not supported."),
             );
         }
+        if let Err(err) = TopicFlushPolicy::parse(&topic_config.attributes) {
+            return Some(response.set_code(ResponseCode::SystemError).set_remark(err));
+        }
 
         let topic_config_origin = self
             .inner
@@ -243,6 +247,9 @@ impl TopicRequestHandler {
                         .set_remark("MIXED message type is not supported.".to_string()),
                 );
             }
+            if let Err(err) = TopicFlushPolicy::parse(&topic_config.attributes) {
+                return Some(response.set_code(ResponseCode::SystemError).set_remark(err));
+            }
             let topic_config_origin = self
                 .inner
                 .topic_config_manager
@@ -536,6 +543,81 @@ impl TopicRequestHandler {
         Some(response)
     }
 
+    /// Part of static topic remapping: fences writes of a physical queue at the agreed logic
+    /// offset, a negative or missing `fenceOffset` lifts the fence. Responds with the current
+    /// fences of the topic.
+    pub async fn update_queue_write_fence(
+        &mut self,
+        _channel: Channel,
+        _ctx: ConnectionHandlerContext,
+        _request_code: RequestCode,
+        request: RemotingCommand,
+    ) -> Option<RemotingCommand> {
+        let response = RemotingCommand::create_response_command();
+        let fields = request.ext_fields();
+        let topic = fields.and_then(|fields| fields.get("topic")).cloned();
+        let queue_id = fields
+            .and_then(|fields| fields.get("queueId"))
+            .and_then(|queue_id| queue_id.parse::<i32>().ok());
+        let (topic, queue_id) = match (topic, queue_id) {
+            (Some(topic), Some(queue_id)) if !topic.is_empty() && queue_id >= 0 => {
+                (topic, queue_id)
+            }
+            _ => {
+                return Some(
+                    response
+                        .set_code(ResponseCode::InvalidParameter)
+                        .set_remark("topic and queueId are required"),
+                );
+            }
+        };
+        let fence_offset = match fields.and_then(|fields| fields.get("fenceOffset")) {
+            Some(offset) => match offset.parse::<i64>() {
+                Ok(offset) => offset,
+                Err(_) => {
+                    return Some(
+                        response
+                            .set_code(ResponseCode::InvalidParameter)
+                            .set_remark(format!("invalid fenceOffset {}", offset)),
+                    );
+                }
+            },
+            None => -1,
+        };
+        let mapping_manager = &self.inner.topic_queue_mapping_manager;
+        if fence_offset >= 0 {
+            if mapping_manager
+                .get_topic_queue_mapping(topic.as_str())
+                .is_none()
+            {
+                return Some(
+                    response
+                        .set_code(ResponseCode::TopicNotExist)
+                        .set_remark(format!("{} is not a static topic of this broker", topic)),
+                );
+            }
+            let max_offset = self
+                .inner
+                .default_message_store
+                .get_max_offset_in_queue(&topic, queue_id);
+            if max_offset > fence_offset {
+                return Some(
+                    response
+                        .set_code(ResponseCode::SystemError)
+                        .set_remark(format!(
+                            "{}-{} max offset {} is already past fence offset {}",
+                            topic, queue_id, max_offset, fence_offset
+                        )),
+                );
+            }
+            mapping_manager.fence_queue_write(&topic, queue_id, fence_offset);
+        } else {
+            mapping_manager.unfence_queue_write(&topic, queue_id);
+        }
+        let fences = mapping_manager.queue_write_fences(topic.as_str());
+        Some(response.set_body(serde_json::to_vec(&fences).unwrap()))
+    }
+
     pub async fn query_topic_consume_by_who(
         &mut self,
         _channel: Channel,
@@ -30,6 +30,7 @@ use rocketmq_remoting::code::request_code::RequestCode;
 use rocketmq_remoting::net::channel::Channel;
 use rocketmq_remoting::protocol::header::unregister_client_request_header::UnregisterClientRequestHeader;
 use rocketmq_remoting::protocol::heartbeat::consume_type::ConsumeType;
+use rocketmq_remoting::protocol::heartbeat::consumer_data::ConsumerData;
 use rocketmq_remoting::protocol::heartbeat::heartbeat_data::HeartbeatData;
 use rocketmq_remoting::protocol::remoting_command::RemotingCommand;
 use rocketmq_remoting::runtime::connection_handler_context::ConnectionHandlerContext;
@@ -160,25 +161,54 @@ where
                 consumer_data.group_name.clone(),
                 heartbeat_data.heartbeat_fingerprint,
             );
-            let mut has_order_topic_sub = false;
-            for subscription_data in consumer_data.subscription_data_set.iter() {
-                if self
-                    .topic_config_manager
-                    .is_order_topic(subscription_data.topic.as_str())
-                {
-                    has_order_topic_sub = true;
-                    break;
-                }
-            }
-            let subscription_group_config = self
-                .subscription_group_manager
-                .find_subscription_group_config(consumer_data.group_name.as_ref());
-            if subscription_group_config.is_none() {
-                continue;
-            }
-            let subscription_group_config = subscription_group_config.unwrap();
-            let is_notify_consumer_ids_changed_enable =
-                subscription_group_config.notify_consumer_ids_changed_enable();
+            self.register_consumer_data(&channel, consumer_data, &client_channel_info, false);
+        }
+        //do producer data handle
+        for producer_data in heartbeat_data.producer_data_set.iter() {
+            self.producer_manager
+                .register_producer(&producer_data.group_name, &client_channel_info);
+        }
+        let mut response_command = RemotingCommand::create_response_command();
+        response_command.add_ext_field(IS_SUPPORT_HEART_BEAT_V2.to_string(), true.to_string());
+        response_command.add_ext_field(IS_SUB_CHANGE.to_string(), true.to_string());
+        Some(response_command)
+    }
+
+    /// Registers one consumer of a heartbeat. With `without_sub` only the channel is refreshed
+    /// and the subscriptions of the group's last full heartbeat are kept.
+    fn register_consumer_data(
+        &mut self,
+        channel: &Channel,
+        consumer_data: &ConsumerData,
+        client_channel_info: &ClientChannelInfo,
+        without_sub: bool,
+    ) {
+        let subscription_group_config = self
+            .subscription_group_manager
+            .find_subscription_group_config(consumer_data.group_name.as_ref());
+        let Some(subscription_group_config) = subscription_group_config else {
+            return;
+        };
+        let is_notify_consumer_ids_changed_enable =
+            subscription_group_config.notify_consumer_ids_changed_enable();
+        let changed = if without_sub {
+            self.consumer_manager.register_consumer_without_sub(
+                consumer_data.group_name.as_ref(),
+                client_channel_info.clone(),
+                consumer_data.consume_type,
+                consumer_data.message_model,
+                consumer_data.consume_from_where,
+                is_notify_consumer_ids_changed_enable,
+            )
+        } else {
+            let has_order_topic_sub =
+                consumer_data
+                    .subscription_data_set
+                    .iter()
+                    .any(|subscription_data| {
+                        self.topic_config_manager
+                            .is_order_topic(subscription_data.topic.as_str())
+                    });
             let topic_sys_flag = if consumer_data.unit_mode {
                 topic_sys_flag::build_sys_flag(false, true)
             } else {
@@ -195,7 +225,7 @@ where
                     has_order_topic_sub,
                     topic_sys_flag,
                 );
-            let changed = self.consumer_manager.register_consumer(
+            self.consumer_manager.register_consumer(
                 consumer_data.group_name.as_ref(),
                 client_channel_info.clone(),
                 consumer_data.consume_type,
@@ -203,36 +233,59 @@ where
                 consumer_data.consume_from_where,
                 consumer_data.subscription_data_set.clone(),
                 is_notify_consumer_ids_changed_enable,
-            );
-            if changed {
-                info!(
-                    "ClientManageProcessor: registerConsumer info changed, SDK address={}, \
-                     consumerData={:?}",
-                    channel.remote_address(),
-                    consumer_data
-                )
-            }
-        }
-        //do producer data handle
-        for producer_data in heartbeat_data.producer_data_set.iter() {
-            self.producer_manager
-                .register_producer(&producer_data.group_name, &client_channel_info);
+            )
+        };
+        if changed {
+            info!(
+                "ClientManageProcessor: registerConsumer info changed, SDK address={}, \
+                 consumerData={:?}",
+                channel.remote_address(),
+                consumer_data
+            )
         }
-        let mut response_command = RemotingCommand::create_response_command();
-        response_command.add_ext_field(IS_SUPPORT_HEART_BEAT_V2.to_string(), true.to_string());
-        response_command.add_ext_field(IS_SUB_CHANGE.to_string(), true.to_string());
-        Some(response_command)
     }
 
     fn heart_beat_v2(
-        &self,
-        _channel: &Channel,
+        &mut self,
+        channel: &Channel,
         _ctx: &ConnectionHandlerContext,
         heartbeat_data: HeartbeatData,
         client_channel_info: ClientChannelInfo,
     ) -> Option<RemotingCommand> {
-        let is_sub_change = false;
+        let mut is_sub_change = false;
         //handle consumer data
+        for consumer_data in heartbeat_data.consumer_data_set.iter() {
+            if self.broker_config.reject_pull_consumer_enable
+                && ConsumeType::ConsumeActively == consumer_data.consume_type
+            {
+                continue;
+            }
+            let last_fingerprint = self
+                .consumer_group_heartbeat_table
+                .read()
+                .get(&consumer_data.group_name)
+                .copied();
+            if last_fingerprint != Some(heartbeat_data.heartbeat_fingerprint) {
+                // the subscriptions known here are stale or missing, so a heartbeat without
+                // subscriptions cannot be trusted and the client has to send them in full
+                is_sub_change = true;
+                if heartbeat_data.is_without_sub {
+                    continue;
+                }
+            }
+            self.register_consumer_data(
+                channel,
+                consumer_data,
+                &client_channel_info,
+                heartbeat_data.is_without_sub,
+            );
+            if !heartbeat_data.is_without_sub {
+                self.consumer_group_heartbeat_table.write().insert(
+                    consumer_data.group_name.clone(),
+                    heartbeat_data.heartbeat_fingerprint,
+                );
+            }
+        }
 
         //handle producer data
         for producer_data in heartbeat_data.producer_data_set.iter() {
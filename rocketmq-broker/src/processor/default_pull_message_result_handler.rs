@@ -15,6 +15,7 @@
  * limitations under the License.
  */
 use std::any::Any;
+use std::collections::HashSet;
 use std::net::SocketAddr;
 use std::sync::Arc;
 
@@ -22,12 +23,15 @@ use bytes::Bytes;
 use bytes::BytesMut;
 use cheetah_string::CheetahString;
 use rocketmq_common::common::broker::broker_config::BrokerConfig;
+use rocketmq_common::common::message::message_decoder;
 use rocketmq_common::common::message::message_queue::MessageQueue;
+use rocketmq_common::common::message::STRING_HASH_SET;
 use rocketmq_common::common::mix_all::MASTER_ID;
 use rocketmq_common::common::sys_flag::pull_sys_flag::PullSysFlag;
 use rocketmq_common::TimeUtils::get_current_millis;
 use rocketmq_remoting::code::response_code::RemotingSysResponseCode;
 use rocketmq_remoting::code::response_code::ResponseCode;
+use rocketmq_remoting::net::body_transfer::BodyTransfer;
 use rocketmq_remoting::net::channel::Channel;
 use rocketmq_remoting::protocol::header::pull_message_request_header::PullMessageRequestHeader;
 use rocketmq_remoting::protocol::header::pull_message_response_header::PullMessageResponseHeader;
@@ -62,6 +66,7 @@ use crate::offset::manager::consumer_offset_manager::ConsumerOffsetManager;
 use crate::processor::pull_message_processor::is_broadcast;
 use crate::processor::pull_message_processor::rewrite_response_for_static_topic;
 use crate::processor::pull_message_result_handler::PullMessageResultHandler;
+use crate::processor::response_body_compressor::ResponseBodyCompressor;
 use crate::topic::manager::topic_config_manager::TopicConfigManager;
 
 pub struct DefaultPullMessageResultHandler {
@@ -74,6 +79,7 @@ pub struct DefaultPullMessageResultHandler {
     broker_config: Arc<BrokerConfig>,
     consume_message_hook_list: Arc<Vec<Box<dyn ConsumeMessageHook>>>,
     pull_request_hold_service: Option<ArcMut<PullRequestHoldService<DefaultMessageStore>>>,
+    body_compressor: ResponseBodyCompressor,
 }
 
 impl DefaultPullMessageResultHandler {
@@ -88,6 +94,7 @@ impl DefaultPullMessageResultHandler {
         consume_message_hook_list: Arc<Vec<Box<dyn ConsumeMessageHook>>>,
     ) -> Self {
         Self {
+            body_compressor: ResponseBodyCompressor::new(&broker_config),
             topic_config_manager,
             message_store_config,
             consumer_offset_manager,
@@ -194,19 +201,46 @@ impl PullMessageResultHandler for DefaultPullMessageResultHandler {
 
                 ctx.upgrade()?;
 
-                if self.broker_config.transfer_msg_by_heap {
+                let accept_compressed =
+                    PullSysFlag::has_accept_compressed_body_flag(request_header.sys_flag as u32);
+                // projection and compression rewrite the messages, those bodies are built on heap
+                let transfer_in_place = !self.broker_config.transfer_msg_by_heap
+                    && request_header.projected_properties.is_none()
+                    && !(accept_compressed
+                        && self
+                            .body_compressor
+                            .may_compress(get_message_result.buffer_total_size() as usize));
+                if transfer_in_place {
+                    response.set_body_transfer_mut_ref(Self::transfer_get_message_result(
+                        &get_message_result,
+                    ));
+                    Some(response)
+                } else {
                     let body = self.read_get_message_result(
                         &get_message_result,
                         request_header.consumer_group.as_str(),
                         request_header.topic.as_str(),
                         request_header.queue_id,
+                        request_header.projected_properties.as_deref(),
                     );
                     if let Some(body) = body {
-                        response.set_body_mut_ref(body);
+                        let compressed = if accept_compressed {
+                            self.body_compressor.compress(&body)
+                        } else {
+                            None
+                        };
+                        match compressed {
+                            Some((compressed, compression_type)) => {
+                                response
+                                    .read_custom_header_mut::<PullMessageResponseHeader>()
+                                    .unwrap()
+                                    .body_compression_type = Some(compression_type);
+                                response.set_body_mut_ref(compressed);
+                            }
+                            None => response.set_body_mut_ref(body),
+                        }
                     }
                     Some(response)
-                } else {
-                    None
                 }
             }
             ResponseCode::PullNotFound => {
@@ -303,19 +337,46 @@ impl PullMessageResultHandler for DefaultPullMessageResultHandler {
 }
 
 impl DefaultPullMessageResultHandler {
+    /// Hands the selected messages to the connection without copying them out of the mapped
+    /// files, each one keeps its file mapped until it has been written.
+    fn transfer_get_message_result(get_message_result: &GetMessageResult) -> BodyTransfer {
+        let mut body_transfer = BodyTransfer::new();
+        for msg in get_message_result.message_mapped_list() {
+            if let Some(bytes) = msg.transfer_bytes() {
+                body_transfer.push(bytes);
+            }
+        }
+        body_transfer
+    }
+
     fn read_get_message_result(
         &self,
         get_message_result: &GetMessageResult,
         _group: &str,
         _topic: &str,
         _queue_id: i32,
+        projected_properties: Option<&str>,
     ) -> Option<Bytes> {
+        let projected_properties = projected_properties.map(|names| {
+            names
+                .split(',')
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+                .collect::<HashSet<_>>()
+        });
         let mut bytes_mut =
             BytesMut::with_capacity(get_message_result.buffer_total_size() as usize);
         for msg in get_message_result.message_mapped_list() {
-            let data = &msg.mapped_file.as_ref().unwrap().get_mapped_file()
-                [msg.start_offset as usize..(msg.start_offset + msg.size as u64) as usize];
-            bytes_mut.extend_from_slice(data);
+            let data = msg.get_buffer();
+            let projected = projected_properties.as_ref().and_then(|names| {
+                message_decoder::project_properties(data, |name| {
+                    names.contains(name) || STRING_HASH_SET.contains(name)
+                })
+            });
+            match projected {
+                Some(projected) => bytes_mut.extend_from_slice(&projected),
+                None => bytes_mut.extend_from_slice(data),
+            }
         }
         Some(bytes_mut.freeze())
     }
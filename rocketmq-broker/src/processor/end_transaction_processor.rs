@@ -25,14 +25,19 @@ use rocketmq_common::common::message::MessageConst;
 use rocketmq_common::common::message::MessageTrait;
 use rocketmq_common::common::sys_flag::message_sys_flag::MessageSysFlag;
 use rocketmq_common::common::TopicFilterType;
+use rocketmq_common::utils::serde_json_utils::SerdeJsonUtils;
 use rocketmq_common::utils::string_utils::StringUtils;
 use rocketmq_common::MessageAccessor::MessageAccessor;
 use rocketmq_common::TimeUtils::get_current_millis;
 use rocketmq_remoting::code::request_code::RequestCode;
 use rocketmq_remoting::code::response_code::ResponseCode;
 use rocketmq_remoting::net::channel::Channel;
+use rocketmq_remoting::protocol::body::end_transaction_batch_body::EndTransactionBatchBody;
+use rocketmq_remoting::protocol::header::end_transaction_batch_request_header::EndTransactionBatchRequestHeader;
 use rocketmq_remoting::protocol::header::end_transaction_request_header::EndTransactionRequestHeader;
+use rocketmq_remoting::protocol::header::resolve_orphaned_transaction_request_header::ResolveOrphanedTransactionRequestHeader;
 use rocketmq_remoting::protocol::remoting_command::RemotingCommand;
+use rocketmq_remoting::protocol::RemotingSerializable;
 use rocketmq_remoting::runtime::connection_handler_context::ConnectionHandlerContext;
 use rocketmq_rust::ArcMut;
 use rocketmq_store::base::message_status_enum::PutMessageStatus;
@@ -41,16 +46,17 @@ use rocketmq_store::config::message_store_config::MessageStoreConfig;
 use rocketmq_store::log_file::MessageStore;
 use tracing::warn;
 
+use crate::acl::admin_access_validator::AdminAccessValidator;
 use crate::transaction::operation_result::OperationResult;
 use crate::transaction::queue::transactional_message_util::TransactionalMessageUtil;
 use crate::transaction::transactional_message_service::TransactionalMessageService;
 
-#[derive(Default)]
 pub struct EndTransactionProcessor<TM, MS> {
     message_store_config: Arc<MessageStoreConfig>,
     broker_config: Arc<BrokerConfig>,
     transactional_message_service: ArcMut<TM>,
     message_store: ArcMut<MS>,
+    admin_access_validator: Arc<AdminAccessValidator>,
 }
 
 impl<TM, MS> EndTransactionProcessor<TM, MS> {
@@ -59,12 +65,14 @@ impl<TM, MS> EndTransactionProcessor<TM, MS> {
         broker_config: Arc<BrokerConfig>,
         transactional_message_service: ArcMut<TM>,
         message_store: ArcMut<MS>,
+        admin_access_validator: Arc<AdminAccessValidator>,
     ) -> Self {
         Self {
             message_store_config,
             broker_config,
             transactional_message_service,
             message_store,
+            admin_access_validator,
         }
     }
 }
@@ -131,22 +139,12 @@ where
                 }
                 let res =
                     self.check_prepare_message(result.prepare_message.as_ref(), &request_header);
-                if ResponseCode::from(res.code()) != ResponseCode::Success {
-                    let mut msg_inner =
-                        end_message_transaction(result.prepare_message.as_ref().unwrap());
-                    msg_inner.message_ext_inner.sys_flag = MessageSysFlag::reset_transaction_value(
-                        msg_inner.message_ext_inner.sys_flag,
+                if ResponseCode::from(res.code()) == ResponseCode::Success {
+                    let msg_inner = final_message(
+                        result.prepare_message.as_ref().unwrap(),
                         request_header.commit_or_rollback,
-                    );
-                    msg_inner.message_ext_inner.queue_offset =
-                        request_header.tran_state_table_offset as i64;
-                    msg_inner.message_ext_inner.prepared_transaction_offset =
-                        request_header.commit_log_offset as i64;
-                    msg_inner.message_ext_inner.store_timestamp =
-                        result.prepare_message.as_ref().unwrap().store_timestamp;
-                    MessageAccessor::clear_property(
-                        &mut msg_inner,
-                        MessageConst::PROPERTY_TRANSACTION_PREPARED,
+                        request_header.tran_state_table_offset,
+                        request_header.commit_log_offset,
                     );
                     let send_result = self.send_final_message(msg_inner).await;
                     if ResponseCode::from(send_result.code()) == ResponseCode::Success {
@@ -200,6 +198,268 @@ where
         )
     }
 
+    /// Ends all half messages of a transactional batch. Every half message is looked up and
+    /// checked before any of them is touched, so a batch with a missing or foreign half
+    /// message is rejected as a whole and left to the transaction check.
+    ///
+    /// On commit the messages are restored one by one and each half message is deleted right
+    /// after its restore. If a restore fails the remaining half messages stay prepared, a retry
+    /// or the transaction check then commits them without duplicating the restored ones.
+    pub async fn process_batch_request(
+        &mut self,
+        _channel: Channel,
+        _ctx: ConnectionHandlerContext,
+        _request_code: RequestCode,
+        request: RemotingCommand,
+    ) -> Option<RemotingCommand> {
+        let Ok(request_header) =
+            request.decode_command_custom_header::<EndTransactionBatchRequestHeader>()
+        else {
+            return Some(
+                RemotingCommand::create_response_command_with_code(ResponseCode::SystemError)
+                    .set_remark("decode EndTransactionBatchRequestHeader failed"),
+            );
+        };
+        if BrokerRole::Slave == self.message_store_config.broker_role {
+            warn!("Message store is slave mode, so end transaction is forbidden. ");
+            return Some(RemotingCommand::create_response_command_with_code(
+                ResponseCode::SlaveNotAvailable,
+            ));
+        }
+        let commit = match request_header.commit_or_rollback {
+            MessageSysFlag::TRANSACTION_COMMIT_TYPE => true,
+            MessageSysFlag::TRANSACTION_ROLLBACK_TYPE => false,
+            _ => return None,
+        };
+        let body = request
+            .body()
+            .as_ref()
+            .and_then(|body| SerdeJsonUtils::decode::<EndTransactionBatchBody>(body.as_ref()).ok());
+        let Some(body) = body.filter(|body| !body.entries.is_empty()) else {
+            return Some(
+                RemotingCommand::create_response_command_with_code(ResponseCode::SystemError)
+                    .set_remark("transaction batch body is empty"),
+            );
+        };
+
+        let mut staged = Vec::with_capacity(body.entries.len());
+        for entry in &body.entries {
+            let entry_header = EndTransactionRequestHeader {
+                topic: entry.topic.clone(),
+                producer_group: request_header.producer_group.clone(),
+                tran_state_table_offset: entry.tran_state_table_offset,
+                commit_log_offset: entry.commit_log_offset,
+                commit_or_rollback: request_header.commit_or_rollback,
+                from_transaction_check: request_header.from_transaction_check,
+                msg_id: entry.msg_id.clone(),
+                transaction_id: Some(request_header.transaction_id.clone()),
+                ..Default::default()
+            };
+            let result = if commit {
+                self.transactional_message_service
+                    .commit_message(&entry_header)
+            } else {
+                self.transactional_message_service
+                    .rollback_message(&entry_header)
+            };
+            let Some(prepare_message) = result
+                .prepare_message
+                .filter(|_| result.response_code == ResponseCode::Success)
+            else {
+                return Some(
+                    RemotingCommand::create_response_command_with_code(ResponseCode::SystemError)
+                        .set_remark(format!(
+                            "Find prepared transaction message failed, msgId={}",
+                            entry.msg_id
+                        )),
+                );
+            };
+            if let Some(remark) =
+                check_batch_member(&prepare_message, &request_header, body.entries.len())
+            {
+                return Some(
+                    RemotingCommand::create_response_command_with_code(ResponseCode::SystemError)
+                        .set_remark(remark),
+                );
+            }
+            if self
+                .reject_commit_or_rollback(request_header.from_transaction_check, &prepare_message)
+            {
+                warn!(
+                    "Transaction batch end fail [producer end]. currentTimeMillis - bornTime >                      checkImmunityTime, batchId={}, wait check",
+                    request_header.transaction_id
+                );
+                return Some(RemotingCommand::create_response_command_with_code(
+                    ResponseCode::IllegalOperation,
+                ));
+            }
+            let res = self.check_prepare_message(Some(&prepare_message), &entry_header);
+            if ResponseCode::from(res.code()) != ResponseCode::Success {
+                return Some(res);
+            }
+            staged.push((entry_header, prepare_message));
+        }
+
+        let total = staged.len();
+        for (restored, (entry_header, prepare_message)) in staged.iter().enumerate() {
+            if commit {
+                let msg_inner = final_message(
+                    prepare_message,
+                    entry_header.commit_or_rollback,
+                    entry_header.tran_state_table_offset,
+                    entry_header.commit_log_offset,
+                );
+                let send_result = self.send_final_message(msg_inner).await;
+                if ResponseCode::from(send_result.code()) != ResponseCode::Success {
+                    warn!(
+                        "Transaction batch {} commit stopped after {} of {} messages: {:?}",
+                        request_header.transaction_id,
+                        restored,
+                        total,
+                        send_result.remark()
+                    );
+                    return Some(send_result);
+                }
+            }
+            let _ = self
+                .transactional_message_service
+                .delete_prepare_message(prepare_message)
+                .await;
+        }
+        Some(RemotingCommand::create_response_command())
+    }
+
+    /// Lists the half messages the transaction check gave up on and that were neither committed
+    /// nor rolled back since.
+    pub fn get_orphaned_transactions(
+        &self,
+        _request_code: RequestCode,
+        _request: RemotingCommand,
+    ) -> Option<RemotingCommand> {
+        let body = self
+            .transactional_message_service
+            .get_transaction_metrics()
+            .orphaned_transactions();
+        Some(
+            RemotingCommand::create_response_command()
+                .set_body(body.encode().expect("orphaned transactions encode error")),
+        )
+    }
+
+    /// Commits or rolls back an orphaned transaction on behalf of its producer, which no longer
+    /// answers the transaction check. The half message is looked up by commit log offset, or by
+    /// transaction id when no offset is given.
+    pub async fn resolve_orphaned_transaction(
+        &mut self,
+        channel: Channel,
+        request_code: RequestCode,
+        request: RemotingCommand,
+    ) -> Option<RemotingCommand> {
+        if let Some(response) =
+            self.admin_access_validator
+                .check(request_code, &request, channel.remote_address().ip())
+        {
+            return Some(response);
+        }
+        let Ok(request_header) =
+            request.decode_command_custom_header::<ResolveOrphanedTransactionRequestHeader>()
+        else {
+            return Some(
+                RemotingCommand::create_response_command_with_code(ResponseCode::SystemError)
+                    .set_remark("decode ResolveOrphanedTransactionRequestHeader failed"),
+            );
+        };
+        if BrokerRole::Slave == self.message_store_config.broker_role {
+            warn!("Message store is slave mode, so end transaction is forbidden. ");
+            return Some(RemotingCommand::create_response_command_with_code(
+                ResponseCode::SlaveNotAvailable,
+            ));
+        }
+        let commit = match request_header.commit_or_rollback {
+            MessageSysFlag::TRANSACTION_COMMIT_TYPE => true,
+            MessageSysFlag::TRANSACTION_ROLLBACK_TYPE => false,
+            other => {
+                return Some(
+                    RemotingCommand::create_response_command_with_code(
+                        ResponseCode::IllegalOperation,
+                    )
+                    .set_remark(format!("illegal commitOrRollback {}", other)),
+                );
+            }
+        };
+        let Some(orphaned) = self
+            .transactional_message_service
+            .get_transaction_metrics()
+            .find_orphaned(
+                request_header.commit_log_offset,
+                request_header.transaction_id.as_ref(),
+            )
+        else {
+            return Some(
+                RemotingCommand::create_response_command_with_code(ResponseCode::SystemError)
+                    .set_remark("no such orphaned transaction"),
+            );
+        };
+
+        let end_header = EndTransactionRequestHeader {
+            topic: orphaned.topic.clone(),
+            producer_group: orphaned.producer_group.clone(),
+            tran_state_table_offset: orphaned.tran_state_table_offset as u64,
+            commit_log_offset: orphaned.commit_log_offset as u64,
+            commit_or_rollback: request_header.commit_or_rollback,
+            from_transaction_check: true,
+            msg_id: orphaned.msg_id.clone(),
+            transaction_id: Some(orphaned.transaction_id.clone()),
+            ..Default::default()
+        };
+        let result = if commit {
+            self.transactional_message_service
+                .commit_message(&end_header)
+        } else {
+            self.transactional_message_service
+                .rollback_message(&end_header)
+        };
+        let Some(prepare_message) = result
+            .prepare_message
+            .filter(|_| result.response_code == ResponseCode::Success)
+        else {
+            return Some(
+                RemotingCommand::create_response_command_with_code(ResponseCode::SystemError)
+                    .set_remark(format!(
+                        "Find prepared transaction message failed, commitLogOffset={}",
+                        orphaned.commit_log_offset
+                    )),
+            );
+        };
+        let res = self.check_prepare_message(Some(&prepare_message), &end_header);
+        if ResponseCode::from(res.code()) != ResponseCode::Success {
+            return Some(res);
+        }
+        if commit {
+            let msg_inner = final_message(
+                &prepare_message,
+                end_header.commit_or_rollback,
+                end_header.tran_state_table_offset,
+                end_header.commit_log_offset,
+            );
+            let send_result = self.send_final_message(msg_inner).await;
+            if ResponseCode::from(send_result.code()) != ResponseCode::Success {
+                return Some(send_result);
+            }
+        }
+        let _ = self
+            .transactional_message_service
+            .delete_prepare_message(&prepare_message)
+            .await;
+        warn!(
+            "Orphaned transaction {} manually {}, commitLogOffset={}",
+            orphaned.transaction_id,
+            if commit { "committed" } else { "rolled back" },
+            orphaned.commit_log_offset
+        );
+        Some(RemotingCommand::create_response_command())
+    }
+
     pub fn reject_commit_or_rollback(
         &self,
         from_transaction_check: bool,
@@ -337,6 +597,54 @@ where
     }
 }
 
+/// Checks a half message belongs to the batch being ended, returns why it does not.
+fn check_batch_member(
+    prepare_message: &MessageExt,
+    request_header: &EndTransactionBatchRequestHeader,
+    entries: usize,
+) -> Option<String> {
+    let batch_id = prepare_message.get_property(&CheetahString::from_static_str(
+        MessageConst::PROPERTY_TRANSACTION_BATCH_ID,
+    ));
+    if batch_id.as_ref() != Some(&request_header.transaction_id) {
+        return Some(format!(
+            "The message {} is not part of transaction batch {}",
+            prepare_message.msg_id, request_header.transaction_id
+        ));
+    }
+    let batch_size = prepare_message
+        .get_property(&CheetahString::from_static_str(
+            MessageConst::PROPERTY_TRANSACTION_BATCH_SIZE,
+        ))
+        .and_then(|size| size.parse::<usize>().ok());
+    if batch_size != Some(entries) {
+        return Some(format!(
+            "Transaction batch {} holds {:?} messages, {} were ended",
+            request_header.transaction_id, batch_size, entries
+        ));
+    }
+    None
+}
+
+/// Message restored to the real topic when a half message is committed.
+fn final_message(
+    prepare_message: &MessageExt,
+    commit_or_rollback: i32,
+    tran_state_table_offset: u64,
+    commit_log_offset: u64,
+) -> MessageExtBrokerInner {
+    let mut msg_inner = end_message_transaction(prepare_message);
+    msg_inner.message_ext_inner.sys_flag = MessageSysFlag::reset_transaction_value(
+        msg_inner.message_ext_inner.sys_flag,
+        commit_or_rollback,
+    );
+    msg_inner.message_ext_inner.queue_offset = tran_state_table_offset as i64;
+    msg_inner.message_ext_inner.prepared_transaction_offset = commit_log_offset as i64;
+    msg_inner.message_ext_inner.store_timestamp = prepare_message.store_timestamp;
+    MessageAccessor::clear_property(&mut msg_inner, MessageConst::PROPERTY_TRANSACTION_PREPARED);
+    msg_inner
+}
+
 fn end_message_transaction(msg_ext: &MessageExt) -> MessageExtBrokerInner {
     let mut msg_inner = MessageExtBrokerInner::default();
     msg_inner.set_topic(
@@ -475,4 +783,27 @@ mod tests {
         assert!(msg_inner.get_topic().is_empty());
         assert_eq!(msg_inner.message_ext_inner.queue_id, 0);
     }
+
+    #[test]
+    fn check_batch_member_requires_same_batch_and_size() {
+        let mut msg_ext = MessageExt::default();
+        let header = EndTransactionBatchRequestHeader {
+            transaction_id: CheetahString::from_static_str("batch-1"),
+            ..Default::default()
+        };
+        assert!(check_batch_member(&msg_ext, &header, 2).is_some());
+
+        MessageAccessor::put_property(
+            &mut msg_ext,
+            CheetahString::from_static_str(MessageConst::PROPERTY_TRANSACTION_BATCH_ID),
+            CheetahString::from_static_str("batch-1"),
+        );
+        MessageAccessor::put_property(
+            &mut msg_ext,
+            CheetahString::from_static_str(MessageConst::PROPERTY_TRANSACTION_BATCH_SIZE),
+            CheetahString::from_static_str("2"),
+        );
+        assert!(check_batch_member(&msg_ext, &header, 2).is_none());
+        assert!(check_batch_member(&msg_ext, &header, 1).is_some());
+    }
 }
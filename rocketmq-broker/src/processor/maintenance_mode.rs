@@ -0,0 +1,220 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::net::IpAddr;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicI64;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+
+use parking_lot::RwLock;
+use rocketmq_common::common::broker::broker_config::BrokerConfig;
+use rocketmq_common::TimeUtils::get_current_millis;
+use rocketmq_remoting::code::request_code::RequestCode;
+use rocketmq_remoting::code::response_code::ResponseCode;
+use rocketmq_remoting::protocol::remoting_command::RemotingCommand;
+use serde::Deserialize;
+use serde::Serialize;
+use tracing::info;
+use tracing::warn;
+
+/// Snapshot of the maintenance state returned by `UPDATE_MAINTENANCE_MODE`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MaintenanceModeStatus {
+    pub enabled: bool,
+    pub reason: String,
+    pub since_timestamp: i64,
+    pub white_list: Vec<String>,
+    pub rejected_requests: u64,
+}
+
+/// Restricts the broker to requests coming from the admin white list while recovery, rebuild
+/// or verification work is running. Everyone else gets `BROKER_IN_MAINTENANCE` and is expected
+/// to retry later or against another broker.
+pub(crate) struct MaintenanceMode {
+    enabled: AtomicBool,
+    since_timestamp: AtomicI64,
+    reason: RwLock<String>,
+    white_list: RwLock<HashSet<IpAddr>>,
+    rejected_requests: AtomicU64,
+}
+
+impl MaintenanceMode {
+    pub fn new(broker_config: &BrokerConfig) -> Self {
+        MaintenanceMode {
+            enabled: AtomicBool::new(false),
+            since_timestamp: AtomicI64::new(0),
+            reason: RwLock::new(String::new()),
+            white_list: RwLock::new(parse_white_list(
+                broker_config.maintenance_admin_white_list.as_str(),
+            )),
+            rejected_requests: AtomicU64::new(0),
+        }
+    }
+
+    pub fn enter(&self, reason: &str) {
+        *self.reason.write() = reason.to_string();
+        self.since_timestamp
+            .store(get_current_millis() as i64, Ordering::Release);
+        self.rejected_requests.store(0, Ordering::Relaxed);
+        self.enabled.store(true, Ordering::Release);
+        info!("broker entered maintenance mode, reason: {}", reason);
+    }
+
+    pub fn exit(&self) {
+        if self.enabled.swap(false, Ordering::AcqRel) {
+            info!(
+                "broker left maintenance mode, {} requests were rejected",
+                self.rejected_requests.load(Ordering::Relaxed)
+            );
+        }
+        self.reason.write().clear();
+        self.since_timestamp.store(0, Ordering::Release);
+    }
+
+    #[inline]
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Acquire)
+    }
+
+    /// Replaces the admin white list, `white_list` is a comma separated list of IPs.
+    pub fn update_white_list(&self, white_list: &str) {
+        *self.white_list.write() = parse_white_list(white_list);
+    }
+
+    pub fn is_allowed(&self, remote_ip: IpAddr) -> bool {
+        if !self.is_enabled() || remote_ip.is_loopback() {
+            return true;
+        }
+        let remote_ip = match remote_ip {
+            IpAddr::V6(v6) => v6.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(remote_ip),
+            ip => ip,
+        };
+        self.white_list.read().contains(&remote_ip)
+    }
+
+    /// Returns the rejection response when the request must not be processed.
+    pub fn check(&self, request_code: RequestCode, remote_ip: IpAddr) -> Option<RemotingCommand> {
+        if self.is_allowed(remote_ip) {
+            return None;
+        }
+        self.rejected_requests.fetch_add(1, Ordering::Relaxed);
+        warn!(
+            "reject request {:?} from {} while broker is in maintenance",
+            request_code, remote_ip
+        );
+        Some(RemotingCommand::create_response_command_with_code_remark(
+            ResponseCode::BrokerInMaintenance,
+            format!(
+                "[BROKER_IN_MAINTENANCE] broker is under maintenance ({}), try again later",
+                self.reason.read()
+            ),
+        ))
+    }
+
+    pub fn status(&self) -> MaintenanceModeStatus {
+        let mut white_list = self
+            .white_list
+            .read()
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+        white_list.sort();
+        MaintenanceModeStatus {
+            enabled: self.is_enabled(),
+            reason: self.reason.read().clone(),
+            since_timestamp: self.since_timestamp.load(Ordering::Acquire),
+            white_list,
+            rejected_requests: self.rejected_requests.load(Ordering::Relaxed),
+        }
+    }
+
+    pub fn build_running_stats(&self, stats: &mut HashMap<String, String>) {
+        stats.insert("maintenanceMode".to_string(), self.is_enabled().to_string());
+        stats.insert(
+            "maintenanceRejectedRequests".to_string(),
+            self.rejected_requests.load(Ordering::Relaxed).to_string(),
+        );
+    }
+}
+
+fn parse_white_list(white_list: &str) -> HashSet<IpAddr> {
+    white_list
+        .split(',')
+        .map(str::trim)
+        .filter(|ip| !ip.is_empty())
+        .filter_map(|ip| match ip.parse::<IpAddr>() {
+            Ok(ip) => Some(ip),
+            Err(_) => {
+                warn!("ignore invalid maintenance white list entry: {}", ip);
+                None
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use cheetah_string::CheetahString;
+
+    use super::*;
+
+    fn maintenance_mode() -> MaintenanceMode {
+        let broker_config = BrokerConfig {
+            maintenance_admin_white_list: CheetahString::from_static_str("10.0.0.1, bad-ip"),
+            ..Default::default()
+        };
+        MaintenanceMode::new(&broker_config)
+    }
+
+    #[test]
+    fn only_white_listed_requests_pass_in_maintenance() {
+        let mode = maintenance_mode();
+        let client: IpAddr = "10.0.0.2".parse().unwrap();
+        assert!(mode.check(RequestCode::SendMessage, client).is_none());
+
+        mode.enter("rebuild consume queue");
+        let response = mode.check(RequestCode::SendMessage, client).unwrap();
+        assert_eq!(response.code(), ResponseCode::BrokerInMaintenance as i32);
+        assert!(mode
+            .check(RequestCode::SendMessage, "10.0.0.1".parse().unwrap())
+            .is_none());
+        assert!(mode
+            .check(RequestCode::SendMessage, "::ffff:10.0.0.1".parse().unwrap())
+            .is_none());
+        assert!(mode
+            .check(RequestCode::SendMessage, "127.0.0.1".parse().unwrap())
+            .is_none());
+        assert_eq!(mode.status().rejected_requests, 1);
+
+        mode.exit();
+        assert!(mode.check(RequestCode::SendMessage, client).is_none());
+    }
+
+    #[test]
+    fn status_reports_reason_and_white_list() {
+        let mode = maintenance_mode();
+        mode.enter("verify");
+        let status = mode.status();
+        assert!(status.enabled);
+        assert_eq!(status.reason, "verify");
+        assert!(status.since_timestamp > 0);
+        assert_eq!(status.white_list, vec!["10.0.0.1".to_string()]);
+    }
+}
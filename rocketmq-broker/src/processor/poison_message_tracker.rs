@@ -0,0 +1,303 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+
+use cheetah_string::CheetahString;
+use parking_lot::RwLock;
+use rocketmq_common::common::broker::broker_config::BrokerConfig;
+use rocketmq_common::TimeUtils::get_current_millis;
+use serde::Deserialize;
+use serde::Serialize;
+use tracing::warn;
+
+const MAX_POISON_MESSAGES_PER_GROUP: usize = 1024;
+const SEND_BACK_RATE_WINDOW_MILLIS: i64 = 60 * 1000;
+
+/// A message that came back close to, or past, the maximum reconsume times of its group.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PoisonMessage {
+    pub msg_id: String,
+    pub topic: String,
+    pub queue_id: i32,
+    pub reconsume_times: i32,
+    pub max_reconsume_times: i32,
+    pub moved_to_dlq: bool,
+    pub last_send_back_timestamp: i64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupRetryReport {
+    pub group: String,
+    pub send_back_total: u64,
+    pub dlq_total: u64,
+    /// Send backs counted during the last complete minute.
+    pub send_back_per_minute: u64,
+    pub poison_messages: Vec<PoisonMessage>,
+    /// Queues paused for the group, formatted as `topic@queueId`.
+    pub paused_queues: Vec<String>,
+}
+
+/// Body of the `GET_POISON_MESSAGE_REPORT` response.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PoisonMessageReport {
+    pub groups: Vec<GroupRetryReport>,
+}
+
+#[derive(Default)]
+struct GroupRetryStats {
+    send_back_total: u64,
+    dlq_total: u64,
+    window_start: i64,
+    window_send_back: u64,
+    last_window_send_back: u64,
+    poison_messages: HashMap<String, PoisonMessage>,
+    paused_queues: HashSet<(CheetahString, i32)>,
+}
+
+impl GroupRetryStats {
+    fn roll_window(&mut self, now: i64) {
+        let elapsed = now - self.window_start;
+        if elapsed < SEND_BACK_RATE_WINDOW_MILLIS {
+            return;
+        }
+        self.last_window_send_back = if elapsed < 2 * SEND_BACK_RATE_WINDOW_MILLIS {
+            self.window_send_back
+        } else {
+            0
+        };
+        self.window_send_back = 0;
+        self.window_start = now;
+    }
+}
+
+/// Tracks per group send back (retry) rates and the messages that keep failing, so operators
+/// can spot a consumer bug before the DLQ floods. With `poison_message_auto_pause` the queue a
+/// poison message was consumed from is paused for the group until it is resumed by an admin.
+pub(crate) struct PoisonMessageTracker {
+    reconsume_gap: i32,
+    auto_pause: bool,
+    paused_queue_count: AtomicUsize,
+    groups: RwLock<HashMap<CheetahString, GroupRetryStats>>,
+}
+
+impl PoisonMessageTracker {
+    pub fn new(broker_config: &BrokerConfig) -> Self {
+        PoisonMessageTracker {
+            reconsume_gap: broker_config.poison_message_reconsume_gap.max(0),
+            auto_pause: broker_config.poison_message_auto_pause,
+            paused_queue_count: AtomicUsize::new(0),
+            groups: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Records one `CONSUMER_SEND_MSG_BACK`. `topic` and `queue_id` locate the message that
+    /// failed, `reconsume_times` is how often it has been consumed before this send back.
+    #[allow(clippy::too_many_arguments)]
+    pub fn on_send_back(
+        &self,
+        group: &CheetahString,
+        msg_id: &str,
+        topic: &CheetahString,
+        queue_id: i32,
+        reconsume_times: i32,
+        max_reconsume_times: i32,
+        moved_to_dlq: bool,
+    ) {
+        let now = get_current_millis() as i64;
+        let mut groups = self.groups.write();
+        let stats = groups.entry(group.clone()).or_default();
+        stats.roll_window(now);
+        stats.send_back_total += 1;
+        stats.window_send_back += 1;
+        if moved_to_dlq {
+            stats.dlq_total += 1;
+        }
+        if !moved_to_dlq && reconsume_times + 1 + self.reconsume_gap < max_reconsume_times {
+            return;
+        }
+
+        if !stats.poison_messages.contains_key(msg_id)
+            && stats.poison_messages.len() >= MAX_POISON_MESSAGES_PER_GROUP
+        {
+            let oldest = stats
+                .poison_messages
+                .values()
+                .min_by_key(|message| message.last_send_back_timestamp)
+                .map(|message| message.msg_id.clone());
+            if let Some(oldest) = oldest {
+                stats.poison_messages.remove(&oldest);
+            }
+        }
+        stats.poison_messages.insert(
+            msg_id.to_string(),
+            PoisonMessage {
+                msg_id: msg_id.to_string(),
+                topic: topic.to_string(),
+                queue_id,
+                reconsume_times: reconsume_times + 1,
+                max_reconsume_times,
+                moved_to_dlq,
+                last_send_back_timestamp: now,
+            },
+        );
+
+        if self.auto_pause && !moved_to_dlq && stats.paused_queues.insert((topic.clone(), queue_id))
+        {
+            self.paused_queue_count.fetch_add(1, Ordering::AcqRel);
+            warn!(
+                "poison message {} detected, pause queue {}@{} for group {}, reconsumeTimes={}, \
+                 maxReconsumeTimes={}",
+                msg_id,
+                topic,
+                queue_id,
+                group,
+                reconsume_times + 1,
+                max_reconsume_times
+            );
+        }
+    }
+
+    pub fn is_paused(&self, group: &CheetahString, topic: &CheetahString, queue_id: i32) -> bool {
+        if self.paused_queue_count.load(Ordering::Acquire) == 0 {
+            return false;
+        }
+        self.groups
+            .read()
+            .get(group)
+            .is_some_and(|stats| stats.paused_queues.contains(&(topic.clone(), queue_id)))
+    }
+
+    /// Resumes the paused queues of `group`, all of them when `topic` is `None`, and returns
+    /// how many were resumed.
+    pub fn resume(
+        &self,
+        group: &CheetahString,
+        topic: Option<&CheetahString>,
+        queue_id: Option<i32>,
+    ) -> usize {
+        let mut groups = self.groups.write();
+        let Some(stats) = groups.get_mut(group) else {
+            return 0;
+        };
+        let before = stats.paused_queues.len();
+        stats
+            .paused_queues
+            .retain(|(paused_topic, paused_queue_id)| {
+                let topic_matches = topic.is_none_or(|topic| topic == paused_topic);
+                let queue_matches = queue_id.is_none_or(|queue_id| queue_id == *paused_queue_id);
+                !(topic_matches && queue_matches)
+            });
+        let resumed = before - stats.paused_queues.len();
+        self.paused_queue_count.fetch_sub(resumed, Ordering::AcqRel);
+        resumed
+    }
+
+    pub fn report(&self, group: Option<&CheetahString>) -> PoisonMessageReport {
+        let now = get_current_millis() as i64;
+        let mut groups = self.groups.write();
+        let mut reports = groups
+            .iter_mut()
+            .filter(|(name, _)| group.is_none_or(|group| group == name))
+            .map(|(name, stats)| {
+                stats.roll_window(now);
+                let mut poison_messages =
+                    stats.poison_messages.values().cloned().collect::<Vec<_>>();
+                poison_messages
+                    .sort_by(|a, b| b.last_send_back_timestamp.cmp(&a.last_send_back_timestamp));
+                let mut paused_queues = stats
+                    .paused_queues
+                    .iter()
+                    .map(|(topic, queue_id)| format!("{}@{}", topic, queue_id))
+                    .collect::<Vec<_>>();
+                paused_queues.sort();
+                GroupRetryReport {
+                    group: name.to_string(),
+                    send_back_total: stats.send_back_total,
+                    dlq_total: stats.dlq_total,
+                    send_back_per_minute: stats.last_window_send_back,
+                    poison_messages,
+                    paused_queues,
+                }
+            })
+            .collect::<Vec<_>>();
+        reports.sort_by(|a, b| a.group.cmp(&b.group));
+        PoisonMessageReport { groups: reports }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tracker(auto_pause: bool) -> PoisonMessageTracker {
+        let broker_config = BrokerConfig {
+            poison_message_reconsume_gap: 1,
+            poison_message_auto_pause: auto_pause,
+            ..Default::default()
+        };
+        PoisonMessageTracker::new(&broker_config)
+    }
+
+    #[test]
+    fn messages_close_to_max_reconsume_times_are_reported() {
+        let tracker = tracker(false);
+        let group = CheetahString::from_static_str("group");
+        let topic = CheetahString::from_static_str("topic");
+        tracker.on_send_back(&group, "healthy", &topic, 0, 0, 16, false);
+        tracker.on_send_back(&group, "poison", &topic, 1, 14, 16, false);
+        tracker.on_send_back(&group, "dead", &topic, 2, 16, 16, true);
+
+        let report = tracker.report(Some(&group));
+        assert_eq!(report.groups.len(), 1);
+        let group_report = &report.groups[0];
+        assert_eq!(group_report.send_back_total, 3);
+        assert_eq!(group_report.dlq_total, 1);
+        let mut ids = group_report
+            .poison_messages
+            .iter()
+            .map(|message| message.msg_id.as_str())
+            .collect::<Vec<_>>();
+        ids.sort();
+        assert_eq!(ids, vec!["dead", "poison"]);
+        assert!(group_report.paused_queues.is_empty());
+        assert!(!tracker.is_paused(&group, &topic, 1));
+    }
+
+    #[test]
+    fn auto_pause_until_resumed() {
+        let tracker = tracker(true);
+        let group = CheetahString::from_static_str("group");
+        let topic = CheetahString::from_static_str("topic");
+        tracker.on_send_back(&group, "poison", &topic, 3, 15, 16, false);
+        assert!(tracker.is_paused(&group, &topic, 3));
+        assert!(!tracker.is_paused(&group, &topic, 2));
+        assert_eq!(
+            tracker.report(None).groups[0].paused_queues,
+            vec!["topic@3".to_string()]
+        );
+
+        assert_eq!(tracker.resume(&group, Some(&topic), Some(3)), 1);
+        assert!(!tracker.is_paused(&group, &topic, 3));
+        assert_eq!(tracker.resume(&group, None, None), 0);
+    }
+}
@@ -24,6 +24,7 @@ use rocketmq_common::common::filter::expression_type::ExpressionType;
 use rocketmq_common::common::sys_flag::pull_sys_flag::PullSysFlag;
 use rocketmq_common::common::FAQUrl;
 use rocketmq_common::TimeUtils::get_current_millis;
+use rocketmq_common::UtilAll::local_millis_of_day;
 use rocketmq_remoting::code::request_code::RequestCode;
 use rocketmq_remoting::code::response_code::RemotingSysResponseCode;
 use rocketmq_remoting::code::response_code::ResponseCode;
@@ -39,6 +40,8 @@ use rocketmq_remoting::protocol::request_source::RequestSource;
 use rocketmq_remoting::protocol::static_topic::topic_queue_mapping_context::TopicQueueMappingContext;
 use rocketmq_remoting::protocol::static_topic::topic_queue_mapping_detail::TopicQueueMappingDetail;
 use rocketmq_remoting::protocol::static_topic::topic_queue_mapping_utils::TopicQueueMappingUtils;
+use rocketmq_remoting::protocol::subscription::consume_time_window::ConsumeTimeWindow;
+use rocketmq_remoting::protocol::subscription::subscription_group_config::SubscriptionGroupConfig;
 use rocketmq_remoting::rpc::rpc_client::RpcClient;
 use rocketmq_remoting::rpc::rpc_client_utils::RpcClientUtils;
 use rocketmq_remoting::rpc::rpc_request::RpcRequest;
@@ -64,8 +67,10 @@ use crate::filter::manager::consumer_filter_manager::ConsumerFilterManager;
 use crate::offset::manager::broadcast_offset_manager::BroadcastOffsetManager;
 use crate::offset::manager::consumer_offset_manager::ConsumerOffsetManager;
 use crate::out_api::broker_outer_api::BrokerOuterAPI;
+use crate::processor::poison_message_tracker::PoisonMessageTracker;
 use crate::processor::pull_message_result_handler::PullMessageResultHandler;
 use crate::subscription::manager::subscription_group_manager::SubscriptionGroupManager;
+use crate::subscription::replay_subscription_manager::ReplaySubscriptionManager;
 use crate::topic::manager::topic_config_manager::TopicConfigManager;
 use crate::topic::manager::topic_queue_mapping_manager::TopicQueueMappingManager;
 
@@ -82,6 +87,8 @@ pub struct PullMessageProcessor<MS> {
     message_store: ArcMut<MS>,
     cold_data_cg_ctr_service: Arc<ColdDataCgCtrService>,
     broker_outer_api: Arc<BrokerOuterAPI>,
+    poison_message_tracker: Arc<PoisonMessageTracker>,
+    replay_subscription_manager: Arc<ReplaySubscriptionManager>,
     // write message to consume client runtime
     write_message_runtime: Arc<RocketMQRuntime>,
     // write message to consume client lock
@@ -101,6 +108,8 @@ impl<MS> PullMessageProcessor<MS> {
         broadcast_offset_manager: Arc<BroadcastOffsetManager>,
         message_store: ArcMut<MS>,
         broker_outer_api: Arc<BrokerOuterAPI>,
+        poison_message_tracker: Arc<PoisonMessageTracker>,
+        replay_subscription_manager: Arc<ReplaySubscriptionManager>,
     ) -> Self {
         let cpus = num_cpus::get();
         Self {
@@ -116,6 +125,8 @@ impl<MS> PullMessageProcessor<MS> {
             message_store,
             cold_data_cg_ctr_service: Arc::new(Default::default()),
             broker_outer_api,
+            poison_message_tracker,
+            replay_subscription_manager,
             write_message_runtime: Arc::new(RocketMQRuntime::new_multi(
                 cpus,
                 "write_consumer_message_runtime",
@@ -426,6 +437,25 @@ where
                     )),
             );
         }
+        if let Some(retry_after) = consume_time_window_retry_after(
+            subscription_group_config.as_ref().unwrap(),
+            local_millis_of_day(),
+        ) {
+            return Some(
+                response
+                    .set_code(ResponseCode::NotInConsumeWindow)
+                    .set_remark(format!(
+                        "[NOT_IN_CONSUME_WINDOW] subscription group [{}] consumes only within {}, \
+                         retry after {}ms",
+                        request_header.consumer_group,
+                        subscription_group_config
+                            .as_ref()
+                            .unwrap()
+                            .consume_time_window(),
+                        retry_after
+                    )),
+            );
+        }
         let topic_config = self
             .topic_config_manager
             .select_topic_config(request_header.topic.as_ref());
@@ -481,6 +511,42 @@ where
                     )),
             );
         }
+        if self.poison_message_tracker.is_paused(
+            &request_header.consumer_group,
+            &request_header.topic,
+            request_header.queue_id,
+        ) {
+            return Some(
+                response
+                    .set_code(ResponseCode::NoPermission)
+                    .set_remark(format!(
+                        "queue {}@{} is paused for group {} after a poison message, resume it \
+                         once the consumer is fixed",
+                        request_header.topic,
+                        request_header.queue_id,
+                        request_header.consumer_group
+                    )),
+            );
+        }
+        if let Some(end_offset) = self.replay_subscription_manager.end_offset(
+            &request_header.consumer_group,
+            &request_header.topic,
+            request_header.queue_id,
+        ) {
+            if request_header.queue_offset >= end_offset {
+                return Some(
+                    response
+                        .set_code(ResponseCode::NoPermission)
+                        .set_remark(format!(
+                            "replay of {}@{} for group {} reached its end offset {}",
+                            request_header.topic,
+                            request_header.queue_id,
+                            request_header.consumer_group,
+                            end_offset
+                        )),
+                );
+            }
+        }
         match RequestSource::parse_integer(request_header.request_source) {
             RequestSource::ProxyForBroadcast => {
                 unimplemented!("ProxyForBroadcast not implement")
@@ -680,7 +746,8 @@ where
             );
         }
 
-        let message_filter: Box<dyn MessageFilter> = if self.broker_config.filter_support_retry {
+        let mut message_filter: Box<dyn MessageFilter> = if self.broker_config.filter_support_retry
+        {
             Box::new(ExpressionForRetryMessageFilter)
         } else {
             Box::new(ExpressionMessageFilter::new(
@@ -731,18 +798,58 @@ where
                 get_message_result.set_next_begin_offset(broadcast_init_offset);
                 Some(get_message_result)
             } else {
-                let result = self
-                    .message_store
-                    .get_message(
-                        group,
-                        topic,
-                        queue_id,
-                        request_header.queue_offset,
-                        request_header.max_msg_nums,
-                        MAX_PULL_MSG_SIZE,
-                        Some(message_filter.as_ref()),
-                    )
-                    .await;
+                let result = match self.message_store.read_executor() {
+                    // keep read storms of lagging consumers off the runtime serving puts
+                    Some(read_executor) => {
+                        let message_store = self.message_store.clone();
+                        let group = request_header.consumer_group.clone();
+                        let topic = request_header.topic.clone();
+                        let queue_offset = request_header.queue_offset;
+                        let max_msg_nums = request_header.max_msg_nums;
+                        let read = read_executor
+                            .execute("pull_message", async move {
+                                let result = message_store
+                                    .get_message(
+                                        &group,
+                                        &topic,
+                                        queue_id,
+                                        queue_offset,
+                                        max_msg_nums,
+                                        MAX_PULL_MSG_SIZE,
+                                        Some(message_filter.as_ref()),
+                                    )
+                                    .await;
+                                (result, message_filter)
+                            })
+                            .await;
+                        match read {
+                            Ok((result, filter)) => {
+                                message_filter = filter;
+                                result
+                            }
+                            Err(err) => {
+                                return Some(
+                                    response
+                                        .set_code(ResponseCode::SystemError)
+                                        .set_remark(format!("store getMessage failed: {err}")),
+                                );
+                            }
+                        }
+                    }
+                    None => {
+                        self.message_store
+                            .get_message(
+                                group,
+                                topic,
+                                queue_id,
+                                request_header.queue_offset,
+                                request_header.max_msg_nums,
+                                MAX_PULL_MSG_SIZE,
+                                Some(message_filter.as_ref()),
+                            )
+                            .await
+                    }
+                };
                 if result.is_none() {
                     return Some(
                         response
@@ -851,6 +958,39 @@ where
             }
         });
     }
+
+    /// Re-executes the pulls woken by a message arrival once the consume queue units and the
+    /// first commit log pages of the queue have been faulted in, so the first read after a
+    /// quiet period does not stall on disk. The prefetch runs once for the whole batch.
+    pub fn execute_requests_when_wakeup_with_prefetch(
+        &self,
+        pull_message_processor: ArcMut<PullMessageProcessor<MS>>,
+        topic: CheetahString,
+        queue_id: i32,
+        offset: i64,
+        requests: Vec<(Channel, ConnectionHandlerContext, RemotingCommand)>,
+    ) {
+        let message_store = self.message_store.clone();
+        let max_msg_nums = self.broker_config.long_polling_prefetch_msg_nums;
+        self.write_message_runtime.get_handle().spawn(async move {
+            let prefetched = tokio::task::spawn_blocking(move || {
+                message_store.prefetch_message(&topic, queue_id, offset, max_msg_nums)
+            })
+            .await;
+            if let Err(e) = prefetched {
+                warn!("prefetch message before pull wakeup failed: {}", e);
+            }
+            for (channel, ctx, request) in requests {
+                let pull_message_this = pull_message_processor.clone();
+                pull_message_processor.execute_request_when_wakeup(
+                    pull_message_this,
+                    channel,
+                    ctx,
+                    request,
+                );
+            }
+        });
+    }
 }
 pub(crate) fn is_broadcast(
     proxy_pull_broadcast: bool,
@@ -863,6 +1003,29 @@ pub(crate) fn is_broadcast(
         })
 }
 
+/// Returns how long the group has to wait when `millis_of_day` is outside its consume time
+/// window. A window that fails to parse does not restrict the group.
+pub(crate) fn consume_time_window_retry_after(
+    subscription_group_config: &SubscriptionGroupConfig,
+    millis_of_day: u64,
+) -> Option<u64> {
+    let window = subscription_group_config.consume_time_window();
+    if window.is_empty() {
+        return None;
+    }
+    match ConsumeTimeWindow::parse(window) {
+        Ok(window) => window.millis_until_open(millis_of_day),
+        Err(err) => {
+            warn!(
+                "ignore consume time window of group {}: {}",
+                subscription_group_config.group_name(),
+                err
+            );
+            None
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use rocketmq_common::common::consumer::consume_from_where::ConsumeFromWhere;
@@ -919,4 +1082,18 @@ mod tests {
             "Should return false when no consumer group info is provided"
         );
     }
+
+    #[test]
+    fn consume_time_window_restricts_pulls_outside_the_window() {
+        let mut config = SubscriptionGroupConfig::new("batch_group".into());
+        assert_eq!(consume_time_window_retry_after(&config, 0), None);
+
+        config.set_consume_time_window("02:00-06:00".into());
+        let hour = 60 * 60 * 1000;
+        assert_eq!(consume_time_window_retry_after(&config, 3 * hour), None);
+        assert_eq!(consume_time_window_retry_after(&config, hour), Some(hour));
+
+        config.set_consume_time_window("not a window".into());
+        assert_eq!(consume_time_window_retry_after(&config, hour), None);
+    }
 }
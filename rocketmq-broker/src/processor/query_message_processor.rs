@@ -79,16 +79,38 @@ where
         if is_unique_key.is_some() && is_unique_key.unwrap() == "true" {
             request_header.max_num = self.message_store_config.default_query_max_num as i32;
         }
-        let query_message_result = self
-            .message_store
-            .query_message(
-                request_header.topic.as_ref(),
-                request_header.key.as_ref(),
-                request_header.max_num,
-                request_header.begin_timestamp,
-                request_header.end_timestamp,
-            )
-            .await?;
+        let query_message_result = match self.message_store.read_executor() {
+            Some(read_executor) => {
+                let message_store = self.message_store.clone();
+                let header = request_header.clone();
+                read_executor
+                    .execute("query_message", async move {
+                        message_store
+                            .query_message(
+                                &header.topic,
+                                &header.key,
+                                header.max_num,
+                                header.begin_timestamp,
+                                header.end_timestamp,
+                            )
+                            .await
+                    })
+                    .await
+                    .ok()
+                    .flatten()?
+            }
+            None => {
+                self.message_store
+                    .query_message(
+                        request_header.topic.as_ref(),
+                        request_header.key.as_ref(),
+                        request_header.max_num,
+                        request_header.begin_timestamp,
+                        request_header.end_timestamp,
+                    )
+                    .await?
+            }
+        };
 
         let response_header = response
             .read_custom_header_mut::<QueryMessageResponseHeader>()
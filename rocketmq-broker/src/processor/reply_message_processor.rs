@@ -46,7 +46,9 @@ use tracing::warn;
 
 use crate::client::manager::producer_manager::ProducerManager;
 use crate::client::rebalance::rebalance_lock_manager::RebalanceLockManager;
+use crate::hook::message_body_validator::MessageBodyValidatorRegistry;
 use crate::mqtrace::send_message_context::SendMessageContext;
+use crate::processor::poison_message_tracker::PoisonMessageTracker;
 use crate::processor::send_message_processor::Inner;
 use crate::subscription::manager::subscription_group_manager::SubscriptionGroupManager;
 use crate::topic::manager::topic_config_manager::TopicConfigManager;
@@ -73,8 +75,11 @@ where
         broker_stats_manager: Arc<BrokerStatsManager>,
         producer_manager: Option<Arc<ProducerManager>>,
         transactional_message_service: ArcMut<TS>,
+        poison_message_tracker: Arc<PoisonMessageTracker>,
+        message_body_validators: Arc<MessageBodyValidatorRegistry>,
     ) -> Self {
-        let store_host = format!("{}:{}", broker_config.broker_ip1, broker_config.listen_port)
+        let store_host = broker_config
+            .get_broker_addr()
             .parse::<SocketAddr>()
             .unwrap();
         Self {
@@ -92,6 +97,8 @@ where
                 producer_manager,
                 broker_to_client: Default::default(),
                 store_host,
+                poison_message_tracker,
+                message_body_validators,
             },
             store_host,
         }
@@ -0,0 +1,246 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::sync::atomic::AtomicI64;
+use std::sync::atomic::Ordering;
+
+use rocketmq_common::common::broker::broker_config::BrokerConfig;
+use rocketmq_remoting::code::request_code::RequestCode;
+use tokio::sync::Semaphore;
+use tokio::sync::SemaphorePermit;
+
+/// Scheduling class of a broker request.
+///
+/// `High` requests keep client sessions and consume progress alive and never wait, `Low`
+/// requests carry message payloads and are the first to be throttled during bursts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum RequestPriority {
+    High = 0,
+    Normal = 1,
+    Low = 2,
+}
+
+impl RequestPriority {
+    pub const ALL: [RequestPriority; 3] = [
+        RequestPriority::High,
+        RequestPriority::Normal,
+        RequestPriority::Low,
+    ];
+
+    pub fn of(request_code: RequestCode) -> Self {
+        match request_code {
+            RequestCode::HeartBeat
+            | RequestCode::UnregisterClient
+            | RequestCode::CheckClientConfig
+            | RequestCode::UpdateConsumerOffset
+            | RequestCode::QueryConsumerOffset
+            | RequestCode::AckMessage
+            | RequestCode::BatchAckMessage
+            | RequestCode::ChangeMessageInvisibleTime
+            | RequestCode::EndTransaction
+            | RequestCode::LockBatchMq
+            | RequestCode::UnlockBatchMq => RequestPriority::High,
+            RequestCode::SendMessage
+            | RequestCode::SendMessageV2
+            | RequestCode::SendBatchMessage
+            | RequestCode::ConsumerSendMsgBack
+            | RequestCode::SendReplyMessage
+            | RequestCode::SendReplyMessageV2
+            | RequestCode::PullMessage
+            | RequestCode::LitePullMessage
+            | RequestCode::PopMessage
+            | RequestCode::PeekMessage => RequestPriority::Low,
+            _ => RequestPriority::Normal,
+        }
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+impl Display for RequestPriority {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RequestPriority::High => write!(f, "high"),
+            RequestPriority::Normal => write!(f, "normal"),
+            RequestPriority::Low => write!(f, "low"),
+        }
+    }
+}
+
+/// Bounds the number of normal and low priority requests processed at the same time so that
+/// high priority requests are never starved behind large send/pull bursts.
+pub(crate) struct RequestPriorityDispatcher {
+    enable: bool,
+    normal_permits: Semaphore,
+    low_permits: Semaphore,
+    queue_depth: [AtomicI64; 3],
+    in_flight: [AtomicI64; 3],
+}
+
+/// Held while a request is processed, releases its slot on drop.
+pub(crate) struct RequestPriorityPermit<'a> {
+    dispatcher: &'a RequestPriorityDispatcher,
+    priority: RequestPriority,
+    _permit: Option<SemaphorePermit<'a>>,
+}
+
+impl Drop for RequestPriorityPermit<'_> {
+    fn drop(&mut self) {
+        self.dispatcher.in_flight[self.priority.index()].fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Keeps the queue depth accurate even when the waiting future is cancelled.
+struct QueueDepthGuard<'a>(&'a AtomicI64);
+
+impl<'a> QueueDepthGuard<'a> {
+    fn new(depth: &'a AtomicI64) -> Self {
+        depth.fetch_add(1, Ordering::Relaxed);
+        QueueDepthGuard(depth)
+    }
+}
+
+impl Drop for QueueDepthGuard<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl RequestPriorityDispatcher {
+    pub fn new(broker_config: &BrokerConfig) -> Self {
+        RequestPriorityDispatcher {
+            enable: broker_config.request_priority_enable,
+            normal_permits: Semaphore::new(
+                broker_config.normal_priority_request_max_concurrency.max(1),
+            ),
+            low_permits: Semaphore::new(broker_config.low_priority_request_max_concurrency.max(1)),
+            queue_depth: Default::default(),
+            in_flight: Default::default(),
+        }
+    }
+
+    /// Waits until a request of the given code may be processed.
+    pub async fn acquire(&self, request_code: RequestCode) -> RequestPriorityPermit<'_> {
+        let priority = RequestPriority::of(request_code);
+        let semaphore = match priority {
+            RequestPriority::Normal if self.enable => Some(&self.normal_permits),
+            RequestPriority::Low if self.enable => Some(&self.low_permits),
+            _ => None,
+        };
+        let permit = match semaphore {
+            Some(semaphore) => {
+                let _waiting = QueueDepthGuard::new(&self.queue_depth[priority.index()]);
+                semaphore.acquire().await.ok()
+            }
+            None => None,
+        };
+        self.in_flight[priority.index()].fetch_add(1, Ordering::Relaxed);
+        RequestPriorityPermit {
+            dispatcher: self,
+            priority,
+            _permit: permit,
+        }
+    }
+
+    /// Number of requests waiting for a slot.
+    pub fn queue_depth(&self, priority: RequestPriority) -> i64 {
+        self.queue_depth[priority.index()].load(Ordering::Relaxed)
+    }
+
+    /// Number of requests currently being processed.
+    pub fn in_flight(&self, priority: RequestPriority) -> i64 {
+        self.in_flight[priority.index()].load(Ordering::Relaxed)
+    }
+
+    pub fn build_running_stats(&self, stats: &mut HashMap<String, String>) {
+        for priority in RequestPriority::ALL {
+            stats.insert(
+                format!("{}PriorityRequestQueueDepth", priority),
+                self.queue_depth(priority).to_string(),
+            );
+            stats.insert(
+                format!("{}PriorityRequestInFlight", priority),
+                self.in_flight(priority).to_string(),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dispatcher(low: usize) -> RequestPriorityDispatcher {
+        let broker_config = BrokerConfig {
+            low_priority_request_max_concurrency: low,
+            ..Default::default()
+        };
+        RequestPriorityDispatcher::new(&broker_config)
+    }
+
+    #[test]
+    fn classifies_request_codes() {
+        assert_eq!(
+            RequestPriority::of(RequestCode::HeartBeat),
+            RequestPriority::High
+        );
+        assert_eq!(
+            RequestPriority::of(RequestCode::UpdateConsumerOffset),
+            RequestPriority::High
+        );
+        assert_eq!(
+            RequestPriority::of(RequestCode::PullMessage),
+            RequestPriority::Low
+        );
+        assert_eq!(
+            RequestPriority::of(RequestCode::GetBrokerConfig),
+            RequestPriority::Normal
+        );
+    }
+
+    #[tokio::test]
+    async fn high_priority_is_not_blocked_by_exhausted_low_slots() {
+        let dispatcher = dispatcher(1);
+        let _send = dispatcher.acquire(RequestCode::SendMessage).await;
+        let heartbeat = tokio::time::timeout(
+            std::time::Duration::from_millis(100),
+            dispatcher.acquire(RequestCode::HeartBeat),
+        )
+        .await;
+        assert!(heartbeat.is_ok());
+        assert_eq!(dispatcher.in_flight(RequestPriority::High), 1);
+        assert_eq!(dispatcher.in_flight(RequestPriority::Low), 1);
+    }
+
+    #[tokio::test]
+    async fn low_priority_waits_and_reports_queue_depth() {
+        let dispatcher = dispatcher(1);
+        let send = dispatcher.acquire(RequestCode::SendMessage).await;
+        let pending = tokio::time::timeout(
+            std::time::Duration::from_millis(50),
+            dispatcher.acquire(RequestCode::PullMessage),
+        )
+        .await;
+        assert!(pending.is_err());
+        assert_eq!(dispatcher.queue_depth(RequestPriority::Low), 0);
+        drop(send);
+        assert_eq!(dispatcher.in_flight(RequestPriority::Low), 0);
+    }
+}
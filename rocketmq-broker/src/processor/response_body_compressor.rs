@@ -0,0 +1,156 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::time::Instant;
+
+use bytes::Bytes;
+use rocketmq_common::common::broker::broker_config::BrokerConfig;
+use rocketmq_common::common::compression::compression_type::CompressionType;
+use rocketmq_common::common::compression::compressor_factory::CompressorFactory;
+use rocketmq_common::TimeUtils::get_current_millis;
+use tracing::warn;
+
+const BUDGET_WINDOW_MILLIS: u64 = 1000;
+
+/// Compresses large message response bodies with zstd for clients that accept it.
+///
+/// Compression time is charged against a per second CPU budget shared by all requests, once
+/// the budget of the current second is spent bodies are sent uncompressed until the next one.
+pub(crate) struct ResponseBodyCompressor {
+    enable: bool,
+    threshold: usize,
+    level: i32,
+    // 0 means unlimited
+    cpu_budget_micros_per_second: u64,
+    window_start_millis: AtomicU64,
+    window_used_micros: AtomicU64,
+}
+
+impl ResponseBodyCompressor {
+    pub(crate) fn new(broker_config: &BrokerConfig) -> Self {
+        Self {
+            enable: broker_config.pull_body_compression_enable,
+            threshold: broker_config.pull_body_compression_threshold,
+            level: broker_config.pull_body_compression_level,
+            cpu_budget_micros_per_second: broker_config
+                .pull_body_compression_cpu_budget_micros_per_second,
+            window_start_millis: AtomicU64::new(0),
+            window_used_micros: AtomicU64::new(0),
+        }
+    }
+
+    /// Returns the compressed body and the compression type flag to put in the response
+    /// header, `None` when the body should be sent as it is.
+    pub(crate) fn compress(&self, body: &[u8]) -> Option<(Bytes, i32)> {
+        if !self.enable || body.len() < self.threshold || !self.has_budget() {
+            return None;
+        }
+        let compression_type = CompressionType::Zstd;
+        let begin = Instant::now();
+        let compressed =
+            CompressorFactory::get_compressor(compression_type).compress(body, self.level);
+        self.window_used_micros
+            .fetch_add(begin.elapsed().as_micros() as u64, Ordering::Relaxed);
+        match compressed {
+            Ok(compressed) if compressed.len() < body.len() => {
+                Some((compressed, compression_type.get_compression_flag()))
+            }
+            Ok(_) => None,
+            Err(e) => {
+                warn!(
+                    "compress response body of {} bytes failed: {}",
+                    body.len(),
+                    e
+                );
+                None
+            }
+        }
+    }
+
+    /// Whether a body of `body_len` bytes is large enough to be compressed at all.
+    pub(crate) fn may_compress(&self, body_len: usize) -> bool {
+        self.enable && body_len >= self.threshold
+    }
+
+    fn has_budget(&self) -> bool {
+        if self.cpu_budget_micros_per_second == 0 {
+            return true;
+        }
+        let now = get_current_millis();
+        let window_start = self.window_start_millis.load(Ordering::Relaxed);
+        if now.saturating_sub(window_start) >= BUDGET_WINDOW_MILLIS
+            && self
+                .window_start_millis
+                .compare_exchange(window_start, now, Ordering::Relaxed, Ordering::Relaxed)
+                .is_ok()
+        {
+            self.window_used_micros.store(0, Ordering::Relaxed);
+        }
+        self.window_used_micros.load(Ordering::Relaxed) < self.cpu_budget_micros_per_second
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn compressor(threshold: usize, cpu_budget_micros_per_second: u64) -> ResponseBodyCompressor {
+        let broker_config = BrokerConfig {
+            pull_body_compression_enable: true,
+            pull_body_compression_threshold: threshold,
+            pull_body_compression_cpu_budget_micros_per_second: cpu_budget_micros_per_second,
+            ..BrokerConfig::default()
+        };
+        ResponseBodyCompressor::new(&broker_config)
+    }
+
+    #[test]
+    fn compress_skips_small_bodies_and_round_trips_large_ones() {
+        let compressor = compressor(1024, 0);
+        assert!(compressor.compress(&[7u8; 512]).is_none());
+
+        let body = vec![7u8; 64 * 1024];
+        let (compressed, flag) = compressor.compress(&body).unwrap();
+        assert!(compressed.len() < body.len());
+        assert_eq!(flag, CompressionType::Zstd.get_compression_flag());
+        let decompressed = CompressorFactory::get_compressor(CompressionType::Zstd)
+            .decompress(&compressed)
+            .unwrap();
+        assert_eq!(decompressed.as_ref(), body.as_slice());
+    }
+
+    #[test]
+    fn compress_stops_once_the_cpu_budget_is_spent() {
+        let compressor = compressor(0, 1);
+        let body = vec![7u8; 64 * 1024];
+        compressor.window_used_micros.store(1, Ordering::Relaxed);
+        compressor
+            .window_start_millis
+            .store(get_current_millis(), Ordering::Relaxed);
+        assert!(compressor.compress(&body).is_none());
+
+        compressor.window_start_millis.store(0, Ordering::Relaxed);
+        assert!(compressor.compress(&body).is_some());
+    }
+
+    #[test]
+    fn compress_is_disabled_by_default() {
+        let compressor = ResponseBodyCompressor::new(&BrokerConfig::default());
+        assert!(compressor.compress(&vec![7u8; 1024 * 1024]).is_none());
+    }
+}
@@ -78,10 +78,12 @@ use tracing::warn;
 use crate::client::manager::producer_manager::ProducerManager;
 use crate::client::net::broker_to_client::Broker2Client;
 use crate::client::rebalance::rebalance_lock_manager::RebalanceLockManager;
+use crate::hook::message_body_validator::MessageBodyValidatorRegistry;
 use crate::mqtrace::consume_message_context::ConsumeMessageContext;
 use crate::mqtrace::consume_message_hook::ConsumeMessageHook;
 use crate::mqtrace::send_message_context::SendMessageContext;
 use crate::mqtrace::send_message_hook::SendMessageHook;
+use crate::processor::poison_message_tracker::PoisonMessageTracker;
 use crate::subscription::manager::subscription_group_manager::SubscriptionGroupManager;
 use crate::topic::manager::topic_config_manager::TopicConfigManager;
 use crate::topic::manager::topic_queue_mapping_manager::TopicQueueMappingManager;
@@ -193,8 +195,11 @@ where
         transactional_message_service: ArcMut<TS>,
         rebalance_lock_manager: Arc<RebalanceLockManager>,
         broker_stats_manager: Arc<BrokerStatsManager>,
+        poison_message_tracker: Arc<PoisonMessageTracker>,
+        message_body_validators: Arc<MessageBodyValidatorRegistry>,
     ) -> Self {
-        let store_host = format!("{}:{}", broker_config.broker_ip1, broker_config.listen_port)
+        let store_host = broker_config
+            .get_broker_addr()
             .parse::<SocketAddr>()
             .unwrap();
         Self {
@@ -212,6 +217,8 @@ where
                 producer_manager: None,
                 broker_to_client: Default::default(),
                 store_host,
+                poison_message_tracker,
+                message_body_validators,
             }),
             store_host,
         }
@@ -246,6 +253,11 @@ where
         if queue_id < 0 {
             queue_id = self.inner.random_queue_id(topic_config.write_queue_nums) as i32;
         }
+        if let Some(fenced) = self.check_queue_write_fence(request_header.topic(), queue_id, || {
+            MessageDecoder::count_inner_msg_num(request.body().clone()) as i64
+        }) {
+            return Ok(Some(fenced));
+        }
 
         if request_header.topic.len() > i8::MAX as usize {
             return Ok(Some(
@@ -270,6 +282,17 @@ where
                     )),
             ));
         }
+        if let Err(reason) = self
+            .inner
+            .message_body_validators
+            .validate_batch(&topic_config, request.body().as_deref())
+        {
+            return Ok(Some(
+                response
+                    .set_code(ResponseCode::MessageIllegal)
+                    .set_remark(format!("message body illegal, {}", reason)),
+            ));
+        }
         let mut message_ext = MessageExtBrokerInner::default();
         message_ext.message_ext_inner.message.topic = request_header.topic().clone();
         message_ext.message_ext_inner.queue_id = queue_id;
@@ -369,18 +392,19 @@ where
             .topic()
             .clone();
         if self.inner.broker_config.async_send_enable {
-            let mut message_store = self.inner.message_store.clone();
-            let put_message_result = tokio::spawn(async move {
-                if is_inner_batch {
-                    message_store
-                        .put_message(batch_message.message_ext_broker_inner)
-                        .await
-                } else {
-                    message_store.put_messages(batch_message).await
-                }
-            })
-            .await
-            .map_err(|e| RemotingCommandError(e.to_string()))?;
+            // the store is only held for the append, flush and replication are awaited after
+            let put_message_future = if is_inner_batch {
+                self.inner
+                    .message_store
+                    .async_put_message(batch_message.message_ext_broker_inner)
+                    .await
+            } else {
+                self.inner
+                    .message_store
+                    .async_put_messages(batch_message)
+                    .await
+            };
+            let put_message_result = put_message_future.await;
             Ok(self
                 .handle_put_message_result(
                     put_message_result,
@@ -457,6 +481,9 @@ where
         if queue_id < 0 {
             queue_id = self.inner.random_queue_id(topic_config.write_queue_nums) as i32;
         }
+        if let Some(fenced) = self.check_queue_write_fence(request_header.topic(), queue_id, || 1) {
+            return Ok(Some(fenced));
+        }
 
         let mut message_ext = MessageExtBrokerInner::default();
         message_ext.message_ext_inner.message.topic = request_header.topic().clone();
@@ -473,6 +500,17 @@ where
         ) {
             return Ok(Some(response));
         }
+        if let Err(reason) = self.inner.message_body_validators.validate_message(
+            &topic_config,
+            request_header.sys_flag,
+            request.body().as_deref(),
+        ) {
+            return Ok(Some(
+                response
+                    .set_code(ResponseCode::MessageIllegal)
+                    .set_remark(format!("message body illegal, {}", reason)),
+            ));
+        }
         message_ext
             .message_ext_inner
             .message
@@ -561,7 +599,7 @@ where
         let transaction_id =
             MessageClientIDSetter::get_uniq_id(&message_ext.message_ext_inner.message);
         if self.inner.broker_config.async_send_enable {
-            let put_message_handle = if send_transaction_prepare_message {
+            let put_message_result = if send_transaction_prepare_message {
                 let mut transactional_message_service =
                     self.inner.transactional_message_service.clone();
                 tokio::spawn(async move {
@@ -569,13 +607,17 @@ where
                         .async_prepare_message(message_ext)
                         .await
                 })
+                .await
+                .map_err(|e| RemotingCommandError(e.to_string()))?
             } else {
-                let mut message_store = self.inner.message_store.clone();
-                tokio::spawn(async move { message_store.put_message(message_ext).await })
+                // the store is only held for the append, flush and replication are awaited after
+                let put_message_future = self
+                    .inner
+                    .message_store
+                    .async_put_message(message_ext)
+                    .await;
+                put_message_future.await
             };
-            let put_message_result = put_message_handle
-                .await
-                .map_err(|e| RemotingCommandError(e.to_string()))?;
             Ok(self
                 .handle_put_message_result(
                     put_message_result,
@@ -830,6 +872,26 @@ where
         }
     }
 
+    fn check_queue_write_fence(
+        &self,
+        topic: &CheetahString,
+        queue_id: i32,
+        message_num: impl FnOnce() -> i64,
+    ) -> Option<RemotingCommand> {
+        self.inner
+            .topic_queue_mapping_manager
+            .check_queue_write_fence(
+                topic,
+                queue_id,
+                || {
+                    self.inner
+                        .message_store
+                        .get_max_offset_in_queue(topic, queue_id)
+                },
+                message_num,
+            )
+    }
+
     pub fn pre_send(
         &mut self,
         channel: &Channel,
@@ -978,6 +1040,8 @@ pub(crate) struct Inner<MS, TS> {
     pub(crate) producer_manager: Option<Arc<ProducerManager>>,
     pub(crate) broker_to_client: Broker2Client,
     pub(crate) store_host: SocketAddr,
+    pub(crate) poison_message_tracker: Arc<PoisonMessageTracker>,
+    pub(crate) message_body_validators: Arc<MessageBodyValidatorRegistry>,
 }
 
 impl<MS, TS> Inner<MS, TS>
@@ -1207,6 +1271,15 @@ where
         } else {
             msg_ext.msg_id.clone()
         };
+        self.poison_message_tracker.on_send_back(
+            &request_header.group,
+            origin_msg_id.as_str(),
+            msg_ext.get_topic(),
+            msg_ext.queue_id,
+            msg_ext.reconsume_times,
+            max_reconsume_times,
+            is_dlq,
+        );
         MessageAccessor::set_origin_message_id(&mut msg_inner, origin_msg_id);
         msg_inner.properties_string = message_properties_to_string(msg_ext.get_properties());
 
@@ -16,3 +16,4 @@
  */
 
 pub(crate) mod manager;
+pub(crate) mod replay_subscription_manager;
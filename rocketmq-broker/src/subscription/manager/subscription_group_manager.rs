@@ -30,6 +30,7 @@ use rocketmq_store::log_file::MessageStore;
 use serde::Deserialize;
 use serde::Serialize;
 use tracing::info;
+use tracing::warn;
 
 use crate::broker_path_config_helper::get_subscription_group_path;
 
@@ -170,6 +171,57 @@ where
             .cloned()
     }
 
+    pub fn update_subscription_group_config(&self, config: SubscriptionGroupConfig) {
+        let old = self
+            .subscription_group_wrapper
+            .lock()
+            .subscription_group_table
+            .insert(
+                CheetahString::from_slice(config.group_name()),
+                config.clone(),
+            );
+        match old {
+            Some(old) => info!(
+                "update subscription group config, old: {:?} new: {:?}",
+                old, config
+            ),
+            None => info!("create new subscription group, {:?}", config),
+        }
+        self.update_data_version();
+        self.persist();
+    }
+
+    pub fn delete_subscription_group_config(&self, group: &CheetahString) {
+        let old = {
+            let mut wrapper = self.subscription_group_wrapper.lock();
+            wrapper.forbidden_table.remove(group);
+            wrapper.subscription_group_table.remove(group)
+        };
+        match old {
+            Some(old) => {
+                info!("delete subscription group OK, subscription group:{:?}", old);
+                self.update_data_version();
+                self.persist();
+            }
+            None => warn!(
+                "delete subscription group failed, subscription groupName: {} not exist",
+                group
+            ),
+        }
+    }
+
+    fn update_data_version(&self) {
+        let state_machine_version = if let Some(ref store) = self.message_store {
+            store.get_state_machine_version()
+        } else {
+            0
+        };
+        self.subscription_group_wrapper
+            .lock()
+            .data_version
+            .next_version_with(state_machine_version);
+    }
+
     pub fn get_forbidden(&self, group: &str, topic: &str, forbidden_index: i32) -> bool {
         let topic_forbidden = self.get_forbidden_internal(group, topic);
         let bit_forbidden = 1 << forbidden_index;
@@ -0,0 +1,190 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::HashMap;
+
+use cheetah_string::CheetahString;
+use parking_lot::RwLock;
+use rocketmq_remoting::protocol::body::replay_subscription_body::ReplaySubscription;
+use rocketmq_store::log_file::MessageStore;
+use tracing::info;
+
+use crate::offset::manager::consumer_offset_manager::ConsumerOffsetManager;
+use crate::subscription::manager::subscription_group_manager::SubscriptionGroupManager;
+
+pub(crate) const REPLAY_GROUP_PREFIX: &str = "%REPLAY%";
+
+/// Temporary consumer groups created to re-read historic messages. Their offsets live in the
+/// regular offset table under their own group name, so production groups are never touched,
+/// and they are removed once `expire_at_millis` has passed.
+#[derive(Default)]
+pub(crate) struct ReplaySubscriptionManager {
+    subscriptions: RwLock<HashMap<CheetahString, ReplaySubscription>>,
+}
+
+impl ReplaySubscriptionManager {
+    pub fn generate_group_name(topic: &CheetahString, now: i64) -> CheetahString {
+        CheetahString::from_string(format!("{}{}_{}", REPLAY_GROUP_PREFIX, topic, now))
+    }
+
+    /// Returns `false` when a replay subscription with the same group already exists.
+    pub fn register(&self, subscription: ReplaySubscription) -> bool {
+        let mut subscriptions = self.subscriptions.write();
+        if subscriptions.contains_key(&subscription.consumer_group) {
+            return false;
+        }
+        subscriptions.insert(subscription.consumer_group.clone(), subscription);
+        true
+    }
+
+    pub fn remove(&self, group: &CheetahString) -> Option<ReplaySubscription> {
+        self.subscriptions.write().remove(group)
+    }
+
+    pub fn is_replay_group(&self, group: &CheetahString) -> bool {
+        self.subscriptions.read().contains_key(group)
+    }
+
+    /// Exclusive end of the replay range for the queue, `None` for unbounded replays and
+    /// regular groups.
+    pub fn end_offset(
+        &self,
+        group: &CheetahString,
+        topic: &CheetahString,
+        queue_id: i32,
+    ) -> Option<i64> {
+        let subscriptions = self.subscriptions.read();
+        let subscription = subscriptions.get(group)?;
+        if subscription.topic != *topic {
+            return None;
+        }
+        subscription.end_offsets.get(&queue_id).copied()
+    }
+
+    pub fn list(&self) -> Vec<ReplaySubscription> {
+        self.subscriptions.read().values().cloned().collect()
+    }
+
+    /// Removes and returns the subscriptions that expired at `now`.
+    pub fn take_expired(&self, now: i64) -> Vec<ReplaySubscription> {
+        let mut subscriptions = self.subscriptions.write();
+        let expired = subscriptions
+            .values()
+            .filter(|subscription| subscription.expire_at_millis <= now)
+            .map(|subscription| subscription.consumer_group.clone())
+            .collect::<Vec<_>>();
+        expired
+            .iter()
+            .filter_map(|group| subscriptions.remove(group))
+            .collect()
+    }
+}
+
+/// Drops the group config and offsets left behind by an expired or deleted replay.
+pub(crate) fn release_replay_subscription<MS: MessageStore>(
+    subscription: &ReplaySubscription,
+    consumer_offset_manager: &ConsumerOffsetManager,
+    subscription_group_manager: &SubscriptionGroupManager<MS>,
+) {
+    subscription_group_manager.delete_subscription_group_config(&subscription.consumer_group);
+    consumer_offset_manager.remove_offset(&subscription.consumer_group);
+    info!(
+        "replay subscription {} on {} released",
+        subscription.consumer_group, subscription.topic
+    );
+}
+
+/// Offset of the first message of the queue stored at or after `timestamp`, or the max offset
+/// when every message is older.
+pub(crate) fn search_offset_by_timestamp<MS: MessageStore>(
+    message_store: &MS,
+    topic: &CheetahString,
+    queue_id: i32,
+    timestamp: i64,
+) -> i64 {
+    let min_offset = message_store.get_min_offset_in_queue(topic, queue_id);
+    let max_offset = message_store.get_max_offset_in_queue(topic, queue_id);
+    lower_bound_by_store_time(min_offset, max_offset, timestamp, |offset| {
+        message_store.get_message_store_timestamp(topic, queue_id, offset)
+    })
+}
+
+fn lower_bound_by_store_time(
+    min_offset: i64,
+    max_offset: i64,
+    timestamp: i64,
+    store_time: impl Fn(i64) -> i64,
+) -> i64 {
+    let (mut low, mut high) = (min_offset, max_offset);
+    while low < high {
+        let mid = low + (high - low) / 2;
+        let mid_time = store_time(mid);
+        if mid_time < 0 {
+            // unreadable unit, most likely already cleaned, search the newer half
+            low = mid + 1;
+        } else if mid_time < timestamp {
+            low = mid + 1;
+        } else {
+            high = mid;
+        }
+    }
+    low
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn subscription(group: &str, expire_at_millis: i64) -> ReplaySubscription {
+        ReplaySubscription {
+            consumer_group: group.into(),
+            topic: "TopicA".into(),
+            start_offsets: HashMap::from([(0, 10)]),
+            end_offsets: HashMap::from([(0, 20)]),
+            expire_at_millis,
+        }
+    }
+
+    #[test]
+    fn expired_subscriptions_are_taken_once() {
+        let manager = ReplaySubscriptionManager::default();
+        assert!(manager.register(subscription("g1", 100)));
+        assert!(!manager.register(subscription("g1", 300)));
+        assert!(manager.register(subscription("g2", 200)));
+
+        assert_eq!(
+            manager.end_offset(&"g1".into(), &"TopicA".into(), 0),
+            Some(20)
+        );
+        assert_eq!(manager.end_offset(&"g1".into(), &"TopicB".into(), 0), None);
+
+        let expired = manager.take_expired(150);
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].consumer_group, CheetahString::from("g1"));
+        assert!(manager.take_expired(150).is_empty());
+        assert!(manager.is_replay_group(&"g2".into()));
+    }
+
+    #[test]
+    fn lower_bound_finds_first_message_not_older_than_timestamp() {
+        let times = [100, 200, 200, 300, 400];
+        let store_time = |offset: i64| times[offset as usize];
+        assert_eq!(lower_bound_by_store_time(0, 5, 50, store_time), 0);
+        assert_eq!(lower_bound_by_store_time(0, 5, 200, store_time), 1);
+        assert_eq!(lower_bound_by_store_time(0, 5, 250, store_time), 3);
+        assert_eq!(lower_bound_by_store_time(0, 5, 500, store_time), 5);
+    }
+}
@@ -439,8 +439,8 @@ impl TopicConfigManager {
                 .into_iter()
                 .map(|(k, v)| (k.into(), v))
                 .collect(),
-            new_attributes,
             current_attributes,
+            new_attributes,
         );
         topic_config.attributes = final_attributes;
         match self.put_topic_config(topic_config.clone()) {
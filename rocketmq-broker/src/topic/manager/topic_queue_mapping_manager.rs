@@ -39,6 +39,10 @@ pub(crate) struct TopicQueueMappingManager {
     pub(crate) data_version: parking_lot::Mutex<DataVersion>,
     pub(crate) topic_queue_mapping_table:
         parking_lot::Mutex<HashMap<CheetahString /* topic */, TopicQueueMappingDetail>>,
+    /// Logic offsets at which writes to a physical queue stop while the queue is remapped to
+    /// another broker, the new leader item starts at that offset.
+    pub(crate) queue_write_fences:
+        parking_lot::Mutex<HashMap<CheetahString /* topic */, HashMap<i32 /* queue id */, i64>>>,
     pub(crate) broker_config: Arc<BrokerConfig>,
 }
 
@@ -190,12 +194,86 @@ impl TopicQueueMappingManager {
         }
     }
 
+    /// Stops accepting writes to `queue_id` once its max offset reaches `fence_offset`.
+    pub(crate) fn fence_queue_write(
+        &self,
+        topic: &CheetahString,
+        queue_id: i32,
+        fence_offset: i64,
+    ) {
+        info!(
+            "fence writes of static topic queue {}-{} at offset {}",
+            topic, queue_id, fence_offset
+        );
+        self.queue_write_fences
+            .lock()
+            .entry(topic.clone())
+            .or_default()
+            .insert(queue_id, fence_offset);
+    }
+
+    pub(crate) fn unfence_queue_write(&self, topic: &CheetahString, queue_id: i32) -> Option<i64> {
+        let mut fences = self.queue_write_fences.lock();
+        let queues = fences.get_mut(topic)?;
+        let old = queues.remove(&queue_id);
+        if queues.is_empty() {
+            fences.remove(topic);
+        }
+        if let Some(fence_offset) = old {
+            info!(
+                "unfence writes of static topic queue {}-{}, fence offset was {}",
+                topic, queue_id, fence_offset
+            );
+        }
+        old
+    }
+
+    pub(crate) fn queue_write_fence(&self, topic: &str, queue_id: i32) -> Option<i64> {
+        self.queue_write_fences
+            .lock()
+            .get(topic)
+            .and_then(|queues| queues.get(&queue_id).copied())
+    }
+
+    pub(crate) fn queue_write_fences(&self, topic: &str) -> HashMap<i32, i64> {
+        self.queue_write_fences
+            .lock()
+            .get(topic)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Rejects a write of `message_num` messages that would move the queue past its fence,
+    /// otherwise the old and the new leader of the queue would hand out the same offsets.
+    pub(crate) fn check_queue_write_fence(
+        &self,
+        topic: &CheetahString,
+        queue_id: i32,
+        max_offset_in_queue: impl FnOnce() -> i64,
+        message_num: impl FnOnce() -> i64,
+    ) -> Option<RemotingCommand> {
+        let fence_offset = self.queue_write_fence(topic.as_str(), queue_id)?;
+        let max_offset = max_offset_in_queue();
+        let message_num = message_num().max(1);
+        if max_offset + message_num <= fence_offset {
+            return None;
+        }
+        Some(RemotingCommand::create_response_command_with_code_remark(
+            ResponseCode::NotLeaderForQueue,
+            format!(
+                "{}-{} is fenced at offset {} for remapping, current max offset {}",
+                topic, queue_id, fence_offset, max_offset
+            ),
+        ))
+    }
+
     pub fn get_topic_queue_mapping(&self, topic: &str) -> Option<TopicQueueMappingDetail> {
         self.topic_queue_mapping_table.lock().get(topic).cloned()
     }
 
     pub fn delete(&self, topic: &CheetahString) {
         let old = self.topic_queue_mapping_table.lock().remove(topic);
+        self.queue_write_fences.lock().remove(topic);
         match old {
             None => {
                 warn!(
@@ -296,6 +374,33 @@ mod tests {
         assert!(manager.get_topic_queue_mapping("existing_topic").is_some());
     }
 
+    #[test]
+    fn queue_write_fence_rejects_writes_past_fence_offset() {
+        let manager = TopicQueueMappingManager::new(Arc::new(BrokerConfig::default()));
+        let topic = CheetahString::from_static_str("static_topic");
+        assert!(manager
+            .check_queue_write_fence(&topic, 0, || 100, || 1)
+            .is_none());
+
+        manager.fence_queue_write(&topic, 0, 100);
+        assert!(manager
+            .check_queue_write_fence(&topic, 0, || 98, || 2)
+            .is_none());
+        let response = manager
+            .check_queue_write_fence(&topic, 0, || 99, || 2)
+            .unwrap();
+        assert_eq!(response.code(), ResponseCode::NotLeaderForQueue as i32);
+        assert!(manager
+            .check_queue_write_fence(&topic, 1, || 1000, || 1)
+            .is_none());
+
+        assert_eq!(manager.unfence_queue_write(&topic, 0), Some(100));
+        assert!(manager.queue_write_fences(topic.as_str()).is_empty());
+        assert!(manager
+            .check_queue_write_fence(&topic, 0, || 1000, || 1)
+            .is_none());
+    }
+
     #[test]
     fn delete_removes_existing_topic() {
         let broker_config = Arc::new(BrokerConfig::default());
@@ -257,6 +257,14 @@ impl TopicRouteInfoManager {
         topic_publish_info
     }
 
+    /// Returns whether every broker serving `topic` reported `feature`, unknown topics support
+    /// no feature.
+    pub fn is_broker_feature_supported(&self, topic: &CheetahString, feature: u64) -> bool {
+        self.topic_route_table
+            .get(topic)
+            .is_some_and(|topic_route_data| topic_route_data.supports_feature(feature))
+    }
+
     pub fn find_broker_address_in_publish(
         &self,
         broker_name: Option<&CheetahString>,
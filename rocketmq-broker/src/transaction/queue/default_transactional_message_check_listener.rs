@@ -70,9 +70,13 @@ impl<MS> DefaultTransactionalMessageCheckListener<MS> {
 
 impl<MS> TransactionalMessageCheckListener for DefaultTransactionalMessageCheckListener<MS>
 where
-    MS: MessageStore,
+    MS: MessageStore + Send + Sync + 'static,
 {
-    async fn resolve_discard_msg(&mut self, msg_ext: MessageExt) {
+    fn resolve_half_msg(&self, msg_ext: MessageExt) {
+        let _ = self.inner.resolve_half_msg(msg_ext);
+    }
+
+    async fn resolve_discard_msg(&self, msg_ext: MessageExt) {
         error!(
             "MsgExt:{} has been checked too many times, so discard it by moving it to system \
              topic TRANS_CHECK_MAXTIME_TOPIC",
@@ -81,13 +85,18 @@ where
 
         let topic_config = self
             .topic_config_manager
+            .clone()
             .create_topic_of_tran_check_max_time(
                 TCMT_QUEUE_NUMS,
                 PermName::PERM_READ | PermName::PERM_WRITE,
             )
             .expect("Create topic of tran check max time failed");
         let broker_inner = to_message_ext_broker_inner(&topic_config, &msg_ext);
-        let put_message_result = self.message_store.put_message(broker_inner).await;
+        let put_message_result = self
+            .message_store
+            .mut_from_ref()
+            .put_message(broker_inner)
+            .await;
 
         if put_message_result.put_message_status() == PutMessageStatus::PutOk {
             info!(
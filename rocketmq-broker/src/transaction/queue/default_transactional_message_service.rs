@@ -15,27 +15,40 @@
  * limitations under the License.
  */
 use std::collections::HashMap;
-use std::sync::Arc;
+use std::collections::HashSet;
 use std::time::Duration;
 
+use cheetah_string::CheetahString;
+use rocketmq_client_rust::consumer::pull_result::PullResult;
+use rocketmq_client_rust::consumer::pull_status::PullStatus;
 use rocketmq_common::common::message::message_ext::MessageExt;
 use rocketmq_common::common::message::message_ext_broker_inner::MessageExtBrokerInner;
+use rocketmq_common::common::message::message_queue::MessageQueue;
 use rocketmq_common::common::message::message_single::Message;
+use rocketmq_common::common::message::MessageConst;
+use rocketmq_common::common::message::MessageTrait;
+use rocketmq_common::MessageAccessor::MessageAccessor;
 use rocketmq_common::TimeUtils::get_current_millis;
 use rocketmq_remoting::code::response_code::ResponseCode;
 use rocketmq_remoting::protocol::header::end_transaction_request_header::EndTransactionRequestHeader;
+use rocketmq_rust::ArcMut;
 use rocketmq_store::base::message_result::PutMessageResult;
+use rocketmq_store::base::message_status_enum::PutMessageStatus;
 use rocketmq_store::log_file::MessageStore;
 use tokio::sync::Mutex;
+use tracing::debug;
 use tracing::error;
+use tracing::info;
 use tracing::warn;
 
 use crate::transaction::operation_result::OperationResult;
+use crate::transaction::queue::get_result::GetResult;
 use crate::transaction::queue::message_queue_op_context::MessageQueueOpContext;
 use crate::transaction::queue::transactional_message_bridge::TransactionalMessageBridge;
 use crate::transaction::queue::transactional_message_util::TransactionalMessageUtil;
 use crate::transaction::queue::transactional_op_batch_service::TransactionalOpBatchService;
 use crate::transaction::transaction_metrics::TransactionMetrics;
+use crate::transaction::transactional_message_check_listener::TransactionalMessageCheckListener;
 use crate::transaction::transactional_message_service::TransactionalMessageService;
 
 const PULL_MSG_RETRY_NUMBER: i32 = 1;
@@ -47,7 +60,7 @@ const SLEEP_WHILE_NO_OP: i32 = 1000;
 
 pub struct DefaultTransactionalMessageService<MS> {
     transactional_message_bridge: TransactionalMessageBridge<MS>,
-    delete_context: Arc<Mutex<HashMap<i32, MessageQueueOpContext>>>,
+    delete_context: Mutex<HashMap<i32, MessageQueueOpContext>>,
     transactional_op_batch_service: TransactionalOpBatchService,
     transaction_metrics: TransactionMetrics,
 }
@@ -59,12 +72,22 @@ where
     pub fn new(transactional_message_bridge: TransactionalMessageBridge<MS>) -> Self {
         Self {
             transactional_message_bridge,
-            delete_context: Arc::new(Mutex::new(HashMap::new())),
+            delete_context: Mutex::new(HashMap::new()),
             transactional_op_batch_service: TransactionalOpBatchService::new(),
-            transaction_metrics: TransactionMetrics,
+            transaction_metrics: TransactionMetrics::default(),
         }
     }
 
+    #[inline]
+    pub fn transactional_message_bridge(&self) -> &TransactionalMessageBridge<MS> {
+        &self.transactional_message_bridge
+    }
+
+    #[inline]
+    pub fn transactional_message_bridge_mut(&mut self) -> &mut TransactionalMessageBridge<MS> {
+        &mut self.transactional_message_bridge
+    }
+
     fn get_half_message_by_offset(&self, offset: i64) -> OperationResult {
         let message_ext = self
             .transactional_message_bridge
@@ -85,61 +108,568 @@ where
         }
     }
 
+    /// Drains the queued op offsets of `queue_id` into a single op message, prefixed with
+    /// `more_data` when the caller could not queue its own offset.
     pub async fn get_op_message(
         &self,
         queue_id: i32,
         more_data: Option<String>,
     ) -> Option<Message> {
-        let topic = TransactionalMessageUtil::build_op_topic();
         let delete_context = self.delete_context.lock().await;
         let mq_context = delete_context.get(&queue_id)?;
+        drain_op_message(
+            mq_context,
+            more_data,
+            self.transactional_message_bridge
+                .broker_config
+                .transaction_op_msg_max_size as usize,
+        )
+        .await
+    }
 
-        let more_data_length = if let Some(ref data) = more_data {
-            data.len()
-        } else {
+    /// Writes the pending op offsets of every queue whose batch is full or whose batch interval
+    /// elapsed, and returns the timestamp the op batch service should wake up at next.
+    pub async fn batch_send_op_message(&self) -> u64 {
+        let start_time = get_current_millis();
+        let broker_config = &self.transactional_message_bridge.broker_config;
+        let interval = broker_config.transaction_op_batch_interval;
+        let max_size = broker_config.transaction_op_msg_max_size;
+        let mut first_timestamp = start_time;
+        let mut over_size = false;
+        let mut send_map = HashMap::new();
+        {
+            let delete_context = self.delete_context.lock().await;
+            for (queue_id, mq_context) in delete_context.iter() {
+                let total_size = mq_context.get_total_size();
+                let last_write_timestamp = mq_context.get_last_write_timestamp().await;
+                if total_size <= 0
+                    || mq_context.context_queue().is_empty().await
+                    || (total_size < max_size
+                        && start_time.saturating_sub(last_write_timestamp) < interval)
+                {
+                    continue;
+                }
+                let Some(op_message) = drain_op_message(mq_context, None, max_size as usize).await
+                else {
+                    continue;
+                };
+                send_map.insert(*queue_id, op_message);
+                first_timestamp = first_timestamp.min(last_write_timestamp);
+                if total_size >= max_size {
+                    over_size = true;
+                }
+            }
+        }
+        let queue_count = send_map.len();
+        for (queue_id, op_message) in send_map {
+            if !self
+                .transactional_message_bridge
+                .write_op(queue_id, op_message)
+                .await
+            {
+                error!(
+                    "Transaction batch op message write failed. queueId={}",
+                    queue_id
+                );
+            }
+        }
+        debug!(
+            "Send op message queueIds={} cost={}",
+            queue_count,
+            get_current_millis().saturating_sub(start_time)
+        );
+        if over_size {
             0
-        };
-        let mut length = more_data_length;
-        let max_size = self
+        } else {
+            first_timestamp + interval
+        }
+    }
+
+    /// Starts the op batch service writing queued op offsets of `this` to the op half topic.
+    pub fn start_op_batch_service(this: ArcMut<Self>)
+    where
+        MS: Send + Sync + 'static,
+    {
+        let interval = this
             .transactional_message_bridge
             .broker_config
-            .transaction_op_msg_max_size as usize;
-        if length < max_size {
-            let sz = mq_context.get_total_size() as usize;
-            if sz > max_size || length + sz > max_size {
-                length = max_size + 100;
+            .transaction_op_batch_interval;
+        this.transactional_op_batch_service
+            .clone()
+            .start(this, interval);
+    }
+
+    /// Returns the op queue paired with the half queue `message_queue`.
+    pub async fn get_op_queue(&self, message_queue: &MessageQueue) -> MessageQueue {
+        let mut op_queue_map = self.transactional_message_bridge.op_queue_map.lock().await;
+        op_queue_map
+            .entry(message_queue.get_queue_id())
+            .or_insert_with(|| {
+                MessageQueue::from_parts(
+                    TransactionalMessageUtil::build_op_topic(),
+                    message_queue.get_broker_name().clone(),
+                    message_queue.get_queue_id(),
+                )
+            })
+            .clone()
+    }
+
+    /// Reads the half message stored at `offset` of `message_queue`.
+    pub async fn get_half_msg(&self, message_queue: &MessageQueue, offset: i64) -> GetResult {
+        let pull_result = self
+            .transactional_message_bridge
+            .get_half_message(message_queue.get_queue_id(), offset, PULL_MSG_RETRY_NUMBER)
+            .await;
+        let msg = pull_result.as_ref().and_then(|pull_result| {
+            pull_result
+                .msg_found_list
+                .first()
+                .map(|msg| msg.message_ext_inner.clone())
+        });
+        GetResult { msg, pull_result }
+    }
+
+    /// Pulls op messages of `op_queue` starting at `pull_offset_of_op` and records which half
+    /// offsets they remove.
+    ///
+    /// Every removed half offset not below `mini_offset` is mapped to the op offset removing it
+    /// in `remove_map`, and grouped per op offset in `op_msg_map`. Op messages removing nothing
+    /// are appended to `done_op_offset`.
+    pub async fn fill_op_remove_map(
+        &self,
+        remove_map: &mut HashMap<i64, i64>,
+        op_queue: &MessageQueue,
+        pull_offset_of_op: i64,
+        mini_offset: i64,
+        op_msg_map: &mut HashMap<i64, HashSet<i64>>,
+        done_op_offset: &mut Vec<i64>,
+    ) -> Option<PullResult> {
+        let pull_result = self
+            .transactional_message_bridge
+            .get_op_message(op_queue.get_queue_id(), pull_offset_of_op, OP_MSG_PULL_NUMS)
+            .await?;
+        match pull_result.pull_status {
+            PullStatus::OffsetIllegal | PullStatus::NoMatchedMsg => {
+                warn!(
+                    "The miss op offset={} in queue={} is illegal, pullResult={}",
+                    pull_offset_of_op, op_queue, pull_result
+                );
+                self.transactional_message_bridge
+                    .update_consume_offset(op_queue, pull_result.next_begin_offset as i64);
+                return Some(pull_result);
+            }
+            PullStatus::NoNewMsg => return Some(pull_result),
+            PullStatus::Found => {}
+        }
+        for op_message in &pull_result.msg_found_list {
+            let op_message = &op_message.message_ext_inner;
+            let Some(body) = op_message.get_body() else {
+                done_op_offset.push(op_message.queue_offset);
+                continue;
+            };
+            let mut set = HashSet::new();
+            if op_message
+                .get_tags()
+                .is_some_and(|tags| tags.as_str() == TransactionalMessageUtil::REMOVE_TAG)
+            {
+                for offset in String::from_utf8_lossy(body)
+                    .split(TransactionalMessageUtil::OFFSET_SEPARATOR)
+                    .filter(|offset| !offset.is_empty())
+                {
+                    let offset_value = offset.trim().parse::<i64>().unwrap_or(-1);
+                    if offset_value < mini_offset {
+                        continue;
+                    }
+                    remove_map.insert(offset_value, op_message.queue_offset);
+                    set.insert(offset_value);
+                }
+            } else {
+                error!("Found a illegal tag in opMessageExt= {} ", op_message);
+            }
+            if set.is_empty() {
+                done_op_offset.push(op_message.queue_offset);
             } else {
-                length += sz;
+                op_msg_map.insert(op_message.queue_offset, set);
             }
         }
+        Some(pull_result)
+    }
 
-        let mut sb = String::with_capacity(length);
+    /// Appends `msg_ext` to the end of the half queue again so it is checked later.
+    pub async fn put_back_half_msg(&self, msg_ext: &MessageExt) -> PutMessageResult {
+        let message_inner = TransactionalMessageBridge::<MS>::renew_half_message_inner(msg_ext);
+        self.transactional_message_bridge
+            .put_message_return_result(message_inner)
+            .await
+    }
 
-        if let Some(data) = more_data {
-            sb.push_str(&data);
+    /// Appends `msg_ext` to the end of the half queue again, remembering its original queue
+    /// offset so the immunity time keeps counting from the first prepare.
+    pub async fn put_immunity_msg_back_to_half_queue(&self, msg_ext: &MessageExt) -> bool {
+        let message_inner =
+            TransactionalMessageBridge::<MS>::renew_immunity_half_message_inner(msg_ext);
+        self.transactional_message_bridge
+            .put_message_return_result(message_inner)
+            .await
+            .put_message_status()
+            == PutMessageStatus::PutOk
+    }
+}
+
+impl<MS> DefaultTransactionalMessageService<MS>
+where
+    MS: MessageStore + Send + Sync + 'static,
+{
+    /// Scans one half queue from its committed offset, skipping half messages removed by op
+    /// messages and checking back the ones whose immunity time elapsed. Both the half queue and
+    /// its op queue offsets are committed afterwards.
+    async fn check_half_queue<L>(
+        &self,
+        message_queue: &MessageQueue,
+        transaction_timeout: u64,
+        transaction_check_max: i32,
+        listener: &L,
+    ) where
+        L: TransactionalMessageCheckListener,
+    {
+        let start_time = get_current_millis();
+        let bridge = &self.transactional_message_bridge;
+        let op_queue = self.get_op_queue(message_queue).await;
+        let half_offset = bridge.fetch_consume_offset(message_queue);
+        let op_offset = bridge.fetch_consume_offset(&op_queue);
+        info!(
+            "Before check, the queue={} msgOffset={} opOffset={}",
+            message_queue, half_offset, op_offset
+        );
+        if half_offset < 0 || op_offset < 0 {
+            error!(
+                "MessageQueue: {} illegal offset read: {}, op offset: {},skip this queue",
+                message_queue, half_offset, op_offset
+            );
+            return;
         }
 
-        while !mq_context.context_queue().is_empty().await {
-            if sb.len() >= max_size {
+        let mut done_op_offset = Vec::new();
+        let mut remove_map = HashMap::new();
+        let mut op_msg_map: HashMap<i64, HashSet<i64>> = HashMap::new();
+        let Some(mut pull_result) = self
+            .fill_op_remove_map(
+                &mut remove_map,
+                &op_queue,
+                op_offset,
+                half_offset,
+                &mut op_msg_map,
+                &mut done_op_offset,
+            )
+            .await
+        else {
+            error!(
+                "The queue={} check msgOffset={} with opOffset={} failed, pullResult is null",
+                message_queue, half_offset, op_offset
+            );
+            return;
+        };
+
+        let mut get_message_null_count = 1;
+        let mut new_offset = half_offset;
+        let mut i = half_offset;
+        loop {
+            if get_current_millis().saturating_sub(start_time) > MAX_PROCESS_TIME_LIMIT as u64 {
+                info!(
+                    "Queue={} process time reach max={}",
+                    message_queue, MAX_PROCESS_TIME_LIMIT
+                );
                 break;
             }
-            {
-                if let Some(data) = mq_context.context_queue().try_poll().await {
-                    sb.push_str(&data);
+            if let Some(removed_op_offset) = remove_map.remove(&i) {
+                debug!("Half offset {} has been committed/rolled back", i);
+                if let Some(set) = op_msg_map.get_mut(&removed_op_offset) {
+                    set.remove(&i);
+                    if set.is_empty() {
+                        op_msg_map.remove(&removed_op_offset);
+                        done_op_offset.push(removed_op_offset);
+                    }
+                }
+            } else {
+                let get_result = self.get_half_msg(message_queue, i).await;
+                let Some(mut msg_ext) = get_result.msg else {
+                    if get_message_null_count > MAX_RETRY_COUNT_WHEN_HALF_NULL {
+                        break;
+                    }
+                    get_message_null_count += 1;
+                    match get_result.pull_result {
+                        Some(pull_result) if pull_result.pull_status != PullStatus::NoNewMsg => {
+                            info!(
+                                "Illegal offset, the messageQueue={}, illegal offset={} status={}",
+                                message_queue, i, pull_result.pull_status
+                            );
+                            i = pull_result.next_begin_offset as i64;
+                            new_offset = i;
+                            continue;
+                        }
+                        _ => {
+                            debug!(
+                                "No new msg, the miss offset={} in={}, continue check={}",
+                                i, message_queue, get_message_null_count
+                            );
+                            break;
+                        }
+                    }
+                };
+
+                if need_discard(&mut msg_ext, transaction_check_max)
+                    || need_skip(&msg_ext, bridge.message_store_config.file_reserved_time)
+                {
+                    self.transaction_metrics.record_orphaned(&msg_ext);
+                    listener.resolve_discard_msg(msg_ext).await;
+                    new_offset = i + 1;
+                    i += 1;
+                    continue;
+                }
+                if msg_ext.store_timestamp as u64 >= start_time {
+                    debug!(
+                        "Fresh stored. the miss offset={}, check it later, store={}",
+                        i, msg_ext.store_timestamp
+                    );
+                    break;
+                }
+
+                let value_of_current_minus_born =
+                    get_current_millis() as i64 - msg_ext.born_timestamp;
+                let mut check_immunity_time = transaction_timeout as i64;
+                let check_immunity_time_str =
+                    msg_ext.get_user_property(&CheetahString::from_static_str(
+                        MessageConst::PROPERTY_CHECK_IMMUNITY_TIME_IN_SECONDS,
+                    ));
+                if let Some(check_immunity_time_str) = check_immunity_time_str {
+                    check_immunity_time = TransactionalMessageUtil::get_immunity_time(
+                        check_immunity_time_str.as_str(),
+                        transaction_timeout,
+                    ) as i64;
+                    if value_of_current_minus_born < check_immunity_time
+                        && self
+                            .check_prepare_queue_offset(
+                                &mut remove_map,
+                                &mut done_op_offset,
+                                &msg_ext,
+                            )
+                            .await
+                    {
+                        new_offset = i + 1;
+                        i += 1;
+                        continue;
+                    }
+                } else if 0 <= value_of_current_minus_born
+                    && value_of_current_minus_born < check_immunity_time
+                {
+                    debug!(
+                        "New arrived, the miss offset={}, check it later checkImmunity={}, born={}",
+                        i, check_immunity_time, msg_ext.born_timestamp
+                    );
+                    break;
+                }
+
+                let op_msg = &pull_result.msg_found_list;
+                let is_need_check = (op_msg.is_empty()
+                    && value_of_current_minus_born > check_immunity_time)
+                    || op_msg.last().is_some_and(|op| {
+                        op.message_ext_inner.born_timestamp - start_time as i64
+                            > transaction_timeout as i64
+                    })
+                    || value_of_current_minus_born <= -1;
+                if is_need_check {
+                    if self.put_back_half_msg(&msg_ext).await.put_message_status()
+                        != PutMessageStatus::PutOk
+                    {
+                        continue;
+                    }
+                    info!(
+                        "Check transaction. \
+                         real_topic={:?},uniqKey={:?},offset={},commitLogOffset={}",
+                        msg_ext.get_user_property(&CheetahString::from_static_str(
+                            MessageConst::PROPERTY_REAL_TOPIC
+                        )),
+                        msg_ext.get_user_property(&CheetahString::from_static_str(
+                            MessageConst::PROPERTY_UNIQ_CLIENT_MESSAGE_ID_KEYIDX
+                        )),
+                        msg_ext.queue_offset,
+                        msg_ext.commit_log_offset
+                    );
+                    listener.resolve_half_msg(msg_ext);
+                } else {
+                    let next_op_offset = pull_result.next_begin_offset as i64;
+                    match self
+                        .fill_op_remove_map(
+                            &mut remove_map,
+                            &op_queue,
+                            next_op_offset,
+                            half_offset,
+                            &mut op_msg_map,
+                            &mut done_op_offset,
+                        )
+                        .await
+                    {
+                        Some(next_pull_result) => {
+                            if next_pull_result.pull_status != PullStatus::Found {
+                                tokio::time::sleep(Duration::from_millis(SLEEP_WHILE_NO_OP as u64))
+                                    .await;
+                            } else {
+                                info!(
+                                    "The miss messageQueue={} pullOpMsg={} opOffset={}",
+                                    message_queue, next_pull_result, next_op_offset
+                                );
+                            }
+                            pull_result = next_pull_result;
+                        }
+                        None => {
+                            tokio::time::sleep(Duration::from_millis(SLEEP_WHILE_NO_OP as u64))
+                                .await;
+                        }
+                    }
+                    continue;
                 }
             }
+            new_offset = i + 1;
+            i += 1;
         }
 
-        if sb.is_empty() {
-            return None;
+        if new_offset != half_offset {
+            bridge.update_consume_offset(message_queue, new_offset);
+        }
+        let new_op_offset = calculate_op_offset(&mut done_op_offset, op_offset);
+        if new_op_offset != op_offset {
+            bridge.update_consume_offset(&op_queue, new_op_offset);
+        }
+        info!(
+            "After check, {} opOffset={} opOffsetDiff={} msgOffset={} msgOffsetDiff={}",
+            message_queue,
+            new_op_offset,
+            new_op_offset - op_offset,
+            new_offset,
+            new_offset - half_offset
+        );
+    }
+
+    /// Resolves a half message put back to the half queue within its immunity time. When the
+    /// original prepare offset has already been removed by an op message the op offset is done,
+    /// otherwise the message is put back once more.
+    async fn check_prepare_queue_offset(
+        &self,
+        remove_map: &mut HashMap<i64, i64>,
+        done_op_offset: &mut Vec<i64>,
+        msg_ext: &MessageExt,
+    ) -> bool {
+        let prepare_queue_offset_str = msg_ext.get_user_property(&CheetahString::from_static_str(
+            MessageConst::PROPERTY_TRANSACTION_PREPARED_QUEUE_OFFSET,
+        ));
+        let Some(prepare_queue_offset_str) = prepare_queue_offset_str else {
+            return self.put_immunity_msg_back_to_half_queue(msg_ext).await;
+        };
+        let Ok(prepare_queue_offset) = prepare_queue_offset_str.as_str().parse::<i64>() else {
+            return false;
+        };
+        if let Some(tmp_op_offset) = remove_map.remove(&prepare_queue_offset) {
+            done_op_offset.push(tmp_op_offset);
+            true
+        } else {
+            self.put_immunity_msg_back_to_half_queue(msg_ext).await
+        }
+    }
+}
+
+/// Returns whether `msg_ext` has been checked `transaction_check_max` times, otherwise records
+/// one more check in its properties.
+fn need_discard(msg_ext: &mut MessageExt, transaction_check_max: i32) -> bool {
+    let check_times = msg_ext.get_property(&CheetahString::from_static_str(
+        MessageConst::PROPERTY_TRANSACTION_CHECK_TIMES,
+    ));
+    let mut check_time = 1;
+    if let Some(check_times) = check_times {
+        check_time = check_times.as_str().parse::<i32>().unwrap_or(-1);
+        if check_time >= transaction_check_max {
+            return true;
         }
+        check_time += 1;
+    }
+    MessageAccessor::put_property(
+        msg_ext,
+        CheetahString::from_static_str(MessageConst::PROPERTY_TRANSACTION_CHECK_TIMES),
+        CheetahString::from_string(check_time.to_string()),
+    );
+    false
+}
 
-        Some(Message::with_tags(
-            topic,
-            TransactionalMessageUtil::REMOVE_TAG,
-            sb.as_bytes(),
-        ))
+/// Returns whether `msg_ext` is older than the commit log reserved time, in which case its body
+/// may already be gone and checking it back is pointless.
+fn need_skip(msg_ext: &MessageExt, file_reserved_time: usize) -> bool {
+    if file_reserved_time == 0 {
+        return false;
+    }
+    let value_of_current_minus_born = get_current_millis() as i64 - msg_ext.born_timestamp;
+    if value_of_current_minus_born > (file_reserved_time as i64) * 3600 * 1000 {
+        info!(
+            "Half message exceed file reserved time ,so skip it.messageId {},bornTime {}",
+            msg_ext.msg_id, msg_ext.born_timestamp
+        );
+        return true;
+    }
+    false
+}
+
+/// Returns the op offset the op queue can be committed to, i.e. `old_offset` advanced over the
+/// leading run of consecutive offsets in `done_offset`.
+pub(crate) fn calculate_op_offset(done_offset: &mut [i64], old_offset: i64) -> i64 {
+    done_offset.sort_unstable();
+    let mut new_offset = old_offset;
+    for offset in done_offset.iter() {
+        if *offset == new_offset {
+            new_offset += 1;
+        } else {
+            break;
+        }
     }
+    new_offset
+}
+
+async fn drain_op_message(
+    mq_context: &MessageQueueOpContext,
+    more_data: Option<String>,
+    max_size: usize,
+) -> Option<Message> {
+    let more_data_length = more_data.as_ref().map_or(0, |data| data.len());
+    let mut length = more_data_length;
+    if length < max_size {
+        let sz = mq_context.get_total_size().max(0) as usize;
+        if sz > max_size || length + sz > max_size {
+            length = max_size + 100;
+        } else {
+            length += sz;
+        }
+    }
+
+    let mut sb = String::with_capacity(length);
+    if let Some(data) = more_data {
+        sb.push_str(&data);
+    }
+    while sb.len() < max_size {
+        match mq_context.context_queue().try_poll().await {
+            Some(data) => sb.push_str(&data),
+            None => break,
+        }
+    }
+    if sb.is_empty() {
+        return None;
+    }
+    mq_context.total_size_add_and_get(-((sb.len() - more_data_length) as i32));
+    mq_context
+        .set_last_write_timestamp(get_current_millis())
+        .await;
+
+    Some(Message::with_tags(
+        TransactionalMessageUtil::build_op_topic(),
+        TransactionalMessageUtil::REMOVE_TAG,
+        sb.as_bytes(),
+    ))
 }
 
 impl<MS> TransactionalMessageService for DefaultTransactionalMessageService<MS>
@@ -162,39 +692,43 @@ where
     }
 
     async fn delete_prepare_message(&mut self, message_ext: &MessageExt) -> bool {
+        self.transaction_metrics
+            .resolve_orphaned(message_ext.commit_log_offset);
         let queue_id = message_ext.queue_id;
-        let mut delete_context = self.delete_context.lock().await;
-        let mq_context = delete_context
-            .entry(queue_id)
-            .or_insert(MessageQueueOpContext::new(get_current_millis(), 20000));
         let data = format!(
             "{}{}",
             message_ext.queue_offset,
             TransactionalMessageUtil::OFFSET_SEPARATOR
         );
         let len = data.len();
-        let res = mq_context
-            .context_queue()
-            .offer(data.clone(), Duration::from_millis(100))
-            .await;
-        if res {
-            let total_size = mq_context.total_size_add_and_get(len as i32);
-            if total_size
-                > self
-                    .transactional_message_bridge
-                    .broker_config
-                    .transaction_op_msg_max_size
-            {
-                self.transactional_op_batch_service.wakeup();
+        let max_size = self
+            .transactional_message_bridge
+            .broker_config
+            .transaction_op_msg_max_size;
+        let msg = {
+            let mut delete_context = self.delete_context.lock().await;
+            let mq_context = delete_context
+                .entry(queue_id)
+                .or_insert_with(|| MessageQueueOpContext::new(get_current_millis(), 20000));
+            let res = mq_context
+                .context_queue()
+                .offer(data.clone(), Duration::from_millis(100))
+                .await;
+            if res {
+                if mq_context.total_size_add_and_get(len as i32) > max_size {
+                    self.transactional_op_batch_service.wakeup();
+                }
+                return true;
             }
-            return true;
-        } else {
             self.transactional_op_batch_service.wakeup();
-        }
-        let msg = self.get_op_message(queue_id, Some(data)).await;
+            drain_op_message(mq_context, Some(data), max_size as usize).await
+        };
         if self
             .transactional_message_bridge
-            .write_op(queue_id, msg.expect("message is none"))
+            .write_op(
+                queue_id,
+                msg.expect("op message contains the offset to remove"),
+            )
             .await
         {
             warn!("Force add remove op data. queueId={}", queue_id);
@@ -221,8 +755,28 @@ where
         self.get_half_message_by_offset(request_header.commit_log_offset as i64)
     }
 
-    fn check(&self, _transaction_timeout: u64, _transaction_check_max: i32) {
-        todo!()
+    async fn check<L>(&mut self, transaction_timeout: u64, transaction_check_max: i32, listener: &L)
+    where
+        L: TransactionalMessageCheckListener,
+    {
+        let topic = CheetahString::from_static_str(TransactionalMessageUtil::build_half_topic());
+        let msg_queues = self
+            .transactional_message_bridge
+            .fetch_message_queues(&topic);
+        if msg_queues.is_empty() {
+            warn!("The queue of topic is empty :{}", topic);
+            return;
+        }
+        debug!("Check topic={}, queues={:?}", topic, msg_queues);
+        for message_queue in msg_queues {
+            self.check_half_queue(
+                &message_queue,
+                transaction_timeout,
+                transaction_check_max,
+                listener,
+            )
+            .await;
+        }
     }
 
     fn open(&self) -> bool {
@@ -230,14 +784,79 @@ where
     }
 
     fn close(&self) {
-        //nothing to do
+        self.transactional_op_batch_service.shutdown();
     }
 
     fn get_transaction_metrics(&self) -> &TransactionMetrics {
-        unimplemented!("get_transaction_metrics")
+        &self.transaction_metrics
     }
 
-    fn set_transaction_metrics(&mut self, _transaction_metrics: TransactionMetrics) {
-        unimplemented!("set_transaction_metrics")
+    fn set_transaction_metrics(&mut self, transaction_metrics: TransactionMetrics) {
+        self.transaction_metrics = transaction_metrics;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn calculate_op_offset_skips_leading_done_offsets() {
+        let mut done_offset = vec![12, 10, 11, 14];
+        assert_eq!(calculate_op_offset(&mut done_offset, 10), 13);
+        assert_eq!(calculate_op_offset(&mut done_offset, 9), 9);
+        assert_eq!(calculate_op_offset(&mut [], 5), 5);
+    }
+
+    #[test]
+    fn need_discard_counts_checks_up_to_max() {
+        let mut msg_ext = MessageExt::default();
+        assert!(!need_discard(&mut msg_ext, 2));
+        assert!(!need_discard(&mut msg_ext, 2));
+        assert!(need_discard(&mut msg_ext, 2));
+        assert_eq!(
+            msg_ext
+                .get_property(&CheetahString::from_static_str(
+                    MessageConst::PROPERTY_TRANSACTION_CHECK_TIMES
+                ))
+                .unwrap()
+                .as_str(),
+            "2"
+        );
+    }
+
+    #[test]
+    fn need_skip_only_past_file_reserved_time() {
+        let mut msg_ext = MessageExt::default();
+        msg_ext.born_timestamp = get_current_millis() as i64 - 2 * 3600 * 1000;
+        assert!(need_skip(&msg_ext, 1));
+        assert!(!need_skip(&msg_ext, 3));
+        assert!(!need_skip(&msg_ext, 0));
+    }
+
+    #[tokio::test]
+    async fn drain_op_message_joins_queued_offsets() {
+        let mq_context = MessageQueueOpContext::new(0, 16);
+        for offset in ["1,", "2,", "3,"] {
+            assert!(
+                mq_context
+                    .context_queue()
+                    .offer(offset.to_string(), Duration::from_millis(10))
+                    .await
+            );
+            mq_context.total_size_add_and_get(offset.len() as i32);
+        }
+
+        let message = drain_op_message(&mq_context, Some("0,".to_string()), 4096)
+            .await
+            .unwrap();
+        assert_eq!(&message.get_body().unwrap()[..], b"0,1,2,3,");
+        assert_eq!(
+            message.get_tags().unwrap().as_str(),
+            TransactionalMessageUtil::REMOVE_TAG
+        );
+        assert_eq!(mq_context.get_total_size(), 0);
+        assert!(mq_context.get_last_write_timestamp().await > 0);
+        assert!(drain_op_message(&mq_context, None, 4096).await.is_none());
     }
 }
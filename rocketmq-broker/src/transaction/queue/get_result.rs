@@ -18,6 +18,6 @@ use rocketmq_client_rust::consumer::pull_result::PullResult;
 use rocketmq_common::common::message::message_ext::MessageExt;
 
 pub(crate) struct GetResult {
-    pub(crate) msg: MessageExt,
-    pub(crate) pull_result: PullResult,
+    pub(crate) msg: Option<MessageExt>,
+    pub(crate) pull_result: Option<PullResult>,
 }
@@ -45,6 +45,7 @@ use rocketmq_store::base::get_message_result::GetMessageResult;
 use rocketmq_store::base::message_result::PutMessageResult;
 use rocketmq_store::base::message_status_enum::GetMessageStatus;
 use rocketmq_store::base::message_status_enum::PutMessageStatus;
+use rocketmq_store::config::message_store_config::MessageStoreConfig;
 use rocketmq_store::log_file::MessageStore;
 use rocketmq_store::log_file::MAX_PULL_MSG_SIZE;
 use rocketmq_store::stats::broker_stats_manager::BrokerStatsManager;
@@ -62,6 +63,7 @@ pub struct TransactionalMessageBridge<MS> {
     pub(crate) broker_stats_manager: Arc<BrokerStatsManager>,
     pub(crate) consumer_offset_manager: ConsumerOffsetManager,
     pub(crate) broker_config: Arc<BrokerConfig>,
+    pub(crate) message_store_config: Arc<MessageStoreConfig>,
     pub(crate) topic_config_manager: TopicConfigManager,
 }
 
@@ -74,9 +76,11 @@ where
         broker_stats_manager: Arc<BrokerStatsManager>,
         consumer_offset_manager: ConsumerOffsetManager,
         broker_config: Arc<BrokerConfig>,
+        message_store_config: Arc<MessageStoreConfig>,
         topic_config_manager: TopicConfigManager,
     ) -> Self {
-        let store_host = format!("{}:{}", broker_config.broker_ip1, broker_config.listen_port)
+        let store_host = broker_config
+            .get_broker_addr()
             .parse::<SocketAddr>()
             .expect("parse store host failed");
         Self {
@@ -86,6 +90,7 @@ where
             broker_stats_manager,
             consumer_offset_manager,
             broker_config,
+            message_store_config,
             topic_config_manager,
         }
     }
@@ -230,9 +235,7 @@ where
     fn decode_msg_list(get_message_result: &GetMessageResult) -> Vec<MessageClientExt> {
         let mut found_list = Vec::new();
         for bb in get_message_result.message_mapped_list() {
-            let data = &bb.mapped_file.as_ref().unwrap().get_mapped_file()
-                [bb.start_offset as usize..(bb.start_offset + bb.size as u64) as usize];
-            let mut bytes = Bytes::copy_from_slice(data);
+            let mut bytes = Bytes::copy_from_slice(bb.get_buffer());
             let msg_ext = message_decoder::decode_client(&mut bytes, true, false, false, false);
             if let Some(msg_ext) = msg_ext {
                 found_list.push(msg_ext);
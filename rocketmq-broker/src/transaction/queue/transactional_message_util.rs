@@ -64,9 +64,9 @@ impl TransactionalMessageUtil {
         if let Some(body) = msg_ext.get_body() {
             msg_inner.set_body(body.clone());
         }
-        if let Some(real_queue_id_str) =
-            msg_ext.get_property(&CheetahString::from_static_str("REAL_QUEUE_ID"))
-        {
+        if let Some(real_queue_id_str) = msg_ext.get_property(&CheetahString::from_static_str(
+            MessageConst::PROPERTY_REAL_QUEUE_ID,
+        )) {
             if let Ok(value) = real_queue_id_str.parse::<i32>() {
                 msg_inner.message_ext_inner.set_queue_id(value);
             }
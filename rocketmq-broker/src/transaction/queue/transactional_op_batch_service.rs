@@ -14,41 +14,69 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
 
 use rocketmq_common::TimeUtils::get_current_millis;
+use rocketmq_rust::ArcMut;
+use rocketmq_store::log_file::MessageStore;
 use tokio::sync::Notify;
 use tracing::info;
 
+use crate::transaction::queue::default_transactional_message_service::DefaultTransactionalMessageService;
+
+/// Background service flushing the queued op offsets of
+/// [`DefaultTransactionalMessageService`] to the op half topic, either when the batch interval
+/// elapses or as soon as a queue reaches `transaction_op_msg_max_size`.
 #[derive(Default, Clone)]
 pub struct TransactionalOpBatchService {
     notify: Arc<Notify>,
+    stopped: Arc<AtomicBool>,
 }
 
 impl TransactionalOpBatchService {
     pub fn new() -> Self {
         TransactionalOpBatchService {
             notify: Arc::new(Notify::new()),
+            stopped: Arc::new(AtomicBool::new(false)),
         }
     }
 
     pub fn wakeup(&self) {
-        self.notify.notify_waiters();
+        self.notify.notify_one();
     }
 
-    pub fn run(&self, transaction_op_batch_interval: u64) {
+    pub fn start<MS>(
+        &self,
+        transactional_message_service: ArcMut<DefaultTransactionalMessageService<MS>>,
+        transaction_op_batch_interval: u64,
+    ) where
+        MS: MessageStore + Send + Sync + 'static,
+    {
         let this = self.clone();
         tokio::spawn(async move {
             info!("TransactionalOpBatchService started");
-            let wakeup_timestamp = get_current_millis() + transaction_op_batch_interval;
-            loop {
-                let mut interval = (wakeup_timestamp as i64) - get_current_millis() as i64;
-                if interval <= 0 {
-                    interval = 0;
-                    this.wakeup();
+            let mut wakeup_timestamp = get_current_millis() + transaction_op_batch_interval;
+            while !this.stopped.load(Ordering::Acquire) {
+                let interval = wakeup_timestamp.saturating_sub(get_current_millis());
+                if interval > 0 {
+                    tokio::select! {
+                        _ = tokio::time::sleep(tokio::time::Duration::from_millis(interval)) => {}
+                        _ = this.notify.notified() => {}
+                    }
+                }
+                if this.stopped.load(Ordering::Acquire) {
+                    break;
                 }
-                tokio::time::sleep(tokio::time::Duration::from_millis(interval as u64)).await;
+                wakeup_timestamp = transactional_message_service.batch_send_op_message().await;
             }
+            info!("TransactionalOpBatchService end");
         });
     }
+
+    pub fn shutdown(&self) {
+        self.stopped.store(true, Ordering::Release);
+        self.notify.notify_one();
+    }
 }
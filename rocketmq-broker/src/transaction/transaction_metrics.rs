@@ -14,4 +14,146 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
-pub(crate) struct TransactionMetrics;
+
+use std::collections::BTreeMap;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+
+use cheetah_string::CheetahString;
+use rocketmq_common::common::message::message_ext::MessageExt;
+use rocketmq_common::common::message::MessageConst;
+use rocketmq_common::common::message::MessageTrait;
+use rocketmq_common::TimeUtils::get_current_millis;
+use rocketmq_remoting::protocol::body::orphaned_transaction_body::OrphanedTransactionBody;
+use rocketmq_remoting::protocol::body::orphaned_transaction_body::OrphanedTransactionInfo;
+use tracing::warn;
+
+/// Oldest orphaned transactions are forgotten beyond this, they stay in
+/// `TRANS_CHECK_MAX_TIME_TOPIC` anyway.
+const MAX_TRACKED_ORPHANED_TRANSACTIONS: usize = 10_000;
+
+/// Tracks the half messages the transaction check gave up on, keyed by the commit log offset of
+/// their latest copy, until they are committed or rolled back.
+#[derive(Default)]
+pub(crate) struct TransactionMetrics {
+    discarded_total: AtomicU64,
+    resolved_total: AtomicU64,
+    orphaned: parking_lot::Mutex<BTreeMap<i64, OrphanedTransactionInfo>>,
+}
+
+impl TransactionMetrics {
+    pub fn record_orphaned(&self, msg_ext: &MessageExt) {
+        self.discarded_total.fetch_add(1, Ordering::Relaxed);
+        let info = orphaned_transaction_info(msg_ext);
+        let mut orphaned = self.orphaned.lock();
+        orphaned.insert(info.commit_log_offset, info);
+        if orphaned.len() > MAX_TRACKED_ORPHANED_TRANSACTIONS {
+            if let Some((commit_log_offset, _)) = orphaned.pop_first() {
+                warn!(
+                    "too many orphaned transactions, stop tracking the one at commitLogOffset={}",
+                    commit_log_offset
+                );
+            }
+        }
+    }
+
+    /// Forgets the orphaned transaction at `commit_log_offset` once its half message has been
+    /// committed or rolled back, returns whether it was tracked.
+    pub fn resolve_orphaned(&self, commit_log_offset: i64) -> bool {
+        let resolved = self.orphaned.lock().remove(&commit_log_offset).is_some();
+        if resolved {
+            self.resolved_total.fetch_add(1, Ordering::Relaxed);
+        }
+        resolved
+    }
+
+    pub fn find_orphaned(
+        &self,
+        commit_log_offset: Option<i64>,
+        transaction_id: Option<&CheetahString>,
+    ) -> Option<OrphanedTransactionInfo> {
+        let orphaned = self.orphaned.lock();
+        match (commit_log_offset, transaction_id) {
+            (Some(commit_log_offset), _) => orphaned.get(&commit_log_offset).cloned(),
+            (None, Some(transaction_id)) => orphaned
+                .values()
+                .find(|info| &info.transaction_id == transaction_id)
+                .cloned(),
+            (None, None) => None,
+        }
+    }
+
+    pub fn orphaned_transactions(&self) -> OrphanedTransactionBody {
+        OrphanedTransactionBody {
+            transactions: self.orphaned.lock().values().cloned().collect(),
+            discarded_total: self.discarded_total.load(Ordering::Relaxed),
+            resolved_total: self.resolved_total.load(Ordering::Relaxed),
+        }
+    }
+}
+
+fn orphaned_transaction_info(msg_ext: &MessageExt) -> OrphanedTransactionInfo {
+    let property = |name: &'static str| {
+        msg_ext
+            .get_property(&CheetahString::from_static_str(name))
+            .unwrap_or_default()
+    };
+    OrphanedTransactionInfo {
+        topic: property(MessageConst::PROPERTY_REAL_TOPIC),
+        queue_id: property(MessageConst::PROPERTY_REAL_QUEUE_ID)
+            .as_str()
+            .parse()
+            .unwrap_or_default(),
+        producer_group: property(MessageConst::PROPERTY_PRODUCER_GROUP),
+        transaction_id: property(MessageConst::PROPERTY_UNIQ_CLIENT_MESSAGE_ID_KEYIDX),
+        msg_id: msg_ext.msg_id().clone(),
+        commit_log_offset: msg_ext.commit_log_offset,
+        tran_state_table_offset: msg_ext.queue_offset,
+        check_times: property(MessageConst::PROPERTY_TRANSACTION_CHECK_TIMES)
+            .as_str()
+            .parse()
+            .unwrap_or_default(),
+        born_timestamp: msg_ext.born_timestamp,
+        discard_timestamp: get_current_millis() as i64,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rocketmq_common::MessageAccessor::MessageAccessor;
+
+    use super::*;
+
+    #[test]
+    fn orphaned_transactions_are_tracked_until_resolved() {
+        let metrics = TransactionMetrics::default();
+        let mut msg_ext = MessageExt::default();
+        msg_ext.commit_log_offset = 1024;
+        msg_ext.queue_offset = 3;
+        MessageAccessor::put_property(
+            &mut msg_ext,
+            CheetahString::from_static_str(MessageConst::PROPERTY_UNIQ_CLIENT_MESSAGE_ID_KEYIDX),
+            CheetahString::from_static_str("tx-1"),
+        );
+        MessageAccessor::put_property(
+            &mut msg_ext,
+            CheetahString::from_static_str(MessageConst::PROPERTY_REAL_QUEUE_ID),
+            CheetahString::from_static_str("2"),
+        );
+        metrics.record_orphaned(&msg_ext);
+
+        let transaction_id = CheetahString::from_static_str("tx-1");
+        let info = metrics.find_orphaned(None, Some(&transaction_id)).unwrap();
+        assert_eq!(info.commit_log_offset, 1024);
+        assert_eq!(info.tran_state_table_offset, 3);
+        assert_eq!(info.queue_id, 2);
+        assert!(metrics.find_orphaned(Some(2048), None).is_none());
+
+        assert!(metrics.resolve_orphaned(1024));
+        assert!(!metrics.resolve_orphaned(1024));
+        let body = metrics.orphaned_transactions();
+        assert!(body.transactions.is_empty());
+        assert_eq!(body.discarded_total, 1);
+        assert_eq!(body.resolved_total, 1);
+    }
+}
@@ -18,8 +18,17 @@
 use rocketmq_common::common::message::message_ext::MessageExt;
 
 /// Trait defining the listener for transactional message checks.
-/// This trait provides a method for resolving discarded messages.
-pub trait TransactionalMessageCheckListener {
+/// This trait provides methods for checking back half messages and resolving discarded messages.
+#[trait_variant::make(TransactionalMessageCheckListener: Send)]
+pub trait TransactionalMessageCheckListenerLocal: Sync + 'static {
+    /// Sends a transaction state check request for a half message to an available channel of
+    /// its producer group. The request is sent in the background.
+    ///
+    /// # Arguments
+    ///
+    /// * `msg_ext` - The half message whose transaction state is checked back
+    fn resolve_half_msg(&self, msg_ext: MessageExt);
+
     /// Attempts to resolve a discarded message, typically called when a transaction
     /// message needs cleanup or final disposition.
     ///
@@ -38,5 +47,5 @@ pub trait TransactionalMessageCheckListener {
     /// - The message cannot be resolved
     /// - The broker fails to process the resolution
     /// - The message is in an invalid state
-    async fn resolve_discard_msg(&mut self, msg_ext: MessageExt);
+    async fn resolve_discard_msg(&self, msg_ext: MessageExt);
 }
@@ -15,5 +15,76 @@
  * limitations under the License.
  */
 
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+use rocketmq_common::common::broker::broker_config::BrokerConfig;
+use rocketmq_common::TimeUtils::get_current_millis;
+use rocketmq_rust::ArcMut;
+use tokio::sync::Notify;
+use tracing::info;
+
+use crate::transaction::transactional_message_check_listener::TransactionalMessageCheckListener;
+use crate::transaction::transactional_message_service::TransactionalMessageService;
+
+/// Periodically checks back half messages whose transaction is neither committed nor rolled
+/// back, every `transaction_check_interval` milliseconds.
 #[derive(Clone)]
-pub struct TransactionalMessageCheckService;
+pub struct TransactionalMessageCheckService {
+    broker_config: Arc<BrokerConfig>,
+    notify: Arc<Notify>,
+    stopped: Arc<AtomicBool>,
+}
+
+impl TransactionalMessageCheckService {
+    pub fn new(broker_config: Arc<BrokerConfig>) -> Self {
+        Self {
+            broker_config,
+            notify: Arc::new(Notify::new()),
+            stopped: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    pub fn start<TS, L>(&self, transactional_message_service: ArcMut<TS>, listener: Arc<L>)
+    where
+        TS: TransactionalMessageService + Send,
+        L: TransactionalMessageCheckListener + Send,
+    {
+        let this = self.clone();
+        tokio::spawn(async move {
+            info!("Start transaction check service thread!");
+            let mut transactional_message_service = transactional_message_service;
+            loop {
+                let check_interval = this.broker_config.transaction_check_interval;
+                tokio::select! {
+                    _ = tokio::time::sleep(Duration::from_millis(check_interval)) => {}
+                    _ = this.notify.notified() => {}
+                }
+                if this.stopped.load(Ordering::Acquire) {
+                    break;
+                }
+                let begin = get_current_millis();
+                info!("Begin to check prepare message, begin time:{}", begin);
+                transactional_message_service
+                    .check(
+                        this.broker_config.transaction_timeout,
+                        this.broker_config.transaction_check_max,
+                        listener.as_ref(),
+                    )
+                    .await;
+                info!(
+                    "End to check prepare message, consumed time:{}",
+                    get_current_millis() - begin
+                );
+            }
+            info!("End transaction check service thread!");
+        });
+    }
+
+    pub fn shutdown(&self) {
+        self.stopped.store(true, Ordering::Release);
+        self.notify.notify_one();
+    }
+}
@@ -21,6 +21,7 @@ use rocketmq_store::base::message_result::PutMessageResult;
 
 use crate::transaction::operation_result::OperationResult;
 use crate::transaction::transaction_metrics::TransactionMetrics;
+use crate::transaction::transactional_message_check_listener::TransactionalMessageCheckListener;
 
 /// Trait defining the local transactional message service.
 /// This trait provides methods for preparing, committing, rolling back, and checking transactional
@@ -88,16 +89,22 @@ pub trait TransactionalMessageServiceLocal: Sync + 'static {
 
     /// Checks the state of transactional messages.
     ///
+    /// Half messages older than their check immunity time and not yet committed or rolled back
+    /// are put back to the half queue and checked back through `listener`. Half messages checked
+    /// `transaction_check_max` times are handed to `listener` to be discarded.
+    ///
     /// # Arguments
     ///
     /// * `transaction_timeout` - The timeout for the transaction.
     /// * `transaction_check_max` - The maximum number of transaction checks.
-    fn check(
-        &self,
+    /// * `listener` - The listener checking back or discarding half messages.
+    async fn check<L>(
+        &mut self,
         transaction_timeout: u64,
         transaction_check_max: i32,
-        // listener: AbstractTransactionalMessageCheckListener,
-    );
+        listener: &L,
+    ) where
+        L: TransactionalMessageCheckListener;
 
     /// Opens the transactional message service.
     ///
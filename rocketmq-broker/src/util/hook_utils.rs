@@ -30,13 +30,11 @@ use rocketmq_common::common::sys_flag::message_sys_flag::MessageSysFlag;
 use rocketmq_common::common::topic::TopicValidator;
 use rocketmq_common::utils::queue_type_utils::QueueTypeUtils;
 use rocketmq_common::MessageDecoder::message_properties_to_string;
-use rocketmq_common::TimeUtils::get_current_millis;
 use rocketmq_store::base::message_result::PutMessageResult;
 use rocketmq_store::base::message_status_enum::PutMessageStatus;
 use rocketmq_store::config::broker_role::BrokerRole;
 use rocketmq_store::config::message_store_config::MessageStoreConfig;
 use rocketmq_store::log_file::MessageStore;
-use rocketmq_store::timer::timer_message_store;
 use rocketmq_store::timer::timer_message_store::TimerMessageStore;
 use tracing::error;
 use tracing::warn;
@@ -171,162 +169,24 @@ impl HookUtils {
         message_store_config: &Arc<MessageStoreConfig>,
         msg: &mut MessageExtBrokerInner,
     ) -> Option<PutMessageResult> {
+        if let Some(result) = timer_message_store.handle_timer_message(message_store_config, msg) {
+            return Some(result);
+        }
         let tran_type = MessageSysFlag::get_transaction_value(msg.sys_flag());
-        if tran_type == MessageSysFlag::TRANSACTION_NOT_TYPE
-            || tran_type == MessageSysFlag::TRANSACTION_COMMIT_TYPE
+        if (tran_type == MessageSysFlag::TRANSACTION_NOT_TYPE
+            || tran_type == MessageSysFlag::TRANSACTION_COMMIT_TYPE)
+            && msg.message_ext_inner.message.get_delay_time_level() > 0
+            // nothing delivers delay level messages until the schedule service has its levels
+            && schedule_message_service.get_max_delay_level() > 0
         {
-            if !Self::is_rolled_timer_message(msg) && Self::check_if_timer_message(msg) {
-                if !message_store_config.timer_wheel_enable {
-                    // wheel timer is not enabled, reject the message
-                    return Some(PutMessageResult::new_default(
-                        PutMessageStatus::WheelTimerNotEnable,
-                    ));
-                }
-                if let Some(transform_res) =
-                    Self::transform_timer_message(timer_message_store, message_store_config, msg)
-                {
-                    return Some(transform_res);
-                }
-            }
             // Delay Delivery
-            if msg.message_ext_inner.message.get_delay_time_level() > 0 {
-                Self::transform_delay_level_message(schedule_message_service, msg);
-            }
+            Self::transform_delay_level_message(schedule_message_service, msg);
         }
         None
     }
 
-    fn is_rolled_timer_message(msg: &MessageExtBrokerInner) -> bool {
-        timer_message_store::TIMER_TOPIC == msg.topic()
-    }
-
     pub fn check_if_timer_message(msg: &mut MessageExtBrokerInner) -> bool {
-        if msg.message_ext_inner.message.get_delay_time_level() > 0 {
-            if msg
-                .message_ext_inner
-                .properties()
-                .contains_key(MessageConst::PROPERTY_TIMER_DELIVER_MS)
-            {
-                msg.message_ext_inner
-                    .message
-                    .properties
-                    .remove(MessageConst::PROPERTY_TIMER_DELIVER_MS);
-            }
-            if msg
-                .message_ext_inner
-                .properties()
-                .contains_key(MessageConst::PROPERTY_TIMER_DELAY_SEC)
-            {
-                msg.message_ext_inner
-                    .message
-                    .properties
-                    .remove(MessageConst::PROPERTY_TIMER_DELAY_SEC);
-            }
-            if msg
-                .message_ext_inner
-                .properties()
-                .contains_key(MessageConst::PROPERTY_TIMER_DELAY_MS)
-            {
-                msg.message_ext_inner
-                    .message
-                    .properties
-                    .remove(MessageConst::PROPERTY_TIMER_DELAY_MS);
-            }
-            return false;
-        }
-        if timer_message_store::TIMER_TOPIC == msg.topic()
-            || msg
-                .message_ext_inner
-                .properties()
-                .contains_key(MessageConst::PROPERTY_TIMER_OUT_MS)
-        {
-            return false;
-        }
-        msg.message_ext_inner
-            .properties()
-            .contains_key(MessageConst::PROPERTY_TIMER_DELIVER_MS)
-            || msg
-                .message_ext_inner
-                .properties()
-                .contains_key(MessageConst::PROPERTY_TIMER_DELAY_MS)
-            || msg
-                .message_ext_inner
-                .properties()
-                .contains_key(MessageConst::PROPERTY_TIMER_DELAY_SEC)
-    }
-
-    fn transform_timer_message(
-        timer_message_store: &TimerMessageStore,
-        message_store_config: &Arc<MessageStoreConfig>,
-        msg: &mut MessageExtBrokerInner,
-    ) -> Option<PutMessageResult> {
-        let delay_level = msg.message_ext_inner.message.get_delay_time_level();
-        let deliver_ms = match msg.property(MessageConst::PROPERTY_TIMER_DELAY_SEC) {
-            Some(delay_sec) => get_current_millis() + delay_sec.parse::<u64>().unwrap() * 1000,
-            None => match msg.property(MessageConst::PROPERTY_TIMER_DELAY_MS) {
-                Some(delay_ms) => get_current_millis() + delay_ms.parse::<u64>().unwrap(),
-                None => match msg.property(MessageConst::PROPERTY_TIMER_DELIVER_MS) {
-                    Some(deliver_ms) => deliver_ms.parse::<u64>().unwrap(),
-                    None => {
-                        return Some(PutMessageResult::new_default(
-                            PutMessageStatus::WheelTimerMsgIllegal,
-                        ))
-                    }
-                },
-            },
-        };
-
-        if deliver_ms > get_current_millis() {
-            if delay_level <= 0
-                && deliver_ms - get_current_millis()
-                    > message_store_config.timer_max_delay_sec * 1000
-            {
-                return Some(PutMessageResult::new_default(
-                    PutMessageStatus::WheelTimerMsgIllegal,
-                ));
-            }
-
-            let timer_precision_ms = message_store_config.timer_precision_ms;
-            let deliver_ms = if deliver_ms % timer_precision_ms == 0 {
-                deliver_ms - timer_precision_ms
-            } else {
-                (deliver_ms / timer_precision_ms) * timer_precision_ms
-            };
-
-            if timer_message_store.is_reject(deliver_ms) {
-                return Some(PutMessageResult::new_default(
-                    PutMessageStatus::WheelTimerFlowControl,
-                ));
-            }
-
-            msg.message_ext_inner.message.properties.insert(
-                CheetahString::from_static_str(MessageConst::PROPERTY_TIMER_OUT_MS),
-                CheetahString::from_string(deliver_ms.to_string()),
-            );
-            msg.message_ext_inner.message.properties.insert(
-                CheetahString::from_static_str(MessageConst::PROPERTY_REAL_TOPIC),
-                CheetahString::from_slice(msg.topic()),
-            );
-            msg.message_ext_inner.message.properties.insert(
-                CheetahString::from_static_str(MessageConst::PROPERTY_REAL_QUEUE_ID),
-                CheetahString::from_string(msg.message_ext_inner.queue_id.to_string()),
-            );
-            msg.properties_string =
-                message_properties_to_string(&msg.message_ext_inner.message.properties);
-            msg.message_ext_inner.message.topic =
-                CheetahString::from_static_str(timer_message_store::TIMER_TOPIC);
-            msg.message_ext_inner.queue_id = 0;
-        } else if msg
-            .message_ext_inner
-            .message
-            .properties
-            .contains_key(MessageConst::PROPERTY_TIMER_DEL_UNIQKEY)
-        {
-            return Some(PutMessageResult::new_default(
-                PutMessageStatus::WheelTimerMsgIllegal,
-            ));
-        }
-        None
+        TimerMessageStore::is_timer_message(msg)
     }
 
     pub fn transform_delay_level_message(
@@ -18,6 +18,14 @@ use clap::Parser;
 use rocketmq_cli::command_line::Commands;
 use rocketmq_cli::command_line::RootCli;
 use rocketmq_cli::content_show::print_content;
+use rocketmq_cli::message_query::query_msg_by_id;
+use rocketmq_cli::message_query::query_msg_by_key;
+use rocketmq_cli::message_query::query_msg_by_unique_key;
+use rocketmq_cli::message_query::ResendTarget;
+use rocketmq_cli::store_compaction::compact_store;
+use rocketmq_cli::store_verify::rebuild_store;
+use rocketmq_cli::store_verify::verify_store;
+use rocketmq_cli::topic_flush_policy::update_topic_flush_policy;
 
 fn main() {
     let cli = RootCli::parse();
@@ -25,5 +33,80 @@ fn main() {
         Commands::ReadMessageLog { config, from, to } => {
             print_content(from, to, config);
         }
+        Commands::CompactStore {
+            source,
+            target,
+            retain_hours,
+            deleted_topics,
+            skip_index,
+        } => {
+            compact_store(source, target, retain_hours, deleted_topics, skip_index);
+        }
+        Commands::VerifyStore { store, plan } => {
+            verify_store(store, plan);
+        }
+        Commands::RebuildStore { store, plan } => {
+            rebuild_store(store, plan);
+        }
+        Commands::UpdateTopicFlushPolicy {
+            broker_addr,
+            topic,
+            flush_disk_type,
+            in_sync_replicas,
+            reset,
+        } => {
+            update_topic_flush_policy(broker_addr, topic, flush_disk_type, in_sync_replicas, reset);
+        }
+        Commands::QueryMsgById {
+            namesrv_addr,
+            topic,
+            msg_id,
+            hex,
+            consumer_group,
+            client_id,
+        } => {
+            let resend = resend_target(consumer_group, client_id);
+            query_msg_by_id(namesrv_addr, topic, msg_id, hex, resend);
+        }
+        Commands::QueryMsgByKey {
+            namesrv_addr,
+            topic,
+            key,
+            max_num,
+            begin_timestamp,
+            end_timestamp,
+            hex,
+        } => {
+            query_msg_by_key(
+                namesrv_addr,
+                topic,
+                key,
+                max_num,
+                begin_timestamp,
+                end_timestamp,
+                hex,
+            );
+        }
+        Commands::QueryMsgByUniqueKey {
+            namesrv_addr,
+            topic,
+            unique_key,
+            hex,
+            consumer_group,
+            client_id,
+        } => {
+            let resend = resend_target(consumer_group, client_id);
+            query_msg_by_unique_key(namesrv_addr, topic, unique_key, hex, resend);
+        }
     }
 }
+
+fn resend_target(
+    consumer_group: Option<String>,
+    client_id: Option<String>,
+) -> Option<ResendTarget> {
+    Some(ResendTarget {
+        consumer_group: consumer_group?,
+        client_id: client_id?,
+    })
+}
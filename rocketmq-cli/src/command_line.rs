@@ -64,4 +64,265 @@ pub enum Commands {
         )]
         to: Option<u32>,
     },
+
+    #[command(
+        arg_required_else_help = true,
+        author = "mxsm",
+        version = "0.2.0",
+        about = "rewrite a store without expired and deleted topic messages"
+    )]
+    CompactStore {
+        #[arg(short, long, value_name = "DIR", help = "root dir of the source store")]
+        source: PathBuf,
+
+        #[arg(
+            short,
+            long,
+            value_name = "DIR",
+            help = "root dir of the compacted store, must not hold a commit log yet"
+        )]
+        target: PathBuf,
+
+        #[arg(
+            short = 'r',
+            long,
+            value_name = "HOURS",
+            help = "drop messages stored more than this many hours ago, keeps all by default"
+        )]
+        retain_hours: Option<u64>,
+
+        #[arg(
+            short = 'd',
+            long = "deleted-topic",
+            value_name = "TOPIC",
+            help = "drop all messages of this topic, may be repeated"
+        )]
+        deleted_topics: Vec<String>,
+
+        #[arg(long, help = "skip rebuilding the index files")]
+        skip_index: bool,
+    },
+
+    #[command(
+        arg_required_else_help = true,
+        author = "mxsm",
+        version = "0.2.0",
+        about = "cross check the consume queues and the index of a store against its commit log"
+    )]
+    VerifyStore {
+        #[arg(short, long, value_name = "DIR", help = "root dir of the store")]
+        store: PathBuf,
+
+        #[arg(
+            short,
+            long,
+            value_name = "FILE",
+            help = "write the queues and index to rebuild to this file"
+        )]
+        plan: Option<PathBuf>,
+    },
+
+    #[command(
+        arg_required_else_help = true,
+        author = "mxsm",
+        version = "0.2.0",
+        about = "rebuild the consume queues and the index listed in a repair plan"
+    )]
+    RebuildStore {
+        #[arg(short, long, value_name = "DIR", help = "root dir of the store")]
+        store: PathBuf,
+
+        #[arg(
+            short,
+            long,
+            value_name = "FILE",
+            help = "repair plan written by verify-store"
+        )]
+        plan: PathBuf,
+    },
+
+    #[command(
+        arg_required_else_help = true,
+        author = "mxsm",
+        version = "0.2.0",
+        about = "override the broker flush type and in sync replicas for one topic"
+    )]
+    UpdateTopicFlushPolicy {
+        #[arg(
+            short,
+            long,
+            value_name = "ADDR",
+            help = "broker address, e.g. 127.0.0.1:10911"
+        )]
+        broker_addr: String,
+
+        #[arg(short, long, value_name = "TOPIC", help = "topic to update")]
+        topic: String,
+
+        #[arg(
+            short,
+            long,
+            value_name = "TYPE",
+            help = "flush type the topic requires, SYNC_FLUSH or ASYNC_FLUSH"
+        )]
+        flush_disk_type: Option<String>,
+
+        #[arg(
+            short,
+            long,
+            value_name = "NUM",
+            help = "replicas, the master included, a put on the topic waits for"
+        )]
+        in_sync_replicas: Option<u32>,
+
+        #[arg(
+            long,
+            conflicts_with_all = ["flush_disk_type", "in_sync_replicas"],
+            help = "remove the overrides so the topic follows the broker again"
+        )]
+        reset: bool,
+    },
+
+    #[command(
+        arg_required_else_help = true,
+        author = "mxsm",
+        version = "0.2.0",
+        about = "query message by offset message id, or by unique key when a topic is given"
+    )]
+    QueryMsgById {
+        #[arg(
+            short,
+            long,
+            value_name = "ADDR",
+            help = "name server address, e.g. 127.0.0.1:9876"
+        )]
+        namesrv_addr: String,
+
+        #[arg(short, long, value_name = "TOPIC", help = "topic of the message")]
+        topic: Option<String>,
+
+        #[arg(short = 'i', long, value_name = "MSG_ID", help = "message id")]
+        msg_id: String,
+
+        #[arg(long, help = "print the message body as hex instead of utf8")]
+        hex: bool,
+
+        #[arg(
+            short = 'g',
+            long,
+            value_name = "GROUP",
+            requires = "client_id",
+            help = "push the message again to this consumer group with consumeMessageDirectly"
+        )]
+        consumer_group: Option<String>,
+
+        #[arg(
+            short = 'c',
+            long,
+            value_name = "CLIENT_ID",
+            requires = "consumer_group",
+            help = "the consumer instance that receives the pushed message"
+        )]
+        client_id: Option<String>,
+    },
+
+    #[command(
+        arg_required_else_help = true,
+        author = "mxsm",
+        version = "0.2.0",
+        about = "query message by key"
+    )]
+    QueryMsgByKey {
+        #[arg(
+            short,
+            long,
+            value_name = "ADDR",
+            help = "name server address, e.g. 127.0.0.1:9876"
+        )]
+        namesrv_addr: String,
+
+        #[arg(short, long, value_name = "TOPIC", help = "topic of the message")]
+        topic: String,
+
+        #[arg(short, long, value_name = "KEY", help = "message key")]
+        key: String,
+
+        #[arg(
+            short,
+            long,
+            value_name = "NUM",
+            default_value_t = 64,
+            help = "max number of messages to return"
+        )]
+        max_num: i32,
+
+        #[arg(
+            short,
+            long,
+            value_name = "MILLIS",
+            default_value_t = 0,
+            help = "begin timestamp of the query"
+        )]
+        begin_timestamp: i64,
+
+        #[arg(
+            short,
+            long,
+            value_name = "MILLIS",
+            default_value_t = i64::MAX,
+            help = "end timestamp of the query"
+        )]
+        end_timestamp: i64,
+
+        #[arg(long, help = "print the message body as hex instead of utf8")]
+        hex: bool,
+    },
+
+    #[command(
+        arg_required_else_help = true,
+        author = "mxsm",
+        version = "0.2.0",
+        about = "query message by the unique key the producer generated"
+    )]
+    QueryMsgByUniqueKey {
+        #[arg(
+            short,
+            long,
+            value_name = "ADDR",
+            help = "name server address, e.g. 127.0.0.1:9876"
+        )]
+        namesrv_addr: String,
+
+        #[arg(short, long, value_name = "TOPIC", help = "topic of the message")]
+        topic: String,
+
+        #[arg(
+            short = 'i',
+            long,
+            value_name = "KEY",
+            help = "unique key of the message"
+        )]
+        unique_key: String,
+
+        #[arg(long, help = "print the message body as hex instead of utf8")]
+        hex: bool,
+
+        #[arg(
+            short = 'g',
+            long,
+            value_name = "GROUP",
+            requires = "client_id",
+            help = "push the message again to this consumer group with consumeMessageDirectly"
+        )]
+        consumer_group: Option<String>,
+
+        #[arg(
+            short = 'c',
+            long,
+            value_name = "CLIENT_ID",
+            requires = "consumer_group",
+            help = "the consumer instance that receives the pushed message"
+        )]
+        client_id: Option<String>,
+    },
 }
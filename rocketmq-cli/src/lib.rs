@@ -17,3 +17,7 @@
 
 pub mod command_line;
 pub mod content_show;
+pub mod message_query;
+pub mod store_compaction;
+pub mod store_verify;
+pub mod topic_flush_policy;
@@ -0,0 +1,390 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Arc;
+
+use cheetah_string::CheetahString;
+use rocketmq_common::common::message::message_decoder;
+use rocketmq_common::common::message::message_ext::MessageExt;
+use rocketmq_common::common::message::MessageConst;
+use rocketmq_common::common::message::MessageTrait;
+use rocketmq_common::common::mix_all;
+use rocketmq_common::utils::util_all;
+use rocketmq_remoting::clients::rocketmq_default_impl::RocketmqDefaultClient;
+use rocketmq_remoting::clients::RemotingClient;
+use rocketmq_remoting::code::request_code::RequestCode;
+use rocketmq_remoting::code::response_code::ResponseCode;
+use rocketmq_remoting::protocol::body::consume_message_directly_result::ConsumeMessageDirectlyResult;
+use rocketmq_remoting::protocol::header::client_request_header::GetRouteInfoRequestHeader;
+use rocketmq_remoting::protocol::header::consume_message_directly_result_request_header::ConsumeMessageDirectlyResultRequestHeader;
+use rocketmq_remoting::protocol::header::query_message_request_header::QueryMessageRequestHeader;
+use rocketmq_remoting::protocol::header::view_message_request_header::ViewMessageRequestHeader;
+use rocketmq_remoting::protocol::remoting_command::RemotingCommand;
+use rocketmq_remoting::protocol::route::topic_route_data::TopicRouteData;
+use rocketmq_remoting::protocol::RemotingDeserializable;
+use rocketmq_remoting::remoting::RemotingService;
+use rocketmq_remoting::request_processor::default_request_processor::DefaultRemotingRequestProcessor;
+use rocketmq_remoting::runtime::config::client_config::TokioClientConfig;
+use rocketmq_rust::ArcMut;
+
+const TIMEOUT_MILLIS: u64 = 5000;
+const UNIQUE_KEY_MAX_NUM: i32 = 32;
+
+/// The consumer instance a queried message is pushed to again with `consumeMessageDirectly`.
+pub struct ResendTarget {
+    pub consumer_group: String,
+    pub client_id: String,
+}
+
+pub fn query_msg_by_id(
+    namesrv_addr: String,
+    topic: Option<String>,
+    msg_id: String,
+    hex: bool,
+    resend: Option<ResendTarget>,
+) {
+    block_on(async move {
+        let client = MessageQueryClient::new(namesrv_addr).await;
+        let topic = CheetahString::from(topic.unwrap_or_default());
+        // An offset message id carries the store host and the commit log offset, anything
+        // else is a unique key the client generated and has to go through the index.
+        let found = match message_decoder::try_decode_message_id(&msg_id) {
+            Some(message_id) => {
+                let addr = CheetahString::from(message_id.address.to_string());
+                let msg = client
+                    .view_message(&addr, &topic, message_id.offset)
+                    .await?;
+                vec![(addr, msg)]
+            }
+            None => {
+                if topic.is_empty() {
+                    return Err(format!(
+                        "{} is not an offset message id, a topic is needed to query it as a \
+                         unique key",
+                        msg_id
+                    ));
+                }
+                client.query_by_unique_key(&topic, &msg_id).await?
+            }
+        };
+        print_and_resend(&client, found, hex, resend).await
+    });
+}
+
+pub fn query_msg_by_unique_key(
+    namesrv_addr: String,
+    topic: String,
+    unique_key: String,
+    hex: bool,
+    resend: Option<ResendTarget>,
+) {
+    block_on(async move {
+        let client = MessageQueryClient::new(namesrv_addr).await;
+        let found = client
+            .query_by_unique_key(&CheetahString::from(topic), &unique_key)
+            .await?;
+        print_and_resend(&client, found, hex, resend).await
+    });
+}
+
+pub fn query_msg_by_key(
+    namesrv_addr: String,
+    topic: String,
+    key: String,
+    max_num: i32,
+    begin_timestamp: i64,
+    end_timestamp: i64,
+    hex: bool,
+) {
+    block_on(async move {
+        let client = MessageQueryClient::new(namesrv_addr).await;
+        let topic = CheetahString::from(topic);
+        let mut found = Vec::new();
+        for addr in client.master_addrs(&topic).await? {
+            let header = QueryMessageRequestHeader {
+                topic: topic.clone(),
+                key: CheetahString::from(key.as_str()),
+                max_num,
+                begin_timestamp,
+                end_timestamp,
+                topic_request_header: None,
+            };
+            for msg in client.query_message(&addr, header, false).await? {
+                found.push((addr.clone(), msg));
+            }
+        }
+        found.truncate(max_num.max(0) as usize);
+        print_and_resend(&client, found, hex, None).await
+    });
+}
+
+fn block_on<F>(future: F)
+where
+    F: std::future::Future<Output = Result<(), String>>,
+{
+    let runtime = match tokio::runtime::Runtime::new() {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            println!("create runtime failed: {}", e);
+            return;
+        }
+    };
+    if let Err(e) = runtime.block_on(future) {
+        println!("query message failed: {}", e);
+    }
+}
+
+async fn print_and_resend(
+    client: &MessageQueryClient,
+    found: Vec<(CheetahString, MessageExt)>,
+    hex: bool,
+    resend: Option<ResendTarget>,
+) -> Result<(), String> {
+    if found.is_empty() {
+        println!("no message found");
+        return Ok(());
+    }
+    for (addr, msg) in &found {
+        println!("{}", format_message(msg, hex));
+        if let Some(target) = resend.as_ref() {
+            let result = client.consume_message_directly(addr, msg, target).await?;
+            println!("ConsumeMessageDirectly: {}", result);
+        }
+    }
+    Ok(())
+}
+
+/// Renders the header fields, the properties sorted by name and the body of a message. The body
+/// is shown as lossy UTF-8 unless `hex` is set.
+pub fn format_message(msg: &MessageExt, hex: bool) -> String {
+    let mut out = String::new();
+    let unique_key = msg
+        .get_property(&CheetahString::from_static_str(
+            MessageConst::PROPERTY_UNIQ_CLIENT_MESSAGE_ID_KEYIDX,
+        ))
+        .unwrap_or_default();
+    let _ = writeln!(out, "{:<20}{}", "OffsetID:", msg.msg_id());
+    let _ = writeln!(out, "{:<20}{}", "Unique Key:", unique_key);
+    let _ = writeln!(out, "{:<20}{}", "Topic:", msg.get_topic());
+    let _ = writeln!(out, "{:<20}{}", "Tags:", msg.get_tags().unwrap_or_default());
+    let _ = writeln!(out, "{:<20}{}", "Keys:", msg.get_keys().unwrap_or_default());
+    let _ = writeln!(out, "{:<20}{}", "Queue ID:", msg.queue_id());
+    let _ = writeln!(out, "{:<20}{}", "Queue Offset:", msg.queue_offset());
+    let _ = writeln!(
+        out,
+        "{:<20}{}",
+        "CommitLog Offset:",
+        msg.commit_log_offset()
+    );
+    let _ = writeln!(out, "{:<20}{}", "Reconsume Times:", msg.reconsume_times());
+    let _ = writeln!(
+        out,
+        "{:<20}{}",
+        "Born Timestamp:",
+        util_all::time_millis_to_human_string2(msg.born_timestamp())
+    );
+    let _ = writeln!(
+        out,
+        "{:<20}{}",
+        "Store Timestamp:",
+        util_all::time_millis_to_human_string2(msg.store_timestamp())
+    );
+    let _ = writeln!(out, "{:<20}{}", "Born Host:", msg.born_host());
+    let _ = writeln!(out, "{:<20}{}", "Store Host:", msg.store_host());
+    let _ = writeln!(out, "{:<20}{}", "System Flag:", msg.sys_flag());
+    let _ = writeln!(out, "Properties:");
+    let properties: BTreeMap<_, _> = msg.get_properties().iter().collect();
+    for (name, value) in properties {
+        let _ = writeln!(out, "    {}={}", name, value);
+    }
+    let body = msg.get_body().map(|body| body.as_ref()).unwrap_or_default();
+    let _ = writeln!(out, "Message Body ({} bytes):", body.len());
+    if hex {
+        let _ = write!(out, "{}", util_all::bytes_to_string(body));
+    } else {
+        let _ = write!(out, "{}", String::from_utf8_lossy(body));
+    }
+    out
+}
+
+struct MessageQueryClient {
+    remoting_client: ArcMut<RocketmqDefaultClient<DefaultRemotingRequestProcessor>>,
+}
+
+impl MessageQueryClient {
+    async fn new(namesrv_addr: String) -> Self {
+        let remoting_client = ArcMut::new(RocketmqDefaultClient::new(
+            Arc::new(TokioClientConfig::default()),
+            DefaultRemotingRequestProcessor,
+        ));
+        remoting_client
+            .update_name_server_address_list(
+                namesrv_addr
+                    .split(';')
+                    .filter(|addr| !addr.is_empty())
+                    .map(CheetahString::from)
+                    .collect(),
+            )
+            .await;
+        remoting_client
+            .start(ArcMut::downgrade(&remoting_client))
+            .await;
+        Self { remoting_client }
+    }
+
+    async fn invoke(
+        &self,
+        addr: Option<&CheetahString>,
+        request: RemotingCommand,
+    ) -> Result<RemotingCommand, String> {
+        let response = self
+            .remoting_client
+            .invoke_async(addr, request, TIMEOUT_MILLIS)
+            .await
+            .map_err(|e| e.to_string())?;
+        if ResponseCode::from(response.code()) != ResponseCode::Success {
+            return Err(format!(
+                "code: {}, remark: {}",
+                response.code(),
+                response.remark().cloned().unwrap_or_default()
+            ));
+        }
+        Ok(response)
+    }
+
+    /// Master addresses of every broker that serves `topic`.
+    async fn master_addrs(&self, topic: &CheetahString) -> Result<Vec<CheetahString>, String> {
+        let header = GetRouteInfoRequestHeader {
+            topic: topic.clone(),
+            ..Default::default()
+        };
+        let request =
+            RemotingCommand::create_request_command(RequestCode::GetRouteinfoByTopic, header);
+        let response = self.invoke(None, request).await?;
+        let route = response
+            .body()
+            .as_ref()
+            .and_then(|body| TopicRouteData::decode(body).ok())
+            .ok_or_else(|| format!("no route info of topic {}", topic))?;
+        Ok(route
+            .broker_datas
+            .iter()
+            .filter_map(|broker_data| broker_data.broker_addrs().get(&mix_all::MASTER_ID))
+            .cloned()
+            .collect())
+    }
+
+    async fn view_message(
+        &self,
+        addr: &CheetahString,
+        topic: &CheetahString,
+        offset: i64,
+    ) -> Result<MessageExt, String> {
+        let header = ViewMessageRequestHeader {
+            topic: topic.clone(),
+            offset,
+        };
+        let request = RemotingCommand::create_request_command(RequestCode::ViewMessageById, header);
+        let response = self.invoke(Some(addr), request).await?;
+        let mut body = response.body().clone().unwrap_or_default();
+        message_decoder::decode(&mut body, true, true, false, false, false)
+            .ok_or_else(|| format!("decode message at offset {} failed", offset))
+    }
+
+    async fn query_message(
+        &self,
+        addr: &CheetahString,
+        header: QueryMessageRequestHeader,
+        unique_key: bool,
+    ) -> Result<Vec<MessageExt>, String> {
+        let mut request =
+            RemotingCommand::create_request_command(RequestCode::QueryMessage, header);
+        if unique_key {
+            request.add_ext_field(mix_all::UNIQUE_MSG_QUERY_FLAG, "true");
+        }
+        let response = self
+            .remoting_client
+            .invoke_async(Some(addr), request, TIMEOUT_MILLIS)
+            .await
+            .map_err(|e| e.to_string())?;
+        // A broker that holds no match for the key answers with QueryNotFound.
+        match ResponseCode::from(response.code()) {
+            ResponseCode::Success => {}
+            ResponseCode::QueryNotFound => return Ok(Vec::new()),
+            _ => {
+                return Err(format!(
+                    "code: {}, remark: {}",
+                    response.code(),
+                    response.remark().cloned().unwrap_or_default()
+                ))
+            }
+        }
+        let mut body = response.body().clone().unwrap_or_default();
+        Ok(message_decoder::decodes_batch(&mut body, true, true))
+    }
+
+    async fn query_by_unique_key(
+        &self,
+        topic: &CheetahString,
+        unique_key: &str,
+    ) -> Result<Vec<(CheetahString, MessageExt)>, String> {
+        let property =
+            CheetahString::from_static_str(MessageConst::PROPERTY_UNIQ_CLIENT_MESSAGE_ID_KEYIDX);
+        let mut found = Vec::new();
+        for addr in self.master_addrs(topic).await? {
+            let header = QueryMessageRequestHeader {
+                topic: topic.clone(),
+                key: CheetahString::from(unique_key),
+                max_num: UNIQUE_KEY_MAX_NUM,
+                begin_timestamp: 0,
+                end_timestamp: i64::MAX,
+                topic_request_header: None,
+            };
+            for msg in self.query_message(&addr, header, true).await? {
+                if msg.get_property(&property).as_deref() == Some(unique_key) {
+                    found.push((addr.clone(), msg));
+                }
+            }
+        }
+        Ok(found)
+    }
+
+    async fn consume_message_directly(
+        &self,
+        addr: &CheetahString,
+        msg: &MessageExt,
+        target: &ResendTarget,
+    ) -> Result<ConsumeMessageDirectlyResult, String> {
+        let header = ConsumeMessageDirectlyResultRequestHeader {
+            consumer_group: CheetahString::from(target.consumer_group.as_str()),
+            client_id: Some(CheetahString::from(target.client_id.as_str())),
+            msg_id: Some(msg.msg_id().clone()),
+            topic: Some(msg.get_topic().clone()),
+            ..Default::default()
+        };
+        let request =
+            RemotingCommand::create_request_command(RequestCode::ConsumeMessageDirectly, header);
+        let response = self.invoke(Some(addr), request).await?;
+        response
+            .body()
+            .as_ref()
+            .and_then(|body| ConsumeMessageDirectlyResult::decode(body).ok())
+            .ok_or_else(|| "decode ConsumeMessageDirectlyResult failed".to_string())
+    }
+}
@@ -0,0 +1,61 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::path::PathBuf;
+
+use cheetah_string::CheetahString;
+use rocketmq_common::TimeUtils::get_current_millis;
+use rocketmq_store::base::store_compaction;
+use rocketmq_store::base::store_compaction::CompactionFilter;
+use rocketmq_store::config::message_store_config::MessageStoreConfig;
+
+pub fn compact_store(
+    source: PathBuf,
+    target: PathBuf,
+    retain_hours: Option<u64>,
+    deleted_topics: Vec<String>,
+    skip_index: bool,
+) {
+    let expire_before_timestamp = match retain_hours {
+        None => 0,
+        Some(hours) => get_current_millis().saturating_sub(hours * 60 * 60 * 1000) as i64,
+    };
+    let filter = CompactionFilter {
+        expire_before_timestamp,
+        deleted_topics: deleted_topics
+            .into_iter()
+            .map(CheetahString::from_string)
+            .collect(),
+    };
+    let target_config = MessageStoreConfig {
+        store_path_root_dir: CheetahString::from(target.to_string_lossy().to_string()),
+        message_index_enable: !skip_index,
+        ..MessageStoreConfig::default()
+    };
+    match store_compaction::compact_store(&source, &target_config, &filter) {
+        Ok(report) => {
+            println!("scanned messages: {}", report.scanned_messages);
+            println!("kept messages: {}", report.kept_messages);
+            println!("expired messages: {}", report.expired_messages);
+            println!("deleted topic messages: {}", report.deleted_topic_messages);
+            println!(
+                "message bytes: {}B -> {}B",
+                report.source_bytes, report.target_bytes
+            );
+        }
+        Err(e) => println!("compact store failed: {}", e),
+    }
+}
@@ -0,0 +1,75 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::path::PathBuf;
+
+use cheetah_string::CheetahString;
+use rocketmq_store::base::store_verify;
+use rocketmq_store::base::store_verify::RepairPlan;
+use rocketmq_store::config::message_store_config::MessageStoreConfig;
+
+pub fn verify_store(store: PathBuf, plan: Option<PathBuf>) {
+    let report = match store_verify::verify_store(&store_config(store)) {
+        Ok(report) => report,
+        Err(e) => {
+            println!("verify store failed: {}", e);
+            return;
+        }
+    };
+    for divergence in &report.divergences {
+        println!("{}", divergence);
+    }
+    println!("scanned messages: {}", report.scanned_messages);
+    println!("checked units: {}", report.checked_units);
+    println!("checked index entries: {}", report.checked_index_entries);
+    println!("expired entries: {}", report.expired_entries);
+    println!("divergences: {}", report.divergences.len());
+    if let Some(plan) = plan {
+        match report.repair_plan().write_to(&plan) {
+            Ok(()) => println!("repair plan written to {}", plan.display()),
+            Err(e) => println!("write repair plan failed: {}", e),
+        }
+    }
+}
+
+pub fn rebuild_store(store: PathBuf, plan: PathBuf) {
+    let plan = match RepairPlan::read_from(&plan) {
+        Ok(plan) => plan,
+        Err(e) => {
+            println!("read repair plan failed: {}", e);
+            return;
+        }
+    };
+    if plan.is_empty() {
+        println!("nothing to rebuild");
+        return;
+    }
+    match store_verify::rebuild_store(&store_config(store), &plan) {
+        Ok(report) => {
+            println!("rebuilt queues: {}", report.rebuilt_queues);
+            println!("replayed messages: {}", report.replayed_messages);
+            println!("indexed messages: {}", report.indexed_messages);
+        }
+        Err(e) => println!("rebuild store failed: {}", e),
+    }
+}
+
+fn store_config(store: PathBuf) -> MessageStoreConfig {
+    MessageStoreConfig {
+        store_path_root_dir: CheetahString::from(store.to_string_lossy().to_string()),
+        ..MessageStoreConfig::default()
+    }
+}
@@ -0,0 +1,162 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::sync::Arc;
+
+use cheetah_string::CheetahString;
+use rocketmq_common::common::topic::TopicValidator;
+use rocketmq_common::TopicAttributes;
+use rocketmq_remoting::clients::rocketmq_default_impl::RocketmqDefaultClient;
+use rocketmq_remoting::clients::RemotingClient;
+use rocketmq_remoting::code::request_code::RequestCode;
+use rocketmq_remoting::code::response_code::ResponseCode;
+use rocketmq_remoting::protocol::header::create_topic_request_header::CreateTopicRequestHeader;
+use rocketmq_remoting::protocol::header::get_topic_config_request_header::GetTopicConfigRequestHeader;
+use rocketmq_remoting::protocol::remoting_command::RemotingCommand;
+use rocketmq_remoting::protocol::static_topic::topic_config_and_queue_mapping::TopicConfigAndQueueMapping;
+use rocketmq_remoting::protocol::RemotingDeserializable;
+use rocketmq_remoting::remoting::RemotingService;
+use rocketmq_remoting::request_processor::default_request_processor::DefaultRemotingRequestProcessor;
+use rocketmq_remoting::runtime::config::client_config::TokioClientConfig;
+use rocketmq_rust::ArcMut;
+use rocketmq_store::config::flush_disk_type::FlushDiskType;
+
+const TIMEOUT_MILLIS: u64 = 5000;
+
+/// Sets the `flush.disk.type` and `in.sync.replicas` attributes of `topic` on one broker, or
+/// removes both with `reset` so the topic follows the broker again.
+pub fn update_topic_flush_policy(
+    broker_addr: String,
+    topic: String,
+    flush_disk_type: Option<String>,
+    in_sync_replicas: Option<u32>,
+    reset: bool,
+) {
+    let runtime = match tokio::runtime::Runtime::new() {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            println!("create runtime failed: {}", e);
+            return;
+        }
+    };
+    let result = runtime.block_on(update(
+        CheetahString::from(broker_addr),
+        CheetahString::from(topic),
+        flush_disk_type,
+        in_sync_replicas,
+        reset,
+    ));
+    match result {
+        Ok(attributes) => println!("update topic flush policy OK: {}", attributes),
+        Err(e) => println!("update topic flush policy failed: {}", e),
+    }
+}
+
+async fn update(
+    broker_addr: CheetahString,
+    topic: CheetahString,
+    flush_disk_type: Option<String>,
+    in_sync_replicas: Option<u32>,
+    reset: bool,
+) -> Result<String, String> {
+    let flush_disk_type_name = TopicAttributes::FLUSH_DISK_TYPE_ATTRIBUTE.get_name();
+    let in_sync_replicas_name = TopicAttributes::IN_SYNC_REPLICAS_ATTRIBUTE.get_name();
+
+    let remoting_client = ArcMut::new(RocketmqDefaultClient::new(
+        Arc::new(TokioClientConfig::default()),
+        DefaultRemotingRequestProcessor,
+    ));
+    remoting_client
+        .start(ArcMut::downgrade(&remoting_client))
+        .await;
+
+    let header = GetTopicConfigRequestHeader {
+        topic: topic.clone(),
+        topic_request_header: None,
+    };
+    let request = RemotingCommand::create_request_command(RequestCode::GetTopicConfig, header);
+    let response = invoke(&remoting_client, &broker_addr, request).await?;
+    let topic_config = response
+        .body()
+        .as_ref()
+        .and_then(|body| TopicConfigAndQueueMapping::decode(body).ok())
+        .map(|mapping| mapping.topic_config)
+        .ok_or_else(|| format!("decode config of topic {} failed", topic))?;
+
+    let mut alterations = Vec::new();
+    if reset {
+        // deleting an attribute the topic does not have is rejected by the broker
+        for name in [flush_disk_type_name, in_sync_replicas_name] {
+            if topic_config.attributes.contains_key(name) {
+                alterations.push(format!("-{}", name));
+            }
+        }
+    } else {
+        if let Some(flush_disk_type) = flush_disk_type {
+            if FlushDiskType::from_name(&flush_disk_type).is_none() {
+                return Err(format!(
+                    "invalid flush disk type {}, expect SYNC_FLUSH or ASYNC_FLUSH",
+                    flush_disk_type
+                ));
+            }
+            alterations.push(format!("+{}={}", flush_disk_type_name, flush_disk_type));
+        }
+        if let Some(in_sync_replicas) = in_sync_replicas {
+            alterations.push(format!("+{}={}", in_sync_replicas_name, in_sync_replicas));
+        }
+    }
+    if alterations.is_empty() {
+        return Err("nothing to update".to_string());
+    }
+    let attributes = alterations.join(",");
+
+    let header = CreateTopicRequestHeader {
+        topic: topic.clone(),
+        default_topic: CheetahString::from_static_str(TopicValidator::AUTO_CREATE_TOPIC_KEY_TOPIC),
+        read_queue_nums: topic_config.read_queue_nums as i32,
+        write_queue_nums: topic_config.write_queue_nums as i32,
+        perm: topic_config.perm as i32,
+        topic_filter_type: CheetahString::from(topic_config.topic_filter_type.to_string()),
+        topic_sys_flag: Some(topic_config.topic_sys_flag as i32),
+        order: topic_config.order,
+        attributes: Some(CheetahString::from(attributes.as_str())),
+        force: None,
+        topic_request_header: None,
+    };
+    let request =
+        RemotingCommand::create_request_command(RequestCode::UpdateAndCreateTopic, header);
+    invoke(&remoting_client, &broker_addr, request).await?;
+    Ok(attributes)
+}
+
+async fn invoke(
+    remoting_client: &ArcMut<RocketmqDefaultClient<DefaultRemotingRequestProcessor>>,
+    addr: &CheetahString,
+    request: RemotingCommand,
+) -> Result<RemotingCommand, String> {
+    let response = remoting_client
+        .invoke_async(Some(addr), request, TIMEOUT_MILLIS)
+        .await
+        .map_err(|e| e.to_string())?;
+    if ResponseCode::from(response.code()) != ResponseCode::Success {
+        return Err(format!(
+            "code: {}, remark: {}",
+            response.code(),
+            response.remark().cloned().unwrap_or_default()
+        ));
+    }
+    Ok(response)
+}
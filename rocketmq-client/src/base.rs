@@ -19,4 +19,5 @@ pub mod client_config;
 pub mod mq_admin;
 pub mod mq_client_admin;
 pub mod query_result;
+pub mod topic_route_change_listener;
 pub mod validators;
@@ -47,11 +47,17 @@ pub struct ClientConfig {
     pub client_ip: Option<CheetahString>,
     pub instance_name: CheetahString,
     pub client_callback_executor_threads: usize,
+    /// Upper bound of queued user callbacks, 0 means unbounded.
+    pub client_callback_executor_max_pending: usize,
     pub namespace: Option<CheetahString>,
     pub namespace_initialized: Arc<AtomicBool>,
     pub namespace_v2: Option<CheetahString>,
     pub access_channel: AccessChannel,
     pub poll_name_server_interval: u32,
+    /// How long an on-demand route lookup of an existing topic is served from cache.
+    pub topic_route_cache_ttl_millis: u64,
+    /// How long a name server "topic not exist" answer is cached.
+    pub topic_route_negative_cache_ttl_millis: u64,
     pub heartbeat_broker_interval: u32,
     pub persist_consumer_offset_interval: u32,
     pub pull_time_delay_millis_when_exception: u32,
@@ -59,6 +65,10 @@ pub struct ClientConfig {
     pub unit_name: Option<CheetahString>,
     pub decode_read_body: bool,
     pub decode_decompress_body: bool,
+    /// Verify the producer body CRC of pulled messages, see `ProducerConfig::enable_body_crc`.
+    pub verify_body_crc: bool,
+    /// Let brokers compress large pull response bodies, they are decompressed on receipt.
+    pub accept_compressed_pull_body: bool,
     pub vip_channel_enabled: bool,
     pub use_heartbeat_v2: bool,
     pub use_tls: bool,
@@ -70,9 +80,20 @@ pub struct ClientConfig {
     pub enable_stream_request_type: bool,
     pub send_latency_enable: bool,
     pub start_detector_enable: bool,
+    /// Consecutive send timeouts or busy answers after which a broker is isolated, 0 disables
+    /// the broker circuit breaker.
+    pub broker_circuit_breaker_failure_threshold: u32,
+    /// How long an isolated broker gets no sends before a probe is let through.
+    pub broker_circuit_breaker_open_millis: u64,
     pub enable_heartbeat_channel_event_listener: bool,
     pub enable_trace: bool,
     pub trace_topic: Option<CheetahString>,
+    /// Topics whose routes, broker channels and heartbeats are set up during start, so the
+    /// first send or pull does not pay for them. Empty disables the warm-up.
+    pub warm_up_topics: Vec<CheetahString>,
+    /// User properties pulled messages carry, the broker strips the others. Empty keeps all of
+    /// them.
+    pub pull_projected_properties: Vec<CheetahString>,
 }
 
 impl Default for ClientConfig {
@@ -91,11 +112,14 @@ impl ClientConfig {
                 .unwrap_or_else(|_| "DEFAULT".to_string())
                 .into(),
             client_callback_executor_threads: num_cpus::get(),
+            client_callback_executor_max_pending: 10000,
             namespace: None,
             namespace_initialized: Arc::new(AtomicBool::new(false)),
             namespace_v2: None,
             access_channel: AccessChannel::Local,
             poll_name_server_interval: Duration::from_secs(30).as_millis() as u32,
+            topic_route_cache_ttl_millis: Duration::from_secs(30).as_millis() as u64,
+            topic_route_negative_cache_ttl_millis: Duration::from_secs(5).as_millis() as u64,
             heartbeat_broker_interval: Duration::from_secs(30).as_millis() as u32,
             persist_consumer_offset_interval: Duration::from_secs(5).as_millis() as u32,
             pull_time_delay_millis_when_exception: 1000,
@@ -109,6 +133,8 @@ impl ClientConfig {
                 .unwrap_or_else(|_| "true".to_string())
                 .parse::<bool>()
                 .unwrap_or(true),
+            verify_body_crc: false,
+            accept_compressed_pull_body: true,
             vip_channel_enabled: env::var(SEND_MESSAGE_WITH_VIP_CHANNEL_PROPERTY)
                 .unwrap_or_else(|_| "false".to_string())
                 .parse::<bool>()
@@ -132,9 +158,13 @@ impl ClientConfig {
             start_detector_enable: env::var(START_DETECTOR_ENABLE)
                 .unwrap_or_else(|_| "false".to_string())
                 == "false",
+            broker_circuit_breaker_failure_threshold: 0,
+            broker_circuit_breaker_open_millis: Duration::from_secs(30).as_millis() as u64,
             enable_heartbeat_channel_event_listener: true,
             enable_trace: false,
             trace_topic: None,
+            warm_up_topics: Vec::new(),
+            pull_projected_properties: Vec::new(),
         }
     }
 }
@@ -0,0 +1,185 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+
+use cheetah_string::CheetahString;
+use rocketmq_remoting::protocol::route::topic_route_data::TopicRouteData;
+
+/// Read/write queue counts of a broker before and after a route update.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct QueueNumsChange {
+    pub old_read_queue_nums: u32,
+    pub old_write_queue_nums: u32,
+    pub new_read_queue_nums: u32,
+    pub new_write_queue_nums: u32,
+}
+
+/// Describes how the route of a topic changed after being refreshed from the name server.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TopicRouteChangeEvent {
+    pub topic: CheetahString,
+    /// Brokers that serve the topic now but did not before.
+    pub added_brokers: Vec<CheetahString>,
+    /// Brokers that no longer serve the topic.
+    pub removed_brokers: Vec<CheetahString>,
+    /// Brokers that kept serving the topic but whose queue counts changed.
+    pub queue_nums_changes: BTreeMap<CheetahString, QueueNumsChange>,
+}
+
+impl TopicRouteChangeEvent {
+    /// Computes the change between `old` and `new`, returning `None` when neither the broker set
+    /// nor any queue count changed.
+    pub fn diff(
+        topic: &CheetahString,
+        old: Option<&TopicRouteData>,
+        new: &TopicRouteData,
+    ) -> Option<Self> {
+        let old_queues = queue_nums_by_broker(old);
+        let new_queues = queue_nums_by_broker(Some(new));
+
+        let old_brokers = old_queues.keys().collect::<BTreeSet<_>>();
+        let new_brokers = new_queues.keys().collect::<BTreeSet<_>>();
+
+        let added_brokers = new_brokers
+            .difference(&old_brokers)
+            .map(|broker_name| (*broker_name).clone())
+            .collect::<Vec<_>>();
+        let removed_brokers = old_brokers
+            .difference(&new_brokers)
+            .map(|broker_name| (*broker_name).clone())
+            .collect::<Vec<_>>();
+
+        let mut queue_nums_changes = BTreeMap::new();
+        for (broker_name, (new_read, new_write)) in new_queues.iter() {
+            if let Some((old_read, old_write)) = old_queues.get(broker_name) {
+                if old_read != new_read || old_write != new_write {
+                    queue_nums_changes.insert(
+                        broker_name.clone(),
+                        QueueNumsChange {
+                            old_read_queue_nums: *old_read,
+                            old_write_queue_nums: *old_write,
+                            new_read_queue_nums: *new_read,
+                            new_write_queue_nums: *new_write,
+                        },
+                    );
+                }
+            }
+        }
+
+        if added_brokers.is_empty() && removed_brokers.is_empty() && queue_nums_changes.is_empty() {
+            return None;
+        }
+        Some(TopicRouteChangeEvent {
+            topic: topic.clone(),
+            added_brokers,
+            removed_brokers,
+            queue_nums_changes,
+        })
+    }
+}
+
+fn queue_nums_by_broker(
+    route_data: Option<&TopicRouteData>,
+) -> BTreeMap<CheetahString, (u32, u32)> {
+    route_data
+        .map(|route_data| {
+            route_data
+                .queue_datas
+                .iter()
+                .map(|queue_data| {
+                    (
+                        queue_data.broker_name.clone(),
+                        (queue_data.read_queue_nums, queue_data.write_queue_nums),
+                    )
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// A trait for reacting to topic route changes (brokers added or removed, queue counts changed)
+/// as soon as the client learns about them from the name server.
+///
+/// Listeners are invoked on the route update task, so implementations should return quickly and
+/// hand off any heavy work.
+pub trait TopicRouteChangeListener: Send + Sync {
+    /// Called after the route of `event.topic` has been updated in the client.
+    fn on_route_changed(&self, event: &TopicRouteChangeEvent);
+}
+
+impl<F> TopicRouteChangeListener for F
+where
+    F: Fn(&TopicRouteChangeEvent) + Send + Sync,
+{
+    fn on_route_changed(&self, event: &TopicRouteChangeEvent) {
+        self(event)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rocketmq_remoting::protocol::route::route_data_view::QueueData;
+
+    use super::*;
+
+    fn route(queues: &[(&str, u32, u32)]) -> TopicRouteData {
+        let mut route_data = TopicRouteData::new();
+        for (broker_name, read, write) in queues {
+            route_data.queue_datas.push(QueueData {
+                broker_name: CheetahString::from(*broker_name),
+                read_queue_nums: *read,
+                write_queue_nums: *write,
+                perm: 6,
+                topic_sys_flag: 0,
+            });
+        }
+        route_data
+    }
+
+    #[test]
+    fn diff_detects_broker_and_queue_changes() {
+        let topic = CheetahString::from_static_str("TopicTest");
+        let old = route(&[("broker-a", 4, 4), ("broker-b", 4, 4)]);
+        let new = route(&[("broker-a", 8, 8), ("broker-c", 4, 4)]);
+
+        let event = TopicRouteChangeEvent::diff(&topic, Some(&old), &new).unwrap();
+        assert_eq!(event.added_brokers, vec![CheetahString::from("broker-c")]);
+        assert_eq!(event.removed_brokers, vec![CheetahString::from("broker-b")]);
+        assert_eq!(
+            event
+                .queue_nums_changes
+                .get(&CheetahString::from_static_str("broker-a")),
+            Some(&QueueNumsChange {
+                old_read_queue_nums: 4,
+                old_write_queue_nums: 4,
+                new_read_queue_nums: 8,
+                new_write_queue_nums: 8,
+            })
+        );
+    }
+
+    #[test]
+    fn diff_returns_none_when_queues_unchanged() {
+        let topic = CheetahString::from_static_str("TopicTest");
+        let old = route(&[("broker-a", 4, 4)]);
+        assert!(TopicRouteChangeEvent::diff(&topic, Some(&old), &old.clone()).is_none());
+
+        let event = TopicRouteChangeEvent::diff(&topic, None, &old).unwrap();
+        assert_eq!(event.added_brokers, vec![CheetahString::from("broker-a")]);
+    }
+}
@@ -14,11 +14,17 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
+use std::fmt::Display;
+
 use cheetah_string::CheetahString;
 use rocketmq_common::common::FAQUrl;
+use rocketmq_remoting::code::response_code::ResponseCode;
 use rocketmq_remoting::remoting_error::RemotingError;
 use thiserror::Error;
 
+use crate::common::client_error_code::ClientErrorCode;
+use crate::producer::send_status::SendStatus;
+
 #[derive(Debug, Error)]
 pub enum MQClientError {
     #[error("{0}")]
@@ -41,6 +47,133 @@ pub enum MQClientError {
 
     #[error("{0}")]
     IllegalArgumentError(String),
+
+    #[error("{0}")]
+    SendError(#[from] SendErr),
+}
+
+impl MQClientError {
+    /// Classifies the error into a [`SendErrorKind`] so applications can decide per failure
+    /// class whether to retry, alert or drop.
+    pub fn send_error_kind(&self) -> SendErrorKind {
+        match self {
+            MQClientError::SendError(err) => err.kind(),
+            MQClientError::MQClientBrokerError(err) => {
+                SendErrorKind::from_response_code(err.response_code())
+            }
+            MQClientError::MQClientErr(err) => match err.response_code() {
+                ClientErrorCode::NOT_FOUND_TOPIC_EXCEPTION => SendErrorKind::RouteNotFound,
+                ClientErrorCode::ACCESS_BROKER_TIMEOUT
+                | ClientErrorCode::REQUEST_TIMEOUT_EXCEPTION => SendErrorKind::RemotingTimeout,
+                code => SendErrorKind::from_response_code(code),
+            },
+            MQClientError::RequestTimeoutError(_)
+            | MQClientError::RemotingTooMuchRequestError(_) => SendErrorKind::RemotingTimeout,
+            MQClientError::RemotingError(RemotingError::RemotingTimeoutError(_, _)) => {
+                SendErrorKind::RemotingTimeout
+            }
+            MQClientError::RemotingError(_) => SendErrorKind::Remoting,
+            MQClientError::IllegalArgumentError(_) => SendErrorKind::MessageIllegal,
+            MQClientError::OffsetNotFoundError(_, _, _) => SendErrorKind::Other,
+        }
+    }
+}
+
+/// Failure classes of the send path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SendErrorKind {
+    /// The request timed out waiting for the broker.
+    RemotingTimeout,
+    /// The broker rejected the request because it is overloaded.
+    BrokerBusy,
+    /// The message was stored but flushing to disk timed out.
+    FlushDiskTimeout,
+    /// The message was stored but no slave was available or the slave did not catch up in time.
+    SlaveNotAvailable,
+    /// The message was rejected by validation, retrying will not help.
+    MessageIllegal,
+    /// No route was found for the topic.
+    RouteNotFound,
+    /// The connection to the broker failed.
+    Remoting,
+    /// Any other failure.
+    Other,
+}
+
+impl SendErrorKind {
+    pub fn from_response_code(response_code: i32) -> Self {
+        match ResponseCode::from(response_code) {
+            ResponseCode::SystemBusy
+            | ResponseCode::ServiceNotAvailable
+            | ResponseCode::BrokerInMaintenance => SendErrorKind::BrokerBusy,
+            ResponseCode::FlushDiskTimeout => SendErrorKind::FlushDiskTimeout,
+            ResponseCode::SlaveNotAvailable | ResponseCode::FlushSlaveTimeout => {
+                SendErrorKind::SlaveNotAvailable
+            }
+            ResponseCode::MessageIllegal => SendErrorKind::MessageIllegal,
+            ResponseCode::TopicNotExist => SendErrorKind::RouteNotFound,
+            _ => SendErrorKind::Other,
+        }
+    }
+
+    /// Maps a non-OK [`SendStatus`] of a successful send to its failure class.
+    pub fn from_send_status(send_status: SendStatus) -> Option<Self> {
+        match send_status {
+            SendStatus::SendOk | SendStatus::Spooled => None,
+            SendStatus::FlushDiskTimeout => Some(SendErrorKind::FlushDiskTimeout),
+            SendStatus::FlushSlaveTimeout | SendStatus::SlaveNotAvailable => {
+                Some(SendErrorKind::SlaveNotAvailable)
+            }
+        }
+    }
+
+    /// Returns whether sending the same message again may succeed.
+    pub fn is_retryable(&self) -> bool {
+        !matches!(
+            self,
+            SendErrorKind::MessageIllegal | SendErrorKind::RouteNotFound
+        )
+    }
+}
+
+impl Display for SendErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            SendErrorKind::RemotingTimeout => "REMOTING_TIMEOUT",
+            SendErrorKind::BrokerBusy => "BROKER_BUSY",
+            SendErrorKind::FlushDiskTimeout => "FLUSH_DISK_TIMEOUT",
+            SendErrorKind::SlaveNotAvailable => "SLAVE_NOT_AVAILABLE",
+            SendErrorKind::MessageIllegal => "MESSAGE_ILLEGAL",
+            SendErrorKind::RouteNotFound => "ROUTE_NOT_FOUND",
+            SendErrorKind::Remoting => "REMOTING",
+            SendErrorKind::Other => "OTHER",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Error, Debug)]
+#[error("[{kind}] {message}")]
+pub struct SendErr {
+    kind: SendErrorKind,
+    message: CheetahString,
+}
+
+impl SendErr {
+    pub fn new(kind: SendErrorKind, message: impl Into<CheetahString>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+        }
+    }
+
+    pub fn kind(&self) -> SendErrorKind {
+        self.kind
+    }
+
+    pub fn message(&self) -> &CheetahString {
+        &self.message
+    }
 }
 
 #[derive(Error, Debug)]
@@ -262,6 +395,37 @@ mod tests {
     use super::*;
     use crate::client_error;
 
+    #[test]
+    fn send_error_kind_classifies_broker_response_codes() {
+        let error = MQClientError::MQClientBrokerError(MQBrokerErr::new(
+            ResponseCode::SystemBusy as i32,
+            "busy",
+        ));
+        assert_eq!(error.send_error_kind(), SendErrorKind::BrokerBusy);
+        let error = MQClientError::MQClientBrokerError(MQBrokerErr::new(
+            ResponseCode::MessageIllegal as i32,
+            "illegal",
+        ));
+        assert_eq!(error.send_error_kind(), SendErrorKind::MessageIllegal);
+        assert!(!error.send_error_kind().is_retryable());
+    }
+
+    #[test]
+    fn send_error_kind_classifies_client_errors() {
+        let error = MQClientError::RemotingError(RemotingError::RemotingTimeoutError(
+            "127.0.0.1:10911".to_string(),
+            3000,
+        ));
+        assert_eq!(error.send_error_kind(), SendErrorKind::RemotingTimeout);
+        let error = MQClientError::SendError(SendErr::new(SendErrorKind::RouteNotFound, "none"));
+        assert_eq!(error.send_error_kind(), SendErrorKind::RouteNotFound);
+        assert_eq!(error.to_string(), "[ROUTE_NOT_FOUND] none");
+        assert_eq!(
+            SendErrorKind::from_send_status(SendStatus::FlushDiskTimeout),
+            Some(SendErrorKind::FlushDiskTimeout)
+        );
+    }
+
     #[test]
     fn client_err_with_response_code_formats_correctly() {
         let result: std::result::Result<(), client_error::MQClientError> =
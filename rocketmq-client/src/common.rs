@@ -14,5 +14,6 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
+pub mod callback_executor;
 pub mod client_error_code;
 pub mod thread_local_index;
@@ -0,0 +1,214 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::any::Any;
+use std::panic::catch_unwind;
+use std::panic::AssertUnwindSafe;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Instant;
+
+use rocketmq_runtime::RocketMQRuntime;
+use tokio::sync::Semaphore;
+use tracing::error;
+use tracing::warn;
+
+/// Counters describing how user callbacks behaved on a [`CallbackExecutor`].
+#[derive(Default)]
+pub struct CallbackExecutorStats {
+    submitted: AtomicU64,
+    completed: AtomicU64,
+    panicked: AtomicU64,
+    caller_runs: AtomicU64,
+    max_rt_millis: AtomicU64,
+}
+
+impl CallbackExecutorStats {
+    pub fn submitted(&self) -> u64 {
+        self.submitted.load(Ordering::Relaxed)
+    }
+
+    pub fn completed(&self) -> u64 {
+        self.completed.load(Ordering::Relaxed)
+    }
+
+    pub fn panicked(&self) -> u64 {
+        self.panicked.load(Ordering::Relaxed)
+    }
+
+    /// Callbacks that ran on the submitting task because the pool was saturated.
+    pub fn caller_runs(&self) -> u64 {
+        self.caller_runs.load(Ordering::Relaxed)
+    }
+
+    pub fn max_rt_millis(&self) -> u64 {
+        self.max_rt_millis.load(Ordering::Relaxed)
+    }
+}
+
+/// Runs user-provided callbacks away from the client's internal response handling.
+///
+/// With `threads > 0` callbacks run on a dedicated runtime so a blocking callback only
+/// stalls that runtime; with `threads == 0` they run inline on the caller. `max_pending`
+/// bounds the number of queued callbacks, beyond which the caller runs the callback
+/// itself. Panics are caught and counted in every mode.
+pub struct CallbackExecutor {
+    name: String,
+    runtime: Option<RocketMQRuntime>,
+    pending: Option<Arc<Semaphore>>,
+    stats: Arc<CallbackExecutorStats>,
+}
+
+impl CallbackExecutor {
+    pub fn new(name: &str, threads: usize, max_pending: usize) -> Self {
+        let runtime = if threads > 0 {
+            Some(RocketMQRuntime::new_multi(threads, name))
+        } else {
+            None
+        };
+        let pending = if max_pending > 0 {
+            Some(Arc::new(Semaphore::new(max_pending)))
+        } else {
+            None
+        };
+        Self {
+            name: name.to_string(),
+            runtime,
+            pending,
+            stats: Arc::new(CallbackExecutorStats::default()),
+        }
+    }
+
+    pub fn stats(&self) -> &CallbackExecutorStats {
+        &self.stats
+    }
+
+    pub fn execute<F>(&self, task: F)
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        self.stats.submitted.fetch_add(1, Ordering::Relaxed);
+        let Some(runtime) = self.runtime.as_ref() else {
+            run_with_stats(&self.name, &self.stats, task);
+            return;
+        };
+        let permit = match self.pending.as_ref() {
+            None => None,
+            Some(pending) => match pending.clone().try_acquire_owned() {
+                Ok(permit) => Some(permit),
+                Err(_) => {
+                    self.stats.caller_runs.fetch_add(1, Ordering::Relaxed);
+                    warn!(
+                        "callback executor {} is saturated, running callback on caller",
+                        self.name
+                    );
+                    run_with_stats(&self.name, &self.stats, task);
+                    return;
+                }
+            },
+        };
+        let name = self.name.clone();
+        let stats = self.stats.clone();
+        runtime.get_handle().spawn(async move {
+            run_with_stats(&name, &stats, task);
+            drop(permit);
+        });
+    }
+}
+
+impl Drop for CallbackExecutor {
+    fn drop(&mut self) {
+        if let Some(runtime) = self.runtime.take() {
+            runtime.shutdown();
+        }
+    }
+}
+
+/// Invokes `task`, turning a panic into `None` so it cannot unwind into client internals.
+pub fn catch_callback_panic<R>(name: &str, task: impl FnOnce() -> R) -> Option<R> {
+    match catch_unwind(AssertUnwindSafe(task)) {
+        Ok(value) => Some(value),
+        Err(payload) => {
+            error!(
+                "user callback {} panicked: {}",
+                name,
+                panic_message(payload.as_ref())
+            );
+            None
+        }
+    }
+}
+
+fn run_with_stats<F: FnOnce()>(name: &str, stats: &CallbackExecutorStats, task: F) {
+    let begin = Instant::now();
+    if catch_callback_panic(name, task).is_none() {
+        stats.panicked.fetch_add(1, Ordering::Relaxed);
+    }
+    stats.completed.fetch_add(1, Ordering::Relaxed);
+    stats
+        .max_rt_millis
+        .fetch_max(begin.elapsed().as_millis() as u64, Ordering::Relaxed);
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.as_str()
+    } else {
+        "unknown panic"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn inline_executor_isolates_panics() {
+        let executor = CallbackExecutor::new("test", 0, 0);
+        executor.execute(|| panic!("boom"));
+        executor.execute(|| {});
+        assert_eq!(executor.stats().submitted(), 2);
+        assert_eq!(executor.stats().completed(), 2);
+        assert_eq!(executor.stats().panicked(), 1);
+    }
+
+    #[test]
+    fn dedicated_executor_runs_off_caller() {
+        let executor = CallbackExecutor::new("test-callback", 1, 16);
+        let (tx, rx) = mpsc::channel();
+        let caller = std::thread::current().id();
+        executor.execute(move || {
+            tx.send(std::thread::current().id() != caller).unwrap();
+        });
+        assert!(rx.recv_timeout(Duration::from_secs(5)).unwrap());
+        assert_eq!(executor.stats().caller_runs(), 0);
+    }
+
+    #[test]
+    fn catch_callback_panic_returns_value() {
+        assert_eq!(catch_callback_panic("ok", || 7), Some(7));
+        assert_eq!(
+            catch_callback_panic("panic", || -> i32 { panic!("x") }),
+            None
+        );
+    }
+}
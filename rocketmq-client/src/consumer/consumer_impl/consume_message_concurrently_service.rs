@@ -36,6 +36,7 @@ use tracing::info;
 use tracing::warn;
 
 use crate::base::client_config::ClientConfig;
+use crate::common::callback_executor::catch_callback_panic;
 use crate::consumer::consumer_impl::consume_message_service::ConsumeMessageServiceTrait;
 use crate::consumer::consumer_impl::default_mq_push_consumer_impl::DefaultMQPushConsumerImpl;
 use crate::consumer::consumer_impl::pop_process_queue::PopProcessQueue;
@@ -121,8 +122,37 @@ impl ConsumeMessageConcurrentlyService {
 
         match self.consumer_config.message_model {
             MessageModel::Broadcasting => {
-                for i in ((ack_index + 1) as usize)..consume_request.msgs.len() {
-                    warn!("BROADCASTING, the message consume failed, drop it");
+                // Broadcast messages are never sent back to the broker, failed messages are
+                // retried locally until the max reconsume times is reached and then dropped.
+                let max_reconsume_times = self
+                    .default_mqpush_consumer_impl
+                    .as_ref()
+                    .unwrap()
+                    .get_max_reconsume_times();
+                let failed_msgs = consume_request.msgs.split_off((ack_index + 1) as usize);
+                let mut msg_retry_local = Vec::with_capacity(failed_msgs.len());
+                for mut msg in failed_msgs {
+                    let reconsume_times = msg.message_ext_inner.reconsume_times();
+                    if reconsume_times >= max_reconsume_times {
+                        warn!(
+                            "BROADCASTING, the message consume failed {} times, drop it, {}",
+                            reconsume_times + 1,
+                            msg.message_ext_inner.msg_id
+                        );
+                        consume_request.msgs.push(msg);
+                    } else {
+                        msg.message_ext_inner
+                            .set_reconsume_times(reconsume_times + 1);
+                        msg_retry_local.push(msg);
+                    }
+                }
+                if !msg_retry_local.is_empty() {
+                    self.submit_consume_request_later(
+                        msg_retry_local,
+                        this,
+                        consume_request.process_queue.clone(),
+                        consume_request.message_queue.clone(),
+                    );
                 }
             }
             MessageModel::Clustering => {
@@ -430,11 +460,13 @@ impl ConsumeRequest {
                 .iter()
                 .map(|msg| &msg.message_ext_inner)
                 .collect::<Vec<&MessageExt>>();
-            match self.message_listener.consume_message(&vec, &context) {
-                Ok(value) => {
+            match catch_callback_panic("consumeMessage", || {
+                self.message_listener.consume_message(&vec, &context)
+            }) {
+                Some(Ok(value)) => {
                     status = Some(value);
                 }
-                Err(_) => {
+                Some(Err(_)) | None => {
                     has_exception = true;
                 }
             }
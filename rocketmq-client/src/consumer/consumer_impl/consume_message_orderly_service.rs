@@ -39,6 +39,7 @@ use tracing::info;
 use tracing::warn;
 
 use crate::base::client_config::ClientConfig;
+use crate::common::callback_executor::catch_callback_panic;
 use crate::consumer::consumer_impl::consume_message_service::ConsumeMessageServiceTrait;
 use crate::consumer::consumer_impl::default_mq_push_consumer_impl::DefaultMQPushConsumerImpl;
 use crate::consumer::consumer_impl::pop_process_queue::PopProcessQueue;
@@ -263,7 +264,16 @@ impl ConsumeMessageOrderlyService {
         if !msgs.is_empty() {
             for msg in msgs {
                 let reconsume_times = msg.message_ext_inner.reconsume_times;
-                if reconsume_times >= self.get_max_reconsume_times() {
+                if reconsume_times >= self.get_max_reconsume_times()
+                    && self.consumer_config.message_model == MessageModel::Broadcasting
+                {
+                    // Broadcast messages are only retried locally, never sent back
+                    warn!(
+                        "BROADCASTING, the message consume failed {} times, drop it, {}",
+                        reconsume_times + 1,
+                        msg.message_ext_inner.msg_id
+                    );
+                } else if reconsume_times >= self.get_max_reconsume_times() {
                     MessageAccessor::set_reconsume_time(
                         &mut msg.message_ext_inner,
                         CheetahString::from_string(reconsume_times.to_string()),
@@ -629,14 +639,15 @@ impl ConsumeRequest {
                     .map(|msg| &msg.message_ext_inner)
                     .collect::<Vec<&MessageExt>>();
 
-                match consume_message_orderly_service_inner
-                    .message_listener
-                    .consume_message(&vec, &mut context)
-                {
-                    Ok(value) => {
+                match catch_callback_panic("consumeMessage", || {
+                    consume_message_orderly_service_inner
+                        .message_listener
+                        .consume_message(&vec, &mut context)
+                }) {
+                    Some(Ok(value)) => {
                         status = Some(value);
                     }
-                    Err(_) => {
+                    Some(Err(_)) | None => {
                         has_exception = true;
                     }
                 }
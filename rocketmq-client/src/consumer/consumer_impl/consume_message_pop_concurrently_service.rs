@@ -35,6 +35,7 @@ use tracing::info;
 use tracing::warn;
 
 use crate::base::client_config::ClientConfig;
+use crate::common::callback_executor::catch_callback_panic;
 use crate::consumer::ack_callback::AckCallback;
 use crate::consumer::ack_result::AckResult;
 use crate::consumer::consumer_impl::consume_message_service::ConsumeMessageServiceTrait;
@@ -428,11 +429,13 @@ impl ConsumeRequest {
             .iter()
             .map(|msg| &msg.message_ext_inner)
             .collect::<Vec<&MessageExt>>();
-        match self.message_listener.consume_message(&vec, &context) {
-            Ok(value) => {
+        match catch_callback_panic("consumeMessage", || {
+            self.message_listener.consume_message(&vec, &context)
+        }) {
+            Some(Ok(value)) => {
                 status = Some(value);
             }
-            Err(_) => {
+            Some(Err(_)) | None => {
                 has_exception = true;
             }
         }
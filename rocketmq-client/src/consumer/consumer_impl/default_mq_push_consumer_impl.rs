@@ -52,12 +52,15 @@ use rocketmq_remoting::rpc::topic_request_header::TopicRequestHeader;
 use rocketmq_remoting::runtime::RPCHook;
 use rocketmq_rust::ArcMut;
 use tokio::runtime::Handle;
+use tokio::sync::broadcast;
 use tokio::sync::Mutex;
 use tracing::error;
 use tracing::info;
 use tracing::warn;
 
 use crate::base::client_config::ClientConfig;
+use crate::base::topic_route_change_listener::TopicRouteChangeEvent;
+use crate::base::topic_route_change_listener::TopicRouteChangeListener;
 use crate::base::validators::Validators;
 use crate::client_error::ClientErr;
 use crate::client_error::MQClientError;
@@ -94,6 +97,8 @@ use crate::Result;
 
 const PULL_TIME_DELAY_MILLS_WHEN_CACHE_FLOW_CONTROL: u64 = 50;
 pub(crate) const PULL_TIME_DELAY_MILLS_WHEN_BROKER_FLOW_CONTROL: u64 = 20;
+/// Pulls rejected outside the group's consume time window are retried this often.
+pub(crate) const PULL_TIME_DELAY_MILLS_WHEN_NOT_IN_CONSUME_WINDOW: u64 = 1000 * 60;
 const PULL_TIME_DELAY_MILLS_WHEN_SUSPEND: u64 = 1000;
 const BROKER_SUSPEND_MAX_TIME_MILLIS: u64 = 1000 * 15;
 const CONSUMER_TIMEOUT_MILLIS_WHEN_SUSPEND: u64 = 1000 * 30;
@@ -139,6 +144,7 @@ pub struct DefaultMQPushConsumerImpl {
     queue_max_span_flow_control_times: u64,
     pub(crate) pop_delay_level: Arc<[i32; 16]>,
     default_mqpush_consumer_impl: Option<ArcMut<DefaultMQPushConsumerImpl>>,
+    topic_route_change_listeners: Vec<Arc<dyn TopicRouteChangeListener>>,
 }
 
 impl DefaultMQPushConsumerImpl {
@@ -171,6 +177,7 @@ impl DefaultMQPushConsumerImpl {
                 10, 30, 60, 120, 180, 240, 300, 360, 420, 480, 540, 600, 1200, 1800, 3600, 7200,
             ]),
             default_mqpush_consumer_impl: None,
+            topic_route_change_listeners: vec![],
         };
         let wrapper = ArcMut::downgrade(&this.rebalance_impl);
         this.rebalance_impl.set_rebalance_impl(wrapper);
@@ -222,6 +229,9 @@ impl DefaultMQPushConsumerImpl {
                     )
                     .await;
                 self.client_instance = Some(client_instance.clone());
+                for listener in self.topic_route_change_listeners.drain(..) {
+                    client_instance.register_topic_route_change_listener(listener);
+                }
                 self.rebalance_impl
                     .set_consumer_group(self.consumer_config.consumer_group.clone());
                 self.rebalance_impl
@@ -667,6 +677,28 @@ impl DefaultMQPushConsumerImpl {
         Ok(())
     }
 
+    /// Registers a listener for topic route changes. Listeners registered before start are
+    /// attached to the client instance when the consumer starts.
+    pub fn register_topic_route_change_listener(
+        &mut self,
+        listener: Arc<dyn TopicRouteChangeListener>,
+    ) {
+        match self.client_instance.as_ref() {
+            Some(client_instance) => client_instance.register_topic_route_change_listener(listener),
+            None => self.topic_route_change_listeners.push(listener),
+        }
+    }
+
+    /// Returns a receiver of topic route change events, or `None` if the consumer has not been
+    /// started yet.
+    pub fn subscribe_topic_route_change(
+        &self,
+    ) -> Option<broadcast::Receiver<TopicRouteChangeEvent>> {
+        self.client_instance
+            .as_ref()
+            .map(|client_instance| client_instance.subscribe_topic_route_change())
+    }
+
     pub fn register_consume_message_hook(&mut self, hook: impl ConsumeMessageHook) {
         unimplemented!("registerConsumeMessageHook");
     }
@@ -22,6 +22,8 @@ use cheetah_string::CheetahString;
 use rand::Rng;
 use rocketmq_common::common::filter::expression_type::ExpressionType;
 use rocketmq_common::common::message::message_decoder;
+use rocketmq_common::common::message::message_integrity::verify_body_crc;
+use rocketmq_common::common::message::message_integrity::MessageIntegrityStats;
 use rocketmq_common::common::message::message_queue::MessageQueue;
 use rocketmq_common::common::message::MessageConst;
 use rocketmq_common::common::message::MessageTrait;
@@ -35,6 +37,7 @@ use rocketmq_remoting::protocol::header::pull_message_request_header::PullMessag
 use rocketmq_remoting::protocol::heartbeat::subscription_data::SubscriptionData;
 use rocketmq_remoting::rpc::rpc_request_header::RpcRequestHeader;
 use rocketmq_rust::ArcMut;
+use tracing::warn;
 
 use crate::consumer::consumer_impl::pull_request_ext::PullResultExt;
 use crate::consumer::pull_callback::PullCallback;
@@ -56,6 +59,7 @@ pub struct PullAPIWrapper {
     connect_broker_by_user: bool,
     default_broker_id: u64,
     filter_message_hook_list: Vec<Arc<Box<dyn FilterMessageHook + Send + Sync>>>,
+    body_crc_stats: Arc<MessageIntegrityStats>,
 }
 
 impl PullAPIWrapper {
@@ -72,9 +76,14 @@ impl PullAPIWrapper {
             connect_broker_by_user: false,
             default_broker_id: mix_all::MASTER_ID,
             filter_message_hook_list: Vec::new(),
+            body_crc_stats: Arc::new(MessageIntegrityStats::default()),
         }
     }
 
+    pub fn body_crc_stats(&self) -> &MessageIntegrityStats {
+        &self.body_crc_stats
+    }
+
     pub fn register_filter_message_hook(
         &mut self,
         filter_message_hook_list: Vec<Arc<Box<dyn FilterMessageHook + Send + Sync>>>,
@@ -143,6 +152,33 @@ impl PullAPIWrapper {
                 }
                 msg_vec = inner_msg_vec;
             }
+            if self.client_instance.client_config.verify_body_crc
+                && self.client_instance.client_config.decode_read_body
+            {
+                for msg in &msg_vec {
+                    let sys_flag = if self.client_instance.client_config.decode_decompress_body {
+                        MessageSysFlag::clear_compressed_flag(msg.message_ext_inner.sys_flag)
+                    } else {
+                        msg.message_ext_inner.sys_flag
+                    };
+                    let body = msg
+                        .message_ext_inner
+                        .message
+                        .body
+                        .clone()
+                        .unwrap_or_default();
+                    let check = verify_body_crc(&body, sys_flag, msg.get_properties());
+                    self.body_crc_stats.record(check);
+                    if check.is_mismatched() {
+                        warn!(
+                            "body crc mismatch on receipt, topic={}, msgId={}, check={:?}",
+                            msg.get_topic(),
+                            msg.message_ext_inner.msg_id,
+                            check
+                        );
+                    }
+                }
+            }
             // filter message
             let mut msg_list_filter_again =
                 if !subscription_data.tags_set.is_empty() && !subscription_data.class_filter_mode {
@@ -310,7 +346,27 @@ impl PullAPIWrapper {
                 sys_flag_inner =
                     PullSysFlag::clear_commit_offset_flag(sys_flag_inner as u32) as i32;
             }
+            if self
+                .client_instance
+                .client_config
+                .accept_compressed_pull_body
+            {
+                sys_flag_inner =
+                    PullSysFlag::build_sys_flag_with_accept_compressed_body(sys_flag_inner as u32)
+                        as i32;
+            }
 
+            let projected_properties =
+                &self.client_instance.client_config.pull_projected_properties;
+            let projected_properties = (!projected_properties.is_empty()).then(|| {
+                CheetahString::from_string(
+                    projected_properties
+                        .iter()
+                        .map(CheetahString::as_str)
+                        .collect::<Vec<_>>()
+                        .join(","),
+                )
+            });
             let request_header = PullMessageRequestHeader {
                 consumer_group: self.consumer_group.clone(),
                 topic: CheetahString::from_string(mq.get_topic().to_string()),
@@ -325,6 +381,7 @@ impl PullAPIWrapper {
                 max_msg_bytes: Some(max_size_in_bytes),
                 request_source: None,
                 proxy_forward_client_id: None,
+                projected_properties,
                 expression_type: Some(CheetahString::from_string(expression_type.to_string())),
                 topic_request: Some(TopicRequestHeader {
                     lo: None,
@@ -30,10 +30,13 @@ use rocketmq_remoting::protocol::namespace_util::NamespaceUtil;
 use rocketmq_remoting::runtime::RPCHook;
 use rocketmq_rust::ArcMut;
 use tokio::runtime::Handle;
+use tokio::sync::broadcast;
 
 use crate::base::client_config::ClientConfig;
 use crate::base::mq_admin::MQAdmin;
 use crate::base::query_result::QueryResult;
+use crate::base::topic_route_change_listener::TopicRouteChangeEvent;
+use crate::base::topic_route_change_listener::TopicRouteChangeListener;
 use crate::consumer::allocate_message_queue_strategy::AllocateMessageQueueStrategy;
 use crate::consumer::consumer_impl::default_mq_push_consumer_impl::DefaultMQPushConsumerImpl;
 use crate::consumer::default_mq_push_consumer_builder::DefaultMQPushConsumerBuilder;
@@ -48,6 +51,8 @@ use crate::consumer::message_queue_listener::MessageQueueListener;
 use crate::consumer::message_selector::MessageSelector;
 use crate::consumer::mq_consumer::MQConsumer;
 use crate::consumer::mq_push_consumer::MQPushConsumer;
+use crate::consumer::offset_correction_policy::OffsetCorrectionListener;
+use crate::consumer::offset_correction_policy::OffsetCorrectionPolicy;
 use crate::consumer::rebalance_strategy::allocate_message_queue_averagely::AllocateMessageQueueAveragely;
 use crate::trace::async_trace_dispatcher::AsyncTraceDispatcher;
 use crate::trace::hook::consume_message_trace_hook_impl::ConsumeMessageTraceHookImpl;
@@ -91,6 +96,8 @@ pub struct ConsumerConfig {
     pub(crate) trace_dispatcher: Option<Arc<Box<dyn TraceDispatcher + Send + Sync>>>,
     pub(crate) client_rebalance: bool,
     pub(crate) rpc_hook: Option<Arc<Box<dyn RPCHook>>>,
+    pub(crate) offset_correction_policy: OffsetCorrectionPolicy,
+    pub(crate) offset_correction_listener: Option<Arc<dyn OffsetCorrectionListener>>,
 }
 
 impl ConsumerConfig {
@@ -222,6 +229,14 @@ impl ConsumerConfig {
         &self.rpc_hook
     }
 
+    pub fn offset_correction_policy(&self) -> OffsetCorrectionPolicy {
+        self.offset_correction_policy
+    }
+
+    pub fn offset_correction_listener(&self) -> &Option<Arc<dyn OffsetCorrectionListener>> {
+        &self.offset_correction_listener
+    }
+
     pub fn set_consumer_group(&mut self, consumer_group: CheetahString) {
         self.consumer_group = consumer_group;
     }
@@ -377,6 +392,20 @@ impl ConsumerConfig {
     pub fn set_rpc_hook(&mut self, rpc_hook: Option<Arc<Box<dyn RPCHook>>>) {
         self.rpc_hook = rpc_hook;
     }
+
+    pub fn set_offset_correction_policy(
+        &mut self,
+        offset_correction_policy: OffsetCorrectionPolicy,
+    ) {
+        self.offset_correction_policy = offset_correction_policy;
+    }
+
+    pub fn set_offset_correction_listener(
+        &mut self,
+        offset_correction_listener: Option<Arc<dyn OffsetCorrectionListener>>,
+    ) {
+        self.offset_correction_listener = offset_correction_listener;
+    }
 }
 
 impl Default for ConsumerConfig {
@@ -421,6 +450,8 @@ impl Default for ConsumerConfig {
             trace_dispatcher: None,
             client_rebalance: true,
             rpc_hook: None,
+            offset_correction_policy: OffsetCorrectionPolicy::default(),
+            offset_correction_listener: None,
         }
     }
 }
@@ -683,4 +714,24 @@ impl DefaultMQPushConsumer {
     pub fn set_consume_from_where(&mut self, consume_from_where: ConsumeFromWhere) {
         self.consumer_config.consume_from_where = consume_from_where;
     }
+
+    /// Registers a listener that is notified when the route of a topic changes, e.g. brokers are
+    /// added or removed or queue counts change.
+    pub fn register_topic_route_change_listener(
+        &mut self,
+        listener: impl TopicRouteChangeListener + 'static,
+    ) {
+        if let Some(ref mut default_mqpush_consumer_impl) = self.default_mqpush_consumer_impl {
+            default_mqpush_consumer_impl.register_topic_route_change_listener(Arc::new(listener));
+        }
+    }
+
+    /// Returns a stream of topic route change events. Only available after the consumer started.
+    pub fn subscribe_topic_route_change(
+        &self,
+    ) -> Option<broadcast::Receiver<TopicRouteChangeEvent>> {
+        self.default_mqpush_consumer_impl
+            .as_ref()
+            .and_then(|consumer_impl| consumer_impl.subscribe_topic_route_change())
+    }
 }
@@ -29,6 +29,8 @@ use crate::consumer::default_mq_push_consumer::ConsumerConfig;
 use crate::consumer::default_mq_push_consumer::DefaultMQPushConsumer;
 use crate::consumer::message_queue_listener::MessageQueueListener;
 use crate::consumer::mq_push_consumer::MQPushConsumer;
+use crate::consumer::offset_correction_policy::OffsetCorrectionListener;
+use crate::consumer::offset_correction_policy::OffsetCorrectionPolicy;
 use crate::trace::trace_dispatcher::TraceDispatcher;
 
 pub struct DefaultMQPushConsumerBuilder {
@@ -67,6 +69,8 @@ pub struct DefaultMQPushConsumerBuilder {
     trace_dispatcher: Option<Arc<Box<dyn TraceDispatcher + Send + Sync>>>,
     client_rebalance: Option<bool>,
     rpc_hook: Option<Arc<Box<dyn RPCHook>>>,
+    offset_correction_policy: Option<OffsetCorrectionPolicy>,
+    offset_correction_listener: Option<Arc<dyn OffsetCorrectionListener>>,
 }
 
 impl Default for DefaultMQPushConsumerBuilder {
@@ -105,6 +109,8 @@ impl Default for DefaultMQPushConsumerBuilder {
             trace_dispatcher: None,
             client_rebalance: None,
             rpc_hook: None,
+            offset_correction_policy: None,
+            offset_correction_listener: None,
         }
     }
 }
@@ -120,6 +126,22 @@ impl DefaultMQPushConsumerBuilder {
         self
     }
 
+    /// Topics to resolve and connect to during start, see `ClientConfig::warm_up_topics`.
+    pub fn warm_up_topics(mut self, warm_up_topics: Vec<CheetahString>) -> Self {
+        if let Some(client_config) = self.client_config.as_mut() {
+            client_config.warm_up_topics = warm_up_topics;
+        }
+        self
+    }
+
+    /// User properties to receive, see `ClientConfig::pull_projected_properties`.
+    pub fn pull_projected_properties(mut self, properties: Vec<CheetahString>) -> Self {
+        if let Some(client_config) = self.client_config.as_mut() {
+            client_config.pull_projected_properties = properties;
+        }
+        self
+    }
+
     pub fn client_config(mut self, client_config: ClientConfig) -> Self {
         self.client_config = Some(client_config);
         self
@@ -312,6 +334,22 @@ impl DefaultMQPushConsumerBuilder {
         self
     }
 
+    pub fn offset_correction_policy(
+        mut self,
+        offset_correction_policy: OffsetCorrectionPolicy,
+    ) -> Self {
+        self.offset_correction_policy = Some(offset_correction_policy);
+        self
+    }
+
+    pub fn offset_correction_listener(
+        mut self,
+        offset_correction_listener: impl OffsetCorrectionListener + 'static,
+    ) -> Self {
+        self.offset_correction_listener = Some(Arc::new(offset_correction_listener));
+        self
+    }
+
     // Build method to create a ConsumerConfig instance
     pub fn build(mut self) -> DefaultMQPushConsumer {
         let mut consumer_config = ConsumerConfig::default();
@@ -410,6 +448,10 @@ impl DefaultMQPushConsumerBuilder {
             consumer_config.client_rebalance = client_rebalance;
         }
         consumer_config.rpc_hook = self.rpc_hook.clone();
+        if let Some(offset_correction_policy) = self.offset_correction_policy {
+            consumer_config.offset_correction_policy = offset_correction_policy;
+        }
+        consumer_config.offset_correction_listener = self.offset_correction_listener.clone();
 
         let mut consumer = DefaultMQPushConsumer::new(
             self.client_config.take().unwrap_or_default(),
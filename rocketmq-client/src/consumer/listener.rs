@@ -16,6 +16,7 @@
  */
 pub mod consume_concurrently_context;
 pub mod consume_concurrently_status;
+pub mod consume_dedup_layer;
 pub mod consume_orderly_context;
 pub mod consume_orderly_status;
 pub mod consume_return_type;
@@ -0,0 +1,281 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use cheetah_string::CheetahString;
+use parking_lot::Mutex;
+use rocketmq_common::common::message::message_client_id_setter::MessageClientIDSetter;
+use rocketmq_common::common::message::message_ext::MessageExt;
+use rocketmq_common::common::message::MessageTrait;
+use rocketmq_common::TimeUtils::get_current_millis;
+use tracing::info;
+
+use crate::consumer::listener::consume_concurrently_context::ConsumeConcurrentlyContext;
+use crate::consumer::listener::consume_concurrently_status::ConsumeConcurrentlyStatus;
+use crate::consumer::listener::consume_orderly_context::ConsumeOrderlyContext;
+use crate::consumer::listener::consume_orderly_status::ConsumeOrderlyStatus;
+use crate::consumer::listener::message_listener_concurrently::MessageListenerConcurrently;
+use crate::consumer::listener::message_listener_orderly::MessageListenerOrderly;
+use crate::Result;
+
+/// Storage for the keys of messages that have already been consumed.
+///
+/// Implementations backed by RocksDB, Redis or any other shared storage can be plugged into
+/// [`ConsumeDedupLayer`] to deduplicate across restarts or client instances.
+pub trait DedupStore: Send + Sync {
+    /// Returns `true` if `key` was marked as processed at or after `not_before_millis`.
+    fn is_processed(&self, key: &CheetahString, not_before_millis: u64) -> bool;
+
+    /// Marks `key` as processed at `timestamp_millis`.
+    fn mark_processed(&self, key: &CheetahString, timestamp_millis: u64);
+}
+
+/// An in-memory [`DedupStore`] that keeps at most `capacity` keys and evicts the least recently
+/// marked ones first.
+pub struct InMemoryDedupStore {
+    capacity: usize,
+    inner: Mutex<InMemoryDedupStoreInner>,
+}
+
+#[derive(Default)]
+struct InMemoryDedupStoreInner {
+    processed: HashMap<CheetahString, u64>,
+    order: VecDeque<(CheetahString, u64)>,
+}
+
+impl InMemoryDedupStore {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            inner: Mutex::new(InMemoryDedupStoreInner::default()),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.lock().processed.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Default for InMemoryDedupStore {
+    fn default() -> Self {
+        Self::new(100_000)
+    }
+}
+
+impl DedupStore for InMemoryDedupStore {
+    fn is_processed(&self, key: &CheetahString, not_before_millis: u64) -> bool {
+        self.inner
+            .lock()
+            .processed
+            .get(key)
+            .is_some_and(|timestamp| *timestamp >= not_before_millis)
+    }
+
+    fn mark_processed(&self, key: &CheetahString, timestamp_millis: u64) {
+        let mut inner = self.inner.lock();
+        inner.processed.insert(key.clone(), timestamp_millis);
+        inner.order.push_back((key.clone(), timestamp_millis));
+        while inner.processed.len() > self.capacity {
+            let Some((oldest, timestamp)) = inner.order.pop_front() else {
+                break;
+            };
+            // Skip stale entries of keys that were marked again later
+            if inner.processed.get(&oldest) == Some(&timestamp) {
+                inner.processed.remove(&oldest);
+            }
+        }
+        // Keep the order queue bounded when the same keys are marked repeatedly
+        if inner.order.len() > self.capacity * 2 {
+            let InMemoryDedupStoreInner { processed, order } = &mut *inner;
+            order.retain(|(key, timestamp)| processed.get(key) == Some(timestamp));
+        }
+    }
+}
+
+/// Wraps a message listener and skips messages whose `UNIQ_KEY` was already consumed
+/// successfully within the dedup window, giving at-least-once consumers an idempotency guard.
+///
+/// A message is only marked as processed after the wrapped listener reports success, so failed
+/// messages are still redelivered and retried.
+pub struct ConsumeDedupLayer<L> {
+    listener: L,
+    store: Box<dyn DedupStore>,
+    window: Duration,
+}
+
+impl<L> ConsumeDedupLayer<L> {
+    pub fn new(listener: L, store: impl DedupStore + 'static, window: Duration) -> Self {
+        Self {
+            listener,
+            store: Box::new(store),
+            window,
+        }
+    }
+
+    /// Creates a layer backed by an [`InMemoryDedupStore`].
+    pub fn with_in_memory_store(listener: L, capacity: usize, window: Duration) -> Self {
+        Self::new(listener, InMemoryDedupStore::new(capacity), window)
+    }
+
+    fn dedup_key(msg: &MessageExt) -> CheetahString {
+        MessageClientIDSetter::get_uniq_id(msg).unwrap_or_else(|| msg.msg_id.clone())
+    }
+
+    fn filter_processed<'a>(&self, msgs: &[&'a MessageExt]) -> Vec<&'a MessageExt> {
+        let not_before = get_current_millis().saturating_sub(self.window.as_millis() as u64);
+        msgs.iter()
+            .filter(|msg| {
+                let key = Self::dedup_key(msg);
+                let processed = self.store.is_processed(&key, not_before);
+                if processed {
+                    info!(
+                        "skip duplicated message, key={}, topic={}, queueOffset={}",
+                        key,
+                        msg.get_topic(),
+                        msg.queue_offset
+                    );
+                }
+                !processed
+            })
+            .copied()
+            .collect()
+    }
+
+    fn mark_processed(&self, msgs: &[&MessageExt]) {
+        let now = get_current_millis();
+        for msg in msgs {
+            self.store.mark_processed(&Self::dedup_key(msg), now);
+        }
+    }
+}
+
+impl<L> MessageListenerConcurrently for ConsumeDedupLayer<L>
+where
+    L: MessageListenerConcurrently,
+{
+    fn consume_message(
+        &self,
+        msgs: &[&MessageExt],
+        context: &ConsumeConcurrentlyContext,
+    ) -> Result<ConsumeConcurrentlyStatus> {
+        let pending = self.filter_processed(msgs);
+        if pending.is_empty() {
+            return Ok(ConsumeConcurrentlyStatus::ConsumeSuccess);
+        }
+        let status = self.listener.consume_message(&pending, context)?;
+        if status == ConsumeConcurrentlyStatus::ConsumeSuccess {
+            self.mark_processed(&pending);
+        }
+        Ok(status)
+    }
+}
+
+impl<L> MessageListenerOrderly for ConsumeDedupLayer<L>
+where
+    L: MessageListenerOrderly,
+{
+    #[allow(deprecated)]
+    fn consume_message(
+        &self,
+        msgs: &[&MessageExt],
+        context: &mut ConsumeOrderlyContext,
+    ) -> Result<ConsumeOrderlyStatus> {
+        let pending = self.filter_processed(msgs);
+        if pending.is_empty() {
+            return Ok(ConsumeOrderlyStatus::Success);
+        }
+        let status = self.listener.consume_message(&pending, context)?;
+        if matches!(
+            status,
+            ConsumeOrderlyStatus::Success | ConsumeOrderlyStatus::Commit
+        ) {
+            self.mark_processed(&pending);
+        }
+        Ok(status)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering;
+    use std::sync::Arc;
+
+    use rocketmq_common::common::message::message_queue::MessageQueue;
+
+    use super::*;
+
+    struct CountingListener {
+        consumed: Arc<AtomicUsize>,
+    }
+
+    impl MessageListenerConcurrently for CountingListener {
+        fn consume_message(
+            &self,
+            msgs: &[&MessageExt],
+            _context: &ConsumeConcurrentlyContext,
+        ) -> Result<ConsumeConcurrentlyStatus> {
+            self.consumed.fetch_add(msgs.len(), Ordering::SeqCst);
+            Ok(ConsumeConcurrentlyStatus::ConsumeSuccess)
+        }
+    }
+
+    fn message(msg_id: &str) -> MessageExt {
+        let mut msg = MessageExt::default();
+        msg.msg_id = CheetahString::from(msg_id);
+        msg
+    }
+
+    #[test]
+    fn in_memory_store_evicts_oldest_keys() {
+        let store = InMemoryDedupStore::new(2);
+        store.mark_processed(&CheetahString::from("a"), 1);
+        store.mark_processed(&CheetahString::from("b"), 2);
+        store.mark_processed(&CheetahString::from("c"), 3);
+        assert_eq!(store.len(), 2);
+        assert!(!store.is_processed(&CheetahString::from("a"), 0));
+        assert!(store.is_processed(&CheetahString::from("c"), 0));
+        assert!(!store.is_processed(&CheetahString::from("c"), 4));
+    }
+
+    #[test]
+    fn dedup_layer_skips_consumed_messages() {
+        let consumed = Arc::new(AtomicUsize::new(0));
+        let layer = ConsumeDedupLayer::with_in_memory_store(
+            CountingListener {
+                consumed: consumed.clone(),
+            },
+            16,
+            Duration::from_secs(60),
+        );
+        let context = ConsumeConcurrentlyContext::new(MessageQueue::default());
+        let first = message("ID1");
+        let second = message("ID2");
+
+        let status = layer.consume_message(&[&first], &context).unwrap();
+        assert_eq!(status, ConsumeConcurrentlyStatus::ConsumeSuccess);
+        let status = layer.consume_message(&[&first, &second], &context).unwrap();
+        assert_eq!(status, ConsumeConcurrentlyStatus::ConsumeSuccess);
+        assert_eq!(consumed.load(Ordering::SeqCst), 2);
+    }
+}
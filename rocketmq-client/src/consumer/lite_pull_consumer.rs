@@ -151,6 +151,9 @@ pub trait LitePullConsumerLocal: Sync {
 
     /// Seeks to a specific offset in a message queue.
     ///
+    /// If the broker later reports the offset as illegal, it is corrected according to the
+    /// consumer's `OffsetCorrectionPolicy` instead of failing repeatedly.
+    ///
     /// # Arguments
     ///
     /// * `message_queue` - The message queue to seek.
@@ -0,0 +1,126 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::fmt::Display;
+
+use rocketmq_common::common::message::message_queue::MessageQueue;
+
+/// How a consumer reacts when the broker reports that the requested pull offset is illegal
+/// (`PULL_OFFSET_MOVED`), e.g. after seeking past the end of a queue or to an offset that was
+/// already cleaned up.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OffsetCorrectionPolicy {
+    /// Clamp the requested offset into `[min_offset, max_offset]` reported by the broker and keep
+    /// pulling from the corrected offset without dropping the process queue. Falls back to the
+    /// broker suggested offset when clamping would not move the offset.
+    #[default]
+    ClampToRange,
+    /// Move to the offset suggested by the broker, drop the process queue and trigger a
+    /// rebalance, which is the behavior of the Java client.
+    BrokerSuggested,
+}
+
+impl Display for OffsetCorrectionPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OffsetCorrectionPolicy::ClampToRange => write!(f, "CLAMP_TO_RANGE"),
+            OffsetCorrectionPolicy::BrokerSuggested => write!(f, "BROKER_SUGGESTED"),
+        }
+    }
+}
+
+/// Emitted when an illegal pull offset has been corrected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OffsetCorrectedEvent {
+    pub message_queue: MessageQueue,
+    pub policy: OffsetCorrectionPolicy,
+    pub requested_offset: i64,
+    pub corrected_offset: i64,
+    pub min_offset: i64,
+    pub max_offset: i64,
+}
+
+impl Display for OffsetCorrectedEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "OffsetCorrectedEvent [message_queue: {}, policy: {}, requested_offset: {}, \
+             corrected_offset: {}, min_offset: {}, max_offset: {}]",
+            self.message_queue,
+            self.policy,
+            self.requested_offset,
+            self.corrected_offset,
+            self.min_offset,
+            self.max_offset
+        )
+    }
+}
+
+/// A trait for being notified when the consumer corrects an illegal pull offset.
+pub trait OffsetCorrectionListener: Send + Sync {
+    fn on_offset_corrected(&self, event: &OffsetCorrectedEvent);
+}
+
+impl OffsetCorrectionPolicy {
+    /// Computes the offset to continue from for the given policy.
+    ///
+    /// `next_begin_offset` is the offset suggested by the broker, `min_offset` and `max_offset`
+    /// are the current bounds of the queue.
+    pub fn correct(
+        &self,
+        requested_offset: i64,
+        next_begin_offset: i64,
+        min_offset: i64,
+        max_offset: i64,
+    ) -> i64 {
+        match self {
+            OffsetCorrectionPolicy::ClampToRange => {
+                if min_offset > max_offset {
+                    // The broker did not report a usable range
+                    return next_begin_offset;
+                }
+                let clamped = requested_offset.clamp(min_offset, max_offset);
+                if clamped == requested_offset {
+                    // Already in range, retrying it would loop on the same error
+                    next_begin_offset
+                } else {
+                    clamped
+                }
+            }
+            OffsetCorrectionPolicy::BrokerSuggested => next_begin_offset,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clamp_to_range_corrects_out_of_range_offsets() {
+        let policy = OffsetCorrectionPolicy::ClampToRange;
+        assert_eq!(policy.correct(5, 0, 100, 200), 100);
+        assert_eq!(policy.correct(500, 0, 100, 200), 200);
+        assert_eq!(policy.correct(150, 42, 100, 200), 42);
+        assert_eq!(policy.correct(500, 42, 200, 100), 42);
+    }
+
+    #[test]
+    fn broker_suggested_uses_next_begin_offset() {
+        let policy = OffsetCorrectionPolicy::BrokerSuggested;
+        assert_eq!(policy.correct(500, 42, 100, 200), 42);
+    }
+}
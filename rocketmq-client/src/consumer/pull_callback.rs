@@ -27,9 +27,12 @@ use tracing::warn;
 use crate::client_error::MQClientError;
 use crate::consumer::consumer_impl::default_mq_push_consumer_impl::DefaultMQPushConsumerImpl;
 use crate::consumer::consumer_impl::default_mq_push_consumer_impl::PULL_TIME_DELAY_MILLS_WHEN_BROKER_FLOW_CONTROL;
+use crate::consumer::consumer_impl::default_mq_push_consumer_impl::PULL_TIME_DELAY_MILLS_WHEN_NOT_IN_CONSUME_WINDOW;
 use crate::consumer::consumer_impl::pull_request::PullRequest;
 use crate::consumer::consumer_impl::pull_request_ext::PullResultExt;
 use crate::consumer::consumer_impl::re_balance::Rebalance;
+use crate::consumer::offset_correction_policy::OffsetCorrectedEvent;
+use crate::consumer::offset_correction_policy::OffsetCorrectionPolicy;
 use crate::consumer::pull_status::PullStatus;
 
 pub type PullCallbackFn =
@@ -136,7 +139,46 @@ impl PullCallback for DefaultPullCallback {
                     "the pull request offset illegal, {},{}",
                     pull_result_ext.pull_result, pull_result_ext.pull_result.pull_status
                 );
-                pull_request.next_offset = pull_result_ext.pull_result.next_begin_offset as i64;
+                let policy = push_consumer_impl.consumer_config.offset_correction_policy;
+                let requested_offset = pull_request.next_offset;
+                let pull_result = &pull_result_ext.pull_result;
+                let corrected_offset = policy.correct(
+                    requested_offset,
+                    pull_result.next_begin_offset as i64,
+                    pull_result.min_offset as i64,
+                    pull_result.max_offset as i64,
+                );
+                let event = OffsetCorrectedEvent {
+                    message_queue: pull_request.get_message_queue().clone(),
+                    policy,
+                    requested_offset,
+                    corrected_offset,
+                    min_offset: pull_result.min_offset as i64,
+                    max_offset: pull_result.max_offset as i64,
+                };
+                warn!("correct the illegal pull offset, {}", event);
+                if let Some(listener) = push_consumer_impl
+                    .consumer_config
+                    .offset_correction_listener
+                    .as_ref()
+                {
+                    listener.on_offset_corrected(&event);
+                }
+                pull_request.next_offset = corrected_offset;
+
+                if policy == OffsetCorrectionPolicy::ClampToRange {
+                    push_consumer_impl
+                        .offset_store
+                        .as_mut()
+                        .unwrap()
+                        .update_offset(pull_request.get_message_queue(), corrected_offset, false)
+                        .await;
+                    push_consumer_impl
+                        .execute_pull_request_immediately(pull_request)
+                        .await;
+                    return;
+                }
+
                 pull_request.process_queue.set_dropped(true);
 
                 let offset_store = push_consumer_impl.offset_store.as_mut().unwrap();
@@ -203,11 +245,12 @@ impl PullCallback for DefaultPullCallback {
         let time_delay = if let Some(er) = err.downcast_ref::<MQClientError>() {
             match er {
                 MQClientError::MQClientBrokerError(broker_error) => {
-                    if ResponseCode::from(broker_error.response_code()) == ResponseCode::FlowControl
-                    {
-                        PULL_TIME_DELAY_MILLS_WHEN_BROKER_FLOW_CONTROL
-                    } else {
-                        push_consumer_impl.pull_time_delay_mills_when_exception
+                    match ResponseCode::from(broker_error.response_code()) {
+                        ResponseCode::FlowControl => PULL_TIME_DELAY_MILLS_WHEN_BROKER_FLOW_CONTROL,
+                        ResponseCode::NotInConsumeWindow => {
+                            PULL_TIME_DELAY_MILLS_WHEN_NOT_IN_CONSUME_WINDOW
+                        }
+                        _ => push_consumer_impl.pull_time_delay_mills_when_exception,
                     }
                 }
                 _ => push_consumer_impl.pull_time_delay_mills_when_exception,
@@ -15,3 +15,4 @@
  * limitations under the License.
  */
 pub mod mq_client_instance;
+pub mod topic_route_cache;
@@ -21,6 +21,7 @@ use std::sync::atomic::AtomicI64;
 use std::sync::Arc;
 use std::thread;
 use std::time::Duration;
+use std::time::Instant;
 
 use cheetah_string::CheetahString;
 use rand::seq::SliceRandom;
@@ -31,13 +32,16 @@ use rocketmq_common::common::message::message_ext::MessageExt;
 use rocketmq_common::common::message::message_queue::MessageQueue;
 use rocketmq_common::common::message::message_queue_assignment::MessageQueueAssignment;
 use rocketmq_common::common::mix_all;
+use rocketmq_common::common::topic::TopicValidator;
 use rocketmq_common::TimeUtils::get_current_millis;
 use rocketmq_remoting::base::connection_net_event::ConnectionNetEvent;
+use rocketmq_remoting::code::response_code::ResponseCode;
 use rocketmq_remoting::protocol::body::consume_message_directly_result::ConsumeMessageDirectlyResult;
 use rocketmq_remoting::protocol::heartbeat::consumer_data::ConsumerData;
 use rocketmq_remoting::protocol::heartbeat::heartbeat_data::HeartbeatData;
 use rocketmq_remoting::protocol::heartbeat::message_model::MessageModel;
 use rocketmq_remoting::protocol::heartbeat::producer_data::ProducerData;
+use rocketmq_remoting::protocol::namespace_util::NamespaceUtil;
 use rocketmq_remoting::protocol::route::topic_route_data::TopicRouteData;
 use rocketmq_remoting::rpc::client_metadata::ClientMetadata;
 use rocketmq_remoting::runtime::config::client_config::TokioClientConfig;
@@ -46,18 +50,25 @@ use rocketmq_runtime::RocketMQRuntime;
 use rocketmq_rust::ArcMut;
 use rocketmq_rust::RocketMQTokioMutex;
 use tokio::runtime::Handle;
+use tokio::sync::broadcast;
 use tokio::sync::RwLock;
+use tracing::debug;
 use tracing::error;
 use tracing::info;
 use tracing::warn;
 
 use crate::admin::mq_admin_ext_inner::MQAdminExtInner;
 use crate::base::client_config::ClientConfig;
+use crate::base::topic_route_change_listener::TopicRouteChangeEvent;
+use crate::base::topic_route_change_listener::TopicRouteChangeListener;
+use crate::client_error::MQClientError;
 use crate::client_error::MQClientError::MQClientErr;
 use crate::consumer::consumer_impl::pull_message_service::PullMessageService;
 use crate::consumer::consumer_impl::re_balance::rebalance_service::RebalanceService;
 use crate::consumer::mq_consumer_inner::MQConsumerInner;
 use crate::consumer::mq_consumer_inner::MQConsumerInnerImpl;
+use crate::factory::topic_route_cache::RouteCacheState;
+use crate::factory::topic_route_cache::TopicRouteCache;
 use crate::implementation::client_remoting_processor::ClientRemotingProcessor;
 use crate::implementation::find_broker_result::FindBrokerResult;
 use crate::implementation::mq_admin_impl::MQAdminImpl;
@@ -70,6 +81,7 @@ use crate::producer::producer_impl::topic_publish_info::TopicPublishInfo;
 use crate::Result;
 
 const LOCK_TIMEOUT_MILLIS: u64 = 3000;
+const TOPIC_ROUTE_CHANGE_CHANNEL_CAPACITY: usize = 1024;
 
 pub struct MQClientInstance {
     pub(crate) client_config: ArcMut<ClientConfig>,
@@ -119,6 +131,12 @@ pub struct MQClientInstance {
         >,
     >,
     send_heartbeat_times_total: Arc<AtomicI64>,
+    broker_support_v2_heartbeat_set: Arc<RwLock<HashSet<CheetahString /* address */>>>,
+    broker_addr_heartbeat_fingerprint_table:
+        Arc<RwLock<HashMap<CheetahString /* address */, i32 /* fingerprint */>>>,
+    topic_route_change_listeners: Arc<parking_lot::RwLock<Vec<Arc<dyn TopicRouteChangeListener>>>>,
+    topic_route_change_tx: broadcast::Sender<TopicRouteChangeEvent>,
+    topic_route_cache: Arc<TopicRouteCache>,
 }
 
 impl MQClientInstance {
@@ -223,6 +241,14 @@ impl MQClientInstance {
             broker_addr_table,
             broker_version_table: Arc::new(Default::default()),
             send_heartbeat_times_total: Arc::new(AtomicI64::new(0)),
+            broker_support_v2_heartbeat_set: Arc::new(Default::default()),
+            broker_addr_heartbeat_fingerprint_table: Arc::new(Default::default()),
+            topic_route_change_listeners: Arc::new(Default::default()),
+            topic_route_change_tx: broadcast::channel(TOPIC_ROUTE_CHANGE_CHANNEL_CAPACITY).0,
+            topic_route_cache: Arc::new(TopicRouteCache::new(
+                client_config.topic_route_cache_ttl_millis,
+                client_config.topic_route_negative_cache_ttl_millis,
+            )),
         });
         let instance_clone = instance.clone();
         instance.mq_admin_impl.set_client(instance_clone);
@@ -328,6 +354,10 @@ impl MQClientInstance {
                     .await?;
                 info!("the client factory[{}] start OK", self.client_id);
                 self.service_state = ServiceState::Running;
+                if !self.client_config.warm_up_topics.is_empty() {
+                    let topics = self.client_config.warm_up_topics.clone();
+                    self.warm_up(&topics).await;
+                }
             }
             ServiceState::Running => {}
             ServiceState::ShutdownAlready => {}
@@ -343,6 +373,38 @@ impl MQClientInstance {
 
     pub async fn shutdown(&mut self) {}
 
+    /// Fetches the routes of `topics` and heartbeats every broker they live on, which also
+    /// opens the broker channels. Returns the number of topics a route was found for.
+    pub async fn warm_up(&mut self, topics: &[CheetahString]) -> usize {
+        let begin = Instant::now();
+        let namespace = self.client_config.namespace.clone().unwrap_or_default();
+        let mut resolved = 0;
+        for topic in topics {
+            let topic = CheetahString::from_string(NamespaceUtil::wrap_namespace(
+                namespace.as_str(),
+                topic.as_str(),
+            ));
+            if self
+                .update_topic_route_info_from_name_server_topic(&topic)
+                .await
+            {
+                resolved += 1;
+            } else {
+                warn!("warm up: no route info of topic {}", topic);
+            }
+        }
+        let heartbeat_sent = self.send_heartbeat_to_all_broker_with_lock().await;
+        info!(
+            "the client factory[{}] warmed up {}/{} topics, heartbeat sent: {}, cost {} ms",
+            self.client_id,
+            resolved,
+            topics.len(),
+            heartbeat_sent,
+            begin.elapsed().as_millis()
+        );
+        resolved
+    }
+
     pub async fn register_producer(&mut self, group: &str, producer: MQProducerInnerImpl) -> bool {
         if group.is_empty() {
             return false;
@@ -451,8 +513,9 @@ impl MQClientInstance {
             }
         }
 
+        // the scheduled refresh keeps the cache warm, so it always goes to the name server
         for topic in topic_list.iter() {
-            self.update_topic_route_info_from_name_server_topic(topic)
+            self.update_topic_route_info_from_name_server_detail(topic, false, None, true)
                 .await;
         }
     }
@@ -503,6 +566,17 @@ impl MQClientInstance {
         None
     }
 
+    /// Returns whether every broker serving `topic` reported `feature`, see
+    /// [`BrokerFeature`](rocketmq_remoting::protocol::route::broker_feature::BrokerFeature).
+    /// Unknown topics support no feature.
+    pub async fn is_broker_feature_supported(&self, topic: &str, feature: u64) -> bool {
+        self.topic_route_table
+            .read()
+            .await
+            .get(topic)
+            .is_some_and(|topic_route_data| topic_route_data.supports_feature(feature))
+    }
+
     pub async fn find_broker_addr_by_topic(&self, topic: &str) -> Option<CheetahString> {
         let topic_route_table = self.topic_route_table.read().await;
         if let Some(topic_route_data) = topic_route_table.get(topic) {
@@ -517,34 +591,77 @@ impl MQClientInstance {
         None
     }
 
+    #[inline]
     pub async fn update_topic_route_info_from_name_server_default(
         &mut self,
         topic: &CheetahString,
         is_default: bool,
         producer_config: Option<&Arc<ProducerConfig>>,
+    ) -> bool {
+        self.update_topic_route_info_from_name_server_detail(
+            topic,
+            is_default,
+            producer_config,
+            false,
+        )
+        .await
+    }
+
+    /// Fetches the route of `topic` from the name server, bypassing the route cache.
+    pub async fn refresh_topic_route_info(&mut self, topic: &CheetahString) -> bool {
+        self.topic_route_cache.invalidate(topic);
+        self.update_topic_route_info_from_name_server_detail(topic, false, None, true)
+            .await
+    }
+
+    /// Overrides the cache TTL of found routes for a single topic.
+    pub fn set_topic_route_cache_ttl(&self, topic: impl Into<CheetahString>, ttl_millis: u64) {
+        self.topic_route_cache.set_topic_ttl(topic, ttl_millis);
+    }
+
+    pub fn invalidate_topic_route_cache(&self, topic: &CheetahString) {
+        self.topic_route_cache.invalidate(topic);
+    }
+
+    async fn update_topic_route_info_from_name_server_detail(
+        &mut self,
+        topic: &CheetahString,
+        is_default: bool,
+        producer_config: Option<&Arc<ProducerConfig>>,
+        force: bool,
     ) -> bool {
         let lock = self.lock_namesrv.lock().await;
-        let topic_route_data = if is_default && producer_config.is_some() {
-            let mut result = self
-                .mq_client_api_impl
+        let is_default = is_default && producer_config.is_some();
+        let cache_key = if is_default {
+            CheetahString::from_static_str(TopicValidator::AUTO_CREATE_TOPIC_KEY_TOPIC)
+        } else {
+            topic.clone()
+        };
+        if !force {
+            match self.topic_route_cache.state(&cache_key) {
+                RouteCacheState::NotFound => {
+                    debug!(
+                        "skip updateTopicRouteInfoFromNameServer, Topic: {} is cached as not exist",
+                        cache_key
+                    );
+                    return false;
+                }
+                RouteCacheState::Fresh
+                    if !is_default && self.topic_route_table.read().await.contains_key(topic) =>
+                {
+                    return false;
+                }
+                _ => {}
+            }
+        }
+        let result = if is_default {
+            self.mq_client_api_impl
                 .as_mut()
                 .unwrap()
                 .get_default_topic_route_info_from_name_server(
                     self.client_config.mq_client_api_timeout,
                 )
                 .await
-                .unwrap_or(None);
-            if let Some(topic_route_data) = result.as_mut() {
-                for data in topic_route_data.queue_datas.iter_mut() {
-                    let queue_nums = producer_config
-                        .unwrap()
-                        .default_topic_queue_nums()
-                        .max(data.read_queue_nums);
-                    data.read_queue_nums = queue_nums;
-                    data.write_queue_nums = queue_nums;
-                }
-            }
-            result
         } else {
             self.mq_client_api_impl
                 .as_mut()
@@ -554,8 +671,34 @@ impl MQClientInstance {
                     self.client_config.mq_client_api_timeout,
                 )
                 .await
-                .unwrap_or(None)
         };
+        let mut topic_route_data = match result {
+            Ok(topic_route_data) => {
+                if topic_route_data.is_some() {
+                    self.topic_route_cache.record_found(&cache_key);
+                }
+                topic_route_data
+            }
+            Err(MQClientError::MQClientErr(err))
+                if err.response_code() == ResponseCode::TopicNotExist as i32 =>
+            {
+                self.topic_route_cache.record_not_found(&cache_key);
+                None
+            }
+            Err(_) => None,
+        };
+        if is_default {
+            if let Some(topic_route_data) = topic_route_data.as_mut() {
+                for data in topic_route_data.queue_datas.iter_mut() {
+                    let queue_nums = producer_config
+                        .unwrap()
+                        .default_topic_queue_nums()
+                        .max(data.read_queue_nums);
+                    data.read_queue_nums = queue_nums;
+                    data.write_queue_nums = queue_nums;
+                }
+            }
+        }
         if let Some(mut topic_route_data) = topic_route_data {
             let mut topic_route_table = self.topic_route_table.write().await;
             let old = topic_route_table.get(topic);
@@ -569,6 +712,7 @@ impl MQClientInstance {
                 )
             }
             if changed {
+                let route_change_event = TopicRouteChangeEvent::diff(topic, old, &topic_route_data);
                 let mut broker_addr_table = self.broker_addr_table.write().await;
                 for bd in topic_route_data.broker_datas.iter() {
                     broker_addr_table.insert(bd.broker_name().clone(), bd.broker_addrs().clone());
@@ -619,6 +763,10 @@ impl MQClientInstance {
                 }
                 let clone_topic_route_data = TopicRouteData::from_existing(&topic_route_data);
                 topic_route_table.insert(topic.clone(), clone_topic_route_data);
+                drop(topic_route_table);
+                if let Some(event) = route_change_event {
+                    self.notify_topic_route_changed(event);
+                }
                 return true;
             }
         } else {
@@ -633,6 +781,33 @@ impl MQClientInstance {
         false
     }
 
+    /// Registers a listener that is invoked whenever the route of any topic used by this client
+    /// changes.
+    pub fn register_topic_route_change_listener(
+        &self,
+        listener: Arc<dyn TopicRouteChangeListener>,
+    ) {
+        self.topic_route_change_listeners.write().push(listener);
+    }
+
+    /// Returns a receiver of topic route change events. Slow receivers skip lagged events.
+    pub fn subscribe_topic_route_change(&self) -> broadcast::Receiver<TopicRouteChangeEvent> {
+        self.topic_route_change_tx.subscribe()
+    }
+
+    fn notify_topic_route_changed(&self, event: TopicRouteChangeEvent) {
+        info!(
+            "the topic[{}] route changed, added brokers: {:?}, removed brokers: {:?}, queue \
+             changes: {:?}",
+            event.topic, event.added_brokers, event.removed_brokers, event.queue_nums_changes
+        );
+        for listener in self.topic_route_change_listeners.read().iter() {
+            listener.on_route_changed(&event);
+        }
+        // No subscriber is not an error
+        let _ = self.topic_route_change_tx.send(event);
+    }
+
     async fn is_need_update_topic_route_info(&self, topic: &CheetahString) -> bool {
         let mut result = false;
         let producer_table = self.producer_table.read().await;
@@ -760,7 +935,152 @@ impl MQClientInstance {
     }
 
     async fn send_heartbeat_to_all_broker_v2(&self, is_rebalance: bool) -> bool {
-        unimplemented!()
+        let heartbeat_data_with_sub = self.prepare_heartbeat_data(false).await;
+        let producer_empty = heartbeat_data_with_sub.producer_data_set.is_empty();
+        let consumer_empty = heartbeat_data_with_sub.consumer_data_set.is_empty();
+        if producer_empty && consumer_empty {
+            warn!(
+                "sending heartbeat, but no consumer and no producer. [{}]",
+                self.client_id
+            );
+            return false;
+        }
+        let broker_addr_table = self.broker_addr_table.read().await;
+        if broker_addr_table.is_empty() {
+            return false;
+        }
+        if is_rebalance {
+            // a rebalance may follow a subscription change, send everything once
+            self.broker_addr_heartbeat_fingerprint_table
+                .write()
+                .await
+                .clear();
+        }
+        let (heartbeat_data_with_sub, heartbeat_data_without_sub) = self
+            .fingerprint_heartbeat_data(heartbeat_data_with_sub)
+            .await;
+        for (broker_name, broker_addrs) in broker_addr_table.iter() {
+            if broker_addrs.is_empty() {
+                continue;
+            }
+            for (id, addr) in broker_addrs.iter() {
+                if addr.is_empty() {
+                    continue;
+                }
+                if consumer_empty && *id != mix_all::MASTER_ID {
+                    continue;
+                }
+                self.send_heartbeat_to_broker_v2(
+                    *id,
+                    broker_name,
+                    addr,
+                    &heartbeat_data_with_sub,
+                    &heartbeat_data_without_sub,
+                )
+                .await;
+            }
+        }
+        true
+    }
+
+    /// Stamps the subscription fingerprint on `heartbeat_data_with_sub` and derives the
+    /// lightweight heartbeat sent while brokers already know the subscriptions.
+    async fn fingerprint_heartbeat_data(
+        &self,
+        mut heartbeat_data_with_sub: HeartbeatData,
+    ) -> (HeartbeatData, HeartbeatData) {
+        let mut heartbeat_data_without_sub = self.prepare_heartbeat_data(true).await;
+        let fingerprint = heartbeat_data_with_sub.compute_heartbeat_fingerprint();
+        heartbeat_data_with_sub.heartbeat_fingerprint = fingerprint;
+        heartbeat_data_without_sub.heartbeat_fingerprint = fingerprint;
+        (heartbeat_data_with_sub, heartbeat_data_without_sub)
+    }
+
+    /// Sends the heartbeat without subscriptions when `addr` already acknowledged the current
+    /// fingerprint, and falls back to the full heartbeat as soon as the broker reports that its
+    /// subscriptions are stale.
+    async fn send_heartbeat_to_broker_v2(
+        &self,
+        id: u64,
+        broker_name: &CheetahString,
+        addr: &CheetahString,
+        heartbeat_data_with_sub: &HeartbeatData,
+        heartbeat_data_without_sub: &HeartbeatData,
+    ) -> bool {
+        let fingerprint = heartbeat_data_with_sub.heartbeat_fingerprint;
+        let mut without_sub = self
+            .broker_support_v2_heartbeat_set
+            .read()
+            .await
+            .contains(addr)
+            && self
+                .broker_addr_heartbeat_fingerprint_table
+                .read()
+                .await
+                .get(addr)
+                .is_some_and(|last_fingerprint| *last_fingerprint == fingerprint);
+        loop {
+            let heartbeat_data = if without_sub {
+                heartbeat_data_without_sub
+            } else {
+                heartbeat_data_with_sub
+            };
+            let result = self
+                .mq_client_api_impl
+                .as_ref()
+                .unwrap()
+                .mut_from_ref()
+                .send_heartbeat_v2(
+                    addr,
+                    heartbeat_data,
+                    self.client_config.mq_client_api_timeout,
+                )
+                .await;
+            let result = match result {
+                Ok(result) => result,
+                Err(err) => {
+                    warn!(
+                        "send heart beat v2 to broker[{} {} {}] failed, {}",
+                        broker_name, id, addr, err
+                    );
+                    self.broker_addr_heartbeat_fingerprint_table
+                        .write()
+                        .await
+                        .remove(addr);
+                    return false;
+                }
+            };
+            self.broker_version_table
+                .write()
+                .await
+                .entry(broker_name.clone())
+                .or_default()
+                .insert(addr.clone(), result.version);
+            if !result.is_support_v2 {
+                self.broker_support_v2_heartbeat_set
+                    .write()
+                    .await
+                    .remove(addr);
+                return true;
+            }
+            self.broker_support_v2_heartbeat_set
+                .write()
+                .await
+                .insert(addr.clone());
+            if without_sub && result.is_sub_change {
+                self.broker_addr_heartbeat_fingerprint_table
+                    .write()
+                    .await
+                    .remove(addr);
+                without_sub = false;
+                continue;
+            }
+            self.broker_addr_heartbeat_fingerprint_table
+                .write()
+                .await
+                .insert(addr.clone(), fingerprint);
+            return true;
+        }
     }
 
     async fn send_heartbeat_to_all_broker(&self) -> bool {
@@ -816,7 +1136,16 @@ impl MQClientInstance {
             }
 
             if self.client_config.use_heartbeat_v2 {
-                unimplemented!("sendHeartbeatToBrokerV2")
+                let (heartbeat_data_with_sub, heartbeat_data_without_sub) =
+                    self.fingerprint_heartbeat_data(heartbeat_data).await;
+                self.send_heartbeat_to_broker_v2(
+                    id,
+                    broker_name,
+                    addr,
+                    &heartbeat_data_with_sub,
+                    &heartbeat_data_without_sub,
+                )
+                .await
             } else {
                 self.send_heartbeat_to_broker_inner(id, broker_name, addr, &heartbeat_data)
                     .await
@@ -908,13 +1237,11 @@ impl MQClientInstance {
                 consume_type: value.consume_type(),
                 message_model: value.message_model(),
                 consume_from_where: value.consume_from_where(),
-                subscription_data_set: value.subscriptions(),
+                subscription_data_set: HashSet::new(),
                 unit_mode: value.is_unit_mode(),
             };
             if !is_without_sub {
-                value.subscriptions().iter().for_each(|sub| {
-                    consumer_data.subscription_data_set.insert(sub.clone());
-                });
+                consumer_data.subscription_data_set = value.subscriptions();
             }
             heartbeat_data.consumer_data_set.insert(consumer_data);
         }
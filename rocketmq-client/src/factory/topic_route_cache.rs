@@ -0,0 +1,159 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::HashMap;
+
+use cheetah_string::CheetahString;
+use parking_lot::RwLock;
+use rocketmq_common::TimeUtils::get_current_millis;
+
+/// Freshness of the cached name server lookup of a topic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RouteCacheState {
+    /// The route was fetched within its TTL.
+    Fresh,
+    /// The name server reported the topic as not existing within the negative TTL.
+    NotFound,
+    /// The topic was never looked up or its entry has expired.
+    Expired,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct RouteCacheEntry {
+    fetched_at: u64,
+    found: bool,
+}
+
+/// Remembers when the route of each topic was last fetched from the name server, so repeated
+/// on-demand lookups within the TTL are answered locally. Lookups of topics which do not exist
+/// are cached with a shorter TTL, preventing request storms when unknown topics are probed.
+pub struct TopicRouteCache {
+    ttl_millis: u64,
+    negative_ttl_millis: u64,
+    topic_ttl_millis: RwLock<HashMap<CheetahString, u64>>,
+    entries: RwLock<HashMap<CheetahString, RouteCacheEntry>>,
+}
+
+impl TopicRouteCache {
+    pub fn new(ttl_millis: u64, negative_ttl_millis: u64) -> Self {
+        Self {
+            ttl_millis,
+            negative_ttl_millis,
+            topic_ttl_millis: RwLock::new(HashMap::new()),
+            entries: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Overrides the TTL of found routes for a single topic.
+    pub fn set_topic_ttl(&self, topic: impl Into<CheetahString>, ttl_millis: u64) {
+        self.topic_ttl_millis
+            .write()
+            .insert(topic.into(), ttl_millis);
+    }
+
+    pub fn remove_topic_ttl(&self, topic: &CheetahString) {
+        self.topic_ttl_millis.write().remove(topic);
+    }
+
+    pub fn state(&self, topic: &CheetahString) -> RouteCacheState {
+        self.state_at(topic, get_current_millis())
+    }
+
+    fn state_at(&self, topic: &CheetahString, now: u64) -> RouteCacheState {
+        let Some(entry) = self.entries.read().get(topic).copied() else {
+            return RouteCacheState::Expired;
+        };
+        let ttl_millis = if entry.found {
+            self.topic_ttl_millis
+                .read()
+                .get(topic)
+                .copied()
+                .unwrap_or(self.ttl_millis)
+        } else {
+            self.negative_ttl_millis
+        };
+        if now.saturating_sub(entry.fetched_at) >= ttl_millis {
+            RouteCacheState::Expired
+        } else if entry.found {
+            RouteCacheState::Fresh
+        } else {
+            RouteCacheState::NotFound
+        }
+    }
+
+    pub fn record_found(&self, topic: &CheetahString) {
+        self.record_at(topic, true, get_current_millis());
+    }
+
+    pub fn record_not_found(&self, topic: &CheetahString) {
+        self.record_at(topic, false, get_current_millis());
+    }
+
+    fn record_at(&self, topic: &CheetahString, found: bool, now: u64) {
+        self.entries.write().insert(
+            topic.clone(),
+            RouteCacheEntry {
+                fetched_at: now,
+                found,
+            },
+        );
+    }
+
+    pub fn invalidate(&self, topic: &CheetahString) {
+        self.entries.write().remove(topic);
+    }
+
+    pub fn invalidate_all(&self) {
+        self.entries.write().clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn found_and_not_found_entries_use_their_own_ttl() {
+        let cache = TopicRouteCache::new(1000, 100);
+        let found = CheetahString::from("found");
+        let missing = CheetahString::from("missing");
+        cache.record_at(&found, true, 0);
+        cache.record_at(&missing, false, 0);
+
+        assert_eq!(cache.state_at(&found, 500), RouteCacheState::Fresh);
+        assert_eq!(cache.state_at(&missing, 50), RouteCacheState::NotFound);
+        assert_eq!(cache.state_at(&missing, 100), RouteCacheState::Expired);
+        assert_eq!(cache.state_at(&found, 1000), RouteCacheState::Expired);
+        assert_eq!(
+            cache.state_at(&CheetahString::from("unknown"), 0),
+            RouteCacheState::Expired
+        );
+    }
+
+    #[test]
+    fn topic_ttl_override_and_invalidate() {
+        let cache = TopicRouteCache::new(1000, 100);
+        let topic = CheetahString::from("topic");
+        cache.set_topic_ttl(topic.clone(), 10);
+        cache.record_at(&topic, true, 0);
+        assert_eq!(cache.state_at(&topic, 10), RouteCacheState::Expired);
+
+        cache.remove_topic_ttl(&topic);
+        assert_eq!(cache.state_at(&topic, 10), RouteCacheState::Fresh);
+        cache.invalidate(&topic);
+        assert_eq!(cache.state_at(&topic, 10), RouteCacheState::Expired);
+    }
+}
@@ -14,8 +14,6 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
-use std::net::SocketAddr;
-
 use bytes::Bytes;
 use cheetah_string::CheetahString;
 use rocketmq_common::common::compression::compressor_factory::CompressorFactory;
@@ -24,6 +22,7 @@ use rocketmq_common::common::message::message_ext::MessageExt;
 use rocketmq_common::common::message::MessageConst;
 use rocketmq_common::common::message::MessageTrait;
 use rocketmq_common::common::sys_flag::message_sys_flag::MessageSysFlag;
+use rocketmq_common::utils::network_util::NetworkUtil;
 use rocketmq_common::MessageAccessor::MessageAccessor;
 use rocketmq_common::MessageDecoder;
 use rocketmq_common::TimeUtils::get_current_millis;
@@ -116,10 +115,15 @@ impl ClientRemotingProcessor {
         msg.queue_id = request_header.queue_id;
         msg.store_timestamp = request_header.store_timestamp;
         if !request_header.born_host.is_empty() {
-            match request_header.born_host.parse::<SocketAddr>() {
-                Ok(value) => msg.born_host = value,
-                Err(_) => {
-                    warn!("parse born_host failed: {}", request_header.store_host);
+            match NetworkUtil::parse_socket_addr(&request_header.born_host) {
+                Some(value) => {
+                    if value.is_ipv6() {
+                        msg.with_born_host_v6_flag();
+                    }
+                    msg.born_host = value
+                }
+                None => {
+                    warn!("parse born_host failed: {}", request_header.born_host);
                     return Ok(Some(
                         response
                             .set_code(ResponseCode::SystemError)
@@ -129,9 +133,14 @@ impl ClientRemotingProcessor {
             }
         }
         if !request_header.store_host.is_empty() {
-            match request_header.store_host.parse::<SocketAddr>() {
-                Ok(value) => msg.store_host = value,
-                Err(_) => {
+            match NetworkUtil::parse_socket_addr(&request_header.store_host) {
+                Some(value) => {
+                    if value.is_ipv6() {
+                        msg.with_store_host_v6_flag();
+                    }
+                    msg.store_host = value
+                }
+                None => {
                     warn!("parse store_host failed: {}", request_header.store_host);
                     return Ok(Some(
                         response
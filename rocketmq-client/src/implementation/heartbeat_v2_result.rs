@@ -0,0 +1,26 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+/// Outcome of a heartbeat sent with a subscription fingerprint.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HeartbeatV2Result {
+    pub version: i32,
+    /// The broker understands fingerprinted heartbeats.
+    pub is_support_v2: bool,
+    /// The broker does not know the subscriptions matching the fingerprint and needs a
+    /// heartbeat carrying them in full.
+    pub is_sub_change: bool,
+}
@@ -14,6 +14,7 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
+use std::collections::HashMap;
 use std::collections::HashSet;
 use std::sync::atomic::AtomicU32;
 use std::sync::Arc;
@@ -21,6 +22,8 @@ use std::time::Instant;
 
 use cheetah_string::CheetahString;
 use lazy_static::lazy_static;
+use rocketmq_common::common::compression::compression_type::CompressionType;
+use rocketmq_common::common::compression::compressor_factory::CompressorFactory;
 use rocketmq_common::common::message::message_batch::MessageBatch;
 use rocketmq_common::common::message::message_client_id_setter::MessageClientIDSetter;
 use rocketmq_common::common::message::message_enum::MessageRequestMode;
@@ -33,6 +36,7 @@ use rocketmq_common::common::mix_all;
 use rocketmq_common::common::namesrv::default_top_addressing::DefaultTopAddressing;
 use rocketmq_common::common::namesrv::name_server_update_callback::NameServerUpdateCallback;
 use rocketmq_common::common::namesrv::top_addressing::TopAddressing;
+use rocketmq_common::common::sys_flag::message_sys_flag::MessageSysFlag;
 use rocketmq_common::common::sys_flag::pull_sys_flag::PullSysFlag;
 use rocketmq_common::common::topic::TopicValidator;
 use rocketmq_remoting::base::connection_net_event::ConnectionNetEvent;
@@ -41,18 +45,26 @@ use rocketmq_remoting::clients::RemotingClient;
 use rocketmq_remoting::code::request_code::RequestCode;
 use rocketmq_remoting::code::response_code::ResponseCode;
 use rocketmq_remoting::protocol::body::check_client_request_body::CheckClientRequestBody;
+use rocketmq_remoting::protocol::body::end_transaction_batch_body::EndTransactionBatchBody;
+use rocketmq_remoting::protocol::body::examine_consume_queue_body::ExamineConsumeQueueBody;
 use rocketmq_remoting::protocol::body::get_consumer_listby_group_response_body::GetConsumerListByGroupResponseBody;
 use rocketmq_remoting::protocol::body::query_assignment_request_body::QueryAssignmentRequestBody;
 use rocketmq_remoting::protocol::body::query_assignment_response_body::QueryAssignmentResponseBody;
+use rocketmq_remoting::protocol::body::replay_subscription_body::CreateReplaySubscriptionRequestBody;
+use rocketmq_remoting::protocol::body::replay_subscription_body::ReplaySubscription;
 use rocketmq_remoting::protocol::body::request::lock_batch_request_body::LockBatchRequestBody;
 use rocketmq_remoting::protocol::body::response::lock_batch_response_body::LockBatchResponseBody;
 use rocketmq_remoting::protocol::body::set_message_request_mode_request_body::SetMessageRequestModeRequestBody;
 use rocketmq_remoting::protocol::body::unlock_batch_request_body::UnlockBatchRequestBody;
 use rocketmq_remoting::protocol::header::change_invisible_time_request_header::ChangeInvisibleTimeRequestHeader;
 use rocketmq_remoting::protocol::header::change_invisible_time_response_header::ChangeInvisibleTimeResponseHeader;
+use rocketmq_remoting::protocol::header::check_confirm_offset_response_header::CheckConfirmOffsetResponseHeader;
 use rocketmq_remoting::protocol::header::client_request_header::GetRouteInfoRequestHeader;
+use rocketmq_remoting::protocol::header::client_request_header::GetRouteInfoResponseHeader;
 use rocketmq_remoting::protocol::header::consumer_send_msg_back_request_header::ConsumerSendMsgBackRequestHeader;
+use rocketmq_remoting::protocol::header::end_transaction_batch_request_header::EndTransactionBatchRequestHeader;
 use rocketmq_remoting::protocol::header::end_transaction_request_header::EndTransactionRequestHeader;
+use rocketmq_remoting::protocol::header::examine_consume_queue_request_header::ExamineConsumeQueueRequestHeader;
 use rocketmq_remoting::protocol::header::extra_info_util::ExtraInfoUtil;
 use rocketmq_remoting::protocol::header::get_consumer_listby_group_request_header::GetConsumerListByGroupRequestHeader;
 use rocketmq_remoting::protocol::header::get_max_offset_request_header::GetMaxOffsetRequestHeader;
@@ -92,6 +104,8 @@ use crate::client_error::MQBrokerErr;
 use crate::client_error::MQClientError;
 use crate::client_error::MQClientError::MQClientBrokerError;
 use crate::client_error::MQClientError::RemotingError;
+use crate::common::callback_executor::CallbackExecutor;
+use crate::common::callback_executor::CallbackExecutorStats;
 use crate::consumer::ack_callback::AckCallback;
 use crate::consumer::ack_result::AckResult;
 use crate::consumer::ack_status::AckStatus;
@@ -103,6 +117,7 @@ use crate::factory::mq_client_instance::MQClientInstance;
 use crate::hook::send_message_context::SendMessageContext;
 use crate::implementation::client_remoting_processor::ClientRemotingProcessor;
 use crate::implementation::communication_mode::CommunicationMode;
+use crate::implementation::heartbeat_v2_result::HeartbeatV2Result;
 use crate::mq_client_err;
 use crate::producer::producer_impl::default_mq_producer_impl::DefaultMQProducerImpl;
 use crate::producer::producer_impl::topic_publish_info::TopicPublishInfo;
@@ -124,6 +139,9 @@ pub struct MQClientAPIImpl {
     // client_remoting_processor: ClientRemotingProcessor,
     name_srv_addr: Option<String>,
     client_config: ClientConfig,
+    callback_executor: Arc<CallbackExecutor>,
+    // last route fetched per topic with its data version, returned again on `NotModified`
+    route_versions: parking_lot::Mutex<HashMap<CheetahString, (i64, TopicRouteData)>>,
 }
 
 impl NameServerUpdateCallback for MQClientAPIImpl {
@@ -146,6 +164,11 @@ impl MQClientAPIImpl {
             default_client.register_rpc_hook(hook);
         }
 
+        let callback_executor = Arc::new(CallbackExecutor::new(
+            "NettyClientPublicExecutor_",
+            client_config.client_callback_executor_threads,
+            client_config.client_callback_executor_max_pending,
+        ));
         MQClientAPIImpl {
             remoting_client: ArcMut::new(default_client),
             top_addressing: Box::new(DefaultTopAddressing::new(
@@ -155,9 +178,15 @@ impl MQClientAPIImpl {
             //client_remoting_processor,
             name_srv_addr: None,
             client_config,
+            callback_executor,
+            route_versions: Default::default(),
         }
     }
 
+    pub fn callback_executor_stats(&self) -> &CallbackExecutorStats {
+        self.callback_executor.stats()
+    }
+
     pub async fn start(&self) {
         let client = ArcMut::downgrade(&self.remoting_client);
         self.remoting_client.start(client).await;
@@ -224,10 +253,17 @@ impl MQClientAPIImpl {
         timeout_millis: u64,
         allow_topic_not_exist: bool,
     ) -> Result<Option<TopicRouteData>> {
+        let topic = CheetahString::from_slice(topic);
+        let data_version = self
+            .route_versions
+            .lock()
+            .get(&topic)
+            .map(|(data_version, _)| *data_version);
         let request_header = GetRouteInfoRequestHeader {
-            topic: CheetahString::from_slice(topic),
-            accept_standard_json_only: None,
-            topic_request_header: None,
+            topic: topic.clone(),
+            accept_compressed: Some(true),
+            data_version,
+            ..Default::default()
         };
         let request = RemotingCommand::create_request_command(
             RequestCode::GetRouteinfoByTopic,
@@ -243,16 +279,36 @@ impl MQClientAPIImpl {
                 let response_code = ResponseCode::from(code);
                 match response_code {
                     ResponseCode::Success => {
-                        let body = result.body();
+                        let response_header = result
+                            .decode_command_custom_header::<GetRouteInfoResponseHeader>()
+                            .unwrap_or_default();
+                        let mut body = result.body().clone();
+                        if let (Some(flag), Some(compressed)) =
+                            (response_header.body_compression_type, &body)
+                        {
+                            body = Some(decompress_response_body(flag, compressed)?);
+                        }
                         if body.is_some() && !body.as_ref().unwrap().is_empty() {
                             let route_data =
                                 TopicRouteData::decode(body.as_ref().unwrap().as_ref());
                             if let Ok(data) = route_data {
+                                if response_header.data_version != 0 {
+                                    self.route_versions.lock().insert(
+                                        topic,
+                                        (response_header.data_version, data.clone()),
+                                    );
+                                }
                                 return Ok(Some(data));
                             }
                         }
                     }
+                    ResponseCode::NotModified => {
+                        if let Some((_, data)) = self.route_versions.lock().get(&topic) {
+                            return Ok(Some(data.clone()));
+                        }
+                    }
                     ResponseCode::TopicNotExist => {
+                        self.route_versions.lock().remove(&topic);
                         if allow_topic_not_exist {
                             warn!(
                                 "get Topic [{}] RouteInfoFromNameServer is not exist value",
@@ -493,7 +549,9 @@ impl MQClientAPIImpl {
                             producer.execute_send_message_hook_after(context);
                         }
                         let duration = (Instant::now() - begin_start_time).as_millis() as u64;
-                        send_callback.as_ref().unwrap()(Some(&result), None);
+                        let send_callback = send_callback.unwrap();
+                        self.callback_executor
+                            .execute(move || send_callback(Some(&result), None));
                         producer
                             .update_fault_item(broker_name.clone(), duration, false, true)
                             .await;
@@ -702,6 +760,46 @@ impl MQClientAPIImpl {
         )
     }
 
+    pub async fn send_heartbeat_v2(
+        &mut self,
+        addr: &CheetahString,
+        heartbeat_data: &HeartbeatData,
+        timeout_millis: u64,
+    ) -> Result<HeartbeatV2Result> {
+        let request = RemotingCommand::create_request_command(
+            RequestCode::HeartBeat,
+            HeartbeatRequestHeader::default(),
+        )
+        .set_language(self.client_config.language)
+        .set_body(
+            heartbeat_data
+                .encode()
+                .expect("encode HeartbeatData failed"),
+        );
+        let response = self
+            .remoting_client
+            .invoke_async(Some(addr), request, timeout_millis)
+            .await?;
+        if ResponseCode::from(response.code()) == ResponseCode::Success {
+            let ext_flag = |key: &str| {
+                response
+                    .ext_fields()
+                    .and_then(|ext_fields| ext_fields.get(key))
+                    .is_some_and(|value| value.as_str() == "true")
+            };
+            return Ok(HeartbeatV2Result {
+                version: response.version(),
+                is_support_v2: ext_flag(mix_all::IS_SUPPORT_HEART_BEAT_V2),
+                is_sub_change: ext_flag(mix_all::IS_SUB_CHANGE),
+            });
+        }
+        client_broker_err!(
+            response.code(),
+            response.remark().map_or("".to_string(), |s| s.to_string()),
+            addr.to_string()
+        )
+    }
+
     pub async fn check_client_in_broker(
         &mut self,
         broker_addr: &str,
@@ -988,6 +1086,10 @@ impl MQClientAPIImpl {
         let response_header = response
             .decode_command_custom_header::<PullMessageResponseHeader>()
             .unwrap();
+        let mut message_binary = response.take_body();
+        if let (Some(flag), Some(body)) = (response_header.body_compression_type, &message_binary) {
+            message_binary = Some(decompress_response_body(flag, body)?);
+        }
         let pull_result = PullResultExt {
             pull_result: PullResult {
                 pull_status,
@@ -997,7 +1099,7 @@ impl MQClientAPIImpl {
                 msg_found_list: vec![],
             },
             suggest_which_broker_id: response_header.suggest_which_broker_id,
-            message_binary: response.take_body(),
+            message_binary,
             offset_delta: response_header.offset_delta,
         };
         Ok(pull_result)
@@ -1199,6 +1301,37 @@ impl MQClientAPIImpl {
         Ok(())
     }
 
+    /// Ends all half messages of a transactional batch, unlike the single message variant
+    /// this waits for the broker so a failed commit can be reported to the caller.
+    pub async fn end_transaction_batch(
+        &mut self,
+        addr: &CheetahString,
+        request_header: EndTransactionBatchRequestHeader,
+        body: EndTransactionBatchBody,
+        timeout_millis: u64,
+    ) -> Result<()> {
+        let request = RemotingCommand::create_request_command(
+            RequestCode::EndTransactionBatch,
+            request_header,
+        )
+        .set_body(
+            body.encode()
+                .expect("encode EndTransactionBatchBody failed"),
+        );
+        let response = self
+            .remoting_client
+            .invoke_async(Some(addr), request, timeout_millis)
+            .await?;
+        if ResponseCode::from(response.code()) == ResponseCode::Success {
+            return Ok(());
+        }
+        client_broker_err!(
+            response.code(),
+            response.remark().map_or("".to_string(), |s| s.to_string()),
+            addr.to_string()
+        )
+    }
+
     pub async fn get_max_offset(
         &mut self,
         addr: &str,
@@ -1338,6 +1471,118 @@ impl MQClientAPIImpl {
         )
     }
 
+    pub async fn create_replay_subscription(
+        &mut self,
+        addr: &CheetahString,
+        request_body: CreateReplaySubscriptionRequestBody,
+        timeout_millis: u64,
+    ) -> Result<ReplaySubscription> {
+        let request = RemotingCommand::new_request(
+            RequestCode::CreateReplaySubscription,
+            request_body
+                .encode()
+                .expect("encode CreateReplaySubscriptionRequestBody failed"),
+        );
+        let response = self
+            .remoting_client
+            .invoke_async(Some(addr), request, timeout_millis)
+            .await?;
+        if ResponseCode::from(response.code()) == ResponseCode::Success {
+            if let Some(subscription) = response
+                .body()
+                .as_ref()
+                .and_then(|body| ReplaySubscription::decode(body.as_ref()).ok())
+            {
+                return Ok(subscription);
+            }
+        }
+        client_broker_err!(
+            response.code(),
+            response.remark().map_or("".to_string(), |s| s.to_string()),
+            addr.to_string()
+        )
+    }
+
+    pub async fn delete_replay_subscription(
+        &mut self,
+        addr: &CheetahString,
+        consumer_group: &CheetahString,
+        timeout_millis: u64,
+    ) -> Result<()> {
+        let request =
+            RemotingCommand::create_remoting_command(RequestCode::DeleteReplaySubscription)
+                .set_ext_fields(HashMap::from([(
+                    CheetahString::from_static_str("consumerGroup"),
+                    consumer_group.clone(),
+                )]));
+        let response = self
+            .remoting_client
+            .invoke_async(Some(addr), request, timeout_millis)
+            .await?;
+        if ResponseCode::from(response.code()) == ResponseCode::Success {
+            return Ok(());
+        }
+        client_broker_err!(
+            response.code(),
+            response.remark().map_or("".to_string(), |s| s.to_string()),
+            addr.to_string()
+        )
+    }
+
+    pub async fn check_confirm_offset(
+        &mut self,
+        addr: &CheetahString,
+        timeout_millis: u64,
+    ) -> Result<i64> {
+        let request = RemotingCommand::create_remoting_command(RequestCode::CheckConfirmOffset);
+        let response = self
+            .remoting_client
+            .invoke_async(Some(addr), request, timeout_millis)
+            .await?;
+        if ResponseCode::from(response.code()) == ResponseCode::Success {
+            if let Ok(response_header) =
+                response.decode_command_custom_header::<CheckConfirmOffsetResponseHeader>()
+            {
+                return Ok(response_header.confirm_offset);
+            }
+        }
+        client_broker_err!(
+            response.code(),
+            response.remark().map_or("".to_string(), |s| s.to_string()),
+            addr.to_string()
+        )
+    }
+
+    pub async fn examine_consume_queue(
+        &self,
+        addr: &CheetahString,
+        request_header: ExamineConsumeQueueRequestHeader,
+        timeout_millis: u64,
+    ) -> Result<ExamineConsumeQueueBody> {
+        let request = RemotingCommand::create_request_command(
+            RequestCode::ExamineConsumeQueue,
+            request_header,
+        );
+        let response = self
+            .remoting_client
+            .invoke_async(Some(addr), request, timeout_millis)
+            .await?;
+        if ResponseCode::from(response.code()) == ResponseCode::Success {
+            if let Some(body) = response
+                .body()
+                .as_ref()
+                .and_then(|body| ExamineConsumeQueueBody::decode(body.as_ref()).ok())
+            {
+                return Ok(body);
+            }
+        }
+        client_broker_err!(
+            response.code(),
+            response.remark().map_or("".to_string(), |s| s.to_string()),
+            addr.to_string()
+        )
+    }
+
     pub async fn change_invisible_time_async(
         &self,
         broker_name: &CheetahString,
@@ -1396,3 +1641,40 @@ impl MQClientAPIImpl {
         Ok(())
     }
 }
+
+/// Decompresses a response body the broker or name server compressed, `flag` is the compression
+/// type flag from the `body_compression_type` of the response header.
+fn decompress_response_body(flag: i32, body: &[u8]) -> Result<bytes::Bytes> {
+    let compression_type = (flag & MessageSysFlag::COMPRESSION_TYPE_COMPARATOR) >> 8;
+    if !(1..=3).contains(&compression_type) {
+        return mq_client_err!(format!("unknown pull body compression type {}", flag));
+    }
+    match CompressorFactory::get_compressor(CompressionType::find_by_value(compression_type))
+        .decompress(body)
+    {
+        Ok(body) => Ok(body),
+        Err(e) => mq_client_err!(format!("decompress pull body failed, {}", e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decompress_pull_body_restores_broker_compressed_body() {
+        let body = vec![7u8; 4096];
+        let compressed = CompressorFactory::get_compressor(CompressionType::Zstd)
+            .compress(&body, 3)
+            .unwrap();
+        let flag = CompressionType::Zstd.get_compression_flag();
+        assert_eq!(
+            decompress_response_body(flag, &compressed)
+                .unwrap()
+                .as_ref(),
+            body.as_slice()
+        );
+        assert!(decompress_response_body(0x7 << 8, &compressed).is_err());
+        assert!(decompress_response_body(flag, b"not zstd").is_err());
+    }
+}
@@ -14,6 +14,7 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
+pub mod broker_circuit_breaker;
 pub(crate) mod latency_fault_tolerance;
 pub(crate) mod latency_fault_tolerance_impl;
 pub(crate) mod mq_fault_strategy;
@@ -0,0 +1,234 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::HashMap;
+
+use cheetah_string::CheetahString;
+use parking_lot::Mutex;
+use rocketmq_common::TimeUtils::get_current_millis;
+
+/// Per broker circuit breaker of the send path.
+///
+/// After `failure_threshold` consecutive timeouts or busy answers the circuit of a broker opens
+/// and the broker gets no traffic for `open_millis`. Once the cool down is over a single probe
+/// send is let through (half open): its success closes the circuit, its failure opens it again.
+/// Unlike the latency fault strategy this only reacts to repeated failures, not to slow sends.
+pub struct BrokerCircuitBreaker {
+    failure_threshold: u32,
+    open_millis: u64,
+    circuits: Mutex<HashMap<CheetahString, Circuit>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+    Closed,
+    Open,
+    HalfOpen,
+}
+
+/// Counters of one broker circuit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CircuitBreakerMetrics {
+    pub consecutive_failures: u32,
+    pub total_failures: u64,
+    pub total_successes: u64,
+    /// How often the circuit went from closed or half open to open.
+    pub opened_times: u64,
+    pub probe_times: u64,
+}
+
+#[derive(Debug)]
+struct Circuit {
+    state: CircuitState,
+    /// Open until this timestamp, or when the running probe is given up in half open state.
+    deadline: u64,
+    metrics: CircuitBreakerMetrics,
+}
+
+impl Default for Circuit {
+    fn default() -> Self {
+        Self {
+            state: CircuitState::Closed,
+            deadline: 0,
+            metrics: CircuitBreakerMetrics::default(),
+        }
+    }
+}
+
+impl Circuit {
+    fn permits(&self, now: u64) -> bool {
+        self.state == CircuitState::Closed || now >= self.deadline
+    }
+
+    fn open(&mut self, now: u64, open_millis: u64) {
+        self.state = CircuitState::Open;
+        self.deadline = now + open_millis;
+        self.metrics.opened_times += 1;
+    }
+}
+
+impl BrokerCircuitBreaker {
+    /// A `failure_threshold` of 0 disables the breaker, every broker is always permitted.
+    pub fn new(failure_threshold: u32, open_millis: u64) -> Self {
+        Self {
+            failure_threshold,
+            open_millis,
+            circuits: Mutex::new(HashMap::new()),
+        }
+    }
+
+    #[inline]
+    pub fn is_enabled(&self) -> bool {
+        self.failure_threshold > 0
+    }
+
+    #[inline]
+    pub fn open_millis(&self) -> u64 {
+        self.open_millis
+    }
+
+    /// Whether a send may go to `broker_name`: its circuit is closed, or open with the cool down
+    /// over and no probe running.
+    pub fn permits(&self, broker_name: &CheetahString) -> bool {
+        if !self.is_enabled() {
+            return true;
+        }
+        self.circuits
+            .lock()
+            .get(broker_name)
+            .is_none_or(|circuit| circuit.permits(get_current_millis()))
+    }
+
+    /// Called with the broker a send was routed to, turns that send into the probe of a cooled
+    /// down circuit. A probe that never reports back is given up after another `open_millis`.
+    pub fn on_selected(&self, broker_name: &CheetahString) {
+        if !self.is_enabled() {
+            return;
+        }
+        let now = get_current_millis();
+        if let Some(circuit) = self.circuits.lock().get_mut(broker_name) {
+            if circuit.state != CircuitState::Closed && now >= circuit.deadline {
+                circuit.state = CircuitState::HalfOpen;
+                circuit.deadline = now + self.open_millis;
+                circuit.metrics.probe_times += 1;
+            }
+        }
+    }
+
+    pub fn on_success(&self, broker_name: &CheetahString) {
+        if !self.is_enabled() {
+            return;
+        }
+        let mut circuits = self.circuits.lock();
+        let circuit = circuits.entry(broker_name.clone()).or_default();
+        circuit.state = CircuitState::Closed;
+        circuit.metrics.consecutive_failures = 0;
+        circuit.metrics.total_successes += 1;
+    }
+
+    /// Records a timeout or busy answer of `broker_name`, returns whether the circuit opened.
+    pub fn on_failure(&self, broker_name: &CheetahString) -> bool {
+        if !self.is_enabled() {
+            return false;
+        }
+        let now = get_current_millis();
+        let mut circuits = self.circuits.lock();
+        let circuit = circuits.entry(broker_name.clone()).or_default();
+        circuit.metrics.consecutive_failures += 1;
+        circuit.metrics.total_failures += 1;
+        let open = match circuit.state {
+            CircuitState::Closed => circuit.metrics.consecutive_failures >= self.failure_threshold,
+            CircuitState::HalfOpen => true,
+            // failures of sends that were already in flight when the circuit opened do not
+            // count, a send that went out after the cool down is a failed probe
+            CircuitState::Open => now >= circuit.deadline,
+        };
+        if open {
+            circuit.open(now, self.open_millis);
+        }
+        open
+    }
+
+    pub fn state(&self, broker_name: &CheetahString) -> CircuitState {
+        self.circuits
+            .lock()
+            .get(broker_name)
+            .map_or(CircuitState::Closed, |circuit| circuit.state)
+    }
+
+    /// Drops the circuit of a broker that no longer serves any routed topic.
+    pub fn remove(&self, broker_name: &CheetahString) {
+        self.circuits.lock().remove(broker_name);
+    }
+
+    pub fn metrics(&self) -> HashMap<CheetahString, (CircuitState, CircuitBreakerMetrics)> {
+        self.circuits
+            .lock()
+            .iter()
+            .map(|(broker_name, circuit)| (broker_name.clone(), (circuit.state, circuit.metrics)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn circuit_opens_after_consecutive_failures_and_closes_on_probe_success() {
+        let breaker = BrokerCircuitBreaker::new(2, 0);
+        let broker = CheetahString::from_static_str("broker-a");
+        assert!(!breaker.on_failure(&broker));
+        breaker.on_success(&broker);
+        assert!(!breaker.on_failure(&broker));
+        assert!(breaker.on_failure(&broker));
+        assert_eq!(breaker.state(&broker), CircuitState::Open);
+
+        // the cool down of 0ms is over, the next selected send is the probe
+        assert!(breaker.permits(&broker));
+        breaker.on_selected(&broker);
+        assert_eq!(breaker.state(&broker), CircuitState::HalfOpen);
+        breaker.on_success(&broker);
+        assert_eq!(breaker.state(&broker), CircuitState::Closed);
+
+        let (_, metrics) = breaker.metrics()[&broker];
+        assert_eq!(metrics.opened_times, 1);
+        assert_eq!(metrics.probe_times, 1);
+        assert_eq!(metrics.total_failures, 3);
+        assert_eq!(metrics.total_successes, 2);
+    }
+
+    #[test]
+    fn open_circuit_rejects_until_cool_down_and_failed_probe_reopens() {
+        let breaker = BrokerCircuitBreaker::new(1, 60_000);
+        let broker = CheetahString::from_static_str("broker-a");
+        assert!(breaker.on_failure(&broker));
+        assert!(!breaker.permits(&broker));
+        assert!(breaker.permits(&CheetahString::from_static_str("broker-b")));
+
+        breaker.circuits.lock().get_mut(&broker).unwrap().deadline = 0;
+        breaker.on_selected(&broker);
+        // the probe is running, nothing else goes to the broker
+        assert!(!breaker.permits(&broker));
+        assert!(breaker.on_failure(&broker));
+        assert_eq!(breaker.state(&broker), CircuitState::Open);
+        assert!(!breaker.permits(&broker));
+
+        let disabled = BrokerCircuitBreaker::new(0, 60_000);
+        assert!(!disabled.on_failure(&broker));
+        assert!(disabled.permits(&broker));
+    }
+}
@@ -15,16 +15,22 @@
  * limitations under the License.
  */
 use std::cell::RefCell;
+use std::collections::HashMap;
 use std::sync::atomic::AtomicBool;
 use std::sync::atomic::Ordering;
+use std::sync::Arc;
 use std::thread::scope;
 
 use cheetah_string::CheetahString;
 use rocketmq_common::common::message::message_queue::MessageQueue;
 use rocketmq_rust::ArcMut;
 use tokio::runtime::Handle;
+use tracing::warn;
 
 use crate::base::client_config::ClientConfig;
+use crate::latency::broker_circuit_breaker::BrokerCircuitBreaker;
+use crate::latency::broker_circuit_breaker::CircuitBreakerMetrics;
+use crate::latency::broker_circuit_breaker::CircuitState;
 use crate::latency::latency_fault_tolerance::LatencyFaultTolerance;
 use crate::latency::latency_fault_tolerance_impl::LatencyFaultToleranceImpl;
 use crate::producer::producer_impl::default_mq_producer_impl::DefaultResolver;
@@ -45,6 +51,8 @@ pub struct MQFaultStrategy {
     not_available_duration: &'static [u64],
     reachable_filter: Box<dyn QueueFilter>,
     available_filter: Box<dyn QueueFilter>,
+    circuit_breaker: Arc<BrokerCircuitBreaker>,
+    circuit_filter: CircuitFilter,
 }
 
 impl MQFaultStrategy {
@@ -52,6 +60,10 @@ impl MQFaultStrategy {
         let mut tolerance_impl = LatencyFaultToleranceImpl::new();
         tolerance_impl.set_start_detector_enable(client_config.start_detector_enable);
         let latency_fault_tolerance = ArcMut::new(tolerance_impl);
+        let circuit_breaker = Arc::new(BrokerCircuitBreaker::new(
+            client_config.broker_circuit_breaker_failure_threshold,
+            client_config.broker_circuit_breaker_open_millis,
+        ));
         Self {
             latency_fault_tolerance: latency_fault_tolerance.clone(),
             send_latency_fault_enable: AtomicBool::new(client_config.send_latency_enable),
@@ -64,6 +76,10 @@ impl MQFaultStrategy {
             available_filter: Box::new(AvailableFilter {
                 latency_fault_tolerance,
             }),
+            circuit_filter: CircuitFilter {
+                circuit_breaker: circuit_breaker.clone(),
+            },
+            circuit_breaker,
         }
     }
 
@@ -84,12 +100,26 @@ impl MQFaultStrategy {
         unimplemented!("not implemented")
     }
 
+    /// Selects the queue of the next send. Brokers with an open circuit are skipped, when every
+    /// broker of the topic is open `None` is returned so the send fails fast.
     pub fn select_one_message_queue(
         &self,
         tp_info: &TopicPublishInfo,
         last_broker_name: Option<&CheetahString>,
         reset_index: bool,
     ) -> Option<MessageQueue> {
+        let mq = self.select_one_message_queue_inner(tp_info, last_broker_name, reset_index)?;
+        self.circuit_breaker.on_selected(mq.get_broker_name());
+        Some(mq)
+    }
+
+    fn select_one_message_queue_inner(
+        &self,
+        tp_info: &TopicPublishInfo,
+        last_broker_name: Option<&CheetahString>,
+        reset_index: bool,
+    ) -> Option<MessageQueue> {
+        let circuit_filter: &dyn QueueFilter = &self.circuit_filter;
         THREAD_BROKER_FILTER.with(|filer| {
             filer.borrow_mut().last_broker_name = last_broker_name.cloned();
         });
@@ -98,24 +128,74 @@ impl MQFaultStrategy {
                 tp_info.reset_index();
             }
             let broker_filter = THREAD_BROKER_FILTER.with_borrow(|f| f.clone());
-            let filter = &[self.available_filter.as_ref(), &broker_filter];
+            let filter = &[
+                self.available_filter.as_ref(),
+                &broker_filter,
+                circuit_filter,
+            ];
             let mut mq = tp_info.select_one_message_queue_filters(filter);
             if mq.is_some() {
                 return mq;
             }
-            let filter = &[self.reachable_filter.as_ref(), &broker_filter];
+            let filter = &[
+                self.reachable_filter.as_ref(),
+                &broker_filter,
+                circuit_filter,
+            ];
             mq = tp_info.select_one_message_queue_filters(filter);
             if mq.is_some() {
                 return mq;
             }
-            return tp_info.select_one_message_queue_filters(&[]);
+            return self.select_any_permitted(tp_info);
         }
         let broker_filter = THREAD_BROKER_FILTER.with_borrow(|f| f.clone());
-        let mq = tp_info.select_one_message_queue_filters(&[&broker_filter]);
+        let mq = tp_info.select_one_message_queue_filters(&[&broker_filter, circuit_filter]);
         if mq.is_some() {
             return mq;
         }
-        tp_info.select_one_message_queue_filters(&[])
+        self.select_any_permitted(tp_info)
+    }
+
+    fn select_any_permitted(&self, tp_info: &TopicPublishInfo) -> Option<MessageQueue> {
+        if self.circuit_breaker.is_enabled() {
+            tp_info.select_one_message_queue_filters(&[&self.circuit_filter])
+        } else {
+            tp_info.select_one_message_queue_filters(&[])
+        }
+    }
+
+    /// Returns whether the broker of `message_queue` can take sends, always `true` when latency
+    /// fault tolerance is disabled.
+    pub fn is_message_queue_available(&self, message_queue: &MessageQueue) -> bool {
+        self.circuit_breaker
+            .permits(message_queue.get_broker_name())
+            && (!self.send_latency_fault_enable.load(Ordering::Relaxed)
+                || self.available_filter.filter(message_queue))
+    }
+
+    /// Records the outcome of a send to `broker_name` for its circuit, `failed` is a timeout or a
+    /// busy answer of the broker.
+    pub fn update_circuit(&self, broker_name: &CheetahString, failed: bool) {
+        if !failed {
+            self.circuit_breaker.on_success(broker_name);
+        } else if self.circuit_breaker.on_failure(broker_name) {
+            warn!(
+                "broker {} keeps timing out or answering busy, isolate it for {}ms",
+                broker_name,
+                self.circuit_breaker.open_millis()
+            );
+        }
+    }
+
+    #[inline]
+    pub fn is_circuit_breaker_enabled(&self) -> bool {
+        self.circuit_breaker.is_enabled()
+    }
+
+    pub fn circuit_breaker_metrics(
+        &self,
+    ) -> HashMap<CheetahString, (CircuitState, CircuitBreakerMetrics)> {
+        self.circuit_breaker.metrics()
     }
 
     pub fn get_latency_max(&self) -> &'static [u64] {
@@ -146,6 +226,16 @@ impl MQFaultStrategy {
         }
     }
 
+    /// Drops the fault item of a broker that no longer serves any routed topic, so a broker that
+    /// is later re-added starts with a clean latency record.
+    pub async fn remove_fault_item(&self, broker_name: &CheetahString) {
+        self.circuit_breaker.remove(broker_name);
+        self.latency_fault_tolerance
+            .mut_from_ref()
+            .remove(broker_name)
+            .await;
+    }
+
     fn compute_not_available_duration(&self, current_latency: u64) -> u64 {
         for i in (0..self.latency_max.len()).rev() {
             if current_latency >= self.latency_max[i] {
@@ -177,6 +267,17 @@ impl QueueFilter for BrokerFilter {
     }
 }
 
+struct CircuitFilter {
+    circuit_breaker: Arc<BrokerCircuitBreaker>,
+}
+
+impl QueueFilter for CircuitFilter {
+    fn filter(&self, message_queue: &MessageQueue) -> bool {
+        self.circuit_breaker
+            .permits(message_queue.get_broker_name())
+    }
+}
+
 struct ReachableFilter {
     latency_fault_tolerance:
         ArcMut<LatencyFaultToleranceImpl<DefaultResolver, DefaultServiceDetector>>,
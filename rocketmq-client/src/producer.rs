@@ -16,11 +16,13 @@
  */
 pub mod default_mq_produce_builder;
 pub mod default_mq_producer;
+pub mod disk_spool;
 pub mod local_transaction_state;
 pub mod message_queue_selector;
 pub mod mq_producer;
 pub mod produce_accumulator;
 pub mod producer_impl;
+pub mod queue_selection_mode;
 pub mod request_callback;
 pub(crate) mod request_future_holder;
 pub(crate) mod request_response_future;
@@ -26,6 +26,7 @@ use crate::base::client_config::ClientConfig;
 use crate::producer::default_mq_producer::DefaultMQProducer;
 use crate::producer::produce_accumulator::ProduceAccumulator;
 use crate::producer::producer_impl::default_mq_producer_impl::DefaultMQProducerImpl;
+use crate::producer::queue_selection_mode::QueueSelectionMode;
 use crate::trace::trace_dispatcher::TraceDispatcher;
 
 #[derive(Default)]
@@ -53,6 +54,13 @@ pub struct DefaultMQProducerBuilder {
     compress_level: Option<i32>,
     compress_type: Option<CompressionType>,
     compressor: Option<Arc<Box<dyn Compressor + Send + Sync>>>,
+    queue_selection_mode: Option<QueueSelectionMode>,
+    sticky_queue_max_bytes: Option<u64>,
+    sticky_queue_max_millis: Option<u64>,
+    enable_body_crc: Option<bool>,
+    spool_dir: Option<CheetahString>,
+    spool_max_bytes: Option<u64>,
+    spool_replay_interval_millis: Option<u64>,
 }
 
 impl DefaultMQProducerBuilder {
@@ -81,6 +89,13 @@ impl DefaultMQProducerBuilder {
             compress_level: None,
             compress_type: None,
             compressor: None,
+            queue_selection_mode: None,
+            sticky_queue_max_bytes: None,
+            sticky_queue_max_millis: None,
+            enable_body_crc: None,
+            spool_dir: None,
+            spool_max_bytes: None,
+            spool_replay_interval_millis: None,
         }
     }
 
@@ -122,6 +137,24 @@ impl DefaultMQProducerBuilder {
         self
     }
 
+    /// Topics to resolve and connect to during start, see `ClientConfig::warm_up_topics`.
+    pub fn warm_up_topics(mut self, warm_up_topics: Vec<CheetahString>) -> Self {
+        if let Some(client_config) = self.client_config.as_mut() {
+            client_config.warm_up_topics = warm_up_topics;
+        }
+        self
+    }
+
+    /// Isolates a broker for `open_millis` after `failure_threshold` consecutive send timeouts
+    /// or busy answers, see `ClientConfig::broker_circuit_breaker_failure_threshold`.
+    pub fn broker_circuit_breaker(mut self, failure_threshold: u32, open_millis: u64) -> Self {
+        if let Some(client_config) = self.client_config.as_mut() {
+            client_config.broker_circuit_breaker_failure_threshold = failure_threshold;
+            client_config.broker_circuit_breaker_open_millis = open_millis;
+        }
+        self
+    }
+
     pub fn create_topic_key(mut self, create_topic_key: impl Into<CheetahString>) -> Self {
         self.create_topic_key = Some(create_topic_key.into());
         self
@@ -230,6 +263,41 @@ impl DefaultMQProducerBuilder {
         self
     }
 
+    pub fn queue_selection_mode(mut self, queue_selection_mode: QueueSelectionMode) -> Self {
+        self.queue_selection_mode = Some(queue_selection_mode);
+        self
+    }
+
+    pub fn sticky_queue_max_bytes(mut self, sticky_queue_max_bytes: u64) -> Self {
+        self.sticky_queue_max_bytes = Some(sticky_queue_max_bytes);
+        self
+    }
+
+    pub fn sticky_queue_max_millis(mut self, sticky_queue_max_millis: u64) -> Self {
+        self.sticky_queue_max_millis = Some(sticky_queue_max_millis);
+        self
+    }
+
+    pub fn enable_body_crc(mut self, enable_body_crc: bool) -> Self {
+        self.enable_body_crc = Some(enable_body_crc);
+        self
+    }
+
+    pub fn spool_dir(mut self, spool_dir: impl Into<CheetahString>) -> Self {
+        self.spool_dir = Some(spool_dir.into());
+        self
+    }
+
+    pub fn spool_max_bytes(mut self, spool_max_bytes: u64) -> Self {
+        self.spool_max_bytes = Some(spool_max_bytes);
+        self
+    }
+
+    pub fn spool_replay_interval_millis(mut self, spool_replay_interval_millis: u64) -> Self {
+        self.spool_replay_interval_millis = Some(spool_replay_interval_millis);
+        self
+    }
+
     pub fn build(self) -> DefaultMQProducer {
         let mut mq_producer = DefaultMQProducer::default();
         if let Some(client_config) = self.client_config {
@@ -300,6 +368,27 @@ impl DefaultMQProducerBuilder {
         if let Some(compressor) = self.compressor {
             mq_producer.set_compressor(Some(compressor));
         }
+        if let Some(queue_selection_mode) = self.queue_selection_mode {
+            mq_producer.set_queue_selection_mode(queue_selection_mode);
+        }
+        if let Some(sticky_queue_max_bytes) = self.sticky_queue_max_bytes {
+            mq_producer.set_sticky_queue_max_bytes(sticky_queue_max_bytes);
+        }
+        if let Some(sticky_queue_max_millis) = self.sticky_queue_max_millis {
+            mq_producer.set_sticky_queue_max_millis(sticky_queue_max_millis);
+        }
+        if let Some(enable_body_crc) = self.enable_body_crc {
+            mq_producer.set_enable_body_crc(enable_body_crc);
+        }
+        if let Some(spool_dir) = self.spool_dir {
+            mq_producer.set_spool_dir(Some(spool_dir));
+        }
+        if let Some(spool_max_bytes) = self.spool_max_bytes {
+            mq_producer.set_spool_max_bytes(spool_max_bytes);
+        }
+        if let Some(spool_replay_interval_millis) = self.spool_replay_interval_millis {
+            mq_producer.set_spool_replay_interval_millis(spool_replay_interval_millis);
+        }
 
         if let Some(default_mqproducer_impl) = self.default_mqproducer_impl {
             mq_producer.set_default_mqproducer_impl(default_mqproducer_impl);
@@ -35,15 +35,19 @@ use rocketmq_common::common::topic::TopicValidator;
 use rocketmq_remoting::code::response_code::ResponseCode;
 use rocketmq_remoting::runtime::RPCHook;
 use rocketmq_rust::ArcMut;
+use tokio::sync::broadcast;
 use tracing::error;
 
 use crate::base::client_config::ClientConfig;
+use crate::base::topic_route_change_listener::TopicRouteChangeEvent;
+use crate::base::topic_route_change_listener::TopicRouteChangeListener;
 use crate::base::validators::Validators;
 use crate::mq_client_err;
 use crate::producer::default_mq_produce_builder::DefaultMQProducerBuilder;
 use crate::producer::mq_producer::MQProducer;
 use crate::producer::produce_accumulator::ProduceAccumulator;
 use crate::producer::producer_impl::default_mq_producer_impl::DefaultMQProducerImpl;
+use crate::producer::queue_selection_mode::QueueSelectionMode;
 use crate::producer::send_callback::SendMessageCallback;
 use crate::producer::send_result::SendResult;
 use crate::producer::transaction_send_result::TransactionSendResult;
@@ -103,6 +107,21 @@ pub struct ProducerConfig {
     compress_level: i32,
     compress_type: CompressionType,
     compressor: Option<Arc<Box<dyn Compressor + Send + Sync>>>,
+    /// Strategy used to pick a message queue when no selector is given.
+    queue_selection_mode: QueueSelectionMode,
+    /// In sticky mode, switch to another queue after this many bytes were sent to it.
+    sticky_queue_max_bytes: u64,
+    /// In sticky mode, switch to another queue after sticking to it for this long.
+    sticky_queue_max_millis: u64,
+    /// Attach a CRC32 of the body so the broker and consumers can detect corruption.
+    enable_body_crc: bool,
+    /// Directory of the disk spool buffering synchronous sends while no broker can be reached,
+    /// `None` disables spooling.
+    spool_dir: Option<CheetahString>,
+    /// Maximum size of the disk spool, sends fail once it is full.
+    spool_max_bytes: u64,
+    /// Interval of the task replaying spooled messages.
+    spool_replay_interval_millis: u64,
 }
 
 impl ProducerConfig {
@@ -190,6 +209,34 @@ impl ProducerConfig {
     pub fn compressor(&self) -> &Option<Arc<Box<dyn Compressor + Send + Sync>>> {
         &self.compressor
     }
+
+    pub fn queue_selection_mode(&self) -> QueueSelectionMode {
+        self.queue_selection_mode
+    }
+
+    pub fn sticky_queue_max_bytes(&self) -> u64 {
+        self.sticky_queue_max_bytes
+    }
+
+    pub fn sticky_queue_max_millis(&self) -> u64 {
+        self.sticky_queue_max_millis
+    }
+
+    pub fn enable_body_crc(&self) -> bool {
+        self.enable_body_crc
+    }
+
+    pub fn spool_dir(&self) -> Option<&CheetahString> {
+        self.spool_dir.as_ref()
+    }
+
+    pub fn spool_max_bytes(&self) -> u64 {
+        self.spool_max_bytes
+    }
+
+    pub fn spool_replay_interval_millis(&self) -> u64 {
+        self.spool_replay_interval_millis
+    }
 }
 
 impl Default for ProducerConfig {
@@ -238,6 +285,13 @@ impl Default for ProducerConfig {
             compressor: Some(Arc::new(CompressorFactory::get_compressor(
                 compression_type,
             ))),
+            queue_selection_mode: QueueSelectionMode::RoundRobin,
+            sticky_queue_max_bytes: 256 * 1024,
+            sticky_queue_max_millis: 1000,
+            enable_body_crc: false,
+            spool_dir: None,
+            spool_max_bytes: 128 * 1024 * 1024,
+            spool_replay_interval_millis: 1000,
         }
     }
 }
@@ -464,6 +518,34 @@ impl DefaultMQProducer {
         self.producer_config.compressor = compressor;
     }
 
+    pub fn set_queue_selection_mode(&mut self, queue_selection_mode: QueueSelectionMode) {
+        self.producer_config.queue_selection_mode = queue_selection_mode;
+    }
+
+    pub fn set_sticky_queue_max_bytes(&mut self, sticky_queue_max_bytes: u64) {
+        self.producer_config.sticky_queue_max_bytes = sticky_queue_max_bytes;
+    }
+
+    pub fn set_sticky_queue_max_millis(&mut self, sticky_queue_max_millis: u64) {
+        self.producer_config.sticky_queue_max_millis = sticky_queue_max_millis;
+    }
+
+    pub fn set_enable_body_crc(&mut self, enable_body_crc: bool) {
+        self.producer_config.enable_body_crc = enable_body_crc;
+    }
+
+    pub fn set_spool_dir(&mut self, spool_dir: Option<CheetahString>) {
+        self.producer_config.spool_dir = spool_dir;
+    }
+
+    pub fn set_spool_max_bytes(&mut self, spool_max_bytes: u64) {
+        self.producer_config.spool_max_bytes = spool_max_bytes;
+    }
+
+    pub fn set_spool_replay_interval_millis(&mut self, spool_replay_interval_millis: u64) {
+        self.producer_config.spool_replay_interval_millis = spool_replay_interval_millis;
+    }
+
     pub fn producer_config(&self) -> &ProducerConfig {
         &self.producer_config
     }
@@ -475,6 +557,40 @@ impl DefaultMQProducer {
         }
     }
 
+    /// Registers a listener that is notified when the route of a topic changes, e.g. brokers are
+    /// added or removed or queue counts change.
+    pub fn register_topic_route_change_listener(
+        &mut self,
+        listener: impl TopicRouteChangeListener + 'static,
+    ) {
+        if let Some(ref mut default_mqproducer_impl) = self.default_mqproducer_impl {
+            default_mqproducer_impl.register_topic_route_change_listener(Arc::new(listener));
+        }
+    }
+
+    /// Returns a stream of topic route change events. Only available after the producer started.
+    pub fn subscribe_topic_route_change(
+        &self,
+    ) -> Option<broadcast::Receiver<TopicRouteChangeEvent>> {
+        self.default_mqproducer_impl
+            .as_ref()
+            .and_then(|default_mqproducer_impl| {
+                default_mqproducer_impl.subscribe_topic_route_change()
+            })
+    }
+
+    /// Refetches the route of `topic` from the name server, ignoring any cached lookup result.
+    /// Only available after the producer started.
+    pub async fn refresh_topic_route(&mut self, topic: &str) -> bool {
+        let topic = self.with_namespace(topic);
+        match self.default_mqproducer_impl.as_mut() {
+            Some(default_mqproducer_impl) => {
+                default_mqproducer_impl.refresh_topic_route(&topic).await
+            }
+            None => false,
+        }
+    }
+
     fn batch(&mut self, messages: Vec<Message>) -> Result<MessageBatch> {
         match MessageBatch::generate_from_vec(messages) {
             Ok(mut msg_batch) => {
@@ -495,6 +611,22 @@ impl DefaultMQProducer {
         }
     }
 
+    /// Sends `msg` and additionally waits until the broker's confirm offset (replicated, and
+    /// flushed when the broker uses synchronous flush) has passed the stored message.
+    ///
+    /// Returns a timeout error if the message is not confirmed within `timeout` milliseconds;
+    /// the message may still have been stored in that case.
+    pub async fn send_confirmed(&mut self, mut msg: Message, timeout: u64) -> Result<SendResult> {
+        msg.topic = self.with_namespace(msg.topic.as_str());
+        let result = self
+            .default_mqproducer_impl
+            .as_mut()
+            .unwrap()
+            .send_confirmed(&mut msg, timeout)
+            .await?;
+        Ok(result.expect("SendResult should not be None"))
+    }
+
     #[inline]
     pub fn get_auto_batch(&self) -> bool {
         self.producer_config.produce_accumulator.is_some() && self.producer_config.auto_batch
@@ -579,16 +711,6 @@ impl DefaultMQProducer {
     where
         M: MessageTrait,
     {
-        // produceAccumulator is full
-        if !self
-            .producer_config
-            .produce_accumulator
-            .as_ref()
-            .unwrap()
-            .try_add_message(msg)
-        {
-            return false;
-        }
         // delay message do not support batch processing
         if msg.get_delay_time_level() > 0
             || msg.get_delay_time_ms() > 0
@@ -611,7 +733,13 @@ impl DefaultMQProducer {
         {
             return false;
         }
-        true
+        // produceAccumulator is full, checked last so that the held size is only reserved for
+        // messages which will actually be accumulated
+        self.producer_config
+            .produce_accumulator
+            .as_ref()
+            .unwrap()
+            .try_add_message(msg)
     }
 }
 
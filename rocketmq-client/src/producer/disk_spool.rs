@@ -0,0 +1,348 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::fs;
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::io;
+use std::io::Read;
+use std::io::Seek;
+use std::io::SeekFrom;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+
+use bytes::Buf;
+use bytes::BufMut;
+use bytes::Bytes;
+use bytes::BytesMut;
+use cheetah_string::CheetahString;
+use parking_lot::Mutex;
+use rocketmq_common::common::message::message_single::Message;
+use rocketmq_common::common::message::MessageTrait;
+use rocketmq_common::CRC32Utils::crc32;
+use rocketmq_common::MessageDecoder::message_properties_to_string;
+use rocketmq_common::MessageDecoder::string_to_message_properties;
+use tracing::warn;
+
+const SPOOL_LOG_FILE: &str = "spool.log";
+const SPOOL_ACK_FILE: &str = "spool.ack";
+const RECORD_MAGIC: u32 = 0x5350_4F4C;
+// magic(4) payload_len(4) crc(4)
+const RECORD_HEADER_SIZE: u64 = 12;
+
+/// A message read back from the spool, acknowledge it once it was sent.
+#[derive(Debug)]
+pub struct SpooledMessage {
+    pub message: Message,
+    next_pos: u64,
+}
+
+struct SpoolFiles {
+    log: File,
+    ack: File,
+    read_pos: u64,
+    write_pos: u64,
+    pending: usize,
+}
+
+/// Bounded write ahead log buffering messages on local disk while no broker can be reached.
+///
+/// Records are appended in send order and replayed from the acknowledged position, the log is
+/// reset once everything has been replayed. A record torn by a crash is dropped on open.
+pub struct DiskSpool {
+    dir: PathBuf,
+    max_bytes: u64,
+    files: Mutex<SpoolFiles>,
+}
+
+impl DiskSpool {
+    pub fn open(dir: impl AsRef<Path>, max_bytes: u64) -> io::Result<Self> {
+        let dir = dir.as_ref().to_path_buf();
+        fs::create_dir_all(&dir)?;
+        let mut log = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(dir.join(SPOOL_LOG_FILE))?;
+        let mut ack = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(dir.join(SPOOL_ACK_FILE))?;
+
+        let mut read_pos = [0u8; 8];
+        let read_pos = match ack.read_exact(&mut read_pos) {
+            Ok(()) => u64::from_be_bytes(read_pos),
+            Err(_) => 0,
+        };
+        let log_len = log.metadata()?.len();
+        let read_pos = if read_pos > log_len { 0 } else { read_pos };
+
+        let mut write_pos = read_pos;
+        let mut pending = 0;
+        while let Some((_, next_pos)) = read_record(&mut log, write_pos, log_len)? {
+            write_pos = next_pos;
+            pending += 1;
+        }
+        if write_pos < log_len {
+            warn!(
+                "drop {} bytes of torn records at the tail of spool {}",
+                log_len - write_pos,
+                dir.display()
+            );
+            log.set_len(write_pos)?;
+        }
+        Ok(DiskSpool {
+            dir,
+            max_bytes,
+            files: Mutex::new(SpoolFiles {
+                log,
+                ack,
+                read_pos,
+                write_pos,
+                pending,
+            }),
+        })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Number of messages waiting to be replayed.
+    pub fn pending(&self) -> usize {
+        self.files.lock().pending
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending() == 0
+    }
+
+    /// Appends `msg` and syncs it to disk, fails once the log would exceed `max_bytes`.
+    pub fn append<T: MessageTrait>(&self, msg: &T) -> io::Result<()> {
+        let record = encode_record(msg);
+        let mut files = self.files.lock();
+        if files.write_pos + record.len() as u64 > self.max_bytes {
+            return Err(io::Error::other(format!(
+                "spool {} is full, {} messages pending",
+                self.dir.display(),
+                files.pending
+            )));
+        }
+        let write_pos = files.write_pos;
+        files.log.seek(SeekFrom::Start(write_pos))?;
+        files.log.write_all(&record)?;
+        files.log.sync_data()?;
+        files.write_pos += record.len() as u64;
+        files.pending += 1;
+        Ok(())
+    }
+
+    /// Oldest message not acknowledged yet.
+    pub fn peek(&self) -> io::Result<Option<SpooledMessage>> {
+        let mut files = self.files.lock();
+        let (read_pos, write_pos) = (files.read_pos, files.write_pos);
+        Ok(read_record(&mut files.log, read_pos, write_pos)?
+            .map(|(message, next_pos)| SpooledMessage { message, next_pos }))
+    }
+
+    pub fn ack(&self, spooled: &SpooledMessage) -> io::Result<()> {
+        let mut files = self.files.lock();
+        if spooled.next_pos <= files.read_pos {
+            return Ok(());
+        }
+        files.read_pos = spooled.next_pos;
+        files.pending = files.pending.saturating_sub(1);
+        if files.read_pos >= files.write_pos {
+            // everything replayed, start over with an empty log
+            files.log.set_len(0)?;
+            files.read_pos = 0;
+            files.write_pos = 0;
+            files.pending = 0;
+        }
+        let read_pos = files.read_pos;
+        files.ack.seek(SeekFrom::Start(0))?;
+        files.ack.write_all(&read_pos.to_be_bytes())?;
+        files.ack.sync_data()
+    }
+}
+
+fn encode_record<T: MessageTrait>(msg: &T) -> Bytes {
+    let topic = msg.get_topic().as_bytes();
+    let properties = message_properties_to_string(msg.get_properties());
+    let body = msg.get_body().map_or(&[][..], |body| body.as_ref());
+
+    let mut payload =
+        BytesMut::with_capacity(2 + topic.len() + 4 + 4 + properties.len() + 4 + body.len());
+    payload.put_u16(topic.len() as u16);
+    payload.put_slice(topic);
+    payload.put_i32(msg.get_flag());
+    payload.put_u32(properties.len() as u32);
+    payload.put_slice(properties.as_bytes());
+    payload.put_u32(body.len() as u32);
+    payload.put_slice(body);
+
+    let mut record = BytesMut::with_capacity(RECORD_HEADER_SIZE as usize + payload.len());
+    record.put_u32(RECORD_MAGIC);
+    record.put_u32(payload.len() as u32);
+    record.put_u32(crc32(&payload));
+    record.put_slice(&payload);
+    record.freeze()
+}
+
+/// Reads the record at `pos`, `None` at `end` or when the record is incomplete or corrupt.
+fn read_record(log: &mut File, pos: u64, end: u64) -> io::Result<Option<(Message, u64)>> {
+    if pos + RECORD_HEADER_SIZE > end {
+        return Ok(None);
+    }
+    log.seek(SeekFrom::Start(pos))?;
+    let mut header = [0u8; RECORD_HEADER_SIZE as usize];
+    log.read_exact(&mut header)?;
+    let mut header = &header[..];
+    let (magic, payload_len, crc) = (header.get_u32(), header.get_u32(), header.get_u32());
+    let next_pos = pos + RECORD_HEADER_SIZE + payload_len as u64;
+    if magic != RECORD_MAGIC || next_pos > end {
+        return Ok(None);
+    }
+    let mut payload = vec![0u8; payload_len as usize];
+    log.read_exact(&mut payload)?;
+    if crc32(&payload) != crc {
+        return Ok(None);
+    }
+    Ok(decode_payload(Bytes::from(payload)).map(|message| (message, next_pos)))
+}
+
+fn decode_payload(mut payload: Bytes) -> Option<Message> {
+    let read_slice = |payload: &mut Bytes, len: usize| {
+        (payload.remaining() >= len).then(|| payload.split_to(len))
+    };
+    if payload.remaining() < 2 {
+        return None;
+    }
+    let topic_len = payload.get_u16() as usize;
+    let topic = read_slice(&mut payload, topic_len)?;
+    if payload.remaining() < 8 {
+        return None;
+    }
+    let flag = payload.get_i32();
+    let properties_len = payload.get_u32() as usize;
+    let properties = read_slice(&mut payload, properties_len)?;
+    if payload.remaining() < 4 {
+        return None;
+    }
+    let body_len = payload.get_u32() as usize;
+    let body = read_slice(&mut payload, body_len)?;
+
+    let mut message = Message::default();
+    message.set_topic(CheetahString::from_string(
+        String::from_utf8_lossy(&topic).into_owned(),
+    ));
+    message.set_flag(flag);
+    message.set_properties(string_to_message_properties(Some(
+        &CheetahString::from_string(String::from_utf8_lossy(&properties).into_owned()),
+    )));
+    message.set_body(body);
+    Some(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use rocketmq_common::common::message::MessageConst;
+
+    use super::*;
+
+    fn spool_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "rocketmq-disk-spool-{}-{}",
+            name,
+            rand::random::<u64>()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn message(body: &'static str) -> Message {
+        let mut message = Message::default();
+        message.set_topic(CheetahString::from_static_str("TopicA"));
+        message.set_body(Bytes::from_static(body.as_bytes()));
+        message.put_property(
+            CheetahString::from_static_str(MessageConst::PROPERTY_KEYS),
+            CheetahString::from_static_str(body),
+        );
+        message
+    }
+
+    #[test]
+    fn replays_in_order_across_reopen() {
+        let dir = spool_dir("reopen");
+        let spool = DiskSpool::open(&dir, 1024 * 1024).unwrap();
+        spool.append(&message("m1")).unwrap();
+        spool.append(&message("m2")).unwrap();
+        let first = spool.peek().unwrap().unwrap();
+        assert_eq!(first.message.get_body().unwrap().as_ref(), b"m1");
+        spool.ack(&first).unwrap();
+        drop(spool);
+
+        let spool = DiskSpool::open(&dir, 1024 * 1024).unwrap();
+        assert_eq!(spool.pending(), 1);
+        let second = spool.peek().unwrap().unwrap();
+        assert_eq!(second.message.get_topic().as_str(), "TopicA");
+        assert_eq!(second.message.get_keys().unwrap().as_str(), "m2");
+        spool.ack(&second).unwrap();
+        assert!(spool.is_empty());
+        assert!(spool.peek().unwrap().is_none());
+        assert_eq!(fs::metadata(dir.join(SPOOL_LOG_FILE)).unwrap().len(), 0);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn rejects_appends_beyond_max_bytes_and_drops_torn_tail() {
+        let dir = spool_dir("bounded");
+        let record_len = encode_record(&message("m1")).len() as u64;
+        let spool = DiskSpool::open(&dir, record_len * 2).unwrap();
+        spool.append(&message("m1")).unwrap();
+        spool.append(&message("m2")).unwrap();
+        assert!(spool.append(&message("m3")).is_err());
+        drop(spool);
+
+        // simulate a crash in the middle of the second record
+        let log = OpenOptions::new()
+            .write(true)
+            .open(dir.join(SPOOL_LOG_FILE))
+            .unwrap();
+        log.set_len(record_len + 5).unwrap();
+        let spool = DiskSpool::open(&dir, record_len * 2).unwrap();
+        assert_eq!(spool.pending(), 1);
+        spool.append(&message("m3")).unwrap();
+        let first = spool.peek().unwrap().unwrap();
+        spool.ack(&first).unwrap();
+        assert_eq!(
+            spool
+                .peek()
+                .unwrap()
+                .unwrap()
+                .message
+                .get_body()
+                .unwrap()
+                .as_ref(),
+            b"m3"
+        );
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
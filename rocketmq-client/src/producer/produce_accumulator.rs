@@ -15,40 +15,57 @@
  * limitations under the License.
  */
 use std::collections::HashMap;
-use std::collections::HashSet;
 use std::hash::Hash;
 use std::hash::Hasher;
-use std::sync::atomic::AtomicBool;
-use std::sync::atomic::AtomicI32;
 use std::sync::atomic::AtomicU64;
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
-use std::thread::Builder;
+use std::time::Duration;
 
 use cheetah_string::CheetahString;
+use rocketmq_common::common::message::message_batch::MessageBatch;
+use rocketmq_common::common::message::message_client_id_setter::MessageClientIDSetter;
 use rocketmq_common::common::message::message_queue::MessageQueue;
+use rocketmq_common::common::message::message_single::Message;
 use rocketmq_common::common::message::MessageTrait;
 use rocketmq_common::TimeUtils::get_current_millis;
-use rocketmq_rust::ArcMut;
+use tokio::sync::oneshot;
 use tokio::sync::Mutex;
+use tokio::sync::Notify;
+use tracing::info;
+use tracing::warn;
 
+use crate::client_error::MQClientError;
+use crate::client_error::SendErr;
+use crate::mq_client_err;
 use crate::producer::default_mq_producer::DefaultMQProducer;
 use crate::producer::send_callback::SendMessageCallback;
 use crate::producer::send_result::SendResult;
 use crate::Result;
 
-#[derive(Default)]
+type AccumulationTable = Arc<Mutex<HashMap<AggregateKey, MessageAccumulation>>>;
+
+/// Aggregates small messages sent to the same topic/queue into batch requests.
+///
+/// A batch is flushed as soon as its body size exceeds `hold_size`, or once it has been
+/// lingering for `hold_ms`. `total_hold_size` caps the bytes buffered across all batches;
+/// messages arriving while the cap is exceeded are sent directly.
 pub struct ProduceAccumulator {
     total_hold_size: usize,
     hold_size: usize,
     hold_ms: u32,
-    guard_thread_for_sync_send: GuardForSyncSendService,
-    guard_thread_for_async_send: GuardForAsyncSendService,
-    currently_hold_size: AtomicU64,
     instance_name: String,
+    currently_hold_size: Arc<AtomicU64>,
     currently_hold_size_lock: Arc<parking_lot::Mutex<()>>,
-    sync_send_batchs: Arc<Mutex<HashMap<AggregateKey, ArcMut<MessageAccumulation>>>>,
-    async_send_batchs: Arc<Mutex<HashMap<AggregateKey, ArcMut<MessageAccumulation>>>>,
+    batches: AccumulationTable,
+    shutdown_notify: Arc<Notify>,
+    started: bool,
+}
+
+impl Default for ProduceAccumulator {
+    fn default() -> Self {
+        Self::new("")
+    }
 }
 
 impl ProduceAccumulator {
@@ -58,25 +75,91 @@ impl ProduceAccumulator {
             hold_size: 1024 * 32,
             hold_ms: 10,
             instance_name: instance_name.to_string(),
-            guard_thread_for_async_send: GuardForAsyncSendService {
-                service_name: instance_name.to_string(),
-            },
-            guard_thread_for_sync_send: GuardForSyncSendService {
-                service_name: instance_name.to_string(),
-            },
-            ..Default::default()
+            currently_hold_size: Arc::new(AtomicU64::new(0)),
+            currently_hold_size_lock: Arc::new(parking_lot::Mutex::new(())),
+            batches: Arc::new(Mutex::new(HashMap::new())),
+            shutdown_notify: Arc::new(Notify::new()),
+            started: false,
         }
     }
+
+    #[inline]
+    pub fn total_hold_size(&self) -> usize {
+        self.total_hold_size
+    }
+
+    #[inline]
+    pub fn hold_size(&self) -> usize {
+        self.hold_size
+    }
+
+    #[inline]
+    pub fn hold_ms(&self) -> u32 {
+        self.hold_ms
+    }
+
+    #[inline]
+    pub fn currently_hold_size(&self) -> u64 {
+        self.currently_hold_size.load(Ordering::Acquire)
+    }
+
+    pub fn set_total_hold_size(&mut self, total_hold_size: usize) {
+        self.total_hold_size = total_hold_size;
+    }
+
+    pub fn set_hold_size(&mut self, hold_size: usize) {
+        self.hold_size = hold_size;
+    }
+
+    pub fn set_hold_ms(&mut self, hold_ms: u32) {
+        self.hold_ms = hold_ms;
+    }
 }
 
 impl ProduceAccumulator {
     pub fn start(&mut self) {
-        self.guard_thread_for_sync_send.start();
-        self.guard_thread_for_async_send.start();
+        if self.started {
+            return;
+        }
+        self.started = true;
+        let batches = self.batches.clone();
+        let currently_hold_size = self.currently_hold_size.clone();
+        let shutdown_notify = self.shutdown_notify.clone();
+        let hold_size = self.hold_size;
+        let hold_ms = self.hold_ms.max(1);
+        let instance_name = self.instance_name.clone();
+        tokio::spawn(async move {
+            info!(
+                "ProduceAccumulator guard service started, instance: {}",
+                instance_name
+            );
+            loop {
+                tokio::select! {
+                    _ = tokio::time::sleep(Duration::from_millis(hold_ms as u64)) => {
+                        Self::flush(&batches, &currently_hold_size, |accumulation| {
+                            accumulation.ready_to_send(hold_size, hold_ms)
+                        })
+                        .await;
+                    }
+                    _ = shutdown_notify.notified() => {
+                        Self::flush(&batches, &currently_hold_size, |_| true).await;
+                        break;
+                    }
+                }
+            }
+            info!(
+                "ProduceAccumulator guard service stopped, instance: {}",
+                instance_name
+            );
+        });
     }
+
     pub fn shutdown(&mut self) {
-        self.guard_thread_for_sync_send.shutdown();
-        self.guard_thread_for_async_send.shutdown();
+        if !self.started {
+            return;
+        }
+        self.started = false;
+        self.shutdown_notify.notify_one();
     }
 
     pub(crate) fn try_add_message<T: MessageTrait>(&self, message: &T) -> bool {
@@ -86,45 +169,108 @@ impl ProduceAccumulator {
             return false;
         }
         self.currently_hold_size
-            .fetch_add(message.get_body().unwrap().len() as u64, Ordering::AcqRel);
+            .fetch_add(Self::body_size(message), Ordering::AcqRel);
         drop(lock);
         true
     }
 
-    pub(crate) async fn send<M: MessageTrait + Send + Sync + 'static>(
-        &mut self,
+    pub(crate) async fn send<M: MessageTrait + Clone + Send + Sync + 'static>(
+        &self,
         message: M,
         mq: Option<MessageQueue>,
-        default_mq_producer: DefaultMQProducer,
+        mut default_mq_producer: DefaultMQProducer,
     ) -> Result<Option<SendResult>> {
-        unimplemented!("send")
+        let Some(msg) = message.as_any().downcast_ref::<Message>().cloned() else {
+            self.release(Self::body_size(&message));
+            return default_mq_producer.send_direct(message, mq, None).await;
+        };
+        let (tx, rx) = oneshot::channel();
+        self.enqueue(msg, mq, SendCompletion::Sync(tx), default_mq_producer)
+            .await;
+        match rx.await {
+            Ok(result) => result.map(Some),
+            Err(_) => mq_client_err!("ProduceAccumulator dropped the batch before it was sent"),
+        }
     }
 
-    pub(crate) async fn send_callback<M: MessageTrait + Send + Sync + 'static + Clone>(
-        &mut self,
+    pub(crate) async fn send_callback<M: MessageTrait + Clone + Send + Sync + 'static>(
+        &self,
         message: M,
         mq: Option<MessageQueue>,
         send_callback: Option<SendMessageCallback>,
-        default_mq_producer: DefaultMQProducer,
+        mut default_mq_producer: DefaultMQProducer,
     ) -> Result<()> {
-        let partition_key = AggregateKey::new_from_message_queue(&message, mq);
-        loop {
-            let batch =
-                self.get_or_create_async_send_batch(partition_key.clone(), &default_mq_producer);
-            /*if batch.add(message.clone(), send_callback.clone()) {
-                self.async_send_batchs.lock().await.remove(&partition_key);
-            } else {
-                return Ok(());
-            }*/
+        let Some(msg) = message.as_any().downcast_ref::<Message>().cloned() else {
+            self.release(Self::body_size(&message));
+            return default_mq_producer
+                .send_direct(message, mq, send_callback)
+                .await
+                .map(|_| ());
+        };
+        self.enqueue(
+            msg,
+            mq,
+            SendCompletion::Async(send_callback),
+            default_mq_producer,
+        )
+        .await;
+        Ok(())
+    }
+
+    async fn enqueue(
+        &self,
+        msg: Message,
+        mq: Option<MessageQueue>,
+        completion: SendCompletion,
+        default_mq_producer: DefaultMQProducer,
+    ) {
+        let aggregate_key = AggregateKey::new_from_message_queue(&msg, mq);
+        let mut batches = self.batches.lock().await;
+        let accumulation = batches.entry(aggregate_key.clone()).or_insert_with(|| {
+            MessageAccumulation::new(aggregate_key.clone(), default_mq_producer)
+        });
+        accumulation.add(msg, completion);
+        if accumulation.ready_to_send(self.hold_size, self.hold_ms) {
+            let accumulation = batches.remove(&aggregate_key).unwrap();
+            drop(batches);
+            tokio::spawn(accumulation.send(self.currently_hold_size.clone()));
+        }
+    }
+
+    async fn flush<F>(batches: &AccumulationTable, currently_hold_size: &Arc<AtomicU64>, ready: F)
+    where
+        F: Fn(&MessageAccumulation) -> bool,
+    {
+        let ready_batches = {
+            let mut batches = batches.lock().await;
+            let ready_keys = batches
+                .iter()
+                .filter(|(_, accumulation)| ready(accumulation))
+                .map(|(key, _)| key.clone())
+                .collect::<Vec<_>>();
+            ready_keys
+                .into_iter()
+                .filter_map(|key| batches.remove(&key))
+                .collect::<Vec<_>>()
+        };
+        for accumulation in ready_batches {
+            tokio::spawn(accumulation.send(currently_hold_size.clone()));
         }
     }
 
-    fn get_or_create_async_send_batch(
-        &mut self,
-        aggregate_key: AggregateKey,
-        default_mq_producer: &DefaultMQProducer,
-    ) -> ArcMut<MessageAccumulation> {
-        unimplemented!("getOrCreateAsyncSendBatch")
+    fn release(&self, size: u64) {
+        Self::release_hold_size(&self.currently_hold_size, size);
+    }
+
+    fn release_hold_size(currently_hold_size: &AtomicU64, size: u64) {
+        let _ = currently_hold_size.fetch_update(Ordering::AcqRel, Ordering::Acquire, |current| {
+            Some(current.saturating_sub(size))
+        });
+    }
+
+    #[inline]
+    fn body_size<T: MessageTrait>(message: &T) -> u64 {
+        message.get_body().map_or(0, |body| body.len() as u64)
     }
 }
 
@@ -190,159 +336,185 @@ impl Hash for AggregateKey {
     }
 }
 
+/// How the sender of an accumulated message is told about the outcome.
+enum SendCompletion {
+    Sync(oneshot::Sender<Result<SendResult>>),
+    Async(Option<SendMessageCallback>),
+}
+
+impl SendCompletion {
+    fn complete(self, result: &Result<SendResult>) {
+        match self {
+            SendCompletion::Sync(tx) => {
+                let result = match result {
+                    Ok(send_result) => Ok(send_result.clone()),
+                    Err(err) => Err(MQClientError::SendError(SendErr::new(
+                        err.send_error_kind(),
+                        err.to_string(),
+                    ))),
+                };
+                let _ = tx.send(result);
+            }
+            SendCompletion::Async(Some(callback)) => match result {
+                Ok(send_result) => callback(Some(send_result), None),
+                Err(err) => callback(None, Some(err)),
+            },
+            SendCompletion::Async(None) => {}
+        }
+    }
+}
+
 struct MessageAccumulation {
-    default_mq_producer: ArcMut<DefaultMQProducer>,
-    messages: Vec<Box<dyn MessageTrait + Send + Sync + 'static>>,
-    send_callbacks: Vec<SendMessageCallback>,
-    keys: HashSet<String>,
-    closed: Arc<AtomicBool>,
-    send_results: Option<Vec<SendResult>>,
+    default_mq_producer: DefaultMQProducer,
+    messages: Vec<Message>,
+    completions: Vec<SendCompletion>,
     aggregate_key: AggregateKey,
-    messages_size: Arc<AtomicI32>,
-    count: usize,
+    messages_size: usize,
     create_time: u64,
 }
 
 impl MessageAccumulation {
-    pub fn new(
-        aggregate_key: AggregateKey,
-        default_mq_producer: ArcMut<DefaultMQProducer>,
-    ) -> Self {
+    pub fn new(aggregate_key: AggregateKey, default_mq_producer: DefaultMQProducer) -> Self {
         Self {
             default_mq_producer,
             messages: vec![],
-            send_callbacks: vec![],
-            keys: HashSet::new(),
-            closed: Arc::new(AtomicBool::new(false)),
-            send_results: None,
+            completions: vec![],
             aggregate_key,
-            messages_size: Arc::new(AtomicI32::new(0)),
-            count: 0,
+            messages_size: 0,
             create_time: get_current_millis(),
         }
     }
 
-    pub fn add<M: MessageTrait + Send + Sync + 'static>(
-        &mut self,
-        msg: M,
-        send_callback: Option<SendMessageCallback>,
-    ) -> Result<bool> {
-        unimplemented!()
+    fn add(&mut self, msg: Message, completion: SendCompletion) {
+        self.messages_size += msg.get_body().map_or(0, |body| body.len());
+        self.messages.push(msg);
+        self.completions.push(completion);
     }
 
-    /*    fn ready_to_send(&self, hold_size: i32, hold_ms: u128) -> bool {
-        self.messages_size.load(Ordering::SeqCst) > hold_size
-            || SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .expect("Time went backwards")
-                .as_millis()
-                >= self.create_time + hold_ms
+    fn ready_to_send(&self, hold_size: usize, hold_ms: u32) -> bool {
+        self.messages_size > hold_size || get_current_millis() >= self.create_time + hold_ms as u64
     }
 
-
-
-    pub fn add_with_callback(
-        &mut self,
-        msg: Message,
-        send_callback: SendCallback,
-    ) -> Result<bool, ()> {
-        if self.closed.load(Ordering::SeqCst) {
-            return Err(());
+    fn batch(&mut self) -> Result<MessageBatch> {
+        match MessageBatch::generate_from_vec(std::mem::take(&mut self.messages)) {
+            Ok(mut msg_batch) => {
+                MessageClientIDSetter::set_uniq_id(&mut msg_batch.final_message);
+                msg_batch.set_body(msg_batch.encode());
+                Ok(msg_batch)
+            }
+            Err(err) => mq_client_err!(format!(
+                "Failed to build the accumulated MessageBatch: {}",
+                err
+            )),
         }
-        self.count += 1;
-        self.messages.push_back(msg);
-        self.send_callbacks.push_back(send_callback);
-        self.messages_size
-            .fetch_add(msg.get_body().len() as i32, Ordering::SeqCst);
-
-        Ok(true)
-    }
-
-    fn batch(&self) -> MessageBatch {
-        // Implementation for creating a message batch from the accumulated messages
-        MessageBatch {}
-    }
-
-    fn split_send_results(&mut self, send_result: SendResult) {
-        // Implementation for splitting send results
     }
 
-    pub fn send(&mut self) -> Result<(), ()> {
-        if self.closed.swap(true, Ordering::SeqCst) {
-            return Ok(());
+    async fn send(mut self, currently_hold_size: Arc<AtomicU64>) {
+        let count = self.completions.len();
+        let messages_size = self.messages_size as u64;
+        let result = match self.batch() {
+            Ok(msg_batch) => self
+                .default_mq_producer
+                .send_direct(msg_batch, self.aggregate_key.mq.clone(), None)
+                .await
+                .and_then(|send_result| match send_result {
+                    Some(send_result) => split_send_results(&send_result, count),
+                    None => mq_client_err!("Accumulated batch send returned no SendResult"),
+                }),
+            Err(err) => Err(err),
+        };
+        ProduceAccumulator::release_hold_size(&currently_hold_size, messages_size);
+        match result {
+            Ok(send_results) => {
+                for (completion, send_result) in self.completions.into_iter().zip(send_results) {
+                    completion.complete(&Ok(send_result));
+                }
+            }
+            Err(err) => {
+                warn!(
+                    "Send accumulated batch of {} messages to topic {} failed: {}",
+                    count, self.aggregate_key.topic, err
+                );
+                let result = Err(err);
+                for completion in self.completions {
+                    completion.complete(&result);
+                }
+            }
         }
-        let message_batch = self.batch();
-        let send_result = self
-            .default_mq_producer
-            .send_direct(message_batch, None, None)?;
-        self.split_send_results(send_result);
-        Ok(())
     }
-
-    pub fn send_with_callback(&mut self, send_callback: SendCallback) -> Result<(), ()> {
-        if self.closed.swap(true, Ordering::SeqCst) {
-            return Ok(());
-        }
-        let message_batch = self.batch();
-        let size = self.messages_size.load(Ordering::SeqCst);
-
-        self.default_mq_producer
-            .send_direct(message_batch, None, Some(send_callback))?;
-
-        Ok(())
-    }*/
 }
 
-#[derive(Default)]
-struct GuardForSyncSendService {
-    service_name: String,
-}
-
-impl GuardForSyncSendService {
-    pub fn new(service_name: &str) -> Self {
-        Self {
-            service_name: service_name.to_string(),
-        }
-    }
-
-    pub fn start(&mut self) {
-        let service_name = self.service_name.clone();
-        Builder::new()
-            .name(service_name)
-            .spawn(|| {
-                // Implementation for starting the guard thread
-            })
-            .expect("Failed to start guard thread");
-    }
-
-    pub fn shutdown(&mut self) {
-        // Implementation for shutting down the guard thread
+/// Splits the result of a batch send into one `SendResult` per message. The broker returns the
+/// message ids comma-joined and the offset of the first message in the batch.
+fn split_send_results(send_result: &SendResult, count: usize) -> Result<Vec<SendResult>> {
+    let msg_ids = send_result
+        .msg_id
+        .as_ref()
+        .map(|msg_id| msg_id.as_str().split(',').collect::<Vec<_>>())
+        .unwrap_or_default();
+    let offset_msg_ids = send_result
+        .offset_msg_id
+        .as_ref()
+        .map(|offset_msg_id| offset_msg_id.split(',').collect::<Vec<_>>())
+        .unwrap_or_default();
+    if msg_ids.len() != count || offset_msg_ids.len() != count {
+        return mq_client_err!(format!(
+            "Batch send result mismatch, expect {} ids but got msgId: {}, offsetMsgId: {}",
+            count,
+            msg_ids.len(),
+            offset_msg_ids.len()
+        ));
     }
+    Ok(msg_ids
+        .into_iter()
+        .zip(offset_msg_ids)
+        .enumerate()
+        .map(|(index, (msg_id, offset_msg_id))| {
+            let mut result = SendResult::new(
+                send_result.send_status,
+                Some(CheetahString::from(msg_id)),
+                Some(offset_msg_id.to_string()),
+                send_result.message_queue.clone(),
+                send_result.queue_offset + index as u64,
+            );
+            result.region_id = send_result.region_id.clone();
+            result.trace_on = send_result.trace_on;
+            result
+        })
+        .collect())
 }
 
-#[derive(Default)]
-struct GuardForAsyncSendService {
-    service_name: String,
-}
-
-impl GuardForAsyncSendService {
-    pub fn new(service_name: &str) -> Self {
-        Self {
-            service_name: service_name.to_string(),
-        }
-    }
-
-    pub fn start(&mut self) {
-        let service_name = self.service_name.clone();
-        Builder::new()
-            .name(service_name)
-            .spawn(|| {
-                // Implementation for starting the guard thread
-            })
-            .expect("Failed to start guard thread");
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::producer::send_status::SendStatus;
+
+    #[test]
+    fn split_send_results_assigns_consecutive_offsets() {
+        let send_result = SendResult::new(
+            SendStatus::SendOk,
+            Some(CheetahString::from("id0,id1,id2")),
+            Some("off0,off1,off2".to_string()),
+            None,
+            100,
+        );
+        let results = split_send_results(&send_result, 3).unwrap();
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[1].msg_id.as_ref().unwrap().as_str(), "id1");
+        assert_eq!(results[2].offset_msg_id.as_deref(), Some("off2"));
+        assert_eq!(results[2].queue_offset, 102);
+        assert!(split_send_results(&send_result, 2).is_err());
     }
 
-    pub fn shutdown(&mut self) {
-        // Implementation for shutting down the guard thread
+    #[test]
+    fn try_add_message_respects_total_hold_size() {
+        let mut accumulator = ProduceAccumulator::new("test");
+        accumulator.set_total_hold_size(4);
+        let msg = Message::new("topic", b"12345");
+        assert!(accumulator.try_add_message(&msg));
+        assert_eq!(accumulator.currently_hold_size(), 5);
+        assert!(!accumulator.try_add_message(&msg));
+        accumulator.release(5);
+        assert_eq!(accumulator.currently_hold_size(), 0);
     }
 }
@@ -30,6 +30,7 @@ use rocketmq_common::common::message::message_batch::MessageBatch;
 use rocketmq_common::common::message::message_client_id_setter::MessageClientIDSetter;
 use rocketmq_common::common::message::message_enum::MessageType;
 use rocketmq_common::common::message::message_ext::MessageExt;
+use rocketmq_common::common::message::message_integrity::attach_body_crc;
 use rocketmq_common::common::message::message_queue::MessageQueue;
 use rocketmq_common::common::message::message_single::Message;
 use rocketmq_common::common::message::MessageConst;
@@ -43,7 +44,10 @@ use rocketmq_common::utils::correlation_id_util::CorrelationIdUtil;
 use rocketmq_common::MessageAccessor::MessageAccessor;
 use rocketmq_common::MessageDecoder;
 use rocketmq_common::TimeUtils::get_current_millis;
+use rocketmq_remoting::protocol::body::end_transaction_batch_body::EndTransactionBatchBody;
+use rocketmq_remoting::protocol::body::end_transaction_batch_body::EndTransactionBatchEntry;
 use rocketmq_remoting::protocol::header::check_transaction_state_request_header::CheckTransactionStateRequestHeader;
+use rocketmq_remoting::protocol::header::end_transaction_batch_request_header::EndTransactionBatchRequestHeader;
 use rocketmq_remoting::protocol::header::end_transaction_request_header::EndTransactionRequestHeader;
 use rocketmq_remoting::protocol::header::message_operation_header::send_message_request_header::SendMessageRequestHeader;
 use rocketmq_remoting::protocol::namespace_util::NamespaceUtil;
@@ -53,17 +57,22 @@ use rocketmq_remoting::runtime::RPCHook;
 use rocketmq_runtime::RocketMQRuntime;
 use rocketmq_rust::ArcMut;
 use tokio::runtime::Handle;
+use tokio::sync::broadcast;
 use tokio::sync::RwLock;
 use tokio::sync::Semaphore;
 use tracing::warn;
 
 use crate::base::client_config::ClientConfig;
+use crate::base::topic_route_change_listener::TopicRouteChangeEvent;
+use crate::base::topic_route_change_listener::TopicRouteChangeListener;
 use crate::base::validators::Validators;
 use crate::client_error::ClientErr;
 use crate::client_error::MQClientError;
 use crate::client_error::MQClientError::RemotingTooMuchRequestError;
 use crate::client_error::MQClientError::RequestTimeoutError;
 use crate::client_error::RequestTimeoutErr;
+use crate::client_error::SendErr;
+use crate::client_error::SendErrorKind;
 use crate::common::client_error_code::ClientErrorCode;
 use crate::factory::mq_client_instance::MQClientInstance;
 use crate::hook::check_forbidden_context::CheckForbiddenContext;
@@ -74,16 +83,21 @@ use crate::hook::send_message_context::SendMessageContext;
 use crate::hook::send_message_hook::SendMessageHook;
 use crate::implementation::communication_mode::CommunicationMode;
 use crate::implementation::mq_client_manager::MQClientManager;
+use crate::latency::broker_circuit_breaker::CircuitBreakerMetrics;
+use crate::latency::broker_circuit_breaker::CircuitState;
 use crate::latency::mq_fault_strategy::MQFaultStrategy;
 use crate::latency::resolver::Resolver;
 use crate::latency::service_detector::ServiceDetector;
 use crate::mq_client_err;
 use crate::producer::default_mq_producer::ProducerConfig;
+use crate::producer::disk_spool::DiskSpool;
 use crate::producer::local_transaction_state::LocalTransactionState;
 use crate::producer::message_queue_selector::MessageQueueSelectorFn;
 use crate::producer::producer_impl::mq_producer_inner::MQProducerInner;
 use crate::producer::producer_impl::mq_producer_inner::MQProducerInnerImpl;
+use crate::producer::producer_impl::sticky_queue_selector::StickyQueueSelector;
 use crate::producer::producer_impl::topic_publish_info::TopicPublishInfo;
+use crate::producer::queue_selection_mode::QueueSelectionMode;
 use crate::producer::request_callback::RequestCallbackFn;
 use crate::producer::request_future_holder::REQUEST_FUTURE_HOLDER;
 use crate::producer::request_response_future::RequestResponseFuture;
@@ -91,9 +105,13 @@ use crate::producer::send_callback::SendMessageCallback;
 use crate::producer::send_result::SendResult;
 use crate::producer::send_status::SendStatus;
 use crate::producer::transaction_listener::TransactionListener;
+use crate::producer::transaction_send_result::TransactionBatchSendResult;
 use crate::producer::transaction_send_result::TransactionSendResult;
 use crate::Result;
 
+/// Interval between two `CHECK_CONFIRM_OFFSET` probes of a confirmed send.
+const CONFIRM_OFFSET_POLL_INTERVAL_MILLIS: u64 = 20;
+
 pub struct DefaultMQProducerImpl {
     client_config: ClientConfig,
     producer_config: Arc<ProducerConfig>,
@@ -112,6 +130,9 @@ pub struct DefaultMQProducerImpl {
     default_mqproducer_impl_inner: Option<ArcMut<DefaultMQProducerImpl>>,
     transaction_listener: Option<Arc<Box<dyn TransactionListener>>>,
     check_runtime: Option<Arc<RocketMQRuntime>>,
+    topic_route_change_listeners: Vec<Arc<dyn TopicRouteChangeListener>>,
+    sticky_queue_selector: Option<Arc<StickyQueueSelector>>,
+    disk_spool: Option<Arc<DiskSpool>>,
 }
 
 #[allow(unused_must_use)]
@@ -130,6 +151,15 @@ impl DefaultMQProducerImpl {
                 .max(1024 * 1024) as usize,
         );
         let topic_publish_info_table = Arc::new(RwLock::new(HashMap::new()));
+        let sticky_queue_selector =
+            if producer_config.queue_selection_mode() == QueueSelectionMode::Sticky {
+                Some(Arc::new(StickyQueueSelector::new(
+                    producer_config.sticky_queue_max_bytes(),
+                    producer_config.sticky_queue_max_millis(),
+                )))
+            } else {
+                None
+            };
         DefaultMQProducerImpl {
             client_config: client_config.clone(),
             producer_config: Arc::new(producer_config),
@@ -151,6 +181,9 @@ impl DefaultMQProducerImpl {
             default_mqproducer_impl_inner: None,
             transaction_listener: None,
             check_runtime: None,
+            topic_route_change_listeners: vec![],
+            sticky_queue_selector,
+            disk_spool: None,
         }
     }
 
@@ -163,8 +196,157 @@ impl DefaultMQProducerImpl {
     where
         T: MessageTrait + Clone + Send + Sync,
     {
-        self.send_default_impl(msg, CommunicationMode::Sync, None, timeout)
+        let Some(disk_spool) = self
+            .disk_spool
+            .clone()
+            .filter(|_| Self::is_spoolable_message(msg))
+        else {
+            return self
+                .send_default_impl(msg, CommunicationMode::Sync, None, timeout)
+                .await;
+        };
+        // nothing overtakes the spooled messages, they are replayed in send order
+        if !disk_spool.is_empty() {
+            return Self::spool_message(&disk_spool, msg);
+        }
+        match self
+            .send_default_impl(msg, CommunicationMode::Sync, None, timeout)
             .await
+        {
+            Err(err) if Self::is_spoolable_error(&err) => {
+                warn!("no broker reachable, spool the message: {}", err);
+                Self::spool_message(&disk_spool, msg)
+            }
+            result => result,
+        }
+    }
+
+    fn is_spoolable_message<T: MessageTrait>(msg: &T) -> bool {
+        msg.as_any().downcast_ref::<MessageBatch>().is_none()
+            && msg
+                .get_property(&CheetahString::from_static_str(
+                    MessageConst::PROPERTY_TRANSACTION_PREPARED,
+                ))
+                .is_none()
+    }
+
+    fn is_spoolable_error(err: &MQClientError) -> bool {
+        matches!(
+            err.send_error_kind(),
+            SendErrorKind::RemotingTimeout | SendErrorKind::Remoting | SendErrorKind::RouteNotFound
+        )
+    }
+
+    /// Writes `msg` to the disk spool. Its unique key is set first, so a message replayed after
+    /// a send that timed out but was stored can be deduplicated.
+    fn spool_message<T: MessageTrait>(
+        disk_spool: &DiskSpool,
+        msg: &mut T,
+    ) -> Result<Option<SendResult>> {
+        MessageClientIDSetter::set_uniq_id(msg);
+        if let Err(err) = disk_spool.append(msg) {
+            return Err(MQClientError::SendError(SendErr::new(
+                SendErrorKind::Remoting,
+                format!(
+                    "no broker reachable and the message cannot be spooled: {}",
+                    err
+                ),
+            )));
+        }
+        Ok(Some(SendResult::new(
+            SendStatus::Spooled,
+            MessageClientIDSetter::get_uniq_id(msg),
+            None,
+            None,
+            0,
+        )))
+    }
+
+    /// Sends the spooled messages in order, stops at the first one no broker can take yet.
+    async fn replay_disk_spool(&mut self, disk_spool: &DiskSpool) {
+        loop {
+            let spooled = match disk_spool.peek() {
+                Ok(Some(spooled)) => spooled,
+                Ok(None) => return,
+                Err(err) => {
+                    warn!(
+                        "read disk spool {} failed: {}",
+                        disk_spool.dir().display(),
+                        err
+                    );
+                    return;
+                }
+            };
+            let mut message = spooled.message.clone();
+            let timeout = self.producer_config.send_msg_timeout() as u64;
+            match self
+                .send_default_impl(&mut message, CommunicationMode::Sync, None, timeout)
+                .await
+            {
+                Ok(_) => {}
+                Err(err) if Self::is_spoolable_error(&err) => return,
+                Err(err) => {
+                    warn!(
+                        "drop spooled message {:?} of topic {}, it cannot be sent: {}",
+                        MessageClientIDSetter::get_uniq_id(&message),
+                        message.get_topic(),
+                        err
+                    );
+                }
+            }
+            if let Err(err) = disk_spool.ack(&spooled) {
+                warn!(
+                    "ack disk spool {} failed: {}",
+                    disk_spool.dir().display(),
+                    err
+                );
+                return;
+            }
+        }
+    }
+
+    fn start_disk_spool(&mut self) -> Result<()> {
+        let Some(spool_dir) = self.producer_config.spool_dir() else {
+            return Ok(());
+        };
+        let disk_spool =
+            match DiskSpool::open(spool_dir.as_str(), self.producer_config.spool_max_bytes()) {
+                Ok(disk_spool) => Arc::new(disk_spool),
+                Err(err) => {
+                    return mq_client_err!(format!(
+                        "open disk spool {} failed: {}",
+                        spool_dir, err
+                    ));
+                }
+            };
+        if !disk_spool.is_empty() {
+            warn!(
+                "{} spooled messages in {} wait to be replayed",
+                disk_spool.pending(),
+                spool_dir
+            );
+        }
+        self.disk_spool = Some(disk_spool.clone());
+
+        let Some(mut producer) = self.default_mqproducer_impl_inner.clone() else {
+            return Ok(());
+        };
+        let interval =
+            Duration::from_millis(self.producer_config.spool_replay_interval_millis().max(10));
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(interval).await;
+                match producer.service_state {
+                    ServiceState::Running => {}
+                    ServiceState::ShutdownAlready => return,
+                    _ => continue,
+                }
+                if !disk_spool.is_empty() {
+                    producer.replay_disk_spool(&disk_spool).await;
+                }
+            }
+        });
+        Ok(())
     }
 
     #[inline]
@@ -176,6 +358,77 @@ impl DefaultMQProducerImpl {
             .await
     }
 
+    /// Sends `msg` synchronously, then waits until the broker's confirm offset has passed the
+    /// stored message. Both steps share the `timeout` budget.
+    pub async fn send_confirmed<T>(
+        &mut self,
+        msg: &mut T,
+        timeout: u64,
+    ) -> Result<Option<SendResult>>
+    where
+        T: MessageTrait + Clone + Send + Sync,
+    {
+        let begin = Instant::now();
+        let send_result = self.send_with_timeout(msg, timeout).await?;
+        let Some(send_result) = send_result else {
+            return Ok(None);
+        };
+        let Some(commit_log_offset) = send_result
+            .offset_msg_id
+            .as_deref()
+            .and_then(MessageDecoder::try_decode_message_id)
+            .map(|id| id.offset)
+        else {
+            return mq_client_err!(format!(
+                "send result of {:?} carries no offset message id, cannot confirm",
+                send_result.msg_id
+            ));
+        };
+        let queue = self
+            .client_config
+            .queue_with_namespace(send_result.message_queue.clone().unwrap());
+        let client_instance = self.client_instance.as_mut().unwrap();
+        let broker_name = client_instance
+            .get_broker_name_from_message_queue(&queue)
+            .await;
+        let Some(broker_addr) = client_instance
+            .find_broker_address_in_publish(broker_name.as_ref())
+            .await
+        else {
+            return mq_client_err!(format!("The broker[{}] not exist", broker_name));
+        };
+
+        loop {
+            let elapsed = begin.elapsed().as_millis() as u64;
+            if elapsed >= timeout {
+                return Err(RequestTimeoutError(RequestTimeoutErr::new_with_code(
+                    ClientErrorCode::REQUEST_TIMEOUT_EXCEPTION,
+                    format!(
+                        "message {} stored at offset {} was not confirmed by broker {} within {} \
+                         ms",
+                        send_result.msg_id.clone().unwrap_or_default(),
+                        commit_log_offset,
+                        broker_addr,
+                        timeout
+                    ),
+                )));
+            }
+            let confirm_offset = client_instance
+                .mq_client_api_impl
+                .as_mut()
+                .unwrap()
+                .check_confirm_offset(&broker_addr, timeout - elapsed)
+                .await?;
+            if confirm_offset > commit_log_offset {
+                return Ok(Some(send_result));
+            }
+            tokio::time::sleep(Duration::from_millis(
+                CONFIRM_OFFSET_POLL_INTERVAL_MILLIS.min(timeout - elapsed),
+            ))
+            .await;
+        }
+    }
+
     #[inline]
     pub async fn async_send_with_callback<T>(
         &mut self,
@@ -430,7 +683,10 @@ impl DefaultMQProducerImpl {
             }
         }
         self.validate_name_server_setting()?;
-        mq_client_err!(format!("No route info for this topic, {}", msg.get_topic()))
+        Err(MQClientError::SendError(SendErr::new(
+            SendErrorKind::RouteNotFound,
+            format!("No route info for this topic, {}", msg.get_topic()),
+        )))
     }
 
     #[inline]
@@ -708,6 +964,12 @@ impl DefaultMQProducerImpl {
                     );
                     if mq_selected.is_some() {
                         mq = mq_selected;
+                        if let Some(sticky_queue_selector) = self.sticky_queue_selector.as_ref() {
+                            sticky_queue_selector.record(
+                                &CheetahString::from(mq.as_ref().unwrap().get_topic()),
+                                msg.get_body().map_or(0, |body| body.len() as u64),
+                            );
+                        }
                         brokers_sent[times as usize] =
                             mq.as_ref().unwrap().get_broker_name().to_string();
                         begin_timestamp_prev = Instant::now();
@@ -736,6 +998,11 @@ impl DefaultMQProducerImpl {
                                 timeout - cost_time,
                             )
                             .await;
+                        self.update_circuit(
+                            mq.as_ref().unwrap().get_broker_name(),
+                            communication_mode,
+                            &result_inner,
+                        );
 
                         match result_inner {
                             Ok(result) => {
@@ -846,6 +1113,14 @@ impl DefaultMQProducerImpl {
                             },
                         }
                     } else {
+                        if exception.is_none()
+                            && self.mq_fault_strategy.is_circuit_breaker_enabled()
+                        {
+                            exception = Some(MQClientError::SendError(SendErr::new(
+                                SendErrorKind::BrokerBusy,
+                                format!("the circuit of every broker of topic {} is open", topic),
+                            )));
+                        }
                         break;
                     }
                 }
@@ -854,9 +1129,10 @@ impl DefaultMQProducerImpl {
                 }
 
                 if call_timeout {
-                    return Err(MQClientError::RemotingTooMuchRequestError(
-                        "sendDefaultImpl call timeout".to_string(),
-                    ));
+                    return Err(MQClientError::SendError(SendErr::new(
+                        SendErrorKind::RemotingTimeout,
+                        "sendDefaultImpl call timeout",
+                    )));
                 }
 
                 let info = format!(
@@ -868,44 +1144,56 @@ impl DefaultMQProducerImpl {
                     FAQUrl::suggest_todo(FAQUrl::SEND_MSG_FAILED)
                 );
 
-                return if let Some(err) = exception {
-                    match err {
-                        MQClientError::MQClientErr(_) => {
-                            mq_client_err!(ClientErrorCode::BROKER_NOT_EXIST_EXCEPTION, info)
-                        }
-                        RemotingTooMuchRequestError(_) => {
-                            mq_client_err!(ClientErrorCode::BROKER_NOT_EXIST_EXCEPTION, info)
-                        }
-                        MQClientError::MQClientBrokerError(_) => {
-                            mq_client_err!(ClientErrorCode::BROKER_NOT_EXIST_EXCEPTION, info)
-                        }
-                        MQClientError::RequestTimeoutError(_) => {
-                            mq_client_err!(ClientErrorCode::BROKER_NOT_EXIST_EXCEPTION, info)
-                        }
-                        MQClientError::OffsetNotFoundError(_, _, _) => {
-                            mq_client_err!(ClientErrorCode::BROKER_NOT_EXIST_EXCEPTION, info)
-                        }
-                        MQClientError::RemotingError(_) => {
-                            mq_client_err!(ClientErrorCode::BROKER_NOT_EXIST_EXCEPTION, info)
-                        }
-                        _ => {
-                            unimplemented!("not support error type");
-                        }
-                    }
-                } else {
-                    mq_client_err!(info)
-                };
+                let kind = exception
+                    .as_ref()
+                    .map_or(SendErrorKind::Other, |err| err.send_error_kind());
+                if let Some(err) = exception {
+                    warn!("{}, last exception: {}", info, err);
+                }
+                return Err(MQClientError::SendError(SendErr::new(kind, info)));
             }
         }
         self.validate_name_server_setting()?;
-        mq_client_err!(
-            ClientErrorCode::NOT_FOUND_TOPIC_EXCEPTION,
+        Err(MQClientError::SendError(SendErr::new(
+            SendErrorKind::RouteNotFound,
             format!(
                 "No route info of this topic:{},{}",
                 topic,
                 FAQUrl::suggest_todo(FAQUrl::NO_TOPIC_ROUTE_INFO)
-            )
-        )
+            ),
+        )))
+    }
+
+    /// Feeds the outcome of a send into the broker circuit breaker. Only timeouts and busy
+    /// answers count as failures, async and oneway sends only report failures raised before the
+    /// request went out.
+    fn update_circuit(
+        &self,
+        broker_name: &CheetahString,
+        communication_mode: CommunicationMode,
+        result: &Result<Option<SendResult>>,
+    ) {
+        match result {
+            Ok(_) if communication_mode == CommunicationMode::Sync => {
+                self.mq_fault_strategy.update_circuit(broker_name, false);
+            }
+            Ok(_) => {}
+            Err(err) => {
+                if matches!(
+                    err.send_error_kind(),
+                    SendErrorKind::RemotingTimeout | SendErrorKind::BrokerBusy
+                ) {
+                    self.mq_fault_strategy.update_circuit(broker_name, true);
+                }
+            }
+        }
+    }
+
+    /// State and counters of the broker circuits, empty unless the circuit breaker is enabled.
+    pub fn broker_circuit_breaker_metrics(
+        &self,
+    ) -> HashMap<CheetahString, (CircuitState, CircuitBreakerMetrics)> {
+        self.mq_fault_strategy.circuit_breaker_metrics()
     }
 
     #[inline]
@@ -981,6 +1269,9 @@ impl DefaultMQProducerImpl {
             msg.set_instance_id(self.client_config.get_namespace().unwrap_or_default());
             topic_with_namespace = true;
         }
+        if !batch && self.producer_config.enable_body_crc() {
+            attach_body_crc(msg);
+        }
         let mut sys_flag = 0i32;
         let mut msg_body_compressed = false;
         if self.try_to_compress_message(msg) {
@@ -1246,8 +1537,30 @@ impl DefaultMQProducerImpl {
         last_broker_name: Option<&CheetahString>,
         reset_index: bool,
     ) -> Option<MessageQueue> {
-        self.mq_fault_strategy
-            .select_one_message_queue(tp_info, last_broker_name, reset_index)
+        let Some(sticky_queue_selector) = self.sticky_queue_selector.as_ref() else {
+            return self.mq_fault_strategy.select_one_message_queue(
+                tp_info,
+                last_broker_name,
+                reset_index,
+            );
+        };
+        let topic = CheetahString::from(tp_info.message_queue_list.first()?.get_topic());
+        // Retries always move on, so a failing queue is never stuck to
+        if last_broker_name.is_none() && !reset_index {
+            if let Some(mq) = sticky_queue_selector.current(&topic) {
+                if self.mq_fault_strategy.is_message_queue_available(&mq) {
+                    return Some(mq);
+                }
+                sticky_queue_selector.unstick(&topic);
+            }
+        }
+        let mq = self.mq_fault_strategy.select_one_message_queue(
+            tp_info,
+            last_broker_name,
+            reset_index,
+        )?;
+        sticky_queue_selector.stick(&topic, &mq);
+        Some(mq)
     }
 
     fn validate_name_server_setting(&self) -> Result<()> {
@@ -1370,7 +1683,10 @@ impl DefaultMQProducerImpl {
             }
         }
         self.validate_name_server_setting();
-        mq_client_err!("select message queue return null.")
+        Err(MQClientError::SendError(SendErr::new(
+            SendErrorKind::RouteNotFound,
+            format!("No route info for this topic, {}", msg.get_topic()),
+        )))
     }
 
     pub async fn send_with_selector_timeout<M, T>(
@@ -1891,7 +2207,8 @@ impl DefaultMQProducerImpl {
             }
             SendStatus::FlushDiskTimeout
             | SendStatus::FlushSlaveTimeout
-            | SendStatus::SlaveNotAvailable => LocalTransactionState::RollbackMessage,
+            | SendStatus::SlaveNotAvailable
+            | SendStatus::Spooled => LocalTransactionState::RollbackMessage,
         };
         if let Err(e) = self
             .end_transaction(&msg, &send_result, local_transaction_state)
@@ -1910,6 +2227,214 @@ impl DefaultMQProducerImpl {
         Ok(transaction_send_result)
     }
 
+    /// Stages `msgs` as half messages on one broker under a single batch id, runs the local
+    /// transaction once and commits or rolls back all of them together.
+    ///
+    /// The first message picks the broker, the others are sent to a queue of their topic on
+    /// that broker. If any half message can not be staged the whole batch is rolled back
+    /// without running the local transaction. Transaction checks arrive per half message, the
+    /// listener finds the batch id in `PROPERTY_TRANSACTION_BATCH_ID`.
+    pub async fn send_messages_in_transaction(
+        &mut self,
+        msgs: Vec<Message>,
+        arg: Option<Box<dyn Any + Send + Sync>>,
+    ) -> Result<TransactionBatchSendResult> {
+        if msgs.is_empty() {
+            return mq_client_err!("transaction batch is empty");
+        }
+        let batch_id = CheetahString::from_string(MessageClientIDSetter::create_uniq_id());
+        let batch_size = CheetahString::from_string(msgs.len().to_string());
+        let mut msgs = msgs;
+        for msg in msgs.iter_mut() {
+            if msg.get_delay_time_level() != 0 {
+                MessageAccessor::clear_property(msg, MessageConst::PROPERTY_DELAY_TIME_LEVEL);
+            }
+            Validators::check_message(Some(&*msg), self.producer_config.as_ref())?;
+            MessageAccessor::put_property(
+                msg,
+                CheetahString::from_static_str(MessageConst::PROPERTY_TRANSACTION_PREPARED),
+                CheetahString::from_static_str("true"),
+            );
+            MessageAccessor::put_property(
+                msg,
+                CheetahString::from_static_str(MessageConst::PROPERTY_PRODUCER_GROUP),
+                self.producer_config.producer_group().to_owned(),
+            );
+            MessageAccessor::put_property(
+                msg,
+                CheetahString::from_static_str(MessageConst::PROPERTY_TRANSACTION_BATCH_ID),
+                batch_id.clone(),
+            );
+            MessageAccessor::put_property(
+                msg,
+                CheetahString::from_static_str(MessageConst::PROPERTY_TRANSACTION_BATCH_SIZE),
+                batch_size.clone(),
+            );
+        }
+
+        let mut staged: Vec<(Message, SendResult)> = Vec::with_capacity(msgs.len());
+        let mut stage_error = None;
+        for mut msg in msgs {
+            let result = match staged.first() {
+                None => self.send(&mut msg).await,
+                Some((_, first)) => {
+                    let broker_name = first
+                        .message_queue
+                        .as_ref()
+                        .map(|mq| mq.get_broker_name().clone())
+                        .unwrap_or_default();
+                    match self
+                        .select_queue_on_broker(msg.get_topic(), &broker_name)
+                        .await
+                    {
+                        Ok(mq) => self.sync_send_with_message_queue(msg.clone(), mq).await,
+                        Err(e) => Err(e),
+                    }
+                }
+            };
+            match result {
+                Ok(Some(send_result)) if send_result.send_status == SendStatus::SendOk => {
+                    staged.push((msg, send_result));
+                }
+                Ok(send_result) => {
+                    stage_error = Some(format!(
+                        "stage half message failed, send status {:?}",
+                        send_result.map(|r| r.send_status)
+                    ));
+                    break;
+                }
+                Err(e) => {
+                    stage_error = Some(format!("stage half message failed, {}", e));
+                    break;
+                }
+            }
+        }
+
+        let local_transaction_state = match stage_error {
+            Some(_) => LocalTransactionState::RollbackMessage,
+            None => {
+                let first = &mut staged[0].0;
+                first.set_transaction_id(batch_id.clone());
+                self.transaction_listener
+                    .as_ref()
+                    .unwrap()
+                    .execute_local_transaction(first, arg.as_deref())
+            }
+        };
+        if !staged.is_empty() && local_transaction_state != LocalTransactionState::Unknown {
+            if let Err(e) = self
+                .end_transaction_batch(&batch_id, &staged, local_transaction_state)
+                .await
+            {
+                warn!(
+                    "local transaction of batch {} execute {}, but end broker transaction                      failed, {}",
+                    batch_id, local_transaction_state, e
+                );
+            }
+        }
+        if let Some(stage_error) = stage_error {
+            return mq_client_err!(format!(
+                "send messages in transaction error, batch {} rolled back, {}",
+                batch_id, stage_error
+            ));
+        }
+        Ok(TransactionBatchSendResult {
+            transaction_id: batch_id,
+            local_transaction_state,
+            send_results: staged
+                .into_iter()
+                .map(|(_, send_result)| send_result)
+                .collect(),
+        })
+    }
+
+    async fn select_queue_on_broker(
+        &mut self,
+        topic: &CheetahString,
+        broker_name: &CheetahString,
+    ) -> Result<MessageQueue> {
+        let queues: Vec<MessageQueue> = self
+            .fetch_publish_message_queues(topic)
+            .await?
+            .into_iter()
+            .filter(|mq| mq.get_broker_name() == broker_name)
+            .collect();
+        if queues.is_empty() {
+            return mq_client_err!(format!(
+                "topic {} has no queue on broker {}, a transaction batch must stay on one broker",
+                topic, broker_name
+            ));
+        }
+        Ok(queues[random::<usize>() % queues.len()].clone())
+    }
+
+    async fn end_transaction_batch(
+        &mut self,
+        batch_id: &CheetahString,
+        staged: &[(Message, SendResult)],
+        local_transaction_state: LocalTransactionState,
+    ) -> Result<()> {
+        let queue = self
+            .client_config
+            .queue_with_namespace(staged[0].1.message_queue.clone().unwrap());
+        let dest_broker_name = self
+            .client_instance
+            .as_mut()
+            .unwrap()
+            .get_broker_name_from_message_queue(&queue)
+            .await;
+        let Some(broker_addr) = self
+            .client_instance
+            .as_mut()
+            .unwrap()
+            .find_broker_address_in_publish(dest_broker_name.as_ref())
+            .await
+        else {
+            return mq_client_err!(format!("broker {} address not found", dest_broker_name));
+        };
+        let entries = staged
+            .iter()
+            .map(|(msg, send_result)| {
+                let id = if let Some(ref offset_msg_id) = send_result.offset_msg_id {
+                    MessageDecoder::decode_message_id(offset_msg_id)
+                } else {
+                    MessageDecoder::decode_message_id(send_result.msg_id.as_ref().unwrap())
+                };
+                EndTransactionBatchEntry {
+                    topic: CheetahString::from_string(msg.get_topic().to_string()),
+                    tran_state_table_offset: send_result.queue_offset,
+                    commit_log_offset: id.offset as u64,
+                    msg_id: send_result.msg_id.clone().unwrap_or_default(),
+                }
+            })
+            .collect();
+        let request_header = EndTransactionBatchRequestHeader {
+            producer_group: CheetahString::from_string(
+                self.producer_config.producer_group().to_string(),
+            ),
+            transaction_id: batch_id.clone(),
+            commit_or_rollback: match local_transaction_state {
+                LocalTransactionState::CommitMessage => MessageSysFlag::TRANSACTION_COMMIT_TYPE,
+                LocalTransactionState::RollbackMessage => MessageSysFlag::TRANSACTION_ROLLBACK_TYPE,
+                LocalTransactionState::Unknown => MessageSysFlag::TRANSACTION_NOT_TYPE,
+            },
+            from_transaction_check: false,
+        };
+        self.client_instance
+            .as_mut()
+            .unwrap()
+            .mq_client_api_impl
+            .as_mut()
+            .unwrap()
+            .end_transaction_batch(
+                &broker_addr,
+                request_header,
+                EndTransactionBatchBody { entries },
+                self.producer_config.send_msg_timeout() as u64,
+            )
+            .await
+    }
+
     pub fn init_transaction_env(&mut self, check_runtime: Option<Arc<RocketMQRuntime>>) {
         if check_runtime.is_some() {
             self.check_runtime = check_runtime;
@@ -2234,8 +2759,29 @@ impl DefaultMQProducerImpl {
                     //self.client_instance.as_mut().unwrap().start().await;
                 }
 
+                let mq_fault_strategy = self.mq_fault_strategy.clone();
+                let client_instance = self.client_instance.as_ref().unwrap();
+                client_instance.register_topic_route_change_listener(Arc::new(
+                    move |event: &TopicRouteChangeEvent| {
+                        if event.removed_brokers.is_empty() {
+                            return;
+                        }
+                        let mq_fault_strategy = mq_fault_strategy.clone();
+                        let removed_brokers = event.removed_brokers.clone();
+                        tokio::spawn(async move {
+                            for broker_name in removed_brokers.iter() {
+                                mq_fault_strategy.remove_fault_item(broker_name).await;
+                            }
+                        });
+                    },
+                ));
+                for listener in self.topic_route_change_listeners.drain(..) {
+                    client_instance.register_topic_route_change_listener(listener);
+                }
+
                 self.init_topic_route().await;
                 self.mq_fault_strategy.start_detector();
+                self.start_disk_spool()?;
                 self.service_state = ServiceState::Running;
             }
             ServiceState::Running => {
@@ -2255,6 +2801,37 @@ impl DefaultMQProducerImpl {
         Ok(())
     }
 
+    /// Registers a listener for topic route changes. Listeners registered before start are
+    /// attached to the client instance when the producer starts.
+    pub fn register_topic_route_change_listener(
+        &mut self,
+        listener: Arc<dyn TopicRouteChangeListener>,
+    ) {
+        match self.client_instance.as_ref() {
+            Some(client_instance) => client_instance.register_topic_route_change_listener(listener),
+            None => self.topic_route_change_listeners.push(listener),
+        }
+    }
+
+    /// Returns a receiver of topic route change events, or `None` if the producer has not been
+    /// started yet.
+    pub fn subscribe_topic_route_change(
+        &self,
+    ) -> Option<broadcast::Receiver<TopicRouteChangeEvent>> {
+        self.client_instance
+            .as_ref()
+            .map(|client_instance| client_instance.subscribe_topic_route_change())
+    }
+
+    /// Refetches the route of `topic` from the name server, ignoring any cached lookup result.
+    /// Returns whether the route changed.
+    pub async fn refresh_topic_route(&mut self, topic: &CheetahString) -> bool {
+        match self.client_instance.as_mut() {
+            Some(client_instance) => client_instance.refresh_topic_route_info(topic).await,
+            None => false,
+        }
+    }
+
     pub fn register_end_transaction_hook(&mut self, hook: impl EndTransactionHook) {
         todo!()
     }
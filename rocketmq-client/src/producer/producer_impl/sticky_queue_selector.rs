@@ -0,0 +1,120 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::HashMap;
+use std::time::Duration;
+use std::time::Instant;
+
+use cheetah_string::CheetahString;
+use parking_lot::Mutex;
+use rocketmq_common::common::message::message_queue::MessageQueue;
+
+struct StickyQueue {
+    message_queue: MessageQueue,
+    stuck_at: Instant,
+    sent_bytes: u64,
+}
+
+/// Remembers the queue each topic currently sticks to and decides when to move on.
+pub(crate) struct StickyQueueSelector {
+    max_bytes: u64,
+    max_duration: Duration,
+    sticky_table: Mutex<HashMap<CheetahString /* topic */, StickyQueue>>,
+}
+
+impl StickyQueueSelector {
+    pub(crate) fn new(max_bytes: u64, max_millis: u64) -> Self {
+        Self {
+            max_bytes,
+            max_duration: Duration::from_millis(max_millis),
+            sticky_table: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the queue the topic sticks to, or `None` when a new queue should be selected
+    /// because the byte or time threshold has been reached.
+    pub(crate) fn current(&self, topic: &CheetahString) -> Option<MessageQueue> {
+        let mut sticky_table = self.sticky_table.lock();
+        let sticky = sticky_table.get(topic)?;
+        if sticky.sent_bytes >= self.max_bytes || sticky.stuck_at.elapsed() >= self.max_duration {
+            sticky_table.remove(topic);
+            return None;
+        }
+        Some(sticky.message_queue.clone())
+    }
+
+    /// Sticks the topic to `message_queue`, resetting the thresholds if the queue changed.
+    pub(crate) fn stick(&self, topic: &CheetahString, message_queue: &MessageQueue) {
+        let mut sticky_table = self.sticky_table.lock();
+        match sticky_table.get(topic) {
+            Some(sticky) if &sticky.message_queue == message_queue => {}
+            _ => {
+                sticky_table.insert(
+                    topic.clone(),
+                    StickyQueue {
+                        message_queue: message_queue.clone(),
+                        stuck_at: Instant::now(),
+                        sent_bytes: 0,
+                    },
+                );
+            }
+        }
+    }
+
+    /// Accounts `bytes` sent to the sticky queue of the topic.
+    pub(crate) fn record(&self, topic: &CheetahString, bytes: u64) {
+        if let Some(sticky) = self.sticky_table.lock().get_mut(topic) {
+            sticky.sent_bytes += bytes;
+        }
+    }
+
+    pub(crate) fn unstick(&self, topic: &CheetahString) {
+        self.sticky_table.lock().remove(topic);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sticks_until_byte_threshold() {
+        let selector = StickyQueueSelector::new(100, 60_000);
+        let topic = CheetahString::from_static_str("TopicTest");
+        let mq = MessageQueue::from_parts("TopicTest", "broker-a", 1);
+        assert!(selector.current(&topic).is_none());
+
+        selector.stick(&topic, &mq);
+        selector.record(&topic, 60);
+        assert_eq!(selector.current(&topic), Some(mq.clone()));
+
+        selector.stick(&topic, &mq);
+        selector.record(&topic, 60);
+        assert!(selector.current(&topic).is_none());
+    }
+
+    #[test]
+    fn unstick_forces_new_selection() {
+        let selector = StickyQueueSelector::new(100, 60_000);
+        let topic = CheetahString::from_static_str("TopicTest");
+        selector.stick(
+            &topic,
+            &MessageQueue::from_parts("TopicTest", "broker-a", 0),
+        );
+        selector.unstick(&topic);
+        assert!(selector.current(&topic).is_none());
+    }
+}
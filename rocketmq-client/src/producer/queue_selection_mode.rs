@@ -0,0 +1,38 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::fmt::Display;
+
+/// Strategy the producer uses to pick a message queue when no `MessageQueueSelector` is given.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum QueueSelectionMode {
+    /// Rotate over the queues of a topic on every send.
+    #[default]
+    RoundRobin,
+    /// Keep sending to the same queue of a topic until the sticky byte or time threshold is
+    /// reached, which lets the broker batch writes and improves page locality. The producer still
+    /// moves away from a queue as soon as its broker is marked unavailable.
+    Sticky,
+}
+
+impl Display for QueueSelectionMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QueueSelectionMode::RoundRobin => write!(f, "ROUND_ROBIN"),
+            QueueSelectionMode::Sticky => write!(f, "STICKY"),
+        }
+    }
+}
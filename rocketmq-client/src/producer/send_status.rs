@@ -28,6 +28,9 @@ pub enum SendStatus {
     FlushDiskTimeout,
     FlushSlaveTimeout,
     SlaveNotAvailable,
+    /// No broker was reachable, the message was written to the producer's disk spool and is
+    /// sent once a broker is back.
+    Spooled,
 }
 
 impl Serialize for SendStatus {
@@ -40,6 +43,7 @@ impl Serialize for SendStatus {
             SendStatus::FlushDiskTimeout => "FLUSH_DISK_TIMEOUT",
             SendStatus::FlushSlaveTimeout => "FLUSH_SLAVE_TIMEOUT",
             SendStatus::SlaveNotAvailable => "SLAVE_NOT_AVAILABLE",
+            SendStatus::Spooled => "SPOOLED",
         };
         serializer.serialize_str(value)
     }
@@ -68,6 +72,7 @@ impl<'de> Deserialize<'de> for SendStatus {
                     "FLUSH_DISK_TIMEOUT" => Ok(SendStatus::FlushDiskTimeout),
                     "FLUSH_SLAVE_TIMEOUT" => Ok(SendStatus::FlushSlaveTimeout),
                     "SLAVE_NOT_AVAILABLE" => Ok(SendStatus::SlaveNotAvailable),
+                    "SPOOLED" => Ok(SendStatus::Spooled),
                     _ => Err(serde::de::Error::unknown_variant(
                         value,
                         &[
@@ -75,6 +80,7 @@ impl<'de> Deserialize<'de> for SendStatus {
                             "FLUSH_DISK_TIMEOUT",
                             "FLUSH_SLAVE_TIMEOUT",
                             "SLAVE_NOT_AVAILABLE",
+                            "SPOOLED",
                         ],
                     )),
                 }
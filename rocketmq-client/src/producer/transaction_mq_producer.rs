@@ -28,6 +28,7 @@ use crate::producer::send_callback::SendMessageCallback;
 use crate::producer::send_result::SendResult;
 use crate::producer::transaction_listener::TransactionListener;
 use crate::producer::transaction_mq_produce_builder::TransactionMQProducerBuilder;
+use crate::producer::transaction_send_result::TransactionBatchSendResult;
 use crate::producer::transaction_send_result::TransactionSendResult;
 use crate::Result;
 
@@ -76,6 +77,34 @@ impl TransactionMQProducer {
             .unwrap()
             .set_check_runtime(Arc::new(check_runtime));
     }
+
+    /// Sends `msgs` as one transaction: the local transaction runs once and all messages are
+    /// committed or rolled back together by the broker.
+    ///
+    /// Every message must have a queue on the broker chosen for the first one. Transaction
+    /// checks still arrive per message, they carry the batch id in
+    /// `MessageConst::PROPERTY_TRANSACTION_BATCH_ID`.
+    pub async fn send_messages_in_transaction<T>(
+        &mut self,
+        mut msgs: Vec<Message>,
+        arg: Option<T>,
+    ) -> Result<TransactionBatchSendResult>
+    where
+        T: std::any::Any + Sync + Send,
+    {
+        for msg in msgs.iter_mut() {
+            msg.set_topic(self.default_producer.with_namespace(msg.get_topic()));
+        }
+        self.default_producer
+            .default_mqproducer_impl
+            .as_mut()
+            .unwrap()
+            .send_messages_in_transaction(
+                msgs,
+                arg.map(|x| Box::new(x) as Box<dyn Any + Sync + Send>),
+            )
+            .await
+    }
 }
 
 impl MQProducer for TransactionMQProducer {
@@ -16,6 +16,7 @@
  */
 use std::fmt::Display;
 
+use cheetah_string::CheetahString;
 use serde::Deserialize;
 use serde::Serialize;
 
@@ -30,6 +31,15 @@ pub struct TransactionSendResult {
     pub send_result: Option<SendResult>,
 }
 
+/// Result of sending a transactional batch, every message shares the local transaction state.
+#[derive(Debug, Clone)]
+pub struct TransactionBatchSendResult {
+    /// Batch id, carried by every half message in `PROPERTY_TRANSACTION_BATCH_ID`.
+    pub transaction_id: CheetahString,
+    pub local_transaction_state: LocalTransactionState,
+    pub send_results: Vec<SendResult>,
+}
+
 impl Display for TransactionSendResult {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
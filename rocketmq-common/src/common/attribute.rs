@@ -14,7 +14,11 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
+use std::sync::Arc;
+
 pub mod attribute_enum;
+pub mod attribute_list;
+pub mod attribute_long;
 pub mod attribute_parser;
 pub mod attribute_util;
 pub mod cleanup_policy;
@@ -49,6 +53,20 @@ pub trait AttributeTrait {
     fn verify(&self, value: &str);
 }
 
+impl<T: AttributeTrait + ?Sized> AttributeTrait for Arc<T> {
+    fn name(&self) -> String {
+        (**self).name()
+    }
+
+    fn changeable(&self) -> bool {
+        (**self).changeable()
+    }
+
+    fn verify(&self, value: &str) {
+        (**self).verify(value)
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct Attribute {
     pub(crate) name: String,
@@ -35,8 +35,13 @@ impl AttributeTrait for EnumAttribute {
         self.attribute.changeable
     }
 
-    fn verify(&self, _value: &str) {
-        todo!()
+    fn verify(&self, value: &str) {
+        if !self.universe.contains(value) {
+            panic!(
+                "value is not in set: {:?}, attribute: {}",
+                self.universe, self.attribute.name
+            );
+        }
     }
 }
 
@@ -0,0 +1,120 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use crate::common::attribute::Attribute;
+use crate::common::attribute::AttributeTrait;
+
+/// An attribute whose value is a comma separated list of names, e.g. `orderId,userId`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ListAttribute {
+    pub(crate) attribute: Attribute,
+    pub(crate) max_size: usize,
+}
+
+impl AttributeTrait for ListAttribute {
+    fn name(&self) -> String {
+        self.attribute.name.clone()
+    }
+
+    fn changeable(&self) -> bool {
+        self.attribute.changeable
+    }
+
+    fn verify(&self, value: &str) {
+        let items = Self::parse(value);
+        if items.len() > self.max_size {
+            panic!(
+                "too many items for attribute {}, max: {}",
+                self.attribute.name, self.max_size
+            );
+        }
+        if value.split(',').any(|item| item.trim().is_empty()) {
+            panic!(
+                "empty item in list value of attribute {}",
+                self.attribute.name
+            );
+        }
+        if items.iter().any(|item| item.contains('=')) {
+            panic!(
+                "item of attribute {} must not contain '='",
+                self.attribute.name
+            );
+        }
+    }
+}
+
+impl ListAttribute {
+    pub fn get_name(&self) -> &str {
+        self.attribute.name.as_str()
+    }
+
+    pub fn get_max_size(&self) -> usize {
+        self.max_size
+    }
+
+    /// Splits the raw value into trimmed, non-empty and de-duplicated items, keeping their
+    /// declared order.
+    pub fn parse(value: &str) -> Vec<String> {
+        let mut items: Vec<String> = Vec::new();
+        for item in value.split(',').map(str::trim) {
+            if !item.is_empty() && !items.iter().any(|existing| existing == item) {
+                items.push(item.to_string());
+            }
+        }
+        items
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attribute() -> ListAttribute {
+        ListAttribute {
+            attribute: Attribute {
+                name: String::from("index.property.keys"),
+                changeable: true,
+            },
+            max_size: 2,
+        }
+    }
+
+    #[test]
+    fn parse_trims_and_deduplicates() {
+        assert_eq!(
+            ListAttribute::parse(" orderId, userId,orderId,"),
+            vec!["orderId".to_string(), "userId".to_string()]
+        );
+        assert!(ListAttribute::parse("").is_empty());
+    }
+
+    #[test]
+    fn verify_accepts_valid_list() {
+        attribute().verify("orderId,userId");
+    }
+
+    #[test]
+    #[should_panic]
+    fn verify_rejects_too_many_items() {
+        attribute().verify("a,b,c");
+    }
+
+    #[test]
+    #[should_panic]
+    fn verify_rejects_empty_item() {
+        attribute().verify("orderId,,userId");
+    }
+}
@@ -0,0 +1,96 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use crate::common::attribute::Attribute;
+use crate::common::attribute::AttributeTrait;
+
+/// An attribute whose value is an integer within `[min, max]`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct LongAttribute {
+    pub(crate) attribute: Attribute,
+    pub(crate) min: i64,
+    pub(crate) max: i64,
+    pub(crate) default_value: i64,
+}
+
+impl AttributeTrait for LongAttribute {
+    fn name(&self) -> String {
+        self.attribute.name.clone()
+    }
+
+    fn changeable(&self) -> bool {
+        self.attribute.changeable
+    }
+
+    fn verify(&self, value: &str) {
+        match value.parse::<i64>() {
+            Ok(number) if number >= self.min && number <= self.max => {}
+            _ => panic!(
+                "value is not in range [{}, {}], attribute: {}",
+                self.min, self.max, self.attribute.name
+            ),
+        }
+    }
+}
+
+impl LongAttribute {
+    pub fn get_name(&self) -> &str {
+        self.attribute.name.as_str()
+    }
+
+    pub fn get_default_value(&self) -> i64 {
+        self.default_value
+    }
+
+    /// Parses `value`, `None` when it is not a number or out of range.
+    pub fn parse(&self, value: &str) -> Option<i64> {
+        value
+            .trim()
+            .parse::<i64>()
+            .ok()
+            .filter(|number| *number >= self.min && *number <= self.max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attribute() -> LongAttribute {
+        LongAttribute {
+            attribute: Attribute {
+                name: String::from("in.sync.replicas"),
+                changeable: true,
+            },
+            min: 1,
+            max: 8,
+            default_value: 1,
+        }
+    }
+
+    #[test]
+    fn parse_checks_range() {
+        assert_eq!(attribute().parse(" 2"), Some(2));
+        assert_eq!(attribute().parse("0"), None);
+        assert_eq!(attribute().parse("two"), None);
+    }
+
+    #[test]
+    #[should_panic]
+    fn verify_rejects_out_of_range() {
+        attribute().verify("9");
+    }
+}
@@ -76,6 +76,7 @@ pub fn alter_current_attributes<A: AttributeTrait>(
     let mut final_attributes = current_attributes.clone();
     final_attributes.extend(init);
     final_attributes.extend(add);
+    final_attributes.extend(update);
     for key in delete.keys() {
         final_attributes.remove(key);
     }
@@ -120,3 +121,32 @@ fn validate_alter<A: AttributeTrait>(
 fn real_key(key: &str) -> String {
     key.chars().skip(1).collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::attribute::topic_attributes::ALL;
+
+    #[test]
+    fn alter_current_attributes_applies_updates() {
+        let all: HashMap<CheetahString, _> = ALL
+            .clone()
+            .into_iter()
+            .map(|(k, v)| (k.into(), v))
+            .collect();
+        let current = HashMap::from([("flush.disk.type".into(), "ASYNC_FLUSH".into())]);
+        let new = HashMap::from([
+            ("+flush.disk.type".into(), "SYNC_FLUSH".into()),
+            ("+in.sync.replicas".into(), "2".into()),
+        ]);
+        let final_attributes = alter_current_attributes(false, all, current, new);
+        assert_eq!(
+            final_attributes.get("flush.disk.type").map(|v| v.as_str()),
+            Some("SYNC_FLUSH")
+        );
+        assert_eq!(
+            final_attributes.get("in.sync.replicas").map(|v| v.as_str()),
+            Some("2")
+        );
+    }
+}
@@ -1,10 +1,14 @@
 use std::collections::HashMap;
+use std::sync::Arc;
 
 use lazy_static::lazy_static;
 
 use crate::common::attribute::attribute_enum::EnumAttribute;
+use crate::common::attribute::attribute_list::ListAttribute;
+use crate::common::attribute::attribute_long::LongAttribute;
 use crate::common::attribute::topic_message_type::TopicMessageType;
 use crate::common::attribute::Attribute;
+use crate::common::attribute::AttributeTrait;
 use crate::hashset;
 
 lazy_static! {
@@ -32,19 +36,74 @@ lazy_static! {
         universe: hashset! {String::from("BatchCQ"), String::from("SimpleCQ")},
         default_value: String::from("SimpleCQ"),
     };
-    pub static ref ALL: HashMap<String, EnumAttribute> = {
-        let mut map = HashMap::<String, EnumAttribute>::new();
+    /// Property names whose values are indexed alongside `KEYS` and `UNIQ_KEY`, so that
+    /// messages can be queried with a `name=value` key.
+    pub static ref INDEX_PROPERTY_KEYS_ATTRIBUTE: ListAttribute = ListAttribute {
+        attribute: Attribute {
+            name: String::from("index.property.keys"),
+            changeable: true,
+        },
+        max_size: 8,
+    };
+    /// Ids of the body validators, registered on the broker, every message sent to the topic
+    /// must pass before it is stored, e.g. `json` or `schema:order-v1`.
+    pub static ref BODY_VALIDATORS_ATTRIBUTE: ListAttribute = ListAttribute {
+        attribute: Attribute {
+            name: String::from("body.validators"),
+            changeable: true,
+        },
+        max_size: 4,
+    };
+    /// Flush type the topic requires, overriding the broker wide `flushDiskType`. Unset topics
+    /// follow the broker.
+    pub static ref FLUSH_DISK_TYPE_ATTRIBUTE: EnumAttribute = EnumAttribute {
+        attribute: Attribute {
+            name: String::from("flush.disk.type"),
+            changeable: true,
+        },
+        universe: hashset! {String::from("SYNC_FLUSH"), String::from("ASYNC_FLUSH")},
+        default_value: String::new(),
+    };
+    /// Replicas, the master included, a put on the topic waits for before it is acknowledged,
+    /// overriding the broker wide `inSyncReplicas`.
+    pub static ref IN_SYNC_REPLICAS_ATTRIBUTE: LongAttribute = LongAttribute {
+        attribute: Attribute {
+            name: String::from("in.sync.replicas"),
+            changeable: true,
+        },
+        min: 1,
+        max: 16,
+        default_value: 1,
+    };
+    pub static ref ALL: HashMap<String, Arc<dyn AttributeTrait + Send + Sync>> = {
+        let mut map = HashMap::<String, Arc<dyn AttributeTrait + Send + Sync>>::new();
         map.insert(
             QUEUE_TYPE_ATTRIBUTE.get_name().to_string(),
-            QUEUE_TYPE_ATTRIBUTE.clone(),
+            Arc::new(QUEUE_TYPE_ATTRIBUTE.clone()),
         );
         map.insert(
             CLEANUP_POLICY_ATTRIBUTE.get_name().to_string(),
-            CLEANUP_POLICY_ATTRIBUTE.clone(),
+            Arc::new(CLEANUP_POLICY_ATTRIBUTE.clone()),
         );
         map.insert(
             TOPIC_MESSAGE_TYPE_ATTRIBUTE.get_name().to_string(),
-            TOPIC_MESSAGE_TYPE_ATTRIBUTE.clone(),
+            Arc::new(TOPIC_MESSAGE_TYPE_ATTRIBUTE.clone()),
+        );
+        map.insert(
+            INDEX_PROPERTY_KEYS_ATTRIBUTE.get_name().to_string(),
+            Arc::new(INDEX_PROPERTY_KEYS_ATTRIBUTE.clone()),
+        );
+        map.insert(
+            BODY_VALIDATORS_ATTRIBUTE.get_name().to_string(),
+            Arc::new(BODY_VALIDATORS_ATTRIBUTE.clone()),
+        );
+        map.insert(
+            FLUSH_DISK_TYPE_ATTRIBUTE.get_name().to_string(),
+            Arc::new(FLUSH_DISK_TYPE_ATTRIBUTE.clone()),
+        );
+        map.insert(
+            IN_SYNC_REPLICAS_ATTRIBUTE.get_name().to_string(),
+            Arc::new(IN_SYNC_REPLICAS_ATTRIBUTE.clone()),
         );
         map
     };
@@ -31,6 +31,9 @@ pub struct PlainAccessConfig {
     pub default_group_perm: Option<CheetahString>,
     pub topic_perms: Vec<CheetahString>,
     pub group_perms: Vec<CheetahString>,
+    /// Admin operations granted to a non-admin account, e.g. `updateTopic` or `*` for all.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub admin_perms: Vec<CheetahString>,
 }
 
 impl Display for PlainAccessConfig {
@@ -39,7 +42,7 @@ impl Display for PlainAccessConfig {
             f,
             "PlainAccessConfig {{ access_key: {:?}, secret_key: {:?}, white_remote_address: {:?}, \
              admin: {}, default_topic_perm: {:?}, default_group_perm: {:?}, topic_perms: {:?}, \
-             group_perms: {:?} }}",
+             group_perms: {:?}, admin_perms: {:?} }}",
             self.access_key,
             self.secret_key,
             self.white_remote_address,
@@ -47,7 +50,8 @@ impl Display for PlainAccessConfig {
             self.default_topic_perm,
             self.default_group_perm,
             self.topic_perms,
-            self.group_perms
+            self.group_perms,
+            self.admin_perms
         )
     }
 }
@@ -69,6 +73,7 @@ mod tests {
             default_group_perm: None,
             topic_perms: Vec::new(),
             group_perms: Vec::new(),
+            admin_perms: Vec::new(),
         };
         assert!(config.access_key.is_none());
         assert!(config.secret_key.is_none());
@@ -78,6 +83,7 @@ mod tests {
         assert!(config.default_group_perm.is_none());
         assert!(config.topic_perms.is_empty());
         assert!(config.group_perms.is_empty());
+        assert!(config.admin_perms.is_empty());
     }
 
     #[test]
@@ -91,6 +97,7 @@ mod tests {
             default_group_perm: Some(CheetahString::from("perm2")),
             topic_perms: vec![CheetahString::from("topic1")],
             group_perms: vec![CheetahString::from("group1")],
+            admin_perms: vec![],
         };
 
         let config2 = PlainAccessConfig {
@@ -102,6 +109,7 @@ mod tests {
             default_group_perm: Some(CheetahString::from("perm2")),
             topic_perms: vec![CheetahString::from("topic1")],
             group_perms: vec![CheetahString::from("group1")],
+            admin_perms: vec![],
         };
 
         assert_eq!(config1, config2);
@@ -118,6 +126,7 @@ mod tests {
             default_group_perm: Some(CheetahString::from("perm2")),
             topic_perms: vec![CheetahString::from("topic1")],
             group_perms: vec![CheetahString::from("group1")],
+            admin_perms: vec![],
         };
 
         let config2 = PlainAccessConfig {
@@ -129,6 +138,7 @@ mod tests {
             default_group_perm: Some(CheetahString::from("perm4")),
             topic_perms: vec![CheetahString::from("topic2")],
             group_perms: vec![CheetahString::from("group2")],
+            admin_perms: vec![],
         };
 
         assert_ne!(config1, config2);
@@ -145,6 +155,7 @@ mod tests {
             default_group_perm: Some(CheetahString::from("perm2")),
             topic_perms: vec![CheetahString::from("topic1")],
             group_perms: vec![CheetahString::from("group1")],
+            admin_perms: vec![],
         };
         let serialized = serde_json::to_string(&config).unwrap();
         assert_eq!(
@@ -29,6 +29,7 @@ use crate::common::mix_all;
 use crate::common::mix_all::NAMESRV_ADDR_PROPERTY;
 use crate::common::server::config::ServerConfig;
 use crate::common::topic::TopicValidator;
+use crate::utils::network_util::NetworkUtil;
 
 const DEFAULT_CLUSTER_NAME: &str = "DefaultCluster";
 
@@ -179,6 +180,43 @@ pub struct BrokerConfig {
     pub load_balance_poll_name_server_interval: u64,
     pub server_load_balancer_enable: bool,
     pub enable_remote_escape: bool,
+    pub pop_consumer_kv_service_enable: bool,
+    pub pop_consumer_kv_service_init: bool,
+    pub pop_consumer_kv_service_batch_size: usize,
+    pub request_priority_enable: bool,
+    pub normal_priority_request_max_concurrency: usize,
+    pub low_priority_request_max_concurrency: usize,
+    pub maintenance_admin_white_list: CheetahString,
+    pub admin_acl_enable: bool,
+    pub enable_retry_topic_v2: bool,
+    pub audit_log_enable: bool,
+    pub audit_log_max_file_size: u64,
+    pub audit_log_max_files: u32,
+    pub audit_log_export_trace_topic: bool,
+    pub poison_message_reconsume_gap: i32,
+    pub poison_message_auto_pause: bool,
+    pub auth_jwt_enable: bool,
+    pub auth_jwt_issuer: CheetahString,
+    pub auth_jwt_audience: CheetahString,
+    pub auth_jwt_jwks_url: CheetahString,
+    pub auth_jwt_hmac_secret: CheetahString,
+    pub auth_jwt_jwks_refresh_interval_millis: u64,
+    pub auth_jwt_clock_skew_seconds: i64,
+    pub replay_subscription_max_ttl_millis: i64,
+    pub pull_body_compression_enable: bool,
+    pub pull_body_compression_threshold: usize,
+    pub pull_body_compression_level: i32,
+    pub pull_body_compression_cpu_budget_micros_per_second: u64,
+    pub transaction_op_batch_interval: u64,
+    pub transaction_check_max: i32,
+    pub transaction_check_interval: u64,
+    pub disabled_feature_bitmap: u64,
+    pub long_polling_prefetch_enable: bool,
+    pub long_polling_prefetch_msg_nums: i32,
+    /// Port of the HTTP liveness/readiness endpoint, `0` disables it.
+    pub health_listen_port: u32,
+    /// How long a SIGTERM-initiated drain rejects writes before the broker shuts down.
+    pub shutdown_grace_period_millis: u64,
 }
 
 impl Default for BrokerConfig {
@@ -266,6 +304,41 @@ impl Default for BrokerConfig {
             load_balance_poll_name_server_interval: 30_000,
             server_load_balancer_enable: true,
             enable_remote_escape: false,
+            pop_consumer_kv_service_enable: false,
+            pop_consumer_kv_service_init: false,
+            pop_consumer_kv_service_batch_size: 1024,
+            request_priority_enable: true,
+            normal_priority_request_max_concurrency: 64,
+            low_priority_request_max_concurrency: 256,
+            maintenance_admin_white_list: CheetahString::from_static_str("127.0.0.1"),
+            admin_acl_enable: false,
+            enable_retry_topic_v2: false,
+            audit_log_enable: false,
+            audit_log_max_file_size: 100 * 1024 * 1024,
+            audit_log_max_files: 10,
+            audit_log_export_trace_topic: false,
+            poison_message_reconsume_gap: 1,
+            poison_message_auto_pause: false,
+            auth_jwt_enable: false,
+            auth_jwt_issuer: CheetahString::empty(),
+            auth_jwt_audience: CheetahString::empty(),
+            auth_jwt_jwks_url: CheetahString::empty(),
+            auth_jwt_hmac_secret: CheetahString::empty(),
+            auth_jwt_jwks_refresh_interval_millis: 300000,
+            auth_jwt_clock_skew_seconds: 60,
+            replay_subscription_max_ttl_millis: 24 * 60 * 60 * 1000,
+            pull_body_compression_enable: false,
+            pull_body_compression_threshold: 64 * 1024,
+            pull_body_compression_level: 3,
+            pull_body_compression_cpu_budget_micros_per_second: 200_000,
+            transaction_op_batch_interval: 3_000,
+            transaction_check_max: 15,
+            transaction_check_interval: 30_000,
+            disabled_feature_bitmap: 0,
+            long_polling_prefetch_enable: true,
+            long_polling_prefetch_msg_nums: 32,
+            health_listen_port: 0,
+            shutdown_grace_period_millis: 30_000,
         }
     }
 }
@@ -304,7 +377,7 @@ impl BrokerConfig {
     }
 
     pub fn get_broker_addr(&self) -> String {
-        format!("{}:{}", self.broker_ip1, self.listen_port)
+        NetworkUtil::format_address(self.broker_ip1.as_str(), self.listen_port)
     }
 
     pub fn get_start_accept_send_request_time_stamp(&self) -> i64 {
@@ -559,6 +632,148 @@ impl BrokerConfig {
             "forwardTimeout".into(),
             self.forward_timeout.to_string().into(),
         );
+        properties.insert(
+            "popConsumerKVServiceEnable".into(),
+            self.pop_consumer_kv_service_enable.to_string().into(),
+        );
+        properties.insert(
+            "popConsumerKVServiceInit".into(),
+            self.pop_consumer_kv_service_init.to_string().into(),
+        );
+        properties.insert(
+            "popConsumerKVServiceBatchSize".into(),
+            self.pop_consumer_kv_service_batch_size.to_string().into(),
+        );
+        properties.insert(
+            "requestPriorityEnable".into(),
+            self.request_priority_enable.to_string().into(),
+        );
+        properties.insert(
+            "normalPriorityRequestMaxConcurrency".into(),
+            self.normal_priority_request_max_concurrency
+                .to_string()
+                .into(),
+        );
+        properties.insert(
+            "lowPriorityRequestMaxConcurrency".into(),
+            self.low_priority_request_max_concurrency.to_string().into(),
+        );
+        properties.insert(
+            "maintenanceAdminWhiteList".into(),
+            self.maintenance_admin_white_list.to_string().into(),
+        );
+        properties.insert(
+            "adminAclEnable".into(),
+            self.admin_acl_enable.to_string().into(),
+        );
+        properties.insert(
+            "enableRetryTopicV2".into(),
+            self.enable_retry_topic_v2.to_string().into(),
+        );
+        properties.insert(
+            "auditLogEnable".into(),
+            self.audit_log_enable.to_string().into(),
+        );
+        properties.insert(
+            "auditLogMaxFileSize".into(),
+            self.audit_log_max_file_size.to_string().into(),
+        );
+        properties.insert(
+            "auditLogMaxFiles".into(),
+            self.audit_log_max_files.to_string().into(),
+        );
+        properties.insert(
+            "auditLogExportTraceTopic".into(),
+            self.audit_log_export_trace_topic.to_string().into(),
+        );
+        properties.insert(
+            "poisonMessageReconsumeGap".into(),
+            self.poison_message_reconsume_gap.to_string().into(),
+        );
+        properties.insert(
+            "poisonMessageAutoPause".into(),
+            self.poison_message_auto_pause.to_string().into(),
+        );
+        properties.insert(
+            "authJwtEnable".into(),
+            self.auth_jwt_enable.to_string().into(),
+        );
+        properties.insert(
+            "authJwtIssuer".into(),
+            self.auth_jwt_issuer.to_string().into(),
+        );
+        properties.insert(
+            "authJwtAudience".into(),
+            self.auth_jwt_audience.to_string().into(),
+        );
+        properties.insert(
+            "authJwtJwksUrl".into(),
+            self.auth_jwt_jwks_url.to_string().into(),
+        );
+        properties.insert(
+            "authJwtJwksRefreshIntervalMillis".into(),
+            self.auth_jwt_jwks_refresh_interval_millis
+                .to_string()
+                .into(),
+        );
+        properties.insert(
+            "authJwtClockSkewSeconds".into(),
+            self.auth_jwt_clock_skew_seconds.to_string().into(),
+        );
+        properties.insert(
+            "replaySubscriptionMaxTtlMillis".into(),
+            self.replay_subscription_max_ttl_millis.to_string().into(),
+        );
+        properties.insert(
+            "pullBodyCompressionEnable".into(),
+            self.pull_body_compression_enable.to_string().into(),
+        );
+        properties.insert(
+            "pullBodyCompressionThreshold".into(),
+            self.pull_body_compression_threshold.to_string().into(),
+        );
+        properties.insert(
+            "pullBodyCompressionLevel".into(),
+            self.pull_body_compression_level.to_string().into(),
+        );
+        properties.insert(
+            "pullBodyCompressionCpuBudgetMicrosPerSecond".into(),
+            self.pull_body_compression_cpu_budget_micros_per_second
+                .to_string()
+                .into(),
+        );
+        properties.insert(
+            "transactionOpBatchInterval".into(),
+            self.transaction_op_batch_interval.to_string().into(),
+        );
+        properties.insert(
+            "transactionCheckMax".into(),
+            self.transaction_check_max.to_string().into(),
+        );
+        properties.insert(
+            "transactionCheckInterval".into(),
+            self.transaction_check_interval.to_string().into(),
+        );
+        properties.insert(
+            "disabledFeatureBitmap".into(),
+            self.disabled_feature_bitmap.to_string().into(),
+        );
+        properties.insert(
+            "longPollingPrefetchEnable".into(),
+            self.long_polling_prefetch_enable.to_string().into(),
+        );
+        properties.insert(
+            "longPollingPrefetchMsgNums".into(),
+            self.long_polling_prefetch_msg_nums.to_string().into(),
+        );
+        properties.insert(
+            "healthListenPort".into(),
+            self.health_listen_port.to_string().into(),
+        );
+        properties.insert(
+            "shutdownGracePeriodMillis".into(),
+            self.shutdown_grace_period_millis.to_string().into(),
+        );
         properties
     }
 }
@@ -28,9 +28,9 @@ pub trait ConfigManager {
     /// Loads the configuration from a file.
     ///
     /// This method attempts to load the configuration from a file whose path is returned by
-    /// `config_file_path`. If the file content is empty, it attempts to load from a backup
-    /// file. If the file content is not empty, it decodes the content and logs a success
-    /// message.
+    /// `config_file_path`. If the file content is empty or is not valid JSON, it attempts to
+    /// load from a backup file. If the backup carries a newer data version than the primary
+    /// file, the backup wins. Otherwise it decodes the content and logs a success message.
     ///
     /// # Returns
     /// * `true` if the configuration is successfully loaded and decoded.
@@ -43,7 +43,28 @@ pub trait ConfigManager {
                 if content.is_empty() {
                     warn!("load bak config file");
                     self.load_bak()
+                } else if serde_json::from_str::<serde_json::Value>(content).is_err() {
+                    warn!(
+                        "config file {} is corrupted, load bak config file",
+                        file_name
+                    );
+                    self.load_bak()
                 } else {
+                    let bak_content =
+                        FileUtils::file_to_string(format!("{}{}", file_name, ".bak").as_str())
+                            .unwrap_or_default();
+                    if let (Some(version), Some(bak_version)) =
+                        (data_version_of(content), data_version_of(&bak_content))
+                    {
+                        if bak_version > version {
+                            warn!(
+                                "config file {} has data version {:?} older than its bak {:?}, \
+                                 load bak config file",
+                                file_name, version, bak_version
+                            );
+                            return self.load_bak();
+                        }
+                    }
                     self.decode(content);
                     info!("load Config file: {} -----OK", file_name);
                     true
@@ -67,6 +88,10 @@ pub trait ConfigManager {
         if let Ok(ref content) =
             FileUtils::file_to_string(format!("{}{}", file_name, ".bak").as_str())
         {
+            if !content.is_empty() && serde_json::from_str::<serde_json::Value>(content).is_err() {
+                error!("load Config file: {}.bak -----Corrupted", file_name);
+                return false;
+            }
             if !content.is_empty() {
                 self.decode(content);
                 info!("load Config file: {}.bak -----OK", file_name);
@@ -170,3 +195,76 @@ pub trait ConfigManager {
     /// * `json_string` - A `&str` representing the configuration in JSON format.
     fn decode(&self, json_string: &str);
 }
+
+/// Reads the `(stateVersion, counter)` of the `dataVersion` embedded in a persisted config,
+/// used to order a config file against its backup.
+pub fn data_version_of(json_string: &str) -> Option<(i64, i64)> {
+    let value = serde_json::from_str::<serde_json::Value>(json_string).ok()?;
+    let data_version = value.get("dataVersion")?;
+    let state_version = data_version
+        .get("stateVersion")
+        .and_then(|v| v.as_i64())
+        .unwrap_or_default();
+    let counter = data_version.get("counter")?.as_i64()?;
+    Some((state_version, counter))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    struct TestConfigManager {
+        path: String,
+        decoded: Mutex<Option<String>>,
+    }
+
+    impl ConfigManager for TestConfigManager {
+        fn config_file_path(&self) -> String {
+            self.path.clone()
+        }
+
+        fn encode_pretty(&self, _pretty_format: bool) -> String {
+            self.decoded.lock().unwrap().clone().unwrap_or_default()
+        }
+
+        fn decode(&self, json_string: &str) {
+            *self.decoded.lock().unwrap() = Some(json_string.to_string());
+        }
+    }
+
+    fn manager(dir: &tempfile::TempDir) -> TestConfigManager {
+        TestConfigManager {
+            path: dir.path().join("test.json").to_str().unwrap().to_string(),
+            decoded: Mutex::new(None),
+        }
+    }
+
+    #[test]
+    fn corrupted_file_falls_back_to_bak() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = manager(&dir);
+        let bak = r#"{"dataVersion":{"stateVersion":0,"timestamp":1,"counter":3}}"#;
+        std::fs::write(format!("{}.bak", manager.path), bak).unwrap();
+        std::fs::write(&manager.path, r#"{"dataVersion":{"sta"#).unwrap();
+
+        assert!(manager.load());
+        assert_eq!(manager.decoded.lock().unwrap().as_deref(), Some(bak));
+    }
+
+    #[test]
+    fn newer_bak_data_version_wins() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = manager(&dir);
+        let primary = r#"{"dataVersion":{"stateVersion":0,"timestamp":1,"counter":2}}"#;
+        let bak = r#"{"dataVersion":{"stateVersion":0,"timestamp":1,"counter":5}}"#;
+        std::fs::write(&manager.path, primary).unwrap();
+        std::fs::write(format!("{}.bak", manager.path), bak).unwrap();
+
+        assert!(manager.load());
+        assert_eq!(manager.decoded.lock().unwrap().as_deref(), Some(bak));
+        assert_eq!(data_version_of(primary), Some((0, 2)));
+        assert_eq!(data_version_of("{}"), None);
+    }
+}
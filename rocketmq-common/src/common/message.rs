@@ -36,6 +36,7 @@ pub mod message_enum;
 pub mod message_ext;
 pub mod message_ext_broker_inner;
 pub mod message_id;
+pub mod message_integrity;
 pub mod message_queue;
 pub mod message_queue_assignment;
 pub mod message_single;
@@ -510,6 +511,7 @@ impl MessageConst {
     pub const PROPERTY_CORRECTION_FLAG: &'static str = "CORRECTION_FLAG";
     pub const PROPERTY_CORRELATION_ID: &'static str = "CORRELATION_ID";
     pub const PROPERTY_CRC32: &'static str = "__CRC32#";
+    pub const PROPERTY_BODY_CRC32: &'static str = "__BODY_CRC32";
     pub const PROPERTY_DELAY_TIME_LEVEL: &'static str = "DELAY";
     pub const PROPERTY_DLQ_ORIGIN_MESSAGE_ID: &'static str = "DLQ_ORIGIN_MESSAGE_ID";
     pub const PROPERTY_STARTDE_LIVER_TIME: &'static str = "__STARTDELIVERTIME";
@@ -558,6 +560,8 @@ impl MessageConst {
     pub const PROPERTY_TIMER_ROLL_TIMES: &'static str = "TIMER_ROLL_TIMES";
     pub const PROPERTY_TRACE_CONTEXT: &'static str = "TRACE_CONTEXT";
     pub const PROPERTY_TRACE_SWITCH: &'static str = "TRACE_ON";
+    pub const PROPERTY_TRANSACTION_BATCH_ID: &'static str = "__TRAN_BATCH_ID";
+    pub const PROPERTY_TRANSACTION_BATCH_SIZE: &'static str = "__TRAN_BATCH_SIZE";
     pub const PROPERTY_TRANSACTION_CHECK_TIMES: &'static str = "TRANSACTION_CHECK_TIMES";
     pub const PROPERTY_TRANSACTION_ID: &'static str = "__transactionId__";
     pub const PROPERTY_TRANSACTION_PREPARED: &'static str = "TRAN_MSG";
@@ -635,6 +639,9 @@ lazy_static! {
         set.insert(MessageConst::PROPERTY_DLQ_ORIGIN_TOPIC);
         set.insert(MessageConst::PROPERTY_DLQ_ORIGIN_MESSAGE_ID);
         set.insert(MessageConst::PROPERTY_CRC32);
+        set.insert(MessageConst::PROPERTY_BODY_CRC32);
+        set.insert(MessageConst::PROPERTY_TRANSACTION_BATCH_ID);
+        set.insert(MessageConst::PROPERTY_TRANSACTION_BATCH_SIZE);
         set
     };
 }
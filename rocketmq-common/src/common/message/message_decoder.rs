@@ -306,7 +306,7 @@ pub fn decode(
                 && (sys_flag & MessageSysFlag::COMPRESSED_FLAG) == MessageSysFlag::COMPRESSED_FLAG
             {
                 let compression_type = CompressionType::find_by_value(
-                    (flag & MessageSysFlag::COMPRESSION_TYPE_COMPARATOR) >> 8,
+                    (sys_flag & MessageSysFlag::COMPRESSION_TYPE_COMPARATOR) >> 8,
                 );
                 body_bytes = compression_type.decompression(&body_bytes)
             }
@@ -369,6 +369,92 @@ pub fn decode(
     Some(msg_ext)
 }
 
+/// Rewrites the properties section of the stored message `record`, keeping only the
+/// properties for which `keep` returns `true`. Returns `None` when the record is malformed or
+/// nothing was dropped, the original record can be used as is then.
+pub fn project_properties<F>(record: &[u8], keep: F) -> Option<Bytes>
+where
+    F: Fn(&str) -> bool,
+{
+    if record.len() < BORN_TIMESTAMP_POSITION + 8 {
+        return None;
+    }
+    let magic_code = i32::from_be_bytes(
+        record[MESSAGE_MAGIC_CODE_POSITION..MESSAGE_MAGIC_CODE_POSITION + 4]
+            .try_into()
+            .ok()?,
+    );
+    let version = MessageVersion::value_of_magic_code(magic_code).ok()?;
+    let sys_flag = i32::from_be_bytes(
+        record[SYSFLAG_POSITION..SYSFLAG_POSITION + 4]
+            .try_into()
+            .ok()?,
+    );
+    let born_host_length = if sys_flag & MessageSysFlag::BORNHOST_V6_FLAG != 0 {
+        20
+    } else {
+        8
+    };
+    let store_host_length = if sys_flag & MessageSysFlag::STOREHOSTADDRESS_V6_FLAG != 0 {
+        20
+    } else {
+        8
+    };
+    let body_len_position =
+        BORN_TIMESTAMP_POSITION + 8 + born_host_length + 8 + store_host_length + 4 + 8;
+    let body_len = i32::from_be_bytes(
+        record
+            .get(body_len_position..body_len_position + 4)?
+            .try_into()
+            .ok()?,
+    );
+    let topic_len_position = body_len_position + 4 + body_len.max(0) as usize;
+    if topic_len_position + version.get_topic_length_size() > record.len() {
+        return None;
+    }
+    let topic_len = version.get_topic_length_at_index(record, topic_len_position);
+    let properties_len_position = topic_len_position + version.get_topic_length_size() + topic_len;
+    let properties_len = i16::from_be_bytes(
+        record
+            .get(properties_len_position..properties_len_position + 2)?
+            .try_into()
+            .ok()?,
+    )
+    .max(0) as usize;
+    let properties_position = properties_len_position + 2;
+    let properties =
+        str::from_utf8(record.get(properties_position..properties_position + properties_len)?)
+            .ok()?;
+
+    let mut projected = String::with_capacity(properties.len());
+    let mut dropped = false;
+    for property in properties
+        .split(PROPERTY_SEPARATOR)
+        .filter(|property| !property.is_empty())
+    {
+        let name = property
+            .split_once(NAME_VALUE_SEPARATOR)
+            .map_or(property, |(name, _)| name);
+        if keep(name) {
+            projected.push_str(property);
+            projected.push(PROPERTY_SEPARATOR);
+        } else {
+            dropped = true;
+        }
+    }
+    if !dropped {
+        return None;
+    }
+
+    let store_size = properties_position + projected.len();
+    let mut buffer = BytesMut::with_capacity(store_size);
+    buffer.put_i32(store_size as i32);
+    buffer.put_slice(&record[4..properties_len_position]);
+    buffer.put_i16(projected.len() as i16);
+    buffer.put_slice(projected.as_bytes());
+    Some(buffer.freeze())
+}
+
 pub fn count_inner_msg_num(bytes: Option<Bytes>) -> u32 {
     match bytes {
         None => 0,
@@ -540,9 +626,21 @@ pub fn decode_message(buffer: &mut Bytes) -> Message {
 }
 
 pub fn decode_message_id(msg_id: &str) -> MessageId {
-    let bytes = util_all::string_to_bytes(msg_id).unwrap();
+    try_decode_message_id(msg_id).unwrap_or_else(|| panic!("invalid message id: {}", msg_id))
+}
+
+/// Decodes an offset message id, returning `None` unless it is a 32 (IPv4) or 56 (IPv6)
+/// character hex string.
+pub fn try_decode_message_id(msg_id: &str) -> Option<MessageId> {
+    if msg_id.len() != 32 && msg_id.len() != 56 {
+        return None;
+    }
+    if !msg_id.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    let bytes = util_all::string_to_bytes(msg_id)?;
     let mut buffer = Bytes::from(bytes);
-    let len = if msg_id.len() == 32 {
+    let address = if msg_id.len() == 32 {
         let mut ip = [0u8; 4];
         buffer.copy_to_slice(&mut ip);
         let port = buffer.get_i32();
@@ -553,10 +651,10 @@ pub fn decode_message_id(msg_id: &str) -> MessageId {
         let port = buffer.get_i32();
         SocketAddr::new(IpAddr::V6(Ipv6Addr::from(ip)), port as u16)
     };
-    MessageId {
-        address: len,
+    Some(MessageId {
+        address,
         offset: buffer.get_i64(),
-    }
+    })
 }
 
 pub fn encode(message_ext: &MessageExt, need_compress: bool) -> Result<Bytes> {
@@ -809,6 +907,22 @@ pub fn create_crc32(mut input: &mut [u8], crc32: u32) {
     input.put_u8(PROPERTY_SEPARATOR as u8);
 }
 
+/// Reads back the value of the `PROPERTY_CRC32` property written by [`create_crc32`], the digits
+/// are stored least significant first.
+pub fn parse_crc32(value: &str) -> Option<u32> {
+    if value.is_empty() {
+        return None;
+    }
+    let mut crc32 = 0u64;
+    for b in value.bytes().rev() {
+        if !b.is_ascii_digit() {
+            return None;
+        }
+        crc32 = crc32 * 10 + (b - b'0') as u64;
+    }
+    u32::try_from(crc32).ok()
+}
+
 #[cfg(test)]
 mod tests {
     use bytes::BufMut;
@@ -816,6 +930,22 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    fn parse_crc32_reads_back_create_crc32() {
+        let mut buf = vec![0u8; MessageConst::PROPERTY_CRC32.len() + 1 + 10 + 1];
+        create_crc32(&mut buf, 1234567);
+        let properties = String::from_utf8(buf).unwrap();
+        let value = properties
+            .trim_end_matches(PROPERTY_SEPARATOR)
+            .split(NAME_VALUE_SEPARATOR)
+            .nth(1)
+            .unwrap();
+        assert_eq!(value, "7654321000");
+        assert_eq!(parse_crc32(value), Some(1234567));
+        assert_eq!(parse_crc32("12a"), None);
+        assert_eq!(parse_crc32(""), None);
+    }
+
     #[test]
     fn count_inner_msg_num_counts_correctly_for_multiple_messages() {
         let mut bytes = BytesMut::new();
@@ -855,6 +985,45 @@ mod tests {
         assert_eq!(message_id.offset, 860316681131967304);
     }
 
+    #[test]
+    fn decode_message_id_ipv6_round_trip() {
+        let address: SocketAddr = "[fe80::1:2]:10911".parse().unwrap();
+        let msg_id = build_message_id(address, 123456789);
+        assert_eq!(msg_id.len(), 56);
+        let message_id = decode_message_id(&msg_id);
+        assert_eq!(message_id.address, address);
+        assert_eq!(message_id.offset, 123456789);
+
+        let address: SocketAddr = "10.1.2.3:10911".parse().unwrap();
+        let message_id = decode_message_id(&build_message_id(address, 42));
+        assert_eq!(message_id.address, address);
+        assert_eq!(message_id.offset, 42);
+    }
+
+    #[test]
+    fn try_decode_message_id_rejects_malformed() {
+        assert!(try_decode_message_id("").is_none());
+        assert!(try_decode_message_id("7F0000010007D826").is_none());
+        assert!(try_decode_message_id("ZZ0000010007D8260BF075769D36C348").is_none());
+    }
+
+    #[test]
+    fn born_host_ipv6_display_round_trip() {
+        let mut message_ext = MessageExt::default();
+        message_ext.set_born_host("[2001:db8::7]:5000".parse().unwrap());
+        message_ext.with_born_host_v6_flag();
+        assert_eq!(message_ext.born_host_bytes().len(), 20);
+        let displayed = message_ext.born_host().to_string();
+        assert_eq!(displayed, "[2001:db8::7]:5000");
+        assert_eq!(
+            displayed.parse::<SocketAddr>().unwrap(),
+            message_ext.born_host()
+        );
+        assert!(message_ext
+            .to_string()
+            .contains("born_host: [2001:db8::7]:5000"));
+    }
+
     #[test]
     fn encode_with_compression() {
         let mut message_ext = MessageExt::default();
@@ -936,4 +1105,45 @@ mod tests {
         let bytes = result.unwrap();
         assert!(!bytes.is_empty());
     }
+
+    #[test]
+    fn project_properties_keeps_selected_properties() {
+        let mut message_ext = MessageExt::default();
+        message_ext.set_topic(CheetahString::from_static_str("TopicTest"));
+        message_ext.set_body(Bytes::from("Hello, World!"));
+        message_ext.put_property(
+            CheetahString::from_static_str(MessageConst::PROPERTY_TAGS),
+            CheetahString::from_static_str("TagA"),
+        );
+        message_ext.put_property(
+            CheetahString::from_static_str("wanted"),
+            CheetahString::from_static_str("1"),
+        );
+        message_ext.put_property(
+            CheetahString::from_static_str("blob"),
+            CheetahString::from_string("x".repeat(512)),
+        );
+        let record = encode(&message_ext, false).unwrap();
+
+        let projected = project_properties(&record, |name| name != "blob").unwrap();
+        assert!(projected.len() < record.len());
+        let mut buffer = projected.clone();
+        let decoded = decode(&mut buffer, true, false, false, false, false).unwrap();
+        assert_eq!(decoded.store_size(), projected.len() as i32);
+        assert_eq!(decoded.get_topic(), "TopicTest");
+        assert_eq!(decoded.get_body().unwrap().as_ref(), b"Hello, World!");
+        assert_eq!(decoded.get_tags().unwrap(), "TagA");
+        assert_eq!(
+            decoded
+                .get_property(&CheetahString::from_static_str("wanted"))
+                .unwrap(),
+            "1"
+        );
+        assert!(decoded
+            .get_property(&CheetahString::from_static_str("blob"))
+            .is_none());
+
+        assert!(project_properties(&record, |_| true).is_none());
+        assert!(project_properties(&record[..16], |_| false).is_none());
+    }
 }
@@ -0,0 +1,159 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::HashMap;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+
+use cheetah_string::CheetahString;
+
+use crate::common::compression::compressor_factory::CompressorFactory;
+use crate::common::message::MessageConst;
+use crate::common::message::MessageTrait;
+use crate::common::sys_flag::message_sys_flag::MessageSysFlag;
+use crate::utils::crc32_utils::crc32;
+
+/// Outcome of checking a message body against the CRC attached by its producer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BodyCrcCheck {
+    /// The producer did not attach a CRC.
+    Absent,
+    Matched,
+    Mismatched {
+        expected: u32,
+        actual: u32,
+    },
+}
+
+impl BodyCrcCheck {
+    pub fn is_mismatched(&self) -> bool {
+        matches!(self, BodyCrcCheck::Mismatched { .. })
+    }
+}
+
+/// Attaches the CRC32 of the uncompressed body so every later hop can verify it.
+pub fn attach_body_crc<T: MessageTrait>(msg: &mut T) {
+    let crc = msg.get_body().map_or(0, |body| crc32(body.as_ref()));
+    msg.put_property(
+        CheetahString::from_static_str(MessageConst::PROPERTY_BODY_CRC32),
+        CheetahString::from_string(crc.to_string()),
+    );
+}
+
+/// Verifies `body` against the producer CRC found in `properties`. Compressed bodies are
+/// decompressed first, since the producer computes the CRC before compression.
+pub fn verify_body_crc(
+    body: &[u8],
+    sys_flag: i32,
+    properties: &HashMap<CheetahString, CheetahString>,
+) -> BodyCrcCheck {
+    let Some(expected) = properties
+        .get(MessageConst::PROPERTY_BODY_CRC32)
+        .and_then(|value| value.parse::<u32>().ok())
+    else {
+        return BodyCrcCheck::Absent;
+    };
+    let actual = if MessageSysFlag::check(sys_flag, MessageSysFlag::COMPRESSED_FLAG) {
+        let compressor =
+            CompressorFactory::get_compressor(MessageSysFlag::get_compression_type(sys_flag));
+        match compressor.decompress(body) {
+            Ok(decompressed) => crc32(decompressed.as_ref()),
+            Err(_) => {
+                return BodyCrcCheck::Mismatched {
+                    expected,
+                    actual: 0,
+                }
+            }
+        }
+    } else {
+        crc32(body)
+    };
+    if actual == expected {
+        BodyCrcCheck::Matched
+    } else {
+        BodyCrcCheck::Mismatched { expected, actual }
+    }
+}
+
+/// Per-hop counters of body CRC verification.
+#[derive(Default)]
+pub struct MessageIntegrityStats {
+    verified: AtomicU64,
+    mismatched: AtomicU64,
+}
+
+impl MessageIntegrityStats {
+    pub fn record(&self, check: BodyCrcCheck) {
+        match check {
+            BodyCrcCheck::Absent => {}
+            BodyCrcCheck::Matched => {
+                self.verified.fetch_add(1, Ordering::Relaxed);
+            }
+            BodyCrcCheck::Mismatched { .. } => {
+                self.verified.fetch_add(1, Ordering::Relaxed);
+                self.mismatched.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    pub fn verified(&self) -> u64 {
+        self.verified.load(Ordering::Relaxed)
+    }
+
+    pub fn mismatched(&self) -> u64 {
+        self.mismatched.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use super::*;
+    use crate::common::compression::compression_type::CompressionType;
+    use crate::common::message::message_single::Message;
+
+    #[test]
+    fn attached_crc_verifies_plain_and_compressed_bodies() {
+        let mut msg = Message::default();
+        msg.set_body(Bytes::from_static(b"integrity check body"));
+        attach_body_crc(&mut msg);
+        let body = msg.get_body().unwrap().clone();
+        let properties = msg.get_properties().clone();
+        assert_eq!(
+            verify_body_crc(&body, 0, &properties),
+            BodyCrcCheck::Matched
+        );
+
+        let compressed = CompressorFactory::get_compressor(CompressionType::Zlib)
+            .compress(&body, 5)
+            .unwrap();
+        let sys_flag =
+            MessageSysFlag::COMPRESSED_FLAG | CompressionType::Zlib.get_compression_flag();
+        assert_eq!(
+            verify_body_crc(&compressed, sys_flag, &properties),
+            BodyCrcCheck::Matched
+        );
+
+        let stats = MessageIntegrityStats::default();
+        let check = verify_body_crc(b"tampered", 0, &properties);
+        assert!(check.is_mismatched());
+        stats.record(check);
+        stats.record(verify_body_crc(&body, 0, &HashMap::new()));
+        assert_eq!(stats.verified(), 1);
+        assert_eq!(stats.mismatched(), 1);
+    }
+}
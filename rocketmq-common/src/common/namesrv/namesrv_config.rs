@@ -26,6 +26,7 @@ use crate::common::mix_all::ROCKETMQ_HOME_ENV;
 use crate::common::mix_all::ROCKETMQ_HOME_PROPERTY;
 
 #[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
 pub struct NamesrvConfig {
     #[serde(alias = "rocketmqHome")]
     pub rocketmq_home: String,
@@ -92,6 +93,37 @@ pub struct NamesrvConfig {
 
     #[serde(alias = "configBlackList")]
     pub config_black_list: String,
+
+    #[serde(alias = "enableRequestRateLimit")]
+    pub enable_request_rate_limit: bool,
+
+    #[serde(alias = "routeQueryPermitsPerSecond")]
+    pub route_query_permits_per_second: u32,
+
+    #[serde(alias = "registerPermitsPerSecond")]
+    pub register_permits_per_second: u32,
+
+    /// Rejections within one minute after which a source is banned.
+    #[serde(alias = "rateLimitBanThreshold")]
+    pub rate_limit_ban_threshold: u32,
+
+    #[serde(alias = "rateLimitBanMillis")]
+    pub rate_limit_ban_millis: u64,
+
+    /// Compress route bodies for clients that accept it.
+    #[serde(alias = "routeBodyCompressionEnable")]
+    pub route_body_compression_enable: bool,
+
+    #[serde(alias = "routeBodyCompressionThreshold")]
+    pub route_body_compression_threshold: usize,
+
+    /// Port of the HTTP liveness/readiness endpoint, `0` disables it.
+    #[serde(alias = "healthListenPort")]
+    pub health_listen_port: u32,
+
+    /// How long the name server keeps answering after SIGTERM while reporting not ready.
+    #[serde(alias = "shutdownGracePeriodMillis")]
+    pub shutdown_grace_period_millis: u64,
 }
 
 impl Default for NamesrvConfig {
@@ -139,6 +171,15 @@ impl Default for NamesrvConfig {
             wait_seconds_for_service: 45,
             delete_topic_with_broker_registration: false,
             config_black_list: "configBlackList;configStorePath;kvConfigPath".to_string(),
+            enable_request_rate_limit: false,
+            route_query_permits_per_second: 200,
+            register_permits_per_second: 20,
+            rate_limit_ban_threshold: 1000,
+            rate_limit_ban_millis: 60 * 1000,
+            route_body_compression_enable: true,
+            route_body_compression_threshold: 4 * 1024,
+            health_listen_port: 0,
+            shutdown_grace_period_millis: 10_000,
         }
     }
 }
@@ -236,6 +277,42 @@ impl NamesrvConfig {
             "configBlackList".to_string(),
             Value::String(self.config_black_list.clone()),
         );
+        json_map.insert(
+            "enableRequestRateLimit".to_string(),
+            Value::Bool(self.enable_request_rate_limit),
+        );
+        json_map.insert(
+            "routeQueryPermitsPerSecond".to_string(),
+            Value::Number(self.route_query_permits_per_second.into()),
+        );
+        json_map.insert(
+            "registerPermitsPerSecond".to_string(),
+            Value::Number(self.register_permits_per_second.into()),
+        );
+        json_map.insert(
+            "rateLimitBanThreshold".to_string(),
+            Value::Number(self.rate_limit_ban_threshold.into()),
+        );
+        json_map.insert(
+            "rateLimitBanMillis".to_string(),
+            Value::Number(self.rate_limit_ban_millis.into()),
+        );
+        json_map.insert(
+            "routeBodyCompressionEnable".to_string(),
+            Value::Bool(self.route_body_compression_enable),
+        );
+        json_map.insert(
+            "routeBodyCompressionThreshold".to_string(),
+            Value::Number(self.route_body_compression_threshold.into()),
+        );
+        json_map.insert(
+            "healthListenPort".to_string(),
+            Value::Number(self.health_listen_port.into()),
+        );
+        json_map.insert(
+            "shutdownGracePeriodMillis".to_string(),
+            Value::Number(self.shutdown_grace_period_millis.into()),
+        );
 
         // Convert the HashMap to a JSON value
         match serde_json::to_string_pretty(&json_map) {
@@ -347,6 +424,51 @@ impl NamesrvConfig {
                         .parse()
                         .map_err(|_| format!("Invalid string value for key '{}'", key))?
                 }
+                "enableRequestRateLimit" => {
+                    self.enable_request_rate_limit = value
+                        .parse()
+                        .map_err(|_| format!("Invalid boolean value for key '{}'", key))?
+                }
+                "routeQueryPermitsPerSecond" => {
+                    self.route_query_permits_per_second = value
+                        .parse()
+                        .map_err(|_| format!("Invalid integer value for key '{}'", key))?
+                }
+                "registerPermitsPerSecond" => {
+                    self.register_permits_per_second = value
+                        .parse()
+                        .map_err(|_| format!("Invalid integer value for key '{}'", key))?
+                }
+                "rateLimitBanThreshold" => {
+                    self.rate_limit_ban_threshold = value
+                        .parse()
+                        .map_err(|_| format!("Invalid integer value for key '{}'", key))?
+                }
+                "rateLimitBanMillis" => {
+                    self.rate_limit_ban_millis = value
+                        .parse()
+                        .map_err(|_| format!("Invalid integer value for key '{}'", key))?
+                }
+                "routeBodyCompressionEnable" => {
+                    self.route_body_compression_enable = value
+                        .parse()
+                        .map_err(|_| format!("Invalid boolean value for key '{}'", key))?
+                }
+                "routeBodyCompressionThreshold" => {
+                    self.route_body_compression_threshold = value
+                        .parse()
+                        .map_err(|_| format!("Invalid integer value for key '{}'", key))?
+                }
+                "healthListenPort" => {
+                    self.health_listen_port = value
+                        .parse()
+                        .map_err(|_| format!("Invalid integer value for key '{}'", key))?
+                }
+                "shutdownGracePeriodMillis" => {
+                    self.shutdown_grace_period_millis = value
+                        .parse()
+                        .map_err(|_| format!("Invalid integer value for key '{}'", key))?
+                }
                 _ => {
                     return Err(format!("Unknown configuration key: '{}'", key));
                 }
@@ -0,0 +1,260 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::fmt;
+use std::sync::atomic::AtomicI64;
+use std::sync::atomic::AtomicU8;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use tokio::io::AsyncReadExt;
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpListener;
+use tokio::net::TcpStream;
+use tokio::task::JoinHandle;
+use tracing::info;
+use tracing::warn;
+
+use crate::TimeUtils::get_current_millis;
+
+/// Lifecycle of a broker or name server process as seen by an orchestrator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[repr(u8)]
+pub enum LifecycleState {
+    Starting = 0,
+    /// Loading metadata and recovering the store, not accepting traffic yet.
+    Recovering = 1,
+    Serving = 2,
+    /// Shutdown was requested, in-flight work is finishing within the grace period.
+    Draining = 3,
+    Stopped = 4,
+}
+
+impl LifecycleState {
+    fn from_u8(value: u8) -> Self {
+        match value {
+            0 => LifecycleState::Starting,
+            1 => LifecycleState::Recovering,
+            2 => LifecycleState::Serving,
+            3 => LifecycleState::Draining,
+            _ => LifecycleState::Stopped,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LifecycleState::Starting => "STARTING",
+            LifecycleState::Recovering => "RECOVERING",
+            LifecycleState::Serving => "SERVING",
+            LifecycleState::Draining => "DRAINING",
+            LifecycleState::Stopped => "STOPPED",
+        }
+    }
+}
+
+impl fmt::Display for LifecycleState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Tracks the [`LifecycleState`] of the process, states only ever move forward.
+pub struct Lifecycle {
+    state: AtomicU8,
+    since_timestamp: AtomicI64,
+}
+
+impl Default for Lifecycle {
+    fn default() -> Self {
+        Self {
+            state: AtomicU8::new(LifecycleState::Starting as u8),
+            since_timestamp: AtomicI64::new(get_current_millis() as i64),
+        }
+    }
+}
+
+impl Lifecycle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn state(&self) -> LifecycleState {
+        LifecycleState::from_u8(self.state.load(Ordering::Acquire))
+    }
+
+    /// Millis timestamp of the last transition.
+    pub fn since_timestamp(&self) -> i64 {
+        self.since_timestamp.load(Ordering::Acquire)
+    }
+
+    /// Moves to `state`, returns `false` when the process already is in it or past it.
+    pub fn transition(&self, state: LifecycleState) -> bool {
+        let previous = self.state.fetch_max(state as u8, Ordering::AcqRel);
+        if previous >= state as u8 {
+            return false;
+        }
+        self.since_timestamp
+            .store(get_current_millis() as i64, Ordering::Release);
+        info!(
+            "lifecycle state changed: {} -> {}",
+            LifecycleState::from_u8(previous),
+            state
+        );
+        true
+    }
+
+    /// Liveness: the process must only be restarted once it stopped.
+    pub fn is_live(&self) -> bool {
+        self.state() != LifecycleState::Stopped
+    }
+
+    /// Readiness: only a serving process should receive traffic.
+    pub fn is_ready(&self) -> bool {
+        self.state() == LifecycleState::Serving
+    }
+}
+
+/// Serves the lifecycle over HTTP on `0.0.0.0:port`:
+///
+/// * `GET /health/live`: `200` unless stopped, for liveness probes.
+/// * `GET /health/ready`: `200` only while serving, for readiness probes.
+/// * `GET /health`: the current state as JSON.
+///
+/// Other probes answer `503` when the check fails and `404` for unknown paths.
+pub async fn start_health_server(
+    port: u32,
+    lifecycle: Arc<Lifecycle>,
+) -> std::io::Result<JoinHandle<()>> {
+    let listener = TcpListener::bind(format!("0.0.0.0:{port}")).await?;
+    info!("health endpoint listening on {}", listener.local_addr()?);
+    Ok(tokio::spawn(serve_health(listener, lifecycle)))
+}
+
+async fn serve_health(listener: TcpListener, lifecycle: Arc<Lifecycle>) {
+    loop {
+        match listener.accept().await {
+            Ok((stream, _)) => {
+                let lifecycle = lifecycle.clone();
+                tokio::spawn(async move {
+                    if let Err(err) = handle_probe(stream, &lifecycle).await {
+                        warn!("health probe failed: {}", err);
+                    }
+                });
+            }
+            Err(err) => warn!("health endpoint accept failed: {}", err),
+        }
+    }
+}
+
+async fn handle_probe(mut stream: TcpStream, lifecycle: &Lifecycle) -> std::io::Result<()> {
+    let mut buffer = [0u8; 1024];
+    let read = stream.read(&mut buffer).await?;
+    let request = String::from_utf8_lossy(&buffer[..read]);
+    let path = request
+        .lines()
+        .next()
+        .and_then(|line| line.split_whitespace().nth(1))
+        .unwrap_or("/");
+    let state = lifecycle.state();
+    let (status, body) = match path {
+        "/health/live" => (
+            probe_status(lifecycle.is_live()),
+            state.as_str().to_string(),
+        ),
+        "/health/ready" => (
+            probe_status(lifecycle.is_ready()),
+            state.as_str().to_string(),
+        ),
+        "/health" => (
+            "200 OK",
+            format!(
+                "{{\"state\":\"{}\",\"sinceTimestamp\":{}}}",
+                state,
+                lifecycle.since_timestamp()
+            ),
+        ),
+        _ => ("404 Not Found", String::new()),
+    };
+    let response = format!(
+        "HTTP/1.1 {status}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+fn probe_status(ok: bool) -> &'static str {
+    if ok {
+        "200 OK"
+    } else {
+        "503 Service Unavailable"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn probe(port: u16, path: &str) -> String {
+        let mut stream = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+        stream
+            .write_all(format!("GET {path} HTTP/1.1\r\nHost: localhost\r\n\r\n").as_bytes())
+            .await
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    #[test]
+    fn transitions_only_move_forward() {
+        let lifecycle = Lifecycle::new();
+        assert_eq!(lifecycle.state(), LifecycleState::Starting);
+        assert!(lifecycle.is_live());
+        assert!(!lifecycle.is_ready());
+
+        assert!(lifecycle.transition(LifecycleState::Serving));
+        assert!(lifecycle.is_ready());
+        assert!(!lifecycle.transition(LifecycleState::Recovering));
+        assert_eq!(lifecycle.state(), LifecycleState::Serving);
+
+        assert!(lifecycle.transition(LifecycleState::Stopped));
+        assert!(!lifecycle.is_live());
+    }
+
+    #[tokio::test]
+    async fn serves_probes() {
+        let lifecycle = Arc::new(Lifecycle::new());
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(serve_health(listener, lifecycle.clone()));
+
+        assert!(probe(port, "/health/live")
+            .await
+            .starts_with("HTTP/1.1 200"));
+        assert!(probe(port, "/health/ready")
+            .await
+            .starts_with("HTTP/1.1 503"));
+        lifecycle.transition(LifecycleState::Serving);
+        assert!(probe(port, "/health/ready")
+            .await
+            .starts_with("HTTP/1.1 200"));
+        assert!(probe(port, "/health")
+            .await
+            .contains("{\"state\":\"SERVING\""));
+        assert!(probe(port, "/unknown").await.starts_with("HTTP/1.1 404"));
+    }
+}
@@ -22,6 +22,8 @@ impl PullSysFlag {
     const FLAG_SUBSCRIPTION: u32 = 0x1 << 2;
     const FLAG_CLASS_FILTER: u32 = 0x1 << 3;
     const FLAG_LITE_PULL_MESSAGE: u32 = 0x1 << 4;
+    /// Set by clients that can decompress a pull response body the broker compressed.
+    const FLAG_ACCEPT_COMPRESSED_BODY: u32 = 0x1 << 5;
 
     pub fn build_sys_flag(
         commit_offset: bool,
@@ -97,6 +99,14 @@ impl PullSysFlag {
     pub fn has_lite_pull_flag(sys_flag: u32) -> bool {
         (sys_flag & Self::FLAG_LITE_PULL_MESSAGE) == Self::FLAG_LITE_PULL_MESSAGE
     }
+
+    pub fn build_sys_flag_with_accept_compressed_body(sys_flag: u32) -> u32 {
+        sys_flag | Self::FLAG_ACCEPT_COMPRESSED_BODY
+    }
+
+    pub fn has_accept_compressed_body_flag(sys_flag: u32) -> bool {
+        (sys_flag & Self::FLAG_ACCEPT_COMPRESSED_BODY) == Self::FLAG_ACCEPT_COMPRESSED_BODY
+    }
 }
 
 #[cfg(test)]
@@ -162,4 +172,12 @@ mod tests {
         assert!(PullSysFlag::has_lite_pull_flag(0b10000));
         assert!(!PullSysFlag::has_lite_pull_flag(0b1));
     }
+
+    #[test]
+    fn accept_compressed_body_flag_round_trips() {
+        let flag = PullSysFlag::build_sys_flag_with_accept_compressed_body(0b11);
+        assert_eq!(flag, 0b100011);
+        assert!(PullSysFlag::has_accept_compressed_body_flag(flag));
+        assert!(!PullSysFlag::has_accept_compressed_body_flag(0b11111));
+    }
 }
@@ -20,6 +20,7 @@ pub mod crc32_utils;
 pub mod env_utils;
 pub mod file_utils;
 pub mod http_tiny_client;
+pub mod index_property_utils;
 pub mod message_utils;
 pub mod name_server_address_utils;
 pub mod network_util;
@@ -50,18 +50,25 @@ pub fn file_to_string_impl(file: &File) -> Result<String, io::Error> {
     }
 }
 
+/// Atomically replaces `file_name` with `str_content`.
+///
+/// The content is written and synced to `file_name.tmp` first, the previous file is kept
+/// as `file_name.bak`, and the tmp file is then renamed over the original, so a crash
+/// leaves either the old or the new content in place, never a truncated file.
 pub fn string_to_file(str_content: &str, file_name: &str) -> io::Result<()> {
     let lock = LOCK.lock();
 
-    let bak_file = format!("{}.bak", file_name);
+    let tmp_file = format!("{}.tmp", file_name);
+    string_to_file_not_safe(str_content, &tmp_file)?;
 
-    // Read previous content and create a backup
-    if let Ok(prev_content) = file_to_string(file_name) {
-        string_to_file_not_safe(&prev_content, &bak_file)?;
+    // Keep the previous content as a backup
+    let bak_file = format!("{}.bak", file_name);
+    if Path::new(file_name).exists() {
+        std::fs::copy(file_name, &bak_file)?;
     }
 
-    // Write new content to the file
-    string_to_file_not_safe(str_content, file_name)?;
+    std::fs::rename(&tmp_file, file_name)?;
+    sync_parent_dir(file_name);
     drop(lock);
     Ok(())
 }
@@ -73,17 +80,29 @@ fn string_to_file_not_safe(str_content: &str, file_name: &str) -> io::Result<()>
     }
     let file = File::create(file_name)?;
 
-    write_string_to_file(&file, str_content, "UTF-8")
+    write_string_to_file(&file, str_content, "UTF-8")?;
+    file.sync_all()
 }
 
 fn write_string_to_file(file: &File, data: &str, _encoding: &str) -> io::Result<()> {
     let mut os = io::BufWriter::new(file);
 
     os.write_all(data.as_bytes())?;
+    os.flush()?;
 
     Ok(())
 }
 
+/// Makes the rename durable; directories cannot be opened for sync on every platform, so
+/// failures are ignored.
+fn sync_parent_dir(file_name: &str) {
+    if let Some(parent) = Path::new(file_name).parent() {
+        if let Ok(dir) = File::open(parent) {
+            let _ = dir.sync_all();
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -120,4 +139,21 @@ mod tests {
         assert!(result.is_ok());
         assert_eq!(std::fs::read_to_string(file_path).unwrap(), content);
     }
+
+    #[test]
+    fn string_to_file_keeps_backup_and_removes_tmp() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let file_path = temp_dir.path().join("config.json");
+        let file_name = file_path.to_str().unwrap();
+
+        string_to_file("v1", file_name).unwrap();
+        string_to_file("v2", file_name).unwrap();
+
+        assert_eq!(std::fs::read_to_string(file_name).unwrap(), "v2");
+        assert_eq!(
+            std::fs::read_to_string(format!("{}.bak", file_name)).unwrap(),
+            "v1"
+        );
+        assert!(!Path::new(&format!("{}.tmp", file_name)).exists());
+    }
 }
@@ -0,0 +1,77 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use crate::common::attribute::attribute_list::ListAttribute;
+use crate::common::config::TopicConfig;
+use crate::TopicAttributes;
+
+/// Separator between the property name and its value in a qualified index key.
+pub const PROPERTY_KEY_SEPARATOR: char = '=';
+
+pub struct IndexPropertyUtils;
+
+impl IndexPropertyUtils {
+    /// Returns the property names the topic declared through `index.property.keys`.
+    pub fn get_index_property_keys(topic_config: &Option<TopicConfig>) -> Vec<String> {
+        let attribute_name = TopicAttributes::INDEX_PROPERTY_KEYS_ATTRIBUTE.get_name();
+        topic_config
+            .as_ref()
+            .and_then(|config| config.attributes.get(attribute_name))
+            .map(|value| ListAttribute::parse(value.as_str()))
+            .unwrap_or_default()
+    }
+
+    /// Builds the key under which a property value is indexed, e.g. `orderId=1001`. Query
+    /// requests pass the same form as their key to look messages up by that property.
+    pub fn build_property_key(name: &str, value: &str) -> String {
+        format!("{}{}{}", name, PROPERTY_KEY_SEPARATOR, value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    #[test]
+    fn get_index_property_keys_reads_topic_attribute() {
+        assert!(IndexPropertyUtils::get_index_property_keys(&None).is_empty());
+
+        let topic_config = Some(TopicConfig {
+            attributes: HashMap::from_iter([(
+                TopicAttributes::INDEX_PROPERTY_KEYS_ATTRIBUTE
+                    .get_name()
+                    .to_string()
+                    .into(),
+                "orderId, userId".to_string().into(),
+            )]),
+            ..TopicConfig::default()
+        });
+        assert_eq!(
+            IndexPropertyUtils::get_index_property_keys(&topic_config),
+            vec!["orderId".to_string(), "userId".to_string()]
+        );
+    }
+
+    #[test]
+    fn build_property_key_joins_name_and_value() {
+        assert_eq!(
+            IndexPropertyUtils::build_property_key("orderId", "1001"),
+            "orderId=1001"
+        );
+    }
+}
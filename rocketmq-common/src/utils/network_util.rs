@@ -15,6 +15,7 @@
  * limitations under the License.
  */
 use std::net::IpAddr;
+use std::net::SocketAddr;
 
 pub struct NetworkUtil;
 
@@ -34,4 +35,75 @@ impl NetworkUtil {
             },
         }
     }
+
+    /// Formats `ip:port`, wrapping IPv6 literals in brackets so the result can be
+    /// parsed back unambiguously.
+    pub fn format_address(ip: &str, port: impl std::fmt::Display) -> String {
+        let ip = ip.trim();
+        if ip.contains(':') && !ip.starts_with('[') {
+            format!("[{}]:{}", ip, port)
+        } else {
+            format!("{}:{}", ip, port)
+        }
+    }
+
+    /// Parses a socket address in any of the forms used on the wire: `1.2.3.4:port`,
+    /// `[::1]:port`, the unbracketed Java form `::1:port` and a leading `/` as printed
+    /// by `InetSocketAddress`.
+    pub fn parse_socket_addr(addr: &str) -> Option<SocketAddr> {
+        let addr = addr.trim();
+        let addr = addr.rsplit_once('/').map_or(addr, |(_, tail)| tail);
+        if let Ok(socket_addr) = addr.parse::<SocketAddr>() {
+            return Some(socket_addr);
+        }
+        let (host, port) = addr.rsplit_once(':')?;
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        let ip = host.parse::<IpAddr>().ok()?;
+        let port = port.parse::<u16>().ok()?;
+        Some(SocketAddr::new(ip, port))
+    }
+
+    /// Length of a socket address in the store and message id encoding: 4 or 16
+    /// bytes of address followed by a 4 byte port.
+    pub fn socket_addr_length(addr: &SocketAddr) -> usize {
+        match addr {
+            SocketAddr::V4(_) => 8,
+            SocketAddr::V6(_) => 20,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_address_brackets_ipv6() {
+        assert_eq!(
+            NetworkUtil::format_address("127.0.0.1", 10911),
+            "127.0.0.1:10911"
+        );
+        assert_eq!(
+            NetworkUtil::format_address("fe80::1", 10911),
+            "[fe80::1]:10911"
+        );
+        assert_eq!(
+            NetworkUtil::format_address("[fe80::1]", 10911),
+            "[fe80::1]:10911"
+        );
+    }
+
+    #[test]
+    fn parse_socket_addr_accepts_all_forms() {
+        let v4: SocketAddr = "10.0.0.1:9876".parse().unwrap();
+        let v6: SocketAddr = "[fe80::1]:9876".parse().unwrap();
+        assert_eq!(NetworkUtil::parse_socket_addr("10.0.0.1:9876"), Some(v4));
+        assert_eq!(NetworkUtil::parse_socket_addr("/10.0.0.1:9876"), Some(v4));
+        assert_eq!(NetworkUtil::parse_socket_addr("[fe80::1]:9876"), Some(v6));
+        assert_eq!(NetworkUtil::parse_socket_addr("fe80::1:9876"), Some(v6));
+        assert_eq!(NetworkUtil::parse_socket_addr("/fe80::1:9876"), Some(v6));
+        assert_eq!(NetworkUtil::parse_socket_addr("localhost"), None);
+        assert_eq!(NetworkUtil::socket_addr_length(&v4), 8);
+        assert_eq!(NetworkUtil::socket_addr_length(&v6), 20);
+    }
 }
@@ -72,6 +72,12 @@ pub fn is_it_time_to_do(when: &str) -> bool {
     false
 }
 
+/// Milliseconds elapsed since midnight in the local time zone.
+pub fn local_millis_of_day() -> u64 {
+    let now = Local::now();
+    now.num_seconds_from_midnight() as u64 * 1000 + now.timestamp_subsec_millis() as u64
+}
+
 pub fn time_millis_to_human_string2(t: i64) -> String {
     let dt = Utc.timestamp_millis_opt(t).unwrap();
     format!(
@@ -88,4 +88,29 @@ impl BloomFilter {
             None => false,
         }
     }
+
+    /// Whether every bit of `filter_data` is set in `bit_map`, the bitmap calculated for a
+    /// message at dispatch. A miss means the message surely does not match.
+    pub fn is_hit(&self, filter_data: &BloomFilterData, bit_map: &[u8]) -> bool {
+        filter_data.bit_pos().iter().all(|&pos| {
+            let pos = pos as usize;
+            bit_map
+                .get(pos / 8)
+                .is_some_and(|byte| byte & (1 << (pos % 8)) != 0)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn is_hit_requires_every_bit() {
+        let bloom_filter = BloomFilter::default();
+        let filter_data = BloomFilterData::new(vec![1, 9], 16);
+        assert!(bloom_filter.is_hit(&filter_data, &[0b10, 0b10]));
+        assert!(!bloom_filter.is_hit(&filter_data, &[0b10, 0]));
+        assert!(!bloom_filter.is_hit(&filter_data, &[0b10]));
+    }
 }
@@ -14,6 +14,7 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
+use std::collections::HashMap;
 use std::net::SocketAddr;
 use std::sync::Arc;
 use std::time::Duration;
@@ -21,6 +22,9 @@ use std::time::Duration;
 use cheetah_string::CheetahString;
 use rocketmq_common::common::namesrv::namesrv_config::NamesrvConfig;
 use rocketmq_common::common::server::config::ServerConfig;
+use rocketmq_common::common::server::lifecycle::start_health_server;
+use rocketmq_common::common::server::lifecycle::Lifecycle;
+use rocketmq_common::common::server::lifecycle::LifecycleState;
 use rocketmq_common::utils::network_util::NetworkUtil;
 use rocketmq_remoting::clients::rocketmq_default_impl::RocketmqDefaultClient;
 use rocketmq_remoting::clients::RemotingClient;
@@ -32,8 +36,10 @@ use rocketmq_runtime::RocketMQRuntime;
 use rocketmq_rust::wait_for_signal;
 use rocketmq_rust::ArcMut;
 use tokio::sync::broadcast;
+use tracing::error;
 use tracing::info;
 
+use crate::processor::request_rate_limiter::RequestRateLimiter;
 use crate::processor::ClientRequestProcessor;
 use crate::processor::NameServerRequestProcessor;
 use crate::KVConfigManager;
@@ -60,12 +66,33 @@ struct NameServerRuntime {
 
 impl NameServerBootstrap {
     pub async fn boot(mut self) {
-        /*select! {
-            _ = self.name_server_runtime.start() =>{
-
+        let lifecycle = Arc::new(Lifecycle::new());
+        let health_listen_port = self
+            .name_server_runtime
+            .name_server_config
+            .health_listen_port;
+        if health_listen_port > 0 {
+            if let Err(err) = start_health_server(health_listen_port, lifecycle.clone()).await {
+                error!(
+                    "start health endpoint on port {} failed: {}",
+                    health_listen_port, err
+                );
             }
-        }*/
-        tokio::join!(self.name_server_runtime.start(), wait_for_signal());
+        }
+
+        self.name_server_runtime.start().await;
+        lifecycle.transition(LifecycleState::Serving);
+
+        wait_for_signal().await;
+        // keep answering while load balancers observe the failing readiness probe
+        lifecycle.transition(LifecycleState::Draining);
+        let grace_period = self
+            .name_server_runtime
+            .name_server_config
+            .shutdown_grace_period_millis;
+        info!("name server draining, shutting down in {}ms", grace_period);
+        tokio::time::sleep(Duration::from_millis(grace_period)).await;
+        lifecycle.transition(LifecycleState::Stopped);
     }
 }
 
@@ -119,9 +146,27 @@ impl NameServerRuntime {
                 Some(Duration::from_secs(5)),
                 Duration::from_secs(5),
             );
+
+        let rate_limiter = Arc::new(RequestRateLimiter::new());
+        let rate_limiter_inner = rate_limiter.clone();
+        self.name_server_runtime
+            .as_ref()
+            .unwrap()
+            .schedule_at_fixed_rate(
+                move || {
+                    let mut stats = HashMap::new();
+                    rate_limiter_inner.build_running_stats(&mut stats);
+                    info!("request rate limit stats: {:?}", stats);
+                    rate_limiter_inner.clean_idle_sources();
+                },
+                Some(Duration::from_secs(60)),
+                Duration::from_secs(60),
+            );
         NameServerRequestProcessor {
             client_request_processor: ArcMut::new(client_request_processor),
             default_request_processor: ArcMut::new(default_request_processor),
+            name_server_config: self.name_server_config.clone(),
+            rate_limiter,
         }
     }
 }
@@ -15,6 +15,9 @@
  * limitations under the License.
  */
 
+use std::sync::Arc;
+
+use rocketmq_common::common::namesrv::namesrv_config::NamesrvConfig;
 use rocketmq_remoting::code::request_code::RequestCode;
 use rocketmq_remoting::net::channel::Channel;
 use rocketmq_remoting::protocol::remoting_command::RemotingCommand;
@@ -26,9 +29,11 @@ use tracing::info;
 
 pub use self::client_request_processor::ClientRequestProcessor;
 use crate::processor::default_request_processor::DefaultRequestProcessor;
+use crate::processor::request_rate_limiter::RequestRateLimiter;
 
 mod client_request_processor;
 pub mod default_request_processor;
+pub(crate) mod request_rate_limiter;
 
 const NAMESPACE_ORDER_TOPIC_CONFIG: &str = "ORDER_TOPIC_CONFIG";
 
@@ -36,6 +41,8 @@ const NAMESPACE_ORDER_TOPIC_CONFIG: &str = "ORDER_TOPIC_CONFIG";
 pub struct NameServerRequestProcessor {
     pub(crate) client_request_processor: ArcMut<ClientRequestProcessor>,
     pub(crate) default_request_processor: ArcMut<DefaultRequestProcessor>,
+    pub(crate) name_server_config: ArcMut<NamesrvConfig>,
+    pub(crate) rate_limiter: Arc<RequestRateLimiter>,
 }
 
 impl RequestProcessor for NameServerRequestProcessor {
@@ -47,6 +54,14 @@ impl RequestProcessor for NameServerRequestProcessor {
     ) -> Result<Option<RemotingCommand>> {
         let request_code = RequestCode::from(request.code());
         info!("Name server Received request code: {:?}", request_code);
+        if let Some(response) = self.rate_limiter.check(
+            &self.name_server_config,
+            request_code,
+            &request,
+            channel.remote_address().ip(),
+        ) {
+            return Ok(Some(response));
+        }
         let result = match request_code {
             RequestCode::GetRouteinfoByTopic => {
                 self.client_request_processor
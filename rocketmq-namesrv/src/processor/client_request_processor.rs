@@ -20,14 +20,18 @@ use std::sync::atomic::Ordering;
 use std::time::Duration;
 
 use cheetah_string::CheetahString;
+use rocketmq_common::common::compression::compression_type::CompressionType;
+use rocketmq_common::common::compression::compressor_factory::CompressorFactory;
 use rocketmq_common::common::namesrv::namesrv_config::NamesrvConfig;
 use rocketmq_common::common::FAQUrl;
+use rocketmq_common::utils::crc32_utils::crc32;
 use rocketmq_common::TimeUtils;
 use rocketmq_remoting::code::request_code::RequestCode;
 use rocketmq_remoting::code::response_code::RemotingSysResponseCode;
 use rocketmq_remoting::code::response_code::ResponseCode;
 use rocketmq_remoting::net::channel::Channel;
 use rocketmq_remoting::protocol::header::client_request_header::GetRouteInfoRequestHeader;
+use rocketmq_remoting::protocol::header::client_request_header::GetRouteInfoResponseHeader;
 use rocketmq_remoting::protocol::remoting_command::RemotingCommand;
 use rocketmq_remoting::protocol::RemotingSerializable;
 use rocketmq_remoting::runtime::connection_handler_context::ConnectionHandlerContext;
@@ -40,6 +44,8 @@ use crate::namesrv_error::NamesrvRemotingErrorWithMessage;
 use crate::processor::NAMESPACE_ORDER_TOPIC_CONFIG;
 use crate::route::route_info_manager::RouteInfoManager;
 
+const ROUTE_BODY_COMPRESSION_LEVEL: i32 = 3;
+
 pub struct ClientRequestProcessor {
     route_info_manager: RouteInfoManager,
     namesrv_config: ArcMut<NamesrvConfig>,
@@ -128,13 +134,57 @@ impl ClientRequestProcessor {
                 let content = topic_route_data
                     .encode()
                     .map_err(|_| MQNamesrvError("encode TopicRouteData failed".to_string()))?;
-                Ok(Some(
-                    RemotingCommand::create_response_command_with_code(ResponseCode::Success)
-                        .set_body(content),
-                ))
+                Ok(Some(route_response(
+                    content,
+                    &request_header,
+                    &self.namesrv_config,
+                )))
+            }
+        }
+    }
+}
+
+/// Answers `NotModified` when the client already has this version of the route, otherwise the
+/// route body, compressed if the client accepts it and it is large enough.
+fn route_response(
+    content: Vec<u8>,
+    request_header: &GetRouteInfoRequestHeader,
+    namesrv_config: &NamesrvConfig,
+) -> RemotingCommand {
+    let mut response_header = GetRouteInfoResponseHeader {
+        data_version: crc32(&content) as i64,
+        body_compression_type: None,
+    };
+    if request_header.data_version == Some(response_header.data_version) {
+        return RemotingCommand::create_response_command_with_code(ResponseCode::NotModified)
+            .set_command_custom_header(response_header);
+    }
+    if request_header.accept_compressed.unwrap_or(false)
+        && namesrv_config.route_body_compression_enable
+        && content.len() >= namesrv_config.route_body_compression_threshold
+    {
+        let compression_type = CompressionType::Zstd;
+        match CompressorFactory::get_compressor(compression_type)
+            .compress(&content, ROUTE_BODY_COMPRESSION_LEVEL)
+        {
+            Ok(compressed) if compressed.len() < content.len() => {
+                response_header.body_compression_type =
+                    Some(compression_type.get_compression_flag());
+                return RemotingCommand::create_response_command_with_code(ResponseCode::Success)
+                    .set_command_custom_header(response_header)
+                    .set_body(compressed);
             }
+            Ok(_) => {}
+            Err(e) => warn!(
+                "compress route body of {} bytes failed: {}",
+                content.len(),
+                e
+            ),
         }
     }
+    RemotingCommand::create_response_command_with_code(ResponseCode::Success)
+        .set_command_custom_header(response_header)
+        .set_body(content)
 }
 
 impl ClientRequestProcessor {
@@ -148,3 +198,44 @@ impl ClientRequestProcessor {
         self.get_route_info_by_topic(request)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn route_response_compresses_large_bodies_and_skips_unchanged_routes() {
+        let namesrv_config = NamesrvConfig {
+            route_body_compression_threshold: 1024,
+            ..NamesrvConfig::default()
+        };
+        let content = vec![b'a'; 8 * 1024];
+        let mut request_header = GetRouteInfoRequestHeader {
+            topic: CheetahString::from_static_str("TopicTest"),
+            accept_compressed: Some(true),
+            ..Default::default()
+        };
+
+        let response = route_response(content.clone(), &request_header, &namesrv_config);
+        assert_eq!(ResponseCode::from(response.code()), ResponseCode::Success);
+        let header = response
+            .read_custom_header_ref::<GetRouteInfoResponseHeader>()
+            .unwrap()
+            .clone();
+        assert_eq!(header.data_version, crc32(&content) as i64);
+        let flag = header.body_compression_type.unwrap();
+        assert_eq!(flag, CompressionType::Zstd.get_compression_flag());
+        let body = CompressorFactory::get_compressor(CompressionType::Zstd)
+            .decompress(response.body().as_ref().unwrap())
+            .unwrap();
+        assert_eq!(body.as_ref(), content.as_slice());
+
+        request_header.data_version = Some(header.data_version);
+        let response = route_response(content, &request_header, &namesrv_config);
+        assert_eq!(
+            ResponseCode::from(response.code()),
+            ResponseCode::NotModified
+        );
+        assert!(response.body().is_none());
+    }
+}
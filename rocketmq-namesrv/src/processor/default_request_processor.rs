@@ -282,6 +282,7 @@ impl DefaultRequestProcessor {
             topic_config_wrapper,
             filter_server_list,
             remote_addr,
+            request_header.feature_bitmap,
         );
         if result.is_none() {
             return Ok(response_command
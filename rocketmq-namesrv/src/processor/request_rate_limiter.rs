@@ -0,0 +1,265 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+
+use cheetah_string::CheetahString;
+use parking_lot::Mutex;
+use rocketmq_common::common::namesrv::namesrv_config::NamesrvConfig;
+use rocketmq_common::TimeUtils::get_current_millis;
+use rocketmq_remoting::code::request_code::RequestCode;
+use rocketmq_remoting::code::response_code::ResponseCode;
+use rocketmq_remoting::protocol::remoting_command::RemotingCommand;
+use tracing::warn;
+
+const WINDOW_MILLIS: u64 = 1000;
+const BAN_WINDOW_MILLIS: u64 = 60 * 1000;
+const ACCESS_KEY: &str = "AccessKey";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum LimitKind {
+    RouteQuery,
+    Register,
+}
+
+impl LimitKind {
+    fn of(request_code: RequestCode) -> Option<Self> {
+        match request_code {
+            RequestCode::GetRouteinfoByTopic => Some(LimitKind::RouteQuery),
+            RequestCode::RegisterBroker
+            | RequestCode::UnregisterBroker
+            | RequestCode::RegisterTopicInNamesrv => Some(LimitKind::Register),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Source {
+    Address(IpAddr),
+    Account(CheetahString),
+}
+
+#[derive(Default)]
+struct SourceState {
+    window_start: u64,
+    window_count: HashMap<LimitKind, u32>,
+    ban_window_start: u64,
+    rejected_in_ban_window: u32,
+    banned_until: u64,
+}
+
+/// Per source address and per account quota for route queries and broker registrations.
+///
+/// Requests are counted in one second windows; a source exceeding
+/// `rate_limit_ban_threshold` rejections within a minute is banned for
+/// `rate_limit_ban_millis`.
+pub(crate) struct RequestRateLimiter {
+    sources: Mutex<HashMap<Source, SourceState>>,
+    route_query_rejected: AtomicU64,
+    register_rejected: AtomicU64,
+    banned_rejected: AtomicU64,
+    bans: AtomicU64,
+}
+
+impl RequestRateLimiter {
+    pub fn new() -> Self {
+        Self {
+            sources: Mutex::new(HashMap::new()),
+            route_query_rejected: AtomicU64::new(0),
+            register_rejected: AtomicU64::new(0),
+            banned_rejected: AtomicU64::new(0),
+            bans: AtomicU64::new(0),
+        }
+    }
+
+    /// Returns a `SystemBusy` response when the request exceeds its quota.
+    pub fn check(
+        &self,
+        config: &NamesrvConfig,
+        request_code: RequestCode,
+        request: &RemotingCommand,
+        remote_ip: IpAddr,
+    ) -> Option<RemotingCommand> {
+        if !config.enable_request_rate_limit {
+            return None;
+        }
+        let kind = LimitKind::of(request_code)?;
+        let now = get_current_millis();
+        let mut sources = vec![Source::Address(remote_ip)];
+        if let Some(account) = request.ext_fields().and_then(|f| f.get(ACCESS_KEY)) {
+            sources.push(Source::Account(account.clone()));
+        }
+        for source in sources {
+            if let Some(remark) = self.acquire(config, &source, kind, now) {
+                return Some(
+                    RemotingCommand::create_response_command_with_code(ResponseCode::SystemBusy)
+                        .set_remark(remark),
+                );
+            }
+        }
+        None
+    }
+
+    fn acquire(
+        &self,
+        config: &NamesrvConfig,
+        source: &Source,
+        kind: LimitKind,
+        now: u64,
+    ) -> Option<String> {
+        let mut sources = self.sources.lock();
+        let state = sources.entry(source.clone()).or_default();
+        if state.banned_until > now {
+            self.banned_rejected.fetch_add(1, Ordering::Relaxed);
+            return Some(format!(
+                "[RATE_LIMIT] {:?} is banned for another {}ms",
+                source,
+                state.banned_until - now
+            ));
+        }
+        if now.saturating_sub(state.window_start) >= WINDOW_MILLIS {
+            state.window_start = now;
+            state.window_count.clear();
+        }
+        let permits = match kind {
+            LimitKind::RouteQuery => config.route_query_permits_per_second,
+            LimitKind::Register => config.register_permits_per_second,
+        };
+        let count = state.window_count.entry(kind).or_insert(0);
+        *count += 1;
+        if *count <= permits {
+            return None;
+        }
+
+        match kind {
+            LimitKind::RouteQuery => self.route_query_rejected.fetch_add(1, Ordering::Relaxed),
+            LimitKind::Register => self.register_rejected.fetch_add(1, Ordering::Relaxed),
+        };
+        if now.saturating_sub(state.ban_window_start) >= BAN_WINDOW_MILLIS {
+            state.ban_window_start = now;
+            state.rejected_in_ban_window = 0;
+        }
+        state.rejected_in_ban_window += 1;
+        if config.rate_limit_ban_threshold > 0
+            && state.rejected_in_ban_window >= config.rate_limit_ban_threshold
+        {
+            state.banned_until = now + config.rate_limit_ban_millis;
+            state.rejected_in_ban_window = 0;
+            self.bans.fetch_add(1, Ordering::Relaxed);
+            warn!(
+                "{:?} exceeded the {:?} quota too often, banned for {}ms",
+                source, kind, config.rate_limit_ban_millis
+            );
+        }
+        Some(format!(
+            "[RATE_LIMIT] {:?} exceeds {} {:?} requests per second",
+            source, permits, kind
+        ))
+    }
+
+    /// Drops the state of sources that have been idle for longer than the ban window.
+    pub fn clean_idle_sources(&self) {
+        let now = get_current_millis();
+        self.sources.lock().retain(|_, state| {
+            state.banned_until > now || now.saturating_sub(state.window_start) < BAN_WINDOW_MILLIS
+        });
+    }
+
+    pub fn build_running_stats(&self, stats: &mut HashMap<String, String>) {
+        stats.insert(
+            "rateLimitRouteQueryRejected".to_string(),
+            self.route_query_rejected
+                .load(Ordering::Relaxed)
+                .to_string(),
+        );
+        stats.insert(
+            "rateLimitRegisterRejected".to_string(),
+            self.register_rejected.load(Ordering::Relaxed).to_string(),
+        );
+        stats.insert(
+            "rateLimitBannedRejected".to_string(),
+            self.banned_rejected.load(Ordering::Relaxed).to_string(),
+        );
+        stats.insert(
+            "rateLimitBans".to_string(),
+            self.bans.load(Ordering::Relaxed).to_string(),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> NamesrvConfig {
+        NamesrvConfig {
+            enable_request_rate_limit: true,
+            route_query_permits_per_second: 2,
+            register_permits_per_second: 1,
+            rate_limit_ban_threshold: 3,
+            rate_limit_ban_millis: 60 * 1000,
+            ..NamesrvConfig::default()
+        }
+    }
+
+    #[test]
+    fn rejects_requests_over_quota_and_bans_source() {
+        let limiter = RequestRateLimiter::new();
+        let config = config();
+        let request = RemotingCommand::create_remoting_command(RequestCode::GetRouteinfoByTopic);
+        let ip: IpAddr = "10.0.0.1".parse().unwrap();
+        let route = RequestCode::GetRouteinfoByTopic;
+
+        assert!(limiter.check(&config, route, &request, ip).is_none());
+        assert!(limiter.check(&config, route, &request, ip).is_none());
+        for _ in 0..3 {
+            assert!(limiter.check(&config, route, &request, ip).is_some());
+        }
+        // banned now, even for other request kinds
+        assert!(limiter
+            .check(&config, RequestCode::RegisterBroker, &request, ip)
+            .is_some());
+        // other sources and unlimited codes are unaffected
+        let other: IpAddr = "10.0.0.2".parse().unwrap();
+        assert!(limiter.check(&config, route, &request, other).is_none());
+        assert!(limiter
+            .check(&config, RequestCode::GetKvConfig, &request, ip)
+            .is_none());
+
+        let mut stats = HashMap::new();
+        limiter.build_running_stats(&mut stats);
+        assert_eq!(stats["rateLimitRouteQueryRejected"], "3");
+        assert_eq!(stats["rateLimitBans"], "1");
+        assert_eq!(stats["rateLimitBannedRejected"], "1");
+    }
+
+    #[test]
+    fn disabled_limiter_allows_everything() {
+        let limiter = RequestRateLimiter::new();
+        let config = NamesrvConfig::default();
+        let request = RemotingCommand::create_remoting_command(RequestCode::RegisterBroker);
+        let ip: IpAddr = "10.0.0.1".parse().unwrap();
+        for _ in 0..100 {
+            assert!(limiter
+                .check(&config, RequestCode::RegisterBroker, &request, ip)
+                .is_none());
+        }
+    }
+}
@@ -121,6 +121,7 @@ impl RouteInfoManager {
         topic_config_serialize_wrapper: TopicConfigAndMappingSerializeWrapper,
         filter_server_list: Vec<String>,
         remote_addr: SocketAddr,
+        feature_bitmap: Option<u64>,
     ) -> Option<RegisterBrokerResult> {
         let mut result = RegisterBrokerResult::default();
         let _write = self.lock.write();
@@ -220,6 +221,7 @@ impl RouteInfoManager {
         let old_addr = broker_data
             .broker_addrs_mut()
             .insert(broker_id, broker_addr.clone());
+        broker_data.set_feature_bitmap(broker_id, feature_bitmap.unwrap_or_default());
 
         register_first |= old_addr.is_none();
         let is_master = mix_all::MASTER_ID == broker_id;
@@ -0,0 +1,101 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::net::SocketAddr;
+
+use thiserror::Error;
+
+/// Credentials presented by a caller, independent of the transport they arrived on so the
+/// same provider can back the remoting RPC hook and a gRPC interceptor.
+#[derive(Debug, Clone, Default)]
+pub struct AuthenticationContext {
+    pub remote_address: Option<SocketAddr>,
+    /// Bearer token without the `Bearer ` prefix.
+    pub token: Option<String>,
+}
+
+impl AuthenticationContext {
+    /// Builds a context from an `Authorization` value, accepting both `Bearer <token>` and
+    /// the bare token.
+    pub fn from_authorization(
+        remote_address: Option<SocketAddr>,
+        authorization: Option<&str>,
+    ) -> Self {
+        let token = authorization
+            .map(str::trim)
+            .map(|value| {
+                value
+                    .strip_prefix("Bearer ")
+                    .or_else(|| value.strip_prefix("bearer "))
+                    .unwrap_or(value)
+                    .trim()
+                    .to_string()
+            })
+            .filter(|token| !token.is_empty());
+        AuthenticationContext {
+            remote_address,
+            token,
+        }
+    }
+}
+
+/// Identity established by a successful authentication.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct AuthenticatedPrincipal {
+    pub subject: String,
+    pub issuer: Option<String>,
+    /// Expiry of the credentials in seconds since the epoch, if they expire.
+    pub expires_at: Option<i64>,
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum AuthenticationError {
+    #[error("missing credentials")]
+    MissingCredentials,
+
+    #[error("malformed token: {0}")]
+    MalformedToken(String),
+
+    #[error("unsupported algorithm: {0}")]
+    UnsupportedAlgorithm(String),
+
+    #[error("unknown signing key: {0}")]
+    UnknownKey(String),
+
+    #[error("invalid signature")]
+    InvalidSignature,
+
+    #[error("invalid claim: {0}")]
+    InvalidClaim(String),
+
+    #[error("token expired")]
+    Expired,
+
+    #[error("key set unavailable: {0}")]
+    KeySetUnavailable(String),
+}
+
+/// Verifies the credentials of a caller, complementing the AK/SK based admin ACL for clusters
+/// that standardize on token based authentication.
+pub trait AuthenticationProvider: Send + Sync + 'static {
+    /// Short name used in logs, e.g. `jwt`.
+    fn name(&self) -> &'static str;
+
+    fn authenticate(
+        &self,
+        context: &AuthenticationContext,
+    ) -> Result<AuthenticatedPrincipal, AuthenticationError>;
+}
@@ -0,0 +1,129 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use cheetah_string::CheetahString;
+use tracing::warn;
+
+use crate::auth::authentication_provider::AuthenticationContext;
+use crate::auth::authentication_provider::AuthenticationProvider;
+use crate::code::response_code::ResponseCode;
+use crate::protocol::remoting_command::RemotingCommand;
+use crate::remoting_error::RemotingError;
+use crate::runtime::RPCHook;
+use crate::Result;
+
+/// Extension field carrying the bearer token of a request.
+pub const AUTHORIZATION: &str = "Authorization";
+/// Extension field set by the server to the authenticated subject, for processors and audit.
+pub const AUTHENTICATED_SUBJECT: &str = "AuthenticatedSubject";
+
+/// Server side hook rejecting requests whose token is not accepted by the provider.
+pub struct AuthenticationRPCHook {
+    provider: Arc<dyn AuthenticationProvider>,
+}
+
+impl AuthenticationRPCHook {
+    pub fn new(provider: Arc<dyn AuthenticationProvider>) -> Self {
+        Self { provider }
+    }
+}
+
+impl RPCHook for AuthenticationRPCHook {
+    fn do_before_request(
+        &self,
+        remote_addr: SocketAddr,
+        request: &mut RemotingCommand,
+    ) -> Result<()> {
+        let authorization = request
+            .get_ext_fields()
+            .and_then(|fields| fields.get(AUTHORIZATION))
+            .map(|value| value.as_str());
+        let context = AuthenticationContext::from_authorization(Some(remote_addr), authorization);
+        match self.provider.authenticate(&context) {
+            Ok(principal) => {
+                // never trust a subject sent by the client
+                let mut ext_fields = request.get_ext_fields().cloned().unwrap_or_default();
+                ext_fields.insert(
+                    CheetahString::from_static_str(AUTHENTICATED_SUBJECT),
+                    CheetahString::from_string(principal.subject),
+                );
+                *request = std::mem::take(request).set_ext_fields(ext_fields);
+                Ok(())
+            }
+            Err(error) => {
+                warn!(
+                    "{} authentication failed for request {} from {}: {}",
+                    self.provider.name(),
+                    request.code(),
+                    remote_addr,
+                    error
+                );
+                Err(RemotingError::AbortProcessError(
+                    ResponseCode::NoPermission as i32,
+                    format!("authentication failed: {}", error),
+                ))
+            }
+        }
+    }
+
+    fn do_after_response(
+        &self,
+        _remote_addr: SocketAddr,
+        _response: &mut RemotingCommand,
+    ) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Client side hook attaching a bearer token to every outgoing request.
+pub struct BearerTokenRPCHook {
+    token: CheetahString,
+}
+
+impl BearerTokenRPCHook {
+    pub fn new(token: impl Into<CheetahString>) -> Self {
+        Self {
+            token: token.into(),
+        }
+    }
+}
+
+impl RPCHook for BearerTokenRPCHook {
+    fn do_before_request(
+        &self,
+        _remote_addr: SocketAddr,
+        request: &mut RemotingCommand,
+    ) -> Result<()> {
+        let mut ext_fields = request.get_ext_fields().cloned().unwrap_or_default();
+        ext_fields.insert(
+            CheetahString::from_static_str(AUTHORIZATION),
+            CheetahString::from_string(format!("Bearer {}", self.token)),
+        );
+        *request = std::mem::take(request).set_ext_fields(ext_fields);
+        Ok(())
+    }
+
+    fn do_after_response(
+        &self,
+        _remote_addr: SocketAddr,
+        _response: &mut RemotingCommand,
+    ) -> Result<()> {
+        Ok(())
+    }
+}
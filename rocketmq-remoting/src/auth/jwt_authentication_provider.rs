@@ -0,0 +1,468 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::HashMap;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicI64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use parking_lot::RwLock;
+use ring::hmac;
+use ring::signature;
+use ring::signature::RsaPublicKeyComponents;
+use ring::signature::UnparsedPublicKey;
+use rocketmq_common::utils::http_tiny_client::HttpTinyClient;
+use rocketmq_common::TimeUtils::get_current_millis;
+use serde::Deserialize;
+use tracing::info;
+use tracing::warn;
+
+use crate::auth::authentication_provider::AuthenticatedPrincipal;
+use crate::auth::authentication_provider::AuthenticationContext;
+use crate::auth::authentication_provider::AuthenticationError;
+use crate::auth::authentication_provider::AuthenticationProvider;
+
+const JWKS_FETCH_TIMEOUT_MILLIS: u64 = 3000;
+/// Minimum delay between two refreshes triggered by tokens signed with an unknown key.
+const JWKS_MIN_REFRESH_INTERVAL_MILLIS: i64 = 10 * 1000;
+
+#[derive(Debug, Clone)]
+pub struct JwtAuthenticationConfig {
+    /// Expected `iss` claim, not checked when `None`.
+    pub issuer: Option<String>,
+    /// Expected `aud` claim, not checked when `None`.
+    pub audience: Option<String>,
+    /// JWKS endpoint serving the RS256/ES256 verification keys.
+    pub jwks_url: Option<String>,
+    /// Shared secret for HS256 tokens.
+    pub hmac_secret: Option<String>,
+    pub jwks_refresh_interval_millis: u64,
+    /// Leeway applied to `exp` and `nbf`.
+    pub clock_skew_seconds: i64,
+}
+
+impl Default for JwtAuthenticationConfig {
+    fn default() -> Self {
+        JwtAuthenticationConfig {
+            issuer: None,
+            audience: None,
+            jwks_url: None,
+            hmac_secret: None,
+            jwks_refresh_interval_millis: 5 * 60 * 1000,
+            clock_skew_seconds: 60,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct JwtHeader {
+    alg: String,
+    kid: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Audience {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl Audience {
+    fn contains(&self, audience: &str) -> bool {
+        match self {
+            Audience::One(value) => value == audience,
+            Audience::Many(values) => values.iter().any(|value| value == audience),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct JwtClaims {
+    iss: Option<String>,
+    sub: Option<String>,
+    aud: Option<Audience>,
+    exp: Option<i64>,
+    nbf: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Jwk {
+    kty: String,
+    kid: Option<String>,
+    crv: Option<String>,
+    n: Option<String>,
+    e: Option<String>,
+    x: Option<String>,
+    y: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct JwkSet {
+    keys: Vec<Jwk>,
+}
+
+#[derive(Debug, Clone)]
+enum VerificationKey {
+    Rsa {
+        n: Vec<u8>,
+        e: Vec<u8>,
+    },
+    /// Uncompressed P-256 point.
+    EcP256(Vec<u8>),
+}
+
+/// Validates JWTs signed with HS256, RS256 or ES256 and checks the `iss`, `aud`, `exp` and
+/// `nbf` claims. Asymmetric keys come from a JWKS endpoint refreshed in the background, and
+/// ahead of schedule when a token names a key id that is not cached yet.
+pub struct JwtAuthenticationProvider {
+    config: JwtAuthenticationConfig,
+    keys: RwLock<HashMap<String, VerificationKey>>,
+    last_refresh_millis: AtomicI64,
+    refresh_requested: AtomicBool,
+}
+
+impl JwtAuthenticationProvider {
+    pub fn new(config: JwtAuthenticationConfig) -> Self {
+        JwtAuthenticationProvider {
+            config,
+            keys: RwLock::new(HashMap::new()),
+            last_refresh_millis: AtomicI64::new(0),
+            refresh_requested: AtomicBool::new(false),
+        }
+    }
+
+    /// Replaces the cached keys with the RSA and P-256 keys of `jwks`, returning how many
+    /// were loaded.
+    pub fn load_jwks(&self, jwks: &str) -> Result<usize, AuthenticationError> {
+        let key_set = serde_json::from_str::<JwkSet>(jwks)
+            .map_err(|error| AuthenticationError::KeySetUnavailable(error.to_string()))?;
+        let mut keys = HashMap::with_capacity(key_set.keys.len());
+        for jwk in key_set.keys {
+            let kid = jwk.kid.clone().unwrap_or_default();
+            match to_verification_key(&jwk) {
+                Some(key) => {
+                    keys.insert(kid, key);
+                }
+                None => warn!("skip unsupported jwk, kid={}, kty={}", kid, jwk.kty),
+            }
+        }
+        let loaded = keys.len();
+        *self.keys.write() = keys;
+        self.last_refresh_millis
+            .store(get_current_millis() as i64, Ordering::Release);
+        Ok(loaded)
+    }
+
+    /// Downloads the key set from `jwks_url`. Blocking, call it off the async runtime.
+    pub fn refresh_jwks(&self) -> Result<usize, AuthenticationError> {
+        let Some(jwks_url) = self.config.jwks_url.as_deref() else {
+            return Ok(0);
+        };
+        let result =
+            HttpTinyClient::http_get(jwks_url, None, None, "UTF-8", JWKS_FETCH_TIMEOUT_MILLIS)
+                .map_err(|error| AuthenticationError::KeySetUnavailable(error.to_string()))?;
+        if result.code != 200 {
+            return Err(AuthenticationError::KeySetUnavailable(format!(
+                "{} returned {}",
+                jwks_url, result.code
+            )));
+        }
+        self.load_jwks(&result.content)
+    }
+
+    /// Loads and keeps the key set fresh from a background thread, which stops when the
+    /// provider is dropped. The fetch is blocking, so it never runs on the async runtime.
+    pub fn start_jwks_refresh(self: &Arc<Self>) {
+        if self.config.jwks_url.is_none() {
+            return;
+        }
+        let provider = Arc::downgrade(self);
+        let interval = self.config.jwks_refresh_interval_millis as i64;
+        std::thread::Builder::new()
+            .name("JwksRefreshService".to_string())
+            .spawn(move || loop {
+                std::thread::sleep(Duration::from_secs(1));
+                let Some(provider) = provider.upgrade() else {
+                    return;
+                };
+                let elapsed = get_current_millis() as i64
+                    - provider.last_refresh_millis.load(Ordering::Acquire);
+                let requested = provider.refresh_requested.load(Ordering::Acquire)
+                    && elapsed >= JWKS_MIN_REFRESH_INTERVAL_MILLIS;
+                if !requested && elapsed < interval {
+                    continue;
+                }
+                provider.refresh_requested.store(false, Ordering::Release);
+                match provider.refresh_jwks() {
+                    Ok(loaded) => info!("jwks refreshed, {} keys loaded", loaded),
+                    Err(error) => {
+                        warn!("jwks refresh failed: {}", error);
+                        // retry on the next tick instead of waiting a full interval
+                        provider
+                            .last_refresh_millis
+                            .store(get_current_millis() as i64 - interval, Ordering::Release);
+                    }
+                }
+            })
+            .expect("spawn jwks refresh thread failed");
+    }
+
+    /// Verifies `token` as of `now_seconds`.
+    pub fn verify(
+        &self,
+        token: &str,
+        now_seconds: i64,
+    ) -> Result<AuthenticatedPrincipal, AuthenticationError> {
+        let mut parts = token.split('.');
+        let (Some(header), Some(payload), Some(signature), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Err(AuthenticationError::MalformedToken(
+                "expected three segments".to_string(),
+            ));
+        };
+        let header = serde_json::from_slice::<JwtHeader>(&decode_segment(header)?)
+            .map_err(|error| AuthenticationError::MalformedToken(error.to_string()))?;
+        let signature = decode_segment(signature)?;
+        let signing_input = &token[..token.len() - token.rsplit('.').next().unwrap().len() - 1];
+        self.verify_signature(&header, signing_input.as_bytes(), &signature)?;
+
+        let claims = serde_json::from_slice::<JwtClaims>(&decode_segment(payload)?)
+            .map_err(|error| AuthenticationError::MalformedToken(error.to_string()))?;
+        self.check_claims(&claims, now_seconds)?;
+        Ok(AuthenticatedPrincipal {
+            subject: claims.sub.unwrap_or_default(),
+            issuer: claims.iss,
+            expires_at: claims.exp,
+        })
+    }
+
+    fn verify_signature(
+        &self,
+        header: &JwtHeader,
+        signing_input: &[u8],
+        signature: &[u8],
+    ) -> Result<(), AuthenticationError> {
+        match header.alg.as_str() {
+            "HS256" => {
+                let secret = self
+                    .config
+                    .hmac_secret
+                    .as_deref()
+                    .ok_or_else(|| AuthenticationError::UnsupportedAlgorithm("HS256".into()))?;
+                let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+                hmac::verify(&key, signing_input, signature)
+                    .map_err(|_| AuthenticationError::InvalidSignature)
+            }
+            "RS256" | "ES256" => {
+                let kid = header.kid.clone().unwrap_or_default();
+                let key = self.keys.read().get(&kid).cloned();
+                let Some(key) = key else {
+                    self.refresh_requested.store(true, Ordering::Release);
+                    return Err(AuthenticationError::UnknownKey(kid));
+                };
+                let verified = match (header.alg.as_str(), &key) {
+                    ("RS256", VerificationKey::Rsa { n, e }) => RsaPublicKeyComponents { n, e }
+                        .verify(
+                            &signature::RSA_PKCS1_2048_8192_SHA256,
+                            signing_input,
+                            signature,
+                        )
+                        .is_ok(),
+                    ("ES256", VerificationKey::EcP256(point)) => {
+                        UnparsedPublicKey::new(&signature::ECDSA_P256_SHA256_FIXED, point)
+                            .verify(signing_input, signature)
+                            .is_ok()
+                    }
+                    _ => false,
+                };
+                if verified {
+                    Ok(())
+                } else {
+                    Err(AuthenticationError::InvalidSignature)
+                }
+            }
+            alg => Err(AuthenticationError::UnsupportedAlgorithm(alg.to_string())),
+        }
+    }
+
+    fn check_claims(
+        &self,
+        claims: &JwtClaims,
+        now_seconds: i64,
+    ) -> Result<(), AuthenticationError> {
+        let skew = self.config.clock_skew_seconds;
+        if let Some(issuer) = self.config.issuer.as_deref() {
+            if claims.iss.as_deref() != Some(issuer) {
+                return Err(AuthenticationError::InvalidClaim("iss".to_string()));
+            }
+        }
+        if let Some(audience) = self.config.audience.as_deref() {
+            if !claims
+                .aud
+                .as_ref()
+                .is_some_and(|aud| aud.contains(audience))
+            {
+                return Err(AuthenticationError::InvalidClaim("aud".to_string()));
+            }
+        }
+        match claims.exp {
+            Some(exp) if now_seconds > exp + skew => return Err(AuthenticationError::Expired),
+            Some(_) => {}
+            None => return Err(AuthenticationError::InvalidClaim("exp".to_string())),
+        }
+        if claims.nbf.is_some_and(|nbf| now_seconds + skew < nbf) {
+            return Err(AuthenticationError::InvalidClaim("nbf".to_string()));
+        }
+        if claims.sub.as_deref().is_none_or(str::is_empty) {
+            return Err(AuthenticationError::InvalidClaim("sub".to_string()));
+        }
+        Ok(())
+    }
+}
+
+impl AuthenticationProvider for JwtAuthenticationProvider {
+    fn name(&self) -> &'static str {
+        "jwt"
+    }
+
+    fn authenticate(
+        &self,
+        context: &AuthenticationContext,
+    ) -> Result<AuthenticatedPrincipal, AuthenticationError> {
+        let token = context
+            .token
+            .as_deref()
+            .ok_or(AuthenticationError::MissingCredentials)?;
+        self.verify(token, get_current_millis() as i64 / 1000)
+    }
+}
+
+fn decode_segment(segment: &str) -> Result<Vec<u8>, AuthenticationError> {
+    URL_SAFE_NO_PAD
+        .decode(segment.trim_end_matches('='))
+        .map_err(|error| AuthenticationError::MalformedToken(error.to_string()))
+}
+
+fn to_verification_key(jwk: &Jwk) -> Option<VerificationKey> {
+    let decode = |value: &Option<String>| {
+        value
+            .as_deref()
+            .and_then(|value| URL_SAFE_NO_PAD.decode(value).ok())
+    };
+    match jwk.kty.as_str() {
+        "RSA" => Some(VerificationKey::Rsa {
+            n: decode(&jwk.n)?,
+            e: decode(&jwk.e)?,
+        }),
+        "EC" if jwk.crv.as_deref() == Some("P-256") => {
+            let x = decode(&jwk.x)?;
+            let y = decode(&jwk.y)?;
+            if x.len() != 32 || y.len() != 32 {
+                return None;
+            }
+            let mut point = Vec::with_capacity(65);
+            point.push(0x04);
+            point.extend_from_slice(&x);
+            point.extend_from_slice(&y);
+            Some(VerificationKey::EcP256(point))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &str = "test-secret";
+
+    fn provider() -> JwtAuthenticationProvider {
+        JwtAuthenticationProvider::new(JwtAuthenticationConfig {
+            issuer: Some("https://issuer.example".to_string()),
+            audience: Some("rocketmq".to_string()),
+            hmac_secret: Some(SECRET.to_string()),
+            clock_skew_seconds: 0,
+            ..Default::default()
+        })
+    }
+
+    fn sign_hs256(claims: &str) -> String {
+        let header = URL_SAFE_NO_PAD.encode(r#"{"alg":"HS256","typ":"JWT"}"#);
+        let payload = URL_SAFE_NO_PAD.encode(claims);
+        let signing_input = format!("{}.{}", header, payload);
+        let key = hmac::Key::new(hmac::HMAC_SHA256, SECRET.as_bytes());
+        let signature = URL_SAFE_NO_PAD.encode(hmac::sign(&key, signing_input.as_bytes()));
+        format!("{}.{}", signing_input, signature)
+    }
+
+    #[test]
+    fn verify_accepts_valid_hs256_token() {
+        let token = sign_hs256(
+            r#"{"iss":"https://issuer.example","sub":"app-1","aud":["rocketmq","other"],"exp":2000}"#,
+        );
+        let principal = provider().verify(&token, 1000).unwrap();
+        assert_eq!(principal.subject, "app-1");
+        assert_eq!(principal.expires_at, Some(2000));
+    }
+
+    #[test]
+    fn verify_rejects_bad_claims_and_signature() {
+        let provider = provider();
+        let expired =
+            sign_hs256(r#"{"iss":"https://issuer.example","sub":"a","aud":"rocketmq","exp":999}"#);
+        assert_eq!(
+            provider.verify(&expired, 1000),
+            Err(AuthenticationError::Expired)
+        );
+        let wrong_audience =
+            sign_hs256(r#"{"iss":"https://issuer.example","sub":"a","aud":"other","exp":2000}"#);
+        assert_eq!(
+            provider.verify(&wrong_audience, 1000),
+            Err(AuthenticationError::InvalidClaim("aud".to_string()))
+        );
+
+        let valid =
+            sign_hs256(r#"{"iss":"https://issuer.example","sub":"a","aud":"rocketmq","exp":2000}"#);
+        let tampered = format!("{}x", &valid[..valid.len() - 1]);
+        assert!(provider.verify(&tampered, 1000).is_err());
+        assert!(matches!(
+            provider.verify("not-a-token", 1000),
+            Err(AuthenticationError::MalformedToken(_))
+        ));
+    }
+
+    #[test]
+    fn unknown_key_requests_refresh() {
+        let provider = provider();
+        let jwks =
+            r#"{"keys":[{"kty":"RSA","kid":"k1","n":"AQAB","e":"AQAB"},{"kty":"oct","kid":"k2"}]}"#;
+        assert_eq!(provider.load_jwks(jwks).unwrap(), 1);
+
+        let header = URL_SAFE_NO_PAD.encode(r#"{"alg":"RS256","kid":"k9"}"#);
+        let payload = URL_SAFE_NO_PAD.encode(r#"{"sub":"a","exp":2000}"#);
+        let token = format!("{}.{}.{}", header, payload, URL_SAFE_NO_PAD.encode("sig"));
+        assert_eq!(
+            provider.verify(&token, 1000),
+            Err(AuthenticationError::UnknownKey("k9".to_string()))
+        );
+        assert!(provider.refresh_requested.load(Ordering::Acquire));
+    }
+}
@@ -25,6 +25,7 @@ use crate::remoting::RemotingService;
 use crate::runtime::processor::RequestProcessor;
 use crate::Result;
 
+mod address_resolver;
 mod async_client;
 mod blocking_client;
 
@@ -0,0 +1,231 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::HashMap;
+use std::io;
+use std::net::IpAddr;
+use std::net::SocketAddr;
+use std::time::Duration;
+use std::time::Instant;
+
+use cheetah_string::CheetahString;
+use parking_lot::Mutex;
+use tracing::info;
+
+struct ResolvedAddress {
+    addrs: Vec<SocketAddr>,
+    resolved_at: Instant,
+    /// Index of the address the last connection succeeded with, tried first next time.
+    preferred: usize,
+}
+
+/// Resolves `host:port` remoting addresses and remembers the result, so that a hostname backed by
+/// several IPs can fail over between them and IP changes are picked up periodically.
+pub(crate) struct AddressResolver {
+    resolve_interval: Duration,
+    pinned_hosts: HashMap<String, Vec<IpAddr>>,
+    cache: Mutex<HashMap<CheetahString, ResolvedAddress>>,
+}
+
+impl AddressResolver {
+    pub fn new(resolve_interval: Duration, pinned_hosts: HashMap<String, Vec<IpAddr>>) -> Self {
+        AddressResolver {
+            resolve_interval,
+            pinned_hosts,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Candidate socket addresses for `addr`, the last known good one first.
+    pub async fn resolve(&self, addr: &CheetahString) -> io::Result<Vec<SocketAddr>> {
+        if let Some(addrs) = self.resolve_static(addr)? {
+            return Ok(addrs);
+        }
+        if let Some(resolved) = self.cache.lock().get(addr) {
+            if resolved.resolved_at.elapsed() < self.resolve_interval {
+                return Ok(rotate(&resolved.addrs, resolved.preferred));
+            }
+        }
+        let addrs = self.lookup(addr).await?;
+        Ok(self.update(addr, addrs).0)
+    }
+
+    /// Re-resolves every cached hostname and returns those whose IPs changed together with the
+    /// new IPs. Hostnames that fail to resolve keep their previous addresses.
+    pub async fn refresh(&self) -> Vec<(CheetahString, Vec<SocketAddr>)> {
+        let hosts = self.cache.lock().keys().cloned().collect::<Vec<_>>();
+        let mut changed = Vec::new();
+        for host in hosts {
+            match self.lookup(&host).await {
+                Ok(addrs) => {
+                    let (addrs, is_changed) = self.update(&host, addrs);
+                    if is_changed {
+                        info!("address of {} changed, now resolves to {:?}", host, addrs);
+                        changed.push((host, addrs));
+                    }
+                }
+                Err(e) => info!("re-resolve {} failed, keep cached addresses: {}", host, e),
+            }
+        }
+        changed
+    }
+
+    pub fn mark_connected(&self, addr: &CheetahString, socket_addr: SocketAddr) {
+        if let Some(resolved) = self.cache.lock().get_mut(addr) {
+            if let Some(index) = resolved.addrs.iter().position(|a| *a == socket_addr) {
+                resolved.preferred = index;
+            }
+        }
+    }
+
+    fn resolve_static(&self, addr: &CheetahString) -> io::Result<Option<Vec<SocketAddr>>> {
+        if let Ok(socket_addr) = addr.parse::<SocketAddr>() {
+            return Ok(Some(vec![socket_addr]));
+        }
+        let (host, port) = split_host_port(addr)?;
+        Ok(self.pinned_hosts.get(host).map(|ips| {
+            ips.iter()
+                .map(|ip| SocketAddr::new(*ip, port))
+                .collect::<Vec<_>>()
+        }))
+    }
+
+    async fn lookup(&self, addr: &CheetahString) -> io::Result<Vec<SocketAddr>> {
+        let mut addrs = tokio::net::lookup_host(addr.as_str())
+            .await?
+            .collect::<Vec<_>>();
+        if addrs.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("{} resolves to no address", addr),
+            ));
+        }
+        addrs.sort();
+        addrs.dedup();
+        Ok(addrs)
+    }
+
+    fn update(&self, addr: &CheetahString, addrs: Vec<SocketAddr>) -> (Vec<SocketAddr>, bool) {
+        let mut cache = self.cache.lock();
+        let (preferred, changed) = match cache.get(addr) {
+            Some(old) => {
+                let preferred = old
+                    .addrs
+                    .get(old.preferred)
+                    .and_then(|p| addrs.iter().position(|a| a == p))
+                    .unwrap_or_default();
+                (preferred, old.addrs != addrs)
+            }
+            None => (0, false),
+        };
+        let result = rotate(&addrs, preferred);
+        cache.insert(
+            addr.clone(),
+            ResolvedAddress {
+                addrs,
+                resolved_at: Instant::now(),
+                preferred,
+            },
+        );
+        (result, changed)
+    }
+}
+
+fn rotate(addrs: &[SocketAddr], preferred: usize) -> Vec<SocketAddr> {
+    let mut result = addrs.to_vec();
+    if !result.is_empty() {
+        result.rotate_left(preferred % addrs.len());
+    }
+    result
+}
+
+/// Splits `host:port` and `[v6]:port`.
+pub(crate) fn split_host_port(addr: &str) -> io::Result<(&str, u16)> {
+    let invalid = || {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("invalid address {}", addr),
+        )
+    };
+    let (host, port) = addr.rsplit_once(':').ok_or_else(invalid)?;
+    let host = host
+        .strip_prefix('[')
+        .and_then(|host| host.strip_suffix(']'))
+        .unwrap_or(host);
+    let port = port.parse::<u16>().map_err(|_| invalid())?;
+    Ok((host, port))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolver() -> AddressResolver {
+        AddressResolver::new(
+            Duration::from_secs(30),
+            HashMap::from([(
+                "namesrv.local".to_string(),
+                vec!["10.0.0.1".parse().unwrap(), "10.0.0.2".parse().unwrap()],
+            )]),
+        )
+    }
+
+    #[test]
+    fn splits_host_and_port() {
+        assert_eq!(
+            split_host_port("namesrv.local:9876").unwrap(),
+            ("namesrv.local", 9876)
+        );
+        assert_eq!(split_host_port("[::1]:9876").unwrap(), ("::1", 9876));
+        assert!(split_host_port("namesrv.local").is_err());
+    }
+
+    #[tokio::test]
+    async fn resolves_literals_and_pinned_hosts() {
+        let resolver = resolver();
+        assert_eq!(
+            resolver.resolve(&"127.0.0.1:9876".into()).await.unwrap(),
+            vec!["127.0.0.1:9876".parse().unwrap()]
+        );
+        assert_eq!(
+            resolver.resolve(&"[::1]:9876".into()).await.unwrap(),
+            vec!["[::1]:9876".parse().unwrap()]
+        );
+        assert_eq!(
+            resolver
+                .resolve(&"namesrv.local:9876".into())
+                .await
+                .unwrap(),
+            vec![
+                "10.0.0.1:9876".parse().unwrap(),
+                "10.0.0.2:9876".parse().unwrap()
+            ]
+        );
+    }
+
+    #[test]
+    fn prefers_last_connected_address_and_detects_changes() {
+        let resolver = resolver();
+        let host = CheetahString::from("broker.local:10911");
+        let a: SocketAddr = "10.0.1.1:10911".parse().unwrap();
+        let b: SocketAddr = "10.0.1.2:10911".parse().unwrap();
+        let c: SocketAddr = "10.0.1.3:10911".parse().unwrap();
+        assert_eq!(resolver.update(&host, vec![a, b]), (vec![a, b], false));
+        resolver.mark_connected(&host, b);
+        assert_eq!(resolver.update(&host, vec![a, b]), (vec![b, a], false));
+        assert_eq!(resolver.update(&host, vec![b, c]), (vec![b, c], true));
+    }
+}
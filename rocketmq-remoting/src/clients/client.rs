@@ -15,6 +15,7 @@
  * limitations under the License.
  */
 use std::collections::HashMap;
+use std::net::SocketAddr;
 
 use futures_util::SinkExt;
 use futures_util::StreamExt;
@@ -344,6 +345,10 @@ impl Client {
         }
     }
 
+    pub fn remote_address(&self) -> SocketAddr {
+        self.inner.channel.remote_address()
+    }
+
     pub fn connection(&self) -> &Connection {
         self.inner.ctx.channel.connection_ref()
     }
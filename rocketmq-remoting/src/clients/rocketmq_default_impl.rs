@@ -33,6 +33,7 @@ use tracing::info;
 use tracing::warn;
 
 use crate::base::connection_net_event::ConnectionNetEvent;
+use crate::clients::address_resolver::AddressResolver;
 use crate::clients::Client;
 use crate::clients::RemotingClient;
 use crate::protocol::remoting_command::RemotingCommand;
@@ -59,6 +60,7 @@ pub struct RocketmqDefaultClient<PR = DefaultRemotingRequestProcessor> {
     client_runtime: Arc<RocketMQRuntime>,
     processor: PR,
     tx: Option<tokio::sync::broadcast::Sender<ConnectionNetEvent>>,
+    address_resolver: Arc<AddressResolver>,
 }
 impl<PR: RequestProcessor + Sync + Clone + 'static> RocketmqDefaultClient<PR> {
     pub fn new(tokio_client_config: Arc<TokioClientConfig>, processor: PR) -> Self {
@@ -70,6 +72,10 @@ impl<PR: RequestProcessor + Sync + Clone + 'static> RocketmqDefaultClient<PR> {
         processor: PR,
         tx: Option<tokio::sync::broadcast::Sender<ConnectionNetEvent>>,
     ) -> Self {
+        let address_resolver = Arc::new(AddressResolver::new(
+            Duration::from_millis(tokio_client_config.dns_resolve_interval_millis),
+            tokio_client_config.dns_pinned_hosts.clone(),
+        ));
         Self {
             tokio_client_config,
             connection_tables: Arc::new(Mutex::new(Default::default())),
@@ -80,6 +86,7 @@ impl<PR: RequestProcessor + Sync + Clone + 'static> RocketmqDefaultClient<PR> {
             client_runtime: Arc::new(RocketMQRuntime::new_multi(10, "client-thread")),
             processor,
             tx,
+            address_resolver,
         }
     }
 }
@@ -179,10 +186,21 @@ impl<PR: RequestProcessor + Sync + Clone + 'static> RocketmqDefaultClient<PR> {
             let _ = connection_tables.remove(addr.as_str());
         }
 
-        let addr_inner = addr.to_string();
+        let socket_addrs = match self.address_resolver.resolve(addr).await {
+            Ok(socket_addrs) => socket_addrs,
+            Err(e) => {
+                error!("getAndCreateClient resolve {} failed: {}", addr, e);
+                return None;
+            }
+        };
 
         match time::timeout(duration, async {
-            Client::connect(addr_inner, self.processor.clone(), self.tx.as_ref()).await
+            Client::connect(
+                socket_addrs.as_slice(),
+                self.processor.clone(),
+                self.tx.as_ref(),
+            )
+            .await
         })
         .await
         {
@@ -190,6 +208,8 @@ impl<PR: RequestProcessor + Sync + Clone + 'static> RocketmqDefaultClient<PR> {
                 Ok(client_r) => {
                     //let client = Arc::new(Mutex::new(client_r));
                     let client = client_r;
+                    self.address_resolver
+                        .mark_connected(addr, client.remote_address());
                     connection_tables.insert(addr.clone(), client.clone());
                     Some(client)
                 }
@@ -205,6 +225,28 @@ impl<PR: RequestProcessor + Sync + Clone + 'static> RocketmqDefaultClient<PR> {
         }
     }
 
+    /// Drops connections to IPs a hostname no longer resolves to, they are re-established
+    /// against the new IPs on next use.
+    async fn refresh_resolved_addresses(&self) {
+        let changed = self.address_resolver.refresh().await;
+        if changed.is_empty() {
+            return;
+        }
+        let mut connection_tables = self.connection_tables.lock().await;
+        for (addr, socket_addrs) in changed {
+            let stale = connection_tables
+                .get(&addr)
+                .is_some_and(|client| !socket_addrs.contains(&client.remote_address()));
+            if stale {
+                warn!(
+                    "{} no longer resolves to the connected IP, close the connection",
+                    addr
+                );
+                connection_tables.remove(&addr);
+            }
+        }
+    }
+
     async fn scan_available_name_srv(&self) {
         if self.namesrv_addr_list.as_ref().is_empty() {
             debug!("scanAvailableNameSrv addresses of name remoting_server is null!");
@@ -241,6 +283,16 @@ impl<PR: RequestProcessor + Sync + Clone + 'static> RemotingService for Rocketmq
     async fn start(&self, this: WeakArcMut<Self>) {
         if let Some(client) = this.upgrade() {
             let connect_timeout_millis = self.tokio_client_config.connect_timeout_millis as u64;
+            let dns_resolve_interval_millis = self.tokio_client_config.dns_resolve_interval_millis;
+            if dns_resolve_interval_millis > 0 {
+                let client = client.clone();
+                self.client_runtime.get_handle().spawn(async move {
+                    loop {
+                        time::sleep(Duration::from_millis(dns_resolve_interval_millis)).await;
+                        client.refresh_resolved_addresses().await;
+                    }
+                });
+            }
             self.client_runtime.get_handle().spawn(async move {
                 loop {
                     client.scan_available_name_srv().await;
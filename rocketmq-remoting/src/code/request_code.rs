@@ -148,6 +148,24 @@ pub enum RequestCode {
     RemoveColdDataFlowCtrConfig = 2002,
     GetColdDataFlowCtrInfo = 2003,
     SetCommitlogReadMode = 2004,
+
+    GetStoreHealth = 3001,
+    GetHotMappedFiles = 3002,
+    UpdateMaintenanceMode = 3003,
+    CreateStoreSnapshot = 3004,
+    UpdateQueueWriteFence = 3005,
+    UpdateBrokerDrain = 3006,
+    FlushStore = 3007,
+    GetPoisonMessageReport = 3008,
+    ResumePausedQueue = 3009,
+    CreateReplaySubscription = 3010,
+    DeleteReplaySubscription = 3011,
+    CheckConfirmOffset = 3012,
+    GetDiskUsageInfo = 3013,
+    EndTransactionBatch = 3014,
+    ExamineConsumeQueue = 3015,
+    GetOrphanedTransactions = 3016,
+    ResolveOrphanedTransaction = 3017,
     Unknown = -9999999,
 }
 
@@ -290,6 +308,23 @@ impl From<i32> for RequestCode {
             2002 => RequestCode::RemoveColdDataFlowCtrConfig,
             2003 => RequestCode::GetColdDataFlowCtrInfo,
             2004 => RequestCode::SetCommitlogReadMode,
+            3001 => RequestCode::GetStoreHealth,
+            3002 => RequestCode::GetHotMappedFiles,
+            3003 => RequestCode::UpdateMaintenanceMode,
+            3004 => RequestCode::CreateStoreSnapshot,
+            3005 => RequestCode::UpdateQueueWriteFence,
+            3006 => RequestCode::UpdateBrokerDrain,
+            3007 => RequestCode::FlushStore,
+            3008 => RequestCode::GetPoisonMessageReport,
+            3009 => RequestCode::ResumePausedQueue,
+            3010 => RequestCode::CreateReplaySubscription,
+            3011 => RequestCode::DeleteReplaySubscription,
+            3012 => RequestCode::CheckConfirmOffset,
+            3013 => RequestCode::GetDiskUsageInfo,
+            3014 => RequestCode::EndTransactionBatch,
+            3015 => RequestCode::ExamineConsumeQueue,
+            3016 => RequestCode::GetOrphanedTransactions,
+            3017 => RequestCode::ResolveOrphanedTransaction,
             _ => RequestCode::Unknown,
         }
     }
@@ -88,6 +88,9 @@ pub enum ResponseCode {
     BrokerDispatchNotComplete = 212,
     BroadcastConsumption = 213,
     FlowControl = 215,
+    BrokerInMaintenance = 216,
+    NotModified = 217,
+    NotInConsumeWindow = 218,
     NotLeaderForQueue = 501,
     IllegalOperation = 604,
     RpcUnknown = -1000,
@@ -160,6 +163,9 @@ impl From<i32> for ResponseCode {
             212 => ResponseCode::BrokerDispatchNotComplete,
             213 => ResponseCode::BroadcastConsumption,
             215 => ResponseCode::FlowControl,
+            216 => ResponseCode::BrokerInMaintenance,
+            217 => ResponseCode::NotModified,
+            218 => ResponseCode::NotInConsumeWindow,
             501 => ResponseCode::NotLeaderForQueue,
             604 => ResponseCode::IllegalOperation,
             -1000 => ResponseCode::RpcUnknown,
@@ -15,4 +15,5 @@
  * limitations under the License.
  */
 
+pub mod chunked_response;
 pub mod remoting_command_codec;
@@ -0,0 +1,305 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use bytes::BytesMut;
+use cheetah_string::CheetahString;
+use parking_lot::Mutex;
+use rocketmq_common::CRC32Utils::crc32;
+use tracing::warn;
+
+use crate::code::response_code::ResponseCode;
+use crate::protocol::remoting_command::RemotingCommand;
+
+/// Set on a request whose caller is able to reassemble a chunked response.
+pub const ACCEPT_CHUNKED_RESPONSE: &str = "acceptChunkedResponse";
+pub const CHUNK_INDEX: &str = "chunkIndex";
+pub const CHUNK_LAST: &str = "chunkLast";
+pub const CHUNK_CRC32: &str = "chunkCrc32";
+
+pub const DEFAULT_CHUNK_SIZE: usize = 4 * 1024 * 1024;
+pub const DEFAULT_MAX_REASSEMBLY_BYTES: usize = 256 * 1024 * 1024;
+
+/// Splits responses bigger than `chunk_size` into a sequence of frames sharing the opaque of
+/// the request, but only for requests that declared [`ACCEPT_CHUNKED_RESPONSE`], so peers
+/// that do not know the protocol keep receiving a single frame.
+///
+/// Clones share the accepted opaques, so the read and the write half of a connection can each
+/// own a copy.
+#[derive(Clone)]
+pub struct ResponseChunker {
+    chunk_size: usize,
+    accepted_opaques: Arc<Mutex<HashSet<i32>>>,
+}
+
+impl ResponseChunker {
+    pub fn new(chunk_size: usize) -> Self {
+        Self {
+            chunk_size,
+            accepted_opaques: Arc::new(Mutex::new(HashSet::new())),
+        }
+    }
+
+    /// Remembers the opaque of an inbound request that accepts chunked responses.
+    pub fn on_request(&mut self, request: &RemotingCommand) {
+        if request.is_oneway_rpc() {
+            return;
+        }
+        let accepted = request
+            .get_ext_fields()
+            .is_some_and(|ext| ext.contains_key(ACCEPT_CHUNKED_RESPONSE));
+        if accepted {
+            self.accepted_opaques.lock().insert(request.opaque());
+        }
+    }
+
+    /// Forgets the opaque of a response that is written without going through
+    /// [`split`](Self::split).
+    pub fn forget(&mut self, opaque: i32) {
+        self.accepted_opaques.lock().remove(&opaque);
+    }
+
+    /// Returns the frames to write for `response`.
+    pub fn split(&mut self, response: RemotingCommand) -> Vec<RemotingCommand> {
+        let accepted = self.accepted_opaques.lock().remove(&response.opaque());
+        let body_len = response.get_body().map_or(0, |body| body.len());
+        if !accepted || self.chunk_size == 0 || body_len <= self.chunk_size {
+            return vec![response];
+        }
+
+        let mut response = response;
+        let body = response.take_body().unwrap_or_default();
+        let chunk_count = body_len.div_ceil(self.chunk_size);
+        let mut chunks = Vec::with_capacity(chunk_count);
+        for index in 0..chunk_count {
+            let begin = index * self.chunk_size;
+            let end = (begin + self.chunk_size).min(body_len);
+            let payload = body.slice(begin..end);
+            // the first chunk keeps the original header, the rest only carry the chunk fields
+            let (frame, mut ext_fields) = if index == 0 {
+                let ext_fields = response.get_ext_fields().cloned().unwrap_or_default();
+                (response.clone(), ext_fields)
+            } else {
+                let frame = RemotingCommand::create_response_command()
+                    .set_opaque(response.opaque())
+                    .set_code(response.code())
+                    .set_serialize_type(response.get_serialize_type());
+                (frame, HashMap::new())
+            };
+            ext_fields.insert(CHUNK_INDEX.into(), index.to_string().into());
+            ext_fields.insert(
+                CHUNK_LAST.into(),
+                (index + 1 == chunk_count).to_string().into(),
+            );
+            ext_fields.insert(CHUNK_CRC32.into(), crc32(&payload).to_string().into());
+            chunks.push(frame.set_ext_fields(ext_fields).set_body(payload));
+        }
+        chunks
+    }
+}
+
+#[derive(Clone)]
+struct PendingResponse {
+    head: RemotingCommand,
+    body: BytesMut,
+    next_index: usize,
+}
+
+/// Reassembles chunked responses on the requesting side.
+///
+/// Memory is bounded by `max_reassembly_bytes` across all responses in flight. A response that
+/// fails its checksum or does not fit is turned into a `SystemError` response for the same
+/// opaque, so the waiting caller fails fast while the connection stays usable.
+#[derive(Clone)]
+pub struct ChunkAssembler {
+    max_reassembly_bytes: usize,
+    pending_bytes: usize,
+    pending: HashMap<i32, PendingResponse>,
+}
+
+impl ChunkAssembler {
+    pub fn new(max_reassembly_bytes: usize) -> Self {
+        Self {
+            max_reassembly_bytes,
+            pending_bytes: 0,
+            pending: HashMap::new(),
+        }
+    }
+
+    pub fn is_chunk(command: &RemotingCommand) -> bool {
+        command.is_response_type()
+            && command
+                .get_ext_fields()
+                .is_some_and(|ext| ext.contains_key(CHUNK_INDEX))
+    }
+
+    /// Feeds one chunk, returning the complete response once the last chunk arrived.
+    pub fn accept(&mut self, mut chunk: RemotingCommand) -> Option<RemotingCommand> {
+        let opaque = chunk.opaque();
+        let ext_fields = chunk.get_ext_fields().cloned().unwrap_or_default();
+        let index = parse_field::<usize>(&ext_fields, CHUNK_INDEX);
+        let last = parse_field::<bool>(&ext_fields, CHUNK_LAST).unwrap_or(false);
+        let expected_crc = parse_field::<u32>(&ext_fields, CHUNK_CRC32);
+        let payload = chunk.take_body().unwrap_or_default();
+
+        if expected_crc != Some(crc32(&payload)) {
+            return Some(self.fail(opaque, "chunked response checksum mismatch"));
+        }
+        if self.pending_bytes + payload.len() > self.max_reassembly_bytes {
+            return Some(self.fail(opaque, "chunked response exceeds reassembly limit"));
+        }
+
+        if index == Some(0) {
+            self.discard(opaque);
+            let mut head_ext_fields = ext_fields;
+            head_ext_fields.remove(CHUNK_INDEX);
+            head_ext_fields.remove(CHUNK_LAST);
+            head_ext_fields.remove(CHUNK_CRC32);
+            let pending = PendingResponse {
+                head: chunk.set_ext_fields(head_ext_fields),
+                body: BytesMut::new(),
+                next_index: 0,
+            };
+            self.pending.insert(opaque, pending);
+        }
+        let Some(pending) = self.pending.get_mut(&opaque) else {
+            warn!(
+                "drop chunk {:?} of unknown response, opaque={}",
+                index, opaque
+            );
+            return None;
+        };
+        if index != Some(pending.next_index) {
+            return Some(self.fail(opaque, "chunked response out of order"));
+        }
+        pending.next_index += 1;
+        pending.body.extend_from_slice(&payload);
+        self.pending_bytes += payload.len();
+        if !last {
+            return None;
+        }
+
+        let pending = self.pending.remove(&opaque)?;
+        self.pending_bytes -= pending.body.len();
+        Some(pending.head.set_body(pending.body.freeze()))
+    }
+
+    /// Bytes currently buffered for incomplete responses.
+    pub fn pending_bytes(&self) -> usize {
+        self.pending_bytes
+    }
+
+    fn discard(&mut self, opaque: i32) {
+        if let Some(pending) = self.pending.remove(&opaque) {
+            self.pending_bytes -= pending.body.len();
+        }
+    }
+
+    fn fail(&mut self, opaque: i32, remark: &str) -> RemotingCommand {
+        warn!("{}, opaque={}", remark, opaque);
+        self.discard(opaque);
+        RemotingCommand::create_response_command_with_code_remark(ResponseCode::SystemError, remark)
+            .set_opaque(opaque)
+    }
+}
+
+fn parse_field<T: std::str::FromStr>(
+    ext_fields: &HashMap<CheetahString, CheetahString>,
+    key: &str,
+) -> Option<T> {
+    ext_fields
+        .get(&CheetahString::from_static_str(key))
+        .and_then(|value| value.as_str().parse::<T>().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use super::*;
+
+    fn request(opaque: i32) -> RemotingCommand {
+        RemotingCommand::create_remoting_command(21)
+            .set_opaque(opaque)
+            .mark_accept_chunked_response()
+    }
+
+    fn response(opaque: i32, body: &'static [u8]) -> RemotingCommand {
+        RemotingCommand::create_response_command()
+            .set_opaque(opaque)
+            .set_remark("ok")
+            .set_body(Bytes::from_static(body))
+    }
+
+    #[test]
+    fn split_and_reassemble_round_trip() {
+        let mut chunker = ResponseChunker::new(4);
+        chunker.on_request(&request(7));
+        let chunks = chunker.split(response(7, b"0123456789"));
+        assert_eq!(chunks.len(), 3);
+        assert!(chunks.iter().all(ChunkAssembler::is_chunk));
+
+        let mut assembler = ChunkAssembler::new(1024);
+        let mut assembled = None;
+        for chunk in chunks {
+            assembled = assembler.accept(chunk);
+        }
+        let assembled = assembled.unwrap();
+        assert_eq!(assembled.opaque(), 7);
+        assert_eq!(assembled.remark().unwrap().as_str(), "ok");
+        assert_eq!(assembled.get_body().unwrap().as_ref(), b"0123456789");
+        assert!(!assembled
+            .get_ext_fields()
+            .unwrap()
+            .contains_key(CHUNK_INDEX));
+        assert_eq!(assembler.pending_bytes(), 0);
+    }
+
+    #[test]
+    fn split_keeps_single_frame_without_opt_in() {
+        let mut chunker = ResponseChunker::new(4);
+        let frames = chunker.split(response(8, b"0123456789"));
+        assert_eq!(frames.len(), 1);
+        assert!(!ChunkAssembler::is_chunk(&frames[0]));
+    }
+
+    #[test]
+    fn accept_rejects_corrupted_and_oversized_chunks() {
+        let mut chunker = ResponseChunker::new(4);
+        chunker.on_request(&request(9));
+        let mut chunks = chunker.split(response(9, b"0123456789"));
+        let corrupted = chunks.remove(1).set_body(Bytes::from_static(b"xxxx"));
+
+        let mut assembler = ChunkAssembler::new(1024);
+        assert!(assembler.accept(chunks.remove(0)).is_none());
+        let failed = assembler.accept(corrupted).unwrap();
+        assert_eq!(failed.code(), ResponseCode::SystemError as i32);
+        assert_eq!(assembler.pending_bytes(), 0);
+
+        chunker.on_request(&request(10));
+        let chunks = chunker.split(response(10, b"0123456789"));
+        let mut assembler = ChunkAssembler::new(6);
+        let results: Vec<_> = chunks
+            .into_iter()
+            .filter_map(|chunk| assembler.accept(chunk))
+            .collect();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].code(), ResponseCode::SystemError as i32);
+    }
+}
@@ -20,6 +20,10 @@ use bytes::BytesMut;
 use tokio_util::codec::Decoder;
 use tokio_util::codec::Encoder;
 
+use crate::codec::chunked_response::ChunkAssembler;
+use crate::codec::chunked_response::ResponseChunker;
+use crate::codec::chunked_response::DEFAULT_CHUNK_SIZE;
+use crate::codec::chunked_response::DEFAULT_MAX_REASSEMBLY_BYTES;
 use crate::protocol::remoting_command::RemotingCommand;
 use crate::remoting_error::RemotingError;
 
@@ -43,8 +47,11 @@ use crate::remoting_error::RemotingError;
 /// # Errors
 ///
 /// This function will return an error if the encoding process fails.
-#[derive(Debug, Clone)]
-pub struct RemotingCommandCodec;
+#[derive(Clone)]
+pub struct RemotingCommandCodec {
+    chunker: ResponseChunker,
+    assembler: ChunkAssembler,
+}
 
 impl Default for RemotingCommandCodec {
     fn default() -> Self {
@@ -54,7 +61,21 @@ impl Default for RemotingCommandCodec {
 
 impl RemotingCommandCodec {
     pub fn new() -> Self {
-        Self {}
+        Self::with_chunking(DEFAULT_CHUNK_SIZE, DEFAULT_MAX_REASSEMBLY_BYTES)
+    }
+
+    /// Creates a codec that splits responses larger than `chunk_size` for peers that accept
+    /// chunked responses, and buffers at most `max_reassembly_bytes` of incoming chunks.
+    pub fn with_chunking(chunk_size: usize, max_reassembly_bytes: usize) -> Self {
+        Self {
+            chunker: ResponseChunker::new(chunk_size),
+            assembler: ChunkAssembler::new(max_reassembly_bytes),
+        }
+    }
+
+    /// Called for a response written to the socket directly instead of through the encoder.
+    pub fn forget_chunked_response(&mut self, opaque: i32) {
+        self.chunker.forget(opaque);
     }
 }
 
@@ -89,7 +110,21 @@ impl Decoder for RemotingCommandCodec {
     ///
     /// This function will return an error if the decoding process fails.
     fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
-        RemotingCommand::decode(src)
+        loop {
+            let Some(cmd) = RemotingCommand::decode(src)? else {
+                return Ok(None);
+            };
+            if ChunkAssembler::is_chunk(&cmd) {
+                match self.assembler.accept(cmd) {
+                    Some(response) => return Ok(Some(response)),
+                    None => continue,
+                }
+            }
+            if !cmd.is_response_type() {
+                self.chunker.on_request(&cmd);
+            }
+            return Ok(Some(cmd));
+        }
         /* let read_to = src.len();
         if read_to < 4 {
             // Wait for more data when there are less than 4 bytes.
@@ -160,10 +195,20 @@ impl Encoder<RemotingCommand> for RemotingCommandCodec {
     ///
     /// This function will return an error if the encoding process fails.
     fn encode(&mut self, item: RemotingCommand, dst: &mut BytesMut) -> Result<(), Self::Error> {
-        let mut item = item;
-        item.fast_header_encode(dst);
-        if let Some(body_inner) = item.get_body() {
-            dst.put(body_inner.as_ref());
+        let frames = if item.is_response_type() {
+            self.chunker.split(item)
+        } else {
+            vec![item]
+        };
+        for mut frame in frames {
+            frame.fast_header_encode(dst);
+            if let Some(body_inner) = frame.get_body() {
+                dst.put(body_inner.as_ref());
+            } else if let Some(transfer) = frame.body_transfer() {
+                for region in transfer.regions() {
+                    dst.put(region.as_ref());
+                }
+            }
         }
         Ok(())
     }
@@ -174,6 +219,7 @@ mod tests {
     use bytes::Bytes;
 
     use super::*;
+    use crate::net::body_transfer::BodyTransfer;
     use crate::protocol::header::client_request_header::GetRouteInfoRequestHeader;
     use crate::protocol::LanguageCode;
 
@@ -219,4 +265,33 @@ mod tests {
             .set_remark_option(Some("remark".to_string()));
         assert!(encoder.encode(command, &mut dst).is_ok());
     }
+
+    #[tokio::test]
+    async fn encode_writes_body_transfer_like_a_plain_body() {
+        let response = || {
+            RemotingCommand::create_response_command()
+                .set_opaque(7)
+                .set_remark_option(Some("remark".to_string()))
+        };
+        let mut body_transfer = BodyTransfer::new();
+        body_transfer.push(Bytes::from_static(b"hello"));
+        body_transfer.push(Bytes::from_static(b" world"));
+        let mut transferred = response();
+        transferred.set_body_transfer_mut_ref(body_transfer);
+
+        let mut encoder = RemotingCommandCodec::new();
+        let mut expected = BytesMut::new();
+        let mut dst = BytesMut::new();
+        encoder
+            .encode(
+                response().set_body(Bytes::from_static(b"hello world")),
+                &mut expected,
+            )
+            .unwrap();
+        encoder.encode(transferred, &mut dst).unwrap();
+        assert_eq!(dst, expected);
+
+        let decoded = encoder.decode(&mut dst).unwrap().unwrap();
+        assert_eq!(decoded.get_body().unwrap().as_ref(), b"hello world");
+    }
 }
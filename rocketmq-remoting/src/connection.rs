@@ -17,14 +17,17 @@
 use std::hash::Hash;
 use std::hash::Hasher;
 
-use futures_util::stream::SplitSink;
-use futures_util::stream::SplitStream;
-use futures_util::StreamExt;
+use bytes::BytesMut;
+use futures_util::SinkExt;
+use tokio::net::tcp::OwnedReadHalf;
+use tokio::net::tcp::OwnedWriteHalf;
 use tokio::net::TcpStream;
-use tokio_util::codec::Framed;
+use tokio_util::codec::FramedRead;
+use tokio_util::codec::FramedWrite;
 
 use crate::codec::remoting_command_codec::RemotingCommandCodec;
 use crate::protocol::remoting_command::RemotingCommand;
+use crate::remoting_error::RemotingError;
 
 /// Send and receive `Frame` values from a remote peer.
 ///
@@ -37,13 +40,14 @@ use crate::protocol::remoting_command::RemotingCommand;
 /// the `Connection` creates the frame and returns it to the caller.
 ///
 /// When sending frames, the frame is first encoded into the write buffer.
-/// The contents of the write buffer are then written to the socket.
+/// The contents of the write buffer are then written to the socket. A body transfer
+/// is written after its header straight from its regions, see [`Connection::send`].
 pub struct Connection {
-    /// The `Framed` instance used for reading from and writing to the TCP stream.
-    /// It leverages the `RemotingCommandCodec` for encoding and decoding frames.
+    /// The framed halves of the TCP stream, both leverage a `RemotingCommandCodec`
+    /// for encoding and decoding frames and share its chunking state.
     //pub(crate) framed: Framed<TcpStream, RemotingCommandCodec>,
-    pub(crate) writer: SplitSink<Framed<TcpStream, RemotingCommandCodec>, RemotingCommand>,
-    pub(crate) reader: SplitStream<Framed<TcpStream, RemotingCommandCodec>>,
+    pub(crate) writer: FramedWrite<OwnedWriteHalf, RemotingCommandCodec>,
+    pub(crate) reader: FramedRead<OwnedReadHalf, RemotingCommandCodec>,
 
     /// A boolean flag indicating the current state of the connection.
     /// `true` means the connection is in a good state, while `false` indicates
@@ -58,13 +62,10 @@ impl Hash for Connection {
 
         // Use the addr: *const _ess of writer and reader to hash them (they serve as a unique
         // identifier for these components)
-        let writer_addr: *const SplitSink<
-            Framed<TcpStream, RemotingCommandCodec>,
-            RemotingCommand,
-        > = &self.writer
-            as *const SplitSink<Framed<TcpStream, RemotingCommandCodec>, RemotingCommand>;
-        let reader_addr: *const SplitStream<Framed<TcpStream, RemotingCommandCodec>> =
-            &self.reader as *const SplitStream<Framed<TcpStream, RemotingCommandCodec>>;
+        let writer_addr: *const FramedWrite<OwnedWriteHalf, RemotingCommandCodec> =
+            &self.writer as *const FramedWrite<OwnedWriteHalf, RemotingCommandCodec>;
+        let reader_addr: *const FramedRead<OwnedReadHalf, RemotingCommandCodec> =
+            &self.reader as *const FramedRead<OwnedReadHalf, RemotingCommandCodec>;
 
         writer_addr.hash(state);
         reader_addr.hash(state);
@@ -95,25 +96,46 @@ impl Connection {
     ///
     /// A new `Connection` instance.
     pub fn new(tcp_stream: TcpStream) -> Connection {
-        let framed = Framed::with_capacity(tcp_stream, RemotingCommandCodec::new(), 1024 * 4);
-        let (writer, reader) = framed.split();
+        let codec = RemotingCommandCodec::new();
+        let (read_half, write_half) = tcp_stream.into_split();
         Self {
-            writer,
-            reader,
+            writer: FramedWrite::new(write_half, codec.clone()),
+            reader: FramedRead::with_capacity(read_half, codec, 1024 * 4),
             ok: true,
         }
     }
+
+    /// Writes `command` to the peer. A command carrying a body transfer is not chunked, its
+    /// header is encoded on its own and written together with the regions using vectored
+    /// writes, so the regions are never copied into the write buffer.
+    pub async fn send(&mut self, mut command: RemotingCommand) -> Result<(), RemotingError> {
+        if command.get_body().is_some() || command.body_transfer().is_none() {
+            return self.writer.send(command).await;
+        }
+        self.writer
+            .encoder_mut()
+            .forget_chunked_response(command.opaque());
+        let mut header = BytesMut::new();
+        command.fast_header_encode(&mut header);
+        let body_transfer = command.take_body_transfer().unwrap_or_default();
+        // frames still in the write buffer go first
+        self.writer.flush().await?;
+        body_transfer
+            .write_to(header.freeze(), self.writer.get_mut())
+            .await?;
+        Ok(())
+    }
 }
 
 impl Connection {
     /*pub fn framed(&self) -> &Framed<TcpStream, RemotingCommandCodec> {
         &self.framed
     }*/
-    pub fn reader(&self) -> &SplitStream<Framed<TcpStream, RemotingCommandCodec>> {
+    pub fn reader(&self) -> &FramedRead<OwnedReadHalf, RemotingCommandCodec> {
         &self.reader
     }
 
-    pub fn writer(&self) -> &SplitSink<Framed<TcpStream, RemotingCommandCodec>, RemotingCommand> {
+    pub fn writer(&self) -> &FramedWrite<OwnedWriteHalf, RemotingCommandCodec> {
         &self.writer
     }
 }
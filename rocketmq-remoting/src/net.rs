@@ -15,4 +15,5 @@
  * limitations under the License.
  */
 
+pub mod body_transfer;
 pub mod channel;
@@ -0,0 +1,128 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::VecDeque;
+use std::io;
+use std::io::IoSlice;
+
+use bytes::Buf;
+use bytes::Bytes;
+use tokio::io::AsyncWrite;
+use tokio::io::AsyncWriteExt;
+
+/// At most this many buffers are handed to one vectored write, well below `IOV_MAX`.
+const MAX_IO_SLICES: usize = 64;
+
+/// A response body made of regions that are written to the socket as they are, e.g. messages
+/// still living in the mapped commit log files, instead of being copied into one buffer first.
+///
+/// The regions are written with vectored writes, so for mapped files the kernel copies straight
+/// out of the page cache. Connections are plain TCP, a TLS layer would have to encrypt the
+/// regions in user space and could write them through the encoder instead.
+#[derive(Clone, Default)]
+pub struct BodyTransfer {
+    regions: Vec<Bytes>,
+    len: usize,
+}
+
+impl BodyTransfer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, region: Bytes) {
+        self.len += region.len();
+        self.regions.push(region);
+    }
+
+    /// The total length of the regions.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn regions(&self) -> &[Bytes] {
+        &self.regions
+    }
+
+    /// Writes `header` followed by the regions to `writer` and flushes it.
+    pub async fn write_to<W>(&self, header: Bytes, writer: &mut W) -> io::Result<()>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        let mut pending = VecDeque::with_capacity(self.regions.len() + 1);
+        pending.push_back(header);
+        pending.extend(self.regions.iter().cloned());
+        pending.retain(|buffer| !buffer.is_empty());
+        while !pending.is_empty() {
+            let mut written = {
+                let slices = pending
+                    .iter()
+                    .take(MAX_IO_SLICES)
+                    .map(|buffer| IoSlice::new(buffer))
+                    .collect::<Vec<_>>();
+                writer.write_vectored(&slices).await?
+            };
+            if written == 0 {
+                return Err(io::ErrorKind::WriteZero.into());
+            }
+            while written > 0 {
+                let front = pending.front_mut().unwrap();
+                if written < front.len() {
+                    front.advance(written);
+                    break;
+                }
+                written -= front.len();
+                pending.pop_front();
+            }
+        }
+        writer.flush().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn write_to_writes_header_and_regions_in_order() {
+        let mut transfer = BodyTransfer::new();
+        transfer.push(Bytes::from_static(b"hello"));
+        transfer.push(Bytes::new());
+        transfer.push(Bytes::from_static(b" world"));
+        assert_eq!(transfer.len(), 11);
+
+        // a duplex pipe smaller than the body forces partial vectored writes
+        let (mut client, mut server) = tokio::io::duplex(4);
+        let reader = tokio::spawn(async move {
+            let mut received = Vec::new();
+            tokio::io::AsyncReadExt::read_to_end(&mut server, &mut received)
+                .await
+                .unwrap();
+            received
+        });
+        transfer
+            .write_to(Bytes::from_static(b"head:"), &mut client)
+            .await
+            .unwrap();
+        drop(client);
+        assert_eq!(reader.await.unwrap(), b"head:hello world");
+    }
+}
@@ -30,14 +30,18 @@ pub mod cluster_acl_version_info;
 pub mod cm_result;
 pub mod connection;
 pub mod consume_message_directly_result;
+pub mod end_transaction_batch_body;
+pub mod examine_consume_queue_body;
 pub mod group_list;
 pub mod kv_table;
+pub mod orphaned_transaction_body;
 pub mod pop_process_queue_info;
 pub mod process_queue_info;
 pub mod producer_connection;
 pub mod query_assignment_request_body;
 pub mod query_assignment_response_body;
 pub mod queue_time_span;
+pub mod replay_subscription_body;
 pub mod request;
 pub mod response;
 pub mod set_message_request_mode_request_body;
@@ -0,0 +1,61 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use cheetah_string::CheetahString;
+use serde::Deserialize;
+use serde::Serialize;
+
+/// Raw consume queue units of one queue, as dispatched from the commit log.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExamineConsumeQueueBody {
+    pub topic: CheetahString,
+    pub queue_id: i32,
+    pub min_offset: i64,
+    pub max_offset: i64,
+    pub units: Vec<ConsumeQueueUnitData>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConsumeQueueUnitData {
+    pub queue_offset: i64,
+    pub phy_offset: i64,
+    pub size: i32,
+    pub tags_code: i64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deserialize_examine_consume_queue_body() {
+        let json = r#"{"topic":"TopicA","queueId":1,"minOffset":0,"maxOffset":2,"units":[{"queueOffset":0,"phyOffset":128,"size":200,"tagsCode":42}]}"#;
+        let body: ExamineConsumeQueueBody = serde_json::from_str(json).unwrap();
+        assert_eq!(body.queue_id, 1);
+        assert_eq!(body.max_offset, 2);
+        assert_eq!(
+            body.units,
+            vec![ConsumeQueueUnitData {
+                queue_offset: 0,
+                phy_offset: 128,
+                size: 200,
+                tags_code: 42,
+            }]
+        );
+    }
+}
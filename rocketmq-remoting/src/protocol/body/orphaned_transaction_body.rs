@@ -0,0 +1,50 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use cheetah_string::CheetahString;
+use serde::Deserialize;
+use serde::Serialize;
+
+/// Half messages the transaction check gave up on after `transactionCheckMax` checks, still
+/// waiting for an operator to commit or roll them back.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct OrphanedTransactionBody {
+    pub transactions: Vec<OrphanedTransactionInfo>,
+    /// Half messages discarded since the broker started.
+    pub discarded_total: u64,
+    /// Orphaned half messages committed or rolled back since the broker started.
+    pub resolved_total: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct OrphanedTransactionInfo {
+    /// Topic and queue the message is restored to on commit.
+    pub topic: CheetahString,
+    pub queue_id: i32,
+    pub producer_group: CheetahString,
+    pub transaction_id: CheetahString,
+    pub msg_id: CheetahString,
+    /// Commit log offset of the latest copy of the half message.
+    pub commit_log_offset: i64,
+    /// Offset of that copy in the half queue.
+    pub tran_state_table_offset: i64,
+    pub check_times: i32,
+    pub born_timestamp: i64,
+    pub discard_timestamp: i64,
+}
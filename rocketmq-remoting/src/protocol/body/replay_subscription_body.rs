@@ -0,0 +1,62 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::HashMap;
+
+use cheetah_string::CheetahString;
+use serde::Deserialize;
+use serde::Serialize;
+
+/// Asks a broker for a temporary consumer group positioned either at `start_timestamp` or at
+/// explicit `start_offsets`, which the broker drops again after `ttl_millis`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateReplaySubscriptionRequestBody {
+    /// Generated by the broker when absent.
+    pub consumer_group: Option<CheetahString>,
+    pub topic: CheetahString,
+    pub start_timestamp: Option<i64>,
+    pub start_offsets: Option<HashMap<i32, i64>>,
+    /// Exclusive upper bound per queue, pulls stop there when set.
+    pub end_offsets: Option<HashMap<i32, i64>>,
+    pub ttl_millis: i64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplaySubscription {
+    pub consumer_group: CheetahString,
+    pub topic: CheetahString,
+    pub start_offsets: HashMap<i32, i64>,
+    pub end_offsets: HashMap<i32, i64>,
+    pub expire_at_millis: i64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn deserialize_create_replay_subscription_request_body() {
+        let json = r#"{"topic":"TopicA","startTimestamp":1700000000000,"ttlMillis":60000}"#;
+        let body: CreateReplaySubscriptionRequestBody = serde_json::from_str(json).unwrap();
+        assert_eq!(body.topic, CheetahString::from("TopicA"));
+        assert_eq!(body.start_timestamp, Some(1700000000000));
+        assert!(body.consumer_group.is_none());
+        assert!(body.start_offsets.is_none());
+        assert_eq!(body.ttl_millis, 60000);
+    }
+}
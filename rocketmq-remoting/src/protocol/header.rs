@@ -17,6 +17,7 @@
 pub mod broker;
 pub mod change_invisible_time_request_header;
 pub mod change_invisible_time_response_header;
+pub mod check_confirm_offset_response_header;
 pub mod check_transaction_state_request_header;
 pub mod client_request_header;
 pub mod consume_message_directly_result_request_header;
@@ -24,7 +25,9 @@ pub mod consumer_send_msg_back_request_header;
 pub mod create_topic_request_header;
 pub mod delete_subscription_group_request_header;
 pub mod delete_topic_request_header;
+pub mod end_transaction_batch_request_header;
 pub mod end_transaction_request_header;
+pub mod examine_consume_queue_request_header;
 pub mod extra_info_util;
 pub mod get_all_topic_config_response_header;
 pub mod get_consume_stats_request_header;
@@ -57,6 +60,7 @@ pub mod query_topic_consume_by_who_request_header;
 pub mod query_topics_by_consumer_request_header;
 pub mod reply_message_request_header;
 pub mod reset_offset_request_header;
+pub mod resolve_orphaned_transaction_request_header;
 pub mod search_offset_response_header;
 pub mod unlock_batch_mq_request_header;
 pub mod unregister_client_request_header;
@@ -32,6 +32,25 @@ pub struct GetRouteInfoRequestHeader {
 
     #[serde(flatten)]
     pub topic_request_header: Option<TopicRequestHeader>,
+
+    /// The client can decompress a compressed route body.
+    #[serde(rename = "acceptCompressed")]
+    pub accept_compressed: Option<bool>,
+
+    /// Data version of the route the client already has, answered with
+    /// `ResponseCode::NotModified` when the route did not change since.
+    #[serde(rename = "dataVersion")]
+    pub data_version: Option<i64>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Default, RequestHeaderCodec)]
+#[serde(rename_all = "camelCase")]
+pub struct GetRouteInfoResponseHeader {
+    /// Checksum of the encoded route, the client sends it back in the next request.
+    pub data_version: i64,
+    /// Compression type flag, see `MessageSysFlag::COMPRESSION_TYPE_COMPARATOR`, of a
+    /// compressed body.
+    pub body_compression_type: Option<i32>,
 }
 
 impl GetRouteInfoRequestHeader {
@@ -56,6 +75,7 @@ mod tests {
             topic: CheetahString::from("testTopic"),
             accept_standard_json_only: Some(true),
             topic_request_header: None,
+            ..Default::default()
         };
         assert_eq!(header.topic, CheetahString::from("testTopic"));
         assert_eq!(header.accept_standard_json_only, Some(true));
@@ -68,6 +88,7 @@ mod tests {
             topic: CheetahString::from("testTopic"),
             accept_standard_json_only: None,
             topic_request_header: Some(TopicRequestHeader::default()),
+            ..Default::default()
         };
         assert_eq!(header.topic, CheetahString::from("testTopic"));
         assert!(header.accept_standard_json_only.is_none());
@@ -80,6 +101,7 @@ mod tests {
             topic: CheetahString::from(""),
             accept_standard_json_only: Some(false),
             topic_request_header: None,
+            ..Default::default()
         };
         assert_eq!(header.topic, CheetahString::from(""));
         assert_eq!(header.accept_standard_json_only, Some(false));
@@ -93,6 +115,7 @@ mod tests {
             topic: CheetahString::from(&long_topic),
             accept_standard_json_only: Some(true),
             topic_request_header: None,
+            ..Default::default()
         };
         assert_eq!(header.topic, CheetahString::from(&long_topic));
         assert_eq!(header.accept_standard_json_only, Some(true));
@@ -62,6 +62,10 @@ pub struct RegisterBrokerRequestHeader {
     /// The CRC32 checksum for the message body.
     #[serde(rename = "bodyCrc32")]
     pub body_crc32: u32,
+
+    /// The optional bitmap of wire features supported by the broker.
+    #[serde(rename = "featureBitmap")]
+    pub feature_bitmap: Option<u64>,
 }
 
 impl RegisterBrokerRequestHeader {
@@ -72,6 +76,7 @@ impl RegisterBrokerRequestHeader {
     const CLUSTER_NAME: &'static str = "clusterName";
     const COMPRESSED: &'static str = "compressed";
     const ENABLE_ACTING_MASTER: &'static str = "enableActingMaster";
+    const FEATURE_BITMAP: &'static str = "featureBitmap";
     const HA_SERVER_ADDR: &'static str = "haServerAddr";
     const HEARTBEAT_TIMEOUT_MILLIS: &'static str = "heartbeatTimeoutMillis";
 
@@ -89,6 +94,7 @@ impl RegisterBrokerRequestHeader {
     ///   enabled.
     /// * `compressed` - Indicates whether the data is compressed.
     /// * `body_crc32` - The CRC32 checksum for the message body.
+    /// * `feature_bitmap` - The optional bitmap of wire features supported by the broker.
     ///
     /// # Returns
     ///
@@ -103,6 +109,7 @@ impl RegisterBrokerRequestHeader {
         enable_acting_master: Option<bool>,
         compressed: bool,
         body_crc32: u32,
+        feature_bitmap: Option<u64>,
     ) -> Self {
         RegisterBrokerRequestHeader {
             broker_name,
@@ -114,6 +121,7 @@ impl RegisterBrokerRequestHeader {
             enable_acting_master,
             compressed,
             body_crc32,
+            feature_bitmap,
         }
     }
 }
@@ -177,6 +185,11 @@ impl FromMap for RegisterBrokerRequestHeader {
                 ))
                 .and_then(|s| s.parse::<u32>().ok())
                 .unwrap_or(0),
+            feature_bitmap: map
+                .get(&CheetahString::from_static_str(
+                    RegisterBrokerRequestHeader::FEATURE_BITMAP,
+                ))
+                .and_then(|s| s.parse::<u64>().ok()),
         })
     }
 }
@@ -235,6 +248,13 @@ impl CommandCustomHeader for RegisterBrokerRequestHeader {
             CheetahString::from_string(self.body_crc32.to_string()),
         );
 
+        if let Some(feature_bitmap) = self.feature_bitmap {
+            map.insert(
+                CheetahString::from_static_str(RegisterBrokerRequestHeader::FEATURE_BITMAP),
+                CheetahString::from_string(feature_bitmap.to_string()),
+            );
+        }
+
         Some(map)
     }
 }
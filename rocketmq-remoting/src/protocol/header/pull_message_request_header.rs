@@ -54,6 +54,9 @@ pub struct PullMessageRequestHeader {
     pub max_msg_bytes: Option<i32>,
     pub request_source: Option<i32>,
     pub proxy_forward_client_id: Option<CheetahString>,
+    /// Comma separated names of the user properties the consumer reads, the broker strips the
+    /// others from the pulled messages. System properties are always kept.
+    pub projected_properties: Option<CheetahString>,
     #[serde(flatten)]
     pub topic_request: Option<TopicRequestHeader>,
 }
@@ -65,6 +68,7 @@ impl PullMessageRequestHeader {
     const MAX_MSG_BYTES: &'static str = "maxMsgBytes";
     const MAX_MSG_NUMS: &'static str = "maxMsgNums";
     const PROXY_FORWARD_CLIENT_ID: &'static str = "proxyForwardClientId";
+    const PROJECTED_PROPERTIES: &'static str = "projectedProperties";
     const QUEUE_ID: &'static str = "queueId";
     const QUEUE_OFFSET: &'static str = "queueOffset";
     const REQUEST_SOURCE: &'static str = "requestSource";
@@ -151,6 +155,12 @@ impl CommandCustomHeader for PullMessageRequestHeader {
                 value.clone(),
             );
         }
+        if let Some(ref value) = self.projected_properties {
+            map.insert(
+                CheetahString::from_static_str(Self::PROJECTED_PROPERTIES),
+                value.clone(),
+            );
+        }
 
         if let Some(ref rpc) = self.topic_request {
             if let Some(rpc_map) = rpc.to_map() {
@@ -209,6 +219,9 @@ impl CommandCustomHeader for PullMessageRequestHeader {
         if let Some(ref value) = self.proxy_forward_client_id {
             self.write_if_not_null(out, Self::PROXY_FORWARD_CLIENT_ID, value.as_str());
         }
+        if let Some(ref value) = self.projected_properties {
+            self.write_if_not_null(out, Self::PROJECTED_PROPERTIES, value.as_str());
+        }
 
         // Assuming "lo", "ns", "nsd", "bname", "oway" are other fields in the struct
         if let Some(ref value) = self.topic_request {
@@ -311,6 +324,10 @@ impl CommandCustomHeader for PullMessageRequestHeader {
             ))
             .cloned();
 
+        self.projected_properties = fields
+            .get(&CheetahString::from_static_str(Self::PROJECTED_PROPERTIES))
+            .cloned();
+
         self.topic_request = Some(TopicRequestHeader {
             rpc: Some(RpcRequestHeader::default()),
             ..TopicRequestHeader::default()
@@ -437,6 +454,9 @@ impl FromMap for PullMessageRequestHeader {
                     Self::PROXY_FORWARD_CLIENT_ID,
                 ))
                 .cloned(),
+            projected_properties: map
+                .get(&CheetahString::from_static_str(Self::PROJECTED_PROPERTIES))
+                .cloned(),
             topic_request: Some(<TopicRequestHeader as FromMap>::from(map)?),
         })
     }
@@ -575,6 +595,7 @@ mod tests {
             max_msg_bytes: Some(1024),
             request_source: Some(1),
             proxy_forward_client_id: Some(CheetahString::from_static_str("test_client_id")),
+            projected_properties: Some(CheetahString::from_static_str("a,b")),
             topic_request: None,
         };
         let map = header.to_map().unwrap();
@@ -645,6 +666,11 @@ mod tests {
                 .unwrap(),
             "test_client_id"
         );
+        assert_eq!(
+            map.get(&CheetahString::from_static_str("projectedProperties"))
+                .unwrap(),
+            "a,b"
+        );
     }
 
     #[test]
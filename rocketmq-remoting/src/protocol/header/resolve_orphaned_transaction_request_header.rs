@@ -0,0 +1,32 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use cheetah_string::CheetahString;
+use rocketmq_macros::RequestHeaderCodec;
+use serde::Deserialize;
+use serde::Serialize;
+
+/// Commits or rolls back one orphaned half message, picked by its commit log offset or, when
+/// the offset is absent, by its transaction id.
+#[derive(Debug, Clone, Deserialize, Serialize, Default, RequestHeaderCodec)]
+#[serde(rename_all = "camelCase")]
+pub struct ResolveOrphanedTransactionRequestHeader {
+    pub commit_log_offset: Option<i64>,
+    pub transaction_id: Option<CheetahString>,
+    /// `MessageSysFlag::TRANSACTION_COMMIT_TYPE` or `TRANSACTION_ROLLBACK_TYPE`.
+    pub commit_or_rollback: i32,
+}
@@ -14,7 +14,10 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
+use std::collections::hash_map::DefaultHasher;
 use std::collections::HashSet;
+use std::hash::Hash;
+use std::hash::Hasher;
 
 use cheetah_string::CheetahString;
 use serde::Deserialize;
@@ -37,6 +40,42 @@ pub struct HeartbeatData {
     #[serde(rename = "withoutSub", default)]
     pub is_without_sub: bool,
 }
+
+impl HeartbeatData {
+    /// Fingerprint of the consumer subscriptions carried by this heartbeat.
+    ///
+    /// The value is independent of set iteration order, of the client id and of the
+    /// subscription versions, so all clients of a group with the same subscriptions send the same
+    /// fingerprint. It is never `0`, which marks a heartbeat without fingerprint.
+    pub fn compute_heartbeat_fingerprint(&self) -> i32 {
+        let mut fingerprint = 0u64;
+        for consumer_data in self.consumer_data_set.iter() {
+            let mut hasher = DefaultHasher::new();
+            consumer_data.group_name.hash(&mut hasher);
+            consumer_data.consume_type.hash(&mut hasher);
+            consumer_data.message_model.hash(&mut hasher);
+            consumer_data.consume_from_where.hash(&mut hasher);
+            consumer_data.unit_mode.hash(&mut hasher);
+            let mut subscriptions = 0u64;
+            for subscription_data in consumer_data.subscription_data_set.iter() {
+                // tags and codes are derived from the sub string, and their sets iterate in
+                // arbitrary order
+                let mut hasher = DefaultHasher::new();
+                subscription_data.class_filter_mode.hash(&mut hasher);
+                subscription_data.topic.hash(&mut hasher);
+                subscription_data.sub_string.hash(&mut hasher);
+                subscription_data.expression_type.hash(&mut hasher);
+                subscriptions = subscriptions.wrapping_add(hasher.finish());
+            }
+            subscriptions.hash(&mut hasher);
+            fingerprint = fingerprint.wrapping_add(hasher.finish());
+        }
+        match (fingerprint ^ (fingerprint >> 32)) as i32 {
+            0 => 1,
+            fingerprint => fingerprint,
+        }
+    }
+}
 #[cfg(test)]
 mod tests {
     use std::collections::HashSet;
@@ -44,6 +83,7 @@ mod tests {
     use rocketmq_common::utils::serde_json_utils::SerdeJsonUtils;
 
     use super::*;
+    use crate::protocol::heartbeat::subscription_data::SubscriptionData;
     use crate::protocol::RemotingSerializable;
 
     #[test]
@@ -83,6 +123,36 @@ mod tests {
         assert_eq!(original, deserialized);
     }
 
+    #[test]
+    fn heartbeat_fingerprint_ignores_client_and_tracks_subscriptions() {
+        let subscription = |topic: &str| SubscriptionData {
+            topic: topic.into(),
+            sub_string: "*".into(),
+            ..Default::default()
+        };
+        let consumer_data = |topics: &[&str]| ConsumerData {
+            group_name: "group".into(),
+            subscription_data_set: topics.iter().map(|topic| subscription(topic)).collect(),
+            ..Default::default()
+        };
+        let heartbeat = |client_id: &str, topics: &[&str]| HeartbeatData {
+            client_id: client_id.into(),
+            consumer_data_set: HashSet::from([consumer_data(topics)]),
+            ..Default::default()
+        };
+
+        let fingerprint = heartbeat("client1", &["a", "b"]).compute_heartbeat_fingerprint();
+        assert_ne!(fingerprint, 0);
+        assert_eq!(
+            fingerprint,
+            heartbeat("client2", &["b", "a"]).compute_heartbeat_fingerprint()
+        );
+        assert_ne!(
+            fingerprint,
+            heartbeat("client1", &["a", "c"]).compute_heartbeat_fingerprint()
+        );
+    }
+
     #[test]
     fn heartbeat_data_with_empty_sets_serialization_deserialization() {
         let original = HeartbeatData {
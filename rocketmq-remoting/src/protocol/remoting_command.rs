@@ -40,6 +40,8 @@ use tracing::error;
 use super::RemotingCommandType;
 use super::SerializeType;
 use crate::code::response_code::RemotingSysResponseCode;
+use crate::codec::chunked_response::ACCEPT_CHUNKED_RESPONSE;
+use crate::net::body_transfer::BodyTransfer;
 use crate::protocol::command_custom_header::CommandCustomHeader;
 use crate::protocol::command_custom_header::FromMap;
 use crate::protocol::LanguageCode;
@@ -110,6 +112,8 @@ pub struct RemotingCommand {
     #[serde(skip)]
     body: Option<Bytes>,
     #[serde(skip)]
+    body_transfer: Option<BodyTransfer>,
+    #[serde(skip)]
     suspended: bool,
     #[serde(skip)]
     command_custom_header: Option<ArcMut<Box<dyn CommandCustomHeader + Send + Sync + 'static>>>,
@@ -128,6 +132,7 @@ impl Clone for RemotingCommand {
             remark: self.remark.clone(),
             ext_fields: self.ext_fields.clone(),
             body: self.body.clone(),
+            body_transfer: self.body_transfer.clone(),
             suspended: self.suspended,
             command_custom_header: self.command_custom_header.clone(),
             serialize_type: self.serialize_type,
@@ -165,6 +170,7 @@ impl Default for RemotingCommand {
             remark: None,
             ext_fields: None,
             body: None,
+            body_transfer: None,
             suspended: false,
             command_custom_header: None,
             serialize_type: *SERIALIZE_TYPE_CONFIG_IN_THIS_SERVER,
@@ -336,6 +342,12 @@ impl RemotingCommand {
         self.body = Some(body.into());
     }
 
+    /// Sets a body written region by region after the header instead of as one buffer, it is
+    /// only used when no plain body is set.
+    pub fn set_body_transfer_mut_ref(&mut self, body_transfer: BodyTransfer) {
+        self.body_transfer = Some(body_transfer);
+    }
+
     pub fn set_suspended(mut self, suspended: bool) -> Self {
         self.suspended = suspended;
         self
@@ -371,6 +383,15 @@ impl RemotingCommand {
         self.flag |= mark;
     }
 
+    /// Lets the peer stream a large response back in checksummed chunks, which the codec
+    /// reassembles before the response reaches the caller.
+    pub fn mark_accept_chunked_response(mut self) -> Self {
+        self.ext_fields
+            .get_or_insert_with(HashMap::new)
+            .insert(ACCEPT_CHUNKED_RESPONSE.into(), "true".into());
+        self
+    }
+
     pub fn get_serialize_type(&self) -> SerializeType {
         self.serialize_type
     }
@@ -415,7 +436,7 @@ impl RemotingCommand {
                     }
                 };
                 let header_length = header.as_ref().map_or(0, |h| h.len()) as i32;
-                let body_length = self.body.as_ref().map_or(0, |b| b.len()) as i32;
+                let body_length = self.body_length() as i32;
                 let total_length = 4 + header_length + body_length;
 
                 dst.reserve((total_length + 4) as usize);
@@ -437,7 +458,7 @@ impl RemotingCommand {
                     }
                 }
                 let header_size = RocketMQSerializable::rocketmq_protocol_encode(self, dst);
-                let body_length = self.body.as_ref().map_or(0, |b| b.len()) as i32;
+                let body_length = self.body_length() as i32;
                 let serialize_type = RemotingCommand::mark_serialize_type(
                     header_size as i32,
                     SerializeType::ROCKETMQ,
@@ -527,6 +548,22 @@ impl RemotingCommand {
         self.body.as_mut()
     }
 
+    pub fn body_transfer(&self) -> Option<&BodyTransfer> {
+        self.body_transfer.as_ref()
+    }
+
+    pub fn take_body_transfer(&mut self) -> Option<BodyTransfer> {
+        self.body_transfer.take()
+    }
+
+    fn body_length(&self) -> usize {
+        match (self.body.as_ref(), self.body_transfer.as_ref()) {
+            (Some(body), _) => body.len(),
+            (None, Some(body_transfer)) => body_transfer.len(),
+            (None, None) => 0,
+        }
+    }
+
     pub fn mark_serialize_type(header_length: i32, protocol_type: SerializeType) -> i32 {
         (protocol_type.get_code() as i32) << 24 | (header_length & 0x00FFFFFF)
     }
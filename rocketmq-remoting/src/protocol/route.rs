@@ -14,5 +14,6 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
+pub mod broker_feature;
 pub mod route_data_view;
 pub mod topic_route_data;
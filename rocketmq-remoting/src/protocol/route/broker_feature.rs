@@ -0,0 +1,85 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+/// Wire features a broker reports to the name server in its feature bitmap.
+///
+/// A feature is only used on a path once every broker on it reported the feature, so a
+/// cluster being rolled to a new version keeps talking the old protocol until the upgrade is
+/// complete. Brokers that do not report a bitmap support none of these features.
+pub struct BrokerFeature;
+
+impl BrokerFeature {
+    /// Acknowledging several pop messages with a single `BatchAckMessage` request.
+    pub const BATCH_ACK: u64 = 1;
+    /// Orderly consumption through pop requests.
+    pub const POP_ORDERLY: u64 = 1 << 1;
+    /// The V2 message format storing topics longer than 127 bytes.
+    pub const MESSAGE_V2: u64 = 1 << 2;
+
+    /// Every feature supported by this build.
+    pub const SUPPORTED: u64 = Self::BATCH_ACK | Self::POP_ORDERLY | Self::MESSAGE_V2;
+
+    /// Returns whether `bitmap` contains every bit of `feature`.
+    #[inline]
+    pub const fn contains(bitmap: u64, feature: u64) -> bool {
+        bitmap & feature == feature
+    }
+
+    /// Returns the features shared by all `bitmaps`, none when there is no bitmap at all.
+    pub fn intersect(bitmaps: impl IntoIterator<Item = u64>) -> u64 {
+        bitmaps
+            .into_iter()
+            .reduce(|shared, bitmap| shared & bitmap)
+            .unwrap_or(0)
+    }
+
+    /// Returns the names of the features set in `bitmap`.
+    pub fn names(bitmap: u64) -> Vec<&'static str> {
+        [
+            (Self::BATCH_ACK, "BATCH_ACK"),
+            (Self::POP_ORDERLY, "POP_ORDERLY"),
+            (Self::MESSAGE_V2, "MESSAGE_V2"),
+        ]
+        .into_iter()
+        .filter(|(feature, _)| Self::contains(bitmap, *feature))
+        .map(|(_, name)| name)
+        .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn intersect_keeps_only_shared_features() {
+        assert_eq!(
+            BrokerFeature::intersect([BrokerFeature::SUPPORTED, BrokerFeature::BATCH_ACK]),
+            BrokerFeature::BATCH_ACK
+        );
+        assert_eq!(BrokerFeature::intersect([]), 0);
+        assert!(BrokerFeature::contains(
+            BrokerFeature::SUPPORTED,
+            BrokerFeature::POP_ORDERLY | BrokerFeature::MESSAGE_V2
+        ));
+        assert!(!BrokerFeature::contains(0, BrokerFeature::BATCH_ACK));
+        assert_eq!(
+            BrokerFeature::names(BrokerFeature::BATCH_ACK | BrokerFeature::MESSAGE_V2),
+            vec!["BATCH_ACK", "MESSAGE_V2"]
+        );
+    }
+}
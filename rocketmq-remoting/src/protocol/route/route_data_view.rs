@@ -24,6 +24,8 @@ use rocketmq_common::common::mix_all;
 use serde::Deserialize;
 use serde::Serialize;
 
+use crate::protocol::route::broker_feature::BrokerFeature;
+
 #[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq, Default)]
 pub struct BrokerData {
     cluster: CheetahString,
@@ -35,6 +37,12 @@ pub struct BrokerData {
     zone_name: Option<CheetahString>,
     #[serde(rename = "enableActingMaster")]
     enable_acting_master: bool,
+    #[serde(
+        rename = "featureBitmaps",
+        default,
+        skip_serializing_if = "HashMap::is_empty"
+    )]
+    feature_bitmaps: HashMap<u64 /* broker id */, u64 /* feature bitmap */>,
 }
 
 impl PartialOrd for BrokerData {
@@ -64,6 +72,7 @@ impl BrokerData {
             broker_addrs,
             zone_name,
             enable_acting_master: false,
+            feature_bitmaps: HashMap::new(),
         }
     }
 
@@ -92,6 +101,11 @@ impl BrokerData {
         self.enable_acting_master = enable_acting_master;
     }
 
+    #[inline]
+    pub fn set_feature_bitmap(&mut self, broker_id: u64, feature_bitmap: u64) {
+        self.feature_bitmaps.insert(broker_id, feature_bitmap);
+    }
+
     #[inline]
     pub fn cluster(&self) -> &str {
         &self.cluster
@@ -116,6 +130,9 @@ impl BrokerData {
     pub fn remove_broker_by_addr(&mut self, broker_id: u64, broker_addr: &str) {
         self.broker_addrs
             .retain(|key, value| value != broker_addr || *key == broker_id);
+        let broker_addrs = &self.broker_addrs;
+        self.feature_bitmaps
+            .retain(|key, _| broker_addrs.contains_key(key));
     }
 
     #[inline]
@@ -128,6 +145,26 @@ impl BrokerData {
         self.enable_acting_master
     }
 
+    #[inline]
+    pub fn feature_bitmaps(&self) -> &HashMap<u64, u64> {
+        &self.feature_bitmaps
+    }
+
+    /// Returns the features supported by every broker of this broker group. A broker that did
+    /// not report a feature bitmap supports none.
+    pub fn feature_bitmap(&self) -> u64 {
+        BrokerFeature::intersect(
+            self.broker_addrs
+                .keys()
+                .map(|broker_id| self.feature_bitmaps.get(broker_id).copied().unwrap_or(0)),
+        )
+    }
+
+    #[inline]
+    pub fn supports_feature(&self, feature: u64) -> bool {
+        BrokerFeature::contains(self.feature_bitmap(), feature)
+    }
+
     pub fn select_broker_addr(&self) -> Option<CheetahString> {
         let master_address = self.broker_addrs.get(&(mix_all::MASTER_ID)).cloned();
         if master_address.is_none() {
@@ -292,6 +329,38 @@ mod tests {
         assert_eq!(selected_addr.unwrap(), CheetahString::from("127.0.0.2"));
     }
 
+    #[test]
+    fn broker_data_feature_bitmap_requires_every_broker() {
+        let mut broker_data = BrokerData::new(
+            CheetahString::from("cluster1"),
+            CheetahString::from("broker1"),
+            HashMap::from([
+                (mix_all::MASTER_ID, CheetahString::from("127.0.0.1")),
+                (1, CheetahString::from("127.0.0.2")),
+            ]),
+            None,
+        );
+        broker_data.set_feature_bitmap(mix_all::MASTER_ID, BrokerFeature::SUPPORTED);
+        assert_eq!(broker_data.feature_bitmap(), 0);
+
+        broker_data.set_feature_bitmap(1, BrokerFeature::BATCH_ACK);
+        assert!(broker_data.supports_feature(BrokerFeature::BATCH_ACK));
+        assert!(!broker_data.supports_feature(BrokerFeature::POP_ORDERLY));
+
+        broker_data.remove_broker_by_addr(mix_all::MASTER_ID, "127.0.0.2");
+        assert!(!broker_data.feature_bitmaps().contains_key(&1));
+        assert!(broker_data.supports_feature(BrokerFeature::POP_ORDERLY));
+
+        let json = serde_json::to_string(&broker_data).unwrap();
+        let decoded: BrokerData = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, broker_data);
+        let legacy: BrokerData = serde_json::from_str(
+            r#"{"cluster":"c","brokerName":"b","brokerAddrs":{"0":"127.0.0.1:10911"},"enableActingMaster":false}"#,
+        )
+        .unwrap();
+        assert_eq!(legacy.feature_bitmap(), 0);
+    }
+
     #[test]
     fn queue_data_new_initializes_correctly() {
         let queue_data = QueueData::new(CheetahString::from("broker1"), 4, 4, 6, 0);
@@ -52,6 +52,15 @@ impl TopicRouteData {
         now != old
     }
 
+    /// Returns whether every broker group serving this topic supports `feature`.
+    pub fn supports_feature(&self, feature: u64) -> bool {
+        !self.broker_datas.is_empty()
+            && self
+                .broker_datas
+                .iter()
+                .all(|broker_data| broker_data.supports_feature(feature))
+    }
+
     pub fn new() -> Self {
         TopicRouteData {
             order_topic_conf: None,
@@ -16,6 +16,7 @@
  */
 
 pub mod broker_stats_data;
+pub mod consume_time_window;
 pub mod customized_retry_policy;
 pub mod exponential_retry_policy;
 pub mod group_forbidden;
@@ -0,0 +1,144 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::fmt;
+use std::fmt::Display;
+
+const MILLIS_PER_MINUTE: u64 = 60 * 1000;
+const MINUTES_PER_DAY: u32 = 24 * 60;
+
+/// The times of day a subscription group may consume, e.g. `02:00-06:00` or
+/// `22:00-02:00,12:00-13:00`. A range ending before it starts wraps past midnight, an empty
+/// window places no restriction.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConsumeTimeWindow {
+    // [start, end) in minutes of the day
+    ranges: Vec<(u32, u32)>,
+}
+
+impl ConsumeTimeWindow {
+    pub fn parse(window: &str) -> Result<Self, String> {
+        let mut ranges = Vec::new();
+        for range in window.split(',').map(str::trim).filter(|r| !r.is_empty()) {
+            let (start, end) = range
+                .split_once('-')
+                .ok_or_else(|| format!("invalid consume time window range: {}", range))?;
+            let start = parse_minute_of_day(start.trim())?;
+            let end = parse_minute_of_day(end.trim())?;
+            if start == end {
+                return Err(format!("empty consume time window range: {}", range));
+            }
+            ranges.push((start, end));
+        }
+        Ok(Self { ranges })
+    }
+
+    #[inline]
+    pub fn is_unrestricted(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    /// Returns `None` when consuming is allowed at `millis_of_day`, otherwise how long until
+    /// the next range opens.
+    pub fn millis_until_open(&self, millis_of_day: u64) -> Option<u64> {
+        if self.is_unrestricted() {
+            return None;
+        }
+        let minute = (millis_of_day / MILLIS_PER_MINUTE) as u32 % MINUTES_PER_DAY;
+        let in_range = |&(start, end): &(u32, u32)| {
+            if start < end {
+                start <= minute && minute < end
+            } else {
+                minute >= start || minute < end
+            }
+        };
+        if self.ranges.iter().any(in_range) {
+            return None;
+        }
+        let millis_of_minute = millis_of_day % MILLIS_PER_MINUTE;
+        self.ranges
+            .iter()
+            .map(|&(start, _)| {
+                let minutes = (start + MINUTES_PER_DAY - minute) % MINUTES_PER_DAY;
+                minutes as u64 * MILLIS_PER_MINUTE - millis_of_minute
+            })
+            .min()
+    }
+}
+
+impl Display for ConsumeTimeWindow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, (start, end)) in self.ranges.iter().enumerate() {
+            if i > 0 {
+                write!(f, ",")?;
+            }
+            write!(
+                f,
+                "{:02}:{:02}-{:02}:{:02}",
+                start / 60,
+                start % 60,
+                end / 60,
+                end % 60
+            )?;
+        }
+        Ok(())
+    }
+}
+
+fn parse_minute_of_day(time: &str) -> Result<u32, String> {
+    let invalid = || format!("invalid time of day: {}, expected HH:mm", time);
+    let (hour, minute) = time.split_once(':').ok_or_else(invalid)?;
+    let hour = hour.parse::<u32>().map_err(|_| invalid())?;
+    let minute = minute.parse::<u32>().map_err(|_| invalid())?;
+    // 24:00 is accepted as the end of the day
+    if minute >= 60 || hour > 24 || (hour == 24 && minute != 0) {
+        return Err(invalid());
+    }
+    Ok(hour * 60 + minute)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOUR: u64 = 60 * MILLIS_PER_MINUTE;
+
+    #[test]
+    fn window_within_a_day() {
+        let window = ConsumeTimeWindow::parse("02:00-06:00").unwrap();
+        assert_eq!(window.millis_until_open(3 * HOUR), None);
+        assert_eq!(window.millis_until_open(6 * HOUR), Some(20 * HOUR));
+        assert_eq!(
+            window.millis_until_open(HOUR + 30 * MILLIS_PER_MINUTE + 500),
+            Some(30 * MILLIS_PER_MINUTE - 500)
+        );
+        assert_eq!(window.to_string(), "02:00-06:00");
+    }
+
+    #[test]
+    fn window_wrapping_midnight_and_multiple_ranges() {
+        let window = ConsumeTimeWindow::parse("22:00-02:00, 12:00-13:00").unwrap();
+        assert_eq!(window.millis_until_open(23 * HOUR), None);
+        assert_eq!(window.millis_until_open(HOUR), None);
+        assert_eq!(window.millis_until_open(3 * HOUR), Some(9 * HOUR));
+        assert_eq!(window.millis_until_open(14 * HOUR), Some(8 * HOUR));
+        assert!(ConsumeTimeWindow::parse("").unwrap().is_unrestricted());
+        assert!(ConsumeTimeWindow::parse("02:00").is_err());
+        assert!(ConsumeTimeWindow::parse("25:00-02:00").is_err());
+        assert!(ConsumeTimeWindow::parse("02:00-02:00").is_err());
+    }
+}
@@ -48,6 +48,10 @@ pub struct SubscriptionGroupConfig {
 
     consume_timeout_minute: i32,
 
+    /// Times of day the group may consume, see `ConsumeTimeWindow`, empty for any time.
+    #[serde(default)]
+    consume_time_window: CheetahString,
+
     subscription_data_set: Option<HashSet<SimpleSubscriptionData>>,
     attributes: HashMap<CheetahString, CheetahString>,
 }
@@ -84,6 +88,8 @@ impl Default for SubscriptionGroupConfig {
 
             consume_timeout_minute: 15,
 
+            consume_time_window: CheetahString::default(),
+
             subscription_data_set: None,
             attributes: HashMap::new(),
         }
@@ -156,6 +162,11 @@ impl SubscriptionGroupConfig {
         self.consume_timeout_minute
     }
 
+    #[inline]
+    pub fn consume_time_window(&self) -> &CheetahString {
+        &self.consume_time_window
+    }
+
     #[inline]
     pub fn subscription_data_set(&self) -> Option<&HashSet<SimpleSubscriptionData>> {
         self.subscription_data_set.as_ref()
@@ -234,6 +245,11 @@ impl SubscriptionGroupConfig {
         self.consume_timeout_minute = consume_timeout_minute;
     }
 
+    #[inline]
+    pub fn set_consume_time_window(&mut self, consume_time_window: CheetahString) {
+        self.consume_time_window = consume_time_window;
+    }
+
     #[inline]
     pub fn set_subscription_data_set(
         &mut self,
@@ -269,6 +285,7 @@ mod subscription_group_config_tests {
         assert!(config.notify_consumer_ids_changed_enable);
         assert_eq!(config.group_sys_flag, 0);
         assert_eq!(config.consume_timeout_minute, 15);
+        assert!(config.consume_time_window.is_empty());
         assert!(config.subscription_data_set.is_none());
         assert!(config.attributes.is_empty());
     }
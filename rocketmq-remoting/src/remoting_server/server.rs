@@ -22,6 +22,7 @@ use std::time::Duration;
 
 use futures::SinkExt;
 use rocketmq_common::common::server::config::ServerConfig;
+use rocketmq_common::utils::network_util::NetworkUtil;
 use rocketmq_rust::ArcMut;
 use tokio::net::TcpListener;
 use tokio::net::TcpStream;
@@ -167,7 +168,7 @@ impl<RP: RequestProcessor + Sync + 'static> ConnectionHandler<RP> {
                 }
             };
 
-            let exception = match self.do_before_rpc_hooks(&self.channel, response.as_mut()) {
+            let exception = match self.do_after_rpc_hooks(&self.channel, response.as_mut()) {
                 Ok(_) => None,
                 Err(error) => Some(error),
             };
@@ -182,7 +183,7 @@ impl<RP: RequestProcessor + Sync + 'static> ConnectionHandler<RP> {
             }
             let response = response.unwrap();
             tokio::select! {
-                result =self.connection_handler_context.channel.connection.writer.send(response.set_opaque(opaque)) => match result{
+                result =self.connection_handler_context.channel.connection.send(response.set_opaque(opaque)) => match result{
                     Ok(_) =>{},
                     Err(err) => {
                         match err {
@@ -377,6 +378,7 @@ impl<RP: RequestProcessor + Sync + 'static + Clone> ConnectionListener<RP> {
 
 pub struct RocketMQServer<RP> {
     config: Arc<ServerConfig>,
+    rpc_hooks: Vec<Arc<dyn RPCHook>>,
     _phantom_data: std::marker::PhantomData<RP>,
 }
 
@@ -384,30 +386,34 @@ impl<RP> RocketMQServer<RP> {
     pub fn new(config: Arc<ServerConfig>) -> Self {
         Self {
             config,
+            rpc_hooks: Vec::new(),
             _phantom_data: std::marker::PhantomData,
         }
     }
+
+    /// Registers a hook run around every request served by this server. Must be called before
+    /// `run`.
+    pub fn register_rpc_hook(&mut self, hook: Arc<dyn RPCHook>) {
+        self.rpc_hooks.push(hook);
+    }
 }
 
 impl<RP: RequestProcessor + Sync + 'static + Clone> RocketMQServer<RP> {
     pub async fn run(&self, request_processor: RP) {
-        let listener = TcpListener::bind(&format!(
-            "{}:{}",
-            self.config.bind_address, self.config.listen_port
-        ))
-        .await
-        .unwrap();
-        info!(
-            "Bind local address: {}",
-            format!("{}:{}", self.config.bind_address, self.config.listen_port)
-        );
+        let bind_address =
+            NetworkUtil::format_address(&self.config.bind_address, self.config.listen_port);
+        let listener = TcpListener::bind(&bind_address).await.unwrap();
+        info!("Bind local address: {}", bind_address);
         let (notify_conn_disconnect, _) = broadcast::channel::<SocketAddr>(100);
         run(
             listener,
             tokio::signal::ctrl_c(),
             request_processor,
             Some(notify_conn_disconnect),
-            vec![],
+            self.rpc_hooks
+                .iter()
+                .map(|hook| Box::new(hook.clone()) as Box<dyn RPCHook>)
+                .collect(),
         )
         .await;
     }
@@ -74,3 +74,21 @@ pub trait RPCHook: Send + Sync + 'static {
         response: &mut RemotingCommand,
     ) -> Result<()>;
 }
+
+impl<T: RPCHook + ?Sized> RPCHook for std::sync::Arc<T> {
+    fn do_before_request(
+        &self,
+        remote_addr: SocketAddr,
+        request: &mut RemotingCommand,
+    ) -> Result<()> {
+        (**self).do_before_request(remote_addr, request)
+    }
+
+    fn do_after_response(
+        &self,
+        remote_addr: SocketAddr,
+        response: &mut RemotingCommand,
+    ) -> Result<()> {
+        (**self).do_after_response(remote_addr, response)
+    }
+}
@@ -15,6 +15,9 @@
  * limitations under the License.
  */
 
+use std::collections::HashMap;
+use std::net::IpAddr;
+
 use lazy_static::lazy_static;
 
 use crate::runtime::config::net_system_config::NetSystemConfig;
@@ -45,6 +48,11 @@ pub struct TokioClientConfig {
     pub max_reconnect_interval_time_seconds: i64,
     pub enable_reconnect_for_go_away: bool,
     pub enable_transparent_retry: bool,
+    /// How often hostnames of namesrv and broker addresses are resolved again, `0` disables
+    /// periodic re-resolution.
+    pub dns_resolve_interval_millis: u64,
+    /// Hostnames resolved to fixed IPs instead of asking DNS.
+    pub dns_pinned_hosts: HashMap<String, Vec<IpAddr>>,
 }
 
 impl Default for TokioClientConfig {
@@ -70,6 +78,8 @@ impl Default for TokioClientConfig {
             max_reconnect_interval_time_seconds: 60,
             enable_reconnect_for_go_away: true,
             enable_transparent_retry: true,
+            dns_resolve_interval_millis: 30_000,
+            dns_pinned_hosts: HashMap::new(),
         }
     }
 }
@@ -19,6 +19,9 @@ use std::time::Duration;
 
 pub enum RocketMQRuntime {
     Multi(tokio::runtime::Runtime),
+    /// Runs on a runtime owned by the embedder, which also stays responsible for shutting it
+    /// down.
+    Handle(tokio::runtime::Handle),
 }
 
 impl RocketMQRuntime {
@@ -32,30 +35,39 @@ impl RocketMQRuntime {
                 .unwrap(),
         )
     }
+
+    pub fn from_handle(handle: tokio::runtime::Handle) -> Self {
+        Self::Handle(handle)
+    }
 }
 
 impl RocketMQRuntime {
     pub fn get_handle(&self) -> &tokio::runtime::Handle {
         match self {
             Self::Multi(runtime) => runtime.handle(),
+            Self::Handle(handle) => handle,
         }
     }
 
-    pub fn get_runtime(&self) -> &tokio::runtime::Runtime {
+    /// The owned runtime, `None` when running on an injected handle.
+    pub fn get_runtime(&self) -> Option<&tokio::runtime::Runtime> {
         match self {
-            Self::Multi(runtime) => runtime,
+            Self::Multi(runtime) => Some(runtime),
+            Self::Handle(_) => None,
         }
     }
 
     pub fn shutdown(self) {
         match self {
             Self::Multi(runtime) => runtime.shutdown_background(),
+            Self::Handle(_) => {}
         }
     }
 
     pub fn shutdown_timeout(self, timeout: Duration) {
         match self {
             Self::Multi(runtime) => runtime.shutdown_timeout(timeout),
+            Self::Handle(_) => {}
         }
     }
 
@@ -67,30 +79,26 @@ impl RocketMQRuntime {
     ) where
         F: Fn() + Send + 'static,
     {
-        match self {
-            RocketMQRuntime::Multi(runtime) => {
-                runtime.handle().spawn(async move {
-                    // initial delay
-                    if let Some(initial_delay_inner) = initial_delay {
-                        tokio::time::sleep(initial_delay_inner).await;
-                    }
+        self.get_handle().spawn(async move {
+            // initial delay
+            if let Some(initial_delay_inner) = initial_delay {
+                tokio::time::sleep(initial_delay_inner).await;
+            }
 
-                    loop {
-                        // record current execution time
-                        let current_execution_time = tokio::time::Instant::now();
-                        // execute task
-                        task();
-                        // Calculate the time of the next execution
-                        let next_execution_time = current_execution_time + period;
+            loop {
+                // record current execution time
+                let current_execution_time = tokio::time::Instant::now();
+                // execute task
+                task();
+                // Calculate the time of the next execution
+                let next_execution_time = current_execution_time + period;
 
-                        // Wait until the next execution
-                        let delay = next_execution_time
-                            .saturating_duration_since(tokio::time::Instant::now());
-                        tokio::time::sleep(delay).await;
-                    }
-                });
+                // Wait until the next execution
+                let delay =
+                    next_execution_time.saturating_duration_since(tokio::time::Instant::now());
+                tokio::time::sleep(delay).await;
             }
-        }
+        });
     }
 
     pub fn schedule_at_fixed_rate_mut<F>(
@@ -101,29 +109,25 @@ impl RocketMQRuntime {
     ) where
         F: FnMut() + Send + 'static,
     {
-        match self {
-            RocketMQRuntime::Multi(runtime) => {
-                runtime.handle().spawn(async move {
-                    // initial delay
-                    if let Some(initial_delay_inner) = initial_delay {
-                        tokio::time::sleep(initial_delay_inner).await;
-                    }
+        self.get_handle().spawn(async move {
+            // initial delay
+            if let Some(initial_delay_inner) = initial_delay {
+                tokio::time::sleep(initial_delay_inner).await;
+            }
 
-                    loop {
-                        // record current execution time
-                        let current_execution_time = tokio::time::Instant::now();
-                        // execute task
-                        task();
-                        // Calculate the time of the next execution
-                        let next_execution_time = current_execution_time + period;
+            loop {
+                // record current execution time
+                let current_execution_time = tokio::time::Instant::now();
+                // execute task
+                task();
+                // Calculate the time of the next execution
+                let next_execution_time = current_execution_time + period;
 
-                        // Wait until the next execution
-                        let delay = next_execution_time
-                            .saturating_duration_since(tokio::time::Instant::now());
-                        tokio::time::sleep(delay).await;
-                    }
-                });
+                // Wait until the next execution
+                let delay =
+                    next_execution_time.saturating_duration_since(tokio::time::Instant::now());
+                tokio::time::sleep(delay).await;
             }
-        }
+        });
     }
 }
@@ -21,18 +21,31 @@ pub mod allocate_mapped_file_service;
 pub mod append_message_callback;
 pub mod commit_log_dispatcher;
 pub mod compaction_append_msg_callback;
+pub mod decode_failure_stats;
+pub mod disk_usage;
 pub(crate) mod dispatch_request;
+pub mod dispatch_rule;
 pub mod flush_manager;
 pub mod get_message_result;
+pub mod hot_message_cache;
 pub mod message_arriving_listener;
 pub mod message_result;
 pub mod message_status_enum;
 pub mod put_message_context;
+pub mod put_message_lock;
 pub mod query_message_result;
+pub mod recovery_progress;
 pub mod select_result;
 pub mod store_checkpoint;
+pub mod store_compaction;
 pub mod store_enum;
+pub mod store_event_bus;
+pub mod store_health;
+pub mod store_read_executor;
+pub mod store_snapshot;
 pub mod store_stats_service;
+pub mod store_task_spawner;
+pub mod store_verify;
 pub mod swappable;
 pub mod topic_queue_lock;
 pub mod transient_store_pool;
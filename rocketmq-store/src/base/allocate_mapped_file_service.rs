@@ -17,97 +17,260 @@
 use std::collections::HashMap;
 use std::fmt::Display;
 use std::fmt::Formatter;
+use std::mem;
+use std::panic;
+use std::panic::AssertUnwindSafe;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
 use std::sync::mpsc;
 use std::sync::mpsc::Receiver;
+use std::sync::mpsc::RecvTimeoutError;
 use std::sync::mpsc::Sender;
 use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Duration;
+use std::time::Instant;
 
+use cheetah_string::CheetahString;
+use parking_lot::Condvar;
 use parking_lot::Mutex;
+use tracing::error;
+use tracing::info;
+use tracing::warn;
 
+use crate::config::message_store_config::MessageStoreConfig;
 use crate::log_file::mapped_file::default_mapped_file_impl::DefaultMappedFile;
+use crate::log_file::mapped_file::MappedFile;
 
+/// How long a put waits for the next mapped file before giving up.
+const WAIT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Creates commit log files in the background, so that the put path does not map a new file
+/// inside the put lock every time the last one fills up.
+///
+/// Every request for the next file also queues the file after it, which is then usually ready
+/// by the time it is asked for.
 pub struct AllocateMappedFileService {
-    tx: Sender<AllocateRequest>,
-    rx: Arc<Mutex<Receiver<AllocateRequest>>>,
-    request_table: Arc<parking_lot::Mutex<HashMap<String, AllocateRequest>>>,
+    message_store_config: Arc<MessageStoreConfig>,
+    tx: Sender<Arc<AllocateRequest>>,
+    rx: Mutex<Option<Receiver<Arc<AllocateRequest>>>>,
+    request_table: Arc<Mutex<HashMap<String, Arc<AllocateRequest>>>>,
+    stopped: Arc<AtomicBool>,
+    started: AtomicBool,
+    handle: Mutex<Option<JoinHandle<()>>>,
 }
 
 impl AllocateMappedFileService {
-    pub fn new() -> Self {
+    pub fn new(message_store_config: Arc<MessageStoreConfig>) -> Self {
         let (tx, rx) = mpsc::channel();
         Self {
+            message_store_config,
             tx,
-            rx: Arc::new(parking_lot::Mutex::new(rx)),
+            rx: Mutex::new(Some(rx)),
             request_table: Arc::new(Default::default()),
+            stopped: Arc::new(AtomicBool::new(false)),
+            started: AtomicBool::new(false),
+            handle: Mutex::new(None),
         }
     }
-}
 
-impl Default for AllocateMappedFileService {
-    fn default() -> Self {
-        Self::new()
+    pub fn start(&self) {
+        let Some(rx) = self.rx.lock().take() else {
+            return;
+        };
+        let message_store_config = self.message_store_config.clone();
+        let request_table = self.request_table.clone();
+        let stopped = self.stopped.clone();
+        let handle = std::thread::Builder::new()
+            .name(self.get_service_name())
+            .spawn(move || {
+                info!("AllocateMappedFileService service started");
+                while !stopped.load(Ordering::Acquire) {
+                    match rx.recv_timeout(Duration::from_millis(100)) {
+                        Ok(request) => {
+                            mmap_operation(&message_store_config, &request_table, &request)
+                        }
+                        Err(RecvTimeoutError::Timeout) => {}
+                        Err(RecvTimeoutError::Disconnected) => break,
+                    }
+                }
+                info!("AllocateMappedFileService service end");
+            })
+            .expect("spawn AllocateMappedFileService thread failed");
+        *self.handle.lock() = Some(handle);
+        self.started.store(true, Ordering::Release);
     }
-}
 
-impl AllocateMappedFileService {
+    /// Stops the service and deletes the files created ahead that were never handed out.
+    pub fn shutdown(&self) {
+        if !self.started.swap(false, Ordering::AcqRel) {
+            return;
+        }
+        self.stopped.store(true, Ordering::Release);
+        if let Some(handle) = self.handle.lock().take() {
+            let _ = handle.join();
+        }
+        for (_, request) in self.request_table.lock().drain() {
+            if let AllocateResult::Created(mapped_file) =
+                mem::replace(&mut *request.result.lock(), AllocateResult::Failed)
+            {
+                info!(
+                    "delete pre allocated mapped file, {}",
+                    mapped_file.get_file_name()
+                );
+                mapped_file.destroy(1000);
+            }
+        }
+    }
+
+    #[inline]
+    pub fn is_started(&self) -> bool {
+        self.started.load(Ordering::Acquire)
+    }
+
+    /// Returns the mapped file for `next_file_path`, waiting for it if it is still being
+    /// created, and queues `next_next_file_path` ahead of time.
+    ///
+    /// `None` if the file could not be created in time, the request stays queued and is picked
+    /// up by the next call for the same path.
     pub fn put_request_and_return_mapped_file(
         &self,
         next_file_path: String,
         next_next_file_path: String,
-        file_size: i32,
-    ) -> DefaultMappedFile {
-        unimplemented!()
-    }
-}
+        file_size: u64,
+    ) -> Option<DefaultMappedFile> {
+        if !self.submit(next_file_path.clone(), file_size) {
+            return None;
+        }
+        self.submit(next_next_file_path, file_size);
 
-/*impl ThreadService for AllocateMappedFileService {
-    fn start(&self) {
-        let service_name = self.get_service_name();
-        let builder = std::thread::Builder::new().name(service_name);
-        let rx = self.rx.clone();
-        let request_table = self.request_table.clone();
-        let _ = builder
-            .spawn(move || loop {
-                match rx.lock().recv() {
-                    Ok(ref req) => {
-                        let guard = request_table.lock();
-                        let expected_request = guard.get(&req.file_path);
-                        if expected_request.is_none() {
-                            warn!(
-                                "this mmap request expired, maybe cause timeout {} {}",
-                                req.file_path, req.file_size,
-                            );
-                            continue;
-                        }
-                        if expected_request.unwrap() != req {
-                            warn!(
-                                "this mmap request expired, maybe cause timeout {} {}",
-                                req.file_path, req.file_size,
-                            );
-                            continue;
-                        }
-                        if req.mapped_file.is_none() {}
-                    }
-                    Err(err) => {
-                        error!("{}", err)
-                    }
-                }
-            })
-            .unwrap()
-            .join();
+        let request = self.request_table.lock().get(&next_file_path).cloned()?;
+        let deadline = Instant::now() + WAIT_TIMEOUT;
+        let mut result = request.result.lock();
+        while matches!(*result, AllocateResult::Pending) {
+            if request.done.wait_until(&mut result, deadline).timed_out() {
+                warn!(
+                    "create mmap timeout {} {}",
+                    request.file_path, request.file_size
+                );
+                return None;
+            }
+        }
+        let mapped_file = match mem::replace(&mut *result, AllocateResult::Failed) {
+            AllocateResult::Created(mapped_file) => Some(mapped_file),
+            _ => None,
+        };
+        drop(result);
+        self.request_table.lock().remove(&next_file_path);
+        mapped_file
     }
 
-    fn shutdown(&self) {}
+    fn submit(&self, file_path: String, file_size: u64) -> bool {
+        let mut request_table = self.request_table.lock();
+        if request_table.contains_key(&file_path) {
+            return true;
+        }
+        let request = Arc::new(AllocateRequest::new(file_path.clone(), file_size));
+        if self.tx.send(request.clone()).is_err() {
+            warn!(
+                "AllocateMappedFileService is stopped, {} not queued",
+                request
+            );
+            return false;
+        }
+        request_table.insert(file_path, request);
+        true
+    }
 
-    fn get_service_name(&self) -> String {
+    pub fn get_service_name(&self) -> String {
         "AllocateMappedFileService".to_string()
     }
-}*/
+}
+
+fn mmap_operation(
+    message_store_config: &MessageStoreConfig,
+    request_table: &Mutex<HashMap<String, Arc<AllocateRequest>>>,
+    request: &Arc<AllocateRequest>,
+) {
+    let expected = request_table
+        .lock()
+        .get(&request.file_path)
+        .is_some_and(|expected| Arc::ptr_eq(expected, request));
+    if !expected {
+        warn!(
+            "this mmap request expired, maybe cause timeout {} {}",
+            request.file_path, request.file_size
+        );
+        return;
+    }
+
+    let begin_time = Instant::now();
+    let created = panic::catch_unwind(AssertUnwindSafe(|| {
+        let file_name = CheetahString::from_string(request.file_path.clone());
+        let mapped_file = if message_store_config.write_without_mmap {
+            DefaultMappedFile::new_without_mmap_buffered(
+                file_name,
+                request.file_size,
+                message_store_config.write_without_mmap_buffer_size,
+            )
+        } else {
+            DefaultMappedFile::new(file_name, request.file_size)
+        };
+        if message_store_config.warm_mapped_file_enable
+            && request.file_size >= message_store_config.mapped_file_size_commit_log as u64
+        {
+            mapped_file.warm_mapped_file(
+                message_store_config.flush_disk_type,
+                message_store_config.flush_least_pages_when_warm_mapped_file,
+            );
+            if message_store_config.warm_mapped_file_lock_enable {
+                mapped_file.mlock();
+            }
+        }
+        mapped_file
+    }));
+    let elapsed = begin_time.elapsed().as_millis();
+    if elapsed > 10 {
+        warn!(
+            "create mappedFile spent time(ms) {} queue size {}",
+            elapsed,
+            request_table.lock().len()
+        );
+    }
+
+    let mut result = request.result.lock();
+    *result = match created {
+        Ok(mapped_file) => AllocateResult::Created(mapped_file),
+        Err(_) => {
+            error!("create mapped file failed, {}", request);
+            AllocateResult::Failed
+        }
+    };
+    request.done.notify_all();
+}
+
+enum AllocateResult {
+    Pending,
+    Created(DefaultMappedFile),
+    Failed,
+}
 
 struct AllocateRequest {
     file_path: String,
-    file_size: i32,
-    mapped_file: Option<DefaultMappedFile>,
+    file_size: u64,
+    result: Mutex<AllocateResult>,
+    done: Condvar,
+}
+
+impl AllocateRequest {
+    fn new(file_path: String, file_size: u64) -> Self {
+        Self {
+            file_path,
+            file_size,
+            result: Mutex::new(AllocateResult::Pending),
+            done: Condvar::new(),
+        }
+    }
 }
 
 impl Display for AllocateRequest {
@@ -120,10 +283,39 @@ impl Display for AllocateRequest {
     }
 }
 
-impl PartialEq<Self> for AllocateRequest {
-    fn eq(&self, other: &Self) -> bool {
-        self.file_path == other.file_path && self.file_size == other.file_size
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+
+    #[test]
+    fn allocates_next_file_and_deletes_unused_ones_on_shutdown() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = |offset: u64| {
+            dir.path()
+                .join(format!("{:020}", offset))
+                .to_string_lossy()
+                .to_string()
+        };
+        let service = AllocateMappedFileService::new(Arc::new(MessageStoreConfig::default()));
+        service.start();
+
+        let mapped_file = service
+            .put_request_and_return_mapped_file(path(0), path(1024), 1024)
+            .unwrap();
+        assert_eq!(mapped_file.get_file_name().as_str(), path(0));
+        assert_eq!(mapped_file.get_file_size(), 1024);
+
+        let deadline = Instant::now() + WAIT_TIMEOUT;
+        while !Path::new(&path(1024)).exists() && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        service.shutdown();
+        assert!(!service.is_started());
+        assert!(!Path::new(&path(1024)).exists());
+        assert!(service
+            .put_request_and_return_mapped_file(path(2048), path(3072), 1024)
+            .is_none());
     }
 }
-
-impl Eq for AllocateRequest {}
@@ -24,10 +24,13 @@ use cheetah_string::CheetahString;
 use rocketmq_common::common::config::TopicConfig;
 use rocketmq_common::common::message::message_batch::MessageExtBatch;
 use rocketmq_common::common::message::message_ext_broker_inner::MessageExtBrokerInner;
+use rocketmq_common::common::message::MessageTrait;
 use rocketmq_common::common::sys_flag::message_sys_flag::MessageSysFlag;
 use rocketmq_common::utils::message_utils;
 use rocketmq_common::CRC32Utils::crc32;
 use rocketmq_common::MessageDecoder::create_crc32;
+use rocketmq_common::MessageDecoder::message_properties_to_string;
+use rocketmq_common::MessageDecoder::PROPERTY_SEPARATOR;
 use rocketmq_common::MessageUtils::build_batch_message_id;
 use rocketmq_rust::SyncUnsafeCellWrapper;
 
@@ -105,6 +108,59 @@ impl DefaultAppendMessageCallback {
             topic_config_table,
         }
     }
+
+    /// Multi dispatch messages are encoded without properties, the light queue offsets are only
+    /// known once the offsets are assigned under the topic queue lock. Appends the properties to
+    /// the pre-encoded buffer and fixes the total size.
+    fn handle_properties_for_lmq_msg(
+        &self,
+        pre_encode_buffer: &mut bytes::BytesMut,
+        msg_inner: &mut MessageExtBrokerInner,
+    ) -> Option<AppendMessageResult> {
+        if msg_inner.encode_completed {
+            return None;
+        }
+        msg_inner.properties_string = message_properties_to_string(msg_inner.get_properties());
+        let properties_data = msg_inner.properties_string().as_bytes();
+        let need_append_last_property_separator = self.crc32_reserved_length > 0
+            && properties_data
+                .last()
+                .is_some_and(|last| *last != PROPERTY_SEPARATOR as u8);
+        let properties_length = properties_data.len() as i32
+            + if need_append_last_property_separator {
+                1
+            } else {
+                0
+            }
+            + self.crc32_reserved_length;
+        if properties_length > self.message_store_config.max_message_size {
+            return Some(AppendMessageResult {
+                status: AppendMessageStatus::PropertiesSizeExceeded,
+                ..Default::default()
+            });
+        }
+
+        let msg_len_without_properties =
+            i32::from_be_bytes(pre_encode_buffer[0..4].try_into().unwrap());
+        let msg_len = msg_len_without_properties + 2 + properties_length;
+        if msg_len > self.message_store_config.max_message_size {
+            return Some(AppendMessageResult {
+                status: AppendMessageStatus::MessageSizeExceeded,
+                ..Default::default()
+            });
+        }
+
+        pre_encode_buffer.truncate(msg_len_without_properties as usize);
+        pre_encode_buffer.put_i16(properties_length as i16);
+        pre_encode_buffer.put_slice(properties_data);
+        if need_append_last_property_separator {
+            pre_encode_buffer.put_u8(PROPERTY_SEPARATOR as u8);
+        }
+        // 18 CRC32, filled in once the message is appended
+        pre_encode_buffer.put_bytes(0, self.crc32_reserved_length as usize);
+        pre_encode_buffer[0..4].copy_from_slice(&msg_len.to_be_bytes());
+        None
+    }
 }
 
 impl AppendMessageCallback for DefaultAppendMessageCallback {
@@ -120,7 +176,11 @@ impl AppendMessageCallback for DefaultAppendMessageCallback {
         let is_multi_dispatch_msg = self.message_store_config.enable_multi_dispatch
             && CommitLog::is_multi_dispatch_msg(msg_inner);
         if is_multi_dispatch_msg {
-            unimplemented!("Multi dispatch message is not supported yet");
+            if let Some(result) =
+                self.handle_properties_for_lmq_msg(&mut pre_encode_buffer, msg_inner)
+            {
+                return result;
+            }
         }
 
         let msg_len = i32::from_be_bytes(pre_encode_buffer[0..4].try_into().unwrap());
@@ -298,7 +358,13 @@ impl AppendMessageCallback for DefaultAppendMessageCallback {
                     .to_be_bytes(),
             );
             if enabled_append_prop_crc {
-                let _check_size = msg_len - self.crc32_reserved_length;
+                // 18 CRC32
+                let check_size = (msg_len - self.crc32_reserved_length) as usize;
+                let crc32 = crc32(&messages_byte_buffer[msg_pos..msg_pos + check_size]);
+                create_crc32(
+                    &mut messages_byte_buffer[msg_pos + check_size..msg_pos + msg_len as usize],
+                    crc32,
+                );
             }
             put_message_context.get_phy_pos_mut()[index] = phy_pos;
             msg_num += 1;
@@ -0,0 +1,179 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::VecDeque;
+use std::fs;
+use std::path::Path;
+
+use serde::Deserialize;
+use serde::Serialize;
+use sysinfo::Disks;
+
+const MILLIS_PER_DAY: f64 = 24.0 * 60.0 * 60.0 * 1000.0;
+
+/// Disk usage of the store: capacity of every storage path, the space taken by each kind of
+/// store file and a projection of when the commit log disk fills up.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiskUsageInfo {
+    pub timestamp: i64,
+    pub paths: Vec<StorePathUsage>,
+    pub commit_log_bytes: u64,
+    pub consume_queue_bytes: u64,
+    pub index_bytes: u64,
+    /// Commit log growth over the sampling window, `-1` while fewer than two samples exist.
+    pub ingest_bytes_per_day: i64,
+    /// Days until the commit log disk is full at the current ingest rate, `-1` if unknown or
+    /// the commit log is not growing.
+    pub days_until_full: f64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StorePathUsage {
+    /// What the path stores, e.g. `commitlog`, `consumequeue` or `index`.
+    pub kind: String,
+    pub path: String,
+    /// Mount point of the disk the path lives on, empty if it could not be resolved.
+    pub mount_point: String,
+    pub total_space: u64,
+    pub free_space: u64,
+    pub used_ratio: f64,
+}
+
+impl StorePathUsage {
+    /// Resolves the disk holding `path` as the one with the longest matching mount point.
+    pub fn of(kind: &str, path: &str, disks: &Disks) -> Self {
+        let canonical = fs::canonicalize(path).unwrap_or_else(|_| Path::new(path).to_path_buf());
+        let disk = disks
+            .list()
+            .iter()
+            .filter(|disk| canonical.starts_with(disk.mount_point()))
+            .max_by_key(|disk| disk.mount_point().as_os_str().len());
+        let mut usage = StorePathUsage {
+            kind: kind.to_string(),
+            path: path.to_string(),
+            ..Default::default()
+        };
+        if let Some(disk) = disk {
+            usage.mount_point = disk.mount_point().to_string_lossy().to_string();
+            usage.total_space = disk.total_space();
+            usage.free_space = disk.available_space();
+            if usage.total_space > 0 {
+                usage.used_ratio =
+                    (usage.total_space - usage.free_space) as f64 / usage.total_space as f64;
+            }
+        }
+        usage
+    }
+}
+
+/// Total size of the regular files below `path`, `0` if it does not exist.
+pub fn dir_size(path: &Path) -> u64 {
+    let Ok(entries) = fs::read_dir(path) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .map(|entry| match entry.metadata() {
+            Ok(metadata) if metadata.is_dir() => dir_size(&entry.path()),
+            Ok(metadata) => metadata.len(),
+            Err(_) => 0,
+        })
+        .sum()
+}
+
+/// Samples of the commit log max offset, taken by the clean service, from which the recent
+/// ingest rate is derived.
+pub struct IngestRateTracker {
+    window_millis: i64,
+    samples: parking_lot::Mutex<VecDeque<(i64 /* timestamp */, i64 /* max offset */)>>,
+}
+
+impl IngestRateTracker {
+    pub fn new(window_millis: i64) -> Self {
+        Self {
+            window_millis,
+            samples: parking_lot::Mutex::new(VecDeque::new()),
+        }
+    }
+
+    pub fn record(&self, timestamp: i64, max_offset: i64) {
+        let mut samples = self.samples.lock();
+        samples.push_back((timestamp, max_offset));
+        while samples
+            .front()
+            .is_some_and(|(oldest, _)| timestamp - *oldest > self.window_millis)
+        {
+            samples.pop_front();
+        }
+    }
+
+    /// Bytes appended per day between the oldest and newest sample of the window.
+    pub fn bytes_per_day(&self) -> Option<f64> {
+        let samples = self.samples.lock();
+        let (first_timestamp, first_offset) = *samples.front()?;
+        let (last_timestamp, last_offset) = *samples.back()?;
+        if last_timestamp <= first_timestamp {
+            return None;
+        }
+        let bytes = (last_offset - first_offset).max(0) as f64;
+        Some(bytes * MILLIS_PER_DAY / (last_timestamp - first_timestamp) as f64)
+    }
+}
+
+impl Default for IngestRateTracker {
+    fn default() -> Self {
+        // one day, so daily traffic patterns average out
+        Self::new(MILLIS_PER_DAY as i64)
+    }
+}
+
+/// Days until `free_space` is used up at `bytes_per_day`, `None` if the store is not growing.
+pub fn days_until_full(free_space: u64, bytes_per_day: f64) -> Option<f64> {
+    (bytes_per_day > 0.0).then(|| free_space as f64 / bytes_per_day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ingest_rate_uses_the_sampling_window() {
+        let tracker = IngestRateTracker::new(10_000);
+        assert_eq!(tracker.bytes_per_day(), None);
+        tracker.record(0, 0);
+        tracker.record(5_000, 1_000_000);
+        tracker.record(15_000, 1_000_000);
+        // the first sample fell out of the window, nothing was appended since
+        assert_eq!(tracker.bytes_per_day(), Some(0.0));
+        tracker.record(20_000, 2_000_000);
+        let expected = 1_000_000.0 * MILLIS_PER_DAY / 5_000.0;
+        assert_eq!(tracker.bytes_per_day(), Some(expected));
+        assert_eq!(days_until_full(expected as u64, expected), Some(1.0));
+        assert_eq!(days_until_full(100, 0.0), None);
+    }
+
+    #[test]
+    fn dir_size_sums_nested_files() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a"), [0u8; 10]).unwrap();
+        fs::create_dir(dir.path().join("sub")).unwrap();
+        fs::write(dir.path().join("sub").join("b"), [0u8; 5]).unwrap();
+        assert_eq!(dir_size(dir.path()), 15);
+        assert_eq!(dir_size(&dir.path().join("missing")), 0);
+    }
+}
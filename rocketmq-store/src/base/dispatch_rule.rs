@@ -0,0 +1,207 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::sync::Arc;
+
+use cheetah_string::CheetahString;
+use parking_lot::RwLock;
+use rocketmq_common::common::pop_ack_constants::PopAckConstants;
+use rocketmq_common::common::topic::TopicValidator;
+
+use crate::base::commit_log_dispatcher::CommitLogDispatcher;
+use crate::base::dispatch_request::DispatchRequest;
+use crate::timer::timer_message_store::TIMER_TOPIC;
+
+/// Which of the default dispatchers process the messages of a topic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DispatchRoute {
+    pub consume_queue: bool,
+    pub index: bool,
+}
+
+impl DispatchRoute {
+    /// Messages of user topics are queued and indexed.
+    pub const NORMAL: DispatchRoute = DispatchRoute {
+        consume_queue: true,
+        index: true,
+    };
+    /// Internal messages are only queued for the service reading them back, their keys must
+    /// not show up in message queries.
+    pub const INTERNAL: DispatchRoute = DispatchRoute {
+        consume_queue: true,
+        index: false,
+    };
+    /// Internal messages served by the handler of their rule alone.
+    pub const HANDLER_ONLY: DispatchRoute = DispatchRoute {
+        consume_queue: false,
+        index: false,
+    };
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TopicMatcher {
+    Exact(CheetahString),
+    Prefix(CheetahString),
+}
+
+impl TopicMatcher {
+    pub fn matches(&self, topic: &str) -> bool {
+        match self {
+            TopicMatcher::Exact(name) => name.as_str() == topic,
+            TopicMatcher::Prefix(prefix) => topic.starts_with(prefix.as_str()),
+        }
+    }
+}
+
+/// Routes the messages of one system topic, optionally handing them to the dispatcher of the
+/// feature owning the topic.
+#[derive(Clone)]
+pub struct DispatchRule {
+    pub matcher: TopicMatcher,
+    pub route: DispatchRoute,
+    pub handler: Option<Arc<dyn CommitLogDispatcher>>,
+}
+
+impl DispatchRule {
+    pub fn exact(topic: impl Into<CheetahString>, route: DispatchRoute) -> Self {
+        Self {
+            matcher: TopicMatcher::Exact(topic.into()),
+            route,
+            handler: None,
+        }
+    }
+
+    pub fn prefix(prefix: impl Into<CheetahString>, route: DispatchRoute) -> Self {
+        Self {
+            matcher: TopicMatcher::Prefix(prefix.into()),
+            route,
+            handler: None,
+        }
+    }
+
+    pub fn with_handler(mut self, handler: Arc<dyn CommitLogDispatcher>) -> Self {
+        self.handler = Some(handler);
+        self
+    }
+}
+
+/// System topics the dispatchers treat differently from user topics. Feature modules register
+/// the topics they own, a later rule takes precedence over an earlier one matching the same
+/// topic.
+#[derive(Default)]
+pub struct DispatchRules {
+    rules: RwLock<Vec<DispatchRule>>,
+}
+
+impl DispatchRules {
+    /// Rules for the internal topics of transactions, delayed and timer messages and pop.
+    pub fn with_system_topics() -> Self {
+        let rules = Self::default();
+        for topic in [
+            TopicValidator::RMQ_SYS_TRANS_HALF_TOPIC,
+            TopicValidator::RMQ_SYS_TRANS_OP_HALF_TOPIC,
+            TopicValidator::RMQ_SYS_SCHEDULE_TOPIC,
+            TIMER_TOPIC,
+        ] {
+            rules.register(DispatchRule::exact(topic, DispatchRoute::INTERNAL));
+        }
+        rules.register(DispatchRule::prefix(
+            PopAckConstants::REVIVE_TOPIC,
+            DispatchRoute::INTERNAL,
+        ));
+        rules
+    }
+
+    pub fn register(&self, rule: DispatchRule) {
+        self.rules.write().push(rule);
+    }
+
+    pub fn route(&self, topic: &str) -> DispatchRoute {
+        self.rules
+            .read()
+            .iter()
+            .rev()
+            .find(|rule| rule.matcher.matches(topic))
+            .map_or(DispatchRoute::NORMAL, |rule| rule.route)
+    }
+
+    /// Hands the request to the handler of the rule matching its topic, if any.
+    pub fn dispatch_to_handler(&self, dispatch_request: &DispatchRequest) {
+        let handler = self
+            .rules
+            .read()
+            .iter()
+            .rev()
+            .find(|rule| rule.matcher.matches(dispatch_request.topic.as_str()))
+            .and_then(|rule| rule.handler.clone());
+        if let Some(handler) = handler {
+            handler.dispatch(dispatch_request);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering;
+
+    use super::*;
+
+    #[derive(Default)]
+    struct CountingDispatcher {
+        dispatched: AtomicUsize,
+    }
+
+    impl CommitLogDispatcher for CountingDispatcher {
+        fn dispatch(&self, _dispatch_request: &DispatchRequest) {
+            self.dispatched.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    #[test]
+    fn system_topics_are_not_indexed() {
+        let rules = DispatchRules::with_system_topics();
+        assert_eq!(rules.route("TopicTest"), DispatchRoute::NORMAL);
+        assert_eq!(rules.route(TIMER_TOPIC), DispatchRoute::INTERNAL);
+        assert_eq!(
+            rules.route("rmq_sys_REVIVE_LOG_DefaultCluster"),
+            DispatchRoute::INTERNAL
+        );
+        assert_eq!(
+            rules.route(TopicValidator::RMQ_SYS_TRACE_TOPIC),
+            DispatchRoute::NORMAL
+        );
+    }
+
+    #[test]
+    fn registered_rule_overrides_and_reaches_handler() {
+        let rules = DispatchRules::with_system_topics();
+        let handler = Arc::new(CountingDispatcher::default());
+        rules.register(
+            DispatchRule::exact(TIMER_TOPIC, DispatchRoute::HANDLER_ONLY)
+                .with_handler(handler.clone()),
+        );
+        assert_eq!(rules.route(TIMER_TOPIC), DispatchRoute::HANDLER_ONLY);
+
+        let request = DispatchRequest {
+            topic: CheetahString::from_static_str(TIMER_TOPIC),
+            ..DispatchRequest::default()
+        };
+        rules.dispatch_to_handler(&request);
+        rules.dispatch_to_handler(&DispatchRequest::default());
+        assert_eq!(handler.dispatched.load(Ordering::Relaxed), 1);
+    }
+}
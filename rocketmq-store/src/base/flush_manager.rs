@@ -16,9 +16,11 @@
  */
 
 use rocketmq_common::common::message::message_ext_broker_inner::MessageExtBrokerInner;
+use tokio::sync::oneshot;
 
 use crate::base::message_result::AppendMessageResult;
 use crate::base::message_status_enum::PutMessageStatus;
+use crate::config::flush_disk_type::FlushDiskType;
 
 /// The `RocketMQFlushManager` trait defines the operations for managing the flushing of messages to
 /// disk in RocketMQ.
@@ -38,21 +40,26 @@ pub trait RocketMQFlushManager {
     /// have been successfully flushed and are ready to be committed.
     fn wake_up_commit(&mut self);
 
-    /// Handles the asynchronous disk flushing of messages.
-    /// This method is responsible for writing the messages to disk and updating the message store's
-    /// write position.
+    /// Hands the appended message to the flush services.
+    /// For synchronous flush of a message waiting for store ok, a group commit request is
+    /// submitted and the returned receiver completes once it is flushed. Otherwise the flush
+    /// service is woken up and the receiver is already completed. Callers await the receiver
+    /// without holding the manager, bounded by `sync_flush_timeout`. The flush type may differ
+    /// from the broker's when the message's topic overrides it.
     ///
     /// # Arguments
     ///
     /// * `result` - The result of appending the message to the message store.
     /// * `message_ext` - The message to be flushed to disk.
+    /// * `flush_disk_type` - The flush type that applies to the message.
     ///
     /// # Returns
     ///
-    /// * `PutMessageStatus` - The status of the put message operation.
-    async fn handle_disk_flush(
+    /// * `Receiver<PutMessageStatus>` - Completion of the flush.
+    fn handle_disk_flush(
         &mut self,
         result: &AppendMessageResult,
         message_ext: &MessageExtBrokerInner,
-    ) -> PutMessageStatus;
+        flush_disk_type: FlushDiskType,
+    ) -> oneshot::Receiver<PutMessageStatus>;
 }
@@ -107,6 +107,9 @@ impl GetMessageResult {
     pub fn message_count(&self) -> i32 {
         self.message_count
     }
+    pub fn message_queue_offset(&self) -> &[u64] {
+        &self.message_queue_offset
+    }
     pub fn suggest_pulling_from_slave(&self) -> bool {
         self.suggest_pulling_from_slave
     }
@@ -0,0 +1,336 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+
+use bytes::Bytes;
+use cheetah_string::CheetahString;
+use parking_lot::Mutex;
+use rocketmq_common::TimeUtils::get_current_millis;
+
+use crate::config::message_store_config::MessageStoreConfig;
+
+/// A message kept by the [`HotMessageCache`], the same bytes the commit log holds for it.
+#[derive(Debug, Clone)]
+pub struct CachedMessage {
+    pub queue_offset: i64,
+    pub commit_log_offset: i64,
+    pub data: Bytes,
+}
+
+/// Point-in-time view of the cache counters.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct HotMessageCacheStats {
+    /// Messages served from the cache.
+    pub hits: u64,
+    /// Lookups that found nothing at the requested offset.
+    pub misses: u64,
+    /// Messages dropped to stay under the memory cap.
+    pub evictions: u64,
+    /// Messages too large to be cached.
+    pub rejected: u64,
+    pub cached_messages: usize,
+    pub cached_bytes: usize,
+}
+
+impl HotMessageCacheStats {
+    pub fn hit_ratio(&self) -> f64 {
+        let lookups = self.hits + self.misses;
+        if lookups == 0 {
+            return 0.0;
+        }
+        self.hits as f64 / lookups as f64
+    }
+}
+
+#[derive(Default)]
+struct QueueCache {
+    entries: BTreeMap<i64, CachedMessage>,
+    last_read_timestamp: u64,
+}
+
+#[derive(Default)]
+struct CacheInner {
+    queues: HashMap<(CheetahString, i32), QueueCache>,
+    /// Cached messages in dispatch order, the oldest is evicted first. Keys of messages
+    /// already removed otherwise are skipped when they come up.
+    order: VecDeque<(CheetahString, i32, i64)>,
+    cached_messages: usize,
+    cached_bytes: usize,
+}
+
+/// Bounded cache of recently dispatched messages keyed by `(topic, queueId, queueOffset)`.
+///
+/// Pulls consult it before the consume queue and commit log, so consumers reading right
+/// behind the producers never touch the mapped files. Only queues that were read within
+/// `reader_idle_ms` are cached, appends to queues nobody tails would just push hot entries
+/// out. The message bytes held never exceed `max_bytes`.
+pub struct HotMessageCache {
+    max_bytes: usize,
+    max_message_size: usize,
+    reader_idle_ms: u64,
+    inner: Mutex<CacheInner>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+    evictions: AtomicU64,
+    rejected: AtomicU64,
+}
+
+impl HotMessageCache {
+    pub fn new(max_bytes: usize, max_message_size: usize, reader_idle_ms: u64) -> Self {
+        Self {
+            max_bytes,
+            max_message_size: max_message_size.min(max_bytes),
+            reader_idle_ms,
+            inner: Mutex::new(CacheInner::default()),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            evictions: AtomicU64::new(0),
+            rejected: AtomicU64::new(0),
+        }
+    }
+
+    /// `None` unless the cache is enabled.
+    pub fn from_config(config: &MessageStoreConfig) -> Option<Self> {
+        if !config.hot_message_cache_enable || config.hot_message_cache_max_bytes == 0 {
+            return None;
+        }
+        Some(Self::new(
+            config.hot_message_cache_max_bytes,
+            config.hot_message_cache_max_message_size,
+            config.hot_message_cache_reader_idle_ms,
+        ))
+    }
+
+    /// Caches a dispatched message if its queue has an active reader.
+    pub fn put(
+        &self,
+        topic: &CheetahString,
+        queue_id: i32,
+        queue_offset: i64,
+        commit_log_offset: i64,
+        data: Bytes,
+    ) {
+        if data.len() > self.max_message_size {
+            self.rejected.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+        let now = get_current_millis();
+        let mut inner = self.inner.lock();
+        let inner = &mut *inner;
+        let key = (topic.clone(), queue_id);
+        let Some(queue) = inner.queues.get_mut(&key) else {
+            return;
+        };
+        if now.saturating_sub(queue.last_read_timestamp) > self.reader_idle_ms {
+            // the reader went away, what it left behind will not be read from memory either
+            let released = std::mem::take(&mut queue.entries);
+            inner.cached_messages -= released.len();
+            inner.cached_bytes -= released.values().map(|msg| msg.data.len()).sum::<usize>();
+            inner.queues.remove(&key);
+            return;
+        }
+        let size = data.len();
+        let message = CachedMessage {
+            queue_offset,
+            commit_log_offset,
+            data,
+        };
+        match queue.entries.insert(queue_offset, message) {
+            // dispatched again, e.g. after a truncation
+            Some(replaced) => inner.cached_bytes -= replaced.data.len(),
+            None => {
+                inner.cached_messages += 1;
+                inner
+                    .order
+                    .push_back((topic.clone(), queue_id, queue_offset));
+            }
+        }
+        inner.cached_bytes += size;
+        self.evict(inner);
+    }
+
+    /// Returns the consecutive messages of the queue starting at `offset`, at most
+    /// `max_msg_nums` of them and, past the first one, no more than `max_size` bytes. Empty
+    /// when `offset` is not cached.
+    pub fn get(
+        &self,
+        topic: &CheetahString,
+        queue_id: i32,
+        offset: i64,
+        max_msg_nums: i32,
+        max_size: i32,
+    ) -> Vec<CachedMessage> {
+        let mut messages = Vec::new();
+        let mut inner = self.inner.lock();
+        let queue = inner.queues.entry((topic.clone(), queue_id)).or_default();
+        queue.last_read_timestamp = get_current_millis();
+        let mut total_size = 0;
+        for (expected, (queue_offset, message)) in (offset..).zip(queue.entries.range(offset..)) {
+            if *queue_offset != expected || messages.len() >= max_msg_nums.max(1) as usize {
+                break;
+            }
+            if !messages.is_empty() && total_size + message.data.len() > max_size.max(0) as usize {
+                break;
+            }
+            total_size += message.data.len();
+            messages.push(message.clone());
+        }
+        drop(inner);
+        if messages.is_empty() {
+            self.misses.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.hits
+                .fetch_add(messages.len() as u64, Ordering::Relaxed);
+        }
+        messages
+    }
+
+    /// Drops every cached message of `topic`.
+    pub fn remove_topic(&self, topic: &CheetahString) {
+        let mut inner = self.inner.lock();
+        let inner = &mut *inner;
+        inner.queues.retain(|(queue_topic, _), queue| {
+            if queue_topic != topic {
+                return true;
+            }
+            inner.cached_messages -= queue.entries.len();
+            inner.cached_bytes -= queue
+                .entries
+                .values()
+                .map(|msg| msg.data.len())
+                .sum::<usize>();
+            false
+        });
+        Self::compact_order(inner);
+    }
+
+    /// Drops every cached message, used when the commit log is truncated.
+    pub fn clear(&self) {
+        *self.inner.lock() = CacheInner::default();
+    }
+
+    pub fn stats(&self) -> HotMessageCacheStats {
+        let inner = self.inner.lock();
+        HotMessageCacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            evictions: self.evictions.load(Ordering::Relaxed),
+            rejected: self.rejected.load(Ordering::Relaxed),
+            cached_messages: inner.cached_messages,
+            cached_bytes: inner.cached_bytes,
+        }
+    }
+
+    fn evict(&self, inner: &mut CacheInner) {
+        while inner.cached_bytes > self.max_bytes {
+            let Some((topic, queue_id, queue_offset)) = inner.order.pop_front() else {
+                break;
+            };
+            let Some(queue) = inner.queues.get_mut(&(topic, queue_id)) else {
+                continue;
+            };
+            if let Some(evicted) = queue.entries.remove(&queue_offset) {
+                inner.cached_messages -= 1;
+                inner.cached_bytes -= evicted.data.len();
+                self.evictions.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        // keys of messages dropped with their idle queue pile up otherwise
+        if inner.order.len() > inner.cached_messages * 2 + 1024 {
+            Self::compact_order(inner);
+        }
+    }
+
+    fn compact_order(inner: &mut CacheInner) {
+        let queues = &inner.queues;
+        inner.order.retain(|(topic, queue_id, queue_offset)| {
+            queues
+                .get(&(topic.clone(), *queue_id))
+                .is_some_and(|queue| queue.entries.contains_key(queue_offset))
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(len: usize) -> Bytes {
+        Bytes::from(vec![1u8; len])
+    }
+
+    #[test]
+    fn only_queues_with_a_reader_are_cached() {
+        let cache = HotMessageCache::new(1024, 256, 60_000);
+        let topic = CheetahString::from_static_str("TopicTest");
+        cache.put(&topic, 0, 0, 0, message(10));
+        assert_eq!(cache.stats().cached_messages, 0);
+
+        assert!(cache.get(&topic, 0, 0, 32, 1024).is_empty());
+        cache.put(&topic, 0, 0, 0, message(10));
+        cache.put(&topic, 0, 1, 10, message(10));
+        cache.put(&topic, 0, 3, 20, message(10));
+
+        let messages = cache.get(&topic, 0, 0, 32, 1024);
+        // offset 2 is missing, the run stops in front of it
+        assert_eq!(
+            messages
+                .iter()
+                .map(|msg| msg.queue_offset)
+                .collect::<Vec<_>>(),
+            vec![0, 1]
+        );
+        assert_eq!(messages[1].commit_log_offset, 10);
+        assert!(cache.get(&topic, 0, 2, 32, 1024).is_empty());
+
+        let stats = cache.stats();
+        assert_eq!(stats.hits, 2);
+        assert_eq!(stats.misses, 2);
+        assert_eq!(stats.cached_messages, 3);
+        assert_eq!(stats.cached_bytes, 30);
+        assert_eq!(stats.hit_ratio(), 0.5);
+    }
+
+    #[test]
+    fn memory_cap_evicts_oldest_first() {
+        let cache = HotMessageCache::new(100, 40, 60_000);
+        let topic = CheetahString::from_static_str("TopicTest");
+        cache.get(&topic, 0, 0, 1, 1);
+        cache.get(&topic, 1, 0, 1, 1);
+        for offset in 0..4 {
+            cache.put(&topic, 0, offset, offset * 30, message(30));
+        }
+        cache.put(&topic, 1, 0, 120, message(41));
+
+        let stats = cache.stats();
+        assert_eq!(stats.evictions, 1);
+        assert_eq!(stats.rejected, 1);
+        assert_eq!(stats.cached_bytes, 90);
+        assert!(cache.get(&topic, 0, 0, 32, 1024).is_empty());
+        assert_eq!(cache.get(&topic, 0, 1, 32, 1024).len(), 3);
+        // the size limit applies from the second message on
+        assert_eq!(cache.get(&topic, 0, 1, 32, 10).len(), 1);
+
+        cache.remove_topic(&topic);
+        assert_eq!(cache.stats().cached_bytes, 0);
+        assert_eq!(cache.stats().cached_messages, 0);
+    }
+}
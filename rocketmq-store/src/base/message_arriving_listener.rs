@@ -18,6 +18,9 @@ use std::collections::HashMap;
 
 use cheetah_string::CheetahString;
 
+/// Woken by the reput service for every message dispatched to a consume queue, including the
+/// extra queues of multi-dispatched messages, so suspended pull and pop requests can be answered
+/// without waiting for their next check.
 pub trait MessageArrivingListener {
     /// This method is called when a new message arrives.
     ///
@@ -15,7 +15,11 @@
  * limitations under the License.
  */
 use std::fmt::Display;
+use std::future::Future;
+use std::pin::Pin;
 use std::sync::Arc;
+use std::task::Context;
+use std::task::Poll;
 
 use crate::base::message_status_enum::AppendMessageStatus;
 use crate::base::message_status_enum::PutMessageStatus;
@@ -185,6 +189,42 @@ impl PutMessageResult {
     }
 }
 
+/// Completion of a put whose message is already appended to the commit log, resolving once
+/// the flush and replication the store requires for it are done.
+///
+/// Returning it lets the caller release the store before waiting, so appends of other
+/// producers are not serialized behind this message's flush.
+pub struct PutMessageFuture {
+    inner: Pin<Box<dyn Future<Output = PutMessageResult> + Send>>,
+}
+
+impl PutMessageFuture {
+    pub fn new(future: impl Future<Output = PutMessageResult> + Send + 'static) -> Self {
+        Self {
+            inner: Box::pin(future),
+        }
+    }
+
+    /// A future that is already complete, for puts rejected before or during the append.
+    pub fn ready(result: PutMessageResult) -> Self {
+        Self::new(std::future::ready(result))
+    }
+}
+
+impl From<PutMessageResult> for PutMessageFuture {
+    fn from(result: PutMessageResult) -> Self {
+        Self::ready(result)
+    }
+}
+
+impl Future for PutMessageFuture {
+    type Output = PutMessageResult;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.inner.as_mut().poll(cx)
+    }
+}
+
 #[cfg(test)]
 mod put_message_result_tests {
     use super::*;
@@ -248,6 +288,26 @@ mod put_message_result_tests {
         assert!(!result.is_ok());
     }
 
+    #[tokio::test]
+    async fn put_message_future_resolves_to_result() {
+        let ready: PutMessageFuture =
+            PutMessageResult::new_default(PutMessageStatus::MessageIllegal).into();
+        assert_eq!(
+            ready.await.put_message_status(),
+            PutMessageStatus::MessageIllegal
+        );
+
+        let (tx, rx) = tokio::sync::oneshot::channel();
+        let pending = PutMessageFuture::new(async move {
+            PutMessageResult::new_default(rx.await.unwrap_or(PutMessageStatus::UnknownError))
+        });
+        tx.send(PutMessageStatus::FlushDiskTimeout).unwrap();
+        assert_eq!(
+            pending.await.put_message_status(),
+            PutMessageStatus::FlushDiskTimeout
+        );
+    }
+
     #[test]
     fn is_not_ok_with_append_result_not_ok() {
         let append_result = Some(create_append_message_result(AppendMessageStatus::EndOfFile));
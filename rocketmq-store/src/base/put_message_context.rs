@@ -14,11 +14,23 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
-#[derive(Debug, Clone, Default)]
+use std::fmt;
+use std::fmt::Write;
+use std::ops::Deref;
+use std::ops::DerefMut;
+use std::sync::Arc;
+
+use rocketmq_common::common::message::message_ext_broker_inner::MessageExtBrokerInner;
+
+use crate::config::message_store_config::MessageStoreConfig;
+use crate::message_encoder::message_ext_encoder::MessageExtEncoder;
+
+#[derive(Debug, Default)]
 pub struct PutMessageContext {
     topic_queue_table_key: String,
     phy_pos: Vec<i64>,
     batch_size: i32,
+    buffers: Option<PooledPutMessageBuffers>,
 }
 
 impl PutMessageContext {
@@ -27,6 +39,16 @@ impl PutMessageContext {
             topic_queue_table_key,
             phy_pos: Vec::new(),
             batch_size: 0,
+            buffers: None,
+        }
+    }
+
+    /// Creates a context for a single put that owns `buffers` until the context is dropped,
+    /// after which they go back to their pool for the next put.
+    pub fn with_buffers(buffers: PooledPutMessageBuffers) -> Self {
+        PutMessageContext {
+            buffers: Some(buffers),
+            ..Default::default()
         }
     }
 
@@ -57,4 +79,149 @@ impl PutMessageContext {
     pub fn set_topic_queue_table_key(&mut self, topic_queue_table_key: String) {
         self.topic_queue_table_key = topic_queue_table_key;
     }
+
+    /// Sets the topic queue key to `{topic}-{queue_id}` of `msg`.
+    pub fn set_topic_queue_table_key_of(&mut self, msg: &MessageExtBrokerInner) {
+        self.topic_queue_table_key.clear();
+        self.topic_queue_table_key.push_str(msg.topic());
+        self.topic_queue_table_key.push('-');
+        let _ = write!(self.topic_queue_table_key, "{}", msg.queue_id());
+    }
+
+    pub fn get_buffers_mut(&mut self) -> Option<&mut PutMessageBuffers> {
+        self.buffers.as_deref_mut()
+    }
+}
+
+/// Scratch state of one put: the encoder whose buffer holds the encoded message until it is
+/// appended to the commit log.
+pub struct PutMessageBuffers {
+    encoder: MessageExtEncoder,
+}
+
+impl PutMessageBuffers {
+    pub(crate) fn encoder_mut(&mut self) -> &mut MessageExtEncoder {
+        &mut self.encoder
+    }
+}
+
+/// Pool of [`PutMessageBuffers`] shared by all puts of a commit log.
+///
+/// A put checks buffers out for its whole duration, across the awaits on the queue and put
+/// locks, so concurrent puts scheduled on the same runtime thread never share an encoder.
+pub struct PutMessageBufferPool {
+    message_store_config: Arc<MessageStoreConfig>,
+    idle: parking_lot::Mutex<Vec<PutMessageBuffers>>,
+    max_idle: usize,
+}
+
+impl PutMessageBufferPool {
+    pub fn new(message_store_config: Arc<MessageStoreConfig>) -> Self {
+        let max_idle = std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(1);
+        Self::with_max_idle(message_store_config, max_idle)
+    }
+
+    pub fn with_max_idle(message_store_config: Arc<MessageStoreConfig>, max_idle: usize) -> Self {
+        PutMessageBufferPool {
+            message_store_config,
+            idle: parking_lot::Mutex::new(Vec::with_capacity(max_idle)),
+            max_idle,
+        }
+    }
+
+    /// Takes idle buffers, or allocates new ones when every buffer is in use.
+    pub fn acquire(self: &Arc<Self>) -> PooledPutMessageBuffers {
+        let buffers = self.idle.lock().pop().unwrap_or_else(|| PutMessageBuffers {
+            encoder: MessageExtEncoder::new(Arc::clone(&self.message_store_config)),
+        });
+        PooledPutMessageBuffers {
+            buffers: Some(buffers),
+            pool: Arc::clone(self),
+        }
+    }
+
+    pub fn idle_count(&self) -> usize {
+        self.idle.lock().len()
+    }
+
+    fn release(&self, buffers: PutMessageBuffers) {
+        let mut idle = self.idle.lock();
+        if idle.len() < self.max_idle {
+            idle.push(buffers);
+        }
+    }
+}
+
+/// Buffers checked out of a [`PutMessageBufferPool`], returned to it on drop.
+pub struct PooledPutMessageBuffers {
+    buffers: Option<PutMessageBuffers>,
+    pool: Arc<PutMessageBufferPool>,
+}
+
+impl Deref for PooledPutMessageBuffers {
+    type Target = PutMessageBuffers;
+
+    fn deref(&self) -> &Self::Target {
+        self.buffers.as_ref().unwrap()
+    }
+}
+
+impl DerefMut for PooledPutMessageBuffers {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.buffers.as_mut().unwrap()
+    }
+}
+
+impl Drop for PooledPutMessageBuffers {
+    fn drop(&mut self) {
+        if let Some(buffers) = self.buffers.take() {
+            self.pool.release(buffers);
+        }
+    }
+}
+
+impl fmt::Debug for PooledPutMessageBuffers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PooledPutMessageBuffers")
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rocketmq_common::common::message::MessageTrait;
+
+    use super::*;
+
+    #[test]
+    fn buffers_return_to_pool_when_context_dropped() {
+        let pool = Arc::new(PutMessageBufferPool::with_max_idle(
+            Arc::new(MessageStoreConfig::default()),
+            1,
+        ));
+        let first = PutMessageContext::with_buffers(pool.acquire());
+        let second = PutMessageContext::with_buffers(pool.acquire());
+        assert_eq!(pool.idle_count(), 0);
+
+        drop(first);
+        drop(second);
+        assert_eq!(pool.idle_count(), 1);
+
+        let mut context = PutMessageContext::with_buffers(pool.acquire());
+        assert!(context.get_buffers_mut().is_some());
+        assert_eq!(pool.idle_count(), 0);
+    }
+
+    #[test]
+    fn topic_queue_table_key_is_rebuilt_in_place() {
+        let mut msg = MessageExtBrokerInner::default();
+        msg.set_topic("TopicA".into());
+        msg.message_ext_inner.queue_id = 3;
+        let mut context = PutMessageContext::default();
+        context.set_topic_queue_table_key("stale-key-with-more-bytes".to_string());
+        context.set_topic_queue_table_key_of(&msg);
+        assert_eq!(context.get_topic_queue_table_key(), "TopicA-3");
+    }
 }
@@ -0,0 +1,123 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::hint;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+
+use tokio::sync::Mutex;
+use tokio::sync::MutexGuard;
+
+/// Serializes appends to the commit log.
+///
+/// Like `PutMessageReentrantLock` and `PutMessageSpinLock` of the Java broker, the mutex parks
+/// waiting writers while the spin lock busy waits, which suits few writers with short appends.
+/// The lock is never held across an await point, so spinning does not stall the runtime.
+pub enum PutMessageLock {
+    Mutex(Mutex<()>),
+    Spin(PutMessageSpinLock),
+}
+
+impl PutMessageLock {
+    pub fn new(use_reentrant_lock: bool) -> Self {
+        if use_reentrant_lock {
+            PutMessageLock::Mutex(Mutex::new(()))
+        } else {
+            PutMessageLock::Spin(PutMessageSpinLock::default())
+        }
+    }
+
+    pub async fn lock(&self) -> PutMessageLockGuard<'_> {
+        match self {
+            PutMessageLock::Mutex(mutex) => PutMessageLockGuard::Mutex(mutex.lock().await),
+            PutMessageLock::Spin(spin_lock) => PutMessageLockGuard::Spin(spin_lock.lock()),
+        }
+    }
+
+    pub fn is_spin(&self) -> bool {
+        matches!(self, PutMessageLock::Spin(_))
+    }
+}
+
+pub enum PutMessageLockGuard<'a> {
+    Mutex(MutexGuard<'a, ()>),
+    Spin(PutMessageSpinLockGuard<'a>),
+}
+
+#[derive(Default)]
+pub struct PutMessageSpinLock {
+    locked: AtomicBool,
+}
+
+impl PutMessageSpinLock {
+    pub fn lock(&self) -> PutMessageSpinLockGuard<'_> {
+        loop {
+            if self
+                .locked
+                .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+                .is_ok()
+            {
+                return PutMessageSpinLockGuard { lock: self };
+            }
+            while self.locked.load(Ordering::Relaxed) {
+                hint::spin_loop();
+            }
+        }
+    }
+}
+
+pub struct PutMessageSpinLockGuard<'a> {
+    lock: &'a PutMessageSpinLock,
+}
+
+impl Drop for PutMessageSpinLockGuard<'_> {
+    fn drop(&mut self) {
+        self.lock.locked.store(false, Ordering::Release);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn both_locks_serialize_writers() {
+        for use_reentrant_lock in [true, false] {
+            let lock = Arc::new(PutMessageLock::new(use_reentrant_lock));
+            assert_eq!(lock.is_spin(), !use_reentrant_lock);
+            let counter = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+            let mut handles = Vec::new();
+            for _ in 0..4 {
+                let lock = lock.clone();
+                let counter = counter.clone();
+                handles.push(tokio::spawn(async move {
+                    for _ in 0..1000 {
+                        let _guard = lock.lock().await;
+                        // a racy read-modify-write only stays exact under the lock
+                        let value = counter.load(Ordering::Relaxed);
+                        counter.store(value + 1, Ordering::Relaxed);
+                    }
+                }));
+            }
+            for handle in handles {
+                handle.await.unwrap();
+            }
+            assert_eq!(counter.load(Ordering::Relaxed), 4000);
+        }
+    }
+}
@@ -0,0 +1,184 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicI64;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+
+use rocketmq_common::FileUtils::file_to_string;
+use rocketmq_common::FileUtils::string_to_file;
+use rocketmq_common::TimeUtils::get_current_millis;
+use serde::Deserialize;
+use serde::Serialize;
+use tracing::warn;
+
+/// Live progress of the commit log recovery, readable while the store is still starting.
+#[derive(Debug, Default)]
+pub struct RecoveryProgress {
+    total_files: AtomicU64,
+    files_done: AtomicU64,
+    current_offset: AtomicI64,
+    begin_timestamp: AtomicU64,
+    resumed_from_offset: AtomicI64,
+    finished: AtomicBool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecoveryProgressSnapshot {
+    pub total_files: u64,
+    pub files_done: u64,
+    pub current_offset: i64,
+    pub elapsed_ms: u64,
+    /// Estimated remaining time from the average cost per file, `-1` until one file is done.
+    pub eta_ms: i64,
+    /// File offset recovery resumed after, `-1` when it started from the checkpoint.
+    pub resumed_from_offset: i64,
+    pub finished: bool,
+}
+
+impl RecoveryProgress {
+    pub fn begin(&self, total_files: u64, resumed_from_offset: i64) {
+        self.total_files.store(total_files, Ordering::Release);
+        self.files_done.store(0, Ordering::Release);
+        self.current_offset.store(0, Ordering::Release);
+        self.resumed_from_offset
+            .store(resumed_from_offset, Ordering::Release);
+        self.begin_timestamp
+            .store(get_current_millis(), Ordering::Release);
+        self.finished.store(false, Ordering::Release);
+    }
+
+    pub fn update_offset(&self, offset: i64) {
+        self.current_offset.store(offset, Ordering::Release);
+    }
+
+    pub fn on_file_done(&self) -> RecoveryProgressSnapshot {
+        self.files_done.fetch_add(1, Ordering::AcqRel);
+        self.snapshot()
+    }
+
+    pub fn finish(&self) {
+        self.finished.store(true, Ordering::Release);
+    }
+
+    pub fn snapshot(&self) -> RecoveryProgressSnapshot {
+        let total_files = self.total_files.load(Ordering::Acquire);
+        let files_done = self.files_done.load(Ordering::Acquire);
+        let finished = self.finished.load(Ordering::Acquire);
+        let begin_timestamp = self.begin_timestamp.load(Ordering::Acquire);
+        let elapsed_ms = if begin_timestamp == 0 {
+            0
+        } else {
+            get_current_millis().saturating_sub(begin_timestamp)
+        };
+        let eta_ms = if finished {
+            0
+        } else if files_done == 0 {
+            -1
+        } else {
+            let remaining = total_files.saturating_sub(files_done);
+            (elapsed_ms / files_done * remaining) as i64
+        };
+        RecoveryProgressSnapshot {
+            total_files,
+            files_done,
+            current_offset: self.current_offset.load(Ordering::Acquire),
+            elapsed_ms,
+            eta_ms,
+            resumed_from_offset: self.resumed_from_offset.load(Ordering::Acquire),
+            finished,
+        }
+    }
+}
+
+/// Persisted marker of the last commit log file whose messages were fully dispatched and
+/// whose consume queues were flushed during an abnormal recovery.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecoveryCheckpoint {
+    pub last_verified_file_offset: i64,
+    pub timestamp: u64,
+}
+
+impl RecoveryCheckpoint {
+    pub fn load(path: &str) -> Option<RecoveryCheckpoint> {
+        let content = file_to_string(path).ok()?;
+        if content.trim().is_empty() {
+            return None;
+        }
+        match serde_json::from_str::<RecoveryCheckpoint>(&content) {
+            Ok(checkpoint) => Some(checkpoint),
+            Err(e) => {
+                warn!("ignore corrupted recovery progress file {}: {}", path, e);
+                None
+            }
+        }
+    }
+
+    pub fn save(path: &str, last_verified_file_offset: i64) {
+        let checkpoint = RecoveryCheckpoint {
+            last_verified_file_offset,
+            timestamp: get_current_millis(),
+        };
+        let content = serde_json::to_string(&checkpoint).unwrap_or_default();
+        if let Err(e) = string_to_file(&content, path) {
+            warn!("persist recovery progress to {} failed: {}", path, e);
+        }
+    }
+
+    pub fn clear(path: &str) {
+        let _ = std::fs::remove_file(path);
+        let _ = std::fs::remove_file(format!("{}.bak", path));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snapshot_reports_eta_after_first_file() {
+        let progress = RecoveryProgress::default();
+        progress.begin(4, -1);
+        assert_eq!(progress.snapshot().eta_ms, -1);
+        progress.update_offset(1024);
+        let snapshot = progress.on_file_done();
+        assert_eq!(snapshot.files_done, 1);
+        assert_eq!(snapshot.current_offset, 1024);
+        assert!(snapshot.eta_ms >= 0);
+        progress.finish();
+        assert_eq!(progress.snapshot().eta_ms, 0);
+    }
+
+    #[test]
+    fn checkpoint_round_trip_and_clear() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("recoveryProgress.json");
+        let path = path.to_str().unwrap();
+        assert!(RecoveryCheckpoint::load(path).is_none());
+        RecoveryCheckpoint::save(path, 1073741824);
+        assert_eq!(
+            RecoveryCheckpoint::load(path)
+                .unwrap()
+                .last_verified_file_offset,
+            1073741824
+        );
+        RecoveryCheckpoint::clear(path);
+        assert!(RecoveryCheckpoint::load(path).is_none());
+    }
+}
@@ -15,6 +15,7 @@
  * limitations under the License.
  */
 
+use std::ops::Range;
 use std::sync::Arc;
 
 use bytes::Bytes;
@@ -33,23 +34,63 @@ pub struct SelectMappedBufferResult {
     pub mapped_file: Option<Arc<DefaultMappedFile>>,
     /// Whether the buffer is in cache.
     pub is_in_cache: bool,
+    /// The message bytes when served from memory instead of a mapped file.
+    pub cached_bytes: Option<Bytes>,
 }
 
 impl SelectMappedBufferResult {
+    /// A result backed by `bytes` already in memory, e.g. from the hot message cache.
+    pub fn from_bytes(start_offset: u64, bytes: Bytes) -> Self {
+        Self {
+            start_offset,
+            size: bytes.len() as i32,
+            mapped_file: None,
+            is_in_cache: true,
+            cached_bytes: Some(bytes),
+        }
+    }
+
     /// Returns the buffer.
     pub fn get_buffer(&self) -> &[u8] {
-        self.mapped_file.as_ref().unwrap().get_mapped_file()
-            [self.start_offset as usize..(self.start_offset + self.size as u64) as usize]
-            .as_ref()
+        if let Some(bytes) = self.cached_bytes.as_ref() {
+            return bytes.as_ref();
+        }
+        let mapped_file = self.mapped_file.as_ref().unwrap();
+        mapped_file.get_mapped_file()[self.mapped_range(mapped_file)].as_ref()
     }
 
     pub fn get_buffer_slice_mut(&self) -> &mut [u8] {
-        self.mapped_file.as_ref().unwrap().get_mapped_file_mut()
-            [self.start_offset as usize..(self.start_offset + self.size as u64) as usize]
-            .as_mut()
+        let mapped_file = self.mapped_file.as_ref().unwrap();
+        mapped_file.get_mapped_file_mut()[self.mapped_range(mapped_file)].as_mut()
+    }
+
+    /// Returns the selected bytes for writing to a connection without copying them out of the
+    /// mapped file. The buffer holds its own reference on the mapped file, released when the
+    /// last clone is dropped, so the file stays mapped until the bytes are written out.
+    pub fn transfer_bytes(&self) -> Option<Bytes> {
+        if let Some(bytes) = self.cached_bytes.as_ref() {
+            return Some(bytes.clone());
+        }
+        let mapped_file = self.mapped_file.as_ref()?;
+        if self.size <= 0 || !mapped_file.hold() {
+            return None;
+        }
+        Some(Bytes::from_owner(MappedRegion {
+            range: self.mapped_range(mapped_file),
+            mapped_file: mapped_file.clone(),
+        }))
+    }
+
+    /// The selected range relative to the start of the mapped file.
+    fn mapped_range(&self, mapped_file: &DefaultMappedFile) -> Range<usize> {
+        let pos = (self.start_offset - mapped_file.get_file_from_offset()) as usize;
+        pos..pos + self.size as usize
     }
 
     pub fn get_bytes(&self) -> Option<Bytes> {
+        if let Some(bytes) = self.cached_bytes.as_ref() {
+            return Some(bytes.clone());
+        }
         if self.size <= 0 || self.mapped_file.is_none() {
             return None;
         }
@@ -66,3 +107,45 @@ impl SelectMappedBufferResult {
         }
     }
 }
+
+/// A range of a mapped file kept alive by a hold on the file.
+struct MappedRegion {
+    mapped_file: Arc<DefaultMappedFile>,
+    range: Range<usize>,
+}
+
+impl AsRef<[u8]> for MappedRegion {
+    fn as_ref(&self) -> &[u8] {
+        &self.mapped_file.get_mapped_file()[self.range.clone()]
+    }
+}
+
+impl Drop for MappedRegion {
+    fn drop(&mut self) {
+        self.mapped_file.release();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::consume_queue::mapped_file_queue::MappedFileQueue;
+
+    #[test]
+    fn transfer_bytes_reads_the_range_of_the_selected_file() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let store_path = temp_dir.path().to_string_lossy().into_owned();
+        let mut queue = MappedFileQueue::new(store_path, 1024, None);
+        queue.try_create_mapped_file(0).unwrap();
+        let mapped_file = queue.try_create_mapped_file(1024).unwrap();
+        assert!(mapped_file.append_message_bytes(&Bytes::from_static(b"hello world")));
+
+        let result = mapped_file.clone().select_mapped_buffer_size(6, 5).unwrap();
+        assert_eq!(result.start_offset, 1030);
+        assert_eq!(result.get_buffer(), b"world");
+        let transfer = result.transfer_bytes().unwrap();
+        let chunk = transfer.slice(1..3);
+        drop(transfer);
+        assert_eq!(chunk.as_ref(), b"or");
+    }
+}
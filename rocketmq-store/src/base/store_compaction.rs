@@ -0,0 +1,690 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::BufWriter;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use bytes::Bytes;
+use bytes::BytesMut;
+use cheetah_string::CheetahString;
+use memmap2::Mmap;
+use parking_lot::Mutex;
+use rocketmq_common::common::sys_flag::message_sys_flag::MessageSysFlag;
+use rocketmq_common::MessageDecoder::MESSAGE_MAGIC_CODE_V2;
+use rocketmq_common::MessageDecoder::MESSAGE_PHYSIC_OFFSET_POSITION;
+use tracing::info;
+
+use crate::base::decode_failure_stats::DecodeFailureStats;
+use crate::base::dispatch_request::DispatchRequest;
+use crate::base::store_checkpoint::StoreCheckpoint;
+use crate::config::message_store_config::MessageStoreConfig;
+use crate::index::index_service::IndexService;
+use crate::log_file::commit_log::check_message_and_return_size;
+use crate::log_file::commit_log::BLANK_MAGIC_CODE;
+use crate::log_file::commit_log::MESSAGE_MAGIC_CODE;
+use crate::queue::single_consume_queue::ConsumeQueue;
+use crate::queue::ConsumeQueueTrait;
+use crate::queue::FileQueueLifeCycle;
+use crate::store::running_flags::RunningFlags;
+use crate::store_path_config_helper::get_store_checkpoint;
+use crate::store_path_config_helper::get_store_path_consume_queue;
+
+pub const OFFSET_TRANSLATION_FILE: &str = "offset_translation";
+
+const COMMIT_LOG_DIR: &str = "commitlog";
+const CONFIG_DIR: &str = "config";
+// total size + blank magic code written at the end of a full commit log file
+const END_FILE_MIN_BLANK_LENGTH: usize = 4 + 4;
+
+/// Decides which messages an offline compaction leaves out.
+#[derive(Debug, Clone, Default)]
+pub struct CompactionFilter {
+    /// Messages stored before this timestamp are dropped, 0 keeps them all.
+    pub expire_before_timestamp: i64,
+    pub deleted_topics: HashSet<CheetahString>,
+}
+
+impl CompactionFilter {
+    fn is_deleted_topic(&self, topic: &CheetahString) -> bool {
+        self.deleted_topics.contains(topic)
+    }
+
+    fn is_expired(&self, store_timestamp: i64) -> bool {
+        store_timestamp < self.expire_before_timestamp
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OffsetTranslation {
+    pub new_offset: i64,
+    pub size: i32,
+}
+
+/// Commit log offset of every kept message in the source store, mapped to its offset in the
+/// compacted one.
+#[derive(Debug, Default)]
+pub struct OffsetTranslationMap {
+    entries: BTreeMap<i64, OffsetTranslation>,
+}
+
+impl OffsetTranslationMap {
+    pub fn insert(&mut self, old_offset: i64, new_offset: i64, size: i32) {
+        self.entries
+            .insert(old_offset, OffsetTranslation { new_offset, size });
+    }
+
+    pub fn translate(&self, old_offset: i64) -> Option<i64> {
+        self.entries
+            .get(&old_offset)
+            .map(|translation| translation.new_offset)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Checks that the kept messages keep their order and are laid out back to back from
+    /// offset 0, the only gap allowed is the blank tail of a commit log file.
+    pub fn verify(&self, mapped_file_size: i64) -> Result<(), String> {
+        let mut expected_offset = 0i64;
+        for (old_offset, translation) in &self.entries {
+            if translation.new_offset != expected_offset {
+                let next_file_offset = (expected_offset / mapped_file_size + 1) * mapped_file_size;
+                if translation.new_offset != next_file_offset {
+                    return Err(format!(
+                        "message at offset {} moved to {}, expected {} or {}",
+                        old_offset, translation.new_offset, expected_offset, next_file_offset
+                    ));
+                }
+            }
+            expected_offset = translation.new_offset + translation.size as i64;
+        }
+        Ok(())
+    }
+
+    /// Writes the map as `old new size` lines, so message ids of the source store can still be
+    /// resolved against the compacted one.
+    pub fn write_to(&self, path: &Path) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        for (old_offset, translation) in &self.entries {
+            writeln!(
+                writer,
+                "{} {} {}",
+                old_offset, translation.new_offset, translation.size
+            )?;
+        }
+        writer.flush()
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct StoreCompactionReport {
+    pub scanned_messages: u64,
+    pub kept_messages: u64,
+    pub expired_messages: u64,
+    pub deleted_topic_messages: u64,
+    /// Size of the scanned and of the kept messages.
+    pub source_bytes: u64,
+    pub target_bytes: u64,
+    pub translation: OffsetTranslationMap,
+}
+
+/// Rewrites the commit log under `source_store_root` into the empty store described by
+/// `target_config`, leaving out the messages rejected by `filter`.
+///
+/// Consume queues keep the queue offsets of the source, so consumer offsets copied along with
+/// the `config` directory stay valid, and the index is rebuilt when `message_index_enable` is
+/// set. Commit log offsets start again from 0, the translation map written to
+/// [`OFFSET_TRANSLATION_FILE`] relates them to the source. The source store must not be running
+/// and this must not be called from within an async runtime.
+pub fn compact_store(
+    source_store_root: &Path,
+    target_config: &MessageStoreConfig,
+    filter: &CompactionFilter,
+) -> io::Result<StoreCompactionReport> {
+    let target_root = PathBuf::from(target_config.store_path_root_dir.as_str());
+    let target_commit_log_dir = PathBuf::from(target_config.get_store_path_commit_log());
+    if target_commit_log_dir.exists() && fs::read_dir(&target_commit_log_dir)?.next().is_some() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!(
+                "commit log dir {} is not empty, compaction needs a fresh target store",
+                target_commit_log_dir.display()
+            ),
+        ));
+    }
+    fs::create_dir_all(&target_commit_log_dir)?;
+
+    let target_config = Arc::new(target_config.clone());
+    let checkpoint = Arc::new(StoreCheckpoint::new(get_store_checkpoint(
+        target_root.to_str().unwrap(),
+    ))?);
+    let mut writer = CommitLogWriter::new(
+        target_commit_log_dir,
+        target_config.mapped_file_size_commit_log,
+    );
+    let mut queues = QueueRebuilder::new(target_config.clone(), checkpoint.clone());
+    // the index service spawns a flush of every full index file, those tasks never run on this
+    // runtime and all files are flushed once the rebuild is done
+    let runtime = tokio::runtime::Builder::new_current_thread().build()?;
+    let _guard = runtime.enter();
+    let index_service = target_config.message_index_enable.then(|| {
+        IndexService::new(
+            target_config.clone(),
+            checkpoint.clone(),
+            Arc::new(Mutex::new(HashMap::new())),
+        )
+    });
+
+    let mut report = StoreCompactionReport::default();
+    let mut last_store_timestamp = 0i64;
+    for_each_message(
+        source_store_root,
+        &target_config,
+        |old_offset, message, mut dispatch_request| {
+            let total_size = dispatch_request.msg_size;
+            report.scanned_messages += 1;
+            report.source_bytes += total_size as u64;
+            if filter.is_deleted_topic(&dispatch_request.topic) {
+                report.deleted_topic_messages += 1;
+                return Ok(());
+            }
+            if filter.is_expired(dispatch_request.store_timestamp) {
+                report.expired_messages += 1;
+                return Ok(());
+            }
+
+            let new_offset = writer.append(BytesMut::from(message))?;
+            report
+                .translation
+                .insert(old_offset, new_offset, total_size);
+            report.kept_messages += 1;
+            report.target_bytes += total_size as u64;
+            last_store_timestamp = dispatch_request.store_timestamp;
+            dispatch_request.commit_log_offset = new_offset;
+            queues.put(&dispatch_request)?;
+            if let Some(index_service) = index_service.as_ref() {
+                index_service.build_index(&dispatch_request);
+            }
+            Ok(())
+        },
+    )?;
+    writer.finish()?;
+
+    report
+        .translation
+        .verify(target_config.mapped_file_size_commit_log as i64)
+        .map_err(invalid_data)?;
+    queues.flush_and_verify()?;
+    if let Some(index_service) = index_service.as_ref() {
+        index_service.flush_all();
+    }
+    checkpoint.set_physic_msg_timestamp(last_store_timestamp as u64);
+    checkpoint.set_logics_msg_timestamp(last_store_timestamp as u64);
+    checkpoint.set_index_msg_timestamp(last_store_timestamp as u64);
+    checkpoint.flush()?;
+
+    copy_dir(
+        &source_store_root.join(CONFIG_DIR),
+        &target_root.join(CONFIG_DIR),
+    )?;
+    report
+        .translation
+        .write_to(&target_root.join(OFFSET_TRANSLATION_FILE))?;
+    info!(
+        "store {} compacted into {}, kept {} of {} messages, {} expired, {} of deleted topics",
+        source_store_root.display(),
+        target_root.display(),
+        report.kept_messages,
+        report.scanned_messages,
+        report.expired_messages,
+        report.deleted_topic_messages
+    );
+    Ok(report)
+}
+
+/// Appends messages to preallocated commit log files, sealing a file with a blank marker once
+/// the next message no longer fits.
+struct CommitLogWriter {
+    dir: PathBuf,
+    mapped_file_size: usize,
+    file: Option<BufWriter<File>>,
+    file_from_offset: i64,
+    wrote_position: usize,
+}
+
+impl CommitLogWriter {
+    fn new(dir: PathBuf, mapped_file_size: usize) -> Self {
+        Self {
+            dir,
+            mapped_file_size,
+            file: None,
+            file_from_offset: 0,
+            wrote_position: 0,
+        }
+    }
+
+    /// Writes the message with its physical offset rewritten, returning that offset.
+    fn append(&mut self, mut message: BytesMut) -> io::Result<i64> {
+        if message.len() + END_FILE_MIN_BLANK_LENGTH > self.mapped_file_size {
+            return Err(invalid_data(format!(
+                "message of {} bytes does not fit in a commit log file of {} bytes",
+                message.len(),
+                self.mapped_file_size
+            )));
+        }
+        if self.file.is_some()
+            && self.wrote_position + message.len() + END_FILE_MIN_BLANK_LENGTH
+                > self.mapped_file_size
+        {
+            self.seal()?;
+        }
+        if self.file.is_none() {
+            let file = File::create(self.dir.join(format!("{:020}", self.file_from_offset)))?;
+            file.set_len(self.mapped_file_size as u64)?;
+            self.file = Some(BufWriter::new(file));
+        }
+        let offset = self.file_from_offset + self.wrote_position as i64;
+        message[MESSAGE_PHYSIC_OFFSET_POSITION..MESSAGE_PHYSIC_OFFSET_POSITION + 8]
+            .copy_from_slice(&offset.to_be_bytes());
+        self.file.as_mut().unwrap().write_all(&message)?;
+        self.wrote_position += message.len();
+        Ok(offset)
+    }
+
+    fn seal(&mut self) -> io::Result<()> {
+        let mut file = self.file.take().unwrap();
+        let blank_size = (self.mapped_file_size - self.wrote_position) as i32;
+        file.write_all(&blank_size.to_be_bytes())?;
+        file.write_all(&BLANK_MAGIC_CODE.to_be_bytes())?;
+        file.into_inner()?.sync_all()?;
+        self.file_from_offset += self.mapped_file_size as i64;
+        self.wrote_position = 0;
+        Ok(())
+    }
+
+    fn finish(mut self) -> io::Result<()> {
+        if let Some(file) = self.file.take() {
+            file.into_inner()?.sync_all()?;
+        }
+        Ok(())
+    }
+}
+
+struct RebuiltQueue {
+    consume_queue: ConsumeQueue,
+    /// Queue offset and new commit log offset of the first and the last unit.
+    first: (i64, i64),
+    last: (i64, i64),
+}
+
+/// Rebuilds the consume queues of the kept messages, checking that every queue stays
+/// continuous.
+pub(crate) struct QueueRebuilder {
+    message_store_config: Arc<MessageStoreConfig>,
+    store_checkpoint: Arc<StoreCheckpoint>,
+    running_flags: Arc<RunningFlags>,
+    queues: HashMap<(CheetahString, i32), RebuiltQueue>,
+}
+
+impl QueueRebuilder {
+    pub(crate) fn new(
+        message_store_config: Arc<MessageStoreConfig>,
+        store_checkpoint: Arc<StoreCheckpoint>,
+    ) -> Self {
+        Self {
+            message_store_config,
+            store_checkpoint,
+            running_flags: Arc::new(RunningFlags::new()),
+            queues: HashMap::new(),
+        }
+    }
+
+    pub(crate) fn put(&mut self, dispatch_request: &DispatchRequest) -> io::Result<()> {
+        match MessageSysFlag::get_transaction_value(dispatch_request.sys_flag) {
+            MessageSysFlag::TRANSACTION_NOT_TYPE | MessageSysFlag::TRANSACTION_COMMIT_TYPE => {}
+            _ => return Ok(()),
+        }
+        let key = (dispatch_request.topic.clone(), dispatch_request.queue_id);
+        let unit = (
+            dispatch_request.consume_queue_offset,
+            dispatch_request.commit_log_offset,
+        );
+        let queue = match self.queues.get_mut(&key) {
+            Some(queue) => {
+                if unit.0 != queue.last.0 + 1 {
+                    return Err(invalid_data(format!(
+                        "queue {}-{} jumps from offset {} to {}",
+                        key.0, key.1, queue.last.0, unit.0
+                    )));
+                }
+                queue.last = unit;
+                queue
+            }
+            None => {
+                let consume_queue = ConsumeQueue::new(
+                    key.0.clone(),
+                    key.1,
+                    CheetahString::from_string(get_store_path_consume_queue(
+                        self.message_store_config.store_path_root_dir.as_str(),
+                    )),
+                    self.message_store_config.mapped_file_size_consume_queue as i32,
+                    self.message_store_config.clone(),
+                    self.running_flags.clone(),
+                    self.store_checkpoint.clone(),
+                );
+                self.queues.entry(key.clone()).or_insert(RebuiltQueue {
+                    consume_queue,
+                    first: unit,
+                    last: unit,
+                })
+            }
+        };
+        if !queue.consume_queue.put_message_position_info(
+            dispatch_request.commit_log_offset,
+            dispatch_request.msg_size,
+            dispatch_request.tags_code,
+            dispatch_request.consume_queue_offset,
+        ) {
+            return Err(io::Error::other(format!(
+                "put offset {} into queue {}-{} failed",
+                unit.0, key.0, key.1
+            )));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn flush_and_verify(&self) -> io::Result<()> {
+        for ((topic, queue_id), queue) in &self.queues {
+            queue.consume_queue.flush(0);
+            for (queue_offset, commit_log_offset) in [queue.first, queue.last] {
+                let pos = queue.consume_queue.get(queue_offset).map(|unit| unit.pos);
+                if pos != Some(commit_log_offset) {
+                    return Err(invalid_data(format!(
+                        "queue {}-{} offset {} points to {:?}, expected {}",
+                        topic, queue_id, queue_offset, pos, commit_log_offset
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Decodes every message of the commit log under `store_root` in offset order, handing its
+/// offset, raw bytes and dispatch request to `f`.
+pub(crate) fn for_each_message<F>(
+    store_root: &Path,
+    message_store_config: &Arc<MessageStoreConfig>,
+    mut f: F,
+) -> io::Result<()>
+where
+    F: FnMut(i64, Bytes, DispatchRequest) -> io::Result<()>,
+{
+    let decode_failure_stats = DecodeFailureStats::default();
+    'files: for (file_from_offset, path) in list_commit_log_files(store_root)? {
+        let file = File::open(&path)?;
+        let mapped = unsafe { Mmap::map(&file)? };
+        let mut position = 0usize;
+        while position + END_FILE_MIN_BLANK_LENGTH <= mapped.len() {
+            let total_size = read_i32(&mapped, position);
+            let magic_code = read_i32(&mapped, position + 4);
+            if magic_code == BLANK_MAGIC_CODE {
+                continue 'files;
+            }
+            if total_size <= 0
+                || (magic_code != MESSAGE_MAGIC_CODE && magic_code != MESSAGE_MAGIC_CODE_V2)
+            {
+                // past the last message written to the store
+                break 'files;
+            }
+            let offset = file_from_offset + position as i64;
+            if position + total_size as usize > mapped.len() {
+                return Err(invalid_data(format!(
+                    "message at offset {} exceeds its commit log file",
+                    offset
+                )));
+            }
+            let message = Bytes::copy_from_slice(&mapped[position..position + total_size as usize]);
+            position += total_size as usize;
+            let dispatch_request = check_message_and_return_size(
+                &mut message.clone(),
+                true,
+                false,
+                true,
+                message_store_config,
+                &decode_failure_stats,
+            );
+            if !dispatch_request.success || dispatch_request.msg_size != total_size {
+                return Err(invalid_data(format!(
+                    "message at offset {} can not be decoded",
+                    offset
+                )));
+            }
+            f(offset, message, dispatch_request)?;
+        }
+    }
+    Ok(())
+}
+
+fn list_commit_log_files(store_root: &Path) -> io::Result<Vec<(i64, PathBuf)>> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(store_root.join(COMMIT_LOG_DIR))? {
+        let path = entry?.path();
+        let file_from_offset = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.parse::<i64>().ok());
+        if let Some(file_from_offset) = file_from_offset {
+            files.push((file_from_offset, path));
+        }
+    }
+    files.sort_by_key(|(file_from_offset, _)| *file_from_offset);
+    Ok(files)
+}
+
+fn copy_dir(source: &Path, target: &Path) -> io::Result<()> {
+    if !source.is_dir() {
+        return Ok(());
+    }
+    fs::create_dir_all(target)?;
+    for entry in fs::read_dir(source)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target.join(entry.file_name()))?;
+        } else {
+            fs::copy(entry.path(), target.join(entry.file_name()))?;
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn read_i32(data: &[u8], position: usize) -> i32 {
+    i32::from_be_bytes(data[position..position + 4].try_into().unwrap())
+}
+
+pub(crate) fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use bytes::BufMut;
+    use rocketmq_common::common::message::MessageConst;
+    use rocketmq_common::CRC32Utils::crc32;
+
+    use super::*;
+
+    pub(crate) fn encode_message(topic: &str, queue_offset: i64, store_timestamp: i64) -> Vec<u8> {
+        let body = b"hello";
+        let properties = format!(
+            "{}\u{1}key-{}\u{2}",
+            MessageConst::PROPERTY_KEYS,
+            queue_offset
+        );
+        let total_size = 88 + body.len() + 1 + topic.len() + 2 + properties.len();
+        let mut buf = BytesMut::with_capacity(total_size);
+        buf.put_i32(total_size as i32);
+        buf.put_i32(MESSAGE_MAGIC_CODE);
+        buf.put_i32(crc32(body) as i32);
+        buf.put_i32(0); // queue id
+        buf.put_i32(0); // flag
+        buf.put_i64(queue_offset);
+        buf.put_i64(0); // physical offset, not checked by the compaction
+        buf.put_i32(0); // sys flag
+        buf.put_i64(store_timestamp);
+        buf.put_slice(&[127, 0, 0, 1, 0, 0, 0, 1]);
+        buf.put_i64(store_timestamp);
+        buf.put_slice(&[127, 0, 0, 1, 0, 0, 0, 2]);
+        buf.put_i32(0); // reconsume times
+        buf.put_i64(0); // prepared transaction offset
+        buf.put_i32(body.len() as i32);
+        buf.put_slice(body);
+        buf.put_u8(topic.len() as u8);
+        buf.put_slice(topic.as_bytes());
+        buf.put_i16(properties.len() as i16);
+        buf.put_slice(properties.as_bytes());
+        buf.to_vec()
+    }
+
+    fn read_i64(data: &[u8], position: usize) -> i64 {
+        i64::from_be_bytes(data[position..position + 8].try_into().unwrap())
+    }
+
+    #[test]
+    fn compact_store_drops_expired_and_deleted_topic_messages() {
+        let source = tempfile::tempdir().unwrap();
+        let target = tempfile::tempdir().unwrap();
+        let mut commit_log = Vec::new();
+        let mut old_offsets = Vec::new();
+        for (topic, queue_offset, store_timestamp) in [
+            ("A", 0, 1000),
+            ("A", 1, 2000),
+            ("B", 0, 2500),
+            ("A", 2, 3000),
+            ("A", 3, 4000),
+            ("B", 1, 4500),
+        ] {
+            old_offsets.push(commit_log.len() as i64);
+            commit_log.extend(encode_message(topic, queue_offset, store_timestamp));
+        }
+        let message_size = old_offsets[1];
+        commit_log.resize(4096, 0);
+        fs::create_dir_all(source.path().join("commitlog")).unwrap();
+        fs::write(
+            source.path().join("commitlog").join("00000000000000000000"),
+            commit_log,
+        )
+        .unwrap();
+        fs::create_dir_all(source.path().join("config")).unwrap();
+        fs::write(source.path().join("config").join("topics.json"), b"{}").unwrap();
+
+        let target_config = MessageStoreConfig {
+            store_path_root_dir: CheetahString::from(target.path().to_string_lossy().to_string()),
+            // room for two messages per file
+            mapped_file_size_commit_log: (message_size * 2 + 16) as usize,
+            mapped_file_size_consume_queue: 20 * 16,
+            message_index_enable: true,
+            max_hash_slot_num: 16,
+            max_index_num: 64,
+            ..Default::default()
+        };
+        let filter = CompactionFilter {
+            expire_before_timestamp: 1500,
+            deleted_topics: HashSet::from([CheetahString::from_static_str("B")]),
+        };
+        let report = compact_store(source.path(), &target_config, &filter).unwrap();
+
+        assert_eq!(report.scanned_messages, 6);
+        assert_eq!(report.kept_messages, 3);
+        assert_eq!(report.expired_messages, 1);
+        assert_eq!(report.deleted_topic_messages, 2);
+        let file_size = target_config.mapped_file_size_commit_log as i64;
+        assert_eq!(report.translation.translate(old_offsets[1]), Some(0));
+        assert_eq!(
+            report.translation.translate(old_offsets[3]),
+            Some(message_size)
+        );
+        assert_eq!(
+            report.translation.translate(old_offsets[4]),
+            Some(file_size)
+        );
+        assert_eq!(report.translation.translate(old_offsets[2]), None);
+
+        // the physical offset stored in the message follows it to the new file
+        let second_file = fs::read(
+            target
+                .path()
+                .join("commitlog")
+                .join(format!("{:020}", file_size)),
+        )
+        .unwrap();
+        assert_eq!(
+            read_i64(&second_file, MESSAGE_PHYSIC_OFFSET_POSITION),
+            file_size
+        );
+
+        // queue offsets are kept, the units point to the new commit log offsets
+        let consume_queue = fs::read(
+            target
+                .path()
+                .join("consumequeue")
+                .join("A")
+                .join("0")
+                .join("00000000000000000000"),
+        )
+        .unwrap();
+        assert_eq!(read_i64(&consume_queue, 20), 0);
+        assert_eq!(read_i64(&consume_queue, 40), message_size);
+        assert_eq!(read_i64(&consume_queue, 60), file_size);
+        assert!(!target.path().join("consumequeue").join("B").exists());
+
+        assert!(fs::read_dir(target.path().join("index"))
+            .unwrap()
+            .next()
+            .is_some());
+        assert!(target.path().join("config").join("topics.json").exists());
+        let translation = fs::read_to_string(target.path().join(OFFSET_TRANSLATION_FILE)).unwrap();
+        assert_eq!(translation.lines().count(), 3);
+
+        // the target now holds data
+        assert!(compact_store(source.path(), &target_config, &filter).is_err());
+    }
+
+    #[test]
+    fn verify_rejects_gaps_inside_a_file() {
+        let mut translation = OffsetTranslationMap::default();
+        translation.insert(100, 0, 50);
+        translation.insert(150, 1024, 50);
+        assert!(translation.verify(1024).is_ok());
+
+        translation.insert(200, 1100, 50);
+        assert!(translation.verify(1024).is_err());
+    }
+}
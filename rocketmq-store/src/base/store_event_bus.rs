@@ -0,0 +1,156 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+
+use cheetah_string::CheetahString;
+use tokio::sync::broadcast;
+
+use crate::config::broker_role::BrokerRole;
+
+const DEFAULT_CAPACITY: usize = 1024;
+
+/// The store files an event refers to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StoreFileKind {
+    CommitLog,
+    ConsumeQueue,
+}
+
+/// Something that happened inside the store other subsystems may want to react to.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StoreEvent {
+    /// Appending moved on to a new commit log file.
+    CommitLogRolled {
+        file_name: CheetahString,
+        file_from_offset: u64,
+    },
+    /// Expired files were removed, `min_offset` is the lowest offset still stored.
+    FileDeleted {
+        kind: StoreFileKind,
+        deleted_files: usize,
+        min_offset: i64,
+    },
+    /// The reput service dispatched everything up to the confirm offset.
+    DispatchCaughtUp { reput_offset: i64 },
+    /// Controller mode changed the role of this store.
+    RoleChanged { role: BrokerRole, epoch: i32 },
+    /// The disk of `kind` crossed the warning watermark (`disk_full`) or dropped below it again.
+    DiskWarning {
+        kind: StoreFileKind,
+        used_ratio: f64,
+        disk_full: bool,
+    },
+}
+
+impl StoreEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            StoreEvent::CommitLogRolled { .. } => "CommitLogRolled",
+            StoreEvent::FileDeleted { .. } => "FileDeleted",
+            StoreEvent::DispatchCaughtUp { .. } => "DispatchCaughtUp",
+            StoreEvent::RoleChanged { .. } => "RoleChanged",
+            StoreEvent::DiskWarning { .. } => "DiskWarning",
+        }
+    }
+}
+
+/// Broadcasts [`StoreEvent`]s from the subsystem that observed them to any number of
+/// subscribers, so the store modules do not have to call into each other directly.
+///
+/// Publishing never blocks. A subscriber that falls more than the bus capacity behind loses
+/// the oldest events and sees a `Lagged` error on its next receive.
+pub struct StoreEventBus {
+    sender: broadcast::Sender<StoreEvent>,
+    published: AtomicU64,
+}
+
+impl Default for StoreEventBus {
+    fn default() -> Self {
+        Self::new(DEFAULT_CAPACITY)
+    }
+}
+
+impl StoreEventBus {
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity.max(1));
+        Self {
+            sender,
+            published: AtomicU64::new(0),
+        }
+    }
+
+    pub fn publish(&self, event: StoreEvent) {
+        self.published.fetch_add(1, Ordering::Relaxed);
+        // no subscriber is not an error, the event is simply dropped
+        let _ = self.sender.send(event);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<StoreEvent> {
+        self.sender.subscribe()
+    }
+
+    pub fn published(&self) -> u64 {
+        self.published.load(Ordering::Relaxed)
+    }
+
+    pub fn subscriber_count(&self) -> usize {
+        self.sender.receiver_count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn every_subscriber_receives_published_events() {
+        let bus = StoreEventBus::new(8);
+        bus.publish(StoreEvent::DispatchCaughtUp { reput_offset: 1 });
+        let mut first = bus.subscribe();
+        let mut second = bus.subscribe();
+        let event = StoreEvent::FileDeleted {
+            kind: StoreFileKind::CommitLog,
+            deleted_files: 2,
+            min_offset: 1024,
+        };
+        bus.publish(event.clone());
+
+        assert_eq!(first.recv().await.unwrap(), event);
+        assert_eq!(second.recv().await.unwrap(), event);
+        assert_eq!(bus.published(), 2);
+        assert_eq!(bus.subscriber_count(), 2);
+    }
+
+    #[tokio::test]
+    async fn slow_subscriber_lags_instead_of_blocking() {
+        let bus = StoreEventBus::new(2);
+        let mut receiver = bus.subscribe();
+        for reput_offset in 0..4 {
+            bus.publish(StoreEvent::DispatchCaughtUp { reput_offset });
+        }
+        assert!(matches!(
+            receiver.recv().await,
+            Err(broadcast::error::RecvError::Lagged(2))
+        ));
+        assert_eq!(
+            receiver.recv().await.unwrap(),
+            StoreEvent::DispatchCaughtUp { reput_offset: 2 }
+        );
+    }
+}
@@ -0,0 +1,150 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::base::decode_failure_stats::DecodeFailureSnapshot;
+use crate::base::recovery_progress::RecoveryProgressSnapshot;
+
+/// Point-in-time health snapshot of the message store, one section per subsystem.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StoreHealth {
+    pub healthy: bool,
+    pub timestamp: i64,
+    pub commit_log: CommitLogHealth,
+    pub consume_queue: ConsumeQueueHealth,
+    pub ha: HAHealth,
+    pub clean_service: CleanServiceHealth,
+    pub disk: DiskHealth,
+    /// Progress of the last commit log recovery, so a slow startup can be followed.
+    #[serde(default)]
+    pub recovery: RecoveryProgressSnapshot,
+    /// Entries recovery and reput failed to decode since startup, by failure kind.
+    #[serde(default)]
+    pub decode_failures: DecodeFailureSnapshot,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommitLogHealth {
+    pub writeable: bool,
+    pub max_offset: i64,
+    pub flushed_offset: i64,
+    pub unflushed_bytes: i64,
+    /// Time since the last flushed message was stored, `0` when nothing is pending.
+    pub flush_lag_ms: i64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConsumeQueueHealth {
+    pub writeable: bool,
+    pub logics_queue_error: bool,
+    pub index_file_error: bool,
+    pub dispatch_behind_bytes: i64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HAHealth {
+    pub connection_count: i32,
+    pub master_flushed_offset: i64,
+    pub slaves: Vec<SlaveGap>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SlaveGap {
+    pub address: String,
+    pub ack_offset: i64,
+    pub gap_bytes: i64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CleanServiceHealth {
+    /// Last time the commit log clean service ran, `0` if it never ran.
+    pub last_run_timestamp: i64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiskHealth {
+    pub commit_log_used_ratio: f64,
+    pub consume_queue_used_ratio: f64,
+    pub max_used_ratio: f64,
+    pub disk_full: bool,
+    pub logic_disk_full: bool,
+}
+
+impl StoreHealth {
+    /// Recomputes `healthy` from the subsystem sections.
+    ///
+    /// The store is considered healthy when both the commit log and the consume queues accept
+    /// writes and no disk is over the configured ratio.
+    pub fn evaluate(&mut self) -> bool {
+        self.healthy = self.commit_log.writeable
+            && self.consume_queue.writeable
+            && !self.consume_queue.logics_queue_error
+            && !self.consume_queue.index_file_error
+            && !self.disk.disk_full
+            && !self.disk.logic_disk_full;
+        self.healthy
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn healthy_snapshot() -> StoreHealth {
+        StoreHealth {
+            commit_log: CommitLogHealth {
+                writeable: true,
+                ..Default::default()
+            },
+            consume_queue: ConsumeQueueHealth {
+                writeable: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn evaluate_reports_healthy_when_all_writeable() {
+        let mut health = healthy_snapshot();
+        assert!(health.evaluate());
+        assert!(health.healthy);
+    }
+
+    #[test]
+    fn evaluate_reports_unhealthy_on_disk_full() {
+        let mut health = healthy_snapshot();
+        health.disk.disk_full = true;
+        assert!(!health.evaluate());
+    }
+
+    #[test]
+    fn serializes_with_camel_case_keys() {
+        let json = serde_json::to_string(&healthy_snapshot()).unwrap();
+        assert!(json.contains("\"commitLog\""));
+        assert!(json.contains("\"flushLagMs\""));
+        assert!(json.contains("\"dispatchBehindBytes\""));
+    }
+}
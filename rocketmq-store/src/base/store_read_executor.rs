@@ -0,0 +1,160 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::future::Future;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Instant;
+
+use tokio::runtime::Runtime;
+use tokio::task::JoinError;
+use tracing::info_span;
+use tracing::Instrument;
+
+use crate::base::store_task_spawner::StoreTaskSpawner;
+
+/// A dedicated runtime for the store's read path (pulls, queries and HA transfer), so a read
+/// storm from lagging consumers queues up here instead of adding latency to puts.
+pub struct StoreReadExecutor {
+    runtime: Option<Runtime>,
+    stats: Arc<ReadQueueStats>,
+}
+
+#[derive(Default)]
+struct ReadQueueStats {
+    queued: AtomicUsize,
+    running: AtomicUsize,
+    peak_queued: AtomicUsize,
+    completed: AtomicU64,
+    total_queue_wait_micros: AtomicU64,
+}
+
+/// Point-in-time view of the read queue.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReadQueueSnapshot {
+    /// Reads submitted but not yet picked up by a reader thread.
+    pub queued: usize,
+    /// Reads currently executing.
+    pub running: usize,
+    /// Highest `queued` value seen since start.
+    pub peak_queued: usize,
+    pub completed: u64,
+    /// Average time a read waited before it started executing.
+    pub avg_queue_wait_micros: u64,
+}
+
+impl StoreReadExecutor {
+    pub fn new(thread_nums: usize) -> std::io::Result<Self> {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(thread_nums.max(1))
+            .thread_name("StoreReadThread")
+            .enable_all()
+            .build()?;
+        Ok(Self {
+            runtime: Some(runtime),
+            stats: Arc::new(ReadQueueStats::default()),
+        })
+    }
+
+    /// Runs `future` on a reader thread and waits for its output. Fails when the read panicked
+    /// or the executor was shut down before it completed.
+    pub async fn execute<F>(
+        &self,
+        subsystem: &'static str,
+        future: F,
+    ) -> Result<F::Output, JoinError>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        let stats = self.stats.clone();
+        let queued = stats.queued.fetch_add(1, Ordering::Relaxed) + 1;
+        stats.peak_queued.fetch_max(queued, Ordering::Relaxed);
+        let submitted = Instant::now();
+        let task = async move {
+            stats.queued.fetch_sub(1, Ordering::Relaxed);
+            stats.running.fetch_add(1, Ordering::Relaxed);
+            stats
+                .total_queue_wait_micros
+                .fetch_add(submitted.elapsed().as_micros() as u64, Ordering::Relaxed);
+            let output = future.await;
+            stats.running.fetch_sub(1, Ordering::Relaxed);
+            stats.completed.fetch_add(1, Ordering::Relaxed);
+            output
+        }
+        .instrument(info_span!("store_read", subsystem));
+        self.runtime.as_ref().unwrap().spawn(task).await
+    }
+
+    /// A spawner placing long-lived read tasks, such as HA connections, on the reader runtime.
+    pub fn spawner(&self) -> StoreTaskSpawner {
+        StoreTaskSpawner::new(
+            self.runtime
+                .as_ref()
+                .map(|runtime| runtime.handle().clone()),
+        )
+    }
+
+    pub fn snapshot(&self) -> ReadQueueSnapshot {
+        let completed = self.stats.completed.load(Ordering::Relaxed);
+        let total_wait = self.stats.total_queue_wait_micros.load(Ordering::Relaxed);
+        ReadQueueSnapshot {
+            queued: self.stats.queued.load(Ordering::Relaxed),
+            running: self.stats.running.load(Ordering::Relaxed),
+            peak_queued: self.stats.peak_queued.load(Ordering::Relaxed),
+            completed,
+            avg_queue_wait_micros: if completed == 0 {
+                0
+            } else {
+                total_wait / completed
+            },
+        }
+    }
+}
+
+impl Drop for StoreReadExecutor {
+    fn drop(&mut self) {
+        // dropping a runtime blocks, which panics when done from async context
+        if let Some(runtime) = self.runtime.take() {
+            runtime.shutdown_background();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn executes_reads_on_the_reader_runtime() {
+        let executor = StoreReadExecutor::new(1).unwrap();
+        let thread_name = executor
+            .execute("test", async {
+                std::thread::current().name().map(str::to_string)
+            })
+            .await
+            .unwrap();
+        assert_eq!(thread_name.as_deref(), Some("StoreReadThread"));
+
+        let snapshot = executor.snapshot();
+        assert_eq!(snapshot.completed, 1);
+        assert_eq!(snapshot.queued, 0);
+        assert_eq!(snapshot.running, 0);
+        assert_eq!(snapshot.peak_queued, 1);
+    }
+}
@@ -0,0 +1,273 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+
+use rocketmq_common::TimeUtils::get_current_millis;
+use serde::Deserialize;
+use serde::Serialize;
+use tracing::info;
+
+use crate::base::store_checkpoint::StoreCheckpoint;
+use crate::config::message_store_config::MessageStoreConfig;
+use crate::store_path_config_helper::get_abort_file;
+use crate::store_path_config_helper::get_store_checkpoint;
+
+pub const SNAPSHOT_MANIFEST_FILE: &str = "snapshot_manifest.json";
+
+const COMMIT_LOG_DIR: &str = "commitlog";
+const CHECKPOINT_FILE: &str = "checkpoint";
+/// Directories copied relative to the store root, the commit log is handled separately since
+/// it may live outside of the root.
+const STORE_ROOT_ENTRIES: [&str; 4] = ["consumequeue", "batchconsumequeue", "index", "config"];
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SnapshotFile {
+    /// Path relative to the snapshot directory.
+    pub path: String,
+    pub size: u64,
+}
+
+/// Describes a store snapshot. A slave bootstrapped from it must only trust the commit log up
+/// to `confirm_offset` and catches up from there through HA.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StoreSnapshotManifest {
+    pub min_phy_offset: i64,
+    pub confirm_offset: i64,
+    pub timestamp: u64,
+    pub files: Vec<SnapshotFile>,
+}
+
+/// Copies the store files into `snapshot_dir` and records them in a manifest.
+///
+/// `confirm_offset` must already be flushed to disk, bytes written after it may be copied
+/// half way and are discarded by the recovery of the bootstrapped slave.
+pub fn create_snapshot(
+    message_store_config: &MessageStoreConfig,
+    snapshot_dir: &Path,
+    min_phy_offset: i64,
+    confirm_offset: i64,
+) -> io::Result<StoreSnapshotManifest> {
+    if snapshot_dir.exists() && fs::read_dir(snapshot_dir)?.next().is_some() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("snapshot dir {} is not empty", snapshot_dir.display()),
+        ));
+    }
+    fs::create_dir_all(snapshot_dir)?;
+    let store_root = PathBuf::from(message_store_config.store_path_root_dir.as_str());
+    let mut files = Vec::new();
+    // checkpoint first, so that it never claims more than the copied commit log holds
+    copy_entry(
+        Path::new(&get_store_checkpoint(store_root.to_str().unwrap())),
+        snapshot_dir,
+        Path::new(CHECKPOINT_FILE),
+        &mut files,
+    )?;
+    for entry in STORE_ROOT_ENTRIES {
+        copy_entry(
+            &store_root.join(entry),
+            snapshot_dir,
+            Path::new(entry),
+            &mut files,
+        )?;
+    }
+    copy_entry(
+        Path::new(&message_store_config.get_store_path_commit_log()),
+        snapshot_dir,
+        Path::new(COMMIT_LOG_DIR),
+        &mut files,
+    )?;
+    let manifest = StoreSnapshotManifest {
+        min_phy_offset,
+        confirm_offset,
+        timestamp: get_current_millis(),
+        files,
+    };
+    fs::write(
+        snapshot_dir.join(SNAPSHOT_MANIFEST_FILE),
+        serde_json::to_vec_pretty(&manifest)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?,
+    )?;
+    info!(
+        "store snapshot created in {}, {} files, confirm offset {}",
+        snapshot_dir.display(),
+        manifest.files.len(),
+        confirm_offset
+    );
+    Ok(manifest)
+}
+
+/// Populates an empty store from a snapshot made by [`create_snapshot`].
+///
+/// The checkpoint is pinned to the snapshot confirm offset and an abort file is left behind, so
+/// the next load runs the abnormal recovery which truncates anything past the last valid
+/// message before the slave reports its offset to the master.
+pub fn bootstrap_from_snapshot(
+    message_store_config: &MessageStoreConfig,
+    snapshot_dir: &Path,
+) -> io::Result<StoreSnapshotManifest> {
+    let manifest = read_manifest(snapshot_dir)?;
+    let commit_log_dir = PathBuf::from(message_store_config.get_store_path_commit_log());
+    if commit_log_dir.exists() && fs::read_dir(&commit_log_dir)?.next().is_some() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!(
+                "commit log dir {} is not empty, only a fresh store can be bootstrapped",
+                commit_log_dir.display()
+            ),
+        ));
+    }
+    for file in &manifest.files {
+        let size = fs::metadata(snapshot_dir.join(&file.path))?.len();
+        if size != file.size {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "snapshot file {} has {} bytes, expected {}",
+                    file.path, size, file.size
+                ),
+            ));
+        }
+    }
+
+    let store_root = PathBuf::from(message_store_config.store_path_root_dir.as_str());
+    for file in &manifest.files {
+        let target = match Path::new(&file.path).strip_prefix(COMMIT_LOG_DIR) {
+            Ok(relative) => commit_log_dir.join(relative),
+            Err(_) => store_root.join(&file.path),
+        };
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::copy(snapshot_dir.join(&file.path), &target)?;
+    }
+
+    let checkpoint = StoreCheckpoint::new(get_store_checkpoint(store_root.to_str().unwrap()))?;
+    checkpoint.set_confirm_phy_offset(manifest.confirm_offset as u64);
+    checkpoint.set_master_flushed_offset(manifest.confirm_offset as u64);
+    checkpoint.flush()?;
+    File::create(get_abort_file(store_root.to_str().unwrap()))?;
+    info!(
+        "store bootstrapped from snapshot {}, catch up from offset {}",
+        snapshot_dir.display(),
+        manifest.confirm_offset
+    );
+    Ok(manifest)
+}
+
+pub fn read_manifest(snapshot_dir: &Path) -> io::Result<StoreSnapshotManifest> {
+    let data = fs::read(snapshot_dir.join(SNAPSHOT_MANIFEST_FILE))?;
+    serde_json::from_slice(&data).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+fn copy_entry(
+    source: &Path,
+    snapshot_dir: &Path,
+    relative: &Path,
+    files: &mut Vec<SnapshotFile>,
+) -> io::Result<()> {
+    if !source.exists() {
+        return Ok(());
+    }
+    if source.is_dir() {
+        let mut entries = fs::read_dir(source)?.collect::<io::Result<Vec<_>>>()?;
+        entries.sort_by_key(|entry| entry.file_name());
+        for entry in entries {
+            copy_entry(
+                &entry.path(),
+                snapshot_dir,
+                &relative.join(entry.file_name()),
+                files,
+            )?;
+        }
+        return Ok(());
+    }
+    let target = snapshot_dir.join(relative);
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let size = fs::copy(source, &target)?;
+    files.push(SnapshotFile {
+        path: relative.to_string_lossy().replace('\\', "/"),
+        size,
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use cheetah_string::CheetahString;
+
+    use super::*;
+
+    fn store_config(root: &Path) -> MessageStoreConfig {
+        MessageStoreConfig {
+            store_path_root_dir: CheetahString::from(root.to_string_lossy().to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn snapshot_round_trip_pins_confirm_offset() {
+        let master = tempfile::tempdir().unwrap();
+        let snapshot = tempfile::tempdir().unwrap();
+        let slave = tempfile::tempdir().unwrap();
+        fs::create_dir_all(master.path().join("commitlog")).unwrap();
+        fs::write(
+            master.path().join("commitlog").join("00000000000000000000"),
+            vec![1u8; 1024],
+        )
+        .unwrap();
+        fs::create_dir_all(master.path().join("consumequeue").join("t").join("0")).unwrap();
+        fs::write(
+            master
+                .path()
+                .join("consumequeue")
+                .join("t")
+                .join("0")
+                .join("00000000000000000000"),
+            vec![2u8; 20],
+        )
+        .unwrap();
+
+        let snapshot_dir = snapshot.path().join("snap");
+        let manifest =
+            create_snapshot(&store_config(master.path()), &snapshot_dir, 0, 512).unwrap();
+        assert_eq!(manifest.files.len(), 2);
+        assert_eq!(read_manifest(&snapshot_dir).unwrap(), manifest);
+
+        let slave_config = store_config(slave.path());
+        let restored = bootstrap_from_snapshot(&slave_config, &snapshot_dir).unwrap();
+        assert_eq!(restored.confirm_offset, 512);
+        assert_eq!(
+            fs::read(slave.path().join("commitlog").join("00000000000000000000")).unwrap(),
+            vec![1u8; 1024]
+        );
+        assert!(slave.path().join("abort").exists());
+        let checkpoint = StoreCheckpoint::new(slave.path().join("checkpoint")).unwrap();
+        assert_eq!(checkpoint.confirm_phy_offset(), 512);
+
+        // a store that already has data must not be overwritten
+        assert!(bootstrap_from_snapshot(&slave_config, &snapshot_dir).is_err());
+    }
+}
@@ -17,6 +17,7 @@
 use std::collections::BTreeMap;
 use std::collections::HashMap;
 use std::collections::LinkedList;
+use std::sync::atomic::AtomicU64;
 use std::sync::atomic::AtomicUsize;
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
@@ -27,8 +28,12 @@ use parking_lot::RwLock;
 use rocketmq_common::common::broker::broker_config::BrokerIdentity;
 use rocketmq_common::common::system_clock::SystemClock;
 use rocketmq_common::TimeUtils::get_current_millis;
+use tracing::info;
 
-const FREQUENCY_OF_SAMPLING: u64 = 1000;
+use crate::base::store_event_bus::StoreEvent;
+
+pub const FREQUENCY_OF_SAMPLING: u64 = 1000;
+const PRINT_TPS_INTERVAL_MILLIS: u64 = 60 * 1000;
 const MAX_RECORDS_OF_SAMPLING: usize = 60 * 10;
 const PUT_MESSAGE_ENTIRE_TIME_MAX_DESC: [&str; 13] = [
     "[<=0ms]",
@@ -63,8 +68,8 @@ lazy_static::lazy_static! {
 type AtomicUsizeArray = Arc<Vec<AtomicUsize>>;
 
 pub struct StoreStatsService {
-    buckets: BTreeMap<u64, AtomicUsize>,
-    last_buckets: BTreeMap<u64, AtomicUsize>,
+    buckets: RwLock<BTreeMap<u64, AtomicUsize>>,
+    last_buckets: RwLock<BTreeMap<u64, AtomicUsize>>,
     put_message_failed_times: AtomicUsize,
     put_message_topic_times_total: Arc<RwLock<HashMap<String, AtomicUsize>>>,
     put_message_topic_size_total: Arc<RwLock<HashMap<String, AtomicUsize>>>,
@@ -82,15 +87,16 @@ pub struct StoreStatsService {
     get_message_entire_time_max: Arc<AtomicUsize>,
     dispatch_max_buffer: Arc<AtomicUsize>,
     sampling_lock: Mutex<()>,
-    last_print_timestamp: u64,
+    last_print_timestamp: AtomicU64,
     broker_identity: Option<BrokerIdentity>,
+    store_event_times: Mutex<BTreeMap<&'static str, u64>>,
 }
 
 impl StoreStatsService {
     pub fn new(broker_identity: Option<BrokerIdentity>) -> Self {
-        Self {
-            buckets: BTreeMap::new(),
-            last_buckets: BTreeMap::new(),
+        let service = Self {
+            buckets: RwLock::new(BTreeMap::new()),
+            last_buckets: RwLock::new(BTreeMap::new()),
             put_message_failed_times: AtomicUsize::new(0),
             put_message_topic_times_total: Arc::new(RwLock::new(HashMap::new())),
             put_message_topic_size_total: Arc::new(RwLock::new(HashMap::new())),
@@ -110,9 +116,12 @@ impl StoreStatsService {
             get_message_entire_time_max: Arc::new(AtomicUsize::new(0)),
             dispatch_max_buffer: Arc::new(AtomicUsize::new(0)),
             sampling_lock: Mutex::new(()),
-            last_print_timestamp: get_current_millis(),
+            last_print_timestamp: AtomicU64::new(get_current_millis()),
             broker_identity,
-        }
+            store_event_times: Mutex::new(BTreeMap::new()),
+        };
+        service.reset_put_message_time_buckets();
+        service
     }
 }
 
@@ -133,37 +142,153 @@ impl StoreStatsService {
         &self.put_message_failed_times
     }
 
-    fn reset_put_message_time_buckets(&mut self) {
+    fn reset_put_message_time_buckets(&self) {
         let mut next_buckets: BTreeMap<u64, AtomicUsize> = BTreeMap::new();
-        let index = AtomicUsize::new(0);
+        let mut index = 0u64;
         for (&interval, &times) in PUT_MESSAGE_ENTIRE_TIME_BUCKETS.iter() {
             for _ in 0..times {
-                next_buckets.insert(
-                    index.fetch_add(interval as usize, Ordering::SeqCst) as u64,
-                    AtomicUsize::new(0),
-                );
+                index += interval as u64;
+                next_buckets.insert(index, AtomicUsize::new(0));
             }
         }
-        next_buckets.insert(usize::MAX as u64, AtomicUsize::new(0));
+        next_buckets.insert(u64::MAX, AtomicUsize::new(0));
 
-        self.last_buckets = self
-            .buckets
-            .iter()
-            .map(|(&key, value)| (key, AtomicUsize::new(value.load(Ordering::SeqCst))))
-            .collect();
-        self.buckets = next_buckets;
+        let last_buckets = std::mem::replace(&mut *self.buckets.write(), next_buckets);
+        *self.last_buckets.write() = last_buckets;
     }
 
+    /// Moves the distribution of the last interval into `last_put_message_distribute_time`, which
+    /// is what the runtime info reports.
     fn reset_put_message_distribute_time(&self) {
         for i in 0..13 {
-            self.put_message_distribute_time[i].store(0, Ordering::SeqCst);
-            self.last_put_message_distribute_time[i].store(0, Ordering::SeqCst);
+            let value = self.put_message_distribute_time[i].swap(0, Ordering::SeqCst);
+            self.last_put_message_distribute_time[i].store(value, Ordering::SeqCst);
         }
     }
 
-    pub fn set_put_message_entire_time_max(&self, _value: u64) {}
+    /// Records the entire time of one put, in milliseconds.
+    pub fn set_put_message_entire_time_max(&self, value: u64) {
+        let index = match value {
+            0 => 0,
+            1..10 => 1,
+            10..50 => 2,
+            50..100 => 3,
+            100..200 => 4,
+            200..500 => 5,
+            500..1000 => 6,
+            1000..2000 => 7,
+            2000..3000 => 8,
+            3000..4000 => 9,
+            4000..5000 => 10,
+            5000..10000 => 11,
+            _ => 12,
+        };
+        self.put_message_distribute_time[index].fetch_add(1, Ordering::Relaxed);
+        if let Some((_, bucket)) = self.buckets.read().range(value..).next() {
+            bucket.fetch_add(1, Ordering::Relaxed);
+        }
+        self.put_message_entire_time_max
+            .fetch_max(value as usize, Ordering::Relaxed);
+    }
+
+    pub fn set_get_message_entire_time_max(&self, value: u64) {
+        self.get_message_entire_time_max
+            .fetch_max(value as usize, Ordering::Relaxed);
+    }
 
-    // Add more methods as needed for functionality
+    pub fn set_dispatch_max_buffer(&self, value: u64) {
+        self.dispatch_max_buffer
+            .fetch_max(value as usize, Ordering::Relaxed);
+    }
+
+    /// Counts a successful put of `msg_num` messages taking `wrote_bytes` in the commit log.
+    pub fn record_put_message(&self, topic: &str, msg_num: usize, wrote_bytes: usize) {
+        {
+            let times_total = self.put_message_topic_times_total.read();
+            let size_total = self.put_message_topic_size_total.read();
+            if let (Some(times), Some(size)) = (times_total.get(topic), size_total.get(topic)) {
+                times.fetch_add(msg_num, Ordering::Relaxed);
+                size.fetch_add(wrote_bytes, Ordering::Relaxed);
+                return;
+            }
+        }
+        self.put_message_topic_times_total
+            .write()
+            .entry(topic.to_string())
+            .or_default()
+            .fetch_add(msg_num, Ordering::Relaxed);
+        self.put_message_topic_size_total
+            .write()
+            .entry(topic.to_string())
+            .or_default()
+            .fetch_add(wrote_bytes, Ordering::Relaxed);
+    }
+
+    /// Takes one snapshot of every counter the TPS figures are computed from, called every
+    /// `FREQUENCY_OF_SAMPLING` ms. The last ten minutes of snapshots are kept.
+    pub fn sampling(&self) {
+        let _guard = self.sampling_lock.lock();
+        let now = get_current_millis();
+        let samples = [
+            (&self.put_times_list, self.get_put_message_times_total()),
+            (
+                &self.get_times_found_list,
+                self.get_message_times_total_found.load(Ordering::Relaxed) as u64,
+            ),
+            (
+                &self.get_times_miss_list,
+                self.get_message_times_total_miss.load(Ordering::Relaxed) as u64,
+            ),
+            (
+                &self.transferred_msg_count_list,
+                self.get_message_transferred_msg_count
+                    .load(Ordering::Relaxed) as u64,
+            ),
+        ];
+        for (list, call_times_total) in samples {
+            let mut list = list.lock();
+            list.push_back(CallSnapshot::new(now, call_times_total));
+            if list.len() > MAX_RECORDS_OF_SAMPLING + 1 {
+                list.pop_front();
+            }
+        }
+    }
+
+    /// Logs the TPS once a minute and starts a new interval of the put time distribution.
+    pub fn print_tps(&self) {
+        let now = get_current_millis();
+        let last_print_timestamp = self.last_print_timestamp.load(Ordering::Relaxed);
+        if now < last_print_timestamp + PRINT_TPS_INTERVAL_MILLIS {
+            return;
+        }
+        self.last_print_timestamp.store(now, Ordering::Relaxed);
+        info!(
+            "[STORETPS] put_tps {} get_found_tps {} get_miss_tps {} get_transferred_tps {}",
+            self.get_put_tps_time(60),
+            self.get_get_found_tps_time(60),
+            self.get_get_miss_tps_time(60),
+            self.get_get_transferred_tps_time(60)
+        );
+        self.reset_put_message_distribute_time();
+        self.reset_put_message_time_buckets();
+        info!(
+            "[PAGECACHERT] TotalPut {}, PutMessageDistributeTime {}",
+            self.last_put_message_distribute_time
+                .iter()
+                .map(|time| time.load(Ordering::Relaxed))
+                .sum::<usize>(),
+            self.put_message_distribute_time_to_string()
+        );
+    }
+
+    /// Counts a store event by kind, fed by a subscription to the store event bus.
+    pub fn record_store_event(&self, event: &StoreEvent) {
+        *self
+            .store_event_times
+            .lock()
+            .entry(event.name())
+            .or_insert(0) += 1;
+    }
 
     pub fn get_runtime_info(&self) -> HashMap<String, String> {
         let mut result = HashMap::new();
@@ -203,6 +328,12 @@ impl StoreStatsService {
             "dispatchMaxBuffer".to_string(),
             self.dispatch_max_buffer.load(Ordering::Relaxed).to_string(),
         );
+        result.insert(
+            "getMessageTransferedMsgCount".to_string(),
+            self.get_message_transferred_msg_count
+                .load(Ordering::Relaxed)
+                .to_string(),
+        );
         result.insert(
             "getMessageEntireTimeMax".to_string(),
             self.get_message_entire_time_max
@@ -225,11 +356,14 @@ impl StoreStatsService {
             "putLatency999".to_string(),
             format!("{:.2}", self.find_put_message_entire_time_px(0.999)),
         );
+        for (name, times) in self.store_event_times.lock().iter() {
+            result.insert(format!("storeEvent{}", name), times.to_string());
+        }
         result
     }
 
     pub fn find_put_message_entire_time_px(&self, px: f64) -> f64 {
-        let last_buckets = &self.last_buckets;
+        let last_buckets = self.last_buckets.read();
         let start = Instant::now();
         let mut result = 0.0;
         let total_request: u64 = last_buckets
@@ -538,3 +672,55 @@ mod call_snapshot_tests {
         assert!(tps < 0.0);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn put_times_roll_into_runtime_info() {
+        let service = StoreStatsService::new(None);
+        service.record_put_message("TopicA", 1, 100);
+        service.record_put_message("TopicA", 2, 300);
+        service.record_put_message("TopicB", 1, 200);
+        service.set_put_message_entire_time_max(3);
+        service.set_put_message_entire_time_max(30);
+        service.set_put_message_entire_time_max(1500);
+        service
+            .get_message_transferred_msg_count()
+            .fetch_add(5, Ordering::Relaxed);
+
+        let info = service.get_runtime_info();
+        assert_eq!(info["putMessageTimesTotal"], "4");
+        assert_eq!(info["putMessageSizeTotal"], "600");
+        assert_eq!(info["putMessageAverageSize"], "150");
+        assert_eq!(info["putMessageEntireTimeMax"], "1500");
+        assert_eq!(info["getMessageTransferedMsgCount"], "5");
+        // the distribution is reported for the last finished interval
+        assert!(info["putMessageDistributeTime"].contains("[0~10ms]:0"));
+
+        service.last_print_timestamp.store(0, Ordering::Relaxed);
+        service.print_tps();
+        let info = service.get_runtime_info();
+        assert!(info["putMessageDistributeTime"].contains("[0~10ms]:1"));
+        assert!(info["putMessageDistributeTime"].contains("[10~50ms]:1"));
+        assert!(info["putMessageDistributeTime"].contains("[1~2s]:1"));
+        assert!(service.find_put_message_entire_time_px(0.99) > 1000.0);
+    }
+
+    #[test]
+    fn sampling_keeps_ten_minutes_of_snapshots() {
+        let service = StoreStatsService::new(None);
+        for _ in 0..MAX_RECORDS_OF_SAMPLING + 10 {
+            service.sampling();
+        }
+        assert_eq!(
+            service.put_times_list.lock().len(),
+            MAX_RECORDS_OF_SAMPLING + 1
+        );
+        assert_eq!(
+            service.transferred_msg_count_list.lock().len(),
+            MAX_RECORDS_OF_SAMPLING + 1
+        );
+    }
+}
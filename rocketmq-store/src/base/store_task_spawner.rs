@@ -0,0 +1,71 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::future::Future;
+
+use tokio::runtime::Handle;
+use tokio::task::JoinHandle;
+use tracing::info_span;
+use tracing::Instrument;
+
+/// Spawns the store's background services, either on an injected runtime handle or on the
+/// ambient runtime of the caller.
+///
+/// Every task runs inside a `store_task` span carrying the subsystem label, so log lines of the
+/// flush, reput or cleaning services can be told apart when they share a runtime.
+#[derive(Clone, Default)]
+pub struct StoreTaskSpawner {
+    handle: Option<Handle>,
+}
+
+impl StoreTaskSpawner {
+    pub fn new(handle: Option<Handle>) -> Self {
+        Self { handle }
+    }
+
+    /// The injected handle, `None` when tasks go to the ambient runtime.
+    pub fn handle(&self) -> Option<&Handle> {
+        self.handle.as_ref()
+    }
+
+    pub fn spawn<F>(&self, subsystem: &'static str, future: F) -> JoinHandle<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        let future = future.instrument(info_span!("store_task", subsystem));
+        match self.handle {
+            Some(ref handle) => handle.spawn(future),
+            None => tokio::spawn(future),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spawns_on_the_injected_runtime() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let spawner = StoreTaskSpawner::new(Some(runtime.handle().clone()));
+        let task = spawner.spawn("test", async { 7 });
+        assert_eq!(runtime.block_on(task).unwrap(), 7);
+    }
+}
@@ -0,0 +1,787 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::io::BufWriter;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use cheetah_string::CheetahString;
+use parking_lot::Mutex;
+use rocketmq_common::common::mix_all;
+use rocketmq_common::common::sys_flag::message_sys_flag::MessageSysFlag;
+use tracing::info;
+
+use crate::base::store_checkpoint::StoreCheckpoint;
+use crate::base::store_compaction::for_each_message;
+use crate::base::store_compaction::invalid_data;
+use crate::base::store_compaction::read_i32;
+use crate::base::store_compaction::QueueRebuilder;
+use crate::config::message_store_config::MessageStoreConfig;
+use crate::index::index_header::INDEX_HEADER_SIZE;
+use crate::index::index_service::IndexService;
+use crate::queue::single_consume_queue::CQ_STORE_UNIT_SIZE;
+use crate::store_path_config_helper::get_store_checkpoint;
+use crate::store_path_config_helper::get_store_path_consume_queue;
+use crate::store_path_config_helper::get_store_path_index;
+
+const PLAN_QUEUE: &str = "queue";
+const PLAN_INDEX: &str = "index";
+const HASH_SLOT_SIZE: usize = 4;
+const INDEX_SIZE: usize = 20;
+
+/// Where a consume queue or index entry disagrees with the commit log.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Divergence {
+    /// The unit points to an offset where no message starts.
+    MissingMessage {
+        topic: String,
+        queue_id: i32,
+        queue_offset: i64,
+        commit_log_offset: i64,
+    },
+    SizeMismatch {
+        topic: String,
+        queue_id: i32,
+        queue_offset: i64,
+        commit_log_offset: i64,
+        unit_size: i32,
+        message_size: i32,
+    },
+    TagsCodeMismatch {
+        topic: String,
+        queue_id: i32,
+        queue_offset: i64,
+        commit_log_offset: i64,
+        unit_tags_code: i64,
+        message_tags_code: i64,
+    },
+    /// The unit points to a message stored for another queue or queue offset.
+    WrongMessage {
+        topic: String,
+        queue_id: i32,
+        queue_offset: i64,
+        commit_log_offset: i64,
+        message_topic: String,
+        message_queue_id: i32,
+        message_queue_offset: i64,
+    },
+    /// A message of the commit log no unit points to.
+    MissingUnit {
+        topic: String,
+        queue_id: i32,
+        queue_offset: i64,
+        commit_log_offset: i64,
+    },
+    /// An index entry points to an offset where no message starts.
+    DanglingIndex {
+        index_file: String,
+        index: i32,
+        commit_log_offset: i64,
+    },
+}
+
+impl Divergence {
+    fn queue(&self) -> Option<(&str, i32)> {
+        match self {
+            Divergence::MissingMessage {
+                topic, queue_id, ..
+            }
+            | Divergence::SizeMismatch {
+                topic, queue_id, ..
+            }
+            | Divergence::TagsCodeMismatch {
+                topic, queue_id, ..
+            }
+            | Divergence::WrongMessage {
+                topic, queue_id, ..
+            }
+            | Divergence::MissingUnit {
+                topic, queue_id, ..
+            } => Some((topic, *queue_id)),
+            Divergence::DanglingIndex { .. } => None,
+        }
+    }
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Divergence::MissingMessage {
+                topic,
+                queue_id,
+                queue_offset,
+                commit_log_offset,
+            } => write!(
+                f,
+                "{}-{}@{}: no message at commit log offset {}",
+                topic, queue_id, queue_offset, commit_log_offset
+            ),
+            Divergence::SizeMismatch {
+                topic,
+                queue_id,
+                queue_offset,
+                commit_log_offset,
+                unit_size,
+                message_size,
+            } => write!(
+                f,
+                "{}-{}@{}: size {} but the message at {} has {}",
+                topic, queue_id, queue_offset, unit_size, commit_log_offset, message_size
+            ),
+            Divergence::TagsCodeMismatch {
+                topic,
+                queue_id,
+                queue_offset,
+                commit_log_offset,
+                unit_tags_code,
+                message_tags_code,
+            } => write!(
+                f,
+                "{}-{}@{}: tags code {} but the message at {} has {}",
+                topic, queue_id, queue_offset, unit_tags_code, commit_log_offset, message_tags_code
+            ),
+            Divergence::WrongMessage {
+                topic,
+                queue_id,
+                queue_offset,
+                commit_log_offset,
+                message_topic,
+                message_queue_id,
+                message_queue_offset,
+            } => write!(
+                f,
+                "{}-{}@{}: the message at {} belongs to {}-{}@{}",
+                topic,
+                queue_id,
+                queue_offset,
+                commit_log_offset,
+                message_topic,
+                message_queue_id,
+                message_queue_offset
+            ),
+            Divergence::MissingUnit {
+                topic,
+                queue_id,
+                queue_offset,
+                commit_log_offset,
+            } => write!(
+                f,
+                "{}-{}@{}: no unit for the message at commit log offset {}",
+                topic, queue_id, queue_offset, commit_log_offset
+            ),
+            Divergence::DanglingIndex {
+                index_file,
+                index,
+                commit_log_offset,
+            } => write!(
+                f,
+                "index {} entry {}: no message at commit log offset {}",
+                index_file, index, commit_log_offset
+            ),
+        }
+    }
+}
+
+/// What has to be rebuilt from the commit log to bring the queues and the index back in line
+/// with it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RepairPlan {
+    pub queues: BTreeSet<(String, i32)>,
+    pub rebuild_index: bool,
+}
+
+impl RepairPlan {
+    pub fn is_empty(&self) -> bool {
+        self.queues.is_empty() && !self.rebuild_index
+    }
+
+    /// Writes the plan as `queue <topic> <queueId>` and `index` lines.
+    pub fn write_to(&self, path: &Path) -> io::Result<()> {
+        let mut writer = BufWriter::new(fs::File::create(path)?);
+        for (topic, queue_id) in &self.queues {
+            writeln!(writer, "{} {} {}", PLAN_QUEUE, topic, queue_id)?;
+        }
+        if self.rebuild_index {
+            writeln!(writer, "{}", PLAN_INDEX)?;
+        }
+        writer.flush()
+    }
+
+    pub fn read_from(path: &Path) -> io::Result<Self> {
+        let mut plan = RepairPlan::default();
+        for line in fs::read_to_string(path)?.lines() {
+            let parts = line.split_whitespace().collect::<Vec<_>>();
+            match parts.as_slice() {
+                [] => {}
+                [PLAN_INDEX] => plan.rebuild_index = true,
+                [PLAN_QUEUE, topic, queue_id] => {
+                    let queue_id = queue_id.parse::<i32>().map_err(|_| {
+                        invalid_data(format!("invalid queue id in plan line '{}'", line))
+                    })?;
+                    plan.queues.insert((topic.to_string(), queue_id));
+                }
+                _ => return Err(invalid_data(format!("invalid plan line '{}'", line))),
+            }
+        }
+        Ok(plan)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct StoreVerifyReport {
+    pub scanned_messages: u64,
+    pub checked_units: u64,
+    pub checked_index_entries: u64,
+    /// Units and index entries left behind the first commit log file, they are cleaned up by
+    /// the store and not checked.
+    pub expired_entries: u64,
+    pub divergences: Vec<Divergence>,
+}
+
+impl StoreVerifyReport {
+    pub fn is_consistent(&self) -> bool {
+        self.divergences.is_empty()
+    }
+
+    pub fn repair_plan(&self) -> RepairPlan {
+        let mut plan = RepairPlan::default();
+        for divergence in &self.divergences {
+            match divergence.queue() {
+                Some((topic, queue_id)) => {
+                    plan.queues.insert((topic.to_string(), queue_id));
+                }
+                None => plan.rebuild_index = true,
+            }
+        }
+        plan
+    }
+}
+
+struct StoredMessage {
+    size: i32,
+    tags_code: i64,
+    topic: CheetahString,
+    queue_id: i32,
+    queue_offset: i64,
+    /// Whether the reput puts the message into a consume queue.
+    dispatched: bool,
+    referenced: bool,
+}
+
+/// Cross checks the consume queues and the index of the store under
+/// `message_store_config.store_path_root_dir` against its commit log.
+///
+/// Every consume queue unit must point to a message of its queue with the same size and tags
+/// code, every dispatched message must have a unit, and every index entry must point to a
+/// message. Light message queues are skipped, their units point to messages of other topics.
+/// The commit log is held in memory while checking, the store must not be running.
+pub fn verify_store(message_store_config: &MessageStoreConfig) -> io::Result<StoreVerifyReport> {
+    let store_root = PathBuf::from(message_store_config.store_path_root_dir.as_str());
+    let message_store_config = Arc::new(message_store_config.clone());
+    let mut report = StoreVerifyReport::default();
+    let mut messages = HashMap::new();
+    let mut min_offset = None;
+    for_each_message(
+        &store_root,
+        &message_store_config,
+        |offset, _, dispatch_request| {
+            report.scanned_messages += 1;
+            min_offset.get_or_insert(offset);
+            let dispatched = matches!(
+                MessageSysFlag::get_transaction_value(dispatch_request.sys_flag),
+                MessageSysFlag::TRANSACTION_NOT_TYPE | MessageSysFlag::TRANSACTION_COMMIT_TYPE
+            );
+            messages.insert(
+                offset,
+                StoredMessage {
+                    size: dispatch_request.msg_size,
+                    tags_code: dispatch_request.tags_code,
+                    topic: dispatch_request.topic,
+                    queue_id: dispatch_request.queue_id,
+                    queue_offset: dispatch_request.consume_queue_offset,
+                    dispatched,
+                    referenced: false,
+                },
+            );
+            Ok(())
+        },
+    )?;
+    let min_offset = min_offset.unwrap_or(i64::MAX);
+
+    let queue_min_offsets = verify_consume_queues(
+        Path::new(&get_store_path_consume_queue(
+            message_store_config.store_path_root_dir.as_str(),
+        )),
+        min_offset,
+        &mut messages,
+        &mut report,
+    )?;
+    let mut missing_units = messages
+        .iter()
+        .filter(|(_, message)| message.dispatched && !message.referenced)
+        .filter(|(_, message)| {
+            queue_min_offsets
+                .get(&(message.topic.to_string(), message.queue_id))
+                .is_none_or(|queue_min_offset| message.queue_offset >= *queue_min_offset)
+        })
+        .map(|(offset, message)| (*offset, message))
+        .collect::<Vec<_>>();
+    missing_units.sort_by_key(|(offset, _)| *offset);
+    report
+        .divergences
+        .extend(
+            missing_units
+                .into_iter()
+                .map(|(offset, message)| Divergence::MissingUnit {
+                    topic: message.topic.to_string(),
+                    queue_id: message.queue_id,
+                    queue_offset: message.queue_offset,
+                    commit_log_offset: offset,
+                }),
+        );
+
+    verify_index(
+        Path::new(&get_store_path_index(
+            message_store_config.store_path_root_dir.as_str(),
+        )),
+        message_store_config.max_hash_slot_num as usize,
+        min_offset,
+        &messages,
+        &mut report,
+    )?;
+    info!(
+        "store {} verified, {} messages, {} units, {} index entries, {} divergences",
+        store_root.display(),
+        report.scanned_messages,
+        report.checked_units,
+        report.checked_index_entries,
+        report.divergences.len()
+    );
+    Ok(report)
+}
+
+/// Checks every unit of the consume queues under `dir`, returning the first queue offset found
+/// for each queue.
+fn verify_consume_queues(
+    dir: &Path,
+    min_offset: i64,
+    messages: &mut HashMap<i64, StoredMessage>,
+    report: &mut StoreVerifyReport,
+) -> io::Result<HashMap<(String, i32), i64>> {
+    let mut queue_min_offsets = HashMap::new();
+    if !dir.is_dir() {
+        return Ok(queue_min_offsets);
+    }
+    let unit_size = CQ_STORE_UNIT_SIZE as usize;
+    for (topic, topic_dir) in list_dir(dir)? {
+        if mix_all::is_lmq(Some(topic.as_str())) {
+            continue;
+        }
+        for (queue_id, queue_dir) in list_dir(&topic_dir)? {
+            let Ok(queue_id) = queue_id.parse::<i32>() else {
+                continue;
+            };
+            let mut files = list_dir(&queue_dir)?
+                .into_iter()
+                .filter_map(|(name, path)| name.parse::<i64>().ok().map(|offset| (offset, path)))
+                .collect::<Vec<_>>();
+            files.sort_by_key(|(file_from_offset, _)| *file_from_offset);
+            for (file_from_offset, path) in files {
+                let data = fs::read(&path)?;
+                for position in (0..data.len() / unit_size).map(|index| index * unit_size) {
+                    let commit_log_offset = read_i64(&data, position);
+                    let unit_size_value = read_i32(&data, position + 8);
+                    let unit_tags_code = read_i64(&data, position + 12);
+                    if commit_log_offset == 0 && unit_size_value == 0 {
+                        // past the last unit written to the queue
+                        break;
+                    }
+                    if unit_size_value == i32::MAX {
+                        // blank filled in front of the first unit of the queue
+                        continue;
+                    }
+                    let queue_offset = (file_from_offset + position as i64) / unit_size as i64;
+                    queue_min_offsets
+                        .entry((topic.clone(), queue_id))
+                        .or_insert(queue_offset);
+                    if commit_log_offset < min_offset {
+                        report.expired_entries += 1;
+                        continue;
+                    }
+                    report.checked_units += 1;
+                    let Some(message) = messages.get_mut(&commit_log_offset) else {
+                        report.divergences.push(Divergence::MissingMessage {
+                            topic: topic.clone(),
+                            queue_id,
+                            queue_offset,
+                            commit_log_offset,
+                        });
+                        continue;
+                    };
+                    if message.topic.as_str() != topic
+                        || message.queue_id != queue_id
+                        || message.queue_offset != queue_offset
+                    {
+                        report.divergences.push(Divergence::WrongMessage {
+                            topic: topic.clone(),
+                            queue_id,
+                            queue_offset,
+                            commit_log_offset,
+                            message_topic: message.topic.to_string(),
+                            message_queue_id: message.queue_id,
+                            message_queue_offset: message.queue_offset,
+                        });
+                        continue;
+                    }
+                    message.referenced = true;
+                    if message.size != unit_size_value {
+                        report.divergences.push(Divergence::SizeMismatch {
+                            topic: topic.clone(),
+                            queue_id,
+                            queue_offset,
+                            commit_log_offset,
+                            unit_size: unit_size_value,
+                            message_size: message.size,
+                        });
+                    } else if message.tags_code != unit_tags_code {
+                        report.divergences.push(Divergence::TagsCodeMismatch {
+                            topic: topic.clone(),
+                            queue_id,
+                            queue_offset,
+                            commit_log_offset,
+                            unit_tags_code,
+                            message_tags_code: message.tags_code,
+                        });
+                    }
+                }
+            }
+        }
+    }
+    Ok(queue_min_offsets)
+}
+
+/// Checks that every entry of the index files under `dir` points to a message.
+fn verify_index(
+    dir: &Path,
+    hash_slot_num: usize,
+    min_offset: i64,
+    messages: &HashMap<i64, StoredMessage>,
+    report: &mut StoreVerifyReport,
+) -> io::Result<()> {
+    if !dir.is_dir() {
+        return Ok(());
+    }
+    for (index_file, path) in list_dir(dir)? {
+        let data = fs::read(&path)?;
+        if data.len() < INDEX_HEADER_SIZE {
+            return Err(invalid_data(format!(
+                "index file {} is shorter than its header",
+                index_file
+            )));
+        }
+        // the header only counts the used hash slots, the layout follows the configured number
+        let index_count = read_i32(&data, 36);
+        let entries_position = INDEX_HEADER_SIZE + hash_slot_num * HASH_SLOT_SIZE;
+        // entry 0 is never written, a zero next index ends a hash chain
+        for index in 1..index_count {
+            let position = entries_position + index as usize * INDEX_SIZE;
+            if position + INDEX_SIZE > data.len() {
+                return Err(invalid_data(format!(
+                    "index file {} counts {} entries but holds less",
+                    index_file, index_count
+                )));
+            }
+            let commit_log_offset = read_i64(&data, position + 4);
+            if commit_log_offset < min_offset {
+                report.expired_entries += 1;
+                continue;
+            }
+            report.checked_index_entries += 1;
+            if !messages.contains_key(&commit_log_offset) {
+                report.divergences.push(Divergence::DanglingIndex {
+                    index_file: index_file.clone(),
+                    index,
+                    commit_log_offset,
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, Default)]
+pub struct StoreRebuildReport {
+    pub rebuilt_queues: usize,
+    /// Messages of the rebuilt queues replayed from the commit log.
+    pub replayed_messages: u64,
+    pub indexed_messages: u64,
+}
+
+/// Drops the consume queues and the index listed in `plan` and rebuilds them from the commit
+/// log of the store under `message_store_config.store_path_root_dir`.
+///
+/// The store must not be running and this must not be called from within an async runtime.
+pub fn rebuild_store(
+    message_store_config: &MessageStoreConfig,
+    plan: &RepairPlan,
+) -> io::Result<StoreRebuildReport> {
+    let store_root = PathBuf::from(message_store_config.store_path_root_dir.as_str());
+    let message_store_config = Arc::new(message_store_config.clone());
+    let consume_queue_dir = PathBuf::from(get_store_path_consume_queue(
+        message_store_config.store_path_root_dir.as_str(),
+    ));
+    for (topic, queue_id) in &plan.queues {
+        let queue_dir = consume_queue_dir.join(topic).join(queue_id.to_string());
+        if queue_dir.exists() {
+            fs::remove_dir_all(queue_dir)?;
+        }
+    }
+    let index_dir = PathBuf::from(get_store_path_index(
+        message_store_config.store_path_root_dir.as_str(),
+    ));
+    if plan.rebuild_index && index_dir.exists() {
+        fs::remove_dir_all(&index_dir)?;
+    }
+
+    let checkpoint = Arc::new(StoreCheckpoint::new(get_store_checkpoint(
+        message_store_config.store_path_root_dir.as_str(),
+    ))?);
+    let mut queues = QueueRebuilder::new(message_store_config.clone(), checkpoint.clone());
+    // see compact_store, full index files are flushed once the rebuild is done
+    let runtime = tokio::runtime::Builder::new_current_thread().build()?;
+    let _guard = runtime.enter();
+    let index_service = plan.rebuild_index.then(|| {
+        IndexService::new(
+            message_store_config.clone(),
+            checkpoint.clone(),
+            Arc::new(Mutex::new(HashMap::new())),
+        )
+    });
+
+    let mut report = StoreRebuildReport::default();
+    let mut rebuilt_queues = BTreeSet::new();
+    for_each_message(
+        &store_root,
+        &message_store_config,
+        |_, _, dispatch_request| {
+            let queue = (
+                dispatch_request.topic.to_string(),
+                dispatch_request.queue_id,
+            );
+            if plan.queues.contains(&queue) {
+                queues.put(&dispatch_request)?;
+                rebuilt_queues.insert(queue);
+                report.replayed_messages += 1;
+            }
+            if let Some(index_service) = index_service.as_ref() {
+                index_service.build_index(&dispatch_request);
+                report.indexed_messages += 1;
+            }
+            Ok(())
+        },
+    )?;
+    queues.flush_and_verify()?;
+    if let Some(index_service) = index_service.as_ref() {
+        index_service.flush_all();
+    }
+    checkpoint.flush()?;
+    report.rebuilt_queues = rebuilt_queues.len();
+    info!(
+        "store {} rebuilt, {} queues from {} messages, {} messages indexed",
+        store_root.display(),
+        report.rebuilt_queues,
+        report.replayed_messages,
+        report.indexed_messages
+    );
+    Ok(report)
+}
+
+fn list_dir(dir: &Path) -> io::Result<Vec<(String, PathBuf)>> {
+    let mut entries = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        if let Some(name) = entry.file_name().to_str() {
+            entries.push((name.to_string(), entry.path()));
+        }
+    }
+    Ok(entries)
+}
+
+fn read_i64(data: &[u8], position: usize) -> i64 {
+    i64::from_be_bytes(data[position..position + 8].try_into().unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::base::store_compaction::compact_store;
+    use crate::base::store_compaction::tests::encode_message;
+    use crate::base::store_compaction::CompactionFilter;
+
+    /// Compacts a few messages into a fresh store, which leaves it with consistent queues and
+    /// index files.
+    fn consistent_store(source: &Path, target: &Path) -> (MessageStoreConfig, i64) {
+        let mut commit_log = Vec::new();
+        for (topic, queue_offset) in [("A", 0), ("B", 0), ("A", 1), ("A", 2)] {
+            commit_log.extend(encode_message(topic, queue_offset, 1000));
+        }
+        let message_size = commit_log.len() as i64 / 4;
+        commit_log.resize(4096, 0);
+        fs::create_dir_all(source.join("commitlog")).unwrap();
+        fs::write(
+            source.join("commitlog").join("00000000000000000000"),
+            commit_log,
+        )
+        .unwrap();
+        let config = MessageStoreConfig {
+            store_path_root_dir: CheetahString::from(target.to_string_lossy().to_string()),
+            mapped_file_size_commit_log: 4096,
+            mapped_file_size_consume_queue: 20 * 16,
+            message_index_enable: true,
+            max_hash_slot_num: 16,
+            max_index_num: 64,
+            ..Default::default()
+        };
+        let filter = CompactionFilter {
+            expire_before_timestamp: 0,
+            deleted_topics: HashSet::new(),
+        };
+        compact_store(source, &config, &filter).unwrap();
+        (config, message_size)
+    }
+
+    fn patch(path: &Path, position: usize, bytes: &[u8]) {
+        let mut data = fs::read(path).unwrap();
+        data[position..position + bytes.len()].copy_from_slice(bytes);
+        fs::write(path, data).unwrap();
+    }
+
+    #[test]
+    fn verify_reports_divergences_and_rebuild_repairs_them() {
+        let source = tempfile::tempdir().unwrap();
+        let target = tempfile::tempdir().unwrap();
+        let (config, message_size) = consistent_store(source.path(), target.path());
+
+        let report = verify_store(&config).unwrap();
+        assert_eq!(report.scanned_messages, 4);
+        assert_eq!(report.checked_units, 4);
+        assert!(report.checked_index_entries > 0);
+        assert!(report.is_consistent(), "{:?}", report.divergences);
+        assert!(report.repair_plan().is_empty());
+
+        let queue_a = target
+            .path()
+            .join("consumequeue")
+            .join("A")
+            .join("0")
+            .join("00000000000000000000");
+        patch(&queue_a, 20 + 8, &1i32.to_be_bytes());
+        patch(&queue_a, 40, &7i64.to_be_bytes());
+        let queue_b = target.path().join("consumequeue").join("B");
+        fs::remove_dir_all(&queue_b).unwrap();
+        let index_file = fs::read_dir(target.path().join("index"))
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap()
+            .path();
+        let first_entry = INDEX_HEADER_SIZE + 16 * HASH_SLOT_SIZE + INDEX_SIZE;
+        patch(&index_file, first_entry + 4, &7i64.to_be_bytes());
+
+        let report = verify_store(&config).unwrap();
+        let expected = [
+            Divergence::SizeMismatch {
+                topic: "A".to_string(),
+                queue_id: 0,
+                queue_offset: 1,
+                commit_log_offset: message_size * 2,
+                unit_size: 1,
+                message_size: message_size as i32,
+            },
+            Divergence::MissingMessage {
+                topic: "A".to_string(),
+                queue_id: 0,
+                queue_offset: 2,
+                commit_log_offset: 7,
+            },
+            Divergence::MissingUnit {
+                topic: "B".to_string(),
+                queue_id: 0,
+                queue_offset: 0,
+                commit_log_offset: message_size,
+            },
+            Divergence::MissingUnit {
+                topic: "A".to_string(),
+                queue_id: 0,
+                queue_offset: 2,
+                commit_log_offset: message_size * 3,
+            },
+        ];
+        for divergence in &expected {
+            assert!(
+                report.divergences.contains(divergence),
+                "{} not in {:?}",
+                divergence,
+                report.divergences
+            );
+        }
+        assert!(report
+            .divergences
+            .iter()
+            .any(|divergence| matches!(divergence, Divergence::DanglingIndex { .. })));
+
+        let plan_file = target.path().join("repair_plan");
+        report.repair_plan().write_to(&plan_file).unwrap();
+        let plan = RepairPlan::read_from(&plan_file).unwrap();
+        assert_eq!(plan, report.repair_plan());
+        assert_eq!(
+            plan.queues,
+            BTreeSet::from([("A".to_string(), 0), ("B".to_string(), 0)])
+        );
+        assert!(plan.rebuild_index);
+
+        let rebuild = rebuild_store(&config, &plan).unwrap();
+        assert_eq!(rebuild.rebuilt_queues, 2);
+        assert_eq!(rebuild.replayed_messages, 4);
+        assert_eq!(rebuild.indexed_messages, 4);
+        let report = verify_store(&config).unwrap();
+        assert!(report.is_consistent(), "{:?}", report.divergences);
+    }
+
+    #[test]
+    fn repair_plan_rejects_unknown_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("plan");
+        fs::write(&path, "queue A 1\nindex\n").unwrap();
+        let plan = RepairPlan::read_from(&path).unwrap();
+        assert_eq!(plan.queues, BTreeSet::from([("A".to_string(), 1)]));
+        assert!(plan.rebuild_index);
+
+        fs::write(&path, "queue A x\n").unwrap();
+        assert!(RepairPlan::read_from(&path).is_err());
+        fs::write(&path, "topic A\n").unwrap();
+        assert!(RepairPlan::read_from(&path).is_err());
+    }
+}
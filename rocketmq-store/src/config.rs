@@ -19,3 +19,4 @@ pub mod broker_role;
 pub mod flush_disk_type;
 pub mod message_store_config;
 pub(crate) mod store_path_config_helper;
+pub mod topic_flush_policy;
@@ -35,6 +35,15 @@ impl FlushDiskType {
             FlushDiskType::AsyncFlush => "ASYNC_FLUSH",
         }
     }
+
+    /// Parses the name [`get_flush_disk_type`](Self::get_flush_disk_type) returns.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "SYNC_FLUSH" => Some(FlushDiskType::SyncFlush),
+            "ASYNC_FLUSH" => Some(FlushDiskType::AsyncFlush),
+            _ => None,
+        }
+    }
 }
 
 impl<'de> Deserialize<'de> for FlushDiskType {
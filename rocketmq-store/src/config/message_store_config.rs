@@ -78,6 +78,7 @@ pub struct MessageStoreConfig {
     pub max_recovery_commit_log_files: usize,
     pub disk_space_warning_level_ratio: usize,
     pub disk_space_clean_forcibly_ratio: usize,
+    /// Serializes commit log appends with an async mutex, a spin lock is used when off.
     pub use_reentrant_lock_when_put_message: bool,
     pub flush_commit_log_timed: bool,
     pub flush_interval_consume_queue: usize,
@@ -124,11 +125,13 @@ pub struct MessageStoreConfig {
     pub message_delay_level: String,
     pub flush_delay_offset_interval: usize,
     pub clean_file_forcibly_enable: bool,
+    #[serde(alias = "warmMapedFileEnable")]
     pub warm_mapped_file_enable: bool,
     pub offset_check_in_slave: bool,
     pub debug_lock_enable: bool,
     pub duplication_enable: bool,
     pub disk_fall_recorded: bool,
+    #[serde(alias = "osPageCacheBusyTimeOutMills")]
     pub os_page_cache_busy_timeout_mills: u64,
     pub default_query_max_num: usize,
     pub transient_store_pool_enable: bool,
@@ -206,6 +209,51 @@ pub struct MessageStoreConfig {
     pub enable_rocksdb_log: bool,
     pub topic_queue_lock_num: usize,
     pub max_filter_message_size: i32,
+    pub timer_congest_dequeue_behind_ms: u64,
+    pub adaptive_flush_enable: bool,
+    pub flush_stall_latency_threshold_ms: u64,
+    pub flush_stall_dirty_bytes_threshold: i64,
+    pub flush_stall_detect_times: u32,
+    pub flush_stall_accelerate_ratio: i32,
+    pub snapshot_bootstrap_dir: Option<String>,
+    pub verify_body_crc_on_put: bool,
+    pub mapped_file_size_consume_queue_cold: usize,
+    pub consume_queue_hot_file_num: usize,
+    pub ha_read_ahead_activate_gap_bytes: usize,
+    pub ha_read_ahead_max_buffered_bytes: usize,
+    pub ha_read_ahead_max_unacked_bytes: usize,
+    pub consume_queue_write_buffer_units: usize,
+    pub consume_queue_write_buffer_pool_size: usize,
+    /// Also `mlock` warmed-up commit log files, they are unlocked when destroyed.
+    pub warm_mapped_file_lock_enable: bool,
+    /// Worker threads of the dedicated runtime serving pulls, queries and HA transfer, `0`
+    /// serves them on the caller's runtime.
+    pub store_read_thread_pool_nums: usize,
+    /// Keep recently dispatched messages in memory so consumers reading right behind the
+    /// producers are served without touching the mapped files.
+    pub hot_message_cache_enable: bool,
+    /// Hard limit of the message bytes held by the hot message cache.
+    pub hot_message_cache_max_bytes: usize,
+    /// Messages larger than this are never cached.
+    pub hot_message_cache_max_message_size: usize,
+    /// Only queues read within this window are cached, appends to queues nobody tails are
+    /// left out.
+    pub hot_message_cache_reader_idle_ms: u64,
+    /// Upload sealed commit log segments to a tiered store and read expired messages back
+    /// from it.
+    pub tiered_store_enable: bool,
+    /// Backend of the tiered store, only `posix` is built in.
+    pub tiered_store_provider: String,
+    /// Root of the `posix` provider, `<storePathRootDir>/tiered` when unset.
+    pub tiered_store_path: Option<CheetahString>,
+    pub tiered_upload_interval_ms: u64,
+    /// Write and read commit log and consume queue files with positioned file I/O instead of
+    /// mapping them, for platforms where mmap is problematic.
+    pub write_without_mmap: bool,
+    /// Appends to files opened without mmap are gathered up to this many bytes before they are
+    /// written, 0 writes every append through. Like with the transient store pool, appends not
+    /// written yet are lost when the process dies.
+    pub write_without_mmap_buffer_size: usize,
 }
 
 impl Default for MessageStoreConfig {
@@ -242,7 +290,7 @@ impl Default for MessageStoreConfig {
             timer_enable_disruptor: false,
             timer_enable_check_metrics: false,
             timer_intercept_delay_level: false,
-            timer_max_delay_sec: 0,
+            timer_max_delay_sec: 3 * 24 * 3600,
             timer_wheel_enable: false,
             disappear_time_after_start: -1,
             timer_stop_enqueue: false,
@@ -262,9 +310,9 @@ impl Default for MessageStoreConfig {
             flush_interval_commit_log: 500,
             commit_interval_commit_log: 200,
             max_recovery_commit_log_files: 0,
-            disk_space_warning_level_ratio: 0,
-            disk_space_clean_forcibly_ratio: 0,
-            use_reentrant_lock_when_put_message: false,
+            disk_space_warning_level_ratio: 90,
+            disk_space_clean_forcibly_ratio: 85,
+            use_reentrant_lock_when_put_message: true,
             flush_commit_log_timed: true,
             flush_interval_consume_queue: 1000,
             clean_resource_interval: 10000,
@@ -274,14 +322,14 @@ impl Default for MessageStoreConfig {
             redelete_hanged_file_interval: 1000 * 120,
             delete_when: "04".to_string(),
             disk_max_used_space_ratio: 75,
-            file_reserved_time: 0,
-            delete_file_batch_max: 0,
+            file_reserved_time: 72,
+            delete_file_batch_max: 10,
             put_msg_index_hight_water: 0,
             max_message_size: 1024 * 1024 * 4,
             check_crc_on_recover: false,
-            flush_commit_log_least_pages: 0,
+            flush_commit_log_least_pages: 4,
             commit_commit_log_least_pages: 4,
-            flush_least_pages_when_warm_mapped_file: 0,
+            flush_least_pages_when_warm_mapped_file: 1024 / 4 * 16,
             flush_consume_queue_least_pages: 0,
             flush_commit_log_thorough_interval: 1000 * 10,
             commit_commit_log_thorough_interval: 200,
@@ -346,13 +394,13 @@ impl Default for MessageStoreConfig {
             correct_logic_min_offset_sleep_interval: 0,
             correct_logic_min_offset_force_interval: 0,
             mapped_file_swap_enable: false,
-            commit_log_force_swap_map_interval: 0,
-            commit_log_swap_map_interval: 0,
-            commit_log_swap_map_reserve_file_num: 0,
-            logic_queue_force_swap_map_interval: 0,
-            logic_queue_swap_map_interval: 0,
-            clean_swapped_map_interval: 0,
-            logic_queue_swap_map_reserve_file_num: 0,
+            commit_log_force_swap_map_interval: 12 * 60 * 60 * 1000,
+            commit_log_swap_map_interval: 60 * 60 * 1000,
+            commit_log_swap_map_reserve_file_num: 100,
+            logic_queue_force_swap_map_interval: 12 * 60 * 60 * 1000,
+            logic_queue_swap_map_interval: 60 * 60 * 1000,
+            clean_swapped_map_interval: 5 * 60 * 1000,
+            logic_queue_swap_map_reserve_file_num: 20,
             search_bcq_by_cache_enable: false,
             dispatch_from_sender_thread: false,
             wake_commit_when_put_message: false,
@@ -392,11 +440,48 @@ impl Default for MessageStoreConfig {
             enable_rocksdb_log: false,
             topic_queue_lock_num: 32,
             max_filter_message_size: 16000,
+            timer_congest_dequeue_behind_ms: 60_000,
+            adaptive_flush_enable: false,
+            flush_stall_latency_threshold_ms: 500,
+            flush_stall_dirty_bytes_threshold: 256 * 1024 * 1024,
+            flush_stall_detect_times: 3,
+            flush_stall_accelerate_ratio: 4,
+            snapshot_bootstrap_dir: None,
+            verify_body_crc_on_put: false,
+            mapped_file_size_consume_queue_cold: 0,
+            consume_queue_hot_file_num: 2,
+            ha_read_ahead_activate_gap_bytes: 256 * 1024 * 1024,
+            ha_read_ahead_max_buffered_bytes: 16 * 1024 * 1024,
+            ha_read_ahead_max_unacked_bytes: 128 * 1024 * 1024,
+            consume_queue_write_buffer_units: 0,
+            consume_queue_write_buffer_pool_size: 4096,
+            warm_mapped_file_lock_enable: false,
+            store_read_thread_pool_nums: 0,
+            hot_message_cache_enable: false,
+            hot_message_cache_max_bytes: 64 * 1024 * 1024,
+            hot_message_cache_max_message_size: 256 * 1024,
+            hot_message_cache_reader_idle_ms: 30_000,
+            tiered_store_enable: false,
+            tiered_store_provider: "posix".to_string(),
+            tiered_store_path: None,
+            tiered_upload_interval_ms: 10_000,
+            write_without_mmap: false,
+            write_without_mmap_buffer_size: 64 * 1024,
         }
     }
 }
 
 impl MessageStoreConfig {
+    pub fn get_tiered_store_path(&self) -> String {
+        match self.tiered_store_path.as_ref() {
+            Some(path) => path.to_string(),
+            None => PathBuf::from(self.store_path_root_dir.to_string())
+                .join("tiered")
+                .to_string_lossy()
+                .to_string(),
+        }
+    }
+
     pub fn get_store_path_commit_log(&self) -> String {
         if self.store_path_commit_log.is_none() {
             return PathBuf::from(self.store_path_root_dir.to_string())
@@ -416,6 +501,17 @@ impl MessageStoreConfig {
             .ceil() as i32;
         factor * CQ_STORE_UNIT_SIZE
     }
+
+    /// Size of the cold consume queue files, rounded up to a multiple of the hot file size.
+    /// Returns `0` when the hot/cold split is disabled.
+    pub fn get_mapped_file_size_consume_queue_cold(&self) -> i32 {
+        let hot = self.get_mapped_file_size_consume_queue();
+        if hot <= 0 || self.mapped_file_size_consume_queue_cold <= hot as usize {
+            return 0;
+        }
+        let factor = (self.mapped_file_size_consume_queue_cold as f64 / hot as f64).ceil() as i32;
+        factor * hot
+    }
     pub fn is_timer_wheel_enable(&self) -> bool {
         self.timer_wheel_enable
     }
@@ -1102,6 +1198,117 @@ impl MessageStoreConfig {
             "maxFilterMessageSize".into(),
             self.max_filter_message_size.to_string(),
         );
+        properties.insert(
+            "timerCongestDequeueBehindMs".into(),
+            self.timer_congest_dequeue_behind_ms.to_string(),
+        );
+        properties.insert(
+            "adaptiveFlushEnable".into(),
+            self.adaptive_flush_enable.to_string(),
+        );
+        properties.insert(
+            "flushStallLatencyThresholdMs".into(),
+            self.flush_stall_latency_threshold_ms.to_string(),
+        );
+        properties.insert(
+            "flushStallDirtyBytesThreshold".into(),
+            self.flush_stall_dirty_bytes_threshold.to_string(),
+        );
+        properties.insert(
+            "flushStallDetectTimes".into(),
+            self.flush_stall_detect_times.to_string(),
+        );
+        properties.insert(
+            "flushStallAccelerateRatio".into(),
+            self.flush_stall_accelerate_ratio.to_string(),
+        );
+        properties.insert(
+            "snapshotBootstrapDir".into(),
+            self.snapshot_bootstrap_dir.clone().unwrap_or_default(),
+        );
+        properties.insert(
+            "verifyBodyCrcOnPut".into(),
+            self.verify_body_crc_on_put.to_string(),
+        );
+        properties.insert(
+            "mappedFileSizeConsumeQueueCold".into(),
+            self.mapped_file_size_consume_queue_cold.to_string(),
+        );
+        properties.insert(
+            "consumeQueueHotFileNum".into(),
+            self.consume_queue_hot_file_num.to_string(),
+        );
+        properties.insert(
+            "haReadAheadActivateGapBytes".into(),
+            self.ha_read_ahead_activate_gap_bytes.to_string(),
+        );
+        properties.insert(
+            "haReadAheadMaxBufferedBytes".into(),
+            self.ha_read_ahead_max_buffered_bytes.to_string(),
+        );
+        properties.insert(
+            "haReadAheadMaxUnackedBytes".into(),
+            self.ha_read_ahead_max_unacked_bytes.to_string(),
+        );
+        properties.insert(
+            "consumeQueueWriteBufferUnits".into(),
+            self.consume_queue_write_buffer_units.to_string(),
+        );
+        properties.insert(
+            "consumeQueueWriteBufferPoolSize".into(),
+            self.consume_queue_write_buffer_pool_size.to_string(),
+        );
+        properties.insert(
+            "warmMappedFileLockEnable".into(),
+            self.warm_mapped_file_lock_enable.to_string(),
+        );
+        properties.insert(
+            "storeReadThreadPoolNums".into(),
+            self.store_read_thread_pool_nums.to_string(),
+        );
+        properties.insert(
+            "hotMessageCacheEnable".into(),
+            self.hot_message_cache_enable.to_string(),
+        );
+        properties.insert(
+            "hotMessageCacheMaxBytes".into(),
+            self.hot_message_cache_max_bytes.to_string(),
+        );
+        properties.insert(
+            "hotMessageCacheMaxMessageSize".into(),
+            self.hot_message_cache_max_message_size.to_string(),
+        );
+        properties.insert(
+            "hotMessageCacheReaderIdleMs".into(),
+            self.hot_message_cache_reader_idle_ms.to_string(),
+        );
+        properties.insert(
+            "tieredStoreEnable".into(),
+            self.tiered_store_enable.to_string(),
+        );
+        properties.insert(
+            "tieredStoreProvider".into(),
+            self.tiered_store_provider.to_string(),
+        );
+        properties.insert(
+            "tieredStorePath".into(),
+            self.tiered_store_path
+                .clone()
+                .unwrap_or_default()
+                .to_string(),
+        );
+        properties.insert(
+            "tieredUploadIntervalMs".into(),
+            self.tiered_upload_interval_ms.to_string(),
+        );
+        properties.insert(
+            "writeWithoutMmap".into(),
+            self.write_without_mmap.to_string(),
+        );
+        properties.insert(
+            "writeWithoutMmapBufferSize".into(),
+            self.write_without_mmap_buffer_size.to_string(),
+        );
         properties
             .into_iter()
             .map(|(k, v)| (k.into(), v.into()))
@@ -0,0 +1,122 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::HashMap;
+
+use cheetah_string::CheetahString;
+use rocketmq_common::common::config::TopicConfig;
+use rocketmq_common::TopicAttributes;
+use tracing::warn;
+
+use crate::config::flush_disk_type::FlushDiskType;
+
+/// Flush and replication requirements a topic declares through its `flush.disk.type` and
+/// `in.sync.replicas` attributes, e.g. sync flush and two replicas for a financial topic on an
+/// async flush broker. Unset values follow the broker.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TopicFlushPolicy {
+    pub flush_disk_type: Option<FlushDiskType>,
+    pub in_sync_replicas: Option<u32>,
+}
+
+impl TopicFlushPolicy {
+    /// Reads the policy of a stored topic, an invalid attribute is ignored.
+    pub fn from_topic_config(topic_config: Option<&TopicConfig>) -> Self {
+        let Some(topic_config) = topic_config else {
+            return Self::default();
+        };
+        Self::parse(&topic_config.attributes).unwrap_or_else(|err| {
+            warn!(
+                "ignore flush policy of topic {:?}: {}",
+                topic_config.topic_name, err
+            );
+            Self::default()
+        })
+    }
+
+    /// Parses the policy attributes. Keys may carry the `+` of an alteration request, deleted
+    /// (`-`) keys are skipped.
+    pub fn parse(attributes: &HashMap<CheetahString, CheetahString>) -> Result<Self, String> {
+        let mut policy = Self::default();
+        for (key, value) in attributes {
+            if key.as_str().starts_with('-') {
+                continue;
+            }
+            let name = key.as_str().trim_start_matches('+');
+            if name == TopicAttributes::FLUSH_DISK_TYPE_ATTRIBUTE.get_name() {
+                policy.flush_disk_type = Some(
+                    FlushDiskType::from_name(value.as_str())
+                        .ok_or_else(|| format!("invalid {}: {}", name, value))?,
+                );
+            } else if name == TopicAttributes::IN_SYNC_REPLICAS_ATTRIBUTE.get_name() {
+                let replicas = TopicAttributes::IN_SYNC_REPLICAS_ATTRIBUTE
+                    .parse(value.as_str())
+                    .ok_or_else(|| format!("invalid {}: {}", name, value))?;
+                policy.in_sync_replicas = Some(replicas as u32);
+            }
+        }
+        Ok(policy)
+    }
+
+    pub fn flush_disk_type_or(&self, broker_flush_disk_type: FlushDiskType) -> FlushDiskType {
+        self.flush_disk_type.unwrap_or(broker_flush_disk_type)
+    }
+
+    pub fn in_sync_replicas_or(&self, broker_in_sync_replicas: u32) -> u32 {
+        self.in_sync_replicas.unwrap_or(broker_in_sync_replicas)
+    }
+
+    /// Whether puts have to wait for slaves even when the broker itself would not.
+    pub fn requires_replicas(&self) -> bool {
+        self.in_sync_replicas.is_some_and(|replicas| replicas > 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_reads_request_and_stored_attributes() {
+        let attributes = HashMap::from([
+            ("+flush.disk.type".into(), "SYNC_FLUSH".into()),
+            ("in.sync.replicas".into(), "2".into()),
+            ("+queue.type".into(), "SimpleCQ".into()),
+        ]);
+        let policy = TopicFlushPolicy::parse(&attributes).unwrap();
+        assert_eq!(
+            policy.flush_disk_type_or(FlushDiskType::AsyncFlush),
+            FlushDiskType::SyncFlush
+        );
+        assert_eq!(policy.in_sync_replicas_or(1), 2);
+        assert!(policy.requires_replicas());
+
+        let unset = TopicFlushPolicy::from_topic_config(None);
+        assert_eq!(
+            unset.flush_disk_type_or(FlushDiskType::AsyncFlush),
+            FlushDiskType::AsyncFlush
+        );
+        assert!(!unset.requires_replicas());
+    }
+
+    #[test]
+    fn parse_rejects_invalid_values() {
+        let attributes = HashMap::from([("+flush.disk.type".into(), "SOMETIMES".into())]);
+        assert!(TopicFlushPolicy::parse(&attributes).is_err());
+        let attributes = HashMap::from([("+in.sync.replicas".into(), "0".into())]);
+        assert!(TopicFlushPolicy::parse(&attributes).is_err());
+    }
+}
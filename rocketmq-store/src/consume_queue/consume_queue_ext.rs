@@ -15,10 +15,14 @@
  * limitations under the License.
  */
 
-const MIN_EXT_UNIT_SIZE: i16 = 2  // size, 32k max
+use bytes::BufMut;
+use bytes::Bytes;
+use bytes::BytesMut;
+
+pub(crate) const MIN_EXT_UNIT_SIZE: i16 = 2  // size, 32k max
  + 8 * 2 // msg time + tagCode
   + 2; // bitMapSize
-const MAX_EXT_UNIT_SIZE: i16 = i16::MAX;
+pub(crate) const MAX_EXT_UNIT_SIZE: i16 = i16::MAX;
 
 #[derive(Clone, Default)]
 pub struct CqExtUnit {
@@ -61,4 +65,78 @@ impl CqExtUnit {
     pub fn filter_bit_map(&self) -> &Option<Vec<u8>> {
         &self.filter_bit_map
     }
+
+    /// Size the unit takes on disk, which may exceed [`MAX_EXT_UNIT_SIZE`] for oversized
+    /// bitmaps.
+    pub fn calc_unit_size(&self) -> i32 {
+        MIN_EXT_UNIT_SIZE as i32
+            + self
+                .filter_bit_map
+                .as_ref()
+                .map_or(0, |val| val.len() as i32)
+    }
+
+    /// Encodes the unit as `size | tags_code | msg_store_time | bit_map_size | bit_map`.
+    pub fn write(&mut self) -> Bytes {
+        self.bit_map_size = self
+            .filter_bit_map
+            .as_ref()
+            .map_or(0, |val| val.len() as i16);
+        self.size = self.calc_unit_size() as i16;
+        let mut buffer = BytesMut::with_capacity(self.size as usize);
+        buffer.put_i16(self.size);
+        buffer.put_i64(self.tags_code);
+        buffer.put_i64(self.msg_store_time);
+        buffer.put_i16(self.bit_map_size);
+        if let Some(bit_map) = self.filter_bit_map.as_ref() {
+            buffer.put_slice(bit_map);
+        }
+        buffer.freeze()
+    }
+
+    /// Decodes a unit from the start of `buffer`, returns `false` when no unit was written
+    /// there.
+    pub fn read(&mut self, buffer: &[u8]) -> bool {
+        if buffer.len() < 2 {
+            return false;
+        }
+        self.size = i16::from_be_bytes([buffer[0], buffer[1]]);
+        if self.size < 1 || buffer.len() < MIN_EXT_UNIT_SIZE as usize {
+            return false;
+        }
+        self.tags_code = i64::from_be_bytes(buffer[2..10].try_into().unwrap());
+        self.msg_store_time = i64::from_be_bytes(buffer[10..18].try_into().unwrap());
+        self.bit_map_size = i16::from_be_bytes([buffer[18], buffer[19]]);
+        if self.bit_map_size < 1 {
+            self.filter_bit_map = None;
+            return true;
+        }
+        let end = MIN_EXT_UNIT_SIZE as usize + self.bit_map_size as usize;
+        if buffer.len() < end {
+            return false;
+        }
+        self.filter_bit_map = Some(buffer[MIN_EXT_UNIT_SIZE as usize..end].to_vec());
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn write_then_read_round_trips() {
+        let mut unit = CqExtUnit::new(42, 1_700_000_000_000, Some(vec![0b1010, 0xff]));
+        let bytes = unit.write();
+        assert_eq!(bytes.len(), unit.calc_unit_size() as usize);
+
+        let mut decoded = CqExtUnit::default();
+        assert!(decoded.read(&bytes));
+        assert_eq!(decoded.size(), unit.size());
+        assert_eq!(decoded.tags_code(), 42);
+        assert_eq!(decoded.msg_store_time(), 1_700_000_000_000);
+        assert_eq!(decoded.filter_bit_map(), &Some(vec![0b1010, 0xff]));
+
+        assert!(!CqExtUnit::default().read(&[0, 0, 0, 0]));
+    }
 }
@@ -16,34 +16,46 @@
  */
 
 use std::fs;
+use std::io::Write;
 use std::path::Path;
 use std::path::PathBuf;
 use std::sync::atomic::AtomicU64;
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
+use std::time::Duration;
 
 use cheetah_string::CheetahString;
 use log::warn;
 use parking_lot::RwLock;
+use rocketmq_common::TimeUtils::get_current_millis;
 use rocketmq_common::UtilAll::offset_to_file_name;
 use tracing::info;
 
+use crate::base::allocate_mapped_file_service::AllocateMappedFileService;
 use crate::log_file::mapped_file::default_mapped_file_impl::DefaultMappedFile;
 use crate::log_file::mapped_file::MappedFile;
-use crate::services::allocate_mapped_file_service::AllocateMappedFileService;
 
 #[derive(Default, Clone)]
 pub struct MappedFileQueue {
     pub(crate) store_path: String,
 
     pub(crate) mapped_file_size: u64,
+
+    /// Size of the cold files that full hot files are merged into, `0` disables the split.
+    pub(crate) cold_mapped_file_size: u64,
+
+    /// Open files with positioned I/O instead of mapping them.
+    pub(crate) write_without_mmap: bool,
+
+    /// Bytes of appends gathered before a positioned write, `0` writes them through.
+    pub(crate) write_buffer_size: usize,
     //pub(crate) mapped_files: Arc<Mutex<Vec<LocalMappedFile>>>,
     //pub(crate) mapped_files: Vec<Arc<Mutex<LocalMappedFile>>>,
     //pub(crate) mapped_files: Vec<Arc<LocalMappedFile>>,
     //pub(crate) mapped_files: Vec<Arc<DefaultMappedFile>>,
     pub(crate) mapped_files: Arc<RwLock<Vec<Arc<DefaultMappedFile>>>>,
     //  pub(crate) mapped_files: Vec<LocalMappedFile>,
-    pub(crate) allocate_mapped_file_service: Option<AllocateMappedFileService>,
+    pub(crate) allocate_mapped_file_service: Option<Arc<AllocateMappedFileService>>,
 
     pub(crate) flushed_where: Arc<AtomicU64>,
 
@@ -56,11 +68,14 @@ impl MappedFileQueue {
     pub fn new(
         store_path: String,
         mapped_file_size: u64,
-        allocate_mapped_file_service: Option<AllocateMappedFileService>,
+        allocate_mapped_file_service: Option<Arc<AllocateMappedFileService>>,
     ) -> MappedFileQueue {
         MappedFileQueue {
             store_path,
             mapped_file_size,
+            cold_mapped_file_size: 0,
+            write_without_mmap: false,
+            write_buffer_size: 0,
             mapped_files: Arc::new(RwLock::new(Vec::new())),
             allocate_mapped_file_service,
             flushed_where: Arc::new(AtomicU64::new(0)),
@@ -70,7 +85,41 @@ impl MappedFileQueue {
     }
 }
 
+const MERGING_FILE_EXTENSION: &str = "merging";
+
 impl MappedFileQueue {
+    /// Enables the hot/cold split: new files keep `mapped_file_size`, older full files are merged
+    /// into files of `cold_mapped_file_size`. The cold size must be a multiple of the hot size.
+    pub fn set_cold_mapped_file_size(&mut self, cold_mapped_file_size: u64) {
+        self.cold_mapped_file_size = cold_mapped_file_size;
+    }
+
+    pub fn set_write_without_mmap(&mut self, write_without_mmap: bool) {
+        self.write_without_mmap = write_without_mmap;
+    }
+
+    pub fn set_write_buffer_size(&mut self, write_buffer_size: usize) {
+        self.write_buffer_size = write_buffer_size;
+    }
+
+    fn new_mapped_file(&self, file_name: CheetahString, file_size: u64) -> DefaultMappedFile {
+        if self.write_without_mmap {
+            DefaultMappedFile::new_without_mmap_buffered(
+                file_name,
+                file_size,
+                self.write_buffer_size,
+            )
+        } else {
+            DefaultMappedFile::new(file_name, file_size)
+        }
+    }
+
+    pub fn is_cold_tier_enabled(&self) -> bool {
+        self.mapped_file_size > 0
+            && self.cold_mapped_file_size > self.mapped_file_size
+            && self.cold_mapped_file_size % self.mapped_file_size == 0
+    }
+
     pub fn load(&mut self) -> bool {
         //list dir files
         let dir = Path::new(&self.store_path);
@@ -133,8 +182,20 @@ impl MappedFileQueue {
                 continue;
             }
 
+            if file
+                .extension()
+                .is_some_and(|ext| ext == MERGING_FILE_EXTENSION)
+            {
+                // leftover of an interrupted merge, the hot files it was built from still exist
+                warn!("{} is an unfinished cold file, auto delete", file.display());
+                let _ = fs::remove_file(file);
+                continue;
+            }
+
             let file_size = file.metadata().map(|metadata| metadata.len()).unwrap_or(0);
-            if file_size != self.mapped_file_size {
+            if file_size != self.mapped_file_size
+                && !(self.is_cold_tier_enabled() && file_size == self.cold_mapped_file_size)
+            {
                 warn!(
                     "{} {} length not matched message store config value, please check it manually",
                     file.display(),
@@ -143,14 +204,34 @@ impl MappedFileQueue {
                 return false;
             }
 
-            let mapped_file = DefaultMappedFile::new(
+            let file_from_offset = file
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.parse::<u64>().ok());
+            let covered = match (file_from_offset, self.mapped_files.read().last()) {
+                (Some(file_from_offset), Some(last)) => {
+                    file_from_offset < last.get_file_from_offset() + last.get_file_size()
+                }
+                _ => false,
+            };
+            if covered {
+                // a merge was interrupted after the cold file replaced the first hot file
+                warn!(
+                    "{} is already merged into a cold file, auto delete",
+                    file.display()
+                );
+                let _ = fs::remove_file(file);
+                continue;
+            }
+
+            let mapped_file = self.new_mapped_file(
                 CheetahString::from_string(file.to_string_lossy().to_string()),
-                self.mapped_file_size,
+                file_size,
             );
             // Set wrote, flushed, committed positions for mapped_file
-            mapped_file.set_wrote_position(self.mapped_file_size as i32);
-            mapped_file.set_flushed_position(self.mapped_file_size as i32);
-            mapped_file.set_committed_position(self.mapped_file_size as i32);
+            mapped_file.set_wrote_position(file_size as i32);
+            mapped_file.set_flushed_position(file_size as i32);
+            mapped_file.set_committed_position(file_size as i32);
             self.mapped_files.write().push(Arc::new(mapped_file));
             // self.mapped_files
             //     .push(mapped_file);
@@ -188,7 +269,8 @@ impl MappedFileQueue {
             }
             Some(ref value) => {
                 if value.is_full() {
-                    create_offset = value.get_file_from_offset() as i64 + file_size
+                    create_offset =
+                        value.get_file_from_offset() as i64 + value.get_file_size() as i64
                 }
             }
         }
@@ -209,16 +291,19 @@ impl MappedFileQueue {
     fn do_create_mapped_file(
         &mut self,
         next_file_path: PathBuf,
-        _next_next_file_path: PathBuf,
+        next_next_file_path: PathBuf,
     ) -> Option<Arc<DefaultMappedFile>> {
         let mut mapped_file = match self.allocate_mapped_file_service {
-            None => DefaultMappedFile::new(
+            Some(ref service) if service.is_started() => service
+                .put_request_and_return_mapped_file(
+                    next_file_path.to_string_lossy().to_string(),
+                    next_next_file_path.to_string_lossy().to_string(),
+                    self.mapped_file_size,
+                )?,
+            _ => self.new_mapped_file(
                 CheetahString::from_string(next_file_path.to_string_lossy().to_string()),
                 self.mapped_file_size,
             ),
-            Some(ref _value) => {
-                unimplemented!()
-            }
         };
 
         if self.mapped_files.read().is_empty() {
@@ -229,6 +314,46 @@ impl MappedFileQueue {
         Some(inner)
     }
 
+    /// Remaps files older than the newest `reserve_num` ones, which drops their resident pages.
+    /// A file is swapped once its last swap is `force_swap_interval_ms` ago, or
+    /// `normal_swap_interval_ms` ago if it was read since.
+    pub fn swap_map(
+        &self,
+        reserve_num: i32,
+        force_swap_interval_ms: i64,
+        normal_swap_interval_ms: i64,
+    ) {
+        let mapped_files = self.mapped_files.read().clone();
+        let reserve_num = reserve_num.max(3) as usize;
+        if mapped_files.len() <= reserve_num {
+            return;
+        }
+        let now = get_current_millis() as i64;
+        for mapped_file in mapped_files[..mapped_files.len() - reserve_num]
+            .iter()
+            .rev()
+        {
+            let since_last_swap = now - mapped_file.get_recent_swap_map_time();
+            if since_last_swap > force_swap_interval_ms
+                || (since_last_swap > normal_swap_interval_ms
+                    && mapped_file.get_mapped_byte_buffer_access_count_since_last_swap() > 0)
+            {
+                mapped_file.swap_map();
+            }
+        }
+    }
+
+    /// Unmaps the mappings replaced by swaps more than `force_clean_swap_interval_ms` ago.
+    pub fn clean_swapped_map(&self, force_clean_swap_interval_ms: i64) {
+        let mapped_files = self.mapped_files.read().clone();
+        let now = get_current_millis() as i64;
+        for mapped_file in mapped_files.iter() {
+            if now - mapped_file.get_recent_swap_map_time() > force_clean_swap_interval_ms {
+                mapped_file.clean_swaped_map(false);
+            }
+        }
+    }
+
     pub fn get_mapped_files(&self) -> Arc<RwLock<Vec<Arc<DefaultMappedFile>>>> {
         self.mapped_files.clone()
     }
@@ -250,20 +375,25 @@ impl MappedFileQueue {
     pub fn truncate_dirty_files(&mut self, offset: i64) {
         let mut will_remove_files = Vec::new();
         for mapped_file in self.mapped_files.read().iter() {
-            let file_tail_offset = mapped_file.get_file_from_offset() + self.mapped_file_size;
-            if file_tail_offset as i64 > offset {
-                if offset >= mapped_file.get_file_from_offset() as i64 {
-                    mapped_file.set_wrote_position((offset % self.mapped_file_size as i64) as i32);
-                    mapped_file
-                        .set_committed_position((offset % self.mapped_file_size as i64) as i32);
-                    mapped_file
-                        .set_flushed_position((offset % self.mapped_file_size as i64) as i32);
+            let file_from_offset = mapped_file.get_file_from_offset() as i64;
+            let file_tail_offset = file_from_offset + mapped_file.get_file_size() as i64;
+            if file_tail_offset > offset {
+                if offset >= file_from_offset {
+                    let position = (offset - file_from_offset) as i32;
+                    mapped_file.set_wrote_position(position);
+                    mapped_file.set_committed_position(position);
+                    mapped_file.set_flushed_position(position);
                 } else {
                     mapped_file.destroy(1000);
                     will_remove_files.push(mapped_file.clone());
                 }
             }
         }
+        if !will_remove_files.is_empty() {
+            self.mapped_files
+                .write()
+                .retain(|mf| !will_remove_files.contains(mf));
+        }
     }
 
     pub fn get_max_offset(&self) -> i64 {
@@ -288,10 +418,99 @@ impl MappedFileQueue {
 
     pub(crate) fn delete_expired_file(&mut self, files: Vec<Arc<DefaultMappedFile>>) {
         let mut files = files;
-        let read_guard = self.mapped_files.read();
         if !files.is_empty() {
-            files.retain(|mf| read_guard.contains(mf));
-            self.mapped_files.write().retain(|mf| !files.contains(mf));
+            let mut write_guard = self.mapped_files.write();
+            files.retain(|mf| write_guard.contains(mf));
+            write_guard.retain(|mf| !files.contains(mf));
+        }
+    }
+
+    /// Destroys the files, oldest first, whose last modification is older than `expired_time`
+    /// millis, always keeping the file currently written to. Stops at the first file still
+    /// alive or still referenced so the queue never gets a hole, and returns how many files
+    /// were deleted.
+    pub async fn delete_expired_file_by_time(
+        &mut self,
+        expired_time: i64,
+        delete_files_interval: u64,
+        interval_forcibly: i64,
+        clean_immediately: bool,
+        delete_file_batch_max: usize,
+    ) -> usize {
+        let mapped_files = self.mapped_files.read().clone();
+        if mapped_files.len() <= 1 {
+            return 0;
+        }
+        self.check_self();
+        let candidates = mapped_files.len() - 1;
+        let mut files = Vec::new();
+        for (i, mapped_file) in mapped_files.into_iter().take(candidates).enumerate() {
+            let live_max_timestamp = mapped_file.get_last_modified_timestamp() + expired_time;
+            if get_current_millis() as i64 >= live_max_timestamp || clean_immediately {
+                if !mapped_file.destroy(interval_forcibly) {
+                    break;
+                }
+                files.push(mapped_file);
+                if files.len() >= delete_file_batch_max {
+                    break;
+                }
+                if delete_files_interval > 0 && i + 1 < candidates {
+                    tokio::time::sleep(Duration::from_millis(delete_files_interval)).await;
+                }
+            } else {
+                break;
+            }
+        }
+        let delete_count = files.len();
+        self.delete_expired_file(files);
+        delete_count
+    }
+
+    /// Retries destroying the first file once it became unavailable but could not be deleted
+    /// because it was still referenced, forcing it after `interval_forcibly` millis.
+    pub fn retry_delete_first_file(&mut self, interval_forcibly: i64) -> bool {
+        let Some(mapped_file) = self.get_first_mapped_file() else {
+            return false;
+        };
+        if mapped_file.is_available() {
+            return false;
+        }
+        warn!(
+            "the mappedFile was destroyed once, but still alive, {}",
+            mapped_file.get_file_name()
+        );
+        if !mapped_file.destroy(interval_forcibly) {
+            return false;
+        }
+        info!(
+            "the mappedFile re delete OK, {}",
+            mapped_file.get_file_name()
+        );
+        self.delete_expired_file(vec![mapped_file]);
+        true
+    }
+
+    /// Shuts every file down and waits up to `interval_forcibly` ms for readers still
+    /// holding a file to release it before the remaining references are dropped forcibly.
+    pub fn shutdown(&self, interval_forcibly: i64) {
+        let mapped_files = self.mapped_files.read().clone();
+        for mapped_file in mapped_files.iter() {
+            mapped_file.shutdown(interval_forcibly);
+        }
+
+        let deadline = get_current_millis() + interval_forcibly.max(0) as u64;
+        for mapped_file in mapped_files.iter() {
+            while !mapped_file.is_cleanup_over() && get_current_millis() < deadline {
+                std::thread::sleep(Duration::from_millis(10));
+            }
+            if !mapped_file.is_cleanup_over() {
+                warn!(
+                    "mapped file {} still referenced after {}ms, release it forcibly",
+                    mapped_file.get_file_name(),
+                    interval_forcibly
+                );
+                mapped_file.shutdown(0);
+            }
         }
     }
 
@@ -301,6 +520,8 @@ impl MappedFileQueue {
         }
         self.mapped_files.write().clear();
         self.set_flushed_where(0);
+        self.set_committed_where(0);
+        self.set_store_timestamp(0);
         let path = PathBuf::from(&self.store_path);
         if path.is_dir() {
             let _ = fs::remove_dir_all(path);
@@ -315,10 +536,11 @@ impl MappedFileQueue {
         let first_mapped_file = self.get_first_mapped_file();
         let last_mapped_file = self.get_last_mapped_file();
         if first_mapped_file.is_some() && last_mapped_file.is_some() {
+            let last_mapped_file = last_mapped_file.as_ref().unwrap();
             if offset < first_mapped_file.as_ref().unwrap().get_file_from_offset() as i64
                 || offset
-                    >= last_mapped_file.as_ref().unwrap().get_file_from_offset() as i64
-                        + self.mapped_file_size as i64
+                    >= last_mapped_file.get_file_from_offset() as i64
+                        + last_mapped_file.get_file_size() as i64
             {
                 if return_first_on_not_found {
                     first_mapped_file
@@ -331,17 +553,21 @@ impl MappedFileQueue {
                         / self.mapped_file_size as usize;
                 let read_guard = self.mapped_files.read();
                 let target_file = read_guard.get(index).cloned();
-                if target_file.is_some()
-                    && offset >= target_file.as_ref().unwrap().get_file_from_offset() as i64
-                {
-                    return target_file;
+                if let Some(ref target) = target_file {
+                    if offset >= target.get_file_from_offset() as i64
+                        && offset < (target.get_file_from_offset() + target.get_file_size()) as i64
+                    {
+                        return target_file;
+                    }
                 }
-                for index in 0..read_guard.len() {
-                    let mapped_file = read_guard.get(index).unwrap();
-                    if offset >= mapped_file.get_file_from_offset() as i64
-                        && offset
-                            < mapped_file.get_file_from_offset() as i64
-                                + self.mapped_file_size as i64
+                // cold files break the index arithmetic, fall back to a search by start offset
+                let index = read_guard.partition_point(|mapped_file| {
+                    mapped_file.get_file_from_offset() as i64 <= offset
+                });
+                if index > 0 {
+                    let mapped_file = read_guard.get(index - 1).unwrap();
+                    if offset
+                        < (mapped_file.get_file_from_offset() + mapped_file.get_file_size()) as i64
                     {
                         return Some(mapped_file.clone());
                     }
@@ -356,6 +582,101 @@ impl MappedFileQueue {
         }
     }
 
+    /// Merges runs of full and flushed hot files, except the `hot_file_num` most recent ones,
+    /// into cold files. Returns how many cold files were created.
+    pub fn merge_cold_files(&self, hot_file_num: usize) -> usize {
+        if !self.is_cold_tier_enabled() {
+            return 0;
+        }
+        let mut merged = 0;
+        while let Some(run) = self.find_mergeable_run(hot_file_num.max(1)) {
+            match self.merge_run(&run) {
+                Ok(()) => merged += 1,
+                Err(e) => {
+                    warn!(
+                        "merge {} into cold file failed: {}",
+                        run[0].get_file_name(),
+                        e
+                    );
+                    break;
+                }
+            }
+        }
+        merged
+    }
+
+    fn find_mergeable_run(&self, hot_file_num: usize) -> Option<Vec<Arc<DefaultMappedFile>>> {
+        let run_len = (self.cold_mapped_file_size / self.mapped_file_size) as usize;
+        let mapped_files = self.mapped_files.read();
+        let limit = mapped_files.len().saturating_sub(hot_file_num);
+        let mergeable = |mapped_file: &Arc<DefaultMappedFile>| {
+            mapped_file.get_file_size() == self.mapped_file_size
+                && mapped_file.is_full()
+                && mapped_file.get_flushed_position() as u64 == self.mapped_file_size
+        };
+        let mut start = 0;
+        while start + run_len <= limit {
+            let first_offset = mapped_files[start].get_file_from_offset();
+            if first_offset % self.cold_mapped_file_size != 0 || !mergeable(&mapped_files[start]) {
+                start += 1;
+                continue;
+            }
+            let run = &mapped_files[start..start + run_len];
+            let contiguous = run.iter().enumerate().all(|(i, mapped_file)| {
+                mergeable(mapped_file)
+                    && mapped_file.get_file_from_offset()
+                        == first_offset + i as u64 * self.mapped_file_size
+            });
+            if contiguous {
+                return Some(run.to_vec());
+            }
+            start += 1;
+        }
+        None
+    }
+
+    /// Writes the run into a temporary file, renames it over the first hot file and only then
+    /// removes the other hot files, so an interruption at any point is repaired by `load`.
+    fn merge_run(&self, run: &[Arc<DefaultMappedFile>]) -> std::io::Result<()> {
+        let first = &run[0];
+        let target = PathBuf::from(first.get_file_name().as_str());
+        let merging = target.with_extension(MERGING_FILE_EXTENSION);
+        {
+            let mut file = fs::File::create(&merging)?;
+            for mapped_file in run {
+                let bytes = mapped_file
+                    .get_bytes(0, mapped_file.get_file_size() as usize)
+                    .ok_or_else(|| std::io::Error::other("read hot file failed"))?;
+                file.write_all(&bytes)?;
+            }
+            file.sync_all()?;
+        }
+        fs::rename(&merging, &target)?;
+
+        let cold_file =
+            self.new_mapped_file(first.get_file_name().clone(), self.cold_mapped_file_size);
+        cold_file.set_wrote_position(self.cold_mapped_file_size as i32);
+        cold_file.set_flushed_position(self.cold_mapped_file_size as i32);
+        cold_file.set_committed_position(self.cold_mapped_file_size as i32);
+        {
+            let mut mapped_files = self.mapped_files.write();
+            let start = mapped_files
+                .iter()
+                .position(|mapped_file| Arc::ptr_eq(mapped_file, first))
+                .ok_or_else(|| std::io::Error::other("hot file removed during merge"))?;
+            mapped_files.splice(start..start + run.len(), [Arc::new(cold_file)]);
+        }
+        for mapped_file in &run[1..] {
+            fs::remove_file(mapped_file.get_file_name().as_str())?;
+        }
+        info!(
+            "merge {} hot files into cold file {}",
+            run.len(),
+            target.display()
+        );
+        Ok(())
+    }
+
     pub fn get_flushed_where(&self) -> i64 {
         self.flushed_where.load(Ordering::Acquire) as i64
     }
@@ -475,4 +796,143 @@ mod tests {
         assert!(queue.load());
         assert_eq!(queue.mapped_files.read().len(), 1);
     }
+
+    #[test]
+    fn test_merge_cold_files() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let store_path = temp_dir.path().to_string_lossy().into_owned();
+        let mut queue = MappedFileQueue::new(store_path.clone(), 1024, None);
+        queue.set_cold_mapped_file_size(2048);
+        for i in 0..4u64 {
+            let mapped_file = queue.try_create_mapped_file(i * 1024).unwrap();
+            mapped_file.append_message_bytes(&bytes::Bytes::from(vec![i as u8; 1024]));
+            mapped_file.set_flushed_position(1024);
+        }
+
+        // the most recent file stays hot, so only the first two files form a cold file
+        assert_eq!(queue.merge_cold_files(1), 1);
+        assert_eq!(queue.get_mapped_files_size(), 3);
+        let cold_file = queue.find_mapped_file_by_offset(1500, false).unwrap();
+        assert_eq!(cold_file.get_file_from_offset(), 0);
+        assert_eq!(cold_file.get_file_size(), 2048);
+        assert_eq!(cold_file.get_bytes(1500, 1).unwrap()[0], 1);
+        let hot_file = queue.find_mapped_file_by_offset(3000, false).unwrap();
+        assert_eq!(hot_file.get_file_from_offset(), 2048);
+
+        let mut reloaded = MappedFileQueue::new(store_path, 1024, None);
+        reloaded.set_cold_mapped_file_size(2048);
+        assert!(reloaded.load());
+        assert_eq!(reloaded.get_mapped_files_size(), 3);
+        assert_eq!(
+            reloaded.get_first_mapped_file().unwrap().get_file_size(),
+            2048
+        );
+    }
+
+    #[test]
+    fn write_without_mmap_appends_and_selects() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let store_path = temp_dir.path().to_string_lossy().into_owned();
+        let mut queue = MappedFileQueue::new(store_path.clone(), 1024, None);
+        queue.set_write_without_mmap(true);
+        let mapped_file = queue.try_create_mapped_file(0).unwrap();
+        assert!(mapped_file.is_write_without_mmap());
+        assert!(mapped_file.append_message_bytes(&bytes::Bytes::from_static(b"hello")));
+        assert!(mapped_file.append_message_bytes(&bytes::Bytes::from_static(b" world")));
+        mapped_file.flush(0);
+
+        let selected = mapped_file.clone().select_mapped_buffer(6).unwrap();
+        assert_eq!(selected.start_offset, 6);
+        assert_eq!(selected.get_buffer(), b"world");
+        assert_eq!(mapped_file.get_data(0, 5).unwrap().as_ref(), b"hello");
+        assert_eq!(mapped_file.get_flushed_position(), 11);
+        assert_eq!(
+            &fs::read(mapped_file.get_file_name().as_str()).unwrap()[..11],
+            b"hello world"
+        );
+    }
+
+    #[test]
+    fn write_without_mmap_buffers_appends_until_flushed() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let store_path = temp_dir.path().to_string_lossy().into_owned();
+        let mut queue = MappedFileQueue::new(store_path, 1024, None);
+        queue.set_write_without_mmap(true);
+        queue.set_write_buffer_size(8);
+        let mapped_file = queue.try_create_mapped_file(0).unwrap();
+        let on_disk = || fs::read(mapped_file.get_file_name().as_str()).unwrap()[..11].to_vec();
+        assert!(mapped_file.append_message_bytes(&bytes::Bytes::from_static(b"hello")));
+        assert_eq!(on_disk(), [0u8; 11]);
+
+        // the second append does not fit, the first one is written out to make room
+        assert!(mapped_file.append_message_bytes(&bytes::Bytes::from_static(b" world")));
+        assert_eq!(&on_disk()[..6], b"hello\0");
+        assert_eq!(
+            mapped_file.get_data(0, 11).unwrap().as_ref(),
+            b"hello world"
+        );
+        assert_eq!(mapped_file.get_bytes(3, 5).unwrap().as_ref(), b"lo wo");
+
+        mapped_file.flush(0);
+        assert_eq!(on_disk(), b"hello world");
+    }
+
+    #[test]
+    fn shutdown_drains_held_files_and_destroy_resets_offsets() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let store_path = temp_dir.path().join("commitlog");
+        let mut queue = MappedFileQueue::new(store_path.to_string_lossy().into_owned(), 1024, None);
+        let free = queue.try_create_mapped_file(0).unwrap();
+        let held = queue.try_create_mapped_file(1024).unwrap();
+        assert!(held.hold());
+        queue.set_flushed_where(1024);
+        queue.set_committed_where(1024);
+
+        // the hold is never released, so the second file is only dropped forcibly
+        queue.shutdown(50);
+        assert!(free.is_cleanup_over());
+        assert!(held.is_cleanup_over());
+        assert!(!held.hold());
+
+        queue.destroy();
+        assert_eq!(queue.get_mapped_files_size(), 0);
+        assert_eq!(queue.get_flushed_where(), 0);
+        assert_eq!(queue.get_committed_where(), 0);
+        assert!(!store_path.exists());
+    }
+
+    #[tokio::test]
+    async fn delete_expired_file_by_time_keeps_last_and_referenced_files() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let store_path = temp_dir.path().to_string_lossy().into_owned();
+        let mut queue = MappedFileQueue::new(store_path, 1024, None);
+        let files: Vec<_> = (0..4u64)
+            .map(|i| queue.try_create_mapped_file(i * 1024).unwrap())
+            .collect();
+
+        // a reader still holds the second file, so deletion stops right before it
+        assert!(files[1].hold());
+        assert_eq!(
+            queue
+                .delete_expired_file_by_time(i64::MAX / 2, 0, 120_000, true, 10)
+                .await,
+            1
+        );
+        assert!(!Path::new(files[0].get_file_name().as_str()).exists());
+        assert_eq!(queue.get_mapped_files_size(), 3);
+        assert!(!queue.get_first_mapped_file().unwrap().is_available());
+
+        files[1].release();
+        assert!(queue.retry_delete_first_file(120_000));
+        assert_eq!(queue.get_mapped_files_size(), 2);
+
+        assert_eq!(
+            queue
+                .delete_expired_file_by_time(i64::MAX / 2, 0, 120_000, true, 10)
+                .await,
+            1
+        );
+        assert_eq!(queue.get_mapped_files_size(), 1);
+        assert!(Path::new(files[3].get_file_name().as_str()).exists());
+    }
 }
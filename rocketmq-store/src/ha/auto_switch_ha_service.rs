@@ -0,0 +1,450 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicI64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+use parking_lot::Mutex;
+use parking_lot::RwLock;
+use rocketmq_common::TimeUtils::get_current_millis;
+use tracing::info;
+use tracing::warn;
+
+use crate::base::message_status_enum::PutMessageStatus;
+use crate::base::store_task_spawner::StoreTaskSpawner;
+use crate::config::message_store_config::MessageStoreConfig;
+use crate::ha::default_ha_service::DefaultHAService;
+use crate::ha::epoch_file_cache::EpochEntry;
+use crate::ha::epoch_file_cache::EpochFileCache;
+use crate::log_file::commit_log::CommitLog;
+use crate::store_path_config_helper::get_epoch_file_path;
+
+const DEFAULT_MAX_TIME_SLAVE_NOT_CATCHUP_MILLIS: u64 = 15000;
+const SYNC_STATE_SET_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Called with the proposed sync state set whenever the master expands or shrinks it, the
+/// broker forwards the proposal to the controller and answers with
+/// [`AutoSwitchHAService::set_sync_state_set`].
+pub type SyncStateSetChangedListener = Arc<dyn Fn(&HashSet<i64>) + Send + Sync>;
+
+/// HA service for controller mode, where the controller elects the master and tracks the set
+/// of replicas in sync with it.
+///
+/// On top of the plain [`DefaultHAService`] it keeps the epoch history of the commit log,
+/// maintains the sync state set from the slaves' progress and derives the confirm offset, the
+/// highest offset every replica of the sync state set holds.
+#[derive(Clone)]
+pub struct AutoSwitchHAService {
+    ha_service: DefaultHAService,
+    message_store_config: Arc<MessageStoreConfig>,
+    local_broker_id: i64,
+    epoch_cache: Arc<Mutex<EpochFileCache>>,
+    is_master: Arc<AtomicBool>,
+    /// Sync state set confirmed by the controller.
+    sync_state_set: Arc<RwLock<HashSet<i64>>>,
+    /// Proposed set waiting for the controller, `None` when nothing is in flight.
+    remote_sync_state_set: Arc<RwLock<Option<HashSet<i64>>>>,
+    /// Last time each connected slave had caught up with the master.
+    caught_up_times: Arc<Mutex<HashMap<i64, u64>>>,
+    /// Master max offset seen by the previous sync state set check.
+    last_master_max_offset: Arc<AtomicI64>,
+    confirm_offset: Arc<AtomicI64>,
+    sync_state_set_listener: Arc<RwLock<Option<SyncStateSetChangedListener>>>,
+}
+
+impl AutoSwitchHAService {
+    pub fn new(message_store_config: Arc<MessageStoreConfig>, local_broker_id: i64) -> Self {
+        let mut epoch_cache = EpochFileCache::new(get_epoch_file_path(
+            message_store_config.store_path_root_dir.as_str(),
+        ));
+        epoch_cache.init_from_file();
+        let epoch_cache = Arc::new(Mutex::new(epoch_cache));
+        Self {
+            ha_service: DefaultHAService::new(message_store_config.clone())
+                .with_local_broker_id(local_broker_id)
+                .with_epoch_cache(epoch_cache.clone()),
+            message_store_config,
+            local_broker_id,
+            epoch_cache,
+            is_master: Arc::new(AtomicBool::new(false)),
+            sync_state_set: Arc::new(RwLock::new(HashSet::new())),
+            remote_sync_state_set: Arc::new(RwLock::new(None)),
+            caught_up_times: Arc::new(Mutex::new(HashMap::new())),
+            last_master_max_offset: Arc::new(AtomicI64::new(0)),
+            confirm_offset: Arc::new(AtomicI64::new(-1)),
+            sync_state_set_listener: Arc::new(RwLock::new(None)),
+        }
+    }
+
+    pub fn ha_service(&self) -> &DefaultHAService {
+        &self.ha_service
+    }
+
+    /// Starts accepting slaves and the HA client, which stays idle until
+    /// [`change_to_slave`](Self::change_to_slave) gives it a master, and the periodic sync
+    /// state set check.
+    pub fn start(&self, commit_log: CommitLog, task_spawner: &StoreTaskSpawner) {
+        self.ha_service
+            .start_accept(commit_log.clone(), task_spawner);
+        self.ha_service
+            .start_client(commit_log.clone(), task_spawner);
+        let service = self.clone();
+        task_spawner.spawn("ha_sync_state_set", async move {
+            while !service.ha_service.is_shutdown() {
+                tokio::time::sleep(SYNC_STATE_SET_CHECK_INTERVAL).await;
+                service.check_sync_state_set(commit_log.get_max_offset());
+            }
+        });
+    }
+
+    pub fn register_sync_state_set_changed_listener(&self, listener: SyncStateSetChangedListener) {
+        *self.sync_state_set_listener.write() = Some(listener);
+    }
+
+    /// Becomes the master of `master_epoch`, whose data starts at `max_phy_offset`.
+    pub fn change_to_master(&self, master_epoch: i32, max_phy_offset: i64) -> bool {
+        self.ha_service.update_master_address(None);
+        {
+            let mut epoch_cache = self.epoch_cache.lock();
+            if epoch_cache.last_epoch() >= master_epoch {
+                epoch_cache.truncate_suffix_by_epoch(master_epoch);
+            }
+            if !epoch_cache.append_entry(EpochEntry::new(master_epoch, max_phy_offset)) {
+                warn!(
+                    "append epoch {} at offset {} failed, last epoch {:?}",
+                    master_epoch,
+                    max_phy_offset,
+                    epoch_cache.last_entry()
+                );
+                return false;
+            }
+        }
+        self.caught_up_times.lock().clear();
+        self.set_sync_state_set(HashSet::from([self.local_broker_id]));
+        self.is_master.store(true, Ordering::Release);
+        self.update_confirm_offset(max_phy_offset);
+        info!(
+            "broker {} changed to master, epoch {}, max offset {}",
+            self.local_broker_id, master_epoch, max_phy_offset
+        );
+        true
+    }
+
+    /// Becomes a slave replicating from `master_ha_address`. The commit log must already be
+    /// truncated to `truncated_offset`, the confirm offset, so epochs past it are dropped.
+    pub fn change_to_slave(
+        &self,
+        master_ha_address: String,
+        master_epoch: i32,
+        truncated_offset: i64,
+    ) {
+        self.is_master.store(false, Ordering::Release);
+        self.ha_service.close_connections();
+        {
+            let mut epoch_cache = self.epoch_cache.lock();
+            epoch_cache.truncate_suffix_by_offset(truncated_offset);
+            epoch_cache.set_last_entry_end_offset(truncated_offset);
+        }
+        self.caught_up_times.lock().clear();
+        self.sync_state_set.write().clear();
+        *self.remote_sync_state_set.write() = None;
+        self.ha_service
+            .update_master_address(Some(master_ha_address.clone()));
+        info!(
+            "broker {} changed to slave of {}, epoch {}, truncated to {}",
+            self.local_broker_id, master_ha_address, master_epoch, truncated_offset
+        );
+    }
+
+    pub fn is_master(&self) -> bool {
+        self.is_master.load(Ordering::Acquire)
+    }
+
+    pub fn last_epoch(&self) -> i32 {
+        self.epoch_cache.lock().last_epoch()
+    }
+
+    pub fn epoch_entries(&self) -> Vec<EpochEntry> {
+        self.epoch_cache.lock().entries().to_vec()
+    }
+
+    /// Installs the sync state set the controller accepted.
+    pub fn set_sync_state_set(&self, sync_state_set: HashSet<i64>) {
+        *self.sync_state_set.write() = sync_state_set;
+        *self.remote_sync_state_set.write() = None;
+    }
+
+    /// The sync state set, including members of a proposal the controller has not accepted
+    /// yet, so the confirm offset never outruns a replica that may be part of it.
+    pub fn get_sync_state_set(&self) -> HashSet<i64> {
+        let mut sync_state_set = self.sync_state_set.read().clone();
+        if let Some(remote) = self.remote_sync_state_set.read().as_ref() {
+            sync_state_set.extend(remote.iter().copied());
+        }
+        sync_state_set
+    }
+
+    pub fn is_synchronizing_sync_state_set(&self) -> bool {
+        self.remote_sync_state_set.read().is_some()
+    }
+
+    pub fn in_sync_replicas_nums(&self) -> u32 {
+        self.sync_state_set.read().len() as u32
+    }
+
+    pub fn update_connection_last_caught_up_time(&self, slave_broker_id: i64, timestamp: u64) {
+        let mut caught_up_times = self.caught_up_times.lock();
+        let last = caught_up_times.entry(slave_broker_id).or_insert(0);
+        *last = (*last).max(timestamp);
+    }
+
+    /// Proposes adding a slave that caught up with the confirm offset and the current epoch,
+    /// returns the proposed set.
+    pub fn maybe_expand_in_sync_state_set(
+        &self,
+        slave_broker_id: i64,
+        slave_max_offset: i64,
+    ) -> Option<HashSet<i64>> {
+        let mut sync_state_set = self.sync_state_set.read().clone();
+        if sync_state_set.contains(&slave_broker_id) {
+            return None;
+        }
+        if slave_max_offset < self.confirm_offset() {
+            return None;
+        }
+        let epoch_start_offset = self
+            .epoch_cache
+            .lock()
+            .last_entry()
+            .map_or(0, |entry| entry.start_offset);
+        if slave_max_offset < epoch_start_offset {
+            return None;
+        }
+        sync_state_set.insert(slave_broker_id);
+        self.mark_synchronizing(sync_state_set.clone());
+        Some(sync_state_set)
+    }
+
+    /// Proposes removing slaves that did not catch up within `ha_max_time_slave_not_catchup`
+    /// or are not connected, returns the proposed set when it changed.
+    pub fn maybe_shrink_sync_state_set(&self, now: u64) -> Option<HashSet<i64>> {
+        let max_time_not_catchup = match self.message_store_config.ha_max_time_slave_not_catchup {
+            0 => DEFAULT_MAX_TIME_SLAVE_NOT_CATCHUP_MILLIS,
+            millis => millis as u64,
+        };
+        let mut sync_state_set = self.sync_state_set.read().clone();
+        let len = sync_state_set.len();
+        {
+            let caught_up_times = self.caught_up_times.lock();
+            sync_state_set.retain(|broker_id| {
+                if *broker_id == self.local_broker_id {
+                    return true;
+                }
+                match caught_up_times.get(broker_id) {
+                    Some(last) => now.saturating_sub(*last) <= max_time_not_catchup,
+                    None => false,
+                }
+            });
+        }
+        if sync_state_set.len() == len {
+            return None;
+        }
+        self.mark_synchronizing(sync_state_set.clone());
+        Some(sync_state_set)
+    }
+
+    fn mark_synchronizing(&self, proposed: HashSet<i64>) {
+        *self.remote_sync_state_set.write() = Some(proposed);
+    }
+
+    /// Min of the master max offset and the ack offsets of the connected sync state set
+    /// members. A member that is not connected keeps the previous confirm offset, so the
+    /// confirm offset never moves past data that member may be missing.
+    pub fn compute_confirm_offset(&self, master_max_offset: i64) -> i64 {
+        let sync_state_set = self.get_sync_state_set();
+        let connections = self.ha_service.connections();
+        let all_connected = sync_state_set.iter().all(|broker_id| {
+            *broker_id == self.local_broker_id
+                || connections
+                    .iter()
+                    .any(|c| c.slave_broker_id() == *broker_id)
+        });
+        if !all_connected {
+            return self.confirm_offset();
+        }
+        connections
+            .iter()
+            .filter(|c| sync_state_set.contains(&c.slave_broker_id()))
+            .filter(|c| c.slave_ack_offset() > 0)
+            .map(|c| c.slave_ack_offset())
+            .fold(master_max_offset, i64::min)
+    }
+
+    pub fn update_confirm_offset(&self, master_max_offset: i64) {
+        let confirm_offset = self.compute_confirm_offset(master_max_offset);
+        self.set_confirm_offset(confirm_offset);
+    }
+
+    /// Confirm offset, `-1` while unknown.
+    pub fn confirm_offset(&self) -> i64 {
+        self.confirm_offset.load(Ordering::Acquire)
+    }
+
+    pub fn set_confirm_offset(&self, confirm_offset: i64) {
+        self.confirm_offset.store(confirm_offset, Ordering::Release);
+    }
+
+    /// Periodic master side check: refreshes the slaves' caught up times, proposes sync state
+    /// set changes and recomputes the confirm offset.
+    pub fn check_sync_state_set(&self, master_max_offset: i64) {
+        if !self.is_master() {
+            return;
+        }
+        let now = get_current_millis();
+        let last_master_max_offset = self
+            .last_master_max_offset
+            .swap(master_max_offset, Ordering::AcqRel);
+        let connections = self.ha_service.connections();
+        self.caught_up_times.lock().retain(|broker_id, _| {
+            connections
+                .iter()
+                .any(|c| c.slave_broker_id() == *broker_id)
+        });
+        for connection in connections.iter().filter(|c| c.slave_broker_id() >= 0) {
+            if connection.slave_ack_offset() >= last_master_max_offset {
+                self.update_connection_last_caught_up_time(connection.slave_broker_id(), now);
+            }
+        }
+        if !self.is_synchronizing_sync_state_set() {
+            let mut proposed = None;
+            for connection in connections.iter().filter(|c| c.slave_broker_id() >= 0) {
+                if let Some(expanded) = self.maybe_expand_in_sync_state_set(
+                    connection.slave_broker_id(),
+                    connection.slave_ack_offset(),
+                ) {
+                    proposed = Some(expanded);
+                    break;
+                }
+            }
+            if proposed.is_none() {
+                proposed = self.maybe_shrink_sync_state_set(now);
+            }
+            if let Some(proposed) = proposed {
+                info!("propose sync state set {:?}", proposed);
+                if let Some(listener) = self.sync_state_set_listener.read().as_ref() {
+                    listener(&proposed);
+                }
+            }
+        }
+        self.update_confirm_offset(master_max_offset);
+    }
+
+    /// Waits until `need_ack_nums` members of the sync state set, the master included,
+    /// acknowledged `next_offset`.
+    pub async fn wait_for_in_sync_replicas(
+        &self,
+        next_offset: i64,
+        need_ack_nums: u32,
+    ) -> PutMessageStatus {
+        let sync_state_set = self.sync_state_set.read().clone();
+        if (sync_state_set.len() as u32) < need_ack_nums {
+            return PutMessageStatus::InSyncReplicasNotEnough;
+        }
+        let status = self
+            .ha_service
+            .wait_for_acks(next_offset, need_ack_nums, |c| {
+                sync_state_set.contains(&c.slave_broker_id())
+            })
+            .await;
+        if status == PutMessageStatus::PutOk {
+            self.confirm_offset
+                .fetch_max(self.compute_confirm_offset(next_offset), Ordering::AcqRel);
+        }
+        status
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn service(dir: &tempfile::TempDir) -> AutoSwitchHAService {
+        AutoSwitchHAService::new(
+            Arc::new(MessageStoreConfig {
+                store_path_root_dir: dir.path().to_string_lossy().to_string().into(),
+                ha_max_time_slave_not_catchup: 1000,
+                ..MessageStoreConfig::default()
+            }),
+            1,
+        )
+    }
+
+    #[test]
+    fn change_to_master_records_epoch_and_resets_sync_state_set() {
+        let dir = tempfile::tempdir().unwrap();
+        let service = service(&dir);
+        assert!(service.change_to_master(1, 0));
+        assert!(service.change_to_master(2, 4096));
+        assert_eq!(service.last_epoch(), 2);
+        assert_eq!(service.get_sync_state_set(), HashSet::from([1]));
+        assert_eq!(service.confirm_offset(), 4096);
+
+        service.change_to_slave("127.0.0.1:10912".to_string(), 3, 2048);
+        assert!(!service.is_master());
+        assert_eq!(service.last_epoch(), 1);
+        assert_eq!(
+            service.ha_service().master_address().as_deref(),
+            Some("127.0.0.1:10912")
+        );
+    }
+
+    #[test]
+    fn sync_state_set_expands_shrinks_and_bounds_confirm_offset() {
+        let dir = tempfile::tempdir().unwrap();
+        let service = service(&dir);
+        service.change_to_master(1, 1000);
+        let slave = service
+            .ha_service()
+            .add_connection("127.0.0.1:10000".parse().unwrap());
+        slave.set_slave_broker_id(2);
+        slave.set_slave_ack_offset(500);
+
+        assert_eq!(service.maybe_expand_in_sync_state_set(2, 500), None);
+        slave.set_slave_ack_offset(1000);
+        assert_eq!(
+            service.maybe_expand_in_sync_state_set(2, 1000),
+            Some(HashSet::from([1, 2]))
+        );
+        assert!(service.is_synchronizing_sync_state_set());
+        service.set_sync_state_set(HashSet::from([1, 2]));
+        assert_eq!(service.in_sync_replicas_nums(), 2);
+
+        slave.set_slave_ack_offset(1500);
+        assert_eq!(service.compute_confirm_offset(2000), 1500);
+
+        service.update_connection_last_caught_up_time(2, 10_000);
+        assert_eq!(service.maybe_shrink_sync_state_set(10_500), None);
+        assert_eq!(
+            service.maybe_shrink_sync_state_set(12_000),
+            Some(HashSet::from([1]))
+        );
+    }
+}
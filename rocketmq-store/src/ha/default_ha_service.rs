@@ -0,0 +1,440 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicI64;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+use parking_lot::Mutex;
+use tokio::net::TcpListener;
+use tokio::sync::Notify;
+use tokio::time::Instant;
+use tracing::error;
+use tracing::info;
+use tracing::warn;
+
+use crate::base::message_status_enum::PutMessageStatus;
+use crate::base::store_health::SlaveGap;
+use crate::base::store_task_spawner::StoreTaskSpawner;
+use crate::config::broker_role::BrokerRole;
+use crate::config::message_store_config::MessageStoreConfig;
+use crate::ha::epoch_file_cache::EpochEntry;
+use crate::ha::epoch_file_cache::EpochFileCache;
+use crate::ha::ha_client::HAClient;
+use crate::ha::ha_connection::HAConnection;
+use crate::log_file::commit_log::CommitLog;
+
+const DEFAULT_HA_LISTEN_PORT: usize = 10912;
+const DEFAULT_HEARTBEAT_INTERVAL_MILLIS: u64 = 5000;
+const DEFAULT_HOUSEKEEPING_INTERVAL_MILLIS: u64 = 20000;
+const DEFAULT_TRANSFER_BATCH_SIZE: usize = 32 * 1024;
+const DEFAULT_MAX_GAP_NOT_IN_SYNC: i64 = 256 * 1024 * 1024;
+const DEFAULT_SLAVE_TIMEOUT_MILLIS: u64 = 3000;
+
+/// Master-slave replication of the commit log.
+///
+/// A master accepts slave connections on the HA port and pushes commit log data to them, a
+/// slave runs an [`HAClient`] against `ha_master_address`. Sync masters wait in
+/// [`wait_for_replicas`](Self::wait_for_replicas) until enough slaves acknowledged a message.
+#[derive(Clone)]
+pub struct DefaultHAService {
+    message_store_config: Arc<MessageStoreConfig>,
+    connections: Arc<Mutex<HashMap<u64, Arc<HAConnection>>>>,
+    next_connection_id: Arc<AtomicU64>,
+    /// Highest offset acknowledged by any slave.
+    push_to_slave_max_offset: Arc<AtomicI64>,
+    /// Signalled when a slave acknowledges a new offset.
+    slave_acked: Arc<Notify>,
+    /// Signalled when new data was appended, wakes idle connection writers.
+    new_data: Arc<Notify>,
+    shutdown: Arc<AtomicBool>,
+    shutdown_notify: Arc<Notify>,
+    /// Master the HA client replicates from, `None` while this broker is not a slave.
+    master_address: Arc<Mutex<Option<String>>>,
+    /// Broker id sent in the handshake, set for controller mode slaves.
+    local_broker_id: Option<i64>,
+    /// Epoch history exchanged in the handshake, set in controller mode.
+    epoch_cache: Option<Arc<Mutex<EpochFileCache>>>,
+}
+
+impl DefaultHAService {
+    pub fn new(message_store_config: Arc<MessageStoreConfig>) -> Self {
+        Self {
+            connections: Arc::new(Mutex::new(HashMap::new())),
+            next_connection_id: Arc::new(AtomicU64::new(0)),
+            push_to_slave_max_offset: Arc::new(AtomicI64::new(0)),
+            slave_acked: Arc::new(Notify::new()),
+            new_data: Arc::new(Notify::new()),
+            shutdown: Arc::new(AtomicBool::new(false)),
+            shutdown_notify: Arc::new(Notify::new()),
+            master_address: Arc::new(Mutex::new(message_store_config.ha_master_address.clone())),
+            local_broker_id: None,
+            epoch_cache: None,
+            message_store_config,
+        }
+    }
+
+    pub fn with_local_broker_id(mut self, local_broker_id: i64) -> Self {
+        self.local_broker_id = Some(local_broker_id);
+        self
+    }
+
+    pub fn with_epoch_cache(mut self, epoch_cache: Arc<Mutex<EpochFileCache>>) -> Self {
+        self.epoch_cache = Some(epoch_cache);
+        self
+    }
+
+    /// Starts the accept loop on a master, or the replicating client on a slave.
+    pub fn start(&self, commit_log: CommitLog, task_spawner: &StoreTaskSpawner) {
+        if self.message_store_config.broker_role == BrokerRole::Slave {
+            if self.master_address().is_none() {
+                warn!("slave broker has no ha_master_address, HA client not started");
+                return;
+            }
+            self.start_client(commit_log, task_spawner);
+            return;
+        }
+        self.start_accept(commit_log, task_spawner);
+    }
+
+    pub(crate) fn start_accept(&self, commit_log: CommitLog, task_spawner: &StoreTaskSpawner) {
+        let service = self.clone();
+        let spawner = task_spawner.clone();
+        task_spawner.spawn("ha_accept", async move {
+            service.accept_loop(commit_log, spawner).await;
+        });
+    }
+
+    pub(crate) fn start_client(&self, commit_log: CommitLog, task_spawner: &StoreTaskSpawner) {
+        let client = HAClient::new(commit_log, self.clone());
+        task_spawner.spawn("ha_client", client.run());
+    }
+
+    pub fn master_address(&self) -> Option<String> {
+        self.master_address.lock().clone()
+    }
+
+    /// Points the HA client at a new master, `None` leaves it idle.
+    pub fn update_master_address(&self, master_address: Option<String>) {
+        *self.master_address.lock() = master_address;
+    }
+
+    pub fn local_broker_id(&self) -> Option<i64> {
+        self.local_broker_id
+    }
+
+    /// Epoch history a master resolves slave handshakes against, empty outside controller
+    /// mode.
+    pub fn epoch_entries(&self) -> Vec<EpochEntry> {
+        self.epoch_cache
+            .as_ref()
+            .map_or_else(Vec::new, |epoch_cache| {
+                epoch_cache.lock().entries().to_vec()
+            })
+    }
+
+    /// Last epoch a slave sends in its handshake.
+    pub fn last_epoch_entry(&self) -> Option<EpochEntry> {
+        self.epoch_cache
+            .as_ref()
+            .and_then(|epoch_cache| epoch_cache.lock().last_entry())
+    }
+
+    /// Drops the epochs a slave truncated away on the master's request.
+    pub(crate) fn truncate_epochs(&self, offset: i64) {
+        if let Some(epoch_cache) = self.epoch_cache.as_ref() {
+            epoch_cache.lock().truncate_suffix_by_offset(offset);
+        }
+    }
+
+    /// Closes every slave connection, used when this broker stops being the master.
+    pub fn close_connections(&self) {
+        for connection in self.connections.lock().values() {
+            connection.stop();
+        }
+    }
+
+    pub(crate) fn connections(&self) -> Vec<Arc<HAConnection>> {
+        self.connections.lock().values().cloned().collect()
+    }
+
+    pub fn shutdown(&self) {
+        self.shutdown.store(true, Ordering::Release);
+        self.shutdown_notify.notify_waiters();
+        self.close_connections();
+        self.new_data.notify_waiters();
+    }
+
+    pub fn is_shutdown(&self) -> bool {
+        self.shutdown.load(Ordering::Acquire)
+    }
+
+    async fn accept_loop(self, commit_log: CommitLog, task_spawner: StoreTaskSpawner) {
+        let address = format!("0.0.0.0:{}", self.listen_port());
+        let listener = match TcpListener::bind(&address).await {
+            Ok(listener) => listener,
+            Err(e) => {
+                error!("HA service bind {} failed: {}", address, e);
+                return;
+            }
+        };
+        info!("HA service listening on {}", address);
+        while !self.is_shutdown() {
+            let accepted = tokio::select! {
+                accepted = listener.accept() => accepted,
+                _ = self.shutdown_notify.notified() => break,
+            };
+            match accepted {
+                Ok((stream, remote)) => {
+                    let _ = stream.set_nodelay(true);
+                    let connection = self.add_connection(remote);
+                    info!("HA service accepted slave connection {}", remote);
+                    let service = self.clone();
+                    let commit_log = commit_log.clone();
+                    task_spawner.spawn("ha_connection", async move {
+                        connection
+                            .clone()
+                            .serve(stream, commit_log, service.clone())
+                            .await;
+                        service.remove_connection(connection.id());
+                    });
+                }
+                Err(e) => {
+                    warn!("HA service accept failed: {}", e);
+                }
+            }
+        }
+        info!("HA service accept loop stopped");
+    }
+
+    pub(crate) fn add_connection(&self, remote: SocketAddr) -> Arc<HAConnection> {
+        let id = self.next_connection_id.fetch_add(1, Ordering::Relaxed);
+        let connection = Arc::new(HAConnection::new(id, remote));
+        self.connections.lock().insert(id, connection.clone());
+        connection
+    }
+
+    fn remove_connection(&self, id: u64) {
+        if let Some(connection) = self.connections.lock().remove(&id) {
+            info!("HA connection {} removed", connection.remote_addr());
+        }
+    }
+
+    /// Called by connections when a slave acknowledged `offset`.
+    pub(crate) fn notify_transfer_some(&self, offset: i64) {
+        self.push_to_slave_max_offset
+            .fetch_max(offset, Ordering::AcqRel);
+        self.slave_acked.notify_waiters();
+    }
+
+    /// Wakes connection writers waiting for new commit log data.
+    pub fn wakeup_all(&self) {
+        self.new_data.notify_waiters();
+    }
+
+    pub(crate) fn new_data(&self) -> &Notify {
+        &self.new_data
+    }
+
+    pub fn connection_count(&self) -> usize {
+        self.connections.lock().len()
+    }
+
+    pub fn push_to_slave_max_offset(&self) -> i64 {
+        self.push_to_slave_max_offset.load(Ordering::Acquire)
+    }
+
+    /// Replicas within `ha_max_gap_not_in_sync` of `master_put_where`, the master included.
+    pub fn in_sync_replicas_nums(&self, master_put_where: i64) -> u32 {
+        let max_gap = self.max_gap_not_in_sync();
+        let in_sync_slaves = self
+            .connections
+            .lock()
+            .values()
+            .filter(|c| c.slave_ack_offset() >= 0)
+            .filter(|c| master_put_where - c.slave_ack_offset() < max_gap)
+            .count();
+        in_sync_slaves as u32 + 1
+    }
+
+    /// Whether at least one slave is connected and close enough to `master_put_where`.
+    pub fn is_slave_ok(&self, master_put_where: i64) -> bool {
+        self.in_sync_replicas_nums(master_put_where) > 1
+    }
+
+    /// Replicas, the master included, that acknowledged at least `offset`. Only connections
+    /// accepted by `counted` are considered.
+    fn acked_replicas_nums(&self, offset: i64, counted: &impl Fn(&HAConnection) -> bool) -> u32 {
+        let acked_slaves = self
+            .connections
+            .lock()
+            .values()
+            .filter(|c| counted(c) && c.slave_ack_offset() >= offset)
+            .count();
+        acked_slaves as u32 + 1
+    }
+
+    /// Waits until `need_ack_nums` replicas, the master included, hold data up to
+    /// `next_offset`.
+    pub async fn wait_for_replicas(
+        &self,
+        next_offset: i64,
+        need_ack_nums: u32,
+    ) -> PutMessageStatus {
+        if self.in_sync_replicas_nums(next_offset) < need_ack_nums {
+            return PutMessageStatus::SlaveNotAvailable;
+        }
+        self.wait_for_acks(next_offset, need_ack_nums, |_| true)
+            .await
+    }
+
+    /// Waits until `need_ack_nums` replicas, the master and the connections accepted by
+    /// `counted`, acknowledged `next_offset`, or the slave timeout elapses.
+    pub(crate) async fn wait_for_acks(
+        &self,
+        next_offset: i64,
+        need_ack_nums: u32,
+        counted: impl Fn(&HAConnection) -> bool,
+    ) -> PutMessageStatus {
+        self.wakeup_all();
+        let deadline = Instant::now() + self.slave_timeout();
+        loop {
+            let acked = self.slave_acked.notified();
+            if self.acked_replicas_nums(next_offset, &counted) >= need_ack_nums {
+                return PutMessageStatus::PutOk;
+            }
+            if self.is_shutdown() || tokio::time::timeout_at(deadline, acked).await.is_err() {
+                warn!(
+                    "wait for {} replicas to ack offset {} timed out",
+                    need_ack_nums, next_offset
+                );
+                return PutMessageStatus::FlushSlaveTimeout;
+            }
+        }
+    }
+
+    /// Per-slave acknowledged offset and gap to `master_max_offset`, for health reporting.
+    pub fn slave_gaps(&self, master_max_offset: i64) -> Vec<SlaveGap> {
+        self.connections
+            .lock()
+            .values()
+            .map(|c| SlaveGap {
+                address: c.remote_addr().to_string(),
+                ack_offset: c.slave_ack_offset(),
+                gap_bytes: (master_max_offset - c.slave_ack_offset()).max(0),
+            })
+            .collect()
+    }
+
+    fn listen_port(&self) -> usize {
+        match self.message_store_config.ha_listen_port {
+            0 => DEFAULT_HA_LISTEN_PORT,
+            port => port,
+        }
+    }
+
+    fn max_gap_not_in_sync(&self) -> i64 {
+        match self.message_store_config.ha_max_gap_not_in_sync {
+            0 => DEFAULT_MAX_GAP_NOT_IN_SYNC,
+            gap => gap as i64,
+        }
+    }
+
+    fn slave_timeout(&self) -> Duration {
+        match self.message_store_config.slave_timeout {
+            0 => Duration::from_millis(DEFAULT_SLAVE_TIMEOUT_MILLIS),
+            timeout => Duration::from_millis(timeout as u64),
+        }
+    }
+
+    pub(crate) fn heartbeat_interval(&self) -> Duration {
+        match self.message_store_config.ha_send_heartbeat_interval {
+            0 => Duration::from_millis(DEFAULT_HEARTBEAT_INTERVAL_MILLIS),
+            interval => Duration::from_millis(interval as u64),
+        }
+    }
+
+    /// A connection silent for this long is considered dead.
+    pub(crate) fn housekeeping_interval(&self) -> Duration {
+        match self.message_store_config.ha_housekeeping_interval {
+            0 => Duration::from_millis(DEFAULT_HOUSEKEEPING_INTERVAL_MILLIS),
+            interval => Duration::from_millis(interval as u64),
+        }
+    }
+
+    pub(crate) fn transfer_batch_size(&self) -> usize {
+        match self.message_store_config.ha_transfer_batch_size {
+            0 => DEFAULT_TRANSFER_BATCH_SIZE,
+            size => size,
+        }
+    }
+
+    pub(crate) fn message_store_config(&self) -> &MessageStoreConfig {
+        &self.message_store_config
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn service() -> DefaultHAService {
+        DefaultHAService::new(Arc::new(MessageStoreConfig {
+            slave_timeout: 50,
+            ha_max_gap_not_in_sync: 1024,
+            ..MessageStoreConfig::default()
+        }))
+    }
+
+    #[tokio::test]
+    async fn wait_for_replicas_without_slaves_is_not_available() {
+        let service = service();
+        assert_eq!(
+            service.wait_for_replicas(100, 2).await,
+            PutMessageStatus::SlaveNotAvailable
+        );
+    }
+
+    #[tokio::test]
+    async fn wait_for_replicas_completes_on_ack_and_times_out_otherwise() {
+        let service = service();
+        let connection = service.add_connection("127.0.0.1:10000".parse().unwrap());
+        connection.set_slave_ack_offset(90);
+        assert_eq!(service.in_sync_replicas_nums(100), 2);
+        assert_eq!(
+            service.wait_for_replicas(100, 2).await,
+            PutMessageStatus::FlushSlaveTimeout
+        );
+
+        let waiter = {
+            let service = service.clone();
+            tokio::spawn(async move { service.wait_for_replicas(200, 2).await })
+        };
+        tokio::task::yield_now().await;
+        connection.set_slave_ack_offset(200);
+        service.notify_transfer_some(200);
+        assert_eq!(waiter.await.unwrap(), PutMessageStatus::PutOk);
+        assert_eq!(service.push_to_slave_max_offset(), 200);
+
+        connection.set_slave_ack_offset(-1);
+        assert!(!service.is_slave_ok(100));
+    }
+}
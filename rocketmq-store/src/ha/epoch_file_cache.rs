@@ -0,0 +1,241 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use rocketmq_common::FileUtils::file_to_string;
+use rocketmq_common::FileUtils::string_to_file;
+use serde::Deserialize;
+use serde::Serialize;
+use tracing::warn;
+
+/// Commit log range written while `epoch` was the master epoch. The end offset of the last
+/// entry is open, `i64::MAX`, until the next epoch starts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EpochEntry {
+    pub epoch: i32,
+    pub start_offset: i64,
+    #[serde(skip, default = "open_end_offset")]
+    pub end_offset: i64,
+}
+
+fn open_end_offset() -> i64 {
+    i64::MAX
+}
+
+impl EpochEntry {
+    pub fn new(epoch: i32, start_offset: i64) -> Self {
+        Self {
+            epoch,
+            start_offset,
+            end_offset: i64::MAX,
+        }
+    }
+}
+
+/// Persisted history of master epochs and the commit log offset each one started at, used to
+/// find where the logs of two replicas diverge after a master switch.
+pub struct EpochFileCache {
+    path: String,
+    entries: Vec<EpochEntry>,
+}
+
+impl EpochFileCache {
+    pub fn new(path: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            entries: Vec::new(),
+        }
+    }
+
+    /// Loads the entries from disk, a missing or corrupted file leaves the cache empty.
+    pub fn init_from_file(&mut self) -> bool {
+        let content = match file_to_string(&self.path) {
+            Ok(content) => content,
+            Err(e) => {
+                warn!("read epoch file {} failed: {}", self.path, e);
+                return false;
+            }
+        };
+        if content.trim().is_empty() {
+            return true;
+        }
+        match serde_json::from_str::<Vec<EpochEntry>>(&content) {
+            Ok(entries) => {
+                self.entries = entries;
+                self.link_end_offsets();
+                true
+            }
+            Err(e) => {
+                warn!("ignore corrupted epoch file {}: {}", self.path, e);
+                false
+            }
+        }
+    }
+
+    /// Appends a new epoch, which must be newer than the last one and start at or after it.
+    pub fn append_entry(&mut self, entry: EpochEntry) -> bool {
+        if let Some(last) = self.entries.last() {
+            if entry.epoch <= last.epoch || entry.start_offset < last.start_offset {
+                return false;
+            }
+        }
+        self.entries
+            .push(EpochEntry::new(entry.epoch, entry.start_offset));
+        self.link_end_offsets();
+        self.flush();
+        true
+    }
+
+    pub fn entries(&self) -> &[EpochEntry] {
+        &self.entries
+    }
+
+    pub fn last_entry(&self) -> Option<EpochEntry> {
+        self.entries.last().copied()
+    }
+
+    /// Last epoch, `-1` if no epoch was recorded yet.
+    pub fn last_epoch(&self) -> i32 {
+        self.entries.last().map_or(-1, |entry| entry.epoch)
+    }
+
+    pub fn get_entry(&self, epoch: i32) -> Option<EpochEntry> {
+        self.entries
+            .iter()
+            .find(|entry| entry.epoch == epoch)
+            .copied()
+    }
+
+    /// Epoch whose range holds `offset`.
+    pub fn find_entry_by_offset(&self, offset: i64) -> Option<EpochEntry> {
+        self.entries
+            .iter()
+            .rev()
+            .find(|entry| entry.start_offset <= offset)
+            .filter(|entry| offset < entry.end_offset)
+            .copied()
+    }
+
+    /// Closes the last epoch at `end_offset`, the local max offset when this broker steps down.
+    pub fn set_last_entry_end_offset(&mut self, end_offset: i64) {
+        if let Some(last) = self.entries.last_mut() {
+            last.end_offset = end_offset;
+        }
+    }
+
+    /// Drops the epochs starting at or after `offset`, after the commit log was truncated there.
+    pub fn truncate_suffix_by_offset(&mut self, offset: i64) {
+        let len = self.entries.len();
+        self.entries.retain(|entry| entry.start_offset < offset);
+        if self.entries.len() != len {
+            self.link_end_offsets();
+            self.flush();
+        }
+    }
+
+    /// Drops the epochs equal to or newer than `epoch`.
+    pub fn truncate_suffix_by_epoch(&mut self, epoch: i32) {
+        let len = self.entries.len();
+        self.entries.retain(|entry| entry.epoch < epoch);
+        if self.entries.len() != len {
+            self.link_end_offsets();
+            self.flush();
+        }
+    }
+
+    /// Drops the epochs that ended before `offset`, once that part of the commit log expired.
+    pub fn truncate_prefix_by_offset(&mut self, offset: i64) {
+        let len = self.entries.len();
+        self.entries.retain(|entry| entry.end_offset > offset);
+        if self.entries.len() != len {
+            self.flush();
+        }
+    }
+
+    /// Highest offset up to which this history and `other` agree, `-1` if they share no epoch.
+    pub fn find_consistent_point(&self, other: &EpochFileCache) -> i64 {
+        for local in self.entries.iter().rev() {
+            if let Some(remote) = other.get_entry(local.epoch) {
+                if remote.start_offset == local.start_offset {
+                    return local.end_offset.min(remote.end_offset);
+                }
+            }
+        }
+        -1
+    }
+
+    fn link_end_offsets(&mut self) {
+        for i in 0..self.entries.len() {
+            self.entries[i].end_offset = self
+                .entries
+                .get(i + 1)
+                .map_or(i64::MAX, |next| next.start_offset);
+        }
+    }
+
+    fn flush(&self) {
+        let content = serde_json::to_string(&self.entries).unwrap_or_default();
+        if let Err(e) = string_to_file(&content, &self.path) {
+            warn!("persist epoch file {} failed: {}", self.path, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn append_truncate_and_reload() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("epochFileCheckpoint");
+        let path = path.to_str().unwrap();
+        let mut cache = EpochFileCache::new(path);
+        assert!(cache.append_entry(EpochEntry::new(1, 0)));
+        assert!(cache.append_entry(EpochEntry::new(2, 1000)));
+        assert!(!cache.append_entry(EpochEntry::new(2, 2000)));
+        assert!(cache.append_entry(EpochEntry::new(3, 3000)));
+        assert_eq!(cache.get_entry(1).unwrap().end_offset, 1000);
+        assert_eq!(cache.find_entry_by_offset(1500).unwrap().epoch, 2);
+        assert_eq!(cache.find_entry_by_offset(5000).unwrap().epoch, 3);
+
+        cache.truncate_suffix_by_offset(3000);
+        assert_eq!(cache.last_epoch(), 2);
+        assert_eq!(cache.last_entry().unwrap().end_offset, i64::MAX);
+
+        let mut reloaded = EpochFileCache::new(path);
+        assert!(reloaded.init_from_file());
+        assert_eq!(reloaded.entries(), cache.entries());
+    }
+
+    #[test]
+    fn consistent_point_is_end_of_last_shared_epoch() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut master = EpochFileCache::new(dir.path().join("master").to_str().unwrap());
+        let mut slave = EpochFileCache::new(dir.path().join("slave").to_str().unwrap());
+        master.append_entry(EpochEntry::new(1, 0));
+        master.append_entry(EpochEntry::new(2, 1000));
+        master.append_entry(EpochEntry::new(3, 2000));
+        slave.append_entry(EpochEntry::new(1, 0));
+        slave.append_entry(EpochEntry::new(2, 1000));
+        slave.set_last_entry_end_offset(1500);
+        assert_eq!(slave.find_consistent_point(&master), 1500);
+
+        slave.truncate_suffix_by_epoch(2);
+        slave.append_entry(EpochEntry::new(2, 1200));
+        assert_eq!(slave.find_consistent_point(&master), 1000);
+    }
+}
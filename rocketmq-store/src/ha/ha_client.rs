@@ -0,0 +1,227 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::time::Duration;
+
+use bytes::Buf;
+use bytes::BufMut;
+use bytes::BytesMut;
+use tokio::io::AsyncReadExt;
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpStream;
+use tokio::time::Instant;
+use tracing::error;
+use tracing::info;
+use tracing::warn;
+
+use crate::ha::default_ha_service::DefaultHAService;
+use crate::ha::ha_connection::REPORT_SIZE;
+use crate::ha::ha_connection::TRANSFER_HEADER_SIZE;
+use crate::ha::ha_handshake::HAHandshake;
+use crate::ha::ha_handshake::HandshakeAck;
+use crate::ha::ha_handshake::HandshakeResult;
+use crate::ha::ha_handshake::HA_HANDSHAKE_ACK_LENGTH;
+use crate::ha::ha_handshake::HA_HANDSHAKE_ACK_V2_LENGTH;
+use crate::ha::ha_handshake::HA_HANDSHAKE_VERSION;
+use crate::log_file::commit_log::CommitLog;
+
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(5);
+const IDLE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Slave side of the replication: connects to the master, handshakes with the local max
+/// offset and appends the pushed commit log data, reporting its progress back.
+///
+/// The master address is read from the service before every connect, so a controller mode
+/// broker can be pointed at a new master, or left idle while it is the master itself.
+pub struct HAClient {
+    master_address: String,
+    commit_log: CommitLog,
+    service: DefaultHAService,
+}
+
+impl HAClient {
+    pub fn new(commit_log: CommitLog, service: DefaultHAService) -> Self {
+        Self {
+            master_address: String::new(),
+            commit_log,
+            service,
+        }
+    }
+
+    pub async fn run(mut self) {
+        info!("HA client started");
+        while !self.service.is_shutdown() {
+            let Some(master_address) = self.service.master_address() else {
+                tokio::time::sleep(IDLE_INTERVAL).await;
+                continue;
+            };
+            self.master_address = master_address;
+            match TcpStream::connect(self.master_address.as_str()).await {
+                Ok(stream) => {
+                    let _ = stream.set_nodelay(true);
+                    info!("HA client connected to master {}", self.master_address);
+                    if let Err(e) = self.transfer(stream).await {
+                        warn!(
+                            "HA client connection to {} closed: {}",
+                            self.master_address, e
+                        );
+                    }
+                }
+                Err(e) => {
+                    warn!("HA client connect to {} failed: {}", self.master_address, e);
+                }
+            }
+            if !self.service.is_shutdown() {
+                tokio::time::sleep(RECONNECT_INTERVAL).await;
+            }
+        }
+        info!("HA client stopped");
+    }
+
+    async fn transfer(&mut self, mut stream: TcpStream) -> Result<(), String> {
+        let housekeeping_interval = self.service.housekeeping_interval();
+        let heartbeat_interval = self.service.heartbeat_interval();
+
+        let mut handshake = HAHandshake::new(self.commit_log.get_max_offset());
+        if let Some(broker_id) = self.service.local_broker_id() {
+            handshake = handshake.with_broker_id(broker_id);
+        }
+        if let Some(entry) = self.service.last_epoch_entry() {
+            handshake = handshake.with_epoch(entry.epoch, entry.start_offset);
+        }
+        stream
+            .write_all(&handshake.encode())
+            .await
+            .map_err(|e| e.to_string())?;
+        let mut ack = [0u8; HA_HANDSHAKE_ACK_V2_LENGTH];
+        tokio::time::timeout(
+            housekeeping_interval,
+            stream.read_exact(&mut ack[..HA_HANDSHAKE_ACK_LENGTH]),
+        )
+        .await
+        .map_err(|_| "handshake ack timed out".to_string())?
+        .map_err(|e| e.to_string())?;
+        let ack_length =
+            HA_HANDSHAKE_ACK_LENGTH + HandshakeAck::remaining_length((&ack[..4]).get_i32());
+        tokio::time::timeout(
+            housekeeping_interval,
+            stream.read_exact(&mut ack[HA_HANDSHAKE_ACK_LENGTH..ack_length]),
+        )
+        .await
+        .map_err(|_| "handshake ack timed out".to_string())?
+        .map_err(|e| e.to_string())?;
+        let ack = HandshakeAck::decode(&ack[..ack_length]);
+        if ack.master_version < HA_HANDSHAKE_VERSION && handshake.has_epoch() {
+            warn!(
+                "master {} speaks HA protocol version {}, epochs are not checked",
+                self.master_address, ack.master_version
+            );
+        }
+        let from_offset = match ack.result {
+            HandshakeResult::CatchUp { from_offset } => from_offset,
+            HandshakeResult::Truncate { truncate_offset } => {
+                warn!(
+                    "HA client diverged from master {} at epoch {}, truncating to {}",
+                    self.master_address, ack.master_epoch, truncate_offset
+                );
+                if !self.commit_log.truncate_to(truncate_offset) {
+                    return Err(format!("truncate to offset {} failed", truncate_offset));
+                }
+                self.service.truncate_epochs(truncate_offset);
+                truncate_offset
+            }
+            HandshakeResult::FullResync { reason } => {
+                error!(
+                    "master {} requires a full resync of this slave, HA transfer refused: {}",
+                    self.master_address, reason
+                );
+                return Err("full resync required".to_string());
+            }
+            HandshakeResult::Incompatible { reason } => {
+                error!(
+                    "master {} can not replicate to this slave (HA protocol version {}): {}",
+                    self.master_address, HA_HANDSHAKE_VERSION, reason
+                );
+                return Err("incompatible master".to_string());
+            }
+        };
+        info!(
+            "HA client handshake accepted, transfer from {}",
+            from_offset
+        );
+
+        let mut buf = BytesMut::with_capacity(64 * 1024);
+        let mut last_report = Instant::now();
+        let mut last_read = Instant::now();
+        loop {
+            while buf.len() >= TRANSFER_HEADER_SIZE {
+                let mut header = &buf[..TRANSFER_HEADER_SIZE];
+                let master_phy_offset = header.get_i64();
+                let body_size = header.get_i32() as usize;
+                if buf.len() < TRANSFER_HEADER_SIZE + body_size {
+                    break;
+                }
+                buf.advance(TRANSFER_HEADER_SIZE);
+                let body = buf.split_to(body_size).freeze();
+                if body.is_empty() {
+                    continue;
+                }
+                let slave_phy_offset = self.commit_log.get_max_offset();
+                if slave_phy_offset != 0 && slave_phy_offset != master_phy_offset {
+                    return Err(format!(
+                        "master pushed offset {} does not match slave max offset {}",
+                        master_phy_offset, slave_phy_offset
+                    ));
+                }
+                if !self.commit_log.append_data(master_phy_offset, &body).await {
+                    return Err(format!(
+                        "append data at offset {} failed",
+                        master_phy_offset
+                    ));
+                }
+                self.report_offset(&mut stream).await?;
+                last_report = Instant::now();
+            }
+
+            if last_report.elapsed() >= heartbeat_interval {
+                self.report_offset(&mut stream).await?;
+                last_report = Instant::now();
+            }
+            match tokio::time::timeout(heartbeat_interval, stream.read_buf(&mut buf)).await {
+                Ok(Ok(0)) => return Err("closed by master".to_string()),
+                Ok(Ok(_)) => last_read = Instant::now(),
+                Ok(Err(e)) => return Err(e.to_string()),
+                Err(_) => {}
+            }
+            if last_read.elapsed() >= housekeeping_interval {
+                return Err(format!("master silent for {:?}", last_read.elapsed()));
+            }
+            if self.service.is_shutdown() {
+                return Ok(());
+            }
+            if self.service.master_address().as_deref() != Some(self.master_address.as_str()) {
+                info!("HA client master changed, leaving {}", self.master_address);
+                return Ok(());
+            }
+        }
+    }
+
+    async fn report_offset(&self, stream: &mut TcpStream) -> Result<(), String> {
+        let mut report = BytesMut::with_capacity(REPORT_SIZE);
+        report.put_i64(self.commit_log.get_max_offset());
+        stream.write_all(&report).await.map_err(|e| e.to_string())
+    }
+}
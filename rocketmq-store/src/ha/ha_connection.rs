@@ -0,0 +1,350 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::net::SocketAddr;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicI64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+use bytes::Buf;
+use bytes::BufMut;
+use bytes::Bytes;
+use bytes::BytesMut;
+use tokio::io::AsyncReadExt;
+use tokio::io::AsyncWriteExt;
+use tokio::net::tcp::OwnedReadHalf;
+use tokio::net::tcp::OwnedWriteHalf;
+use tokio::net::TcpStream;
+use tokio::sync::Notify;
+use tokio::time::Instant;
+use tracing::info;
+use tracing::warn;
+
+use crate::ha::default_ha_service::DefaultHAService;
+use crate::ha::ha_handshake::HandshakeAck;
+use crate::ha::ha_handshake::HandshakeResult;
+use crate::ha::ha_handshake::MasterHandshakeState;
+use crate::ha::ha_handshake::SlaveReport;
+use crate::ha::ha_read_ahead::HAReadAheadPipeline;
+use crate::ha::ha_read_ahead::ReadAheadConfig;
+use crate::ha::ha_read_ahead::SegmentSource;
+use crate::log_file::commit_log::CommitLog;
+
+/// Master to slave frame header: physical offset (8 bytes) and body size (4 bytes).
+pub const TRANSFER_HEADER_SIZE: usize = 12;
+/// Slave to master ack: the slave's max physical offset.
+pub const REPORT_SIZE: usize = 8;
+const WAIT_NEW_DATA: Duration = Duration::from_millis(100);
+
+/// One slave connected to the master. The read side consumes the slave's offset reports, the
+/// write side pushes commit log data from the offset agreed in the handshake.
+pub struct HAConnection {
+    id: u64,
+    remote_addr: SocketAddr,
+    /// Max offset the slave reported, `-1` until the first report arrives.
+    slave_ack_offset: AtomicI64,
+    /// Broker id the slave sent in its handshake, `-1` if unknown.
+    slave_broker_id: AtomicI64,
+    stopped: AtomicBool,
+    stop_notify: Notify,
+}
+
+impl HAConnection {
+    pub fn new(id: u64, remote_addr: SocketAddr) -> Self {
+        Self {
+            id,
+            remote_addr,
+            slave_ack_offset: AtomicI64::new(-1),
+            slave_broker_id: AtomicI64::new(-1),
+            stopped: AtomicBool::new(false),
+            stop_notify: Notify::new(),
+        }
+    }
+
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    pub fn remote_addr(&self) -> SocketAddr {
+        self.remote_addr
+    }
+
+    pub fn slave_ack_offset(&self) -> i64 {
+        self.slave_ack_offset.load(Ordering::Acquire)
+    }
+
+    pub(crate) fn set_slave_ack_offset(&self, offset: i64) {
+        self.slave_ack_offset.store(offset, Ordering::Release);
+    }
+
+    pub fn slave_broker_id(&self) -> i64 {
+        self.slave_broker_id.load(Ordering::Acquire)
+    }
+
+    pub(crate) fn set_slave_broker_id(&self, broker_id: i64) {
+        self.slave_broker_id.store(broker_id, Ordering::Release);
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.stopped.load(Ordering::Acquire)
+    }
+
+    pub fn stop(&self) {
+        self.stopped.store(true, Ordering::Release);
+        self.stop_notify.notify_one();
+    }
+
+    /// Runs the connection until the slave disconnects, falls silent or the service shuts down.
+    pub(crate) async fn serve(
+        self: Arc<Self>,
+        mut stream: TcpStream,
+        commit_log: CommitLog,
+        service: DefaultHAService,
+    ) {
+        let mut buf = BytesMut::with_capacity(1024);
+        let report = loop {
+            if let Some(report) = SlaveReport::decode(&mut buf) {
+                break report;
+            }
+            if !self.read_some(&mut stream, &mut buf, &service).await {
+                return;
+            }
+        };
+        let from_offset = match report {
+            SlaveReport::Handshake(handshake) => {
+                self.set_slave_broker_id(handshake.slave_broker_id);
+                let master = MasterHandshakeState {
+                    min_offset: commit_log.get_min_offset(),
+                    max_offset: commit_log.get_max_offset(),
+                    epochs: service.epoch_entries(),
+                };
+                let master_epoch = master.epochs.last().map_or(-1, |entry| entry.epoch);
+                let ack = HandshakeAck::new(
+                    handshake.resolve_against(&master),
+                    master_epoch,
+                    master.max_offset,
+                );
+                if let Err(e) = stream.write_all(&ack.encode(handshake.version)).await {
+                    warn!(
+                        "HA connection {} write handshake ack failed: {}",
+                        self.remote_addr, e
+                    );
+                    return;
+                }
+                match ack.result {
+                    HandshakeResult::CatchUp { from_offset } => from_offset,
+                    HandshakeResult::Truncate { truncate_offset } => {
+                        warn!(
+                            "HA connection {} diverged from master epoch {}, slave truncates to {}",
+                            self.remote_addr, master_epoch, truncate_offset
+                        );
+                        truncate_offset
+                    }
+                    HandshakeResult::FullResync { reason } => {
+                        warn!(
+                            "HA connection {} needs a full resync, closing: {}",
+                            self.remote_addr, reason
+                        );
+                        return;
+                    }
+                    HandshakeResult::Incompatible { reason } => {
+                        warn!(
+                            "HA connection {} rejected, incompatible slave: {}",
+                            self.remote_addr, reason
+                        );
+                        return;
+                    }
+                }
+            }
+            SlaveReport::Legacy(0) => {
+                // an empty legacy slave starts from the beginning of the last commit log file
+                let max_offset = commit_log.get_max_offset();
+                max_offset
+                    - max_offset % service.message_store_config().mapped_file_size_commit_log as i64
+            }
+            SlaveReport::Legacy(offset) => offset,
+        };
+        let acked_offset = match report {
+            SlaveReport::Handshake(_) => from_offset,
+            SlaveReport::Legacy(offset) => offset,
+        };
+        self.set_slave_ack_offset(acked_offset);
+        service.notify_transfer_some(acked_offset);
+        info!(
+            "HA connection {} transfers from offset {}",
+            self.remote_addr, from_offset
+        );
+
+        let (reader, writer) = stream.into_split();
+        tokio::select! {
+            _ = self.read_loop(reader, buf, &service) => {}
+            _ = self.write_loop(writer, from_offset, &commit_log, &service) => {}
+            _ = self.stop_notify.notified() => {}
+        }
+        self.stop();
+        info!("HA connection {} closed", self.remote_addr);
+    }
+
+    async fn read_some<R: AsyncReadExt + Unpin>(
+        &self,
+        reader: &mut R,
+        buf: &mut BytesMut,
+        service: &DefaultHAService,
+    ) -> bool {
+        match tokio::time::timeout(service.housekeeping_interval(), reader.read_buf(buf)).await {
+            Ok(Ok(0)) => {
+                info!("HA connection {} closed by slave", self.remote_addr);
+                false
+            }
+            Ok(Ok(_)) => !self.is_stopped(),
+            Ok(Err(e)) => {
+                warn!("HA connection {} read failed: {}", self.remote_addr, e);
+                false
+            }
+            Err(_) => {
+                warn!(
+                    "HA connection {} expired, no report within {:?}",
+                    self.remote_addr,
+                    service.housekeeping_interval()
+                );
+                false
+            }
+        }
+    }
+
+    async fn read_loop(
+        &self,
+        mut reader: OwnedReadHalf,
+        mut buf: BytesMut,
+        service: &DefaultHAService,
+    ) {
+        loop {
+            while buf.len() >= REPORT_SIZE {
+                let offset = buf.get_i64();
+                self.set_slave_ack_offset(offset);
+                service.notify_transfer_some(offset);
+            }
+            if !self.read_some(&mut reader, &mut buf, service).await {
+                return;
+            }
+        }
+    }
+
+    async fn write_loop(
+        &self,
+        mut writer: OwnedWriteHalf,
+        from_offset: i64,
+        commit_log: &CommitLog,
+        service: &DefaultHAService,
+    ) {
+        let read_ahead_config = ReadAheadConfig::new(service.message_store_config());
+        let batch_size = service.transfer_batch_size();
+        let heartbeat_interval = service.heartbeat_interval();
+        let mut pipeline: Option<HAReadAheadPipeline> = None;
+        let mut next_offset = from_offset;
+        let mut last_write = Instant::now();
+
+        while !self.is_stopped() {
+            let master_max_offset = commit_log.get_max_offset();
+            if !read_ahead_config.should_read_ahead(next_offset, master_max_offset) {
+                pipeline = None;
+            } else if pipeline.is_none() {
+                info!(
+                    "HA connection {} lags {} bytes, reading ahead",
+                    self.remote_addr,
+                    master_max_offset - next_offset
+                );
+                pipeline = Some(HAReadAheadPipeline::start(
+                    Arc::new(commit_log.clone()),
+                    next_offset,
+                    read_ahead_config,
+                    &self.remote_addr.to_string(),
+                ));
+            }
+
+            if let Some(reader) = pipeline.as_mut() {
+                reader.on_slave_ack(self.slave_ack_offset());
+                match tokio::time::timeout(heartbeat_interval, reader.next_segment()).await {
+                    Ok(Some(segment)) if segment.offset == next_offset => {
+                        let len = segment.data.len() as i64;
+                        if !self
+                            .write_frame(&mut writer, next_offset, segment.data)
+                            .await
+                        {
+                            return;
+                        }
+                        next_offset += len;
+                        last_write = Instant::now();
+                    }
+                    Ok(_) => pipeline = None,
+                    Err(_) => {
+                        if !self
+                            .write_frame(&mut writer, next_offset, Bytes::new())
+                            .await
+                        {
+                            return;
+                        }
+                        last_write = Instant::now();
+                    }
+                }
+                continue;
+            }
+
+            let new_data = service.new_data().notified();
+            if let Some(data) = commit_log.read(next_offset, batch_size) {
+                let len = data.len() as i64;
+                if !self.write_frame(&mut writer, next_offset, data).await {
+                    return;
+                }
+                next_offset += len;
+                last_write = Instant::now();
+                continue;
+            }
+            if last_write.elapsed() >= heartbeat_interval {
+                if !self
+                    .write_frame(&mut writer, next_offset, Bytes::new())
+                    .await
+                {
+                    return;
+                }
+                last_write = Instant::now();
+            }
+            tokio::select! {
+                _ = new_data => {}
+                _ = tokio::time::sleep(WAIT_NEW_DATA) => {}
+            }
+        }
+    }
+
+    /// Writes one transfer frame, an empty body is a heartbeat.
+    async fn write_frame(&self, writer: &mut OwnedWriteHalf, offset: i64, body: Bytes) -> bool {
+        let mut header = BytesMut::with_capacity(TRANSFER_HEADER_SIZE);
+        header.put_i64(offset);
+        header.put_i32(body.len() as i32);
+        let result = match writer.write_all(&header).await {
+            Ok(()) if body.is_empty() => Ok(()),
+            Ok(()) => writer.write_all(&body).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            warn!("HA connection {} write failed: {}", self.remote_addr, e);
+            return false;
+        }
+        true
+    }
+}
@@ -0,0 +1,584 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use bytes::Buf;
+use bytes::BufMut;
+use bytes::Bytes;
+use bytes::BytesMut;
+
+use crate::ha::epoch_file_cache::EpochEntry;
+
+/// "HAHS", distinguishes a handshake from the legacy 8 byte offset report.
+pub const HA_HANDSHAKE_MAGIC: u32 = 0x4841_4853;
+/// Protocol version spoken by this build. Version 2 added the epoch extension and the
+/// versioned ack.
+pub const HA_HANDSHAKE_VERSION: u16 = 2;
+/// Oldest slave version a master still serves.
+pub const HA_HANDSHAKE_MIN_VERSION: u16 = 1;
+pub const HA_HANDSHAKE_LENGTH: usize = 24;
+/// Ack sent to version 1 slaves: accepted flag and transfer start offset.
+pub const HA_HANDSHAKE_ACK_LENGTH: usize = 12;
+/// Ack sent to version 2 slaves, the version 1 layout followed by the master epoch and max
+/// offset.
+pub const HA_HANDSHAKE_ACK_V2_LENGTH: usize = 24;
+const LEGACY_REPORT_LENGTH: usize = 8;
+
+/// The slave store was populated from a snapshot and has never received HA data.
+pub const FLAG_SNAPSHOT_BOOTSTRAP: u16 = 0x1;
+/// The handshake carries the slave broker id in 8 extra bytes, sent by controller mode slaves.
+pub const FLAG_BROKER_ID: u16 = 0x2;
+/// The handshake carries the slave's last epoch (4 bytes) and its start offset (8 bytes),
+/// after the broker id if both are sent.
+pub const FLAG_EPOCH: u16 = 0x4;
+const KNOWN_FLAGS: u16 = FLAG_SNAPSHOT_BOOTSTRAP | FLAG_BROKER_ID | FLAG_EPOCH;
+const BROKER_ID_LENGTH: usize = 8;
+const EPOCH_LENGTH: usize = 4 + 8;
+
+const ACK_FULL_RESYNC: i32 = 0;
+const ACK_CATCH_UP: i32 = 1;
+const ACK_TRUNCATE: i32 = 2;
+const ACK_INCOMPATIBLE: i32 = 3;
+
+/// First frame a slave sends after connecting to the master.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HAHandshake {
+    pub version: u16,
+    pub flags: u16,
+    pub slave_max_offset: i64,
+    /// Confirm offset of the snapshot the slave was bootstrapped from, `-1` if none.
+    pub snapshot_confirm_offset: i64,
+    /// Broker id of the slave, `-1` when not sent.
+    pub slave_broker_id: i64,
+    /// Last epoch of the slave's commit log, `-1` when not sent.
+    pub slave_epoch: i32,
+    pub slave_epoch_start_offset: i64,
+}
+
+/// What the master reads from a slave connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SlaveReport {
+    /// Pre-handshake slaves only report their max offset.
+    Legacy(i64),
+    Handshake(HAHandshake),
+}
+
+/// Master decision for a new slave connection.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HandshakeResult {
+    CatchUp {
+        from_offset: i64,
+    },
+    /// The slave wrote data the master does not have, it drops everything from
+    /// `truncate_offset` on and catches up from there.
+    Truncate {
+        truncate_offset: i64,
+    },
+    FullResync {
+        reason: String,
+    },
+    /// The peers can not replicate at all, e.g. an unsupported protocol version or a stale
+    /// master.
+    Incompatible {
+        reason: String,
+    },
+}
+
+/// Master side of the handshake.
+#[derive(Debug, Clone, Default)]
+pub struct MasterHandshakeState {
+    pub min_offset: i64,
+    pub max_offset: i64,
+    /// Epoch history of a controller mode master, empty otherwise.
+    pub epochs: Vec<EpochEntry>,
+}
+
+/// Master reply to a handshake.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HandshakeAck {
+    /// Protocol version of the master, 1 for masters that predate the versioned ack.
+    pub master_version: u16,
+    pub result: HandshakeResult,
+    /// `-1` when the master keeps no epoch history or only sent a version 1 ack.
+    pub master_epoch: i32,
+    pub master_max_offset: i64,
+}
+
+impl HAHandshake {
+    pub fn new(slave_max_offset: i64) -> Self {
+        HAHandshake {
+            version: HA_HANDSHAKE_VERSION,
+            flags: 0,
+            slave_max_offset,
+            snapshot_confirm_offset: -1,
+            slave_broker_id: -1,
+            slave_epoch: -1,
+            slave_epoch_start_offset: -1,
+        }
+    }
+
+    pub fn from_snapshot(slave_max_offset: i64, snapshot_confirm_offset: i64) -> Self {
+        HAHandshake {
+            version: HA_HANDSHAKE_VERSION,
+            flags: FLAG_SNAPSHOT_BOOTSTRAP,
+            slave_max_offset,
+            snapshot_confirm_offset,
+            slave_broker_id: -1,
+            slave_epoch: -1,
+            slave_epoch_start_offset: -1,
+        }
+    }
+
+    pub fn with_broker_id(mut self, slave_broker_id: i64) -> Self {
+        self.flags |= FLAG_BROKER_ID;
+        self.slave_broker_id = slave_broker_id;
+        self
+    }
+
+    /// Sends the last epoch of the slave's commit log, so the master can tell where the two
+    /// logs diverge.
+    pub fn with_epoch(mut self, slave_epoch: i32, slave_epoch_start_offset: i64) -> Self {
+        self.flags |= FLAG_EPOCH;
+        self.slave_epoch = slave_epoch;
+        self.slave_epoch_start_offset = slave_epoch_start_offset;
+        self
+    }
+
+    #[inline]
+    pub fn is_snapshot_bootstrap(&self) -> bool {
+        self.flags & FLAG_SNAPSHOT_BOOTSTRAP != 0
+    }
+
+    #[inline]
+    pub fn has_broker_id(&self) -> bool {
+        self.flags & FLAG_BROKER_ID != 0
+    }
+
+    #[inline]
+    pub fn has_epoch(&self) -> bool {
+        self.flags & FLAG_EPOCH != 0
+    }
+
+    /// Offset the master should start transferring from. A bootstrapped slave never trusts
+    /// data past the snapshot confirm offset, even if recovery left some behind.
+    pub fn transfer_from_offset(&self) -> i64 {
+        if self.is_snapshot_bootstrap() && self.snapshot_confirm_offset >= 0 {
+            self.slave_max_offset.min(self.snapshot_confirm_offset)
+        } else {
+            self.slave_max_offset
+        }
+    }
+
+    pub fn encode(&self) -> Bytes {
+        let mut buf = BytesMut::with_capacity(HA_HANDSHAKE_LENGTH);
+        buf.put_u32(HA_HANDSHAKE_MAGIC);
+        buf.put_u16(self.version);
+        buf.put_u16(self.flags);
+        buf.put_i64(self.slave_max_offset);
+        buf.put_i64(self.snapshot_confirm_offset);
+        if self.has_broker_id() {
+            buf.put_i64(self.slave_broker_id);
+        }
+        if self.has_epoch() {
+            buf.put_i32(self.slave_epoch);
+            buf.put_i64(self.slave_epoch_start_offset);
+        }
+        buf.freeze()
+    }
+
+    /// Checks whether a master without epoch history can serve this slave incrementally.
+    pub fn resolve(&self, master_min_offset: i64, master_max_offset: i64) -> HandshakeResult {
+        self.resolve_against(&MasterHandshakeState {
+            min_offset: master_min_offset,
+            max_offset: master_max_offset,
+            epochs: Vec::new(),
+        })
+    }
+
+    /// Checks whether the master can serve this slave incrementally. When both sides know
+    /// their epochs, a slave holding data past the point where its log and the master's
+    /// diverge is told to truncate it first.
+    pub fn resolve_against(&self, master: &MasterHandshakeState) -> HandshakeResult {
+        if !(HA_HANDSHAKE_MIN_VERSION..=HA_HANDSHAKE_VERSION).contains(&self.version) {
+            return HandshakeResult::Incompatible {
+                reason: format!(
+                    "slave speaks HA protocol version {}, master supports {} to {}",
+                    self.version, HA_HANDSHAKE_MIN_VERSION, HA_HANDSHAKE_VERSION
+                ),
+            };
+        }
+        if self.flags & !KNOWN_FLAGS != 0 {
+            return HandshakeResult::Incompatible {
+                reason: format!("unknown handshake flags {:#x}", self.flags & !KNOWN_FLAGS),
+            };
+        }
+        let mut from_offset = self.transfer_from_offset();
+        let mut truncate = false;
+        if self.has_epoch() && !master.epochs.is_empty() {
+            let consistent_offset = match self.consistent_offset(&master.epochs) {
+                Ok(consistent_offset) => consistent_offset,
+                Err(result) => return result,
+            };
+            if from_offset > consistent_offset {
+                from_offset = consistent_offset;
+                truncate = true;
+            }
+        }
+        let (master_min_offset, master_max_offset) = (master.min_offset, master.max_offset);
+        if from_offset < master_min_offset {
+            return HandshakeResult::FullResync {
+                reason: format!(
+                    "slave offset {} is behind master min offset {}",
+                    from_offset, master_min_offset
+                ),
+            };
+        }
+        if from_offset > master_max_offset {
+            return HandshakeResult::FullResync {
+                reason: format!(
+                    "slave offset {} is ahead of master max offset {}",
+                    from_offset, master_max_offset
+                ),
+            };
+        }
+        if truncate {
+            HandshakeResult::Truncate {
+                truncate_offset: from_offset,
+            }
+        } else {
+            HandshakeResult::CatchUp { from_offset }
+        }
+    }
+
+    /// Highest offset up to which the slave's log agrees with the master's epoch history. The
+    /// epochs before the slave's last one are assumed to match the master's.
+    fn consistent_offset(&self, epochs: &[EpochEntry]) -> Result<i64, HandshakeResult> {
+        let master_epoch = epochs.last().map_or(-1, |entry| entry.epoch);
+        if self.slave_epoch > master_epoch {
+            return Err(HandshakeResult::Incompatible {
+                reason: format!(
+                    "slave epoch {} is newer than master epoch {}, the master is stale",
+                    self.slave_epoch, master_epoch
+                ),
+            });
+        }
+        if let Some(entry) = epochs.iter().find(|entry| {
+            entry.epoch == self.slave_epoch && entry.start_offset == self.slave_epoch_start_offset
+        }) {
+            return Ok(entry.end_offset);
+        }
+        match epochs
+            .iter()
+            .rev()
+            .find(|entry| entry.epoch < self.slave_epoch)
+        {
+            Some(entry) => Ok(entry.end_offset.min(self.slave_epoch_start_offset)),
+            None => Err(HandshakeResult::FullResync {
+                reason: format!(
+                    "slave epoch {} shares no history with the master",
+                    self.slave_epoch
+                ),
+            }),
+        }
+    }
+}
+
+impl SlaveReport {
+    /// Decodes the first frame of a slave connection, returns `None` until enough bytes are
+    /// buffered.
+    pub fn decode(buf: &mut BytesMut) -> Option<SlaveReport> {
+        if buf.len() >= 4 && (&buf[..4]).get_u32() == HA_HANDSHAKE_MAGIC {
+            if buf.len() < HA_HANDSHAKE_LENGTH {
+                return None;
+            }
+            let flags = (&buf[6..8]).get_u16();
+            let mut length = HA_HANDSHAKE_LENGTH;
+            if flags & FLAG_BROKER_ID != 0 {
+                length += BROKER_ID_LENGTH;
+            }
+            if flags & FLAG_EPOCH != 0 {
+                length += EPOCH_LENGTH;
+            }
+            if buf.len() < length {
+                return None;
+            }
+            let mut frame = buf.split_to(length);
+            frame.advance(4);
+            let mut handshake = HAHandshake {
+                version: frame.get_u16(),
+                flags: frame.get_u16(),
+                slave_max_offset: frame.get_i64(),
+                snapshot_confirm_offset: frame.get_i64(),
+                slave_broker_id: -1,
+                slave_epoch: -1,
+                slave_epoch_start_offset: -1,
+            };
+            if handshake.has_broker_id() {
+                handshake.slave_broker_id = frame.get_i64();
+            }
+            if handshake.has_epoch() {
+                handshake.slave_epoch = frame.get_i32();
+                handshake.slave_epoch_start_offset = frame.get_i64();
+            }
+            return Some(SlaveReport::Handshake(handshake));
+        }
+        if buf.len() < LEGACY_REPORT_LENGTH {
+            return None;
+        }
+        Some(SlaveReport::Legacy(
+            buf.split_to(LEGACY_REPORT_LENGTH).get_i64(),
+        ))
+    }
+
+    pub fn transfer_from_offset(&self) -> i64 {
+        match self {
+            SlaveReport::Legacy(offset) => *offset,
+            SlaveReport::Handshake(handshake) => handshake.transfer_from_offset(),
+        }
+    }
+}
+
+impl HandshakeAck {
+    pub fn new(result: HandshakeResult, master_epoch: i32, master_max_offset: i64) -> Self {
+        HandshakeAck {
+            master_version: HA_HANDSHAKE_VERSION,
+            result,
+            master_epoch,
+            master_max_offset,
+        }
+    }
+
+    /// Encodes the reply in the layout the slave understands. A version 1 slave gets the
+    /// accepted flag (4 bytes) and the transfer start offset, newer slaves get the master
+    /// version and result code in that first int, followed by the master epoch and max offset.
+    pub fn encode(&self, slave_version: u16) -> Bytes {
+        let (code, offset) = match &self.result {
+            HandshakeResult::CatchUp { from_offset } => (ACK_CATCH_UP, *from_offset),
+            HandshakeResult::Truncate { truncate_offset } => (ACK_TRUNCATE, *truncate_offset),
+            HandshakeResult::FullResync { .. } => (ACK_FULL_RESYNC, -1),
+            HandshakeResult::Incompatible { .. } => (ACK_INCOMPATIBLE, -1),
+        };
+        if slave_version < 2 {
+            let mut buf = BytesMut::with_capacity(HA_HANDSHAKE_ACK_LENGTH);
+            if code == ACK_CATCH_UP {
+                buf.put_i32(1);
+                buf.put_i64(offset);
+            } else {
+                buf.put_i32(0);
+                buf.put_i64(-1);
+            }
+            return buf.freeze();
+        }
+        let mut buf = BytesMut::with_capacity(HA_HANDSHAKE_ACK_V2_LENGTH);
+        buf.put_i32(((self.master_version as i32) << 16) | code);
+        buf.put_i64(offset);
+        buf.put_i32(self.master_epoch);
+        buf.put_i64(self.master_max_offset);
+        buf.freeze()
+    }
+
+    /// Bytes that follow the first [`HA_HANDSHAKE_ACK_LENGTH`] of an ack starting with `head`,
+    /// a version 1 master sends no more.
+    pub fn remaining_length(head: i32) -> usize {
+        if head >> 16 == 0 {
+            0
+        } else {
+            HA_HANDSHAKE_ACK_V2_LENGTH - HA_HANDSHAKE_ACK_LENGTH
+        }
+    }
+
+    pub fn decode(mut buf: &[u8]) -> HandshakeAck {
+        let head = buf.get_i32();
+        let offset = buf.get_i64();
+        let master_version = (head >> 16) as u16;
+        if master_version == 0 {
+            let result = if head == 1 {
+                HandshakeResult::CatchUp {
+                    from_offset: offset,
+                }
+            } else {
+                HandshakeResult::FullResync {
+                    reason: "refused by master".to_string(),
+                }
+            };
+            return HandshakeAck {
+                master_version: 1,
+                result,
+                master_epoch: -1,
+                master_max_offset: -1,
+            };
+        }
+        let result = match head & 0xFFFF {
+            ACK_CATCH_UP => HandshakeResult::CatchUp {
+                from_offset: offset,
+            },
+            ACK_TRUNCATE => HandshakeResult::Truncate {
+                truncate_offset: offset,
+            },
+            ACK_INCOMPATIBLE => HandshakeResult::Incompatible {
+                reason: format!(
+                    "master speaks HA protocol version {}, this slave {}",
+                    master_version, HA_HANDSHAKE_VERSION
+                ),
+            },
+            _ => HandshakeResult::FullResync {
+                reason: "refused by master".to_string(),
+            },
+        };
+        HandshakeAck {
+            master_version,
+            result,
+            master_epoch: buf.get_i32(),
+            master_max_offset: buf.get_i64(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_handshake_and_legacy_report() {
+        let handshake = HAHandshake::from_snapshot(2048, 1024);
+        let mut buf = BytesMut::from(&handshake.encode()[..]);
+        assert_eq!(
+            SlaveReport::decode(&mut buf),
+            Some(SlaveReport::Handshake(handshake))
+        );
+        assert!(buf.is_empty());
+
+        let mut buf = BytesMut::new();
+        buf.put_i64(4096);
+        assert_eq!(
+            SlaveReport::decode(&mut buf),
+            Some(SlaveReport::Legacy(4096))
+        );
+
+        let mut partial = BytesMut::from(&HAHandshake::new(1).encode()[..10]);
+        assert_eq!(SlaveReport::decode(&mut partial), None);
+
+        let with_id = HAHandshake::new(4096).with_broker_id(2);
+        let encoded = with_id.encode();
+        let mut partial = BytesMut::from(&encoded[..HA_HANDSHAKE_LENGTH]);
+        assert_eq!(SlaveReport::decode(&mut partial), None);
+        let mut buf = BytesMut::from(&encoded[..]);
+        assert_eq!(
+            SlaveReport::decode(&mut buf),
+            Some(SlaveReport::Handshake(with_id))
+        );
+    }
+
+    #[test]
+    fn snapshot_slave_catches_up_from_confirm_offset() {
+        let handshake = HAHandshake::from_snapshot(2048, 1024);
+        assert_eq!(
+            handshake.resolve(0, 4096),
+            HandshakeResult::CatchUp { from_offset: 1024 }
+        );
+        assert!(matches!(
+            handshake.resolve(1 << 20, 2 << 20),
+            HandshakeResult::FullResync { .. }
+        ));
+        assert!(matches!(
+            HAHandshake::new(8192).resolve(0, 4096),
+            HandshakeResult::FullResync { .. }
+        ));
+    }
+
+    fn master_state(epochs: &[(i32, i64)], max_offset: i64) -> MasterHandshakeState {
+        let mut entries: Vec<EpochEntry> = epochs
+            .iter()
+            .map(|(epoch, start_offset)| EpochEntry::new(*epoch, *start_offset))
+            .collect();
+        for i in 1..entries.len() {
+            entries[i - 1].end_offset = entries[i].start_offset;
+        }
+        MasterHandshakeState {
+            min_offset: 0,
+            max_offset,
+            epochs: entries,
+        }
+    }
+
+    #[test]
+    fn epoch_handshake_tells_diverged_slave_to_truncate() {
+        let with_epoch = HAHandshake::new(1500).with_broker_id(2).with_epoch(2, 1200);
+        let mut buf = BytesMut::from(&with_epoch.encode()[..]);
+        assert_eq!(
+            SlaveReport::decode(&mut buf),
+            Some(SlaveReport::Handshake(with_epoch))
+        );
+
+        // the slave led epoch 2 while the master never saw it
+        let master = master_state(&[(1, 0), (3, 1000)], 4096);
+        assert_eq!(
+            with_epoch.resolve_against(&master),
+            HandshakeResult::Truncate {
+                truncate_offset: 1000
+            }
+        );
+        // same epoch, the slave is simply behind
+        assert_eq!(
+            HAHandshake::new(1500)
+                .with_epoch(3, 1000)
+                .resolve_against(&master),
+            HandshakeResult::CatchUp { from_offset: 1500 }
+        );
+        assert!(matches!(
+            HAHandshake::new(1500)
+                .with_epoch(4, 1200)
+                .resolve_against(&master),
+            HandshakeResult::Incompatible { .. }
+        ));
+
+        let mut newer = HAHandshake::new(0);
+        newer.version = HA_HANDSHAKE_VERSION + 1;
+        assert!(matches!(
+            newer.resolve(0, 4096),
+            HandshakeResult::Incompatible { .. }
+        ));
+    }
+
+    #[test]
+    fn ack_layout_follows_slave_version() {
+        let ack = HandshakeAck::new(
+            HandshakeResult::Truncate {
+                truncate_offset: 1000,
+            },
+            3,
+            4096,
+        );
+        let encoded = ack.encode(HA_HANDSHAKE_VERSION);
+        assert_eq!(encoded.len(), HA_HANDSHAKE_ACK_V2_LENGTH);
+        let head = (&encoded[..4]).get_i32();
+        assert_eq!(
+            HandshakeAck::remaining_length(head),
+            HA_HANDSHAKE_ACK_V2_LENGTH - HA_HANDSHAKE_ACK_LENGTH
+        );
+        assert_eq!(HandshakeAck::decode(&encoded), ack);
+
+        let legacy =
+            HandshakeAck::new(HandshakeResult::CatchUp { from_offset: 512 }, -1, 4096).encode(1);
+        assert_eq!(legacy.len(), HA_HANDSHAKE_ACK_LENGTH);
+        assert_eq!(HandshakeAck::remaining_length((&legacy[..4]).get_i32()), 0);
+        let decoded = HandshakeAck::decode(&legacy);
+        assert_eq!(decoded.master_version, 1);
+        assert_eq!(
+            decoded.result,
+            HandshakeResult::CatchUp { from_offset: 512 }
+        );
+    }
+}
@@ -0,0 +1,276 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicI64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+use bytes::Bytes;
+use parking_lot::Condvar;
+use parking_lot::Mutex;
+use tokio::sync::mpsc;
+use tracing::info;
+
+use crate::config::message_store_config::MessageStoreConfig;
+use crate::log_file::commit_log::CommitLog;
+
+const DEFAULT_CHUNK_SIZE: usize = 32 * 1024;
+const IDLE_WAIT: Duration = Duration::from_millis(100);
+
+/// Sequential reader the read-ahead pipeline pulls commit log data from.
+pub trait SegmentSource: Send + Sync + 'static {
+    /// Returns up to `max_len` bytes starting at `offset`, `None` when nothing is readable
+    /// there yet. Reads never cross a segment boundary.
+    fn read(&self, offset: i64, max_len: usize) -> Option<Bytes>;
+}
+
+impl SegmentSource for CommitLog {
+    fn read(&self, offset: i64, max_len: usize) -> Option<Bytes> {
+        let result = self.get_data(offset)?;
+        let buffer = result.get_buffer();
+        if buffer.is_empty() {
+            return None;
+        }
+        Some(Bytes::copy_from_slice(&buffer[..buffer.len().min(max_len)]))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReadAheadConfig {
+    pub chunk_size: usize,
+    /// Memory held by chunks read but not yet handed to the connection.
+    pub max_buffered_bytes: usize,
+    /// How far reads may run ahead of the offset the slave acknowledged.
+    pub max_unacked_bytes: i64,
+    /// Slaves closer than this to the master are served directly, without read-ahead.
+    pub activate_gap_bytes: i64,
+}
+
+impl ReadAheadConfig {
+    pub fn new(message_store_config: &MessageStoreConfig) -> Self {
+        let chunk_size = if message_store_config.ha_transfer_batch_size > 0 {
+            message_store_config.ha_transfer_batch_size
+        } else {
+            DEFAULT_CHUNK_SIZE
+        };
+        ReadAheadConfig {
+            chunk_size,
+            max_buffered_bytes: message_store_config
+                .ha_read_ahead_max_buffered_bytes
+                .max(chunk_size),
+            max_unacked_bytes: message_store_config
+                .ha_read_ahead_max_unacked_bytes
+                .max(chunk_size) as i64,
+            activate_gap_bytes: message_store_config.ha_read_ahead_activate_gap_bytes as i64,
+        }
+    }
+
+    /// Whether a slave at `slave_offset` is far enough behind to benefit from read-ahead.
+    #[inline]
+    pub fn should_read_ahead(&self, slave_offset: i64, master_max_offset: i64) -> bool {
+        master_max_offset - slave_offset >= self.activate_gap_bytes
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Segment {
+    pub offset: i64,
+    pub data: Bytes,
+}
+
+struct Progress {
+    read_offset: AtomicI64,
+    acked_offset: AtomicI64,
+    stopped: AtomicBool,
+    lock: Mutex<()>,
+    ack_advanced: Condvar,
+}
+
+/// Prefetches commit log data for one lagging slave on a dedicated thread. Reads are
+/// sequential so the kernel readahead keeps up, buffered memory is bounded by the channel
+/// capacity, and the reader pauses once it is `max_unacked_bytes` ahead of the slave ack so a
+/// slow slave does not pull the whole backlog through the page cache at once.
+pub struct HAReadAheadPipeline {
+    progress: Arc<Progress>,
+    receiver: mpsc::Receiver<Segment>,
+}
+
+impl HAReadAheadPipeline {
+    pub fn start<S: SegmentSource>(
+        source: Arc<S>,
+        from_offset: i64,
+        config: ReadAheadConfig,
+        name: &str,
+    ) -> Self {
+        let capacity = (config.max_buffered_bytes / config.chunk_size).max(1);
+        let (sender, receiver) = mpsc::channel(capacity);
+        let progress = Arc::new(Progress {
+            read_offset: AtomicI64::new(from_offset),
+            acked_offset: AtomicI64::new(from_offset),
+            stopped: AtomicBool::new(false),
+            lock: Mutex::new(()),
+            ack_advanced: Condvar::new(),
+        });
+        let reader_progress = progress.clone();
+        std::thread::Builder::new()
+            .name(format!("HAReadAhead-{}", name))
+            .spawn(move || read_ahead_loop(source.as_ref(), &reader_progress, sender, config))
+            .expect("spawn ha read ahead thread failed");
+        HAReadAheadPipeline { progress, receiver }
+    }
+
+    /// Next prefetched segment in offset order, `None` once the pipeline stopped.
+    pub async fn next_segment(&mut self) -> Option<Segment> {
+        self.receiver.recv().await
+    }
+
+    /// Records the offset the slave reported, releasing the reader if it was held back.
+    pub fn on_slave_ack(&self, slave_offset: i64) {
+        let previous = self
+            .progress
+            .acked_offset
+            .fetch_max(slave_offset, Ordering::AcqRel);
+        if slave_offset > previous {
+            let _guard = self.progress.lock.lock();
+            self.progress.ack_advanced.notify_all();
+        }
+    }
+
+    /// Offset the next read starts from.
+    pub fn read_offset(&self) -> i64 {
+        self.progress.read_offset.load(Ordering::Acquire)
+    }
+
+    pub fn stop(&self) {
+        self.progress.stopped.store(true, Ordering::Release);
+        let _guard = self.progress.lock.lock();
+        self.progress.ack_advanced.notify_all();
+    }
+}
+
+impl Drop for HAReadAheadPipeline {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+fn read_ahead_loop<S: SegmentSource + ?Sized>(
+    source: &S,
+    progress: &Progress,
+    sender: mpsc::Sender<Segment>,
+    config: ReadAheadConfig,
+) {
+    while !progress.stopped.load(Ordering::Acquire) {
+        let read_offset = progress.read_offset.load(Ordering::Acquire);
+        if read_offset - progress.acked_offset.load(Ordering::Acquire) >= config.max_unacked_bytes {
+            let mut guard = progress.lock.lock();
+            progress.ack_advanced.wait_for(&mut guard, IDLE_WAIT);
+            continue;
+        }
+        let Some(data) = source.read(read_offset, config.chunk_size) else {
+            // caught up with the writer, wait for new data
+            let mut guard = progress.lock.lock();
+            progress.ack_advanced.wait_for(&mut guard, IDLE_WAIT);
+            continue;
+        };
+        let len = data.len() as i64;
+        if sender
+            .blocking_send(Segment {
+                offset: read_offset,
+                data,
+            })
+            .is_err()
+        {
+            break;
+        }
+        progress
+            .read_offset
+            .store(read_offset + len, Ordering::Release);
+    }
+    info!(
+        "ha read ahead stopped at offset {}",
+        progress.read_offset.load(Ordering::Acquire)
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct MemorySource {
+        data: Vec<u8>,
+        segment_size: usize,
+    }
+
+    impl SegmentSource for MemorySource {
+        fn read(&self, offset: i64, max_len: usize) -> Option<Bytes> {
+            let offset = offset as usize;
+            if offset >= self.data.len() {
+                return None;
+            }
+            let segment_end = (offset / self.segment_size + 1) * self.segment_size;
+            let end = (offset + max_len).min(segment_end).min(self.data.len());
+            Some(Bytes::copy_from_slice(&self.data[offset..end]))
+        }
+    }
+
+    fn config() -> ReadAheadConfig {
+        ReadAheadConfig {
+            chunk_size: 64,
+            max_buffered_bytes: 256,
+            max_unacked_bytes: 256,
+            activate_gap_bytes: 1024,
+        }
+    }
+
+    #[tokio::test]
+    async fn segments_arrive_in_order_and_are_paced_by_acks() {
+        let data = (0..1000).map(|i| i as u8).collect::<Vec<_>>();
+        let source = Arc::new(MemorySource {
+            data: data.clone(),
+            segment_size: 100,
+        });
+        let mut pipeline = HAReadAheadPipeline::start(source, 0, config(), "test");
+
+        let mut received = Vec::new();
+        while received.len() < 256 {
+            let segment = pipeline.next_segment().await.unwrap();
+            assert_eq!(segment.offset, received.len() as i64);
+            received.extend_from_slice(&segment.data);
+        }
+        // without an ack the reader may not run further than max_unacked_bytes
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(pipeline.read_offset() <= 256 + 64);
+
+        pipeline.on_slave_ack(received.len() as i64);
+        while received.len() < 512 {
+            let segment = pipeline.next_segment().await.unwrap();
+            assert_eq!(segment.offset, received.len() as i64);
+            received.extend_from_slice(&segment.data);
+        }
+        assert_eq!(&received[..], &data[..received.len()]);
+        pipeline.stop();
+    }
+
+    #[test]
+    fn read_ahead_only_for_lagging_slaves() {
+        let config = config();
+        assert!(config.should_read_ahead(0, 4096));
+        assert!(!config.should_read_ahead(4000, 4096));
+    }
+}
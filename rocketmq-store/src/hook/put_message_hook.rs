@@ -14,11 +14,16 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
-use rocketmq_common::common::message::message_ext::MessageExt;
+use rocketmq_common::common::message::message_batch::MessageExtBatch;
+use rocketmq_common::common::message::message_ext_broker_inner::MessageExtBrokerInner;
 
 use crate::base::message_result::PutMessageResult;
 
 /// Trait for hook executed before putting a message.
+///
+/// The store runs its hooks in registration order before the message reaches the commit log,
+/// the first hook returning a result short-circuits the put with that result. Hooks may rewrite
+/// the message, e.g. to redirect timer or delay messages to their system topic.
 pub trait PutMessageHook {
     /// Returns the name of the hook.
     fn hook_name(&self) -> String;
@@ -32,8 +37,21 @@ pub trait PutMessageHook {
     ///
     /// # Returns
     ///
-    /// The result of putting the message
-    fn execute_before_put_message(&self, msg: &MessageExt) -> Option<PutMessageResult>;
+    /// `Some` to reject the put with that result, `None` to go on
+    fn execute_before_put_message(
+        &self,
+        msg: &mut MessageExtBrokerInner,
+    ) -> Option<PutMessageResult>;
+
+    /// Execute before putting a batch, runs `execute_before_put_message` on the batch envelope
+    /// unless overridden. Hooks rewriting messages should leave batches alone, the messages are
+    /// already encoded.
+    fn execute_before_put_messages(
+        &self,
+        msg_batch: &mut MessageExtBatch,
+    ) -> Option<PutMessageResult> {
+        self.execute_before_put_message(&mut msg_batch.message_ext_broker_inner)
+    }
 }
 
 /// Alias for `Arc<dyn PutMessageHook>`.
@@ -19,6 +19,7 @@ use std::sync::Arc;
 
 use crate::base::commit_log_dispatcher::CommitLogDispatcher;
 use crate::base::dispatch_request::DispatchRequest;
+use crate::base::dispatch_rule::DispatchRules;
 use crate::config::message_store_config::MessageStoreConfig;
 use crate::index::index_service::IndexService;
 
@@ -26,20 +27,31 @@ use crate::index::index_service::IndexService;
 pub struct CommitLogDispatcherBuildIndex {
     index_service: IndexService,
     message_store_config: Arc<MessageStoreConfig>,
+    dispatch_rules: Arc<DispatchRules>,
 }
 
 impl CommitLogDispatcherBuildIndex {
-    pub fn new(index_service: IndexService, message_store_config: Arc<MessageStoreConfig>) -> Self {
+    pub fn new(
+        index_service: IndexService,
+        message_store_config: Arc<MessageStoreConfig>,
+        dispatch_rules: Arc<DispatchRules>,
+    ) -> Self {
         Self {
             index_service,
             message_store_config,
+            dispatch_rules,
         }
     }
 }
 
 impl CommitLogDispatcher for CommitLogDispatcherBuildIndex {
     fn dispatch(&self, dispatch_request: &DispatchRequest) {
-        if self.message_store_config.message_index_enable {
+        if self.message_store_config.message_index_enable
+            && self
+                .dispatch_rules
+                .route(dispatch_request.topic.as_str())
+                .index
+        {
             self.index_service.build_index(dispatch_request);
         }
     }
@@ -18,7 +18,8 @@
 use std::sync::Arc;
 
 use bytes::Buf;
-use bytes::Bytes;
+use bytes::BufMut;
+use bytes::BytesMut;
 use cheetah_string::CheetahString;
 use rocketmq_common::common::hasher::string_hasher::JavaStringHasher;
 
@@ -115,7 +116,13 @@ impl IndexFile {
         let begin_time = std::time::Instant::now();
         if self.mapped_file.hold() {
             self.index_header.update_byte_buffer();
-            self.mapped_file.flush(0);
+            if let Err(e) = self.mapped_file.get_mapped_file().flush() {
+                log::warn!(
+                    "flush index file {} failed: {}",
+                    self.mapped_file.get_file_name(),
+                    e
+                );
+            }
             self.mapped_file.release();
             log::info!(
                 "flush index file elapsed time(ms) {}",
@@ -133,81 +140,51 @@ impl IndexFile {
     }
 
     pub fn put_key(&self, key: &str, phy_offset: i64, store_timestamp: i64) -> bool {
-        if self.index_header.get_index_count() < self.index_num as i32 {
-            let key_hash = self.index_key_hash_method(key);
-            let slot_pos = key_hash as usize % self.hash_slot_num;
-            let abs_slot_pos = INDEX_HEADER_SIZE + slot_pos * HASH_SLOT_SIZE;
-
-            let mut buffer = self
-                .mapped_file
-                .get_data(abs_slot_pos, abs_slot_pos + 4)
-                .unwrap();
-            let mut slot_value = buffer.get_i32();
-            if slot_value <= INVALID_INDEX || slot_value > self.index_header.get_index_count() {
-                slot_value = INVALID_INDEX;
-            }
-
-            let mut time_diff = store_timestamp - self.index_header.get_begin_timestamp();
-            time_diff /= 1000;
-            if self.index_header.get_begin_timestamp() <= 0 {
-                time_diff = 0;
-            } else if time_diff > i32::MAX as i64 {
-                time_diff = i32::MAX as i64;
-            } else if time_diff < 0 {
-                time_diff = 0;
-            }
-
-            let abs_index_pos = INDEX_HEADER_SIZE
-                + self.hash_slot_num * HASH_SLOT_SIZE
-                + self.index_header.get_index_count() as usize * INDEX_SIZE;
-
-            self.mapped_file.append_message_offset_length(
-                &Bytes::copy_from_slice(&key_hash.to_be_bytes()),
-                abs_index_pos,
-                4,
-            );
-            self.mapped_file.append_message_offset_length(
-                &Bytes::copy_from_slice(&phy_offset.to_be_bytes()),
-                abs_index_pos,
-                8,
-            );
-            self.mapped_file.append_message_offset_length(
-                &Bytes::copy_from_slice(&(time_diff as i32).to_be_bytes()),
-                abs_index_pos,
-                4,
-            );
-            self.mapped_file.append_message_offset_length(
-                &Bytes::copy_from_slice(&slot_value.to_be_bytes()),
-                abs_index_pos,
-                4,
-            );
-            self.mapped_file.append_message_offset_length(
-                &Bytes::copy_from_slice(&self.index_header.get_index_count().to_be_bytes()),
-                abs_slot_pos,
-                4,
-            );
-
-            if self.index_header.get_index_count() <= 1 {
-                self.index_header.set_begin_phy_offset(phy_offset);
-                self.index_header.set_begin_timestamp(store_timestamp);
-            }
-
-            if slot_value == INVALID_INDEX {
-                self.index_header.inc_hash_slot_count();
-            }
-            self.index_header.inc_index_count();
-            self.index_header.set_end_phy_offset(phy_offset);
-            self.index_header.set_end_timestamp(store_timestamp);
-
-            true
-        } else {
+        let index_count = self.index_header.get_index_count();
+        if index_count >= self.index_num as i32 {
             log::warn!(
                 "Over index file capacity: index count = {}; index max num = {}",
-                self.index_header.get_index_count(),
+                index_count,
                 self.index_num
             );
-            false
+            return false;
+        }
+        let key_hash = self.index_key_hash_method(key);
+        let abs_slot_pos = self.abs_slot_pos(key_hash);
+        let mut slot_value = self.read_i32(abs_slot_pos);
+        if slot_value <= INVALID_INDEX || slot_value > index_count {
+            slot_value = INVALID_INDEX;
+        }
+
+        let begin_timestamp = self.index_header.get_begin_timestamp();
+        let time_diff = if begin_timestamp <= 0 {
+            0
+        } else {
+            ((store_timestamp - begin_timestamp) / 1000).clamp(0, i32::MAX as i64)
+        };
+
+        // the new unit links to the previous head of the slot, the slot then points to it
+        let mut unit = BytesMut::with_capacity(INDEX_SIZE);
+        unit.put_i32(key_hash);
+        unit.put_i64(phy_offset);
+        unit.put_i32(time_diff as i32);
+        unit.put_i32(slot_value);
+        self.mapped_file
+            .put_slice(&unit, self.abs_index_pos(index_count));
+        self.mapped_file
+            .put_slice(&index_count.to_be_bytes(), abs_slot_pos);
+
+        if index_count <= 1 {
+            self.index_header.set_begin_phy_offset(phy_offset);
+            self.index_header.set_begin_timestamp(store_timestamp);
         }
+        if slot_value == INVALID_INDEX {
+            self.index_header.inc_hash_slot_count();
+        }
+        self.index_header.inc_index_count();
+        self.index_header.set_end_phy_offset(phy_offset);
+        self.index_header.set_end_timestamp(store_timestamp);
+        true
     }
 
     pub fn index_key_hash_method(&self, key: &str) -> i32 {
@@ -240,6 +217,7 @@ impl IndexFile {
             || end >= begin_timestamp && end <= end_timestamp
     }
 
+    /// Collects the physical offsets stored for `key` between `begin` and `end`, newest first.
     pub fn select_phy_offset(
         &self,
         phy_offsets: &mut Vec<i64>,
@@ -251,58 +229,115 @@ impl IndexFile {
         if !self.mapped_file.hold() {
             return;
         }
+        self.walk_slot(phy_offsets, key, max_num, begin, end);
+        self.mapped_file.release();
+    }
 
+    fn walk_slot(
+        &self,
+        phy_offsets: &mut Vec<i64>,
+        key: &str,
+        max_num: usize,
+        begin: i64,
+        end: i64,
+    ) {
+        let index_count = self.index_header.get_index_count();
         let key_hash = self.index_key_hash_method(key);
-        let slot_pos = key_hash as usize % self.hash_slot_num;
-        let abs_slot_pos = INDEX_HEADER_SIZE + slot_pos * HASH_SLOT_SIZE;
-
-        let mut buffer = self
-            .mapped_file
-            .get_data(abs_slot_pos, abs_slot_pos + 4)
-            .unwrap();
-        let slot_value = buffer.get_i32();
-        if slot_value <= INVALID_INDEX
-            || slot_value > self.index_header.get_index_count()
-            || self.index_header.get_index_count() <= 1
-        {
+        let slot_value = self.read_i32(self.abs_slot_pos(key_hash));
+        if slot_value <= INVALID_INDEX || slot_value > index_count || index_count <= 1 {
             return;
         }
 
         let mut next_index_to_read = slot_value;
         while phy_offsets.len() < max_num {
-            let abs_index_pos = INDEX_HEADER_SIZE
-                + self.hash_slot_num * HASH_SLOT_SIZE
-                + next_index_to_read as usize * INDEX_SIZE;
-
-            let key_hash_read = buffer.slice(abs_index_pos..abs_index_pos + 4).get_i32();
-            let phy_offset_read = buffer
-                .slice(abs_index_pos + 4..abs_index_pos + 12)
-                .get_i64();
-            let time_diff = buffer
-                .slice(abs_index_pos + 12..abs_index_pos + 16)
-                .get_i32();
-            let prev_index_read = buffer
-                .slice(abs_index_pos + 16..abs_index_pos + 20)
-                .get_i32();
-
+            let Some(mut unit) = self
+                .mapped_file
+                .get_bytes(self.abs_index_pos(next_index_to_read), INDEX_SIZE)
+            else {
+                break;
+            };
+            let key_hash_read = unit.get_i32();
+            let phy_offset_read = unit.get_i64();
+            let time_diff = unit.get_i32();
+            let prev_index_read = unit.get_i32();
             if time_diff < 0 {
                 break;
             }
 
             let time_read = self.index_header.get_begin_timestamp() + time_diff as i64 * 1000;
-            if key_hash == key_hash_read && (time_read >= begin && time_read <= end) {
+            if key_hash == key_hash_read && time_read >= begin && time_read <= end {
                 phy_offsets.push(phy_offset_read);
             }
 
             if prev_index_read <= INVALID_INDEX
-                || prev_index_read > self.index_header.get_index_count()
+                || prev_index_read > index_count
                 || prev_index_read == next_index_to_read
                 || time_read < begin
             {
                 break;
             }
-
             next_index_to_read = prev_index_read;
         }
     }
+
+    #[inline]
+    fn abs_slot_pos(&self, key_hash: i32) -> usize {
+        INDEX_HEADER_SIZE + (key_hash as usize % self.hash_slot_num) * HASH_SLOT_SIZE
+    }
+
+    #[inline]
+    fn abs_index_pos(&self, index: i32) -> usize {
+        INDEX_HEADER_SIZE + self.hash_slot_num * HASH_SLOT_SIZE + index as usize * INDEX_SIZE
+    }
+
+    fn read_i32(&self, pos: usize) -> i32 {
+        self.mapped_file
+            .get_bytes(pos, HASH_SLOT_SIZE)
+            .map_or(INVALID_INDEX, |mut bytes| bytes.get_i32())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn put_and_select_keys_sharing_a_slot() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_name = dir.path().join("20241016000000000");
+        // a single slot chains every key
+        let index_file = IndexFile::new(file_name.to_str().unwrap(), 1, 8, 0, 0);
+        assert!(index_file.put_key("topic#a", 100, 1_000));
+        assert!(index_file.put_key("topic#b", 200, 2_000));
+        assert!(index_file.put_key("topic#a", 300, 3_000));
+        assert_eq!(index_file.get_begin_timestamp(), 1_000);
+        assert_eq!(index_file.get_end_phy_offset(), 300);
+
+        let mut phy_offsets = Vec::new();
+        index_file.select_phy_offset(&mut phy_offsets, "topic#a", 10, 0, i64::MAX);
+        assert_eq!(phy_offsets, vec![300, 100]);
+
+        let mut phy_offsets = Vec::new();
+        index_file.select_phy_offset(&mut phy_offsets, "topic#a", 10, 0, 2_000);
+        assert_eq!(phy_offsets, vec![100]);
+
+        index_file.flush();
+        let reloaded = IndexFile::new(file_name.to_str().unwrap(), 1, 8, 0, 0);
+        reloaded.load();
+        let mut phy_offsets = Vec::new();
+        reloaded.select_phy_offset(&mut phy_offsets, "topic#b", 10, 0, i64::MAX);
+        assert_eq!(phy_offsets, vec![200]);
+    }
+
+    #[test]
+    fn put_key_fails_once_full() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_name = dir.path().join("20241016000000000");
+        let index_file = IndexFile::new(file_name.to_str().unwrap(), 4, 3, 0, 0);
+        // index 0 is reserved as the invalid index
+        assert!(index_file.put_key("k1", 1, 1_000));
+        assert!(index_file.put_key("k2", 2, 1_000));
+        assert!(index_file.is_write_full());
+        assert!(!index_file.put_key("k3", 3, 1_000));
+    }
 }
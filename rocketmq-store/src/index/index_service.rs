@@ -15,15 +15,20 @@
  * limitations under the License.
  */
 
+use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
 use std::sync::Arc;
 use std::thread;
 use std::time::Duration;
 
+use cheetah_string::CheetahString;
+use parking_lot::Mutex;
 use parking_lot::RwLock;
+use rocketmq_common::common::config::TopicConfig;
 use rocketmq_common::common::message::MessageConst;
 use rocketmq_common::common::sys_flag::message_sys_flag::MessageSysFlag;
+use rocketmq_common::utils::index_property_utils::IndexPropertyUtils;
 use rocketmq_common::TimeUtils::get_current_millis;
 use rocketmq_common::UtilAll::time_millis_to_human_string;
 use tracing::error;
@@ -47,12 +52,14 @@ pub struct IndexService {
     index_file_list: Arc<RwLock<Vec<Arc<IndexFile>>>>,
     message_store_config: Arc<MessageStoreConfig>,
     store_checkpoint: Arc<StoreCheckpoint>,
+    topic_config_table: Arc<Mutex<HashMap<CheetahString, TopicConfig>>>,
 }
 
 impl IndexService {
     pub fn new(
         message_store_config: Arc<MessageStoreConfig>,
         store_checkpoint: Arc<StoreCheckpoint>,
+        topic_config_table: Arc<Mutex<HashMap<CheetahString, TopicConfig>>>,
     ) -> Self {
         Self {
             hash_slot_num: message_store_config.max_hash_slot_num,
@@ -61,6 +68,7 @@ impl IndexService {
             index_file_list: Arc::new(Default::default()),
             message_store_config,
             store_checkpoint,
+            topic_config_table,
         }
     }
 
@@ -111,7 +119,11 @@ impl IndexService {
             return;
         }
         let mut files = Vec::new();
-        for index_file in index_file_list_lock.iter() {
+        // the last file is still being written
+        for index_file in index_file_list_lock
+            .iter()
+            .take(index_file_list_lock.len() - 1)
+        {
             if (index_file.get_end_phy_offset() as u64) < offset {
                 files.push(index_file.clone());
             } else {
@@ -119,6 +131,10 @@ impl IndexService {
             }
         }
         if !files.is_empty() {
+            for index_file in &files {
+                info!("delete expired index file {}", index_file.get_file_name());
+                index_file.destroy(3000);
+            }
             index_file_list_lock.retain(|index_file| !files.contains(index_file));
         }
     }
@@ -145,36 +161,29 @@ impl IndexService {
         let max_num = max_num.min(self.message_store_config.max_msgs_num_batch as i32);
 
         let index_file_list = self.index_file_list.read();
-        let mut index = 0;
-        if !index_file_list.is_empty() {
-            for f in index_file_list.iter() {
-                let last_file = index == index_file_list.len() - 1;
-                if last_file {
-                    // Assuming IndexFile has methods to get end timestamp and phy offset
-                    index_last_update_timestamp = f.get_end_timestamp();
-                    index_last_update_phyoffset = f.get_end_phy_offset();
-                }
-
-                // Assuming IndexFile has a method to check if the file's timestamps match the query
-                if f.is_time_matched(begin, end) {
-                    // Assuming IndexFile has a method to select physical offsets based on the query
-                    f.select_phy_offset(
-                        &mut phy_offsets,
-                        build_key(topic, key).as_str(),
-                        max_num as usize,
-                        begin,
-                        end,
-                    );
-                }
+        if let Some(last_file) = index_file_list.last() {
+            index_last_update_timestamp = last_file.get_end_timestamp();
+            index_last_update_phyoffset = last_file.get_end_phy_offset();
+        }
+        let idx_key = build_key(topic, key);
+        // newest files first, older files can only hold older messages
+        for f in index_file_list.iter().rev() {
+            if f.is_time_matched(begin, end) {
+                f.select_phy_offset(
+                    &mut phy_offsets,
+                    idx_key.as_str(),
+                    max_num as usize,
+                    begin,
+                    end,
+                );
+            }
 
-                if f.get_begin_timestamp() < begin {
-                    break;
-                }
+            if f.get_begin_timestamp() < begin {
+                break;
+            }
 
-                if phy_offsets.len() as i32 >= max_num {
-                    break;
-                }
-                index += 1;
+            if phy_offsets.len() as i32 >= max_num {
+                break;
             }
         }
         QueryOffsetResult::new(
@@ -204,39 +213,29 @@ impl IndexService {
                     _ => (),
                 }
 
-                let mut index_file_new = None;
+                let mut idx_keys = Vec::new();
                 if let Some(ref uniq_key) = dispatch_request.uniq_key {
-                    index_file_new = self.put_key(
-                        index_file_inner,
-                        dispatch_request,
-                        build_key(topic, uniq_key.as_str()).as_str(),
-                    );
-                    if index_file_new.is_none() {
-                        error!(
-                            "putKey error commitlog {} uniqkey {}",
-                            dispatch_request.commit_log_offset, uniq_key
-                        );
-                        return;
+                    idx_keys.push(build_key(topic, uniq_key.as_str()));
+                }
+                for key in keys.split(MessageConst::KEY_SEPARATOR) {
+                    if !key.is_empty() {
+                        idx_keys.push(build_key(topic, key));
                     }
                 }
+                for key in self.property_keys(dispatch_request) {
+                    idx_keys.push(build_key(topic, key.as_str()));
+                }
 
-                if !keys.is_empty() {
-                    let keyset = keys.split(MessageConst::KEY_SEPARATOR);
-                    for key in keyset {
-                        if !key.is_empty() {
-                            let index_file = self.put_key(
-                                index_file_new.take().unwrap(),
-                                dispatch_request,
-                                build_key(topic, key).as_str(),
+                let mut index_file = index_file_inner;
+                for idx_key in idx_keys {
+                    match self.put_key(index_file, dispatch_request, idx_key.as_str()) {
+                        Some(next) => index_file = next,
+                        None => {
+                            error!(
+                                "putKey error commitlog {} key {}",
+                                dispatch_request.commit_log_offset, idx_key
                             );
-                            if index_file.is_none() {
-                                error!(
-                                    "putKey error commitlog {} uniqkey {}",
-                                    dispatch_request.commit_log_offset,
-                                    dispatch_request.uniq_key.as_ref().unwrap()
-                                );
-                                return;
-                            }
+                            return;
                         }
                     }
                 }
@@ -247,6 +246,28 @@ impl IndexService {
         }
     }
 
+    /// Qualified `name=value` keys for the properties the topic declared as indexed.
+    fn property_keys(&self, dispatch_request: &DispatchRequest) -> Vec<String> {
+        let properties = match dispatch_request.properties_map.as_ref() {
+            Some(properties) if !properties.is_empty() => properties,
+            _ => return Vec::new(),
+        };
+        let topic_config = self
+            .topic_config_table
+            .lock()
+            .get(dispatch_request.topic.as_str())
+            .cloned();
+        IndexPropertyUtils::get_index_property_keys(&topic_config)
+            .iter()
+            .filter_map(|name| {
+                properties
+                    .get(name.as_str())
+                    .filter(|value| !value.is_empty())
+                    .map(|value| IndexPropertyUtils::build_property_key(name, value.as_str()))
+            })
+            .collect()
+    }
+
     fn put_key(
         &self,
         mut index_file: Arc<IndexFile>,
@@ -345,6 +366,20 @@ impl IndexService {
         index_file
     }
 
+    /// Flushes the index file currently being written, if any.
+    pub fn flush_last_index_file(&self) {
+        let last_index_file = self.index_file_list.read().last().cloned();
+        self.flush(last_index_file);
+    }
+
+    /// Flushes every index file, used when nothing else flushes the files that filled up.
+    pub fn flush_all(&self) {
+        let index_files = self.index_file_list.read().clone();
+        for index_file in index_files {
+            self.flush(Some(index_file));
+        }
+    }
+
     pub fn flush(&self, index_file: Option<Arc<IndexFile>>) {
         match index_file {
             None => {}
@@ -371,3 +406,45 @@ fn build_key(topic: &str, key: &str) -> String {
     keys.push_str(key);
     keys
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn build_index_and_query_by_key_and_uniq_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let message_store_config = Arc::new(MessageStoreConfig {
+            store_path_root_dir: CheetahString::from(dir.path().to_string_lossy().to_string()),
+            max_hash_slot_num: 16,
+            max_index_num: 64,
+            ..MessageStoreConfig::default()
+        });
+        let store_checkpoint =
+            Arc::new(StoreCheckpoint::new(dir.path().join("checkpoint")).unwrap());
+        let index_service = IndexService::new(
+            message_store_config,
+            store_checkpoint,
+            Arc::new(Mutex::new(HashMap::new())),
+        );
+        for (offset, keys, uniq_key) in [(0, "order-1 order-2", "U1"), (100, "order-1", "U2")] {
+            index_service.build_index(&DispatchRequest {
+                topic: CheetahString::from_static_str("TopicTest"),
+                commit_log_offset: offset,
+                msg_size: 100,
+                store_timestamp: 1_000 + offset,
+                keys: CheetahString::from_static_str(keys),
+                uniq_key: Some(CheetahString::from_static_str(uniq_key)),
+                success: true,
+                ..DispatchRequest::default()
+            });
+        }
+
+        let result = index_service.query_offset("TopicTest", "order-1", 32, 0, i64::MAX);
+        assert_eq!(result.get_phy_offsets(), &vec![100, 0]);
+        let result = index_service.query_offset("TopicTest", "U2", 32, 0, i64::MAX);
+        assert_eq!(result.get_phy_offsets(), &vec![100]);
+        let result = index_service.query_offset("OtherTopic", "order-2", 32, 0, i64::MAX);
+        assert!(result.get_phy_offsets().is_empty());
+    }
+}
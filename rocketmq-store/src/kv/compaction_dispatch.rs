@@ -0,0 +1,72 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use cheetah_string::CheetahString;
+use rocketmq_common::common::attribute::cleanup_policy::CleanupPolicy;
+use rocketmq_common::common::config::TopicConfig;
+use rocketmq_common::CleanupPolicyUtils::get_delete_policy;
+
+use crate::base::commit_log_dispatcher::CommitLogDispatcher;
+use crate::base::dispatch_request::DispatchRequest;
+use crate::config::message_store_config::MessageStoreConfig;
+use crate::kv::compaction_service::CompactionRequest;
+use crate::kv::compaction_service::CompactionService;
+
+/// Routes the messages of topics with the `compaction` cleanup policy to the compaction
+/// service, next to their normal consume queue.
+pub struct CommitLogDispatcherCompaction {
+    compaction_service: CompactionService,
+    message_store_config: Arc<MessageStoreConfig>,
+    topic_config_table: Arc<parking_lot::Mutex<HashMap<CheetahString, TopicConfig>>>,
+}
+
+impl CommitLogDispatcherCompaction {
+    pub fn new(
+        compaction_service: CompactionService,
+        message_store_config: Arc<MessageStoreConfig>,
+        topic_config_table: Arc<parking_lot::Mutex<HashMap<CheetahString, TopicConfig>>>,
+    ) -> Self {
+        Self {
+            compaction_service,
+            message_store_config,
+            topic_config_table,
+        }
+    }
+}
+
+impl CommitLogDispatcher for CommitLogDispatcherCompaction {
+    fn dispatch(&self, dispatch_request: &DispatchRequest) {
+        if !self.message_store_config.enable_compaction || !dispatch_request.success {
+            return;
+        }
+        let policy = get_delete_policy(self.topic_config_table.lock().get(&dispatch_request.topic));
+        if policy != CleanupPolicy::COMPACTION {
+            return;
+        }
+        self.compaction_service.put_request(CompactionRequest {
+            topic: dispatch_request.topic.clone(),
+            queue_id: dispatch_request.queue_id,
+            commit_log_offset: dispatch_request.commit_log_offset,
+            msg_size: dispatch_request.msg_size,
+            queue_offset: dispatch_request.consume_queue_offset,
+            keys: dispatch_request.keys.clone(),
+        });
+    }
+}
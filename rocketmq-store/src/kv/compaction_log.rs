@@ -0,0 +1,583 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use bytes::Buf;
+use bytes::Bytes;
+use cheetah_string::CheetahString;
+use parking_lot::Mutex;
+use rocketmq_common::common::message::MessageTrait;
+use rocketmq_common::MessageDecoder;
+use tracing::error;
+use tracing::info;
+use tracing::warn;
+
+use crate::base::get_message_result::GetMessageResult;
+use crate::base::message_status_enum::GetMessageStatus;
+use crate::log_file::mapped_file::default_mapped_file_impl::DefaultMappedFile;
+use crate::log_file::mapped_file::MappedFile;
+
+const COMPACTING_DIR: &str = "compacting";
+// written once the compacted segments are complete, holds "<active base offset> <segment count>"
+const COMMIT_MARKER: &str = "commit";
+
+/// What a compaction run did to one log.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CompactionStats {
+    pub removed_messages: usize,
+    pub removed_segments: usize,
+}
+
+/// Keyed copy of one (topic, queueId) for topics with the `compaction` cleanup policy.
+///
+/// Messages are appended in their commit log encoding to segment files named after the first
+/// queue offset they hold. Only the last segment is written to; compaction rewrites the sealed
+/// ones keeping the latest message of every key and packs the survivors into as few segments
+/// as possible. Queue offsets are kept, so a consumer reading an offset that was compacted
+/// away is moved on to the next surviving message.
+pub struct CompactionLog {
+    topic: CheetahString,
+    queue_id: i32,
+    dir: PathBuf,
+    segment_size: u64,
+    inner: Mutex<CompactionLogInner>,
+}
+
+#[derive(Default)]
+struct CompactionLogInner {
+    segments: Vec<Segment>,
+    index: BTreeMap<i64, IndexEntry>,
+    // queue offset after the last appended message
+    max_offset: i64,
+}
+
+#[derive(Clone)]
+struct Segment {
+    base_offset: i64,
+    file: Arc<DefaultMappedFile>,
+}
+
+#[derive(Clone)]
+struct IndexEntry {
+    file: Arc<DefaultMappedFile>,
+    pos: i32,
+    size: i32,
+    keys: CheetahString,
+}
+
+impl CompactionLog {
+    pub fn new(topic: CheetahString, queue_id: i32, root_dir: &Path, segment_size: u64) -> Self {
+        let dir = root_dir.join(topic.as_str()).join(queue_id.to_string());
+        Self {
+            topic,
+            queue_id,
+            dir,
+            segment_size,
+            inner: Mutex::new(CompactionLogInner::default()),
+        }
+    }
+
+    pub fn topic(&self) -> &CheetahString {
+        &self.topic
+    }
+
+    pub fn queue_id(&self) -> i32 {
+        self.queue_id
+    }
+
+    /// Maps the segments on disk and rebuilds the offset index from the messages they hold.
+    /// A compaction interrupted after its segments were complete is finished first.
+    pub fn load(&self) -> bool {
+        if let Err(e) = self.finish_interrupted_compaction() {
+            error!(
+                "finish interrupted compaction of {}-{} failed: {}",
+                self.topic, self.queue_id, e
+            );
+            return false;
+        }
+        let mut bases = match segment_bases(&self.dir) {
+            Ok(bases) => bases,
+            Err(e) => {
+                error!("list compaction log dir {:?} failed: {}", self.dir, e);
+                return false;
+            }
+        };
+        bases.sort_unstable();
+
+        let mut inner = self.inner.lock();
+        for base_offset in bases {
+            let path = segment_path(&self.dir, base_offset);
+            let file = Arc::new(DefaultMappedFile::new(
+                CheetahString::from(path.to_string_lossy().as_ref()),
+                self.segment_size,
+            ));
+            let mut pos = 0i32;
+            while let Some((size, queue_offset, keys)) = read_record(&file, pos, self.segment_size)
+            {
+                inner.index.insert(
+                    queue_offset,
+                    IndexEntry {
+                        file: file.clone(),
+                        pos,
+                        size,
+                        keys,
+                    },
+                );
+                inner.max_offset = inner.max_offset.max(queue_offset + 1);
+                pos += size;
+            }
+            file.set_wrote_position(pos);
+            file.set_committed_position(pos);
+            file.set_flushed_position(pos);
+            inner.segments.push(Segment { base_offset, file });
+        }
+        info!(
+            "load compaction log {}-{}, segments: {}, messages: {}, max offset: {}",
+            self.topic,
+            self.queue_id,
+            inner.segments.len(),
+            inner.index.len(),
+            inner.max_offset
+        );
+        true
+    }
+
+    /// Appends one message read from the commit log. Offsets below the current max were
+    /// appended before and are skipped, which makes a re-dispatch after recovery harmless.
+    pub fn put_message(&self, queue_offset: i64, keys: CheetahString, data: &Bytes) -> bool {
+        let size = data.len() as u64;
+        if size > self.segment_size {
+            warn!(
+                "message of {}-{} at offset {} is larger than a compaction segment, size: {}",
+                self.topic, self.queue_id, queue_offset, size
+            );
+            return false;
+        }
+        let mut inner = self.inner.lock();
+        if queue_offset < inner.max_offset {
+            return true;
+        }
+        let need_new_segment = match inner.segments.last() {
+            None => true,
+            Some(segment) => segment.file.get_wrote_position() as u64 + size > self.segment_size,
+        };
+        if need_new_segment {
+            let file = Arc::new(DefaultMappedFile::new(
+                CheetahString::from(
+                    segment_path(&self.dir, queue_offset)
+                        .to_string_lossy()
+                        .as_ref(),
+                ),
+                self.segment_size,
+            ));
+            inner.segments.push(Segment {
+                base_offset: queue_offset,
+                file,
+            });
+        }
+        let file = inner.segments.last().unwrap().file.clone();
+        let pos = file.get_wrote_position();
+        if !file.append_message_bytes(data) {
+            return false;
+        }
+        inner.index.insert(
+            queue_offset,
+            IndexEntry {
+                file,
+                pos,
+                size: data.len() as i32,
+                keys,
+            },
+        );
+        inner.max_offset = queue_offset + 1;
+        true
+    }
+
+    pub fn get_min_offset(&self) -> i64 {
+        let inner = self.inner.lock();
+        inner
+            .index
+            .keys()
+            .next()
+            .copied()
+            .unwrap_or(inner.max_offset)
+    }
+
+    pub fn get_max_offset(&self) -> i64 {
+        self.inner.lock().max_offset
+    }
+
+    /// Reads up to `max_msg_nums` messages starting at the first one kept at or after `offset`.
+    pub fn get_message(
+        &self,
+        offset: i64,
+        max_msg_nums: i32,
+        max_total_msg_size: i32,
+    ) -> GetMessageResult {
+        let inner = self.inner.lock();
+        let mut result = GetMessageResult::new();
+        let min_offset = inner
+            .index
+            .keys()
+            .next()
+            .copied()
+            .unwrap_or(inner.max_offset);
+        let max_offset = inner.max_offset;
+        result.set_min_offset(min_offset);
+        result.set_max_offset(max_offset);
+
+        let (status, next_begin_offset) = if inner.index.is_empty() {
+            (GetMessageStatus::NoMessageInQueue, max_offset)
+        } else if offset < min_offset {
+            (GetMessageStatus::OffsetTooSmall, min_offset)
+        } else if offset == max_offset {
+            (GetMessageStatus::OffsetOverflowOne, offset)
+        } else if offset > max_offset {
+            (GetMessageStatus::OffsetOverflowBadly, max_offset)
+        } else {
+            let mut next_begin_offset = offset;
+            for (queue_offset, entry) in inner.index.range(offset..) {
+                if result.message_count() >= max_msg_nums
+                    || (result.message_count() > 0
+                        && result.buffer_total_size() + entry.size > max_total_msg_size)
+                {
+                    break;
+                }
+                match entry
+                    .file
+                    .clone()
+                    .select_mapped_buffer_size(entry.pos, entry.size)
+                {
+                    Some(select_result) => {
+                        result.add_message(select_result, *queue_offset as u64, 1);
+                        next_begin_offset = *queue_offset + 1;
+                    }
+                    None => break,
+                }
+            }
+            let status = if result.message_count() > 0 {
+                GetMessageStatus::Found
+            } else {
+                GetMessageStatus::NoMatchedMessage
+            };
+            (status, next_begin_offset)
+        };
+        result.set_status(Some(status));
+        result.set_next_begin_offset(next_begin_offset);
+        result
+    }
+
+    pub fn flush(&self) {
+        for segment in self.inner.lock().segments.iter() {
+            segment.file.flush(0);
+        }
+    }
+
+    /// Drops the messages of the sealed segments that a later message with the same keys
+    /// supersedes, then packs the survivors into new segments and swaps them in. The segment
+    /// being written to is left alone.
+    pub fn compact(&self) -> std::io::Result<CompactionStats> {
+        let mut inner = self.inner.lock();
+        if inner.segments.len() < 2 {
+            return Ok(CompactionStats::default());
+        }
+        let active_base = inner.segments.last().unwrap().base_offset;
+        let mut latest: HashMap<&CheetahString, i64> = HashMap::new();
+        for (queue_offset, entry) in inner.index.iter() {
+            latest.insert(&entry.keys, *queue_offset);
+        }
+        let survivors: Vec<(i64, IndexEntry)> = inner
+            .index
+            .range(..active_base)
+            .filter(|(queue_offset, entry)| latest.get(&entry.keys) == Some(*queue_offset))
+            .map(|(queue_offset, entry)| (*queue_offset, entry.clone()))
+            .collect();
+        drop(latest);
+        let sealed_count = inner.segments.len() - 1;
+        let sealed_messages = inner.index.range(..active_base).count();
+        let removed_messages = sealed_messages - survivors.len();
+        let live_bytes: u64 = survivors.iter().map(|(_, entry)| entry.size as u64).sum();
+        // nothing superseded and the survivors would not fit in fewer segments
+        if removed_messages == 0 && live_bytes.div_ceil(self.segment_size) as usize >= sealed_count
+        {
+            return Ok(CompactionStats::default());
+        }
+
+        let compacting_dir = self.dir.join(COMPACTING_DIR);
+        if compacting_dir.exists() {
+            fs::remove_dir_all(&compacting_dir)?;
+        }
+        fs::create_dir_all(&compacting_dir)?;
+        let mut new_segments: Vec<Segment> = Vec::new();
+        let mut new_entries = Vec::with_capacity(survivors.len());
+        for (queue_offset, entry) in survivors {
+            let Some(data) = entry
+                .file
+                .get_bytes(entry.pos as usize, entry.size as usize)
+            else {
+                return Err(std::io::Error::other(format!(
+                    "read message at offset {} failed",
+                    queue_offset
+                )));
+            };
+            let need_new_segment = match new_segments.last() {
+                None => true,
+                Some(segment) => {
+                    segment.file.get_wrote_position() as u64 + data.len() as u64 > self.segment_size
+                }
+            };
+            if need_new_segment {
+                let file = Arc::new(DefaultMappedFile::new(
+                    CheetahString::from(
+                        segment_path(&compacting_dir, queue_offset)
+                            .to_string_lossy()
+                            .as_ref(),
+                    ),
+                    self.segment_size,
+                ));
+                new_segments.push(Segment {
+                    base_offset: queue_offset,
+                    file,
+                });
+            }
+            let file = new_segments.last().unwrap().file.clone();
+            let pos = file.get_wrote_position();
+            file.append_message_bytes(&data);
+            new_entries.push((
+                queue_offset,
+                IndexEntry {
+                    file,
+                    pos,
+                    size: entry.size,
+                    keys: entry.keys,
+                },
+            ));
+        }
+        for segment in new_segments.iter() {
+            segment.file.flush(0);
+        }
+        fs::write(
+            compacting_dir.join(COMMIT_MARKER),
+            format!("{} {}", active_base, new_segments.len()),
+        )?;
+
+        // the old mappings stay valid for readers that still hold them once the files are gone
+        remove_segments_below(&self.dir, active_base)?;
+        move_compacted_segments(&self.dir)?;
+
+        let active = inner.segments.pop().unwrap();
+        inner.segments = new_segments;
+        inner.segments.push(active);
+        let kept_index = inner.index.split_off(&active_base);
+        inner.index = new_entries.into_iter().collect();
+        inner.index.extend(kept_index);
+        let stats = CompactionStats {
+            removed_messages,
+            removed_segments: sealed_count.saturating_sub(inner.segments.len() - 1),
+        };
+        info!(
+            "compact {}-{} done, removed messages: {}, removed segments: {}",
+            self.topic, self.queue_id, stats.removed_messages, stats.removed_segments
+        );
+        Ok(stats)
+    }
+
+    fn finish_interrupted_compaction(&self) -> std::io::Result<()> {
+        let compacting_dir = self.dir.join(COMPACTING_DIR);
+        if !compacting_dir.exists() {
+            return Ok(());
+        }
+        let marker = compacting_dir.join(COMMIT_MARKER);
+        let Ok(content) = fs::read_to_string(&marker) else {
+            // the new segments were not complete, the old ones are still all in place
+            return fs::remove_dir_all(&compacting_dir);
+        };
+        let mut parts = content.split_whitespace().map(str::parse::<i64>);
+        let (Some(Ok(active_base)), Some(Ok(segment_count))) = (parts.next(), parts.next()) else {
+            return fs::remove_dir_all(&compacting_dir);
+        };
+        // all compacted segments still waiting means the old ones may not be removed yet
+        if segment_bases(&compacting_dir)?.len() as i64 == segment_count {
+            remove_segments_below(&self.dir, active_base)?;
+        }
+        move_compacted_segments(&self.dir)
+    }
+}
+
+fn segment_path(dir: &Path, base_offset: i64) -> PathBuf {
+    dir.join(format!("{:020}", base_offset))
+}
+
+fn segment_bases(dir: &Path) -> std::io::Result<Vec<i64>> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut bases = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        if !entry.file_type()?.is_file() {
+            continue;
+        }
+        if let Ok(base_offset) = entry.file_name().to_string_lossy().parse::<i64>() {
+            bases.push(base_offset);
+        }
+    }
+    Ok(bases)
+}
+
+fn remove_segments_below(dir: &Path, active_base: i64) -> std::io::Result<()> {
+    for base_offset in segment_bases(dir)? {
+        if base_offset < active_base {
+            fs::remove_file(segment_path(dir, base_offset))?;
+        }
+    }
+    Ok(())
+}
+
+fn move_compacted_segments(dir: &Path) -> std::io::Result<()> {
+    let compacting_dir = dir.join(COMPACTING_DIR);
+    for base_offset in segment_bases(&compacting_dir)? {
+        fs::rename(
+            segment_path(&compacting_dir, base_offset),
+            segment_path(dir, base_offset),
+        )?;
+    }
+    fs::remove_dir_all(compacting_dir)
+}
+
+/// Size, queue offset and keys of the message stored at `pos`, `None` at the end of the data.
+fn read_record(
+    file: &DefaultMappedFile,
+    pos: i32,
+    segment_size: u64,
+) -> Option<(i32, i64, CheetahString)> {
+    if pos as u64 + 4 > segment_size {
+        return None;
+    }
+    let size = file.get_bytes(pos as usize, 4)?.get_i32();
+    if size <= 0 || pos as u64 + size as u64 > segment_size {
+        return None;
+    }
+    let mut record = file.get_bytes(pos as usize, size as usize)?;
+    let message = MessageDecoder::decode(&mut record, false, false, false, false, false)?;
+    Some((
+        size,
+        message.queue_offset(),
+        message.get_keys().unwrap_or_default(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use rocketmq_common::common::message::message_ext::MessageExt;
+    use rocketmq_common::common::message::MessageConst;
+    use tempfile::tempdir;
+
+    use super::*;
+
+    fn record(queue_offset: i64, keys: &str) -> Bytes {
+        let mut message = MessageExt::default();
+        message.set_topic(CheetahString::from_static_str("CompactTopic"));
+        message.set_queue_offset(queue_offset);
+        message.set_body(Bytes::from(format!("value-{}", queue_offset)));
+        message.put_property(
+            CheetahString::from_static_str(MessageConst::PROPERTY_KEYS),
+            CheetahString::from(keys),
+        );
+        MessageDecoder::encode(&message, false).unwrap()
+    }
+
+    fn put(log: &CompactionLog, queue_offset: i64, keys: &str) {
+        assert!(log.put_message(
+            queue_offset,
+            CheetahString::from(keys),
+            &record(queue_offset, keys)
+        ));
+    }
+
+    fn offsets(log: &CompactionLog, from: i64) -> Vec<u64> {
+        log.get_message(from, 100, i32::MAX)
+            .message_queue_offset()
+            .to_vec()
+    }
+
+    #[test]
+    fn compact_keeps_latest_message_per_key_and_offsets() {
+        let dir = tempdir().unwrap();
+        let segment_size = record(0, "k0").len() as u64 * 2;
+        let log = CompactionLog::new("CompactTopic".into(), 0, dir.path(), segment_size);
+        for (queue_offset, keys) in ["k0", "k1", "k1", "k2", "k0", "k3"].iter().enumerate() {
+            put(&log, queue_offset as i64, keys);
+        }
+        // [0, 1] and [2, 3] are sealed, [4, 5] is active
+        let stats = log.compact().unwrap();
+        assert_eq!(stats.removed_messages, 2);
+        assert_eq!(stats.removed_segments, 1);
+        assert_eq!(offsets(&log, 2), vec![2, 3, 4, 5]);
+        let result = log.get_message(0, 1, i32::MAX);
+        assert_eq!(result.status(), Some(GetMessageStatus::OffsetTooSmall));
+        assert_eq!(result.next_begin_offset(), 2);
+
+        put(&log, 6, "k2");
+        put(&log, 7, "k5");
+        let stats = log.compact().unwrap();
+        assert_eq!(stats.removed_messages, 1);
+        // an offset compacted away continues at the next kept message
+        let result = log.get_message(3, 1, i32::MAX);
+        assert_eq!(result.message_queue_offset(), &[4]);
+        assert_eq!(result.next_begin_offset(), 5);
+        assert_eq!(log.get_max_offset(), 8);
+
+        let reloaded = CompactionLog::new("CompactTopic".into(), 0, dir.path(), segment_size);
+        assert!(reloaded.load());
+        assert_eq!(offsets(&reloaded, 2), vec![2, 4, 5, 6, 7]);
+        put(&reloaded, 7, "k5");
+        put(&reloaded, 8, "k1");
+        assert_eq!(offsets(&reloaded, 2), vec![2, 4, 5, 6, 7, 8]);
+    }
+
+    #[test]
+    fn load_finishes_compaction_with_complete_segments() {
+        let dir = tempdir().unwrap();
+        let segment_size = record(0, "k0").len() as u64 * 2;
+        let log = CompactionLog::new("CompactTopic".into(), 0, dir.path(), segment_size);
+        for (queue_offset, keys) in ["k0", "k0", "k1"].iter().enumerate() {
+            put(&log, queue_offset as i64, keys);
+        }
+        log.flush();
+        let queue_dir = dir.path().join("CompactTopic").join("0");
+        let compacting_dir = queue_dir.join(COMPACTING_DIR);
+        fs::create_dir_all(&compacting_dir).unwrap();
+        fs::copy(
+            segment_path(&queue_dir, 0),
+            segment_path(&compacting_dir, 0),
+        )
+        .unwrap();
+        fs::write(compacting_dir.join(COMMIT_MARKER), "2 1").unwrap();
+
+        let reloaded = CompactionLog::new("CompactTopic".into(), 0, dir.path(), segment_size);
+        assert!(reloaded.load());
+        assert!(!compacting_dir.exists());
+        assert_eq!(offsets(&reloaded, 0), vec![0, 1, 2]);
+    }
+}
@@ -14,15 +14,106 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
-#[derive(Default, Clone)]
-pub struct CompactionService {}
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use cheetah_string::CheetahString;
+use parking_lot::Mutex;
+use tokio::sync::mpsc;
+use tracing::info;
+use tracing::warn;
+
+use crate::base::store_task_spawner::StoreTaskSpawner;
+use crate::kv::compaction_store::CompactionStore;
+use crate::log_file::commit_log::CommitLog;
+
+/// A message of a compacted topic the reput service dispatched.
+pub struct CompactionRequest {
+    pub topic: CheetahString,
+    pub queue_id: i32,
+    pub commit_log_offset: i64,
+    pub msg_size: i32,
+    pub queue_offset: i64,
+    pub keys: CheetahString,
+}
+
+/// Copies dispatched messages of compacted topics from the commit log into the compaction
+/// store and compacts it on a schedule.
+#[derive(Clone)]
+pub struct CompactionService {
+    compaction_store: Arc<CompactionStore>,
+    request_tx: mpsc::UnboundedSender<CompactionRequest>,
+    request_rx: Arc<Mutex<Option<mpsc::UnboundedReceiver<CompactionRequest>>>>,
+}
 
 impl CompactionService {
+    pub fn new(compaction_store: Arc<CompactionStore>) -> Self {
+        let (request_tx, request_rx) = mpsc::unbounded_channel();
+        Self {
+            compaction_store,
+            request_tx,
+            request_rx: Arc::new(Mutex::new(Some(request_rx))),
+        }
+    }
+
     pub fn load(&mut self, exit_ok: bool) -> bool {
-        println!(
-            "[unimplemented]load compaction service, exit ok: {}",
-            exit_ok
-        );
-        true
+        info!("load compaction service, exit ok: {}", exit_ok);
+        self.compaction_store.load()
+    }
+
+    pub fn put_request(&self, request: CompactionRequest) {
+        let _ = self.request_tx.send(request);
+    }
+
+    pub fn start(
+        &self,
+        commit_log: CommitLog,
+        compaction_schedule_interval_millis: u64,
+        task_spawner: &StoreTaskSpawner,
+    ) {
+        let Some(mut request_rx) = self.request_rx.lock().take() else {
+            return;
+        };
+        let compaction_store = self.compaction_store.clone();
+        task_spawner.spawn("compaction_dispatch", async move {
+            while let Some(request) = request_rx.recv().await {
+                let data = commit_log
+                    .get_message(request.commit_log_offset, request.msg_size)
+                    .and_then(|result| result.get_bytes());
+                let Some(data) = data else {
+                    warn!(
+                        "read message of compacted topic {} at commit log offset {} failed",
+                        request.topic, request.commit_log_offset
+                    );
+                    continue;
+                };
+                compaction_store.put_message(
+                    &request.topic,
+                    request.queue_id,
+                    request.queue_offset,
+                    request.keys,
+                    &data,
+                );
+            }
+        });
+
+        let compaction_store = self.compaction_store.clone();
+        task_spawner.spawn("compaction_schedule", async move {
+            let mut interval =
+                tokio::time::interval(Duration::from_millis(compaction_schedule_interval_millis));
+            interval.tick().await;
+            loop {
+                interval.tick().await;
+                let stats = compaction_store.compact_all();
+                compaction_store.flush();
+                if stats.removed_messages > 0 {
+                    info!(
+                        "compaction round done, removed messages: {}, removed segments: {}",
+                        stats.removed_messages, stats.removed_segments
+                    );
+                }
+            }
+        });
     }
 }
@@ -14,29 +14,165 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use bytes::Bytes;
 use cheetah_string::CheetahString;
+use parking_lot::RwLock;
+use tracing::error;
 
 use crate::base::get_message_result::GetMessageResult;
+use crate::config::message_store_config::MessageStoreConfig;
+use crate::kv::compaction_log::CompactionLog;
+use crate::kv::compaction_log::CompactionStats;
+use crate::store_path_config_helper::get_store_path_compaction_log;
 
-pub struct CompactionStore;
+/// Holds the compaction logs of all queues of the topics with the `compaction` cleanup policy
+/// and serves their reads.
+pub struct CompactionStore {
+    root_dir: PathBuf,
+    segment_size: u64,
+    compaction_log_table: RwLock<HashMap<CheetahString, HashMap<i32, Arc<CompactionLog>>>>,
+}
 
 impl CompactionStore {
-    pub fn new() -> Self {
-        CompactionStore
+    pub fn new(message_store_config: &MessageStoreConfig) -> Self {
+        CompactionStore {
+            root_dir: PathBuf::from(get_store_path_compaction_log(
+                message_store_config.store_path_root_dir.as_str(),
+            )),
+            segment_size: message_store_config.compaction_mapped_file_size as u64,
+            compaction_log_table: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Loads every `<topic>/<queueId>` log found under the compaction log dir.
+    pub fn load(&self) -> bool {
+        let Ok(topic_dirs) = fs::read_dir(&self.root_dir) else {
+            return true;
+        };
+        let mut table = self.compaction_log_table.write();
+        for topic_dir in topic_dirs.flatten() {
+            let topic = CheetahString::from(topic_dir.file_name().to_string_lossy().as_ref());
+            let Ok(queue_dirs) = fs::read_dir(topic_dir.path()) else {
+                continue;
+            };
+            for queue_dir in queue_dirs.flatten() {
+                let Ok(queue_id) = queue_dir.file_name().to_string_lossy().parse::<i32>() else {
+                    continue;
+                };
+                let log =
+                    CompactionLog::new(topic.clone(), queue_id, &self.root_dir, self.segment_size);
+                if !log.load() {
+                    return false;
+                }
+                table
+                    .entry(topic.clone())
+                    .or_default()
+                    .insert(queue_id, Arc::new(log));
+            }
+        }
+        true
+    }
+
+    fn find_or_create_log(&self, topic: &CheetahString, queue_id: i32) -> Arc<CompactionLog> {
+        if let Some(log) = self
+            .compaction_log_table
+            .read()
+            .get(topic)
+            .and_then(|logs| logs.get(&queue_id))
+        {
+            return log.clone();
+        }
+        self.compaction_log_table
+            .write()
+            .entry(topic.clone())
+            .or_default()
+            .entry(queue_id)
+            .or_insert_with(|| {
+                Arc::new(CompactionLog::new(
+                    topic.clone(),
+                    queue_id,
+                    &self.root_dir,
+                    self.segment_size,
+                ))
+            })
+            .clone()
+    }
+
+    fn find_log(&self, topic: &CheetahString, queue_id: i32) -> Option<Arc<CompactionLog>> {
+        self.compaction_log_table
+            .read()
+            .get(topic)
+            .and_then(|logs| logs.get(&queue_id))
+            .cloned()
+    }
+
+    pub fn put_message(
+        &self,
+        topic: &CheetahString,
+        queue_id: i32,
+        queue_offset: i64,
+        keys: CheetahString,
+        data: &Bytes,
+    ) -> bool {
+        self.find_or_create_log(topic, queue_id)
+            .put_message(queue_offset, keys, data)
     }
-}
 
-#[allow(unused_variables)]
-impl CompactionStore {
     pub fn get_message(
         &self,
-        group: &CheetahString,
+        _group: &CheetahString,
         topic: &CheetahString,
         queue_id: i32,
         offset: i64,
         max_msg_nums: i32,
         max_total_msg_size: i32,
     ) -> Option<GetMessageResult> {
-        None
+        self.find_log(topic, queue_id)
+            .map(|log| log.get_message(offset, max_msg_nums, max_total_msg_size))
+    }
+
+    pub fn get_min_offset_in_queue(&self, topic: &CheetahString, queue_id: i32) -> i64 {
+        self.find_log(topic, queue_id)
+            .map_or(0, |log| log.get_min_offset())
+    }
+
+    pub fn get_max_offset_in_queue(&self, topic: &CheetahString, queue_id: i32) -> i64 {
+        self.find_log(topic, queue_id)
+            .map_or(0, |log| log.get_max_offset())
+    }
+
+    /// Runs a compaction over every log, a failing log is logged and left as it was.
+    pub fn compact_all(&self) -> CompactionStats {
+        let logs: Vec<Arc<CompactionLog>> = self
+            .compaction_log_table
+            .read()
+            .values()
+            .flat_map(|logs| logs.values().cloned())
+            .collect();
+        let mut total = CompactionStats::default();
+        for log in logs {
+            match log.compact() {
+                Ok(stats) => {
+                    total.removed_messages += stats.removed_messages;
+                    total.removed_segments += stats.removed_segments;
+                }
+                Err(e) => error!("compact {}-{} failed: {}", log.topic(), log.queue_id(), e),
+            }
+        }
+        total
+    }
+
+    pub fn flush(&self) {
+        for logs in self.compaction_log_table.read().values() {
+            for log in logs.values() {
+                log.flush();
+            }
+        }
     }
 }
@@ -22,6 +22,7 @@ pub mod base;
 pub mod config;
 pub mod consume_queue;
 pub mod filter;
+pub mod ha;
 pub mod hook;
 mod index;
 mod kv;
@@ -30,9 +31,9 @@ pub(crate) mod message_encoder;
 pub mod message_store;
 pub mod pop;
 mod queue;
-pub(crate) mod services;
 pub mod stats;
 pub mod store;
 pub mod store_path_config_helper;
+pub mod tiered;
 pub mod timer;
 pub mod utils;
@@ -27,9 +27,12 @@ use rocketmq_common::TimeUtils::get_current_millis;
 
 use crate::base::dispatch_request::DispatchRequest;
 use crate::base::get_message_result::GetMessageResult;
+use crate::base::message_result::PutMessageFuture;
 use crate::base::message_result::PutMessageResult;
 use crate::base::query_message_result::QueryMessageResult;
 use crate::base::select_result::SelectMappedBufferResult;
+use crate::base::store_event_bus::StoreEventBus;
+use crate::base::store_read_executor::StoreReadExecutor;
 use crate::filter::MessageFilter;
 use crate::hook::put_message_hook::BoxedPutMessageHook;
 use crate::queue::ArcConsumeQueue;
@@ -63,6 +66,9 @@ pub trait RocketMQMessageStore: Sync + 'static {
     /// Shutdown the message store.
     fn shutdown(&mut self);
 
+    /// Destroy this message store. All persistent files are removed after invocation.
+    fn destroy(&mut self);
+
     /// Set the confirm offset.
     ///
     /// # Arguments
@@ -70,6 +76,12 @@ pub trait RocketMQMessageStore: Sync + 'static {
     /// * `phy_offset` - The physical offset to set as the confirm offset.
     fn set_confirm_offset(&mut self, phy_offset: i64);
 
+    /// Get the confirm offset a producer can rely on.
+    ///
+    /// This is the replicated confirm offset, additionally bounded by the flushed position when
+    /// the store uses synchronous flush.
+    fn get_confirm_offset(&self) -> i64;
+
     /// Get the maximum physical offset.
     ///
     /// # Returns
@@ -123,6 +135,30 @@ pub trait RocketMQMessageStore: Sync + 'static {
     /// A `PutMessageResult` indicating the result of the operation.
     async fn put_messages(&mut self, msg_batch: MessageExtBatch) -> PutMessageResult;
 
+    /// Store a message, returning as soon as it is appended to the commit log.
+    ///
+    /// # Arguments
+    ///
+    /// * `msg` - The message to store.
+    ///
+    /// # Returns
+    ///
+    /// A `PutMessageFuture` resolving to the `PutMessageResult` once the message is flushed
+    /// and replicated as the store requires. Awaiting it does not hold the store.
+    async fn async_put_message(&mut self, msg: MessageExtBrokerInner) -> PutMessageFuture;
+
+    /// Store a batch of messages, returning as soon as it is appended to the commit log.
+    ///
+    /// # Arguments
+    ///
+    /// * `msg_batch` - The batch of messages to store.
+    ///
+    /// # Returns
+    ///
+    /// A `PutMessageFuture` resolving to the `PutMessageResult` once the batch is flushed
+    /// and replicated as the store requires.
+    async fn async_put_messages(&mut self, msg_batch: MessageExtBatch) -> PutMessageFuture;
+
     /// Truncate files up to a specified offset.
     ///
     /// # Arguments
@@ -143,6 +179,24 @@ pub trait RocketMQMessageStore: Sync + 'static {
         false
     }
 
+    /// Get the dedicated runtime serving reads, if one is configured.
+    ///
+    /// # Returns
+    ///
+    /// `None` when reads run on the caller's runtime.
+    fn read_executor(&self) -> Option<Arc<StoreReadExecutor>> {
+        None
+    }
+
+    /// Get the bus store subsystems publish their events on.
+    ///
+    /// # Returns
+    ///
+    /// `None` if the store does not publish events.
+    fn event_bus(&self) -> Option<Arc<StoreEventBus>> {
+        None
+    }
+
     /// Get the running flags of the message store.
     ///
     /// # Returns
@@ -299,6 +353,24 @@ pub trait RocketMQMessageStore: Sync + 'static {
     /// The number of topics deleted.
     fn delete_topics(&mut self, delete_topics: Vec<&CheetahString>) -> i32;
 
+    /// Warms up the consume queue units from `offset` and the commit log pages of the next
+    /// `max_msg_nums` messages, so a following `get_message` does not stall on disk reads.
+    ///
+    /// # Returns
+    ///
+    /// The number of commit log pages touched.
+    fn prefetch_message(
+        &self,
+        topic: &CheetahString,
+        queue_id: i32,
+        offset: i64,
+        max_msg_nums: i32,
+    ) -> usize;
+
+    /// Makes the clean service delete expired commit log files on its next runs, regardless of
+    /// the `delete_when` window and the disk usage.
+    fn execute_delete_files_manually(&self);
+
     /// Query messages asynchronously.
     ///
     /// # Arguments
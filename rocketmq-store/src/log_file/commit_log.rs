@@ -15,12 +15,12 @@
  * limitations under the License.
  */
 
-#![allow(clippy::missing_const_for_thread_local)]
-use std::cell::RefCell;
 use std::collections::HashMap;
 use std::mem;
 use std::sync::atomic::AtomicU64;
 use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
 
 use bytes::Buf;
 use bytes::Bytes;
@@ -40,43 +40,65 @@ use rocketmq_common::common::system_clock::SystemClock;
 use rocketmq_common::utils::queue_type_utils::QueueTypeUtils;
 use rocketmq_common::utils::time_utils;
 use rocketmq_common::CRC32Utils::crc32;
+use rocketmq_common::MessageDecoder::parse_crc32;
 use rocketmq_common::MessageDecoder::string_to_message_properties;
+use rocketmq_common::MessageDecoder::MESSAGE_MAGIC_CODE;
 use rocketmq_common::MessageDecoder::MESSAGE_MAGIC_CODE_POSITION;
 use rocketmq_common::MessageDecoder::MESSAGE_MAGIC_CODE_V2;
 use rocketmq_common::MessageDecoder::SYSFLAG_POSITION;
 use rocketmq_common::TimeUtils::get_current_millis;
 use rocketmq_common::UtilAll::time_millis_to_human_string;
 use rocketmq_rust::ArcMut;
+use tokio::runtime::Handle;
 use tokio::time::Instant;
 use tracing::error;
 use tracing::info;
 use tracing::warn;
 
+use crate::base::allocate_mapped_file_service::AllocateMappedFileService;
 use crate::base::append_message_callback::DefaultAppendMessageCallback;
 use crate::base::commit_log_dispatcher::CommitLogDispatcher;
+use crate::base::decode_failure_stats::DecodeFailure;
+use crate::base::decode_failure_stats::DecodeFailureStats;
 use crate::base::dispatch_request::DispatchRequest;
 use crate::base::flush_manager::FlushManager;
 use crate::base::message_result::AppendMessageResult;
+use crate::base::message_result::PutMessageFuture;
 use crate::base::message_result::PutMessageResult;
 use crate::base::message_status_enum::AppendMessageStatus;
 use crate::base::message_status_enum::PutMessageStatus;
+use crate::base::put_message_context::PutMessageBufferPool;
 use crate::base::put_message_context::PutMessageContext;
+use crate::base::put_message_lock::PutMessageLock;
+use crate::base::recovery_progress::RecoveryCheckpoint;
+use crate::base::recovery_progress::RecoveryProgress;
 use crate::base::select_result::SelectMappedBufferResult;
 use crate::base::store_checkpoint::StoreCheckpoint;
+use crate::base::store_event_bus::StoreEvent;
+use crate::base::store_event_bus::StoreEventBus;
+use crate::base::store_task_spawner::StoreTaskSpawner;
 use crate::base::swappable::Swappable;
 use crate::base::topic_queue_lock::TopicQueueLock;
 use crate::config::broker_role::BrokerRole;
+use crate::config::flush_disk_type::FlushDiskType;
 use crate::config::message_store_config::MessageStoreConfig;
+use crate::config::topic_flush_policy::TopicFlushPolicy;
 use crate::consume_queue::mapped_file_queue::MappedFileQueue;
+use crate::ha::auto_switch_ha_service::AutoSwitchHAService;
+use crate::ha::default_ha_service::DefaultHAService;
 use crate::log_file::cold_data_check_service::ColdDataCheckService;
 use crate::log_file::flush_manager_impl::defalut_flush_manager::DefaultFlushManager;
+use crate::log_file::flush_manager_impl::flush_stall_detector::FlushStallDetector;
 use crate::log_file::mapped_file::default_mapped_file_impl::DefaultMappedFile;
+use crate::log_file::mapped_file::mapped_file_access_stats::MappedFileAccessInfo;
 use crate::log_file::mapped_file::MappedFile;
 use crate::message_encoder::message_ext_encoder::MessageExtEncoder;
 use crate::message_store::default_message_store::CommitLogDispatcherDefault;
 use crate::message_store::default_message_store::DefaultMessageStore;
 use crate::queue::local_file_consume_queue_store::ConsumeQueueStore;
 use crate::queue::ConsumeQueueStoreTrait;
+use crate::queue::FileQueueLifeCycle;
+use crate::store_path_config_helper::get_recovery_progress_path;
 
 // Message's MAGIC CODE daa320a7
 pub const MESSAGE_MAGIC_CODE: i32 = -626843481;
@@ -88,65 +110,33 @@ pub const BLANK_MAGIC_CODE: i32 = -875286124;
 // PROPERTY_SEPARATOR]
 pub const CRC32_RESERVED_LEN: i32 = (MessageConst::PROPERTY_CRC32.len() + 1 + 10 + 1) as i32;
 
-struct PutMessageThreadLocal {
-    encoder: RefCell<Option<MessageExtEncoder>>,
-    key: RefCell<String>,
-}
-
-thread_local! {
-    static PUT_MESSAGE_THREAD_LOCAL: PutMessageThreadLocal = const { PutMessageThreadLocal{
-        encoder: RefCell::new(None),
-        key: RefCell::new(String::new()),
-    } };
-}
+// How long shutdown waits for readers to release commit log files before dropping them
+const SHUTDOWN_MAPPED_FILE_INTERVAL_FORCIBLY: i64 = 1000 * 3;
+// size of the commit log windows read at once while recovering
+const RECOVERY_READ_AHEAD_SIZE: usize = 4 * 1024 * 1024;
 
 fn encode_message_ext(
     message_ext: &MessageExtBrokerInner,
-    message_store_config: &Arc<MessageStoreConfig>,
+    put_message_context: &mut PutMessageContext,
 ) -> (Option<PutMessageResult>, ArcMut<BytesMut>) {
-    PUT_MESSAGE_THREAD_LOCAL.with(|thread_local| {
-        if thread_local.encoder.borrow().is_none() {
-            let encoder = MessageExtEncoder::new(Arc::clone(message_store_config));
-            //*thread_local.encoder.borrow_mut() = Some(encoder);
-            thread_local.encoder.replace(Some(encoder));
-        }
-        let mut ref_mut = thread_local.encoder.borrow_mut();
-        let encoder = ref_mut.as_mut().unwrap();
-        let result = encoder.encode(message_ext);
-        let bytes_mut = encoder.byte_buf();
-        (result, bytes_mut)
-    })
+    let encoder = put_message_context
+        .get_buffers_mut()
+        .expect("put message context has no buffers")
+        .encoder_mut();
+    let result = encoder.encode(message_ext);
+    (result, encoder.byte_buf())
 }
 
 fn encode_message_ext_batch(
     message_ext_batch: &MessageExtBatch,
     put_message_context: &mut PutMessageContext,
-    message_store_config: &Arc<MessageStoreConfig>,
+    buffer_pool: &Arc<PutMessageBufferPool>,
 ) -> Option<BytesMut> {
-    PUT_MESSAGE_THREAD_LOCAL.with(|thread_local| {
-        if thread_local.encoder.borrow().is_none() {
-            let encoder = MessageExtEncoder::new(Arc::clone(message_store_config));
-            //*thread_local.encoder.borrow_mut() = Some(encoder);
-            thread_local.encoder.replace(Some(encoder));
-        }
-        thread_local
-            .encoder
-            .borrow_mut()
-            .as_mut()
-            .unwrap()
-            .encode_batch(message_ext_batch, put_message_context)
-    })
-}
-
-fn generate_key(msg: &MessageExtBrokerInner) -> String {
-    PUT_MESSAGE_THREAD_LOCAL.with(|thead_local| {
-        let mut topic_queue_key = thead_local.key.borrow_mut();
-        topic_queue_key.clear();
-        topic_queue_key.push_str(msg.topic());
-        topic_queue_key.push('-');
-        topic_queue_key.push_str(msg.queue_id().to_string().as_str());
-        topic_queue_key.to_string()
-    })
+    // The batch is copied out of the encoder, so the buffers can go back to the pool right away.
+    buffer_pool
+        .acquire()
+        .encoder_mut()
+        .encode_batch(message_ext_batch, put_message_context)
 }
 
 pub fn get_cq_type(
@@ -192,14 +182,23 @@ pub struct CommitLog {
     confirm_offset: i64,
     store_checkpoint: Arc<StoreCheckpoint>,
     append_message_callback: Arc<DefaultAppendMessageCallback>,
-    put_message_lock: Arc<tokio::sync::Mutex<()>>,
+    put_message_lock: Arc<PutMessageLock>,
     topic_queue_lock: Arc<TopicQueueLock>,
+    put_message_buffer_pool: Arc<PutMessageBufferPool>,
     topic_config_table: Arc<parking_lot::Mutex<HashMap<CheetahString, TopicConfig>>>,
     consume_queue_store: ConsumeQueueStore,
     flush_manager: Arc<tokio::sync::Mutex<DefaultFlushManager>>,
     //flush_manager: Arc<parking_lot::Mutex<DefaultFlushManager>>,
     begin_time_in_lock: Arc<AtomicU64>,
     cold_data_check_service: Arc<ColdDataCheckService>,
+    flush_stall_detector: Arc<FlushStallDetector>,
+    recovery_progress: Arc<RecoveryProgress>,
+    decode_failure_stats: Arc<DecodeFailureStats>,
+    task_spawner: StoreTaskSpawner,
+    ha_service: DefaultHAService,
+    /// Set in controller mode, shares its connections with `ha_service`.
+    auto_switch_ha_service: Option<AutoSwitchHAService>,
+    event_bus: Arc<StoreEventBus>,
 }
 
 impl CommitLog {
@@ -210,11 +209,32 @@ impl CommitLog {
         store_checkpoint: Arc<StoreCheckpoint>,
         topic_config_table: Arc<parking_lot::Mutex<HashMap<CheetahString, TopicConfig>>>,
         consume_queue_store: ConsumeQueueStore,
+        task_spawner: StoreTaskSpawner,
+        allocate_mapped_file_service: Arc<AllocateMappedFileService>,
+        event_bus: Arc<StoreEventBus>,
     ) -> Self {
         let enabled_append_prop_crc = message_store_config.enabled_append_prop_crc;
         let store_path = message_store_config.get_store_path_commit_log();
         let mapped_file_size = message_store_config.mapped_file_size_commit_log;
-        let mapped_file_queue = MappedFileQueue::new(store_path, mapped_file_size as u64, None);
+        let mut mapped_file_queue = MappedFileQueue::new(
+            store_path,
+            mapped_file_size as u64,
+            Some(allocate_mapped_file_service),
+        );
+        mapped_file_queue.set_write_without_mmap(message_store_config.write_without_mmap);
+        mapped_file_queue
+            .set_write_buffer_size(message_store_config.write_without_mmap_buffer_size);
+        let flush_stall_detector = Arc::new(FlushStallDetector::new(&message_store_config));
+        let auto_switch_ha_service = broker_config.enable_controller_mode.then(|| {
+            AutoSwitchHAService::new(
+                message_store_config.clone(),
+                broker_config.broker_identity.broker_id as i64,
+            )
+        });
+        let ha_service = match auto_switch_ha_service.as_ref() {
+            Some(auto_switch_ha_service) => auto_switch_ha_service.ha_service().clone(),
+            None => DefaultHAService::new(message_store_config.clone()),
+        };
         Self {
             mapped_file_queue: mapped_file_queue.clone(),
             message_store_config: message_store_config.clone(),
@@ -228,19 +248,33 @@ impl CommitLog {
                 message_store_config.clone(),
                 topic_config_table.clone(),
             )),
-            put_message_lock: Arc::new(Default::default()),
+            put_message_lock: Arc::new(PutMessageLock::new(
+                message_store_config.use_reentrant_lock_when_put_message,
+            )),
             topic_queue_lock: Arc::new(TopicQueueLock::new(
                 message_store_config.topic_queue_lock_num,
             )),
+            put_message_buffer_pool: Arc::new(PutMessageBufferPool::new(
+                message_store_config.clone(),
+            )),
             topic_config_table,
             consume_queue_store,
+            ha_service,
+            auto_switch_ha_service,
             flush_manager: Arc::new(tokio::sync::Mutex::new(DefaultFlushManager::new(
                 message_store_config,
                 mapped_file_queue,
                 store_checkpoint,
+                flush_stall_detector.clone(),
+                task_spawner.clone(),
             ))),
             begin_time_in_lock: Arc::new(AtomicU64::new(0)),
             cold_data_check_service: Arc::new(Default::default()),
+            flush_stall_detector,
+            recovery_progress: Arc::new(RecoveryProgress::default()),
+            decode_failure_stats: Arc::new(DecodeFailureStats::default()),
+            task_spawner,
+            event_bus,
         }
     }
 }
@@ -256,7 +290,7 @@ impl CommitLog {
 
     pub fn start(&mut self) {
         let flush_manager = self.flush_manager.clone();
-        tokio::spawn(async move {
+        self.task_spawner.spawn("flush_manager", async move {
             let flush_manager_weak = Arc::downgrade(&flush_manager);
             let mut guard = flush_manager.lock().await;
             if let Some(service) = guard.commit_real_time_service_mut() {
@@ -266,9 +300,70 @@ impl CommitLog {
         });
     }
 
-    pub fn shutdown(&mut self) {}
+    pub fn shutdown(&mut self) {
+        let flush_manager = self.flush_manager.clone();
+        let handle = Handle::current();
+        let _ = thread::spawn(move || {
+            handle.block_on(async move {
+                flush_manager.lock().await.shutdown();
+            });
+        })
+        .join();
+
+        // the services stop asynchronously, make sure nothing written is left behind
+        self.mapped_file_queue.commit(0);
+        self.mapped_file_queue.flush(0);
+        self.mapped_file_queue
+            .shutdown(SHUTDOWN_MAPPED_FILE_INTERVAL_FORCIBLY);
+        info!(
+            "commit log shutdown, flushed where {}",
+            self.mapped_file_queue.get_flushed_where()
+        );
+    }
+
+    /// Access statistics of every commit log file, oldest file first.
+    pub fn mapped_file_access_infos(&self) -> Vec<MappedFileAccessInfo> {
+        self.mapped_file_queue
+            .get_mapped_files()
+            .read()
+            .iter()
+            .map(|mapped_file| mapped_file.access_info())
+            .collect()
+    }
+
+    /// Full commit log files appending already moved past, oldest file first.
+    pub fn sealed_mapped_files(&self) -> Vec<Arc<DefaultMappedFile>> {
+        let mapped_files = self.mapped_file_queue.get_mapped_files();
+        let mapped_files = mapped_files.read();
+        let sealed = mapped_files.len().saturating_sub(1);
+        mapped_files
+            .iter()
+            .take(sealed)
+            .filter(|mapped_file| mapped_file.is_full())
+            .cloned()
+            .collect()
+    }
+
+    pub fn flush_stall_detector(&self) -> &Arc<FlushStallDetector> {
+        &self.flush_stall_detector
+    }
+
+    pub fn decode_failure_stats(&self) -> &Arc<DecodeFailureStats> {
+        &self.decode_failure_stats
+    }
+
+    pub fn recovery_progress(&self) -> &Arc<RecoveryProgress> {
+        &self.recovery_progress
+    }
 
-    pub fn destroy(&mut self) {}
+    pub fn destroy(&mut self) {
+        self.mapped_file_queue.destroy();
+        self.confirm_offset = -1;
+        info!(
+            "commit log destroyed, {}",
+            self.message_store_config.get_store_path_commit_log()
+        );
+    }
 
     pub fn get_message(&self, offset: i64, size: i32) -> Option<SelectMappedBufferResult> {
         let mapped_file_size = self.message_store_config.mapped_file_size_commit_log;
@@ -289,13 +384,48 @@ impl CommitLog {
         }
     }
 
+    /// Faults in the pages holding `[offset, offset + size)`, following the range into the
+    /// next files when it crosses a file boundary.
+    pub fn prefetch(&self, offset: i64, size: i64) -> usize {
+        let end = offset + size;
+        let mut offset = offset;
+        let mut pages = 0;
+        while offset < end {
+            let Some(mapped_file) = self
+                .mapped_file_queue
+                .find_mapped_file_by_offset(offset, false)
+            else {
+                break;
+            };
+            let file_from_offset = mapped_file.get_file_from_offset() as i64;
+            let file_end = file_from_offset + mapped_file.get_file_size() as i64;
+            let pos = offset - file_from_offset;
+            pages += mapped_file.prefetch(pos as usize, (end.min(file_end) - offset) as usize);
+            offset = file_end;
+        }
+        pages
+    }
+
     pub fn set_confirm_offset(&mut self, phy_offset: i64) {
         self.confirm_offset = phy_offset;
+        if let Some(auto_switch_ha_service) = self.auto_switch_ha_service.as_ref() {
+            auto_switch_ha_service.set_confirm_offset(phy_offset);
+        }
         self.store_checkpoint
             .set_confirm_phy_offset(phy_offset as u64);
     }
 
-    pub async fn put_messages(&mut self, mut msg_batch: MessageExtBatch) -> PutMessageResult {
+    pub async fn put_messages(&mut self, msg_batch: MessageExtBatch) -> PutMessageResult {
+        self.async_put_messages(msg_batch).await.await
+    }
+
+    pub async fn put_message(&mut self, msg: MessageExtBrokerInner) -> PutMessageResult {
+        self.async_put_message(msg).await.await
+    }
+
+    /// Appends the batch and returns once it is in the commit log, the returned future
+    /// resolves after the flush and replication the put requires.
+    pub async fn async_put_messages(&mut self, mut msg_batch: MessageExtBatch) -> PutMessageFuture {
         msg_batch
             .message_ext_broker_inner
             .message_ext_inner
@@ -303,7 +433,7 @@ impl CommitLog {
         let tran_type =
             MessageSysFlag::get_transaction_value(msg_batch.message_ext_broker_inner.sys_flag());
         if MessageSysFlag::TRANSACTION_NOT_TYPE != tran_type {
-            return PutMessageResult::new_default(PutMessageStatus::MessageIllegal);
+            return PutMessageResult::new_default(PutMessageStatus::MessageIllegal).into();
         }
         if msg_batch
             .message_ext_broker_inner
@@ -312,7 +442,7 @@ impl CommitLog {
             .get_delay_time_level()
             > 0
         {
-            return PutMessageResult::new_default(PutMessageStatus::MessageIllegal);
+            return PutMessageResult::new_default(PutMessageStatus::MessageIllegal).into();
         }
 
         //setting ip type:IPV4 OR IPV6, default is ipv4
@@ -333,10 +463,19 @@ impl CommitLog {
         } else {
             0
         };
-        let need_ack_nums = self.message_store_config.in_sync_replicas;
-        let need_handle_ha = self.need_handle_ha(&msg_batch.message_ext_broker_inner);
+        let flush_policy = self.topic_flush_policy(&msg_batch.message_ext_broker_inner);
+        let mut need_ack_nums =
+            flush_policy.in_sync_replicas_or(self.message_store_config.in_sync_replicas);
+        let need_handle_ha =
+            self.need_handle_ha(&msg_batch.message_ext_broker_inner, &flush_policy);
         if need_handle_ha && self.broker_config.enable_controller_mode {
-            unimplemented!("controller mode not support HA")
+            match self.controller_mode_need_ack_nums(need_ack_nums) {
+                Some(nums) => need_ack_nums = nums,
+                None => {
+                    return PutMessageResult::new_default(PutMessageStatus::InSyncReplicasNotEnough)
+                        .into()
+                }
+            }
         } else if need_handle_ha && self.broker_config.enable_slave_acting_master {
             unimplemented!("slave acting master not support HA")
         }
@@ -352,15 +491,14 @@ impl CommitLog {
         let encoded_buff = encode_message_ext_batch(
             &msg_batch,
             &mut put_message_context,
-            &self.message_store_config,
+            &self.put_message_buffer_pool,
         );
 
-        let topic_queue_key = generate_key(&msg_batch.message_ext_broker_inner);
-        put_message_context.set_topic_queue_table_key(topic_queue_key.clone());
+        put_message_context.set_topic_queue_table_key_of(&msg_batch.message_ext_broker_inner);
         msg_batch.encoded_buff = encoded_buff;
         let topic_queue_lock = self
             .topic_queue_lock
-            .lock(topic_queue_key.as_str())
+            .lock(put_message_context.get_topic_queue_table_key())
             .lock()
             .await;
         self.assign_offset(&mut msg_batch.message_ext_broker_inner);
@@ -392,7 +530,7 @@ impl CommitLog {
             );
             self.begin_time_in_lock
                 .store(0, std::sync::atomic::Ordering::Release);
-            return PutMessageResult::new_default(PutMessageStatus::CreateMappedFileFailed);
+            return PutMessageResult::new_default(PutMessageStatus::CreateMappedFileFailed).into();
         }
 
         let result = mapped_file.as_ref().unwrap().append_messages(
@@ -423,8 +561,10 @@ impl CommitLog {
                     return PutMessageResult::new_append_result(
                         PutMessageStatus::CreateMappedFileFailed,
                         Some(result),
-                    );
+                    )
+                    .into();
                 }
+                self.publish_rolled(mapped_file.as_ref().unwrap());
                 let result = mapped_file.as_ref().unwrap().append_messages(
                     &mut msg_batch,
                     self.append_message_callback.as_ref(),
@@ -477,14 +617,19 @@ impl CommitLog {
                 msg_batch.message_ext_broker_inner,
                 need_ack_nums,
                 need_handle_ha,
+                flush_policy.flush_disk_type_or(self.message_store_config.flush_disk_type),
             )
-            .await
         } else {
-            put_message_result
+            put_message_result.into()
         }
     }
 
-    pub async fn put_message(&mut self, mut msg: MessageExtBrokerInner) -> PutMessageResult {
+    /// Appends the message and returns once it is in the commit log, the returned future
+    /// resolves after the flush and replication the put requires.
+    ///
+    /// Only the append runs under the put lock, flushing and waiting for slaves happen in
+    /// tasks started here, so callers can drop their hold on the store before awaiting.
+    pub async fn async_put_message(&mut self, mut msg: MessageExtBrokerInner) -> PutMessageFuture {
         // Set the storage time
         if !self.message_store_config.duplication_enable {
             msg.message_ext_inner.store_timestamp = time_utils::get_current_millis() as i64;
@@ -523,7 +668,10 @@ impl CommitLog {
             msg.with_store_host_v6_flag();
         }
 
-        let topic_queue_key = generate_key(&msg);
+        // Held until the message is appended, the encoded bytes live in these buffers.
+        let mut put_message_context =
+            PutMessageContext::with_buffers(self.put_message_buffer_pool.acquire());
+        put_message_context.set_topic_queue_table_key_of(&msg);
 
         let mut _unlock_mapped_file = None;
 
@@ -535,10 +683,18 @@ impl CommitLog {
         } else {
             0
         };
-        let need_ack_nums = self.message_store_config.in_sync_replicas;
-        let need_handle_ha = self.need_handle_ha(&msg);
+        let flush_policy = self.topic_flush_policy(&msg);
+        let mut need_ack_nums =
+            flush_policy.in_sync_replicas_or(self.message_store_config.in_sync_replicas);
+        let need_handle_ha = self.need_handle_ha(&msg, &flush_policy);
         if need_handle_ha && self.broker_config.enable_controller_mode {
-            unimplemented!("controller mode not support HA")
+            match self.controller_mode_need_ack_nums(need_ack_nums) {
+                Some(nums) => need_ack_nums = nums,
+                None => {
+                    return PutMessageResult::new_default(PutMessageStatus::InSyncReplicasNotEnough)
+                        .into()
+                }
+            }
         } else if need_handle_ha && self.broker_config.enable_slave_acting_master {
             unimplemented!("slave acting master not support HA")
         }
@@ -548,20 +704,18 @@ impl CommitLog {
 
         let topic_queue_lock = self
             .topic_queue_lock
-            .lock(topic_queue_key.as_str())
+            .lock(put_message_context.get_topic_queue_table_key())
             .lock()
             .await;
         if need_assign_offset {
             self.assign_offset(&mut msg);
         }
 
-        let (put_message_result, encoded_buff) =
-            encode_message_ext(&msg, &self.message_store_config);
+        let (put_message_result, encoded_buff) = encode_message_ext(&msg, &mut put_message_context);
         if let Some(result) = put_message_result {
-            return result;
+            return result.into();
         }
         msg.encoded_buff = Some(encoded_buff);
-        let put_message_context = PutMessageContext::new(topic_queue_key);
         let lock = self.put_message_lock.lock().await;
         let begin_lock_timestamp = time_utils::get_current_millis();
         self.begin_time_in_lock
@@ -588,7 +742,7 @@ impl CommitLog {
             );
             self.begin_time_in_lock
                 .store(0, std::sync::atomic::Ordering::Release);
-            return PutMessageResult::new_default(PutMessageStatus::CreateMappedFileFailed);
+            return PutMessageResult::new_default(PutMessageStatus::CreateMappedFileFailed).into();
         }
 
         let result = mapped_file.as_ref().unwrap().append_message(
@@ -618,8 +772,10 @@ impl CommitLog {
                     return PutMessageResult::new_append_result(
                         PutMessageStatus::CreateMappedFileFailed,
                         Some(result),
-                    );
+                    )
+                    .into();
                 }
+                self.publish_rolled(mapped_file.as_ref().unwrap());
                 let result = mapped_file.as_ref().unwrap().append_message(
                     &mut msg,
                     self.append_message_callback.as_ref(),
@@ -664,10 +820,15 @@ impl CommitLog {
             let message_num = get_message_num(&self.topic_config_table, &msg);
             self.increase_offset(&msg, message_num);
             drop(topic_queue_lock);
-            self.handle_disk_flush_and_ha(put_message_result, msg, need_ack_nums, need_handle_ha)
-                .await
+            self.handle_disk_flush_and_ha(
+                put_message_result,
+                msg,
+                need_ack_nums,
+                need_handle_ha,
+                flush_policy.flush_disk_type_or(self.message_store_config.flush_disk_type),
+            )
         } else {
-            put_message_result
+            put_message_result.into()
         }
     }
 
@@ -692,25 +853,27 @@ impl CommitLog {
         }
     }
 
-    async fn handle_disk_flush_and_ha(
+    fn handle_disk_flush_and_ha(
         &mut self,
         mut put_message_result: PutMessageResult,
         msg: MessageExtBrokerInner,
         need_ack_nums: u32,
         need_handle_ha: bool,
-    ) -> PutMessageResult {
+        flush_disk_type: FlushDiskType,
+    ) -> PutMessageFuture {
+        self.ha_service.wakeup_all();
         let commit_log = Arc::new(self.clone());
         let commit_log_cloned = commit_log.clone();
         let put_message_result_clone =
             Arc::new(put_message_result.append_message_result().unwrap().clone());
         let put_message_result_cloned = put_message_result_clone.clone();
-        let disk_flush_handle = tokio::spawn(async move {
+        let disk_flush_handle = self.task_spawner.spawn("disk_flush", async move {
             commit_log
-                .handle_disk_flush(put_message_result_clone.as_ref(), &msg)
+                .handle_disk_flush(put_message_result_clone.as_ref(), &msg, flush_disk_type)
                 .await
         });
 
-        let replica_result_handle = tokio::spawn(async move {
+        let replica_result_handle = self.task_spawner.spawn("replica", async move {
             if need_handle_ha {
                 commit_log_cloned
                     .handle_ha(put_message_result_cloned.as_ref(), need_ack_nums)
@@ -720,21 +883,22 @@ impl CommitLog {
             }
         });
 
-        match disk_flush_handle.await {
-            Ok(status) => {
-                put_message_result.set_put_message_status(status);
-                if status == PutMessageStatus::PutOk {
-                    if let Ok(replica_status) = replica_result_handle.await {
-                        put_message_result.set_put_message_status(replica_status);
+        PutMessageFuture::new(async move {
+            match disk_flush_handle.await {
+                Ok(status) => {
+                    put_message_result.set_put_message_status(status);
+                    if status == PutMessageStatus::PutOk {
+                        if let Ok(replica_status) = replica_result_handle.await {
+                            put_message_result.set_put_message_status(replica_status);
+                        }
                     }
                 }
+                Err(_) => {
+                    put_message_result.set_put_message_status(PutMessageStatus::FlushDiskTimeout);
+                }
             }
-            Err(error) => {
-                put_message_result.set_put_message_status(PutMessageStatus::FlushDiskTimeout);
-            }
-        }
-
-        put_message_result
+            put_message_result
+        })
     }
 
     async fn handle_ha(
@@ -745,25 +909,84 @@ impl CommitLog {
         if need_ack_nums <= 1 {
             return PutMessageStatus::PutOk;
         }
+        let next_offset = put_message_result.wrote_offset + put_message_result.wrote_bytes as i64;
+        match self.auto_switch_ha_service.as_ref() {
+            Some(auto_switch_ha_service) => {
+                auto_switch_ha_service
+                    .wait_for_in_sync_replicas(next_offset, need_ack_nums)
+                    .await
+            }
+            None => {
+                self.ha_service
+                    .wait_for_replicas(next_offset, need_ack_nums)
+                    .await
+            }
+        }
+    }
 
-        //HA service to do unimplemented
-
-        PutMessageStatus::PutOk
+    /// Acks a controller mode put waits for, `None` when the sync state set is smaller than
+    /// `min_in_sync_replicas`.
+    fn controller_mode_need_ack_nums(&self, need_ack_nums: u32) -> Option<u32> {
+        let auto_switch_ha_service = self.auto_switch_ha_service.as_ref()?;
+        let in_sync_replicas = auto_switch_ha_service.in_sync_replicas_nums();
+        if (in_sync_replicas as usize) < self.message_store_config.min_in_sync_replicas {
+            return None;
+        }
+        if self.message_store_config.all_ack_in_sync_state_set {
+            return Some(in_sync_replicas);
+        }
+        if self.message_store_config.enable_auto_in_sync_replicas {
+            let min_in_sync_replicas = self.message_store_config.min_in_sync_replicas as u32;
+            return Some(
+                need_ack_nums
+                    .min(in_sync_replicas)
+                    .max(min_in_sync_replicas),
+            );
+        }
+        Some(need_ack_nums)
     }
 
     async fn handle_disk_flush(
         &self,
         put_message_result: &AppendMessageResult,
         msg: &MessageExtBrokerInner,
+        flush_disk_type: FlushDiskType,
     ) -> PutMessageStatus {
-        self.flush_manager
-            .lock()
-            .await
-            .handle_disk_flush(put_message_result, msg)
-            .await
+        // only submit under the lock, waiting for the flush must not block other producers
+        let flush_ok = self.flush_manager.lock().await.handle_disk_flush(
+            put_message_result,
+            msg,
+            flush_disk_type,
+        );
+        match tokio::time::timeout(
+            Duration::from_millis(self.message_store_config.sync_flush_timeout),
+            flush_ok,
+        )
+        .await
+        {
+            Ok(Ok(status)) => status,
+            _ => {
+                warn!(
+                    "do groupcommit, wait for flush failed, topic: {} tags: {:?} client address: \
+                     {}",
+                    msg.topic(),
+                    msg.get_tags(),
+                    msg.born_host()
+                );
+                PutMessageStatus::FlushDiskTimeout
+            }
+        }
+    }
+
+    fn topic_flush_policy(&self, msg_inner: &MessageExtBrokerInner) -> TopicFlushPolicy {
+        TopicFlushPolicy::from_topic_config(self.topic_config_table.lock().get(msg_inner.topic()))
     }
 
-    fn need_handle_ha(&self, msg_inner: &MessageExtBrokerInner) -> bool {
+    fn need_handle_ha(
+        &self,
+        msg_inner: &MessageExtBrokerInner,
+        flush_policy: &TopicFlushPolicy,
+    ) -> bool {
         if !msg_inner.is_wait_store_msg_ok() {
             /*
              No need to sync messages that special config to extra broker slaves.
@@ -775,8 +998,9 @@ impl CommitLog {
             return false;
         }
         if BrokerRole::SyncMaster != self.message_store_config.broker_role {
-            // No need to check ha in async or slave broker
-            return false;
+            // No need to check ha in async or slave broker, unless the topic asks for replicas
+            return BrokerRole::AsyncMaster == self.message_store_config.broker_role
+                && flush_policy.requires_replicas();
         }
 
         true
@@ -798,7 +1022,7 @@ impl CommitLog {
         msg_inner
             .property(MessageConst::PROPERTY_INNER_MULTI_DISPATCH)
             .is_some_and(|s| !s.is_empty())
-            && msg_inner
+            && !msg_inner
                 .topic()
                 .starts_with(mix_all::RETRY_GROUP_TOPIC_PREFIX)
     }
@@ -832,8 +1056,10 @@ impl CommitLog {
             // normal recover doesn't require dispatching
             let do_dispatch = false;
             let mut current_pos = 0usize;
+            let mut read_ahead = RecoveryReadAhead::default();
             loop {
-                let (msg, size) = self.get_simple_message_bytes(current_pos, mapped_file.as_ref());
+                let (msg, size) =
+                    read_ahead.get_simple_message_bytes(current_pos, mapped_file.as_ref());
                 if msg.is_none() {
                     break;
                 }
@@ -844,6 +1070,7 @@ impl CommitLog {
                     check_dup_info,
                     true,
                     &message_store_config,
+                    &self.decode_failure_stats,
                 );
                 current_pos += size;
                 if dispatch_request.success && dispatch_request.msg_size > 0 {
@@ -914,31 +1141,13 @@ impl CommitLog {
         }
     }
 
-    fn get_simple_message_bytes<MF: MappedFile>(
-        &self,
-        position: usize,
-        mapped_file: &MF,
-    ) -> (Option<Bytes>, usize) {
-        let mut bytes = mapped_file.get_bytes(position, 4);
-        match bytes {
-            None => (None, 0),
-            Some(ref mut inner) => {
-                let size = inner.get_i32();
-                if size <= 0 {
-                    return (None, 0);
-                }
-                (
-                    mapped_file.get_bytes(position, size as usize),
-                    size as usize,
-                )
-            }
-        }
-    }
-
     //Fetch and compute the newest confirmOffset.
     pub fn get_confirm_offset(&self) -> i64 {
-        if self.broker_config.enable_controller_mode {
-            unimplemented!()
+        if let Some(auto_switch_ha_service) = self.auto_switch_ha_service.as_ref() {
+            let confirm_offset = auto_switch_ha_service.confirm_offset();
+            if confirm_offset >= 0 {
+                return confirm_offset;
+            }
         } else if self.broker_config.duplication_enable {
             return self.confirm_offset;
         }
@@ -975,6 +1184,35 @@ impl CommitLog {
                 index = 0;
             }
             let mut index = index as usize;
+
+            // Skip the files an interrupted abnormal recovery has already verified
+            let progress_path =
+                get_recovery_progress_path(self.message_store_config.store_path_root_dir.as_str());
+            let mut resumed_from_offset = -1;
+            if let Some(checkpoint) = RecoveryCheckpoint::load(progress_path.as_str()) {
+                let verified = mapped_files_inner.iter().position(|mapped_file| {
+                    mapped_file.get_file_from_offset() as i64
+                        == checkpoint.last_verified_file_offset
+                });
+                if let Some(verified) = verified {
+                    let resume_index = (verified + 1).min(mapped_files_inner.len() - 1);
+                    if resume_index > index {
+                        info!(
+                            "resume abnormal recovery after verified file offset {}, skip {} files",
+                            checkpoint.last_verified_file_offset,
+                            resume_index - index
+                        );
+                        index = resume_index;
+                        resumed_from_offset = checkpoint.last_verified_file_offset;
+                    }
+                }
+            }
+            let recovery_progress = self.recovery_progress.clone();
+            recovery_progress.begin(
+                (mapped_files_inner.len() - index) as u64,
+                resumed_from_offset,
+            );
+
             //let mut mapped_file = mapped_files_inner.get(index).unwrap().lock().await;
             let mut mapped_file = mapped_files_inner.get(index).unwrap();
             let mut process_offset = mapped_file.get_file_from_offset();
@@ -986,8 +1224,10 @@ impl CommitLog {
             // normal recover doesn't require dispatching
             let do_dispatch = true;
             let mut current_pos = 0usize;
+            let mut read_ahead = RecoveryReadAhead::default();
             loop {
-                let (msg, size) = self.get_simple_message_bytes(current_pos, mapped_file.as_ref());
+                let (msg, size) =
+                    read_ahead.get_simple_message_bytes(current_pos, mapped_file.as_ref());
                 if msg.is_none() {
                     break;
                 }
@@ -998,6 +1238,7 @@ impl CommitLog {
                     check_dup_info,
                     true,
                     &self.message_store_config,
+                    &self.decode_failure_stats,
                 );
                 current_pos += size;
                 if dispatch_request.success && dispatch_request.msg_size > 0 {
@@ -1027,6 +1268,21 @@ impl CommitLog {
                     // return 0 representatives met last hole,
                     // this can not be included in truncate offset
                     self.on_commit_log_dispatch(&dispatch_request, do_dispatch, true, true);
+                    recovery_progress.update_offset((process_offset + mapped_file_offset) as i64);
+                    self.save_recovery_checkpoint(
+                        progress_path.as_str(),
+                        mapped_file.get_file_from_offset() as i64,
+                    );
+                    let snapshot = recovery_progress.on_file_done();
+                    info!(
+                        "recover progress: {}/{} files, current offset: {}, elapsed: {} ms, eta: \
+                         {} ms",
+                        snapshot.files_done,
+                        snapshot.total_files,
+                        snapshot.current_offset,
+                        snapshot.elapsed_ms,
+                        snapshot.eta_ms
+                    );
                     index += 1;
                     if index >= mapped_files_inner.len() {
                         info!(
@@ -1082,6 +1338,9 @@ impl CommitLog {
                 .set_committed_where(process_offset as i64);
             self.mapped_file_queue
                 .truncate_dirty_files(process_offset as i64);
+            recovery_progress.update_offset(process_offset as i64);
+            recovery_progress.finish();
+            RecoveryCheckpoint::clear(progress_path.as_str());
         } else {
             warn!(
                 "The commitlog files are deleted, and delete the consume queue
@@ -1094,10 +1353,140 @@ impl CommitLog {
         }
     }
 
+    /// Flushes the consume queues built so far and records `file_from_offset` as verified, so
+    /// an interrupted abnormal recovery can resume after it.
+    fn save_recovery_checkpoint(&self, progress_path: &str, file_from_offset: i64) {
+        self.flush_consume_queues();
+        RecoveryCheckpoint::save(progress_path, file_from_offset);
+    }
+
+    /// Flushes every consume queue regardless of the dirty page threshold, returns how many
+    /// queues were flushed.
+    pub fn flush_consume_queues(&self) -> usize {
+        let consume_queue_table = self.consume_queue_store.get_consume_queue_table();
+        let mut flushed = 0;
+        for queues in consume_queue_table.lock().values() {
+            for consume_queue in queues.values() {
+                consume_queue.flush(0);
+                flushed += 1;
+            }
+        }
+        flushed
+    }
+
+    /// Commits and flushes all pending commit log data and records the store timestamp of the
+    /// last flushed message in the checkpoint, returns the flushed offset.
+    pub fn flush_all(&self) -> i64 {
+        self.mapped_file_queue.commit(0);
+        self.mapped_file_queue.flush(0);
+        let store_timestamp = self.mapped_file_queue.get_store_timestamp();
+        if store_timestamp > 0 {
+            self.store_checkpoint
+                .set_physic_msg_timestamp(store_timestamp);
+        }
+        self.mapped_file_queue.get_flushed_where()
+    }
+
     pub fn get_max_offset(&self) -> i64 {
         self.mapped_file_queue.get_max_offset()
     }
 
+    /// Appends commit log data replicated from the master at `start_offset`, used by slaves.
+    pub async fn append_data(&mut self, start_offset: i64, data: &Bytes) -> bool {
+        let _lock = self.put_message_lock.lock().await;
+        let Some(mapped_file) = self
+            .mapped_file_queue
+            .get_last_mapped_file_mut_start_offset(start_offset as u64, true)
+        else {
+            error!(
+                "append data get last mapped file failed, offset {}",
+                start_offset
+            );
+            return false;
+        };
+        mapped_file.append_message_bytes(data)
+    }
+
+    pub fn ha_service(&self) -> &DefaultHAService {
+        &self.ha_service
+    }
+
+    pub fn auto_switch_ha_service(&self) -> Option<&AutoSwitchHAService> {
+        self.auto_switch_ha_service.as_ref()
+    }
+
+    /// Whether a message starts at `offset`.
+    pub fn is_offset_aligned(&self, offset: i64) -> bool {
+        let Some(result) = self.get_data(offset) else {
+            return false;
+        };
+        let buffer = result.get_buffer();
+        if buffer.len() < MESSAGE_MAGIC_CODE_POSITION + 4 {
+            return false;
+        }
+        let magic_code = (&buffer[MESSAGE_MAGIC_CODE_POSITION..]).get_i32();
+        magic_code == MESSAGE_MAGIC_CODE || magic_code == MESSAGE_MAGIC_CODE_V2
+    }
+
+    /// Drops the commit log data from `offset` on.
+    pub fn truncate_dirty_files(&mut self, offset: i64) {
+        self.mapped_file_queue.truncate_dirty_files(offset);
+        if self.confirm_offset > offset {
+            self.set_confirm_offset(offset);
+        } else if let Some(auto_switch_ha_service) = self.auto_switch_ha_service.as_ref() {
+            if auto_switch_ha_service.confirm_offset() > offset {
+                auto_switch_ha_service.set_confirm_offset(offset);
+            }
+        }
+    }
+
+    /// Drops the data from `offset` on together with the consume queue units pointing past it,
+    /// used by a slave whose log diverged from its master. `false` if `offset` is not at a
+    /// message boundary.
+    pub fn truncate_to(&mut self, offset: i64) -> bool {
+        if offset >= self.get_max_offset() {
+            return true;
+        }
+        if !self.is_offset_aligned(offset) {
+            error!("offset {} to truncate is not at a message boundary", offset);
+            return false;
+        }
+        self.consume_queue_store.truncate_dirty(offset);
+        self.truncate_dirty_files(offset);
+        true
+    }
+
+    pub async fn delete_expired_file(
+        &mut self,
+        expired_time: i64,
+        delete_files_interval: u64,
+        interval_forcibly: i64,
+        clean_immediately: bool,
+        delete_file_batch_max: usize,
+    ) -> usize {
+        self.mapped_file_queue
+            .delete_expired_file_by_time(
+                expired_time,
+                delete_files_interval,
+                interval_forcibly,
+                clean_immediately,
+                delete_file_batch_max,
+            )
+            .await
+    }
+
+    fn publish_rolled(&self, mapped_file: &DefaultMappedFile) {
+        self.event_bus.publish(StoreEvent::CommitLogRolled {
+            file_name: mapped_file.get_file_name().clone(),
+            file_from_offset: mapped_file.get_file_from_offset(),
+        });
+    }
+
+    pub fn retry_delete_first_file(&mut self, interval_forcibly: i64) -> bool {
+        self.mapped_file_queue
+            .retry_delete_first_file(interval_forcibly)
+    }
+
     pub fn get_min_offset(&self) -> i64 {
         match self.mapped_file_queue.get_first_mapped_file() {
             None => -1,
@@ -1111,6 +1500,35 @@ impl CommitLog {
         }
     }
 
+    /// Reads the store timestamp of the message at `offset` without decoding the whole message,
+    /// `-1` when the message is no longer (or not yet) in the commit log.
+    pub fn pickup_store_timestamp(&self, offset: i64, size: i32) -> i64 {
+        if size <= 0
+            || offset < self.get_min_offset()
+            || offset + size as i64 > self.get_max_offset()
+        {
+            return -1;
+        }
+        let Some(result) = self.get_message(offset, size) else {
+            return -1;
+        };
+        let buffer = result.get_buffer();
+        if buffer.len() < SYSFLAG_POSITION + 4 {
+            return -1;
+        }
+        let sys_flag = (&buffer[SYSFLAG_POSITION..]).get_i32();
+        let born_host_length = if sys_flag & MessageSysFlag::BORNHOST_V6_FLAG == 0 {
+            8
+        } else {
+            20
+        };
+        let store_timestamp_position = 4 + 4 + 4 + 4 + 4 + 8 + 8 + 4 + 8 + born_host_length;
+        if buffer.len() < store_timestamp_position + 8 {
+            return -1;
+        }
+        (&buffer[store_timestamp_position..]).get_i64()
+    }
+
     pub fn roll_next_file(&self, offset: i64) -> i64 {
         let mapped_file_size = self.message_store_config.mapped_file_size_commit_log as i64;
         offset + mapped_file_size - (offset % mapped_file_size)
@@ -1160,18 +1578,113 @@ impl CommitLog {
         self.mapped_file_queue.remain_how_many_data_to_commit()
     }
 
+    pub fn get_flushed_where(&self) -> i64 {
+        self.mapped_file_queue.get_flushed_where()
+    }
+
     pub fn remain_how_many_data_to_flush(&self) -> i64 {
         self.mapped_file_queue.remain_how_many_data_to_flush()
     }
 }
 
+/// Checks the CRC32 the append wrote into the last `CRC32_RESERVED_LEN` bytes of the properties
+/// against the rest of the record.
+fn verify_properties_crc(
+    message_bytes: &[u8],
+    total_size: i32,
+    properties_map: &HashMap<CheetahString, CheetahString>,
+) -> bool {
+    let Some(expected_crc) = properties_map
+        .get(MessageConst::PROPERTY_CRC32)
+        .and_then(|crc| parse_crc32(crc.as_str()))
+    else {
+        warn!("failed to check message CRC, not found CRC in properties");
+        return false;
+    };
+    let check_size = total_size - CRC32_RESERVED_LEN;
+    if check_size <= 0 || check_size as usize > message_bytes.len() {
+        return false;
+    }
+    let crc = crc32(&message_bytes[..check_size as usize]);
+    if crc != expected_crc {
+        warn!(
+            "failed to check message CRC, expected CRC={}, actual CRC={}",
+            expected_crc, crc
+        );
+        return false;
+    }
+    true
+}
+
+/// Serves recovery with the messages of a commit log file out of windows read ahead in large
+/// chunks, instead of two small reads per message.
+#[derive(Default)]
+struct RecoveryReadAhead {
+    file_from_offset: u64,
+    position: usize,
+    window: Bytes,
+}
+
+impl RecoveryReadAhead {
+    fn get_simple_message_bytes<MF: MappedFile>(
+        &mut self,
+        position: usize,
+        mapped_file: &MF,
+    ) -> (Option<Bytes>, usize) {
+        let mut bytes = self.get_bytes(position, 4, mapped_file);
+        match bytes {
+            None => (None, 0),
+            Some(ref mut inner) => {
+                let size = inner.get_i32();
+                if size <= 0 {
+                    return (None, 0);
+                }
+                (
+                    self.get_bytes(position, size as usize, mapped_file),
+                    size as usize,
+                )
+            }
+        }
+    }
+
+    fn get_bytes<MF: MappedFile>(
+        &mut self,
+        position: usize,
+        size: usize,
+        mapped_file: &MF,
+    ) -> Option<Bytes> {
+        let in_window = self.file_from_offset == mapped_file.get_file_from_offset()
+            && position >= self.position
+            && position + size <= self.position + self.window.len();
+        if !in_window {
+            let file_size = mapped_file.get_file_size() as usize;
+            if position + size > file_size {
+                return None;
+            }
+            let read_size = size.max(RECOVERY_READ_AHEAD_SIZE).min(file_size - position);
+            self.window = mapped_file.read_sequential(position, read_size)?;
+            self.file_from_offset = mapped_file.get_file_from_offset();
+            self.position = position;
+            if self.window.len() < size {
+                return None;
+            }
+        }
+        let start = position - self.position;
+        Some(self.window.slice(start..start + size))
+    }
+}
+
 pub fn check_message_and_return_size(
     bytes: &mut Bytes,
     check_crc: bool,
     check_dup_info: bool,
     read_body: bool,
     message_store_config: &Arc<MessageStoreConfig>,
+    decode_failure_stats: &DecodeFailureStats,
 ) -> DispatchRequest {
+    // the properties CRC covers the whole record, keep a view of it before decoding
+    let message_bytes =
+        (check_crc && message_store_config.force_verify_prop_crc).then(|| bytes.clone());
     let total_size = bytes.get_i32();
     let magic_code = bytes.get_i32();
     if magic_code == MESSAGE_MAGIC_CODE || magic_code == MESSAGE_MAGIC_CODE_V2 {
@@ -1186,6 +1699,7 @@ pub fn check_message_and_return_size(
             "found a illegal magic code 0x{}",
             format!("{:X}", magic_code),
         );
+        decode_failure_stats.record(DecodeFailure::IllegalMagicCode);
         return DispatchRequest {
             msg_size: -1,
             success: false,
@@ -1224,6 +1738,7 @@ pub fn check_message_and_return_size(
                 let crc = crc32(body.as_ref());
                 if crc != body_crc as u32 {
                     warn!("CRC check failed. bodyCRC={}, currentCRC={}", crc, body_crc);
+                    decode_failure_stats.record(DecodeFailure::BodyCrcMismatch);
                     return DispatchRequest {
                         msg_size: -1,
                         success: false,
@@ -1242,7 +1757,7 @@ pub fn check_message_and_return_size(
     let properties_length = bytes.get_i16();
     let (tags_code, keys, uniq_key, properties_map) = if properties_length > 0 {
         let properties = bytes.copy_to_bytes(properties_length as usize);
-        let properties_content = String::from_utf8_lossy(topic_bytes.as_ref()).to_string();
+        let properties_content = String::from_utf8_lossy(properties.as_ref()).to_string();
         //need to optimize
         let properties_map =
             string_to_message_properties(Some(&CheetahString::from_string(properties_content)));
@@ -1254,6 +1769,7 @@ pub fn check_message_and_return_size(
             let dup_info = properties_map.get(MessageConst::DUP_INFO).cloned();
             if dup_info.is_none() {
                 warn!("DupInfo in properties check failed. dupInfo=null");
+                decode_failure_stats.record(DecodeFailure::DupInfoInvalid);
                 return DispatchRequest {
                     msg_size: -1,
                     success: false,
@@ -1264,6 +1780,7 @@ pub fn check_message_and_return_size(
                 let vec = content.split('_').collect::<Vec<&str>>();
                 if vec.len() != 2 {
                     warn!("DupInfo in properties check failed. dupInfo={}", content);
+                    decode_failure_stats.record(DecodeFailure::DupInfoInvalid);
                     return DispatchRequest {
                         msg_size: -1,
                         success: false,
@@ -1287,11 +1804,6 @@ pub fn check_message_and_return_size(
         (0, CheetahString::new(), None, HashMap::new())
     };
 
-    if check_crc && !message_store_config.force_verify_prop_crc {
-        let _expected_crc = -1i32;
-        if !properties_map.is_empty() {}
-    }
-
     let read_length = MessageExtEncoder::cal_msg_length(
         message_version,
         sys_flag,
@@ -1306,12 +1818,25 @@ pub fn check_message_and_return_size(
              bodyLen={}, topicLen={}, propertiesLength={}",
             total_size, read_length, body_len, topic_len, properties_length
         );
+        decode_failure_stats.record(DecodeFailure::SizeMismatch);
         return DispatchRequest {
             msg_size: total_size,
             success: false,
             ..Default::default()
         };
     }
+
+    if let Some(message_bytes) = message_bytes {
+        if !verify_properties_crc(&message_bytes, total_size, &properties_map) {
+            decode_failure_stats.record(DecodeFailure::PropertiesCrcMismatch);
+            // the size is intact, the reput can step over the record
+            return DispatchRequest {
+                msg_size: total_size,
+                success: false,
+                ..Default::default()
+            };
+        }
+    }
     let mut dispatch_request = DispatchRequest {
         success: true,
         topic,
@@ -1411,14 +1936,19 @@ fn is_mapped_file_matched_recover(
 impl Swappable for CommitLog {
     fn swap_map(
         &self,
-        _reserve_num: i32,
-        _force_swap_interval_ms: i64,
-        _normal_swap_interval_ms: i64,
+        reserve_num: i32,
+        force_swap_interval_ms: i64,
+        normal_swap_interval_ms: i64,
     ) {
-        todo!()
+        self.mapped_file_queue.swap_map(
+            reserve_num,
+            force_swap_interval_ms,
+            normal_swap_interval_ms,
+        );
     }
 
-    fn clean_swapped_map(&self, _force_clean_swap_interval_ms: i64) {
-        todo!()
+    fn clean_swapped_map(&self, force_clean_swap_interval_ms: i64) {
+        self.mapped_file_queue
+            .clean_swapped_map(force_clean_swap_interval_ms);
     }
 }
@@ -15,4 +15,5 @@
  * limitations under the License.
  */
 pub mod defalut_flush_manager;
+pub mod flush_stall_detector;
 pub mod group_commit_request;
@@ -14,22 +14,28 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
 use std::sync::Arc;
 use std::sync::Weak;
 
 use rocketmq_common::common::message::message_ext_broker_inner::MessageExtBrokerInner;
 use rocketmq_common::TimeUtils::get_current_millis;
+use tokio::sync::oneshot;
 use tokio::sync::Mutex;
 use tokio::sync::Notify;
 use tokio::time;
+use tracing::info;
 
 use crate::base::flush_manager::FlushManager;
 use crate::base::message_result::AppendMessageResult;
 use crate::base::message_status_enum::PutMessageStatus;
 use crate::base::store_checkpoint::StoreCheckpoint;
+use crate::base::store_task_spawner::StoreTaskSpawner;
 use crate::config::flush_disk_type::FlushDiskType;
 use crate::config::message_store_config::MessageStoreConfig;
 use crate::consume_queue::mapped_file_queue::MappedFileQueue;
+use crate::log_file::flush_manager_impl::flush_stall_detector::FlushStallDetector;
 use crate::log_file::flush_manager_impl::group_commit_request::GroupCommitRequest;
 
 pub struct DefaultFlushManager {
@@ -38,6 +44,7 @@ pub struct DefaultFlushManager {
     commit_real_time_service: Option<CommitRealTimeService>,
     message_store_config: Arc<MessageStoreConfig>,
     mapped_file_queue: Option<MappedFileQueue>,
+    task_spawner: StoreTaskSpawner,
 }
 
 impl DefaultFlushManager {
@@ -45,24 +52,25 @@ impl DefaultFlushManager {
         message_store_config: Arc<MessageStoreConfig>,
         mapped_file_queue: MappedFileQueue,
         store_checkpoint: Arc<StoreCheckpoint>,
+        flush_stall_detector: Arc<FlushStallDetector>,
+        task_spawner: StoreTaskSpawner,
     ) -> Self {
         let (group_commit_service, flush_real_time_service) =
             match message_store_config.flush_disk_type {
                 FlushDiskType::SyncFlush => (
-                    Some(GroupCommitService {
-                        store_checkpoint: store_checkpoint.clone(),
-                        rx_out: None,
-                        tx_in: None,
-                    }),
+                    Some(GroupCommitService::new(
+                        store_checkpoint.clone(),
+                        flush_stall_detector.clone(),
+                    )),
                     None,
                 ),
                 FlushDiskType::AsyncFlush => (
                     None,
-                    Some(FlushRealTimeService {
-                        message_store_config: message_store_config.clone(),
-                        store_checkpoint: store_checkpoint.clone(),
-                        notified: Arc::new(Notify::new()),
-                    }),
+                    Some(FlushRealTimeService::new(
+                        message_store_config.clone(),
+                        store_checkpoint.clone(),
+                        flush_stall_detector.clone(),
+                    )),
                 ),
             };
 
@@ -72,6 +80,8 @@ impl DefaultFlushManager {
                 store_checkpoint,
                 notified: Arc::new(Default::default()),
                 flush_manager: None,
+                flush_stall_detector,
+                stopped: Arc::new(AtomicBool::new(false)),
             })
         } else {
             None
@@ -83,6 +93,7 @@ impl DefaultFlushManager {
             message_store_config,
             commit_real_time_service,
             mapped_file_queue: Some(mapped_file_queue),
+            task_spawner,
         }
     }
 }
@@ -95,20 +106,49 @@ impl DefaultFlushManager {
     pub(crate) fn commit_real_time_service_mut(&mut self) -> Option<&mut CommitRealTimeService> {
         self.commit_real_time_service.as_mut()
     }
+
+    /// Serves a sync flush request of a topic that overrides the flush type of an async flush
+    /// broker, which runs no group commit service.
+    fn flush_for_request(&self, mut request: GroupCommitRequest) {
+        let mapped_file_queue = self.mapped_file_queue.clone().unwrap();
+        let commit_first = self.message_store_config.transient_store_pool_enable;
+        self.task_spawner.spawn("topic_sync_flush", async move {
+            if commit_first {
+                // data still in the transient pool has to reach the file before it can be flushed
+                mapped_file_queue.commit(0);
+            }
+            // There may be a message in the next file, so a maximum of two times the flush
+            let mut flush_ok = mapped_file_queue.get_flushed_where() >= request.next_offset;
+            for _ in 0..2 {
+                if flush_ok {
+                    break;
+                }
+                mapped_file_queue.flush(0);
+                flush_ok = mapped_file_queue.get_flushed_where() >= request.next_offset;
+            }
+            request.wakeup_customer(if flush_ok {
+                PutMessageStatus::PutOk
+            } else {
+                PutMessageStatus::FlushDiskTimeout
+            });
+        });
+    }
 }
 
 impl FlushManager for DefaultFlushManager {
     fn start(&mut self) {
         if let Some(ref mut group_commit_service) = self.group_commit_service {
-            group_commit_service.start(self.mapped_file_queue.clone().unwrap());
+            group_commit_service.start(self.mapped_file_queue.clone().unwrap(), &self.task_spawner);
         }
         if let Some(ref mut flush_real_time_service) = self.flush_real_time_service {
-            flush_real_time_service.start(self.mapped_file_queue.clone().unwrap());
+            flush_real_time_service
+                .start(self.mapped_file_queue.clone().unwrap(), &self.task_spawner);
         }
 
         if self.message_store_config.transient_store_pool_enable {
             if let Some(ref mut commit_real_time_service) = self.commit_real_time_service {
-                commit_real_time_service.start(self.mapped_file_queue.clone().unwrap());
+                commit_real_time_service
+                    .start(self.mapped_file_queue.clone().unwrap(), &self.task_spawner);
             }
         }
     }
@@ -140,129 +180,206 @@ impl FlushManager for DefaultFlushManager {
         }
     }
 
-    async fn handle_disk_flush(
+    fn handle_disk_flush(
         &mut self,
         result: &AppendMessageResult,
         message_ext: &MessageExtBrokerInner,
-    ) -> PutMessageStatus {
-        match self.message_store_config.flush_disk_type {
-            FlushDiskType::SyncFlush => {
-                if message_ext.is_wait_store_msg_ok() {
-                    let commit_request = GroupCommitRequest::new(
-                        result.wrote_offset + result.wrote_bytes as i64,
-                        self.message_store_config.sync_flush_timeout,
-                    );
-
-                    time::timeout(
-                        time::Duration::from_millis(self.message_store_config.sync_flush_timeout),
-                        self.group_commit_service
-                            .as_mut()
-                            .unwrap()
-                            .put_request(commit_request),
-                    )
-                    .await
-                    .map_or(PutMessageStatus::FlushDiskTimeout, |request| {
-                        request.map_or(PutMessageStatus::FlushDiskTimeout, |request| {
-                            request
-                                .flush_ok
-                                .unwrap_or(PutMessageStatus::FlushDiskTimeout)
-                        })
-                    })
-                } else {
-                    self.group_commit_service.as_mut().unwrap().wakeup();
-                    PutMessageStatus::PutOk
+        flush_disk_type: FlushDiskType,
+    ) -> oneshot::Receiver<PutMessageStatus> {
+        match flush_disk_type {
+            FlushDiskType::SyncFlush if message_ext.is_wait_store_msg_ok() => {
+                let (request, flush_ok) = GroupCommitRequest::new(
+                    result.wrote_offset + result.wrote_bytes as i64,
+                    self.message_store_config.sync_flush_timeout,
+                );
+                match self.group_commit_service.as_mut() {
+                    Some(group_commit_service) => group_commit_service.put_request(request),
+                    None => self.flush_for_request(request),
                 }
+                return flush_ok;
+            }
+            FlushDiskType::SyncFlush => {
+                self.wake_up_flush();
             }
             FlushDiskType::AsyncFlush => {
                 if self.message_store_config.transient_store_pool_enable {
-                    self.commit_real_time_service.as_mut().unwrap().wakeup();
+                    self.wake_up_commit();
                 } else {
-                    self.flush_real_time_service.as_mut().unwrap().wakeup();
+                    self.wake_up_flush();
                 }
-                PutMessageStatus::PutOk
             }
         }
+        let (flush_ok_sender, flush_ok) = oneshot::channel();
+        let _ = flush_ok_sender.send(PutMessageStatus::PutOk);
+        flush_ok
     }
 }
 
+/// Serves synchronous flush: producers append [`GroupCommitRequest`]s to the write list, the
+/// service swaps it with the read list every 10ms or on wakeup and completes the whole batch
+/// after a single flush, so concurrent producers share one fsync.
 struct GroupCommitService {
     store_checkpoint: Arc<StoreCheckpoint>,
-    rx_out: Option<tokio::sync::mpsc::Receiver<GroupCommitRequest>>,
-    tx_in: Option<tokio::sync::mpsc::Sender<GroupCommitRequest>>,
+    flush_stall_detector: Arc<FlushStallDetector>,
+    requests_write: Arc<parking_lot::Mutex<Vec<GroupCommitRequest>>>,
+    notified: Arc<Notify>,
+    stopped: Arc<AtomicBool>,
 }
 
 impl GroupCommitService {
-    pub async fn put_request(&mut self, request: GroupCommitRequest) -> Option<GroupCommitRequest> {
-        if let Some(ref tx_in) = self.tx_in {
-            let _ = tx_in.send(request).await;
+    fn new(
+        store_checkpoint: Arc<StoreCheckpoint>,
+        flush_stall_detector: Arc<FlushStallDetector>,
+    ) -> Self {
+        GroupCommitService {
+            store_checkpoint,
+            flush_stall_detector,
+            requests_write: Arc::new(parking_lot::Mutex::new(Vec::new())),
+            notified: Arc::new(Notify::new()),
+            stopped: Arc::new(AtomicBool::new(false)),
         }
-        match self.rx_out {
-            None => None,
-            Some(ref mut rx_out) => rx_out.recv().await,
+    }
+
+    pub fn put_request(&mut self, mut request: GroupCommitRequest) {
+        if self.stopped.load(Ordering::Acquire) {
+            request.wakeup_customer(PutMessageStatus::FlushDiskTimeout);
+            return;
         }
+        self.requests_write.lock().push(request);
+        self.notified.notify_one();
     }
 
-    fn start(&mut self, mapped_file_queue: MappedFileQueue) {
-        let (tx_in, mut rx_in) = tokio::sync::mpsc::channel::<GroupCommitRequest>(1024);
-        self.tx_in = Some(tx_in);
-        let (tx_out, rx_out) = tokio::sync::mpsc::channel::<GroupCommitRequest>(1024);
-        self.rx_out = Some(rx_out);
-        tokio::spawn(async move {
-            loop {
-                match rx_in.recv().await {
-                    None => {}
-                    Some(mut request) => {
-                        let mut flush_ok =
-                            mapped_file_queue.get_flushed_where() >= request.next_offset;
-                        for i in 0..1000 {
-                            if flush_ok {
-                                break;
-                            }
-                            mapped_file_queue.flush(0);
-                            flush_ok = mapped_file_queue.get_flushed_where() >= request.next_offset;
-                            if flush_ok {
-                                break;
-                            }
-                            time::sleep(time::Duration::from_millis(1)).await;
-                        }
-                        request.flush_ok = Some(if flush_ok {
-                            PutMessageStatus::PutOk
-                        } else {
-                            PutMessageStatus::FlushDiskTimeout
-                        });
-                        let _ = tx_out.send(request).await;
-                    }
+    fn start(&mut self, mapped_file_queue: MappedFileQueue, task_spawner: &StoreTaskSpawner) {
+        let store_checkpoint = self.store_checkpoint.clone();
+        let flush_stall_detector = self.flush_stall_detector.clone();
+        let requests_write = self.requests_write.clone();
+        let notified = self.notified.clone();
+        let stopped = self.stopped.clone();
+        task_spawner.spawn("group_commit", async move {
+            info!("GroupCommitService service started");
+            while !stopped.load(Ordering::Acquire) {
+                tokio::select! {
+                    _ = notified.notified() => {}
+                    _ = time::sleep(time::Duration::from_millis(10)) => {}
                 }
+                let requests_read = std::mem::take(&mut *requests_write.lock());
+                Self::do_commit(
+                    requests_read,
+                    &mapped_file_queue,
+                    &store_checkpoint,
+                    &flush_stall_detector,
+                );
             }
+
+            // Under normal circumstances shutdown, wait for the arrival of the request, and then
+            // flush
+            time::sleep(time::Duration::from_millis(10)).await;
+            let requests_read = std::mem::take(&mut *requests_write.lock());
+            Self::do_commit(
+                requests_read,
+                &mapped_file_queue,
+                &store_checkpoint,
+                &flush_stall_detector,
+            );
+            info!("GroupCommitService service end");
         });
     }
 
-    pub fn wakeup(&mut self) {}
+    fn do_commit(
+        requests_read: Vec<GroupCommitRequest>,
+        mapped_file_queue: &MappedFileQueue,
+        store_checkpoint: &StoreCheckpoint,
+        flush_stall_detector: &FlushStallDetector,
+    ) {
+        let begin = get_current_millis();
+        let dirty_bytes = mapped_file_queue.remain_how_many_data_to_flush();
+        if requests_read.is_empty() {
+            // Because of individual messages is set to not sync flush, it will come to this
+            // process
+            mapped_file_queue.flush(0);
+        }
+        for mut request in requests_read {
+            // There may be a message in the next file, so a maximum of two times the flush
+            let mut flush_ok = mapped_file_queue.get_flushed_where() >= request.next_offset;
+            for _ in 0..1000 {
+                if flush_ok {
+                    break;
+                }
+                mapped_file_queue.flush(0);
+                flush_ok = mapped_file_queue.get_flushed_where() >= request.next_offset;
+            }
+            request.wakeup_customer(if flush_ok {
+                PutMessageStatus::PutOk
+            } else {
+                PutMessageStatus::FlushDiskTimeout
+            });
+        }
+        let store_timestamp = mapped_file_queue.get_store_timestamp();
+        if store_timestamp > 0 {
+            store_checkpoint.set_physic_msg_timestamp(store_timestamp);
+        }
+        // the detector is fed here so stalls of sync flush brokers are reported as well
+        flush_stall_detector.record(get_current_millis().saturating_sub(begin), dirty_bytes);
+    }
 
-    pub fn shutdown(&mut self) {}
+    pub fn wakeup(&mut self) {
+        self.notified.notify_one();
+    }
+
+    pub fn shutdown(&mut self) {
+        self.stopped.store(true, Ordering::Release);
+        self.notified.notify_one();
+    }
 }
 
+/// Serves asynchronous flush: wakes every `flush_interval_commit_log` ms (or on wakeup unless
+/// `flush_commit_log_timed` is set) and flushes once at least `flush_commit_log_least_pages` are
+/// dirty, falling back to a full flush every `flush_commit_log_thorough_interval` ms.
 struct FlushRealTimeService {
     message_store_config: Arc<MessageStoreConfig>,
     store_checkpoint: Arc<StoreCheckpoint>,
     notified: Arc<Notify>,
+    flush_stall_detector: Arc<FlushStallDetector>,
+    stopped: Arc<AtomicBool>,
 }
 
 impl FlushRealTimeService {
-    fn start(&mut self, mapped_file_queue: MappedFileQueue) {
+    /// Number of full flush attempts made while shutting down.
+    const RETRY_TIMES_OVER: usize = 10;
+
+    fn new(
+        message_store_config: Arc<MessageStoreConfig>,
+        store_checkpoint: Arc<StoreCheckpoint>,
+        flush_stall_detector: Arc<FlushStallDetector>,
+    ) -> Self {
+        FlushRealTimeService {
+            message_store_config,
+            store_checkpoint,
+            notified: Arc::new(Notify::new()),
+            flush_stall_detector,
+            stopped: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    fn start(&mut self, mapped_file_queue: MappedFileQueue, task_spawner: &StoreTaskSpawner) {
         let message_store_config = self.message_store_config.clone();
         let store_checkpoint = self.store_checkpoint.clone();
         let notified = self.notified.clone();
-        tokio::spawn(async move {
+        let flush_stall_detector = self.flush_stall_detector.clone();
+        let stopped = self.stopped.clone();
+        task_spawner.spawn("flush_real_time", async move {
+            info!("FlushRealTimeService service started");
             let mut last_flush_timestamp = 0;
-            loop {
+            let mut print_times = 0u64;
+            while !stopped.load(Ordering::Acquire) {
                 let flush_commit_log_timed = message_store_config.flush_commit_log_timed;
-                let interval = message_store_config.flush_interval_commit_log;
-                let mut flush_physic_queue_least_pages =
-                    message_store_config.flush_commit_log_least_pages;
+                let interval = flush_stall_detector
+                    .interval(message_store_config.flush_interval_commit_log.max(0) as u64);
+                let mut flush_physic_queue_least_pages = flush_stall_detector
+                    .least_pages(message_store_config.flush_commit_log_least_pages);
                 let flush_physic_queue_thorough_interval =
                     message_store_config.flush_commit_log_thorough_interval;
-                //let mut print_flush_progress = false;
+                let mut print_flush_progress = false;
 
                 let current_time_millis = get_current_millis();
                 if current_time_millis
@@ -270,33 +387,77 @@ impl FlushRealTimeService {
                 {
                     last_flush_timestamp = current_time_millis;
                     flush_physic_queue_least_pages = 0;
+                    print_flush_progress = print_times % 10 == 0;
+                    print_times += 1;
                 }
                 if flush_commit_log_timed {
-                    time::sleep(time::Duration::from_millis(interval as u64)).await;
+                    time::sleep(time::Duration::from_millis(interval)).await;
                 } else {
                     tokio::select! {
                         _ = notified.notified() => {}
-                        _ = tokio::time::sleep(std::time::Duration::from_millis(interval as u64)) => {}
+                        _ = time::sleep(time::Duration::from_millis(interval)) => {}
                     }
                 }
+                if print_flush_progress {
+                    info!(
+                        "how much disk fall behind memory, {}",
+                        mapped_file_queue.remain_how_many_data_to_flush()
+                    );
+                }
 
-                mapped_file_queue.flush(flush_physic_queue_least_pages);
-                let store_timestamp = mapped_file_queue.get_store_timestamp();
-                if store_timestamp > 0 {
-                    store_checkpoint.set_physic_msg_timestamp(store_timestamp);
+                Self::do_flush(
+                    &mapped_file_queue,
+                    flush_physic_queue_least_pages,
+                    &store_checkpoint,
+                    &flush_stall_detector,
+                );
+            }
+
+            // Normal shutdown, to ensure that all the flush before exit
+            let mut result = false;
+            for _ in 0..Self::RETRY_TIMES_OVER {
+                if result {
+                    break;
                 }
+                result = mapped_file_queue.flush(0);
+            }
+            let store_timestamp = mapped_file_queue.get_store_timestamp();
+            if store_timestamp > 0 {
+                store_checkpoint.set_physic_msg_timestamp(store_timestamp);
             }
+            info!(
+                "FlushRealTimeService service end, flushed where {}",
+                mapped_file_queue.get_flushed_where()
+            );
         });
     }
 
+    fn do_flush(
+        mapped_file_queue: &MappedFileQueue,
+        flush_least_pages: i32,
+        store_checkpoint: &StoreCheckpoint,
+        flush_stall_detector: &FlushStallDetector,
+    ) {
+        let dirty_bytes = mapped_file_queue.remain_how_many_data_to_flush();
+        let begin = get_current_millis();
+        mapped_file_queue.flush(flush_least_pages);
+        flush_stall_detector.record(get_current_millis().saturating_sub(begin), dirty_bytes);
+        let store_timestamp = mapped_file_queue.get_store_timestamp();
+        if store_timestamp > 0 {
+            store_checkpoint.set_physic_msg_timestamp(store_timestamp);
+        }
+    }
+
     pub fn wakeup(&mut self) {
         if !self.message_store_config.flush_commit_log_timed {
-            let notified = self.notified.clone();
-            tokio::spawn(async move { notified.notified().await });
+            self.notified.notify_one();
         }
     }
 
-    pub fn shutdown(&mut self) {}
+    pub fn shutdown(&mut self) {
+        self.stopped.store(true, Ordering::Release);
+        self.notified.notify_one();
+    }
 }
 
 pub(crate) struct CommitRealTimeService {
@@ -304,23 +465,31 @@ pub(crate) struct CommitRealTimeService {
     store_checkpoint: Arc<StoreCheckpoint>,
     notified: Arc<Notify>,
     flush_manager: Option<Weak<Mutex<DefaultFlushManager>>>,
+    flush_stall_detector: Arc<FlushStallDetector>,
+    stopped: Arc<AtomicBool>,
 }
 
 impl CommitRealTimeService {
+    /// Number of full commit attempts made while shutting down.
+    const RETRY_TIMES_OVER: usize = 10;
+
     pub fn wakeup(&mut self) {
-        let notified = self.notified.clone();
-        tokio::spawn(async move { notified.notified().await });
+        self.notified.notify_one();
     }
 
-    fn start(&mut self, mapped_file_queue: MappedFileQueue) {
+    fn start(&mut self, mapped_file_queue: MappedFileQueue, task_spawner: &StoreTaskSpawner) {
         let message_store_config = self.message_store_config.clone();
         let store_checkpoint = self.store_checkpoint.clone();
         let notified = self.notified.clone();
         let flush_manager = self.flush_manager.clone();
-        tokio::spawn(async move {
+        let flush_stall_detector = self.flush_stall_detector.clone();
+        let stopped = self.stopped.clone();
+        task_spawner.spawn("commit_real_time", async move {
             let mut last_commit_timestamp = 0;
-            loop {
-                let interval = message_store_config.commit_interval_commit_log;
+            while !stopped.load(Ordering::Acquire) {
+                // commits feed the flush, so they speed up together with it
+                let interval =
+                    flush_stall_detector.interval(message_store_config.commit_interval_commit_log);
                 let mut commit_data_least_pages =
                     message_store_config.commit_commit_log_least_pages;
                 let commit_data_thorough_interval =
@@ -346,12 +515,57 @@ impl CommitRealTimeService {
                     _ = tokio::time::sleep(std::time::Duration::from_millis(interval)) => {}
                 }
             }
+
+            let mut result = false;
+            for _ in 0..Self::RETRY_TIMES_OVER {
+                if result {
+                    break;
+                }
+                result = mapped_file_queue.commit(0);
+            }
+            info!("CommitRealTimeService end");
         });
     }
 
-    pub fn shutdown(&mut self) {}
+    pub fn shutdown(&mut self) {
+        self.stopped.store(true, Ordering::Release);
+        self.notified.notify_one();
+    }
 
     pub fn set_flush_manager(&mut self, flush_manager: Option<Weak<Mutex<DefaultFlushManager>>>) {
         self.flush_manager = flush_manager;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn group_commit_completes_every_request_of_a_batch() {
+        let dir = tempfile::tempdir().unwrap();
+        let mapped_file_queue = MappedFileQueue::new(
+            dir.path().join("commitlog").to_string_lossy().to_string(),
+            1024,
+            None,
+        );
+        let mut service = GroupCommitService::new(
+            Arc::new(StoreCheckpoint::new(dir.path().join("checkpoint")).unwrap()),
+            Arc::new(FlushStallDetector::new(&MessageStoreConfig::default())),
+        );
+        service.start(mapped_file_queue, &StoreTaskSpawner::default());
+
+        // nothing was written, so only the request up to offset 0 can ever be flushed
+        let (flushed, flushed_ok) = GroupCommitRequest::new(0, 1000);
+        let (beyond, beyond_ok) = GroupCommitRequest::new(512, 1000);
+        service.put_request(flushed);
+        service.put_request(beyond);
+        assert_eq!(flushed_ok.await.unwrap(), PutMessageStatus::PutOk);
+        assert_eq!(beyond_ok.await.unwrap(), PutMessageStatus::FlushDiskTimeout);
+
+        service.shutdown();
+        let (late, late_ok) = GroupCommitRequest::new(0, 1000);
+        service.put_request(late);
+        assert_eq!(late_ok.await.unwrap(), PutMessageStatus::FlushDiskTimeout);
+    }
+}
@@ -0,0 +1,203 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+
+use parking_lot::Mutex;
+use tracing::info;
+use tracing::warn;
+
+use crate::config::message_store_config::MessageStoreConfig;
+
+/// Flush behaviour selected by the [`FlushStallDetector`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FlushMode {
+    #[default]
+    Normal,
+    /// Flush latency or the dirty page backlog kept growing, flush more often and thoroughly
+    /// until it recovers.
+    Accelerated,
+}
+
+#[derive(Default)]
+struct DetectorState {
+    mode: FlushMode,
+    slow_times: u32,
+    fast_times: u32,
+}
+
+/// Watches commit log flush latency and the unflushed backlog to detect sustained fsync
+/// pressure. After `flush_stall_detect_times` consecutive slow flushes the flush services switch
+/// to [`FlushMode::Accelerated`], and switch back after as many consecutive fast ones.
+pub struct FlushStallDetector {
+    enable: bool,
+    latency_threshold_ms: u64,
+    dirty_bytes_threshold: i64,
+    detect_times: u32,
+    accelerate_ratio: i32,
+    state: Mutex<DetectorState>,
+    stalled: AtomicBool,
+    last_latency_ms: AtomicU64,
+    max_latency_ms: AtomicU64,
+    mode_switch_times: AtomicU64,
+}
+
+impl FlushStallDetector {
+    pub fn new(message_store_config: &MessageStoreConfig) -> Self {
+        Self {
+            enable: message_store_config.adaptive_flush_enable,
+            latency_threshold_ms: message_store_config.flush_stall_latency_threshold_ms,
+            dirty_bytes_threshold: message_store_config.flush_stall_dirty_bytes_threshold,
+            detect_times: message_store_config.flush_stall_detect_times.max(1),
+            accelerate_ratio: message_store_config.flush_stall_accelerate_ratio.max(1),
+            state: Mutex::new(DetectorState::default()),
+            stalled: AtomicBool::new(false),
+            last_latency_ms: AtomicU64::new(0),
+            max_latency_ms: AtomicU64::new(0),
+            mode_switch_times: AtomicU64::new(0),
+        }
+    }
+
+    /// Records one flush and returns the mode the flush services should use next.
+    pub fn record(&self, latency_ms: u64, dirty_bytes: i64) -> FlushMode {
+        self.last_latency_ms.store(latency_ms, Ordering::Relaxed);
+        self.max_latency_ms.fetch_max(latency_ms, Ordering::Relaxed);
+        if !self.enable {
+            return FlushMode::Normal;
+        }
+        let slow =
+            latency_ms > self.latency_threshold_ms || dirty_bytes > self.dirty_bytes_threshold;
+        let mut state = self.state.lock();
+        if slow {
+            state.slow_times += 1;
+            state.fast_times = 0;
+        } else {
+            state.fast_times += 1;
+            state.slow_times = 0;
+        }
+        match state.mode {
+            FlushMode::Normal if state.slow_times >= self.detect_times => {
+                state.mode = FlushMode::Accelerated;
+                self.stalled.store(true, Ordering::Release);
+                self.mode_switch_times.fetch_add(1, Ordering::Relaxed);
+                warn!(
+                    "commit log flush stall detected, latency: {}ms, dirty bytes: {}, switch to \
+                     accelerated flush",
+                    latency_ms, dirty_bytes
+                );
+            }
+            FlushMode::Accelerated if state.fast_times >= self.detect_times => {
+                state.mode = FlushMode::Normal;
+                self.stalled.store(false, Ordering::Release);
+                self.mode_switch_times.fetch_add(1, Ordering::Relaxed);
+                info!(
+                    "commit log flush recovered, latency: {}ms, dirty bytes: {}, switch back to \
+                     normal flush",
+                    latency_ms, dirty_bytes
+                );
+            }
+            _ => {}
+        }
+        state.mode
+    }
+
+    pub fn mode(&self) -> FlushMode {
+        if self.stalled.load(Ordering::Acquire) {
+            FlushMode::Accelerated
+        } else {
+            FlushMode::Normal
+        }
+    }
+
+    /// Interval between flushes (or commits) for the current mode.
+    pub fn interval(&self, interval: u64) -> u64 {
+        match self.mode() {
+            FlushMode::Normal => interval,
+            FlushMode::Accelerated => (interval / self.accelerate_ratio as u64).max(1),
+        }
+    }
+
+    /// Minimum dirty pages per flush for the current mode. Accelerated flushes write back
+    /// everything so the backlog cannot build up again.
+    pub fn least_pages(&self, least_pages: i32) -> i32 {
+        match self.mode() {
+            FlushMode::Normal => least_pages,
+            FlushMode::Accelerated => 0,
+        }
+    }
+
+    pub fn is_stalled(&self) -> bool {
+        self.stalled.load(Ordering::Acquire)
+    }
+
+    pub fn last_latency_ms(&self) -> u64 {
+        self.last_latency_ms.load(Ordering::Relaxed)
+    }
+
+    pub fn max_latency_ms(&self) -> u64 {
+        self.max_latency_ms.load(Ordering::Relaxed)
+    }
+
+    pub fn mode_switch_times(&self) -> u64 {
+        self.mode_switch_times.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detector() -> FlushStallDetector {
+        let config = MessageStoreConfig {
+            adaptive_flush_enable: true,
+            flush_stall_latency_threshold_ms: 100,
+            flush_stall_dirty_bytes_threshold: 1024,
+            flush_stall_detect_times: 2,
+            flush_stall_accelerate_ratio: 4,
+            ..MessageStoreConfig::default()
+        };
+        FlushStallDetector::new(&config)
+    }
+
+    #[test]
+    fn switches_mode_after_consecutive_samples() {
+        let detector = detector();
+        assert_eq!(detector.record(200, 0), FlushMode::Normal);
+        assert_eq!(detector.record(10, 0), FlushMode::Normal);
+        assert_eq!(detector.record(10, 4096), FlushMode::Normal);
+        assert_eq!(detector.record(300, 0), FlushMode::Accelerated);
+        assert!(detector.is_stalled());
+        assert_eq!(detector.interval(500), 125);
+        assert_eq!(detector.least_pages(4), 0);
+
+        assert_eq!(detector.record(10, 0), FlushMode::Accelerated);
+        assert_eq!(detector.record(10, 0), FlushMode::Normal);
+        assert_eq!(detector.interval(500), 500);
+        assert_eq!(detector.mode_switch_times(), 2);
+        assert_eq!(detector.max_latency_ms(), 300);
+    }
+
+    #[test]
+    fn disabled_detector_stays_normal() {
+        let detector = FlushStallDetector::new(&MessageStoreConfig::default());
+        for _ in 0..10 {
+            assert_eq!(detector.record(10_000, i64::MAX), FlushMode::Normal);
+        }
+        assert_eq!(detector.last_latency_ms(), 10_000);
+    }
+}
@@ -17,35 +17,64 @@
 use std::sync::atomic::AtomicI32;
 
 use rocketmq_common::TimeUtils::get_current_nano;
+use tokio::sync::oneshot;
 
 use crate::base::message_status_enum::PutMessageStatus;
 
 #[derive(Debug)]
 pub(crate) struct GroupCommitRequest {
     pub(crate) next_offset: i64,
-    pub(crate) flush_ok: Option<PutMessageStatus>,
     pub(crate) ack_nums: AtomicI32,
     pub(crate) dead_line: u64,
+    flush_ok_sender: Option<oneshot::Sender<PutMessageStatus>>,
 }
 
 impl Default for GroupCommitRequest {
     fn default() -> Self {
         Self {
             next_offset: 0,
-            flush_ok: None,
             ack_nums: AtomicI32::new(1),
             dead_line: 0,
+            flush_ok_sender: None,
         }
     }
 }
 
 impl GroupCommitRequest {
-    pub(crate) fn new(next_offset: i64, timeout_millis: u64) -> Self {
+    /// Creates a request together with the receiver completed once the data up to
+    /// `next_offset` is flushed.
+    pub(crate) fn new(
+        next_offset: i64,
+        timeout_millis: u64,
+    ) -> (Self, oneshot::Receiver<PutMessageStatus>) {
         let dead_line = get_current_nano() + timeout_millis * 1_000_000;
-        Self {
+        let (flush_ok_sender, flush_ok_receiver) = oneshot::channel();
+        let request = Self {
             next_offset,
             dead_line,
+            flush_ok_sender: Some(flush_ok_sender),
             ..Self::default()
+        };
+        (request, flush_ok_receiver)
+    }
+
+    pub(crate) fn wakeup_customer(&mut self, status: PutMessageStatus) {
+        if let Some(sender) = self.flush_ok_sender.take() {
+            // the producer may have timed out and dropped the receiver already
+            let _ = sender.send(status);
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn wakeup_customer_completes_receiver_once() {
+        let (mut request, flush_ok) = GroupCommitRequest::new(1024, 5000);
+        request.wakeup_customer(PutMessageStatus::FlushDiskTimeout);
+        request.wakeup_customer(PutMessageStatus::PutOk);
+        assert_eq!(flush_ok.await.unwrap(), PutMessageStatus::FlushDiskTimeout);
+    }
+}
@@ -32,6 +32,10 @@ use crate::base::select_result::SelectMappedBufferResult;
 use crate::config::flush_disk_type::FlushDiskType;
 
 pub mod default_mapped_file_impl;
+pub mod mapped_file_access_stats;
+pub mod positioned_io;
+#[cfg(all(target_os = "linux", feature = "io_uring"))]
+pub mod uring_io;
 
 pub trait MappedFile {
     /// Returns the file name of the mapped file.
@@ -159,6 +163,13 @@ pub trait MappedFile {
     /// requested slice goes beyond the file boundaries or the file is not available.
     fn get_bytes(&self, pos: usize, size: usize) -> Option<bytes::Bytes>;
 
+    /// Reads a large range meant to be consumed sequentially, as recovery does.
+    ///
+    /// Same contract as [`get_bytes`](Self::get_bytes), which it defaults to.
+    fn read_sequential(&self, pos: usize, size: usize) -> Option<bytes::Bytes> {
+        self.get_bytes(pos, size)
+    }
+
     /// Appends a byte array to the mapped file.
     ///
     /// This method appends a given byte array to the mapped file. It is a convenience method that
@@ -15,6 +15,7 @@
  * limitations under the License.
  */
 
+use std::fs;
 use std::fs::File;
 use std::fs::OpenOptions;
 use std::io::Write;
@@ -25,6 +26,7 @@ use std::sync::atomic::AtomicI32;
 use std::sync::atomic::AtomicI64;
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
+use std::time::Instant;
 
 use bytes::Bytes;
 use bytes::BytesMut;
@@ -32,6 +34,7 @@ use cheetah_string::CheetahString;
 use memmap2::MmapMut;
 use rocketmq_common::common::message::message_batch::MessageExtBatch;
 use rocketmq_common::common::message::message_ext_broker_inner::MessageExtBrokerInner;
+use rocketmq_common::TimeUtils::get_current_millis;
 use rocketmq_common::UtilAll::ensure_dir_ok;
 use rocketmq_rust::SyncUnsafeCellWrapper;
 use tracing::debug;
@@ -47,17 +50,29 @@ use crate::base::put_message_context::PutMessageContext;
 use crate::base::select_result::SelectMappedBufferResult;
 use crate::base::transient_store_pool::TransientStorePool;
 use crate::config::flush_disk_type::FlushDiskType;
+use crate::log_file::mapped_file::mapped_file_access_stats::MappedFileAccessInfo;
+use crate::log_file::mapped_file::mapped_file_access_stats::MappedFileAccessStats;
+use crate::log_file::mapped_file::positioned_io;
+#[cfg(all(target_os = "linux", feature = "io_uring"))]
+use crate::log_file::mapped_file::uring_io;
 use crate::log_file::mapped_file::MappedFile;
 
 pub const OS_PAGE_SIZE: u64 = 1024 * 4;
 
+/// A swapped out mapping is only unmapped this long after the swap, unless forced.
+const MIN_SWAP_CLEAN_GAP_MILLIS: i64 = 120 * 1000;
+
 static TOTAL_MAPPED_VIRTUAL_MEMORY: AtomicI64 = AtomicI64::new(0);
 static TOTAL_MAPPED_FILES: AtomicI32 = AtomicI32::new(0);
 
 pub struct DefaultMappedFile {
     reference_resource: ReferenceResource,
     file: File,
-    mmapped_file: SyncUnsafeCellWrapper<MmapMut>,
+    // `None` when the file is written and read with positioned I/O instead of being mapped
+    mmapped_file: SyncUnsafeCellWrapper<Option<MmapMut>>,
+    write_without_mmap: bool,
+    // appends gathered before a positioned write, only set without mmap
+    write_buffer: Option<parking_lot::Mutex<WriteBuffer>>,
     transient_store_pool: Option<TransientStorePool>,
     file_name: CheetahString,
     file_from_offset: u64,
@@ -69,10 +84,14 @@ pub struct DefaultMappedFile {
     store_timestamp: AtomicI64,
     first_create_in_queue: bool,
     last_flush_time: u64,
-    swap_map_time: u64,
+    swap_map_time: AtomicI64,
     mapped_byte_buffer_access_count_since_last_swap: AtomicI64,
+    // the mapping replaced by the last swap, kept until readers that may still point into it
+    // are gone
+    mmapped_file_wait_to_clean: parking_lot::Mutex<Option<MmapMut>>,
     start_timestamp: u64,
     stop_timestamp: u64,
+    access_stats: MappedFileAccessStats,
 }
 
 impl AsRef<DefaultMappedFile> for DefaultMappedFile {
@@ -101,6 +120,32 @@ impl Default for DefaultMappedFile {
 
 impl DefaultMappedFile {
     pub fn new(file_name: CheetahString, file_size: u64) -> Self {
+        Self::open(file_name, file_size, false)
+    }
+
+    /// Opens the file without mapping it, appends become `pwritev` calls and reads copy out of
+    /// the page cache with `pread`.
+    pub fn new_without_mmap(file_name: CheetahString, file_size: u64) -> Self {
+        Self::open(file_name, file_size, true)
+    }
+
+    /// Like [`new_without_mmap`](Self::new_without_mmap), with contiguous appends gathered in a
+    /// buffer of `write_buffer_size` bytes and written with one positioned write once it is
+    /// full, on flush or on shutdown. Reads see the buffered appends.
+    pub fn new_without_mmap_buffered(
+        file_name: CheetahString,
+        file_size: u64,
+        write_buffer_size: usize,
+    ) -> Self {
+        let mut mapped_file = Self::open(file_name, file_size, true);
+        if write_buffer_size > 0 {
+            mapped_file.write_buffer =
+                Some(parking_lot::Mutex::new(WriteBuffer::new(write_buffer_size)));
+        }
+        mapped_file
+    }
+
+    fn open(file_name: CheetahString, file_size: u64, write_without_mmap: bool) -> Self {
         let file_from_offset = Self::get_file_from_offset(&file_name);
         let path_buf = PathBuf::from(file_name.as_str());
         ensure_dir_ok(path_buf.parent().unwrap().to_str().unwrap());
@@ -113,7 +158,7 @@ impl DefaultMappedFile {
             .unwrap();
         file.set_len(file_size).unwrap();
 
-        let mmap = unsafe { MmapMut::map_mut(&file).unwrap() };
+        let mmap = (!write_without_mmap).then(|| unsafe { MmapMut::map_mut(&file).unwrap() });
         Self {
             reference_resource: ReferenceResource {
                 ref_count: AtomicI64::new(1),
@@ -123,6 +168,8 @@ impl DefaultMappedFile {
             },
             file,
             mmapped_file: SyncUnsafeCellWrapper::new(mmap),
+            write_without_mmap,
+            write_buffer: None,
             file_name,
             file_from_offset,
             mapped_byte_buffer: None,
@@ -133,11 +180,13 @@ impl DefaultMappedFile {
             store_timestamp: Default::default(),
             first_create_in_queue: false,
             last_flush_time: 0,
-            swap_map_time: 0,
+            swap_map_time: AtomicI64::new(get_current_millis() as i64),
             mapped_byte_buffer_access_count_since_last_swap: Default::default(),
+            mmapped_file_wait_to_clean: parking_lot::Mutex::new(None),
             start_timestamp: 0,
             transient_store_pool: None,
             stop_timestamp: 0,
+            access_stats: MappedFileAccessStats::default(),
         }
     }
 
@@ -201,14 +250,36 @@ impl DefaultMappedFile {
             store_timestamp: Default::default(),
             first_create_in_queue: false,
             last_flush_time: 0,
-            swap_map_time: 0,
+            swap_map_time: AtomicI64::new(get_current_millis() as i64),
             mapped_byte_buffer_access_count_since_last_swap: Default::default(),
+            mmapped_file_wait_to_clean: parking_lot::Mutex::new(None),
             start_timestamp: 0,
             transient_store_pool: Some(transient_store_pool),
             stop_timestamp: 0,
-            mmapped_file: SyncUnsafeCellWrapper::new(mmap),
+            mmapped_file: SyncUnsafeCellWrapper::new(Some(mmap)),
+            write_without_mmap: false,
+            write_buffer: None,
+            access_stats: MappedFileAccessStats::default(),
         }
     }
+
+    pub fn is_write_without_mmap(&self) -> bool {
+        self.write_without_mmap
+    }
+
+    /// Whether the file has been shut down and every outstanding hold released.
+    pub fn is_cleanup_over(&self) -> bool {
+        self.reference_resource.is_cleanup_over()
+    }
+
+    pub fn access_stats(&self) -> &MappedFileAccessStats {
+        &self.access_stats
+    }
+
+    pub fn access_info(&self) -> MappedFileAccessInfo {
+        self.access_stats
+            .snapshot(&self.file_name, self.file_from_offset)
+    }
 }
 
 #[allow(unused_variables)]
@@ -311,22 +382,30 @@ impl MappedFile for DefaultMappedFile {
         if pos + size > self.file_size as usize {
             return None;
         }
-        Some(Bytes::copy_from_slice(
-            &self.get_mapped_file()[pos..pos + size],
-        ))
+        self.access_stats.record_read();
+        self.read_at(pos, size).ok()
+    }
+
+    fn read_sequential(&self, pos: usize, size: usize) -> Option<bytes::Bytes> {
+        #[cfg(all(target_os = "linux", feature = "io_uring"))]
+        if pos + size <= self.file_size as usize && uring_io::is_available() {
+            if let Ok(bytes) = uring_io::read_at(&self.file, pos as u64, size) {
+                self.access_stats.record_read();
+                return Some(bytes);
+            }
+        }
+        self.get_bytes(pos, size)
     }
 
     fn append_message_offset_length(&self, data: &Bytes, offset: usize, length: usize) -> bool {
         let current_pos = self.wrote_position.load(Ordering::Relaxed) as usize;
 
         if current_pos + length <= self.file_size as usize {
-            let mut mapped_file =
-                &mut self.get_mapped_file_mut()[current_pos..current_pos + length];
-
             if let Some(data_slice) = data.get(offset..offset + length) {
-                if mapped_file.write_all(data_slice).is_ok() {
+                if self.write_at(current_pos, data_slice).is_ok() {
                     self.wrote_position
                         .fetch_add(length as i32, Ordering::AcqRel);
+                    self.access_stats.record_write();
                     return true;
                 } else {
                     error!("append_message_offset_length write_all error");
@@ -342,11 +421,9 @@ impl MappedFile for DefaultMappedFile {
         let current_pos = self.wrote_position.load(Ordering::Relaxed) as usize;
 
         if current_pos + length <= self.file_size as usize {
-            let mut mapped_file =
-                &mut self.get_mapped_file_mut()[current_pos..current_pos + length];
-
             if let Some(data_slice) = data.get(offset..offset + length) {
-                if mapped_file.write_all(data_slice).is_ok() {
+                if self.write_at(current_pos, data_slice).is_ok() {
+                    self.access_stats.record_write();
                     return true;
                 } else {
                     error!("append_message_offset_length write_all error");
@@ -367,11 +444,9 @@ impl MappedFile for DefaultMappedFile {
         let current_pos = self.wrote_position.load(Ordering::Relaxed) as usize;
 
         if current_pos + length <= self.file_size as usize {
-            let mut mapped_file =
-                &mut self.get_mapped_file_mut()[current_pos..current_pos + length];
-
             if let Some(data_slice) = data.get(offset..offset + length) {
-                if mapped_file.write_all(data_slice).is_ok() {
+                if self.write_at(current_pos, data_slice).is_ok() {
+                    self.access_stats.record_write();
                     return true;
                 } else {
                     error!("append_message_offset_length write_all error");
@@ -385,15 +460,16 @@ impl MappedFile for DefaultMappedFile {
 
     fn write_bytes_segment(&self, data: &[u8], start: usize, offset: usize, length: usize) -> bool {
         if start + length <= self.file_size as usize {
-            let mut mapped_file = &mut self.get_mapped_file_mut()[start..start + length];
             if data.len() == length {
-                if mapped_file.write_all(data).is_ok() {
+                if self.write_at(start, data).is_ok() {
+                    self.access_stats.record_write();
                     return true;
                 } else {
                     error!("append_message_offset_length write_all error");
                 }
             } else if let Some(data_slice) = data.get(offset..offset + length) {
-                if mapped_file.write_all(data_slice).is_ok() {
+                if self.write_at(start, data_slice).is_ok() {
+                    self.access_stats.record_write();
                     return true;
                 } else {
                     error!("append_message_offset_length write_all error");
@@ -409,8 +485,8 @@ impl MappedFile for DefaultMappedFile {
         let length = data.len();
         let end_index = index + length;
         if length > 0 && end_index <= self.file_size as usize {
-            let mut mapped_file = &mut self.get_mapped_file_mut()[index..end_index];
-            if mapped_file.write_all(data).is_ok() {
+            if self.write_at(index, data).is_ok() {
+                self.access_stats.record_write();
                 return true;
             } else {
                 error!("append_message_offset_length write_all error");
@@ -427,7 +503,15 @@ impl MappedFile for DefaultMappedFile {
         if self.is_able_to_flush(flush_least_pages) {
             if self.reference_resource.hold() {
                 let value = self.get_read_position();
-                if self.transient_store_pool.is_none() {
+                self.write_out_buffer()
+                    .expect("Error occurred when writing buffered appends.");
+                if self.flush_with_io_uring() {
+                    // synced through io_uring
+                } else if self.write_without_mmap {
+                    self.file
+                        .sync_data()
+                        .expect("Error occurred when force data to disk.");
+                } else if self.transient_store_pool.is_none() {
                     self.get_mapped_file()
                         .flush()
                         .expect("Error occurred when force data to disk.");
@@ -461,11 +545,14 @@ impl MappedFile for DefaultMappedFile {
             if self.hold() {
                 self.mapped_byte_buffer_access_count_since_last_swap
                     .fetch_add(1, Ordering::SeqCst);
+                self.access_stats.record_read();
+                let cached_bytes = self.read_for_select(pos as usize, size as usize)?;
                 Some(SelectMappedBufferResult {
                     start_offset: self.file_from_offset + pos as u64,
                     size,
                     mapped_file: Some(self),
                     is_in_cache: true,
+                    cached_bytes,
                 })
             } else {
                 None
@@ -483,11 +570,17 @@ impl MappedFile for DefaultMappedFile {
     fn select_mapped_buffer(self: Arc<Self>, pos: i32) -> Option<SelectMappedBufferResult> {
         let read_position = self.get_read_position();
         if pos < read_position && read_position > 0 && self.hold() {
+            self.mapped_byte_buffer_access_count_since_last_swap
+                .fetch_add(1, Ordering::SeqCst);
+            self.access_stats.record_read();
+            let cached_bytes =
+                self.read_for_select(pos as usize, (read_position - pos) as usize)?;
             Some(SelectMappedBufferResult {
                 start_offset: self.get_file_from_offset() + pos as u64,
                 size: read_position - pos,
                 mapped_file: Some(self),
                 is_in_cache: true,
+                cached_bytes,
             })
         } else {
             None
@@ -507,7 +600,12 @@ impl MappedFile for DefaultMappedFile {
     }
 
     fn get_last_modified_timestamp(&self) -> i64 {
-        todo!()
+        self.file
+            .metadata()
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| modified.duration_since(std::time::UNIX_EPOCH).ok())
+            .map_or(0, |duration| duration.as_millis() as i64)
     }
 
     fn get_data(&self, pos: usize, size: usize) -> Option<bytes::Bytes> {
@@ -515,8 +613,10 @@ impl MappedFile for DefaultMappedFile {
         let read_end_position = pos + size;
         if read_end_position <= read_position as usize {
             if self.hold() {
-                let buffer = BytesMut::from(&self.get_mapped_file()[pos..read_end_position]);
-                Some(buffer.freeze())
+                self.mapped_byte_buffer_access_count_since_last_swap
+                    .fetch_add(1, Ordering::SeqCst);
+                self.access_stats.record_read();
+                self.read_at(pos, size).ok()
             } else {
                 debug!(
                     "matched, but hold failed, request pos: {}, fileFromOffset: {}",
@@ -535,11 +635,45 @@ impl MappedFile for DefaultMappedFile {
     }
 
     fn destroy(&self, interval_forcibly: i64) -> bool {
+        self.shutdown(interval_forcibly);
+        if !self.reference_resource.is_cleanup_over() {
+            warn!(
+                "destroy mapped file[REF:{}] {} Failed. cleanupOver: {}",
+                self.reference_resource.get_ref_count(),
+                self.file_name,
+                self.reference_resource.cleanup_over.load(Ordering::Relaxed)
+            );
+            return false;
+        }
+        self.munlock();
+        let begin_time = Instant::now();
+        match fs::remove_file(self.file_name.as_str()) {
+            Ok(_) => {
+                info!(
+                    "delete file[REF:{}] {} OK, W:{} M:{}, {} ms elapsed",
+                    self.reference_resource.get_ref_count(),
+                    self.file_name,
+                    self.get_wrote_position(),
+                    self.get_flushed_position(),
+                    begin_time.elapsed().as_millis()
+                );
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+            Err(err) => {
+                warn!("close file channel {} Failed. {:?}", self.file_name, err);
+            }
+        }
         true
     }
 
     fn shutdown(&self, interval_forcibly: i64) {
         if self.reference_resource.available.load(Ordering::Relaxed) {
+            if let Err(err) = self.write_out_buffer() {
+                error!(
+                    "write buffered appends of {} failed on shutdown: {}",
+                    self.file_name, err
+                );
+            }
             self.reference_resource
                 .available
                 .store(false, Ordering::Relaxed);
@@ -634,31 +768,117 @@ impl MappedFile for DefaultMappedFile {
     }
 
     fn mlock(&self) {
-        todo!()
+        let begin_time = Instant::now();
+        #[cfg(unix)]
+        if !self.write_without_mmap {
+            let mmap = self.get_mapped_file();
+            if let Err(err) = mmap.lock() {
+                warn!("mlock {} failed: {}", self.file_name, err);
+            }
+            if let Err(err) = mmap.advise(memmap2::Advice::WillNeed) {
+                warn!("madvise {} failed: {}", self.file_name, err);
+            }
+        }
+        info!(
+            "mlock {} {}, time consuming = {} ms",
+            self.file_name,
+            self.file_size,
+            begin_time.elapsed().as_millis()
+        );
     }
 
     fn munlock(&self) {
-        todo!()
+        #[cfg(unix)]
+        if !self.write_without_mmap {
+            if let Err(err) = self.get_mapped_file().unlock() {
+                warn!("munlock {} failed: {}", self.file_name, err);
+            }
+        }
     }
 
     fn warm_mapped_file(&self, flush_disk_type: FlushDiskType, pages: usize) {
-        todo!()
+        if self.write_without_mmap {
+            return;
+        }
+        let begin_time = Instant::now();
+        let page_size = OS_PAGE_SIZE as usize;
+        let mmap = self.get_mapped_file_mut();
+        let mut flush = 0usize;
+        for i in (0..self.file_size as usize).step_by(page_size) {
+            mmap[i] = 0;
+            // force flush when flush disk type is sync
+            if flush_disk_type == FlushDiskType::SyncFlush && (i - flush) / page_size >= pages {
+                flush = i;
+                if let Err(err) = mmap.flush_range(0, i) {
+                    warn!("warm mapped file {} flush failed: {}", self.file_name, err);
+                }
+            }
+        }
+        if flush_disk_type == FlushDiskType::SyncFlush {
+            if let Err(err) = mmap.flush() {
+                warn!("warm mapped file {} flush failed: {}", self.file_name, err);
+            }
+        }
+        info!(
+            "mapped file warm-up done. mappedFile={}, costTime={} ms",
+            self.file_name,
+            begin_time.elapsed().as_millis()
+        );
     }
 
     fn swap_map(&self) -> bool {
-        todo!()
+        if self.write_without_mmap
+            || self.reference_resource.get_ref_count() != 1
+            || self.mmapped_file_wait_to_clean.lock().is_some()
+        {
+            return false;
+        }
+        if !self.hold() {
+            return false;
+        }
+        let swapped = match unsafe { MmapMut::map_mut(&self.file) } {
+            Ok(mmap) => {
+                let old = std::mem::replace(self.get_mapped_file_mut(), mmap);
+                *self.mmapped_file_wait_to_clean.lock() = Some(old);
+                self.mapped_byte_buffer_access_count_since_last_swap
+                    .store(0, Ordering::Relaxed);
+                self.swap_map_time
+                    .store(get_current_millis() as i64, Ordering::Relaxed);
+                info!("swap file {} success.", self.file_name);
+                true
+            }
+            Err(err) => {
+                error!("swap file {} failed: {}", self.file_name, err);
+                false
+            }
+        };
+        self.release();
+        swapped
     }
 
     fn clean_swaped_map(&self, force: bool) {
-        todo!()
+        let mut wait_to_clean = self.mmapped_file_wait_to_clean.lock();
+        if wait_to_clean.is_none() {
+            return;
+        }
+        if !force
+            && get_current_millis() as i64 - self.get_recent_swap_map_time()
+                < MIN_SWAP_CLEAN_GAP_MILLIS
+        {
+            return;
+        }
+        // dropping the mapping unmaps it
+        wait_to_clean.take();
+        info!("clean swapped map of file {} success.", self.file_name);
     }
 
     fn get_recent_swap_map_time(&self) -> i64 {
-        todo!()
+        self.swap_map_time.load(Ordering::Relaxed)
     }
 
     fn get_mapped_byte_buffer_access_count_since_last_swap(&self) -> i64 {
-        todo!()
+        self.mapped_byte_buffer_access_count_since_last_swap
+            .load(Ordering::Relaxed)
     }
 
     fn get_file(&self) -> &File {
@@ -685,11 +905,125 @@ impl MappedFile for DefaultMappedFile {
 #[allow(unused_variables)]
 impl DefaultMappedFile {
     pub fn get_mapped_file_mut(&self) -> &mut MmapMut {
-        self.mmapped_file.mut_from_ref()
+        self.mmapped_file
+            .mut_from_ref()
+            .as_mut()
+            .expect("mapped file opened without mmap")
     }
 
     pub fn get_mapped_file(&self) -> &MmapMut {
-        self.mmapped_file.as_ref()
+        self.mmapped_file
+            .as_ref()
+            .as_ref()
+            .expect("mapped file opened without mmap")
+    }
+
+    /// Syncs the file through io_uring when the backend is compiled in and the kernel supports
+    /// it, returns false to fall back to `msync` or `fdatasync`.
+    fn flush_with_io_uring(&self) -> bool {
+        #[cfg(all(target_os = "linux", feature = "io_uring"))]
+        if self.transient_store_pool.is_none() && uring_io::is_available() {
+            return uring_io::fdatasync(&self.file).is_ok();
+        }
+        false
+    }
+
+    /// Writes `data` at `pos`, the caller checks the range against the file size.
+    fn write_at(&self, pos: usize, data: &[u8]) -> std::io::Result<()> {
+        if !self.write_without_mmap {
+            return (&mut self.get_mapped_file_mut()[pos..pos + data.len()]).write_all(data);
+        }
+        let Some(write_buffer) = self.write_buffer.as_ref() else {
+            return positioned_io::write_all_vectored_at(&self.file, pos as u64, &[data]);
+        };
+        let mut write_buffer = write_buffer.lock();
+        if write_buffer.try_append(pos, data) {
+            return Ok(());
+        }
+        self.write_out(&mut write_buffer)?;
+        if !write_buffer.try_append(pos, data) {
+            // larger than the buffer, nothing to gather
+            positioned_io::write_all_vectored_at(&self.file, pos as u64, &[data])?;
+        }
+        Ok(())
+    }
+
+    fn read_at(&self, pos: usize, size: usize) -> std::io::Result<Bytes> {
+        if !self.write_without_mmap {
+            return Ok(Bytes::copy_from_slice(
+                &self.get_mapped_file()[pos..pos + size],
+            ));
+        }
+        let mut buffer = BytesMut::zeroed(size);
+        // held over the read, so buffered appends can not be written out and replaced in between
+        let write_buffer = self
+            .write_buffer
+            .as_ref()
+            .map(|write_buffer| write_buffer.lock());
+        positioned_io::read_exact_at(&self.file, &mut buffer, pos as u64)?;
+        if let Some(write_buffer) = write_buffer.as_ref() {
+            write_buffer.overlay(pos, &mut buffer);
+        }
+        Ok(buffer.freeze())
+    }
+
+    /// Writes the buffered appends to the file.
+    pub fn write_out_buffer(&self) -> std::io::Result<()> {
+        match self.write_buffer.as_ref() {
+            Some(write_buffer) => self.write_out(&mut write_buffer.lock()),
+            None => Ok(()),
+        }
+    }
+
+    fn write_out(&self, write_buffer: &mut WriteBuffer) -> std::io::Result<()> {
+        if write_buffer.data.is_empty() {
+            return Ok(());
+        }
+        positioned_io::write_all_vectored_at(
+            &self.file,
+            write_buffer.position as u64,
+            &[&write_buffer.data],
+        )?;
+        write_buffer.data.clear();
+        Ok(())
+    }
+
+    /// Without a mapping a selected buffer carries its own copy of the range. Releases the hold
+    /// taken by the caller when the read fails.
+    fn read_for_select(&self, pos: usize, size: usize) -> Option<Option<Bytes>> {
+        if !self.write_without_mmap {
+            return Some(None);
+        }
+        match self.read_at(pos, size) {
+            Ok(bytes) => Some(Some(bytes)),
+            Err(err) => {
+                error!("read {} at {} failed: {}", self.file_name, pos, err);
+                self.release();
+                None
+            }
+        }
+    }
+
+    /// Reads one byte of every page in `[pos, pos + size)` below the read position so they are
+    /// faulted into the page cache ahead of the real read, returns the number of pages touched.
+    pub fn prefetch(&self, pos: usize, size: usize) -> usize {
+        let end = (pos + size).min(self.get_read_position().max(0) as usize);
+        if self.write_without_mmap || pos >= end || !self.hold() {
+            return 0;
+        }
+        let mapped_file = self.get_mapped_file();
+        let page_size = OS_PAGE_SIZE as usize;
+        let mut checksum = 0u8;
+        let mut pages = 0;
+        let mut offset = pos - pos % page_size;
+        while offset < end {
+            checksum = checksum.wrapping_add(mapped_file[offset.max(pos)]);
+            pages += 1;
+            offset += page_size;
+        }
+        std::hint::black_box(checksum);
+        self.release();
+        pages
     }
 
     fn is_able_to_flush(&self, flush_least_pages: i32) -> bool {
@@ -727,6 +1061,48 @@ impl DefaultMappedFile {
     }
 }
 
+/// Contiguous appends to a file opened without mmap, not written to it yet.
+struct WriteBuffer {
+    capacity: usize,
+    /// File position of the first buffered byte.
+    position: usize,
+    data: BytesMut,
+}
+
+impl WriteBuffer {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            position: 0,
+            data: BytesMut::with_capacity(capacity),
+        }
+    }
+
+    /// Buffers `data` when it continues the buffered appends, or starts them, and still fits.
+    fn try_append(&mut self, pos: usize, data: &[u8]) -> bool {
+        if self.data.len() + data.len() > self.capacity {
+            return false;
+        }
+        if self.data.is_empty() {
+            self.position = pos;
+        } else if pos != self.position + self.data.len() {
+            return false;
+        }
+        self.data.extend_from_slice(data);
+        true
+    }
+
+    /// Copies the buffered bytes falling in `[pos, pos + buffer.len())` over `buffer`.
+    fn overlay(&self, pos: usize, buffer: &mut [u8]) {
+        let start = pos.max(self.position);
+        let end = (pos + buffer.len()).min(self.position + self.data.len());
+        if start < end {
+            buffer[start - pos..end - pos]
+                .copy_from_slice(&self.data[start - self.position..end - self.position]);
+        }
+    }
+}
+
 pub struct ReferenceResource {
     ref_count: AtomicI64,
     available: AtomicBool,
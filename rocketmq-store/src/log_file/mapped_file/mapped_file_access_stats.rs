@@ -0,0 +1,164 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+
+use cheetah_string::CheetahString;
+use rocketmq_common::TimeUtils::get_current_millis;
+use serde::Deserialize;
+use serde::Serialize;
+
+/// Read/write counters of a single mapped file.
+#[derive(Default)]
+pub struct MappedFileAccessStats {
+    read_count: AtomicU64,
+    write_count: AtomicU64,
+    last_read_timestamp: AtomicU64,
+    last_write_timestamp: AtomicU64,
+}
+
+impl MappedFileAccessStats {
+    #[inline]
+    pub fn record_read(&self) {
+        self.read_count.fetch_add(1, Ordering::Relaxed);
+        self.last_read_timestamp
+            .store(get_current_millis(), Ordering::Relaxed);
+    }
+
+    #[inline]
+    pub fn record_write(&self) {
+        self.write_count.fetch_add(1, Ordering::Relaxed);
+        self.last_write_timestamp
+            .store(get_current_millis(), Ordering::Relaxed);
+    }
+
+    pub fn read_count(&self) -> u64 {
+        self.read_count.load(Ordering::Relaxed)
+    }
+
+    pub fn write_count(&self) -> u64 {
+        self.write_count.load(Ordering::Relaxed)
+    }
+
+    /// Timestamp of the latest read or write, `0` if the file was never accessed.
+    pub fn last_access_timestamp(&self) -> u64 {
+        self.last_read_timestamp
+            .load(Ordering::Relaxed)
+            .max(self.last_write_timestamp.load(Ordering::Relaxed))
+    }
+
+    /// Milliseconds since the file was last accessed, used to pick swap/unmap candidates.
+    pub fn idle_millis(&self, now: u64) -> u64 {
+        now.saturating_sub(self.last_access_timestamp())
+    }
+
+    pub fn snapshot(
+        &self,
+        file_name: &CheetahString,
+        file_from_offset: u64,
+    ) -> MappedFileAccessInfo {
+        MappedFileAccessInfo {
+            file_name: file_name.to_string(),
+            file_from_offset,
+            read_count: self.read_count(),
+            write_count: self.write_count(),
+            last_read_timestamp: self.last_read_timestamp.load(Ordering::Relaxed),
+            last_write_timestamp: self.last_write_timestamp.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MappedFileAccessInfo {
+    pub file_name: String,
+    pub file_from_offset: u64,
+    pub read_count: u64,
+    pub write_count: u64,
+    pub last_read_timestamp: u64,
+    pub last_write_timestamp: u64,
+}
+
+impl MappedFileAccessInfo {
+    #[inline]
+    pub fn access_count(&self) -> u64 {
+        self.read_count + self.write_count
+    }
+}
+
+/// Keeps the `top_n` most accessed files, most recently accessed first on ties.
+pub fn rank_hot_files(
+    mut files: Vec<MappedFileAccessInfo>,
+    top_n: usize,
+) -> Vec<MappedFileAccessInfo> {
+    files.sort_by(|a, b| {
+        b.access_count().cmp(&a.access_count()).then_with(|| {
+            b.last_read_timestamp
+                .max(b.last_write_timestamp)
+                .cmp(&a.last_read_timestamp.max(a.last_write_timestamp))
+        })
+    });
+    files.truncate(top_n);
+    files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(name: &str, read_count: u64, write_count: u64, last_read: u64) -> MappedFileAccessInfo {
+        MappedFileAccessInfo {
+            file_name: name.to_string(),
+            read_count,
+            write_count,
+            last_read_timestamp: last_read,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn rank_hot_files_orders_by_access_count_then_recency() {
+        let ranked = rank_hot_files(
+            vec![
+                info("a", 1, 1, 10),
+                info("b", 10, 0, 10),
+                info("c", 0, 2, 20),
+                info("d", 0, 0, 0),
+            ],
+            3,
+        );
+        let names = ranked
+            .iter()
+            .map(|file| file.file_name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["b", "c", "a"]);
+    }
+
+    #[test]
+    fn stats_track_counts_and_idle_time() {
+        let stats = MappedFileAccessStats::default();
+        assert_eq!(stats.last_access_timestamp(), 0);
+        stats.record_read();
+        stats.record_write();
+        stats.record_write();
+        assert_eq!(stats.read_count(), 1);
+        assert_eq!(stats.write_count(), 2);
+        let last_access = stats.last_access_timestamp();
+        assert!(last_access > 0);
+        assert_eq!(stats.idle_millis(last_access + 100), 100);
+    }
+}
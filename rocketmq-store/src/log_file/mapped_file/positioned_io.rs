@@ -0,0 +1,126 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+//! Positioned file I/O used by mapped files opened without mmap.
+
+use std::fs::File;
+use std::io;
+
+/// Writes all of `bufs` to `file` starting at `offset` without moving the file cursor, with a
+/// single `pwritev` per round on unix.
+#[cfg(unix)]
+pub fn write_all_vectored_at(file: &File, mut offset: u64, bufs: &[&[u8]]) -> io::Result<()> {
+    use std::io::IoSlice;
+    use std::os::fd::AsRawFd;
+
+    let mut slices: Vec<IoSlice<'_>> = bufs.iter().map(|buf| IoSlice::new(buf)).collect();
+    let mut slices = slices.as_mut_slice();
+    while !slices.is_empty() {
+        let iov_count = slices.len().min(libc::IOV_MAX as usize) as libc::c_int;
+        // SAFETY: `IoSlice` is ABI compatible with `iovec` on unix and the slices outlive the call
+        let wrote = unsafe {
+            libc::pwritev(
+                file.as_raw_fd(),
+                slices.as_ptr() as *const libc::iovec,
+                iov_count,
+                offset as libc::off_t,
+            )
+        };
+        if wrote < 0 {
+            let err = io::Error::last_os_error();
+            if err.kind() == io::ErrorKind::Interrupted {
+                continue;
+            }
+            return Err(err);
+        }
+        if wrote == 0 {
+            return Err(io::ErrorKind::WriteZero.into());
+        }
+        offset += wrote as u64;
+        IoSlice::advance_slices(&mut slices, wrote as usize);
+    }
+    Ok(())
+}
+
+/// Writes all of `bufs` to `file` starting at `offset`, coalescing them into one positioned
+/// write where `pwritev` is not available.
+#[cfg(windows)]
+pub fn write_all_vectored_at(file: &File, mut offset: u64, bufs: &[&[u8]]) -> io::Result<()> {
+    use std::os::windows::fs::FileExt;
+
+    let data = bufs.concat();
+    let mut remaining = data.as_slice();
+    while !remaining.is_empty() {
+        match file.seek_write(remaining, offset) {
+            Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+            Ok(wrote) => {
+                offset += wrote as u64;
+                remaining = &remaining[wrote..];
+            }
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(())
+}
+
+/// Fills `buf` from `file` starting at `offset`, served from the page cache when the pages are
+/// resident.
+#[cfg(unix)]
+pub fn read_exact_at(file: &File, buf: &mut [u8], offset: u64) -> io::Result<()> {
+    std::os::unix::fs::FileExt::read_exact_at(file, buf, offset)
+}
+
+/// Fills `buf` from `file` starting at `offset`.
+#[cfg(windows)]
+pub fn read_exact_at(file: &File, mut buf: &mut [u8], mut offset: u64) -> io::Result<()> {
+    use std::os::windows::fs::FileExt;
+
+    while !buf.is_empty() {
+        match file.seek_read(buf, offset) {
+            Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+            Ok(read) => {
+                offset += read as u64;
+                buf = &mut buf[read..];
+            }
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vectored_write_then_read_back() {
+        let file = tempfile::tempfile().unwrap();
+        file.set_len(64).unwrap();
+        write_all_vectored_at(&file, 8, &[b"hello ", b"", b"world"]).unwrap();
+
+        let mut buf = [0u8; 11];
+        read_exact_at(&file, &mut buf, 8).unwrap();
+        assert_eq!(&buf, b"hello world");
+
+        let mut head = [1u8; 8];
+        read_exact_at(&file, &mut head, 0).unwrap();
+        assert_eq!(head, [0u8; 8]);
+        assert!(read_exact_at(&file, &mut [0u8; 8], 60).is_err());
+    }
+}
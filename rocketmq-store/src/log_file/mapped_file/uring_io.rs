@@ -0,0 +1,226 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! io_uring backed file I/O for mapped file flushes and large sequential reads.
+//!
+//! Every thread sets up its own ring on first use. When the kernel refuses io_uring, as older
+//! kernels and seccomp filtered containers do, every call returns `Unsupported` and the callers
+//! keep using mmap, `msync` and `pread`.
+
+use std::cell::RefCell;
+use std::fs::File;
+use std::io;
+use std::os::fd::AsRawFd;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+
+use bytes::Bytes;
+use bytes::BytesMut;
+use io_uring::opcode;
+use io_uring::types;
+use io_uring::IoUring;
+use tracing::warn;
+
+use crate::log_file::mapped_file::positioned_io;
+
+const RING_ENTRIES: u32 = 32;
+/// Size of one read submitted for a sequential read, up to `RING_ENTRIES` are in flight.
+const READ_CHUNK_SIZE: usize = 1024 * 1024;
+
+static UNAVAILABLE: AtomicBool = AtomicBool::new(false);
+
+thread_local! {
+    static RING: RefCell<Option<IoUring>> = const { RefCell::new(None) };
+}
+
+/// Whether io_uring may still be used, false once setting up a ring failed.
+pub fn is_available() -> bool {
+    !UNAVAILABLE.load(Ordering::Relaxed)
+}
+
+/// Writes the data of `file` back to disk like `fdatasync`, which on Linux also covers the pages
+/// dirtied through a shared mapping of it.
+pub fn fdatasync(file: &File) -> io::Result<()> {
+    with_ring(|ring| {
+        let entry = opcode::Fsync::new(types::Fd(file.as_raw_fd()))
+            .flags(types::FsyncFlags::DATASYNC)
+            .build();
+        // SAFETY: the entry refers to no memory, only to a descriptor that outlives the call
+        unsafe { push(ring, &entry)? };
+        loop {
+            match ring.submit_and_wait(1) {
+                Ok(_) => {}
+                Err(err) if is_retryable(&err) => {}
+                Err(err) => return Err(err),
+            }
+            if let Some(cqe) = ring.completion().next() {
+                if cqe.result() < 0 {
+                    return Err(io::Error::from_raw_os_error(-cqe.result()));
+                }
+                return Ok(());
+            }
+        }
+    })
+}
+
+/// Reads `size` bytes of `file` from `offset`, split into chunks read concurrently.
+pub fn read_at(file: &File, offset: u64, size: usize) -> io::Result<Bytes> {
+    let mut buffer = BytesMut::zeroed(size);
+    let mut in_flight = false;
+    let result = with_ring(|ring| read_chunks(ring, file, offset, &mut buffer, &mut in_flight));
+    if in_flight {
+        // the kernel may still complete reads into the buffer, neither it nor the ring are
+        // touched again
+        abandon_ring();
+        std::mem::forget(buffer);
+        return Err(result
+            .err()
+            .unwrap_or_else(|| io::Error::other("io_uring read abandoned")));
+    }
+    result.map(|()| buffer.freeze())
+}
+
+/// Fills `buffer` from `offset`. `in_flight` stays set when an error leaves reads unreaped.
+fn read_chunks(
+    ring: &mut IoUring,
+    file: &File,
+    offset: u64,
+    buffer: &mut [u8],
+    in_flight: &mut bool,
+) -> io::Result<()> {
+    let mut pending = Vec::new();
+    for (index, chunk) in buffer.chunks_mut(READ_CHUNK_SIZE).enumerate() {
+        pending.push((offset + (index * READ_CHUNK_SIZE) as u64, chunk));
+    }
+    for round in pending.chunks_mut(RING_ENTRIES as usize) {
+        for (index, (chunk_offset, chunk)) in round.iter_mut().enumerate() {
+            let entry = opcode::Read::new(
+                types::Fd(file.as_raw_fd()),
+                chunk.as_mut_ptr(),
+                chunk.len() as u32,
+            )
+            .offset(*chunk_offset)
+            .build()
+            .user_data(index as u64);
+            *in_flight = true;
+            // SAFETY: the chunk outlives the read, it is reaped below or the buffer is leaked
+            unsafe { push(ring, &entry)? };
+        }
+        let mut results = vec![None; round.len()];
+        let mut completed = 0;
+        while completed < round.len() {
+            match ring.submit_and_wait(1) {
+                Ok(_) => {}
+                Err(err) if is_retryable(&err) => {}
+                Err(err) => return Err(err),
+            }
+            while let Some(cqe) = ring.completion().next() {
+                results[cqe.user_data() as usize] = Some(cqe.result());
+                completed += 1;
+            }
+        }
+        *in_flight = false;
+        for ((chunk_offset, chunk), read) in round.iter_mut().zip(results) {
+            let read = read.unwrap_or_default();
+            if read < 0 {
+                return Err(io::Error::from_raw_os_error(-read));
+            }
+            let read = read as usize;
+            if read < chunk.len() {
+                // short reads are rare, the rest of the chunk is read synchronously
+                positioned_io::read_exact_at(
+                    file,
+                    &mut chunk[read..],
+                    *chunk_offset + read as u64,
+                )?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn is_retryable(err: &io::Error) -> bool {
+    err.kind() == io::ErrorKind::Interrupted || err.raw_os_error() == Some(libc::EBUSY)
+}
+
+/// Gives up io_uring after a failure that left requests of this thread's ring in flight.
+fn abandon_ring() {
+    UNAVAILABLE.store(true, Ordering::Relaxed);
+    RING.with(|ring| {
+        if let Some(ring) = ring.borrow_mut().take() {
+            std::mem::forget(ring);
+        }
+    });
+    warn!("io_uring request failed with reads in flight, falling back to mmap");
+}
+
+fn with_ring<T>(f: impl FnOnce(&mut IoUring) -> io::Result<T>) -> io::Result<T> {
+    if !is_available() {
+        return Err(io::ErrorKind::Unsupported.into());
+    }
+    RING.with(|ring| {
+        let mut ring = ring.borrow_mut();
+        if ring.is_none() {
+            match IoUring::new(RING_ENTRIES) {
+                Ok(new_ring) => *ring = Some(new_ring),
+                Err(err) => {
+                    if !UNAVAILABLE.swap(true, Ordering::Relaxed) {
+                        warn!("io_uring is not available, falling back to mmap: {}", err);
+                    }
+                    return Err(io::ErrorKind::Unsupported.into());
+                }
+            }
+        }
+        f(ring.as_mut().unwrap())
+    })
+}
+
+/// Queues `entry`, submitting what is queued first when the submission queue is full.
+///
+/// # Safety
+///
+/// Memory the entry points to must stay valid until its completion is reaped.
+unsafe fn push(ring: &mut IoUring, entry: &io_uring::squeue::Entry) -> io::Result<()> {
+    if unsafe { ring.submission().push(entry) }.is_err() {
+        ring.submit()?;
+        unsafe { ring.submission().push(entry) }
+            .map_err(|_| io::Error::other("io_uring submission queue is full"))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_at_matches_the_file_and_fdatasync_succeeds() {
+        let file = tempfile::tempfile().unwrap();
+        let data = (0..READ_CHUNK_SIZE * 2 + 100)
+            .map(|i| (i % 251) as u8)
+            .collect::<Vec<_>>();
+        positioned_io::write_all_vectored_at(&file, 0, &[&data]).unwrap();
+
+        match read_at(&file, 10, data.len() - 10) {
+            Ok(bytes) => {
+                assert_eq!(&bytes[..], &data[10..]);
+                fdatasync(&file).unwrap();
+            }
+            // kernels without io_uring keep the fallback path
+            Err(err) => assert_eq!(err.kind(), io::ErrorKind::Unsupported),
+        }
+    }
+}
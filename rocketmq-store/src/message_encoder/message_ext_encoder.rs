@@ -370,8 +370,9 @@ impl MessageExtEncoder {
             self.byte_buf
                 .put_u8(MessageDecoder::PROPERTY_SEPARATOR as u8);
         }
-        // 18 CRC32
-        self.byte_buf.advance(self.crc32_reserved_length as usize);
+        // 18 CRC32, reserved here and filled in at append time
+        self.byte_buf
+            .put_bytes(0, self.crc32_reserved_length as usize);
         None
     }
 
@@ -517,8 +518,9 @@ impl MessageExtEncoder {
                 }
                 self.byte_buf.put(batch_prop_data);
             }
-            // 18 CRC32
-            self.byte_buf.advance(self.crc32_reserved_length as usize);
+            // 18 CRC32, reserved here and filled in at append time
+            self.byte_buf
+                .put_bytes(0, self.crc32_reserved_length as usize);
         }
         put_message_context.set_batch_size(batch_size);
         put_message_context.set_phy_pos(vec![0; batch_size as usize]);
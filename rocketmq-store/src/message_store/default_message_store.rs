@@ -19,8 +19,10 @@
 use std::collections::HashMap;
 use std::error::Error;
 use std::fs;
+use std::path::Path;
 use std::path::PathBuf;
 use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicI32;
 use std::sync::atomic::AtomicI64;
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
@@ -33,9 +35,13 @@ use cheetah_string::CheetahString;
 use rocketmq_common::common::attribute::cleanup_policy::CleanupPolicy;
 use rocketmq_common::common::message::message_batch::MessageExtBatch;
 use rocketmq_common::common::message::message_ext::MessageExt;
+use rocketmq_common::common::message::message_integrity::verify_body_crc;
+use rocketmq_common::common::message::message_integrity::MessageIntegrityStats;
 use rocketmq_common::common::mix_all::is_lmq;
 use rocketmq_common::common::mix_all::is_sys_consumer_group_for_no_cold_read_limit;
+use rocketmq_common::common::mix_all::MASTER_ID;
 use rocketmq_common::common::mix_all::MULTI_DISPATCH_QUEUE_SPLITTER;
+use rocketmq_common::common::mix_all::MULTI_PATH_SPLITTER;
 use rocketmq_common::common::mix_all::RETRY_GROUP_TOPIC_PREFIX;
 use rocketmq_common::utils::util_all;
 use rocketmq_common::CleanupPolicyUtils::get_delete_policy;
@@ -48,43 +54,75 @@ use rocketmq_common::{
         sys_flag::message_sys_flag::MessageSysFlag,
         //thread::thread_service_tokio::ThreadService,
     },
-    utils::queue_type_utils::QueueTypeUtils,
     FileUtils::string_to_file,
     MessageDecoder,
     UtilAll::ensure_dir_ok,
 };
 use rocketmq_rust::ArcMut;
+use serde::Deserialize;
+use serde::Serialize;
+use sysinfo::Disks;
 use tokio::runtime::Handle;
+use tokio::sync::broadcast::error::RecvError;
 use tokio::sync::mpsc::Sender;
+use tracing::debug;
 use tracing::error;
 use tracing::info;
 use tracing::warn;
 
 use crate::base::allocate_mapped_file_service::AllocateMappedFileService;
 use crate::base::commit_log_dispatcher::CommitLogDispatcher;
+use crate::base::decode_failure_stats::DecodeFailure;
+use crate::base::disk_usage;
+use crate::base::disk_usage::DiskUsageInfo;
+use crate::base::disk_usage::IngestRateTracker;
+use crate::base::disk_usage::StorePathUsage;
 use crate::base::dispatch_request::DispatchRequest;
+use crate::base::dispatch_rule::DispatchRules;
 use crate::base::get_message_result::GetMessageResult;
+use crate::base::hot_message_cache::HotMessageCache;
 use crate::base::message_arriving_listener::MessageArrivingListener;
+use crate::base::message_result::PutMessageFuture;
 use crate::base::message_result::PutMessageResult;
 use crate::base::message_status_enum::GetMessageStatus;
 use crate::base::message_status_enum::PutMessageStatus;
 use crate::base::query_message_result::QueryMessageResult;
 use crate::base::select_result::SelectMappedBufferResult;
 use crate::base::store_checkpoint::StoreCheckpoint;
+use crate::base::store_event_bus::StoreEvent;
+use crate::base::store_event_bus::StoreEventBus;
+use crate::base::store_event_bus::StoreFileKind;
+use crate::base::store_health::CleanServiceHealth;
+use crate::base::store_health::CommitLogHealth;
+use crate::base::store_health::ConsumeQueueHealth;
+use crate::base::store_health::DiskHealth;
+use crate::base::store_health::HAHealth;
+use crate::base::store_health::StoreHealth;
+use crate::base::store_read_executor::StoreReadExecutor;
+use crate::base::store_snapshot;
+use crate::base::store_snapshot::StoreSnapshotManifest;
 use crate::base::store_stats_service::StoreStatsService;
+use crate::base::store_stats_service::FREQUENCY_OF_SAMPLING;
+use crate::base::store_task_spawner::StoreTaskSpawner;
+use crate::base::swappable::Swappable;
 use crate::base::transient_store_pool::TransientStorePool;
 use crate::config::broker_role::BrokerRole;
+use crate::config::flush_disk_type::FlushDiskType;
 use crate::config::message_store_config::MessageStoreConfig;
 use crate::config::store_path_config_helper::get_store_path_batch_consume_queue;
 use crate::config::store_path_config_helper::get_store_path_consume_queue_ext;
 use crate::filter::MessageFilter;
+use crate::ha::ha_handshake::HAHandshake;
 use crate::hook::put_message_hook::BoxedPutMessageHook;
 use crate::index::index_dispatch::CommitLogDispatcherBuildIndex;
 use crate::index::index_service::IndexService;
+use crate::kv::compaction_dispatch::CommitLogDispatcherCompaction;
 use crate::kv::compaction_service::CompactionService;
 use crate::kv::compaction_store::CompactionStore;
 use crate::log_file::commit_log;
 use crate::log_file::commit_log::CommitLog;
+use crate::log_file::mapped_file::mapped_file_access_stats::rank_hot_files;
+use crate::log_file::mapped_file::mapped_file_access_stats::MappedFileAccessInfo;
 use crate::log_file::mapped_file::MappedFile;
 use crate::log_file::MessageStore;
 use crate::log_file::MAX_PULL_MSG_SIZE;
@@ -92,11 +130,15 @@ use crate::queue::build_consume_queue::CommitLogDispatcherBuildConsumeQueue;
 use crate::queue::local_file_consume_queue_store::ConsumeQueueStore;
 use crate::queue::ArcConsumeQueue;
 use crate::queue::ConsumeQueueStoreTrait;
+use crate::queue::CqUnit;
 use crate::stats::broker_stats_manager::BrokerStatsManager;
 use crate::store::running_flags::RunningFlags;
 use crate::store_path_config_helper::get_abort_file;
 use crate::store_path_config_helper::get_store_checkpoint;
 use crate::store_path_config_helper::get_store_path_consume_queue;
+use crate::store_path_config_helper::get_store_path_index;
+use crate::tiered::tiered_message_store::TieredMessageStore;
+use crate::tiered::tiered_store_provider;
 use crate::timer::timer_message_store::TimerMessageStore;
 use crate::utils::store_util::TOTAL_PHYSICAL_MEMORY_SIZE;
 
@@ -125,14 +167,20 @@ pub struct DefaultMessageStore {
     correct_logic_offset_service: Arc<CorrectLogicOffsetService>,
     clean_consume_queue_service: Arc<CleanConsumeQueueService>,
     broker_stats_manager: Option<Arc<BrokerStatsManager>>,
-    message_arriving_listener:
-        Option<Arc<Box<dyn MessageArrivingListener + Sync + Send + 'static>>>,
+    message_arriving_listeners: Vec<Arc<Box<dyn MessageArrivingListener + Sync + Send + 'static>>>,
     notify_message_arrive_in_batch: bool,
     store_stats_service: Arc<StoreStatsService>,
     compaction_store: Arc<CompactionStore>,
     timer_message_store: Arc<TimerMessageStore>,
     transient_store_pool: TransientStorePool,
     message_store_arc: Option<ArcMut<DefaultMessageStore>>,
+    body_crc_stats: Arc<MessageIntegrityStats>,
+    task_spawner: StoreTaskSpawner,
+    read_executor: Option<Arc<StoreReadExecutor>>,
+    event_bus: Arc<StoreEventBus>,
+    hot_message_cache: Option<Arc<HotMessageCache>>,
+    tiered_message_store: Option<Arc<TieredMessageStore>>,
+    dispatch_rules: Arc<DispatchRules>,
 }
 
 impl DefaultMessageStore {
@@ -143,6 +191,27 @@ impl DefaultMessageStore {
         broker_stats_manager: Option<Arc<BrokerStatsManager>>,
         notify_message_arrive_in_batch: bool,
     ) -> Self {
+        Self::new_with_runtime(
+            message_store_config,
+            broker_config,
+            topic_config_table,
+            broker_stats_manager,
+            notify_message_arrive_in_batch,
+            None,
+        )
+    }
+
+    /// Like [`DefaultMessageStore::new`], but spawns the background services (flush, commit,
+    /// reput and the periodic cleaning tasks) on `runtime_handle` instead of the ambient runtime.
+    pub fn new_with_runtime(
+        message_store_config: Arc<MessageStoreConfig>,
+        broker_config: Arc<BrokerConfig>,
+        topic_config_table: Arc<parking_lot::Mutex<HashMap<CheetahString, TopicConfig>>>,
+        broker_stats_manager: Option<Arc<BrokerStatsManager>>,
+        notify_message_arrive_in_batch: bool,
+        runtime_handle: Option<Handle>,
+    ) -> Self {
+        let task_spawner = StoreTaskSpawner::new(runtime_handle);
         let running_flags = Arc::new(RunningFlags::new());
         let store_checkpoint = Arc::new(
             StoreCheckpoint::new(get_store_checkpoint(
@@ -150,10 +219,17 @@ impl DefaultMessageStore {
             ))
             .unwrap(),
         );
-        let index_service =
-            IndexService::new(message_store_config.clone(), store_checkpoint.clone());
-        let build_index =
-            CommitLogDispatcherBuildIndex::new(index_service.clone(), message_store_config.clone());
+        let index_service = IndexService::new(
+            message_store_config.clone(),
+            store_checkpoint.clone(),
+            topic_config_table.clone(),
+        );
+        let dispatch_rules = Arc::new(DispatchRules::with_system_topics());
+        let build_index = CommitLogDispatcherBuildIndex::new(
+            index_service.clone(),
+            message_store_config.clone(),
+            dispatch_rules.clone(),
+        );
         // let topic_config_table = Arc::new(parking_lot::Mutex::new(HashMap::new()));
         let consume_queue_store = ConsumeQueueStore::new(
             message_store_config.clone(),
@@ -162,13 +238,31 @@ impl DefaultMessageStore {
             running_flags.clone(),
             store_checkpoint.clone(),
         );
-        let build_consume_queue =
-            CommitLogDispatcherBuildConsumeQueue::new(consume_queue_store.clone());
+        let build_consume_queue = CommitLogDispatcherBuildConsumeQueue::new(
+            consume_queue_store.clone(),
+            dispatch_rules.clone(),
+        );
+
+        let compaction_store = Arc::new(CompactionStore::new(&message_store_config));
+        let compaction_service = CompactionService::new(compaction_store.clone());
+        let build_compaction = CommitLogDispatcherCompaction::new(
+            compaction_service.clone(),
+            message_store_config.clone(),
+            topic_config_table.clone(),
+        );
 
         let dispatcher = CommitLogDispatcherDefault {
-            dispatcher_vec: Arc::new(vec![Box::new(build_consume_queue), Box::new(build_index)]),
+            dispatcher_vec: Arc::new(vec![
+                Box::new(build_consume_queue),
+                Box::new(build_index),
+                Box::new(build_compaction),
+            ]),
+            dispatch_rules: dispatch_rules.clone(),
         };
 
+        let allocate_mapped_file_service =
+            Arc::new(AllocateMappedFileService::new(message_store_config.clone()));
+        let event_bus = Arc::new(StoreEventBus::default());
         let commit_log = CommitLog::new(
             message_store_config.clone(),
             broker_config.clone(),
@@ -176,6 +270,9 @@ impl DefaultMessageStore {
             store_checkpoint.clone(),
             topic_config_table.clone(),
             consume_queue_store.clone(),
+            task_spawner.clone(),
+            allocate_mapped_file_service.clone(),
+            event_bus.clone(),
         );
 
         ensure_dir_ok(message_store_config.store_path_root_dir.as_str());
@@ -187,6 +284,43 @@ impl DefaultMessageStore {
             message_store_config.transient_store_pool_size,
             message_store_config.mapped_file_size_commit_log,
         );
+        let clean_commit_log_service = Arc::new(CleanCommitLogService::new(
+            message_store_config.clone(),
+            running_flags.clone(),
+            event_bus.clone(),
+        ));
+        let hot_message_cache = HotMessageCache::from_config(&message_store_config).map(Arc::new);
+        let tiered_message_store = if message_store_config.tiered_store_enable {
+            match tiered_store_provider::create_provider(&message_store_config) {
+                Ok(provider) => Some(Arc::new(TieredMessageStore::new(
+                    message_store_config.clone(),
+                    provider,
+                ))),
+                Err(err) => {
+                    error!(
+                        "create tiered store provider failed, tiering is off: {}",
+                        err
+                    );
+                    None
+                }
+            }
+        } else {
+            None
+        };
+        let read_executor = if message_store_config.store_read_thread_pool_nums > 0 {
+            match StoreReadExecutor::new(message_store_config.store_read_thread_pool_nums) {
+                Ok(executor) => Some(Arc::new(executor)),
+                Err(err) => {
+                    error!(
+                        "create store read runtime failed, reads share the caller's runtime: {}",
+                        err
+                    );
+                    None
+                }
+            }
+        } else {
+            None
+        };
         Self {
             message_store_config: message_store_config.clone(),
             broker_config,
@@ -194,11 +328,11 @@ impl DefaultMessageStore {
             topic_config_table,
             // message_store_runtime: Some(RocketMQRuntime::new_multi(10, "message-store-thread")),
             commit_log,
-            compaction_service: Default::default(),
+            compaction_service,
             store_checkpoint: Some(store_checkpoint),
             master_flushed_offset: Arc::new(AtomicI64::new(-1)),
             index_service,
-            allocate_mapped_file_service: Arc::new(AllocateMappedFileService::new()),
+            allocate_mapped_file_service,
             consume_queue_store,
             dispatcher,
             broker_init_max_offset: Arc::new(AtomicI64::new(-1)),
@@ -211,17 +345,24 @@ impl DefaultMessageStore {
                 message_store_config,
                 inner: None,
             },
-            clean_commit_log_service: Arc::new(CleanCommitLogService {}),
+            clean_commit_log_service,
             correct_logic_offset_service: Arc::new(CorrectLogicOffsetService {}),
             clean_consume_queue_service: Arc::new(CleanConsumeQueueService {}),
             broker_stats_manager,
-            message_arriving_listener: None,
+            message_arriving_listeners: Vec::new(),
             notify_message_arrive_in_batch,
             store_stats_service: Arc::new(StoreStatsService::new(Some(identity))),
-            compaction_store: Arc::new(CompactionStore),
+            compaction_store,
             timer_message_store: Arc::new(TimerMessageStore::new_empty()),
             transient_store_pool,
             message_store_arc: None,
+            body_crc_stats: Arc::new(MessageIntegrityStats::default()),
+            task_spawner,
+            read_executor,
+            event_bus,
+            hot_message_cache,
+            tiered_message_store,
+            dispatch_rules,
         }
     }
 
@@ -254,6 +395,17 @@ impl DefaultMessageStore {
     ) {
         self.message_store_arc = message_store_arc;
     }
+
+    pub fn task_spawner(&self) -> &StoreTaskSpawner {
+        &self.task_spawner
+    }
+
+    /// Spawner for the HA transfer tasks, the reader runtime when one is configured.
+    fn read_task_spawner(&self) -> StoreTaskSpawner {
+        self.read_executor
+            .as_ref()
+            .map_or_else(|| self.task_spawner.clone(), |executor| executor.spawner())
+    }
 }
 
 impl Drop for DefaultMessageStore {
@@ -273,6 +425,25 @@ impl DefaultMessageStore {
         self.topic_config_table.lock().get(topic).cloned()
     }
 
+    /// Runs the registered put message hooks in order, the first one returning a result
+    /// short-circuits the put.
+    fn execute_put_message_hooks(
+        &self,
+        msg: &mut MessageExtBrokerInner,
+    ) -> Option<PutMessageResult> {
+        for hook in self.put_message_hook_list.read().iter() {
+            if let Some(result) = hook.execute_before_put_message(msg) {
+                debug!(
+                    "put message rejected by hook {}: {:?}",
+                    hook.hook_name(),
+                    result.put_message_status()
+                );
+                return Some(result);
+            }
+        }
+        None
+    }
+
     fn is_temp_file_exist(&self) -> bool {
         let file_name = get_abort_file(self.message_store_config.store_path_root_dir.as_str());
         fs::metadata(file_name).is_ok()
@@ -411,19 +582,20 @@ impl DefaultMessageStore {
         // clean files  Periodically
         let clean_commit_log_service_arc = self.clean_commit_log_service.clone();
         let clean_resource_interval = self.message_store_config.clean_resource_interval as u64;
-        tokio::spawn(async move {
+        let mut commit_log = self.commit_log.clone();
+        self.task_spawner.spawn("clean_commit_log", async move {
             let mut interval = tokio::time::interval(Duration::from_millis(1000 * 60));
             interval.tick().await;
             let mut interval =
                 tokio::time::interval(Duration::from_millis(clean_resource_interval));
             loop {
-                clean_commit_log_service_arc.run();
+                clean_commit_log_service_arc.run(&mut commit_log).await;
                 interval.tick().await;
             }
         });
 
         let message_store = self.message_store_arc.clone().unwrap();
-        tokio::spawn(async move {
+        self.task_spawner.spawn("check_self", async move {
             let mut interval = tokio::time::interval(Duration::from_secs(60));
             interval.tick().await;
             let mut interval = tokio::time::interval(Duration::from_secs(10 * 60));
@@ -435,7 +607,7 @@ impl DefaultMessageStore {
 
         // store check point flush
         let store_checkpoint_arc = self.store_checkpoint.clone().unwrap();
-        tokio::spawn(async move {
+        self.task_spawner.spawn("store_checkpoint", async move {
             let mut interval = tokio::time::interval(Duration::from_secs(1));
             interval.tick().await;
             let mut interval = tokio::time::interval(Duration::from_secs(1));
@@ -447,17 +619,92 @@ impl DefaultMessageStore {
 
         let correct_logic_offset_service_arc = self.correct_logic_offset_service.clone();
         let clean_consume_queue_service_arc = self.clean_consume_queue_service.clone();
-        tokio::spawn(async move {
+        let commit_log = self.commit_log.clone();
+        let consume_queue_store = self.consume_queue_store.clone();
+        let mut store_events = self.event_bus.subscribe();
+        self.task_spawner.spawn("clean_consume_queue", async move {
             let mut interval = tokio::time::interval(Duration::from_millis(1000 * 60));
             interval.tick().await;
             let mut interval =
                 tokio::time::interval(Duration::from_millis(clean_resource_interval));
             loop {
                 correct_logic_offset_service_arc.run();
-                clean_consume_queue_service_arc.run();
+                clean_consume_queue_service_arc.run(&commit_log, &consume_queue_store);
+                // deleted commit log files leave expired consume queue files behind, clean
+                // them right away instead of waiting for the next round
+                loop {
+                    tokio::select! {
+                        _ = interval.tick() => break,
+                        event = store_events.recv() => match event {
+                            Ok(StoreEvent::FileDeleted {
+                                kind: StoreFileKind::CommitLog,
+                                ..
+                            })
+                            | Err(RecvError::Lagged(_)) => break,
+                            Ok(_) => {}
+                            Err(RecvError::Closed) => {
+                                interval.tick().await;
+                                break;
+                            }
+                        },
+                    }
+                }
+            }
+        });
+
+        let store_stats_service = self.store_stats_service.clone();
+        self.task_spawner.spawn("store_stats_sampling", async move {
+            let mut interval = tokio::time::interval(Duration::from_millis(FREQUENCY_OF_SAMPLING));
+            loop {
                 interval.tick().await;
+                store_stats_service.sampling();
+                store_stats_service.print_tps();
+            }
+        });
+
+        let store_stats_service = self.store_stats_service.clone();
+        let mut store_events = self.event_bus.subscribe();
+        self.task_spawner.spawn("store_event_stats", async move {
+            loop {
+                match store_events.recv().await {
+                    Ok(event) => store_stats_service.record_store_event(&event),
+                    Err(RecvError::Lagged(skipped)) => {
+                        warn!(
+                            "store event stats lagged behind, {} events skipped",
+                            skipped
+                        );
+                    }
+                    Err(RecvError::Closed) => break,
+                }
             }
         });
+
+        // remap cold mapped files so their pages can be reclaimed by the os
+        if self.message_store_config.mapped_file_swap_enable {
+            let config = self.message_store_config.clone();
+            let commit_log = self.commit_log.clone();
+            let consume_queue_store = self.consume_queue_store.clone();
+            self.task_spawner.spawn("swap_mapped_file", async move {
+                let mut interval = tokio::time::interval(Duration::from_secs(60));
+                interval.tick().await;
+                let mut interval = tokio::time::interval(Duration::from_secs(60));
+                loop {
+                    commit_log.swap_map(
+                        config.commit_log_swap_map_reserve_file_num as i32,
+                        config.commit_log_force_swap_map_interval as i64,
+                        config.commit_log_swap_map_interval as i64,
+                    );
+                    consume_queue_store.swap_map(
+                        config.logic_queue_swap_map_reserve_file_num as i32,
+                        config.logic_queue_force_swap_map_interval as i64,
+                        config.logic_queue_swap_map_interval as i64,
+                    );
+                    commit_log.clean_swapped_map(config.clean_swapped_map_interval as i64);
+                    consume_queue_store.clean_swapped_map(config.clean_swapped_map_interval as i64);
+                    interval.tick().await;
+                }
+            });
+        }
     }
 
     fn check_self(&self) {
@@ -465,6 +712,218 @@ impl DefaultMessageStore {
         self.consume_queue_store.check_self();
     }
 
+    /// Returns the `top_n` most accessed commit log files, the main source of page cache
+    /// pressure.
+    pub fn hot_mapped_files(&self, top_n: usize) -> Vec<MappedFileAccessInfo> {
+        rank_hot_files(self.commit_log.mapped_file_access_infos(), top_n)
+    }
+
+    /// Reads up to `count` raw units of a consume queue starting at queue offset `index`, used to
+    /// diagnose dispatch problems. Returns `None` when the queue does not exist, it is not
+    /// created as a side effect.
+    pub fn examine_consume_queue(
+        &self,
+        topic: &CheetahString,
+        queue_id: i32,
+        index: i64,
+        count: usize,
+    ) -> Option<Vec<CqUnit>> {
+        let consume_queue = self
+            .consume_queue_store
+            .find_consume_queue_map(topic)?
+            .remove(&queue_id)?;
+        let max_offset = consume_queue.get_max_offset_in_queue();
+        let mut next_offset = index.max(consume_queue.get_min_offset_in_queue());
+        let mut units = Vec::new();
+        // Each iterator covers one consume queue file only
+        while units.len() < count && next_offset < max_offset {
+            let Some(iter) = consume_queue.iterate_from(next_offset) else {
+                break;
+            };
+            let read_before = units.len();
+            units.extend(iter.take(count - units.len()));
+            match units.last() {
+                Some(last) if units.len() > read_before => {
+                    next_offset = last.queue_offset + last.batch_num.max(1) as i64;
+                }
+                _ => break,
+            }
+        }
+        Some(units)
+    }
+
+    /// Copies the store into `snapshot_dir` so that a fresh slave can be bootstrapped from it
+    /// instead of replicating the whole commit log through HA.
+    pub fn create_snapshot(&self, snapshot_dir: &Path) -> std::io::Result<StoreSnapshotManifest> {
+        if let Some(checkpoint) = self.store_checkpoint.as_ref() {
+            checkpoint.flush()?;
+        }
+        store_snapshot::create_snapshot(
+            &self.message_store_config,
+            snapshot_dir,
+            self.commit_log.get_min_offset(),
+            self.commit_log.get_flushed_where(),
+        )
+    }
+
+    /// Forces commit log, consume queue, index and checkpoint data to disk right away instead of
+    /// waiting for the flush services.
+    pub fn flush_all(&self) -> std::io::Result<StoreFlushResult> {
+        let begin = Instant::now();
+        let commit_log_flushed_offset = self.commit_log.flush_all();
+        let consume_queues_flushed = self.commit_log.flush_consume_queues();
+        self.index_service.flush_last_index_file();
+        if let Some(checkpoint) = self.store_checkpoint.as_ref() {
+            checkpoint.flush()?;
+        }
+        let result = StoreFlushResult {
+            commit_log_flushed_offset,
+            commit_log_max_offset: self.commit_log.get_max_offset(),
+            consume_queues_flushed,
+            cost_ms: begin.elapsed().as_millis() as u64,
+        };
+        info!("flush store on demand: {:?}", result);
+        Ok(result)
+    }
+
+    /// First frame reported to the master by a slave, carries the snapshot confirm offset when
+    /// the store was bootstrapped from a snapshot.
+    pub fn build_ha_handshake(&self) -> HAHandshake {
+        let max_phy_offset = self.commit_log.get_max_offset();
+        let manifest = self
+            .message_store_config
+            .snapshot_bootstrap_dir
+            .as_ref()
+            .filter(|dir| !dir.is_empty())
+            .and_then(|dir| store_snapshot::read_manifest(Path::new(dir)).ok());
+        match manifest {
+            Some(manifest) => HAHandshake::from_snapshot(max_phy_offset, manifest.confirm_offset),
+            None => HAHandshake::new(max_phy_offset),
+        }
+    }
+
+    /// Controller mode: becomes the master of `master_epoch`.
+    pub fn change_to_master(&mut self, master_epoch: i32) -> bool {
+        let Some(auto_switch_ha_service) = self.commit_log.auto_switch_ha_service().cloned() else {
+            warn!("change to master ignored, controller mode is not enabled");
+            return false;
+        };
+        let changed =
+            auto_switch_ha_service.change_to_master(master_epoch, self.commit_log.get_max_offset());
+        if changed {
+            self.event_bus.publish(StoreEvent::RoleChanged {
+                role: BrokerRole::SyncMaster,
+                epoch: master_epoch,
+            });
+        }
+        changed
+    }
+
+    /// Controller mode: becomes a slave of `master_ha_address`. Data past the confirm offset
+    /// was never acknowledged by the sync state set, it is truncated and replicated again
+    /// from the new master.
+    pub fn change_to_slave(&mut self, master_ha_address: String, master_epoch: i32) -> bool {
+        let Some(auto_switch_ha_service) = self.commit_log.auto_switch_ha_service().cloned() else {
+            warn!("change to slave ignored, controller mode is not enabled");
+            return false;
+        };
+        let confirm_offset = auto_switch_ha_service.confirm_offset();
+        if confirm_offset >= 0 && !self.truncate_files(confirm_offset) {
+            return false;
+        }
+        auto_switch_ha_service.change_to_slave(
+            master_ha_address,
+            master_epoch,
+            self.commit_log.get_max_offset(),
+        );
+        self.event_bus.publish(StoreEvent::RoleChanged {
+            role: BrokerRole::Slave,
+            epoch: master_epoch,
+        });
+        true
+    }
+
+    /// Collects a health snapshot of every store subsystem, used by readiness probes.
+    pub fn health(&self) -> StoreHealth {
+        let now = get_current_millis() as i64;
+        let max_offset = self.commit_log.get_max_offset();
+        let flushed_offset = self.commit_log.get_flushed_where();
+        let unflushed_bytes = (max_offset - flushed_offset).max(0);
+        let flush_lag_ms = match self.store_checkpoint.as_ref() {
+            Some(checkpoint) if unflushed_bytes > 0 && checkpoint.physic_msg_timestamp() > 0 => {
+                (now - checkpoint.physic_msg_timestamp() as i64).max(0)
+            }
+            _ => 0,
+        };
+        let mut health = StoreHealth {
+            healthy: false,
+            timestamp: now,
+            commit_log: CommitLogHealth {
+                writeable: self.running_flags.is_writeable(),
+                max_offset,
+                flushed_offset,
+                unflushed_bytes,
+                flush_lag_ms,
+            },
+            consume_queue: ConsumeQueueHealth {
+                writeable: self.running_flags.is_cq_writeable(),
+                logics_queue_error: self.running_flags.is_logics_queue_error(),
+                index_file_error: self.running_flags.is_index_file_error(),
+                dispatch_behind_bytes: self.dispatch_behind_bytes(),
+            },
+            ha: HAHealth {
+                connection_count: self.commit_log.ha_service().connection_count() as i32,
+                master_flushed_offset: self.master_flushed_offset.load(Ordering::Relaxed),
+                slaves: self.commit_log.ha_service().slave_gaps(max_offset),
+            },
+            clean_service: CleanServiceHealth {
+                last_run_timestamp: self.clean_commit_log_service.last_run_timestamp(),
+            },
+            disk: DiskHealth {
+                commit_log_used_ratio: util_all::get_disk_partition_space_used_percent(
+                    Self::get_store_path_physic(&self.message_store_config).as_str(),
+                ),
+                consume_queue_used_ratio: util_all::get_disk_partition_space_used_percent(
+                    Self::get_store_path_logic(&self.message_store_config).as_str(),
+                ),
+                max_used_ratio: self.message_store_config.disk_max_used_space_ratio as f64 / 100.0,
+                disk_full: self.running_flags.is_disk_full(),
+                logic_disk_full: self.running_flags.is_logic_disk_full(),
+            },
+            recovery: self.commit_log.recovery_progress().snapshot(),
+            decode_failures: self.commit_log.decode_failure_stats().snapshot(),
+        };
+        health.evaluate();
+        health
+    }
+
+    /// Capacity of the store paths and the space taken by commit log, consume queue and index
+    /// files, with a projection based on the ingest rate sampled by the clean service.
+    pub fn disk_usage_info(&self) -> DiskUsageInfo {
+        let root_dir = self.message_store_config.store_path_root_dir.as_str();
+        let commit_log_path = Self::get_store_path_physic(&self.message_store_config);
+        let consume_queue_path = Self::get_store_path_logic(&self.message_store_config);
+        let index_path = get_store_path_index(root_dir);
+        let disks = Disks::new_with_refreshed_list();
+        let paths = vec![
+            StorePathUsage::of("commitlog", commit_log_path.as_str(), &disks),
+            StorePathUsage::of("consumequeue", consume_queue_path.as_str(), &disks),
+            StorePathUsage::of("index", index_path.as_str(), &disks),
+        ];
+        let bytes_per_day = self.clean_commit_log_service.ingest_rate.bytes_per_day();
+        let days_until_full =
+            bytes_per_day.and_then(|rate| disk_usage::days_until_full(paths[0].free_space, rate));
+        DiskUsageInfo {
+            timestamp: get_current_millis() as i64,
+            commit_log_bytes: disk_usage::dir_size(Path::new(commit_log_path.as_str())),
+            consume_queue_bytes: disk_usage::dir_size(Path::new(consume_queue_path.as_str())),
+            index_bytes: disk_usage::dir_size(Path::new(index_path.as_str())),
+            paths,
+            ingest_bytes_per_day: bytes_per_day.map_or(-1, |rate| rate as i64),
+            days_until_full: days_until_full.unwrap_or(-1.0),
+        }
+    }
+
     pub fn next_offset_correction(&self, old_offset: i64, new_offset: i64) -> i64 {
         let mut next_offset = old_offset;
         if self.message_store_config.broker_role != BrokerRole::Slave
@@ -483,13 +942,32 @@ impl DefaultMessageStore {
         }
     }
 
+    /// Rules of the system topics the dispatchers keep out of the index or hand to a feature's
+    /// own dispatcher, features register their topics here before the store starts.
+    pub fn dispatch_rules(&self) -> &Arc<DispatchRules> {
+        &self.dispatch_rules
+    }
+
+    /// Replaces every registered arrival listener with `message_arriving_listener`.
     pub fn set_message_arriving_listener(
         &mut self,
         message_arriving_listener: Option<
             Arc<Box<dyn MessageArrivingListener + Sync + Send + 'static>>,
         >,
     ) {
-        self.message_arriving_listener = message_arriving_listener;
+        self.message_arriving_listeners.clear();
+        self.message_arriving_listeners
+            .extend(message_arriving_listener);
+    }
+
+    /// Registers one more listener woken for every dispatched message, e.g. pop long polling
+    /// next to the pull request hold service. Listeners are called in registration order.
+    pub fn add_message_arriving_listener(
+        &mut self,
+        message_arriving_listener: Arc<Box<dyn MessageArrivingListener + Sync + Send + 'static>>,
+    ) {
+        self.message_arriving_listeners
+            .push(message_arriving_listener);
     }
 }
 
@@ -573,6 +1051,13 @@ impl MessageStore for DefaultMessageStore {
             }
         }
 
+        if let Some(tiered_message_store) = self.tiered_message_store.as_ref() {
+            result &= tiered_message_store.load();
+            if !result {
+                return result;
+            }
+        }
+
         if result {
             let checkpoint = self.store_checkpoint.as_ref().unwrap();
             self.master_flushed_offset =
@@ -593,13 +1078,15 @@ impl MessageStore for DefaultMessageStore {
         info!("load over, and the max phy offset = {}", max_offset);
 
         if !result {
-            // self.allocate_mapped_file_service.shutdown();
+            self.allocate_mapped_file_service.shutdown();
         }
         result
     }
 
     fn start(&mut self) -> Result<(), Box<dyn Error>> {
         self.create_temp_file();
+        self.allocate_mapped_file_service.start();
+        self.add_schedule_task();
 
         self.reput_message_service
             .set_reput_from_offset(self.commit_log.get_confirm_offset());
@@ -609,11 +1096,43 @@ impl MessageStore for DefaultMessageStore {
             self.dispatcher.clone(),
             self.notify_message_arrive_in_batch,
             self.message_store_arc.clone().unwrap(),
+            &self.task_spawner,
         );
 
         self.commit_log.start();
-
-        //self.add_schedule_task();
+        if self.message_store_config.enable_compaction {
+            self.compaction_service.start(
+                self.commit_log.clone(),
+                self.message_store_config.compaction_schedule_internal as u64,
+                &self.task_spawner,
+            );
+        }
+        if let Some(tiered_message_store) = self.tiered_message_store.clone() {
+            let commit_log = self.commit_log.clone();
+            let upload_interval = self
+                .message_store_config
+                .tiered_upload_interval_ms
+                .max(1000);
+            self.task_spawner.spawn("tiered_upload", async move {
+                let mut interval = tokio::time::interval(Duration::from_millis(upload_interval));
+                loop {
+                    interval.tick().await;
+                    tiered_message_store.upload_sealed_segments(&commit_log);
+                }
+            });
+        }
+        if !self.message_store_config.duplication_enable {
+            let ha_task_spawner = self.read_task_spawner();
+            match self.commit_log.auto_switch_ha_service() {
+                Some(auto_switch_ha_service) => {
+                    auto_switch_ha_service.start(self.commit_log.clone(), &ha_task_spawner)
+                }
+                None => self
+                    .commit_log
+                    .ha_service()
+                    .start(self.commit_log.clone(), &ha_task_spawner),
+            }
+        }
 
         Ok(())
     }
@@ -621,8 +1140,14 @@ impl MessageStore for DefaultMessageStore {
     fn shutdown(&mut self) {
         if !self.shutdown.load(Ordering::Acquire) {
             self.shutdown.store(true, Ordering::SeqCst);
+            self.timer_message_store.shutdown();
+            self.commit_log.ha_service().shutdown();
             self.reput_message_service.shutdown();
+            if self.message_store_config.enable_compaction {
+                self.compaction_store.flush();
+            }
             self.commit_log.shutdown();
+            self.allocate_mapped_file_service.shutdown();
 
             if self.running_flags.is_writeable() {
                 //delete abort file
@@ -633,10 +1158,33 @@ impl MessageStore for DefaultMessageStore {
         }
     }
 
+    fn destroy(&mut self) {
+        self.consume_queue_store.destroy();
+        self.commit_log.destroy();
+        self.index_service.destroy();
+        if let Some(hot_message_cache) = self.hot_message_cache.as_ref() {
+            hot_message_cache.clear();
+        }
+        self.delete_file(get_abort_file(
+            self.message_store_config.store_path_root_dir.as_str(),
+        ));
+        self.delete_file(get_store_checkpoint(
+            self.message_store_config.store_path_root_dir.as_str(),
+        ));
+    }
+
     fn set_confirm_offset(&mut self, phy_offset: i64) {
         self.commit_log.set_confirm_offset(phy_offset);
     }
 
+    fn get_confirm_offset(&self) -> i64 {
+        let confirm_offset = self.commit_log.get_confirm_offset();
+        match self.message_store_config.flush_disk_type {
+            FlushDiskType::SyncFlush => confirm_offset.min(self.commit_log.get_flushed_where()),
+            _ => confirm_offset,
+        }
+    }
+
     fn get_max_phy_offset(&self) -> i64 {
         self.commit_log.get_max_offset()
     }
@@ -651,85 +1199,152 @@ impl MessageStore for DefaultMessageStore {
     }
 
     async fn put_message(&mut self, msg: MessageExtBrokerInner) -> PutMessageResult {
-        for hook in self.put_message_hook_list.read().iter() {
-            if let Some(result) = hook.execute_before_put_message(&msg.message_ext_inner) {
-                return result;
-            }
+        self.async_put_message(msg).await.await
+    }
+
+    async fn async_put_message(&mut self, mut msg: MessageExtBrokerInner) -> PutMessageFuture {
+        if let Some(result) = self.execute_put_message_hooks(&mut msg) {
+            return result.into();
         }
 
-        if msg
-            .message_ext_inner
-            .properties()
-            .contains_key(MessageConst::PROPERTY_INNER_NUM)
-            && !MessageSysFlag::check(msg.sys_flag(), MessageSysFlag::INNER_BATCH_FLAG)
-        {
-            warn!(
-                "[BUG]The message had property {} but is not an inner batch",
-                MessageConst::PROPERTY_INNER_NUM
-            );
-            return PutMessageResult::new_default(PutMessageStatus::MessageIllegal);
+        // a put stuck in the commit log lock means the page cache is stalled, fail fast
+        // instead of queueing behind it
+        if self.is_os_page_cache_busy() {
+            return PutMessageResult::new_default(PutMessageStatus::OsPageCacheBusy).into();
         }
 
-        if MessageSysFlag::check(msg.sys_flag(), MessageSysFlag::INNER_BATCH_FLAG) {
-            let topic_config = self.get_topic_config(msg.topic());
-            if !QueueTypeUtils::is_batch_cq(&topic_config) {
-                error!("[BUG]The message is an inner batch but cq type is not batch cq");
-                return PutMessageResult::new_default(PutMessageStatus::MessageIllegal);
+        if self.message_store_config.verify_body_crc_on_put {
+            let body = msg
+                .message_ext_inner
+                .message
+                .body
+                .clone()
+                .unwrap_or_default();
+            let check = verify_body_crc(&body, msg.sys_flag(), msg.message_ext_inner.properties());
+            self.body_crc_stats.record(check);
+            if check.is_mismatched() {
+                warn!(
+                    "body crc mismatch on put, topic={}, check={:?}",
+                    msg.topic(),
+                    check
+                );
+                return PutMessageResult::new_default(PutMessageStatus::MessageIllegal).into();
             }
         }
         let begin_time = Instant::now();
+        let topic = msg.topic().clone();
         //put message to commit log
-        let result = self.commit_log.put_message(msg).await;
-        let elapsed_time = begin_time.elapsed().as_millis();
-        if elapsed_time > 500 {
-            warn!(
-                "DefaultMessageStore#putMessage: CommitLog#putMessage cost {}ms",
-                elapsed_time,
-            );
-        }
-        self.store_stats_service
-            .set_put_message_entire_time_max(elapsed_time as u64);
-        if !result.is_ok() {
-            self.store_stats_service
-                .get_message_times_total_found()
-                .fetch_add(1, Ordering::Relaxed);
-        }
-        result
+        let future = self.commit_log.async_put_message(msg).await;
+        let store_stats_service = self.store_stats_service.clone();
+        PutMessageFuture::new(async move {
+            let result = future.await;
+            let elapsed_time = begin_time.elapsed().as_millis();
+            if elapsed_time > 500 {
+                warn!(
+                    "DefaultMessageStore#putMessage: CommitLog#putMessage cost {}ms",
+                    elapsed_time,
+                );
+            }
+            store_stats_service.set_put_message_entire_time_max(elapsed_time as u64);
+            match result.append_message_result() {
+                Some(append_result) if result.is_ok() => store_stats_service.record_put_message(
+                    topic.as_str(),
+                    append_result.msg_num.max(1) as usize,
+                    append_result.wrote_bytes as usize,
+                ),
+                _ => {
+                    store_stats_service
+                        .get_put_message_failed_times()
+                        .fetch_add(1, Ordering::Relaxed);
+                }
+            }
+            result
+        })
     }
 
     async fn put_messages(&mut self, msg_batch: MessageExtBatch) -> PutMessageResult {
+        self.async_put_messages(msg_batch).await.await
+    }
+
+    async fn async_put_messages(&mut self, mut msg_batch: MessageExtBatch) -> PutMessageFuture {
         for hook in self.put_message_hook_list.read().iter() {
-            if let Some(result) = hook
-                .execute_before_put_message(&msg_batch.message_ext_broker_inner.message_ext_inner)
-            {
-                return result;
+            if let Some(result) = hook.execute_before_put_messages(&mut msg_batch) {
+                debug!(
+                    "put messages rejected by hook {}: {:?}",
+                    hook.hook_name(),
+                    result.put_message_status()
+                );
+                return result.into();
             }
         }
 
+        if self.is_os_page_cache_busy() {
+            return PutMessageResult::new_default(PutMessageStatus::OsPageCacheBusy).into();
+        }
+
         let begin_time = Instant::now();
+        let topic = msg_batch.message_ext_broker_inner.topic().clone();
         //put message to commit log
-        let result = self.commit_log.put_messages(msg_batch).await;
-        let elapsed_time = begin_time.elapsed().as_millis();
-        if elapsed_time > 500 {
-            warn!("not in lock eclipse time(ms) {}ms", elapsed_time,);
+        let future = self.commit_log.async_put_messages(msg_batch).await;
+        let store_stats_service = self.store_stats_service.clone();
+        PutMessageFuture::new(async move {
+            let result = future.await;
+            let elapsed_time = begin_time.elapsed().as_millis();
+            if elapsed_time > 500 {
+                warn!("not in lock eclipse time(ms) {}ms", elapsed_time,);
+            }
+            store_stats_service.set_put_message_entire_time_max(elapsed_time as u64);
+            match result.append_message_result() {
+                Some(append_result) if result.is_ok() => store_stats_service.record_put_message(
+                    topic.as_str(),
+                    append_result.msg_num.max(1) as usize,
+                    append_result.wrote_bytes as usize,
+                ),
+                _ => {
+                    store_stats_service
+                        .get_put_message_failed_times()
+                        .fetch_add(1, Ordering::Relaxed);
+                }
+            }
+            result
+        })
+    }
+
+    fn truncate_files(&mut self, offset_to_truncate: i64) -> bool {
+        if offset_to_truncate >= self.commit_log.get_max_offset() {
+            return true;
         }
-        self.store_stats_service
-            .set_put_message_entire_time_max(elapsed_time as u64);
-        if !result.is_ok() {
-            self.store_stats_service
-                .get_message_times_total_found()
-                .fetch_add(1, Ordering::Relaxed);
+        if !self.commit_log.is_offset_aligned(offset_to_truncate) {
+            error!(
+                "offset {} to truncate is not at a message boundary",
+                offset_to_truncate
+            );
+            return false;
         }
-        result
+        self.truncate_dirty_logic_files(offset_to_truncate);
+        self.commit_log.truncate_dirty_files(offset_to_truncate);
+        if let Some(hot_message_cache) = self.hot_message_cache.as_ref() {
+            hot_message_cache.clear();
+        }
+        self.recover_topic_queue_table();
+        info!("truncated commit log to offset {}", offset_to_truncate);
+        true
     }
 
-    fn truncate_files(&mut self, offset_to_truncate: i64) -> bool {
-        unimplemented!()
+    fn read_executor(&self) -> Option<Arc<StoreReadExecutor>> {
+        self.read_executor.clone()
+    }
+
+    fn event_bus(&self) -> Option<Arc<StoreEventBus>> {
+        Some(self.event_bus.clone())
     }
 
     fn is_os_page_cache_busy(&self) -> bool {
-        let begin = self.commit_log.begin_time_in_lock().load(Ordering::Relaxed);
-        let diff = get_current_millis() - begin;
+        let begin = self.commit_log.begin_time_in_lock().load(Ordering::Acquire);
+        if begin == 0 {
+            return false;
+        }
+        let diff = get_current_millis().saturating_sub(begin);
         diff < 10000000 && diff > self.message_store_config.os_page_cache_busy_timeout_mills
     }
 
@@ -755,7 +1370,8 @@ impl MessageStore for DefaultMessageStore {
     }
 
     fn dispatch_behind_bytes(&self) -> i64 {
-        0
+        self.reput_message_service
+            .behind(self.commit_log.get_max_offset())
     }
 
     fn get_min_offset_in_queue(&self, topic: &CheetahString, queue_id: i32) -> i64 {
@@ -774,11 +1390,8 @@ impl MessageStore for DefaultMessageStore {
         committed: bool,
     ) -> i64 {
         if committed {
-            let queue = self
-                .consume_queue_store
-                .find_or_create_consume_queue(topic, queue_id);
-
-            queue.get_max_offset_in_queue()
+            self.consume_queue_store
+                .get_max_offset_in_queue(topic, queue_id)
         } else {
             self.consume_queue_store
                 .get_max_offset(topic, queue_id)
@@ -811,15 +1424,17 @@ impl MessageStore for DefaultMessageStore {
         let topic_config = self.get_topic_config(topic);
         let policy = get_delete_policy(topic_config.as_ref());
         if policy == CleanupPolicy::COMPACTION && self.message_store_config.enable_compaction {
-            //not implemented will be implemented in the future
-            return self.compaction_store.get_message(
+            // queues nothing was compacted into yet are still read from the consume queue
+            if let Some(result) = self.compaction_store.get_message(
                 group,
                 topic,
                 queue_id,
                 offset,
                 max_msg_nums,
                 max_total_msg_size,
-            );
+            ) {
+                return Some(result);
+            }
         }
         let begin_time = Instant::now();
 
@@ -838,6 +1453,23 @@ impl MessageStore for DefaultMessageStore {
                 status = GetMessageStatus::NoMessageInQueue;
                 next_begin_offset = self.next_offset_correction(offset, 0);
             } else if offset < min_offset {
+                // expired locally, the tiered store may still hold it
+                if let Some(mut result) =
+                    self.tiered_message_store
+                        .as_ref()
+                        .and_then(|tiered_message_store| {
+                            tiered_message_store.get_message(
+                                topic,
+                                queue_id,
+                                offset,
+                                max_msg_nums,
+                                max_total_msg_size,
+                            )
+                        })
+                {
+                    result.set_max_offset(max_offset);
+                    return Some(result);
+                }
                 status = GetMessageStatus::OffsetTooSmall;
                 next_begin_offset = self.next_offset_correction(offset, min_offset);
             } else if offset == max_offset {
@@ -863,6 +1495,34 @@ impl MessageStore for DefaultMessageStore {
                 status = GetMessageStatus::NoMatchedMessage;
                 let mut max_phy_offset_pulling = 0;
                 let mut cq_file_num = 0;
+                // consumers right behind the producers are served from memory, a hit skips
+                // the consume queue walk below
+                if let (Some(hot_message_cache), None) =
+                    (self.hot_message_cache.as_ref(), message_filter)
+                {
+                    let get_result_ref = get_result.as_mut().unwrap();
+                    for message in
+                        hot_message_cache.get(topic, queue_id, offset, max_msg_nums, max_pull_size)
+                    {
+                        if message.queue_offset >= max_offset {
+                            break;
+                        }
+                        max_phy_offset_pulling = message.commit_log_offset;
+                        next_begin_offset = message.queue_offset + 1;
+                        get_result_ref.add_message(
+                            SelectMappedBufferResult::from_bytes(
+                                message.commit_log_offset as u64,
+                                message.data,
+                            ),
+                            message.queue_offset as u64,
+                            1,
+                        );
+                        self.store_stats_service
+                            .get_message_transferred_msg_count()
+                            .fetch_add(1, Ordering::Relaxed);
+                        status = GetMessageStatus::Found;
+                    }
+                }
                 while get_result.as_ref().unwrap().buffer_total_size() <= 0
                     && next_begin_offset < max_offset
                     && cq_file_num
@@ -983,6 +1643,9 @@ impl MessageStore for DefaultMessageStore {
                             );
                             status = GetMessageStatus::Found;
                             next_phy_file_start_offset = i64::MIN;
+                        } else {
+                            // the rest of the queue is in the next consume queue file
+                            break;
                         }
                     }
                 }
@@ -1017,6 +1680,8 @@ impl MessageStore for DefaultMessageStore {
                 .fetch_add(1, Ordering::Relaxed);
         }
         let elapsed_time = begin_time.elapsed().as_millis() as u64;
+        self.store_stats_service
+            .set_get_message_entire_time_max(elapsed_time);
         if get_result.is_none() {
             get_result = Some(GetMessageResult::new_result_size(0));
         }
@@ -1064,16 +1729,18 @@ impl MessageStore for DefaultMessageStore {
     }
 
     fn notify_message_arrive_if_necessary(&self, dispatch_request: &mut DispatchRequest) {
-        if self.broker_config.long_polling_enable && self.message_arriving_listener.is_some() {
-            self.message_arriving_listener.as_ref().unwrap().arriving(
-                dispatch_request.topic.as_ref(),
-                dispatch_request.queue_id,
-                dispatch_request.consume_queue_offset + 1,
-                Some(dispatch_request.tags_code),
-                dispatch_request.store_timestamp,
-                dispatch_request.bit_map.clone(),
-                dispatch_request.properties_map.as_ref(),
-            );
+        if self.broker_config.long_polling_enable && !self.message_arriving_listeners.is_empty() {
+            for listener in self.message_arriving_listeners.iter() {
+                listener.arriving(
+                    dispatch_request.topic.as_ref(),
+                    dispatch_request.queue_id,
+                    dispatch_request.consume_queue_offset + 1,
+                    Some(dispatch_request.tags_code),
+                    dispatch_request.store_timestamp,
+                    dispatch_request.bit_map.clone(),
+                    dispatch_request.properties_map.as_ref(),
+                );
+            }
             self.reput_message_service
                 .notify_message_arrive4multi_queue(dispatch_request);
         }
@@ -1086,6 +1753,40 @@ impl MessageStore for DefaultMessageStore {
         )
     }
 
+    fn prefetch_message(
+        &self,
+        topic: &CheetahString,
+        queue_id: i32,
+        offset: i64,
+        max_msg_nums: i32,
+    ) -> usize {
+        let Some(consume_queue) = self.consume_queue_store.find_consume_queue(topic, queue_id)
+        else {
+            return 0;
+        };
+        let Some(first) = consume_queue.get(offset) else {
+            return 0;
+        };
+        let last_offset = (offset + max_msg_nums.max(1) as i64 - 1)
+            .min(consume_queue.get_max_offset_in_queue() - 1)
+            .max(offset);
+        let end = consume_queue
+            .get(last_offset)
+            .map(|last| last.pos + last.size as i64)
+            .filter(|end| *end > first.pos)
+            .unwrap_or(first.pos + first.size as i64);
+        let size = (end - first.pos).min(
+            self.message_store_config
+                .max_transfer_bytes_on_message_in_disk as i64,
+        );
+        self.commit_log.prefetch(first.pos, size)
+    }
+
+    fn execute_delete_files_manually(&self) {
+        self.clean_commit_log_service
+            .execute_delete_files_manually();
+    }
+
     fn delete_topics(&mut self, delete_topics: Vec<&CheetahString>) -> i32 {
         if delete_topics.is_empty() {
             return 0;
@@ -1106,6 +1807,9 @@ impl MessageStore for DefaultMessageStore {
             // remove topic from cq table
             let consume_queue_table = self.consume_queue_store.get_consume_queue_table();
             consume_queue_table.lock().remove(topic);
+            if let Some(hot_message_cache) = self.hot_message_cache.as_ref() {
+                hot_message_cache.remove_topic(topic);
+            }
 
             if self.broker_config.auto_delete_unused_stats {
                 self.broker_stats_manager
@@ -1147,7 +1851,7 @@ impl MessageStore for DefaultMessageStore {
                 key,
                 max_num,
                 begin_timestamp,
-                end_timestamp,
+                last_query_msg_time,
             );
             if query_offset_result.get_phy_offsets().is_empty() {
                 break;
@@ -1160,14 +1864,15 @@ impl MessageStore for DefaultMessageStore {
             query_message_result.index_last_update_phyoffset =
                 query_offset_result.get_index_last_update_phyoffset();
             let phy_offsets = query_offset_result.get_phy_offsets();
-            for m in 0..phy_offsets.len() {
-                let offset = *phy_offsets.get(m).unwrap();
-                let msg = self.look_message_by_offset(offset);
+            for (m, offset) in phy_offsets.iter().enumerate() {
+                // the index outlives expired commit log files
+                let Some(msg) = self.look_message_by_offset(*offset) else {
+                    continue;
+                };
                 if m == 0 {
-                    last_query_msg_time = msg.as_ref().unwrap().store_timestamp;
+                    last_query_msg_time = msg.store_timestamp;
                 }
-                let result = self.commit_log.get_data_with_option(offset, false);
-                if let Some(sbr) = result {
+                if let Some(sbr) = self.commit_log.get_message(*offset, msg.store_size) {
                     query_message_result.add_message(sbr);
                 }
             }
@@ -1234,21 +1939,127 @@ impl MessageStore for DefaultMessageStore {
         queue_id: i32,
         consume_queue_offset: i64,
     ) -> i64 {
-        let consume_queue = self.find_consume_queue(topic, queue_id);
-        if let Some(consume_queue) = consume_queue {
-            if let Some((_, store_time)) =
-                consume_queue.get_cq_unit_and_store_time(consume_queue_offset)
-            {
-                store_time
-            } else {
-                -1
-            }
-        } else {
-            -1
+        let cq_unit = self
+            .find_consume_queue(topic, queue_id)
+            .and_then(|consume_queue| consume_queue.get(consume_queue_offset));
+        match cq_unit {
+            Some(cq_unit) => self
+                .commit_log
+                .pickup_store_timestamp(cq_unit.pos, cq_unit.size),
+            None => -1,
         }
     }
     fn get_runtime_info(&self) -> HashMap<String, String> {
-        self.store_stats_service.get_runtime_info()
+        let mut runtime_info = self.store_stats_service.get_runtime_info();
+        let flush_stall_detector = self.commit_log.flush_stall_detector();
+        runtime_info.insert(
+            "commitLogFlushStalled".to_string(),
+            flush_stall_detector.is_stalled().to_string(),
+        );
+        runtime_info.insert(
+            "commitLogFlushLatencyMs".to_string(),
+            flush_stall_detector.last_latency_ms().to_string(),
+        );
+        runtime_info.insert(
+            "commitLogFlushMaxLatencyMs".to_string(),
+            flush_stall_detector.max_latency_ms().to_string(),
+        );
+        runtime_info.insert(
+            "commitLogFlushModeSwitchTimes".to_string(),
+            flush_stall_detector.mode_switch_times().to_string(),
+        );
+        if let Some(hot_message_cache) = self.hot_message_cache.as_ref() {
+            let stats = hot_message_cache.stats();
+            runtime_info.insert("hotCacheHits".to_string(), stats.hits.to_string());
+            runtime_info.insert("hotCacheMisses".to_string(), stats.misses.to_string());
+            runtime_info.insert(
+                "hotCacheHitRatio".to_string(),
+                format!("{:.4}", stats.hit_ratio()),
+            );
+            runtime_info.insert("hotCacheEvictions".to_string(), stats.evictions.to_string());
+            runtime_info.insert("hotCacheRejected".to_string(), stats.rejected.to_string());
+            runtime_info.insert(
+                "hotCacheMessages".to_string(),
+                stats.cached_messages.to_string(),
+            );
+            runtime_info.insert("hotCacheBytes".to_string(), stats.cached_bytes.to_string());
+        }
+        if let Some(tiered_message_store) = self.tiered_message_store.as_ref() {
+            runtime_info.insert(
+                "tieredMinOffset".to_string(),
+                tiered_message_store.get_min_offset().to_string(),
+            );
+            runtime_info.insert(
+                "tieredMaxOffset".to_string(),
+                tiered_message_store.get_max_offset().to_string(),
+            );
+            runtime_info.insert(
+                "tieredUploadedSegments".to_string(),
+                tiered_message_store.uploaded_segments().to_string(),
+            );
+            runtime_info.insert(
+                "tieredReadMessages".to_string(),
+                tiered_message_store.read_messages().to_string(),
+            );
+        }
+        if let Some(read_executor) = self.read_executor.as_ref() {
+            let read_queue = read_executor.snapshot();
+            runtime_info.insert(
+                "storeReadQueueDepth".to_string(),
+                read_queue.queued.to_string(),
+            );
+            runtime_info.insert(
+                "storeReadRunning".to_string(),
+                read_queue.running.to_string(),
+            );
+            runtime_info.insert(
+                "storeReadQueuePeakDepth".to_string(),
+                read_queue.peak_queued.to_string(),
+            );
+            runtime_info.insert(
+                "storeReadQueueAvgWaitMicros".to_string(),
+                read_queue.avg_queue_wait_micros.to_string(),
+            );
+        }
+        runtime_info.insert(
+            "putBodyCrcVerified".to_string(),
+            self.body_crc_stats.verified().to_string(),
+        );
+        runtime_info.insert(
+            "putBodyCrcMismatched".to_string(),
+            self.body_crc_stats.mismatched().to_string(),
+        );
+        let decode_failure_stats = self.commit_log.decode_failure_stats();
+        for failure in [
+            DecodeFailure::IllegalMagicCode,
+            DecodeFailure::BodyCrcMismatch,
+            DecodeFailure::DupInfoInvalid,
+            DecodeFailure::SizeMismatch,
+        ] {
+            runtime_info.insert(
+                format!("decodeFailure.{}", failure.label()),
+                decode_failure_stats.count(failure).to_string(),
+            );
+        }
+        let recovery = self.commit_log.recovery_progress().snapshot();
+        runtime_info.insert(
+            "recoveryFilesDone".to_string(),
+            recovery.files_done.to_string(),
+        );
+        runtime_info.insert(
+            "recoveryTotalFiles".to_string(),
+            recovery.total_files.to_string(),
+        );
+        runtime_info.insert(
+            "recoveryCurrentOffset".to_string(),
+            recovery.current_offset.to_string(),
+        );
+        runtime_info.insert("recoveryEtaMs".to_string(), recovery.eta_ms.to_string());
+        runtime_info.insert(
+            "recoveryFinished".to_string(),
+            recovery.finished.to_string(),
+        );
+        runtime_info
     }
 
     fn lock_time_mills(&self) -> i64 {
@@ -1283,11 +2094,22 @@ impl MessageStore for DefaultMessageStore {
     }
 }
 
+/// Outcome of an on-demand [`DefaultMessageStore::flush_all`].
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StoreFlushResult {
+    pub commit_log_flushed_offset: i64,
+    pub commit_log_max_offset: i64,
+    pub consume_queues_flushed: usize,
+    pub cost_ms: u64,
+}
+
 #[derive(Clone)]
 pub struct CommitLogDispatcherDefault {
     /*build_index: CommitLogDispatcherBuildIndex,
     build_consume_queue: CommitLogDispatcherBuildConsumeQueue,*/
     dispatcher_vec: Arc<Vec<Box<dyn CommitLogDispatcher>>>,
+    dispatch_rules: Arc<DispatchRules>,
 }
 
 impl CommitLogDispatcher for CommitLogDispatcherDefault {
@@ -1297,6 +2119,7 @@ impl CommitLogDispatcher for CommitLogDispatcherDefault {
         for dispatcher in self.dispatcher_vec.iter() {
             dispatcher.dispatch(dispatch_request);
         }
+        self.dispatch_rules.dispatch_to_handler(dispatch_request);
     }
 }
 #[derive(Clone)]
@@ -1309,57 +2132,8 @@ struct ReputMessageService {
 
 impl ReputMessageService {
     fn notify_message_arrive4multi_queue(&self, dispatch_request: &mut DispatchRequest) {
-        if dispatch_request.properties_map.is_none()
-            || dispatch_request
-                .topic
-                .as_str()
-                .starts_with(RETRY_GROUP_TOPIC_PREFIX)
-        {
-            return;
-        }
-        let prop = dispatch_request.properties_map.as_ref().unwrap();
-        let multi_dispatch_queue = prop.get(MessageConst::PROPERTY_INNER_MULTI_DISPATCH);
-        let multi_queue_offset = prop.get(MessageConst::PROPERTY_INNER_MULTI_QUEUE_OFFSET);
-        if multi_dispatch_queue.is_none()
-            || multi_queue_offset.is_none()
-            || multi_dispatch_queue.as_ref().unwrap().is_empty()
-            || multi_queue_offset.as_ref().unwrap().is_empty()
-        {
-            return;
-        }
-        let queues: Vec<&str> = multi_dispatch_queue
-            .unwrap()
-            .split(MULTI_DISPATCH_QUEUE_SPLITTER)
-            .collect();
-        let queue_offsets: Vec<&str> = multi_dispatch_queue
-            .unwrap()
-            .split(MULTI_DISPATCH_QUEUE_SPLITTER)
-            .collect();
-        if queues.len() != queue_offsets.len() {
-            return;
-        }
-        let reput_message_service_inner = self.inner.as_ref().unwrap();
-        for i in 0..queues.len() {
-            let queue_name = CheetahString::from_slice(queues[i]);
-            let queue_offset: i64 = queue_offsets[i].parse().unwrap();
-            let mut queue_id = dispatch_request.queue_id;
-            if self.message_store_config.enable_lmq && is_lmq(Some(queue_name.as_str())) {
-                queue_id = 0;
-            }
-            reput_message_service_inner
-                .message_store
-                .message_arriving_listener
-                .as_ref()
-                .unwrap()
-                .arriving(
-                    &queue_name,
-                    queue_id,
-                    queue_offset + 1,
-                    Some(dispatch_request.tags_code),
-                    dispatch_request.store_timestamp,
-                    dispatch_request.bit_map.clone(),
-                    dispatch_request.properties_map.as_ref(),
-                );
+        if let Some(inner) = self.inner.as_ref() {
+            inner.notify_message_arrive4multi_queue(dispatch_request);
         }
     }
 
@@ -1367,6 +2141,15 @@ impl ReputMessageService {
         self.reput_from_offset = Some(Arc::new(AtomicI64::new(reput_from_offset)));
     }
 
+    pub fn behind(&self, max_phy_offset: i64) -> i64 {
+        match self.reput_from_offset.as_ref() {
+            Some(reput_from_offset) => {
+                (max_phy_offset - reput_from_offset.load(Ordering::Relaxed)).max(0)
+            }
+            None => 0,
+        }
+    }
+
     pub fn start(
         &mut self,
         commit_log: Arc<CommitLog>,
@@ -1374,6 +2157,7 @@ impl ReputMessageService {
         dispatcher: CommitLogDispatcherDefault,
         notify_message_arrive_in_batch: bool,
         message_store: ArcMut<DefaultMessageStore>,
+        task_spawner: &StoreTaskSpawner,
     ) {
         let mut inner = ReputMessageServiceInner {
             reput_from_offset: self.reput_from_offset.clone().unwrap(),
@@ -1381,12 +2165,14 @@ impl ReputMessageService {
             message_store_config,
             dispatcher,
             notify_message_arrive_in_batch,
+            event_bus: message_store.event_bus.clone(),
+            caught_up: false,
             message_store,
         };
         self.inner = Some(inner.clone());
         let (tx, mut rx) = tokio::sync::mpsc::channel(1);
         self.tx = Some(Arc::new(tx));
-        let handle = tokio::spawn(async move {
+        let handle = task_spawner.spawn("reput", async move {
             let mut interval = tokio::time::interval(Duration::from_millis(1));
             let mut break_flag = false;
             loop {
@@ -1444,9 +2230,14 @@ struct ReputMessageServiceInner {
     message_store_config: Arc<MessageStoreConfig>,
     dispatcher: CommitLogDispatcherDefault,
     notify_message_arrive_in_batch: bool,
+    event_bus: Arc<StoreEventBus>,
+    caught_up: bool,
     message_store: ArcMut<DefaultMessageStore>,
 }
 
+/// Dispatch falling this far behind the confirm offset announces catching up again.
+const DISPATCH_CAUGHT_UP_BACKLOG: i64 = 16 * 1024 * 1024;
+
 impl ReputMessageServiceInner {
     fn notify_message_arrive4multi_queue(&self, dispatch_request: &mut DispatchRequest) {
         let prop = dispatch_request.properties_map.as_ref();
@@ -1481,11 +2272,8 @@ impl ReputMessageServiceInner {
             if self.message_store_config.enable_lmq && is_lmq(Some(queue_name.as_str())) {
                 queue_id = 0;
             }
-            self.message_store
-                .message_arriving_listener
-                .as_ref()
-                .unwrap()
-                .arriving(
+            for listener in self.message_store.message_arriving_listeners.iter() {
+                listener.arriving(
                     &queue_name,
                     queue_id,
                     queue_offset + 1,
@@ -1494,6 +2282,7 @@ impl ReputMessageServiceInner {
                     dispatch_request.bit_map.clone(),
                     dispatch_request.properties_map.as_ref(),
                 );
+            }
         }
     }
 
@@ -1509,6 +2298,21 @@ impl ReputMessageServiceInner {
             self.reput_from_offset
                 .store(self.commit_log.get_min_offset(), Ordering::Release);
         }
+        if reput_from_offset > self.commit_log.get_max_offset() {
+            // the commit log was truncated, e.g. by a slave that diverged from its master
+            warn!(
+                "The reputFromOffset={} is larger than maxPhyOffset={}, dispatch again from there",
+                reput_from_offset,
+                self.commit_log.get_max_offset()
+            );
+            self.reput_from_offset
+                .store(self.commit_log.get_max_offset(), Ordering::Release);
+        }
+        // combined consume queue units only become visible once flushed, so arrivals are
+        // announced after the units of the whole chunk have been written
+        let write_combine = self.message_store_config.consume_queue_write_buffer_units > 0;
+        let defer_notify = write_combine || self.notify_message_arrive_in_batch;
+        let mut deferred_notify = Vec::new();
         let mut do_next = true;
         while do_next && self.is_commit_log_available() {
             let result = self
@@ -1537,13 +2341,21 @@ impl ReputMessageServiceInner {
                     do_next = false;
                     break;
                 }
-
+                let message_bytes = self
+                    .message_store
+                    .hot_message_cache
+                    .is_some()
+                    .then(|| bytes.clone())
+                    .flatten();
+
+                // only the properties CRC is verified here, the body is not read
                 let mut dispatch_request = commit_log::check_message_and_return_size(
                     bytes.as_mut().unwrap(),
-                    false,
+                    self.message_store_config.force_verify_prop_crc,
                     false,
                     false,
                     &self.message_store_config,
+                    self.commit_log.decode_failure_stats(),
                 );
                 if self.reput_from_offset.load(Ordering::Acquire) + dispatch_request.msg_size as i64
                     > self.commit_log.get_confirm_offset()
@@ -1555,18 +2367,39 @@ impl ReputMessageServiceInner {
                     match dispatch_request.msg_size.cmp(&0) {
                         std::cmp::Ordering::Greater => {
                             self.dispatcher.dispatch(&dispatch_request);
-                            if !self.notify_message_arrive_in_batch {
-                                self.message_store
-                                    .notify_message_arrive_if_necessary(&mut dispatch_request);
+                            if let (Some(hot_message_cache), Some(message_bytes)) =
+                                (self.message_store.hot_message_cache.as_ref(), message_bytes)
+                            {
+                                if is_hot_cacheable(&dispatch_request) {
+                                    hot_message_cache.put(
+                                        &dispatch_request.topic,
+                                        dispatch_request.queue_id,
+                                        dispatch_request.consume_queue_offset,
+                                        dispatch_request.commit_log_offset,
+                                        message_bytes,
+                                    );
+                                }
                             }
-                            self.reput_from_offset
-                                .fetch_add(dispatch_request.msg_size as i64, Ordering::AcqRel);
-                            read_size += dispatch_request.msg_size;
+                            let msg_size = dispatch_request.msg_size;
+                            // a slave never sees the puts, count the replicated messages instead
                             if !self.message_store_config.duplication_enable
                                 && self.message_store_config.broker_role == BrokerRole::Slave
                             {
-                                unimplemented!()
+                                self.message_store.store_stats_service.record_put_message(
+                                    dispatch_request.topic.as_str(),
+                                    dispatch_request.batch_size.max(1) as usize,
+                                    msg_size as usize,
+                                );
                             }
+                            if defer_notify {
+                                deferred_notify.push(dispatch_request);
+                            } else {
+                                self.message_store
+                                    .notify_message_arrive_if_necessary(&mut dispatch_request);
+                            }
+                            self.reput_from_offset
+                                .fetch_add(msg_size as i64, Ordering::AcqRel);
+                            read_size += msg_size;
                         }
                         std::cmp::Ordering::Equal => {
                             self.reput_from_offset.store(
@@ -1587,8 +2420,17 @@ impl ReputMessageServiceInner {
                         .fetch_add(dispatch_request.msg_size as i64, Ordering::SeqCst);
                 } else {
                     do_next = false;
-                    if self.message_store_config.enable_dledger_commit_log {
-                        unimplemented!()
+                    // a master must not get stuck on a corrupted message, skip the rest of the
+                    // selected range so dispatch goes on
+                    if self.message_store_config.enable_dledger_commit_log
+                        || self.message_store.broker_config.broker_identity.broker_id == MASTER_ID
+                    {
+                        error!(
+                            "[BUG]dispatch message to consume queue error, COMMITLOG OFFSET: {}",
+                            self.reput_from_offset.load(Ordering::Relaxed)
+                        );
+                        self.reput_from_offset
+                            .fetch_add((result.size - read_size) as i64, Ordering::SeqCst);
                     }
                 }
 
@@ -1600,6 +2442,30 @@ impl ReputMessageServiceInner {
                     break;
                 }
             }
+            if write_combine {
+                self.message_store.consume_queue_store.flush_write_buffers();
+            }
+            if defer_notify {
+                for mut dispatch_request in deferred_notify.drain(..) {
+                    self.message_store
+                        .notify_message_arrive_if_necessary(&mut dispatch_request);
+                }
+            }
+        }
+        self.publish_caught_up();
+    }
+
+    fn publish_caught_up(&mut self) {
+        let reput_offset = self.reput_from_offset.load(Ordering::Acquire);
+        let backlog = self.commit_log.get_confirm_offset() - reput_offset;
+        if backlog <= 0 {
+            if !self.caught_up {
+                self.caught_up = true;
+                self.event_bus
+                    .publish(StoreEvent::DispatchCaughtUp { reput_offset });
+            }
+        } else if backlog > DISPATCH_CAUGHT_UP_BACKLOG {
+            self.caught_up = false;
         }
     }
 
@@ -1617,19 +2483,251 @@ impl ReputMessageServiceInner {
     }
 }
 
-struct CleanCommitLogService {}
+const MAX_MANUAL_DELETE_FILE_TIMES: i32 = 20;
+
+/// Reclaims commit log files older than `file_reserved_time` hours, either inside the
+/// `delete_when` window, as soon as the disk usage crosses the configured watermarks or after a
+/// manual trigger. Above the warning level the disk is marked full so puts are refused until
+/// enough space is reclaimed.
+struct CleanCommitLogService {
+    message_store_config: Arc<MessageStoreConfig>,
+    running_flags: Arc<RunningFlags>,
+    last_run_timestamp: AtomicI64,
+    last_redelete_timestamp: AtomicI64,
+    manual_delete_file_several_times: AtomicI32,
+    clean_immediately: AtomicBool,
+    ingest_rate: IngestRateTracker,
+    event_bus: Arc<StoreEventBus>,
+}
 
 impl CleanCommitLogService {
-    fn run(&self) {
-        info!("clean commit log service run unimplemented!")
+    fn new(
+        message_store_config: Arc<MessageStoreConfig>,
+        running_flags: Arc<RunningFlags>,
+        event_bus: Arc<StoreEventBus>,
+    ) -> Self {
+        Self {
+            message_store_config,
+            running_flags,
+            event_bus,
+            last_run_timestamp: AtomicI64::new(0),
+            last_redelete_timestamp: AtomicI64::new(0),
+            manual_delete_file_several_times: AtomicI32::new(0),
+            clean_immediately: AtomicBool::new(false),
+            ingest_rate: IngestRateTracker::default(),
+        }
+    }
+
+    async fn run(&self, commit_log: &mut CommitLog) {
+        let now = get_current_millis() as i64;
+        self.last_run_timestamp.store(now, Ordering::Relaxed);
+        self.ingest_rate.record(now, commit_log.get_max_offset());
+        self.delete_expired_files(commit_log).await;
+        self.redelete_hanged_file(commit_log, now);
+    }
+
+    fn execute_delete_files_manually(&self) {
+        self.manual_delete_file_several_times
+            .store(MAX_MANUAL_DELETE_FILE_TIMES, Ordering::Release);
+        info!("executeDeleteFilesManually was invoked");
+    }
+
+    async fn delete_expired_files(&self, commit_log: &mut CommitLog) {
+        let config = &self.message_store_config;
+        let is_time_up = self.is_time_to_delete();
+        let is_usage_exceeds_threshold = self.is_space_to_delete();
+        let manual_times = self
+            .manual_delete_file_several_times
+            .load(Ordering::Acquire);
+        let is_manual_delete = manual_times > 0;
+        if !is_time_up && !is_usage_exceeds_threshold && !is_manual_delete {
+            return;
+        }
+        if is_manual_delete {
+            self.manual_delete_file_several_times
+                .fetch_sub(1, Ordering::AcqRel);
+        }
+        let clean_at_once =
+            config.clean_file_forcibly_enable && self.clean_immediately.load(Ordering::Acquire);
+        info!(
+            "begin to delete before {} hours file. isTimeUp: {} isUsageExceedsThreshold: {} \
+             manualDeleteFileSeveralTimes: {} cleanAtOnce: {} deleteFileBatchMax: {}",
+            config.file_reserved_time,
+            is_time_up,
+            is_usage_exceeds_threshold,
+            manual_times,
+            clean_at_once,
+            config.delete_file_batch_max
+        );
+        let delete_count = commit_log
+            .delete_expired_file(
+                config.file_reserved_time as i64 * 60 * 60 * 1000,
+                config.delete_commit_log_files_interval as u64,
+                config.destroy_mapped_file_interval_forcibly as i64,
+                clean_at_once,
+                config.delete_file_batch_max,
+            )
+            .await;
+        if delete_count > 0 {
+            self.event_bus.publish(StoreEvent::FileDeleted {
+                kind: StoreFileKind::CommitLog,
+                deleted_files: delete_count,
+                min_offset: commit_log.get_min_offset(),
+            });
+        } else if is_usage_exceeds_threshold {
+            warn!("disk space will be full soon, but delete file failed.");
+        }
+    }
+
+    /// A file whose destroy failed because it was still referenced stays first in the queue,
+    /// retry it once in a while so it is eventually removed forcibly.
+    fn redelete_hanged_file(&self, commit_log: &mut CommitLog, now: i64) {
+        let interval = self.message_store_config.redelete_hanged_file_interval as i64;
+        if now - self.last_redelete_timestamp.load(Ordering::Relaxed) > interval {
+            self.last_redelete_timestamp.store(now, Ordering::Relaxed);
+            commit_log.retry_delete_first_file(
+                self.message_store_config
+                    .destroy_mapped_file_interval_forcibly as i64,
+            );
+        }
+    }
+
+    fn is_time_to_delete(&self) -> bool {
+        let when = self.message_store_config.delete_when.as_str();
+        if util_all::is_it_time_to_do(when) {
+            info!("it's time to reclaim disk space, {}", when);
+            return true;
+        }
+        false
+    }
+
+    fn is_space_to_delete(&self) -> bool {
+        self.clean_immediately.store(false, Ordering::Release);
+        let config = &self.message_store_config;
+        let warning_ratio = disk_space_warning_level_ratio(config);
+        let clean_forcibly_ratio = disk_space_clean_forcibly_ratio(config);
+        let disks = Disks::new_with_refreshed_list();
+
+        let commit_log_paths = DefaultMessageStore::get_store_path_physic(config);
+        let (min_store_path, min_physic_ratio) = commit_log_paths
+            .trim()
+            .split(MULTI_PATH_SPLITTER.as_str())
+            .map(|path| (path, disk_used_ratio(path, &disks)))
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .unwrap_or(("", -1.0));
+        if min_physic_ratio > warning_ratio {
+            if !self.running_flags.is_disk_full() {
+                error!(
+                    "physic disk maybe full soon {:.2}, so mark disk full, storePathPhysic={}",
+                    min_physic_ratio, min_store_path
+                );
+                self.publish_disk_warning(StoreFileKind::CommitLog, min_physic_ratio, true);
+            }
+            self.running_flags.get_and_make_disk_full();
+            self.clean_immediately.store(true, Ordering::Release);
+            return true;
+        } else if min_physic_ratio > clean_forcibly_ratio {
+            self.clean_immediately.store(true, Ordering::Release);
+            return true;
+        } else if self.running_flags.is_disk_full() {
+            self.running_flags.get_and_make_disk_ok();
+            info!(
+                "physic disk space OK {:.2}, so mark disk ok, storePathPhysic={}",
+                min_physic_ratio, min_store_path
+            );
+            self.publish_disk_warning(StoreFileKind::CommitLog, min_physic_ratio, false);
+        }
+
+        let logics_ratio = disk_used_ratio(
+            DefaultMessageStore::get_store_path_logic(config).as_str(),
+            &disks,
+        );
+        if logics_ratio > warning_ratio {
+            if !self.running_flags.is_logic_disk_full() {
+                error!(
+                    "logics disk maybe full soon {:.2}, so mark disk full",
+                    logics_ratio
+                );
+                self.publish_disk_warning(StoreFileKind::ConsumeQueue, logics_ratio, true);
+            }
+            self.running_flags.get_and_make_logic_disk_full();
+            self.clean_immediately.store(true, Ordering::Release);
+            return true;
+        } else if logics_ratio > clean_forcibly_ratio {
+            self.clean_immediately.store(true, Ordering::Release);
+            return true;
+        } else if self.running_flags.is_logic_disk_full() {
+            self.running_flags.get_and_make_logic_disk_ok();
+            info!("logics disk space OK {:.2}, so mark disk ok", logics_ratio);
+            self.publish_disk_warning(StoreFileKind::ConsumeQueue, logics_ratio, false);
+        }
+
+        let max_used_ratio = config.disk_max_used_space_ratio as f64 / 100.0;
+        if min_physic_ratio < 0.0 || min_physic_ratio > max_used_ratio {
+            info!(
+                "commitLog disk maybe full soon, so reclaim space, {:.2}",
+                min_physic_ratio
+            );
+            return true;
+        }
+        if logics_ratio < 0.0 || logics_ratio > max_used_ratio {
+            info!(
+                "consumeQueue disk maybe full soon, so reclaim space, {:.2}",
+                logics_ratio
+            );
+            return true;
+        }
+        false
+    }
+
+    fn publish_disk_warning(&self, kind: StoreFileKind, used_ratio: f64, disk_full: bool) {
+        self.event_bus.publish(StoreEvent::DiskWarning {
+            kind,
+            used_ratio,
+            disk_full,
+        });
+    }
+
+    fn last_run_timestamp(&self) -> i64 {
+        self.last_run_timestamp.load(Ordering::Relaxed)
     }
 }
 
+/// Whether a dispatched message is served by pulls one queue offset at a time, the only
+/// layout the hot message cache keeps.
+fn is_hot_cacheable(dispatch_request: &DispatchRequest) -> bool {
+    let tran_type = MessageSysFlag::get_transaction_value(dispatch_request.sys_flag);
+    dispatch_request.batch_size <= 1
+        && (tran_type == MessageSysFlag::TRANSACTION_NOT_TYPE
+            || tran_type == MessageSysFlag::TRANSACTION_COMMIT_TYPE)
+}
+
+/// Used ratio of the partition holding `path`, `-1.0` when it can not be measured.
+fn disk_used_ratio(path: &str, disks: &Disks) -> f64 {
+    let usage = StorePathUsage::of("", path, disks);
+    if usage.total_space == 0 {
+        return -1.0;
+    }
+    usage.used_ratio
+}
+
+fn disk_space_warning_level_ratio(config: &MessageStoreConfig) -> f64 {
+    (config.disk_space_warning_level_ratio as f64 / 100.0).clamp(0.35, 0.90)
+}
+
+fn disk_space_clean_forcibly_ratio(config: &MessageStoreConfig) -> f64 {
+    (config.disk_space_clean_forcibly_ratio as f64 / 100.0).clamp(0.30, 0.85)
+}
+
 struct CleanConsumeQueueService {}
 
 impl CleanConsumeQueueService {
-    fn run(&self) {
-        println!("clean consume queue service run unimplemented!")
+    /// Drops consume queues whose last entry points below the commit log minimum and
+    /// removes the directories of queues that never received a message, so idle queues
+    /// cost neither inodes nor mappings.
+    fn run(&self, commit_log: &CommitLog, consume_queue_store: &ConsumeQueueStore) {
+        let min_offset = commit_log.get_min_offset();
+        consume_queue_store.clean_expired(min_offset);
     }
 }
 
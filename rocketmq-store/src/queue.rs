@@ -33,7 +33,9 @@ use crate::queue::queue_offset_operator::QueueOffsetOperator;
 mod batch_consume_queue;
 pub mod build_consume_queue;
 mod consume_queue_ext;
+pub mod consume_queue_write_buffer;
 pub mod local_file_consume_queue_store;
+pub mod multi_dispatch;
 mod queue_offset_operator;
 pub mod single_consume_queue;
 
@@ -188,11 +188,16 @@ impl Swappable for BatchConsumeQueue {
         force_swap_interval_ms: i64,
         normal_swap_interval_ms: i64,
     ) {
-        todo!()
+        self.mapped_file_queue.swap_map(
+            reserve_num,
+            force_swap_interval_ms,
+            normal_swap_interval_ms,
+        );
     }
 
     fn clean_swapped_map(&self, force_clean_swap_interval_ms: i64) {
-        todo!()
+        self.mapped_file_queue
+            .clean_swapped_map(force_clean_swap_interval_ms);
     }
 }
 
@@ -14,28 +14,40 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
+use std::sync::Arc;
+
 use rocketmq_common::common::sys_flag::message_sys_flag::MessageSysFlag;
 
 use crate::base::commit_log_dispatcher::CommitLogDispatcher;
 use crate::base::dispatch_request::DispatchRequest;
+use crate::base::dispatch_rule::DispatchRules;
 use crate::queue::local_file_consume_queue_store::ConsumeQueueStore;
 use crate::queue::ConsumeQueueStoreTrait;
 
 #[derive(Clone)]
 pub struct CommitLogDispatcherBuildConsumeQueue {
     consume_queue_store: ConsumeQueueStore,
+    dispatch_rules: Arc<DispatchRules>,
 }
 
 impl CommitLogDispatcherBuildConsumeQueue {
-    pub fn new(consume_queue_store: ConsumeQueueStore) -> Self {
+    pub fn new(consume_queue_store: ConsumeQueueStore, dispatch_rules: Arc<DispatchRules>) -> Self {
         Self {
             consume_queue_store,
+            dispatch_rules,
         }
     }
 }
 
 impl CommitLogDispatcher for CommitLogDispatcherBuildConsumeQueue {
     fn dispatch(&self, dispatch_request: &DispatchRequest) {
+        if !self
+            .dispatch_rules
+            .route(dispatch_request.topic.as_str())
+            .consume_queue
+        {
+            return;
+        }
         let tran_type = MessageSysFlag::get_transaction_value(dispatch_request.sys_flag);
         match tran_type {
             MessageSysFlag::TRANSACTION_NOT_TYPE | MessageSysFlag::TRANSACTION_COMMIT_TYPE => {
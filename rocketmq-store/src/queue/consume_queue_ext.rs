@@ -17,10 +17,15 @@
 use std::path::PathBuf;
 
 use cheetah_string::CheetahString;
+use tracing::error;
 use tracing::info;
+use tracing::warn;
 
 use crate::consume_queue::consume_queue_ext::CqExtUnit;
+use crate::consume_queue::consume_queue_ext::MAX_EXT_UNIT_SIZE;
 use crate::consume_queue::mapped_file_queue::MappedFileQueue;
+use crate::log_file::mapped_file::default_mapped_file_impl::DefaultMappedFile;
+use crate::log_file::mapped_file::MappedFile;
 
 const END_BLANK_DATA_LENGTH: usize = 4;
 
@@ -28,6 +33,15 @@ const END_BLANK_DATA_LENGTH: usize = 4;
 const MAX_ADDR: i64 = i32::MIN as i64 - 1;
 const MAX_REAL_OFFSET: i64 = MAX_ADDR - i64::MIN;
 
+/// Returned by [`ConsumeQueueExt::put`] on failure, it is not an ext address so the caller
+/// keeps the plain tags code.
+const PUT_FAILED: i64 = 1;
+
+/// Extension file of a consume queue storing the units too large for the fixed 20 byte
+/// entry, such as the filter bitmap and the message store time.
+///
+/// The entry references its unit through the `tags_code` field: ext addresses are the real
+/// offset in the extension file shifted by `i64::MIN`, so they never collide with a tags hash.
 #[derive(Clone)]
 pub struct ConsumeQueueExt {
     mapped_file_queue: MappedFileQueue,
@@ -65,12 +79,74 @@ impl ConsumeQueueExt {
     pub fn is_ext_addr(address: i64) -> bool {
         address <= MAX_ADDR
     }
+
+    /// Turns an ext address back into the real offset in the extension file.
+    pub fn un_decorate(address: i64) -> i64 {
+        if Self::is_ext_addr(address) {
+            return address.wrapping_sub(i64::MIN);
+        }
+        address
+    }
+
+    /// Turns a real offset in the extension file into an ext address.
+    pub fn decorate(offset: i64) -> i64 {
+        if !Self::is_ext_addr(offset) {
+            return offset.wrapping_add(i64::MIN);
+        }
+        offset
+    }
 }
 
 impl ConsumeQueueExt {
-    pub fn truncate_by_max_address(&self, max_address: i64) {}
+    /// Drops everything written after the unit at `max_address`.
+    pub fn truncate_by_max_address(&mut self, max_address: i64) {
+        if !Self::is_ext_addr(max_address) {
+            return;
+        }
+        info!("Truncate consume queue ext by max {}.", max_address);
+        let mut cq_ext_unit = CqExtUnit::default();
+        if !self.get(max_address, &mut cq_ext_unit) {
+            error!(
+                "[BUG] address {} of consume queue extend not found!",
+                max_address
+            );
+            return;
+        }
+        let real_offset = Self::un_decorate(max_address);
+        self.mapped_file_queue
+            .truncate_dirty_files(real_offset + cq_ext_unit.size() as i64);
+    }
 
-    pub fn truncate_by_min_address(&self, min_address: i64) {}
+    /// Destroys the files holding only units before `min_address`.
+    pub fn truncate_by_min_address(&self, min_address: i64) {
+        if !Self::is_ext_addr(min_address) {
+            return;
+        }
+        info!("Truncate consume queue ext by min {}.", min_address);
+        let real_offset = Self::un_decorate(min_address);
+        let mapped_files = self.mapped_file_queue.get_mapped_files();
+        let mut will_remove_files = Vec::new();
+        for file in mapped_files.read().iter() {
+            let file_tail_offset =
+                file.get_file_from_offset() as i64 + self.mapped_file_size as i64;
+            if file_tail_offset < real_offset {
+                info!(
+                    "Destroy consume queue ext by min: file={}, fileTailOffset={}, minOffset={}",
+                    file.get_file_name(),
+                    file_tail_offset,
+                    real_offset
+                );
+                if file.destroy(1000) {
+                    will_remove_files.push(file.clone());
+                }
+            }
+        }
+        if !will_remove_files.is_empty() {
+            mapped_files
+                .write()
+                .retain(|file| !will_remove_files.contains(file));
+        }
+    }
 
     pub fn load(&mut self) -> bool {
         let result = self.mapped_file_queue.load();
@@ -84,17 +160,214 @@ impl ConsumeQueueExt {
         result
     }
 
-    pub fn recover(&mut self) {}
+    /// Walks the units of every file to find the end of the written data, the consume queue
+    /// truncates the tail afterwards by the last ext address it references.
+    pub fn recover(&mut self) {
+        let mapped_files = self.mapped_file_queue.get_mapped_files().read().clone();
+        let Some(mut mapped_file) = mapped_files.first().cloned() else {
+            return;
+        };
+        let mut index = 0;
+        let mut process_offset = mapped_file.get_file_from_offset() as i64;
+        let mut mapped_file_offset = 0usize;
+        loop {
+            let buffer = mapped_file.get_mapped_file();
+            let size = if mapped_file_offset + 2 <= buffer.len() {
+                i16::from_be_bytes([buffer[mapped_file_offset], buffer[mapped_file_offset + 1]])
+            } else {
+                0
+            };
+            if size > 0 {
+                mapped_file_offset += size as usize;
+                continue;
+            }
+            index += 1;
+            if index < mapped_files.len() {
+                mapped_file = mapped_files[index].clone();
+                process_offset = mapped_file.get_file_from_offset() as i64;
+                mapped_file_offset = 0;
+                info!(
+                    "Recover next consume queue extend file, {}",
+                    mapped_file.get_file_name()
+                );
+                continue;
+            }
+            info!(
+                "All files of consume queue extend has been recovered over, last mapped file {}",
+                mapped_file.get_file_name()
+            );
+            break;
+        }
+        process_offset += mapped_file_offset as i64;
+        self.mapped_file_queue.set_flushed_where(process_offset);
+        self.mapped_file_queue.set_committed_where(process_offset);
+        self.mapped_file_queue.truncate_dirty_files(process_offset);
+    }
+
+    /// Appends `cq_ext_unit` and returns its ext address, or a non ext address on failure.
+    pub fn put(&mut self, mut cq_ext_unit: CqExtUnit) -> i64 {
+        const RETRY_TIMES: usize = 3;
+        let size = cq_ext_unit.calc_unit_size();
+        if size > MAX_EXT_UNIT_SIZE as i32 {
+            error!(
+                "Size of cq ext unit is greater than {}, {}",
+                MAX_EXT_UNIT_SIZE, size
+            );
+            return PUT_FAILED;
+        }
+        if self.mapped_file_queue.get_max_offset() + size as i64 > MAX_REAL_OFFSET {
+            warn!("Capacity of ext is maximum!{}, {}", MAX_REAL_OFFSET, size);
+            return PUT_FAILED;
+        }
+        let data = cq_ext_unit.write();
+        for _ in 0..RETRY_TIMES {
+            let Some(mapped_file) = self
+                .mapped_file_queue
+                .get_last_mapped_file_mut_start_offset(0, true)
+            else {
+                error!(
+                    "Create mapped file when save consume queue extend failed, {}-{}",
+                    self.topic, self.queue_id
+                );
+                continue;
+            };
+            let wrote_position = mapped_file.get_wrote_position();
+            let blank_size = self.mapped_file_size - wrote_position - END_BLANK_DATA_LENGTH as i32;
+            // check whether has enough space.
+            if size > blank_size {
+                self.full_fill_to_end(mapped_file.as_ref(), wrote_position);
+                info!(
+                    "No enough space(need:{}, has:{}) of file {}, so fill to end",
+                    size,
+                    blank_size,
+                    mapped_file.get_file_name()
+                );
+                continue;
+            }
+            if mapped_file.append_message_bytes(&data) {
+                return Self::decorate(
+                    wrote_position as i64 + mapped_file.get_file_from_offset() as i64,
+                );
+            }
+        }
+        PUT_FAILED
+    }
+
+    /// Marks the rest of the file as unused so readers and recovery move to the next file.
+    fn full_fill_to_end(&self, mapped_file: &DefaultMappedFile, wrote_position: i32) {
+        let position = wrote_position as usize;
+        if let Some(ending) = mapped_file
+            .get_mapped_file_mut()
+            .get_mut(position..position + 2)
+        {
+            ending.copy_from_slice(&(-1i16).to_be_bytes());
+        }
+        mapped_file.set_wrote_position(self.mapped_file_size);
+    }
 
-    pub fn put(&self, cq_ext_unit: CqExtUnit) -> i64 {
-        unimplemented!()
+    pub fn flush(&self, flush_least_pages: i32) -> bool {
+        self.mapped_file_queue.flush(flush_least_pages)
     }
 
     pub fn destroy(&mut self) {
         self.mapped_file_queue.destroy();
     }
 
-    pub fn get(&self, address: i64, cq_ext_unit: &CqExtUnit) -> bool {
-        unimplemented!()
+    /// Reads the unit at ext address `address` into `cq_ext_unit`.
+    pub fn get(&self, address: i64, cq_ext_unit: &mut CqExtUnit) -> bool {
+        if !Self::is_ext_addr(address) {
+            return false;
+        }
+        let real_offset = Self::un_decorate(address);
+        let Some(mapped_file) = self
+            .mapped_file_queue
+            .find_mapped_file_by_offset(real_offset, real_offset == 0)
+        else {
+            return false;
+        };
+        let pos = (real_offset % self.mapped_file_size as i64) as usize;
+        let read_position = mapped_file.get_read_position() as usize;
+        if pos >= read_position {
+            warn!(
+                "Fail to read ext from file {}, position {}",
+                mapped_file.get_file_name(),
+                pos
+            );
+            return false;
+        }
+        cq_ext_unit.read(&mapped_file.get_mapped_file()[pos..read_position])
+    }
+
+    pub fn get_max_address(&self) -> i64 {
+        match self.mapped_file_queue.get_last_mapped_file() {
+            None => Self::decorate(0),
+            Some(mapped_file) => Self::decorate(
+                mapped_file.get_file_from_offset() as i64 + mapped_file.get_wrote_position() as i64,
+            ),
+        }
+    }
+
+    pub fn get_min_address(&self) -> i64 {
+        match self.mapped_file_queue.get_first_mapped_file() {
+            None => Self::decorate(0),
+            Some(mapped_file) => Self::decorate(mapped_file.get_file_from_offset() as i64),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_ext(store_path: &str) -> ConsumeQueueExt {
+        ConsumeQueueExt::new(
+            CheetahString::from_static_str("TopicTest"),
+            0,
+            CheetahString::from_string(store_path.to_string()),
+            64,
+            64,
+        )
+    }
+
+    #[test]
+    fn addresses_never_collide_with_tags_codes() {
+        assert!(ConsumeQueueExt::is_ext_addr(ConsumeQueueExt::decorate(0)));
+        assert!(!ConsumeQueueExt::is_ext_addr(i32::MIN as i64));
+        let address = ConsumeQueueExt::decorate(12345);
+        assert_eq!(ConsumeQueueExt::un_decorate(address), 12345);
+    }
+
+    #[test]
+    fn put_get_and_recover() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let store_path = temp_dir.path().to_string_lossy().into_owned();
+        let mut ext = new_ext(&store_path);
+
+        let first = ext.put(CqExtUnit::new(7, 1000, Some(vec![1, 2, 3, 4])));
+        let second = ext.put(CqExtUnit::new(8, 2000, None));
+        assert!(ConsumeQueueExt::is_ext_addr(first));
+        // the second unit does not fit in the rest of the first file
+        let third = ext.put(CqExtUnit::new(9, 3000, Some(vec![5; 16])));
+        assert_eq!(ConsumeQueueExt::un_decorate(third), 64);
+
+        let mut unit = CqExtUnit::default();
+        assert!(ext.get(first, &mut unit));
+        assert_eq!(unit.tags_code(), 7);
+        assert_eq!(unit.filter_bit_map(), &Some(vec![1, 2, 3, 4]));
+        assert!(ext.get(second, &mut unit));
+        assert_eq!(unit.msg_store_time(), 2000);
+        assert_eq!(unit.filter_bit_map(), &None);
+        assert!(!ext.get(7, &mut unit));
+
+        let mut recovered = new_ext(&store_path);
+        assert!(recovered.load());
+        recovered.recover();
+        assert!(recovered.get(third, &mut unit));
+        assert_eq!(unit.tags_code(), 9);
+        assert_eq!(recovered.get_max_address(), ext.get_max_address());
+
+        recovered.truncate_by_max_address(second);
+        assert!(!recovered.get(third, &mut unit));
+        assert!(recovered.get(second, &mut unit));
     }
 }
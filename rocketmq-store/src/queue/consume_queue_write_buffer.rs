@@ -0,0 +1,240 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use bytes::BytesMut;
+use tracing::error;
+
+use crate::log_file::mapped_file::default_mapped_file_impl::DefaultMappedFile;
+use crate::log_file::mapped_file::MappedFile;
+
+/// Buffers shared by the write buffers of all consume queues. A queue only holds a buffer while
+/// it has pending units, so idle queues cost no memory, and queues keep writing their units
+/// directly once the pool is exhausted.
+pub struct ConsumeQueueBufferPool {
+    buffer_size: usize,
+    max_buffers: usize,
+    borrowed: AtomicUsize,
+    free: parking_lot::Mutex<Vec<BytesMut>>,
+    dirty: parking_lot::Mutex<Vec<Arc<ConsumeQueueWriteBuffer>>>,
+}
+
+impl ConsumeQueueBufferPool {
+    pub fn new(buffer_size: usize, max_buffers: usize) -> Self {
+        Self {
+            buffer_size,
+            max_buffers,
+            borrowed: AtomicUsize::new(0),
+            free: parking_lot::Mutex::new(Vec::new()),
+            dirty: parking_lot::Mutex::new(Vec::new()),
+        }
+    }
+
+    fn borrow_buffer(&self) -> Option<BytesMut> {
+        let borrowed = self.borrowed.fetch_add(1, Ordering::AcqRel);
+        if borrowed >= self.max_buffers {
+            self.borrowed.fetch_sub(1, Ordering::AcqRel);
+            return None;
+        }
+        Some(
+            self.free
+                .lock()
+                .pop()
+                .unwrap_or_else(|| BytesMut::with_capacity(self.buffer_size)),
+        )
+    }
+
+    fn return_buffer(&self, mut buffer: BytesMut) {
+        buffer.clear();
+        self.free.lock().push(buffer);
+        self.borrowed.fetch_sub(1, Ordering::AcqRel);
+    }
+
+    /// Appends the pending units of every queue to their mapped files, called once a batch of
+    /// commit log data has been dispatched so the units become visible to readers together.
+    pub fn flush_dirty(&self) -> usize {
+        let dirty = std::mem::take(&mut *self.dirty.lock());
+        let count = dirty.len();
+        for write_buffer in dirty {
+            write_buffer.flush();
+        }
+        count
+    }
+
+    pub fn borrowed_buffers(&self) -> usize {
+        self.borrowed.load(Ordering::Acquire)
+    }
+}
+
+struct PendingBatch {
+    mapped_file: Arc<DefaultMappedFile>,
+    next_logic_offset: i64,
+    file_end_offset: i64,
+    buffer: BytesMut,
+}
+
+/// Write-combining buffer of one consume queue. Units are 20 bytes, so instead of dirtying the
+/// mapped file once per message they are collected and appended in one chunk, bounded by the
+/// pool buffer size and the end of the current mapped file.
+pub struct ConsumeQueueWriteBuffer {
+    pool: Arc<ConsumeQueueBufferPool>,
+    pending: parking_lot::Mutex<Option<PendingBatch>>,
+}
+
+impl ConsumeQueueWriteBuffer {
+    pub fn new(pool: Arc<ConsumeQueueBufferPool>) -> Self {
+        Self {
+            pool,
+            pending: parking_lot::Mutex::new(None),
+        }
+    }
+
+    /// Adds `unit` to the open batch if it directly follows the pending units and still fits,
+    /// otherwise the caller has to flush and go through the regular write path.
+    pub fn append(&self, expect_logic_offset: i64, unit: &[u8]) -> bool {
+        let mut pending = self.pending.lock();
+        let Some(batch) = pending.as_mut() else {
+            return false;
+        };
+        if batch.next_logic_offset != expect_logic_offset
+            || batch.next_logic_offset + unit.len() as i64 > batch.file_end_offset
+            || batch.buffer.len() + unit.len() > self.pool.buffer_size
+        {
+            return false;
+        }
+        batch.buffer.extend_from_slice(unit);
+        batch.next_logic_offset += unit.len() as i64;
+        true
+    }
+
+    /// Starts a batch with `unit` on `mapped_file`, whose wrote position must be
+    /// `expect_logic_offset`. Writes the unit directly when no pool buffer is available.
+    pub fn open(
+        self: &Arc<Self>,
+        mapped_file: Arc<DefaultMappedFile>,
+        expect_logic_offset: i64,
+        unit: &[u8],
+    ) -> bool {
+        let file_end_offset =
+            mapped_file.get_file_from_offset() as i64 + mapped_file.get_file_size() as i64;
+        let Some(mut buffer) = self.pool.borrow_buffer() else {
+            return mapped_file.append_message_bytes(&bytes::Bytes::copy_from_slice(unit));
+        };
+        buffer.extend_from_slice(unit);
+        let previous = self.pending.lock().replace(PendingBatch {
+            mapped_file,
+            next_logic_offset: expect_logic_offset + unit.len() as i64,
+            file_end_offset,
+            buffer,
+        });
+        if let Some(previous) = previous {
+            self.write(previous);
+        }
+        self.pool.dirty.lock().push(self.clone());
+        true
+    }
+
+    /// Appends the pending units to the mapped file, returns `false` if they could not be
+    /// written. Must be called from the thread dispatching into the queue, the mapped file has
+    /// a single writer.
+    pub fn flush(&self) -> bool {
+        let mut pending = self.pending.lock();
+        match pending.take() {
+            Some(batch) => self.write(batch),
+            None => true,
+        }
+    }
+
+    /// Drops the pending units, used when the queue is truncated or destroyed.
+    pub fn discard(&self) {
+        if let Some(batch) = self.pending.lock().take() {
+            self.pool.return_buffer(batch.buffer);
+        }
+    }
+
+    pub fn has_pending(&self) -> bool {
+        self.pending.lock().is_some()
+    }
+
+    fn write(&self, batch: PendingBatch) -> bool {
+        let PendingBatch {
+            mapped_file,
+            mut buffer,
+            ..
+        } = batch;
+        let result = mapped_file.append_message_bytes(&buffer.split().freeze());
+        if !result {
+            error!(
+                "append combined consume queue units to {} failed",
+                mapped_file.get_file_name()
+            );
+        }
+        self.pool.return_buffer(buffer);
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cheetah_string::CheetahString;
+
+    use super::*;
+
+    #[test]
+    fn units_are_combined_until_flushed() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let file_name = temp_dir.path().join("00000000000000000000");
+        let mapped_file = Arc::new(DefaultMappedFile::new(
+            CheetahString::from_string(file_name.to_string_lossy().to_string()),
+            100,
+        ));
+        let pool = Arc::new(ConsumeQueueBufferPool::new(60, 1));
+        let write_buffer = Arc::new(ConsumeQueueWriteBuffer::new(pool.clone()));
+        let unit = [7u8; 20];
+
+        assert!(!write_buffer.append(0, &unit));
+        assert!(write_buffer.open(mapped_file.clone(), 0, &unit));
+        assert!(write_buffer.append(20, &unit));
+        assert!(!write_buffer.append(60, &unit));
+        assert_eq!(mapped_file.get_wrote_position(), 0);
+        assert_eq!(pool.borrowed_buffers(), 1);
+
+        assert_eq!(pool.flush_dirty(), 1);
+        assert_eq!(mapped_file.get_wrote_position(), 40);
+        assert_eq!(pool.borrowed_buffers(), 0);
+        assert!(!write_buffer.has_pending());
+    }
+
+    #[test]
+    fn exhausted_pool_writes_directly() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let file_name = temp_dir.path().join("00000000000000000000");
+        let mapped_file = Arc::new(DefaultMappedFile::new(
+            CheetahString::from_string(file_name.to_string_lossy().to_string()),
+            100,
+        ));
+        let pool = Arc::new(ConsumeQueueBufferPool::new(60, 0));
+        let write_buffer = Arc::new(ConsumeQueueWriteBuffer::new(pool));
+
+        assert!(write_buffer.open(mapped_file.clone(), 0, &[1u8; 20]));
+        assert_eq!(mapped_file.get_wrote_position(), 20);
+        assert!(!write_buffer.has_pending());
+    }
+}
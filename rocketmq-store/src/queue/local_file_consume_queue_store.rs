@@ -19,6 +19,7 @@
 use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
+use std::path::PathBuf;
 use std::sync::Arc;
 
 use bytes::Bytes;
@@ -27,21 +28,29 @@ use rocketmq_common::common::attribute::cq_type::CQType;
 use rocketmq_common::common::broker::broker_config::BrokerConfig;
 use rocketmq_common::common::config::TopicConfig;
 use rocketmq_common::common::message::message_ext_broker_inner::MessageExtBrokerInner;
+use rocketmq_common::common::topic::TopicValidator;
 use rocketmq_common::utils::queue_type_utils::QueueTypeUtils;
+use rocketmq_common::utils::util_all;
 use rocketmq_rust::ArcMut;
+use tracing::error;
 use tracing::info;
 
 use crate::base::dispatch_request::DispatchRequest;
 use crate::base::store_checkpoint::StoreCheckpoint;
+use crate::base::swappable::Swappable;
 use crate::config::message_store_config::MessageStoreConfig;
 use crate::queue::batch_consume_queue::BatchConsumeQueue;
+use crate::queue::consume_queue_write_buffer::ConsumeQueueBufferPool;
+use crate::queue::multi_dispatch;
 use crate::queue::queue_offset_operator::QueueOffsetOperator;
 use crate::queue::single_consume_queue::ConsumeQueue;
+use crate::queue::single_consume_queue::CQ_STORE_UNIT_SIZE;
 use crate::queue::ArcConsumeQueue;
 use crate::queue::ConsumeQueueStoreTrait;
 use crate::queue::ConsumeQueueTable;
 use crate::queue::ConsumeQueueTrait;
 use crate::queue::CqUnit;
+use crate::queue::FileQueueLifeCycle;
 use crate::store::running_flags::RunningFlags;
 use crate::store_path_config_helper::get_store_path_batch_consume_queue;
 use crate::store_path_config_helper::get_store_path_consume_queue;
@@ -60,6 +69,7 @@ struct Inner {
     pub(crate) broker_config: Arc<BrokerConfig>,
     pub(crate) queue_offset_operator: QueueOffsetOperator,
     pub(crate) consume_queue_table: Arc<ConsumeQueueTable>,
+    pub(crate) write_buffer_pool: Option<Arc<ConsumeQueueBufferPool>>,
 }
 
 impl Inner {
@@ -80,6 +90,14 @@ impl ConsumeQueueStore {
         running_flags: Arc<RunningFlags>,
         store_checkpoint: Arc<StoreCheckpoint>,
     ) -> Self {
+        let write_buffer_pool =
+            (message_store_config.consume_queue_write_buffer_units > 0).then(|| {
+                Arc::new(ConsumeQueueBufferPool::new(
+                    message_store_config.consume_queue_write_buffer_units
+                        * CQ_STORE_UNIT_SIZE as usize,
+                    message_store_config.consume_queue_write_buffer_pool_size,
+                ))
+            });
         Self {
             inner: Arc::new(Inner {
                 //commit_log,
@@ -87,6 +105,7 @@ impl ConsumeQueueStore {
                 broker_config,
                 queue_offset_operator: QueueOffsetOperator::new(),
                 consume_queue_table: Arc::new(parking_lot::Mutex::new(HashMap::new())),
+                write_buffer_pool,
             }),
             running_flags,
             store_checkpoint,
@@ -162,7 +181,43 @@ impl ConsumeQueueStoreTrait for ConsumeQueueStore {
     }
 
     fn clean_expired(&self, min_phy_offset: i64) {
-        todo!()
+        let mut consume_queue_table = self.inner.consume_queue_table.lock();
+        consume_queue_table.retain(|topic, queue_table| {
+            if TopicValidator::is_system_topic(topic) {
+                return true;
+            }
+            queue_table.retain(|queue_id, consume_queue| {
+                let max_cl_offset_in_consume_queue = consume_queue.get_last_offset();
+                if max_cl_offset_in_consume_queue == -1 {
+                    // nothing written yet, the queue may have been looked up just now, keep it
+                    // but drop the directory a previous run may have left behind
+                    util_all::delete_empty_directory(
+                        self.consume_queue_topic_dir(topic, consume_queue.get_cq_type())
+                            .join(queue_id.to_string()),
+                    );
+                    true
+                } else if max_cl_offset_in_consume_queue < min_phy_offset {
+                    info!(
+                        "cleanExpiredConsumerQueue: {} {} consumer queue destroyed, \
+                         minCommitLogOffset: {} maxCLOffsetInConsumeQueue: {}",
+                        topic, queue_id, min_phy_offset, max_cl_offset_in_consume_queue
+                    );
+                    self.inner.queue_offset_operator.remove(topic, *queue_id);
+                    consume_queue.destroy();
+                    false
+                } else {
+                    true
+                }
+            });
+            if queue_table.is_empty() {
+                info!("cleanExpiredConsumerQueue: {},topic destroyed", topic);
+                for cq_type in [CQType::SimpleCQ, CQType::BatchCQ] {
+                    util_all::delete_empty_directory(self.consume_queue_topic_dir(topic, cq_type));
+                }
+                return false;
+            }
+            true
+        });
     }
 
     fn check_self(&self) {
@@ -203,7 +258,13 @@ impl ConsumeQueueStoreTrait for ConsumeQueueStore {
     fn put_message_position_info_wrapper(&self, request: &DispatchRequest) {
         let mut cq = self.find_or_create_consume_queue(request.topic.as_ref(), request.queue_id);
         self.put_message_position_info_wrapper_with_cq(&mut **cq.as_mut(), request);
-        // println!("put_message_position_info_wrapper-----{}", request.topic)
+        if multi_dispatch::check_multi_dispatch_queue(
+            &self.inner.message_store_config,
+            request.topic.as_str(),
+            request.properties_map.as_ref(),
+        ) {
+            self.multi_dispatch_lmq_queue(request);
+        }
     }
 
     fn put_message_position_info_wrapper_with_cq(
@@ -240,11 +301,13 @@ impl ConsumeQueueStoreTrait for ConsumeQueueStore {
     }
 
     fn increase_lmq_offset(&mut self, queue_key: &CheetahString, message_num: i16) {
-        todo!()
+        self.inner
+            .queue_offset_operator
+            .increase_lmq_offset(queue_key, message_num);
     }
 
     fn get_lmq_queue_offset(&self, queue_key: &CheetahString) -> i64 {
-        todo!()
+        self.inner.queue_offset_operator.get_lmq_offset(queue_key)
     }
 
     fn recover_offset_table(&mut self, min_phy_offset: i64) {
@@ -333,19 +396,25 @@ impl ConsumeQueueStoreTrait for ConsumeQueueStore {
         let consume_queue = topic_map.entry(queue_id).or_insert_with(|| {
             let option = self.topic_config_table.lock().get(topic).cloned();
             match QueueTypeUtils::get_cq_type(&option) {
-                CQType::SimpleCQ => ArcMut::new(Box::new(ConsumeQueue::new(
-                    topic.clone(),
-                    queue_id,
-                    CheetahString::from_string(get_store_path_consume_queue(
-                        self.inner.message_store_config.store_path_root_dir.as_str(),
-                    )),
-                    self.inner
-                        .message_store_config
-                        .get_mapped_file_size_consume_queue(),
-                    self.inner.message_store_config.clone(),
-                    self.running_flags.clone(),
-                    self.store_checkpoint.clone(),
-                ))),
+                CQType::SimpleCQ => {
+                    let mut consume_queue = ConsumeQueue::new(
+                        topic.clone(),
+                        queue_id,
+                        CheetahString::from_string(get_store_path_consume_queue(
+                            self.inner.message_store_config.store_path_root_dir.as_str(),
+                        )),
+                        self.inner
+                            .message_store_config
+                            .get_mapped_file_size_consume_queue(),
+                        self.inner.message_store_config.clone(),
+                        self.running_flags.clone(),
+                        self.store_checkpoint.clone(),
+                    );
+                    if let Some(pool) = self.inner.write_buffer_pool.as_ref() {
+                        consume_queue.set_write_buffer_pool(pool.clone());
+                    }
+                    ArcMut::new(Box::new(consume_queue))
+                }
                 CQType::BatchCQ => ArcMut::new(Box::new(BatchConsumeQueue::new(
                     topic.clone(),
                     queue_id,
@@ -370,7 +439,7 @@ impl ConsumeQueueStoreTrait for ConsumeQueueStore {
         &self,
         topic: &CheetahString,
     ) -> Option<HashMap<i32, ArcConsumeQueue>> {
-        todo!()
+        self.inner.consume_queue_table.lock().get(topic).cloned()
     }
 
     fn get_total_size(&self) -> i64 {
@@ -382,12 +451,14 @@ impl ConsumeQueueStoreTrait for ConsumeQueueStore {
     }
 
     fn get_min_offset_in_queue(&self, topic: &CheetahString, queue_id: i32) -> i64 {
-        let queue = self.find_or_create_consume_queue(topic, queue_id);
-        queue.get_min_offset_in_queue()
+        // offset lookups must not materialize queues that never received a message
+        self.find_consume_queue(topic, queue_id)
+            .map_or(0, |queue| queue.get_min_offset_in_queue())
     }
 
     fn get_max_offset_in_queue(&self, topic: &CheetahString, queue_id: i32) -> i64 {
-        todo!()
+        self.find_consume_queue(topic, queue_id)
+            .map_or(0, |queue| queue.get_max_offset_in_queue())
     }
 
     fn get_consume_queue_table(&self) -> Arc<ConsumeQueueTable> {
@@ -404,6 +475,29 @@ impl ConsumeQueueStore {
         consume_queue.correct_min_offset(min_commit_log_offset)
     }
 
+    /// Looks up an existing consume queue without creating it.
+    pub fn find_consume_queue(
+        &self,
+        topic: &CheetahString,
+        queue_id: i32,
+    ) -> Option<ArcConsumeQueue> {
+        self.inner
+            .consume_queue_table
+            .lock()
+            .get(topic)
+            .and_then(|queue_table| queue_table.get(&queue_id))
+            .cloned()
+    }
+
+    fn consume_queue_topic_dir(&self, topic: &CheetahString, cq_type: CQType) -> PathBuf {
+        let root_dir = self.inner.message_store_config.store_path_root_dir.as_str();
+        let store_path = match cq_type {
+            CQType::BatchCQ => get_store_path_batch_consume_queue(root_dir),
+            _ => get_store_path_consume_queue(root_dir),
+        };
+        PathBuf::from(store_path).join(topic.as_str())
+    }
+
     pub fn set_batch_topic_queue_table(
         &self,
         batch_topic_queue_table: HashMap<CheetahString, i64>,
@@ -497,9 +591,87 @@ impl ConsumeQueueStore {
         let mut file_queue_life_cycle = self.get_life_cycle(topic, queue_id);
         file_queue_life_cycle.truncate_dirty_logic_files(phy_offset);
     }
+
+    /// Writes the position of a multi dispatch message into every light message queue it was
+    /// assigned an offset in, creating those queues on first use.
+    fn multi_dispatch_lmq_queue(&self, request: &DispatchRequest) {
+        let Some(properties) = request.properties_map.as_ref() else {
+            return;
+        };
+        let queues = match multi_dispatch::parse_multi_dispatch(properties) {
+            Some(Ok(queues)) => queues,
+            Some(Err(err)) => {
+                error!("[bug] {}, topic: {}", err, request.topic);
+                return;
+            }
+            None => return,
+        };
+        for (queue_name, queue_offset) in queues {
+            let queue_id = if multi_dispatch::is_lmq_queue(
+                &self.inner.message_store_config,
+                queue_name.as_str(),
+            ) {
+                multi_dispatch::LMQ_QUEUE_ID
+            } else {
+                request.queue_id
+            };
+            let lmq_request = DispatchRequest {
+                topic: queue_name,
+                queue_id,
+                commit_log_offset: request.commit_log_offset,
+                msg_size: request.msg_size,
+                tags_code: request.tags_code,
+                store_timestamp: request.store_timestamp,
+                consume_queue_offset: queue_offset,
+                keys: request.keys.clone(),
+                success: request.success,
+                uniq_key: request.uniq_key.clone(),
+                sys_flag: request.sys_flag,
+                bit_map: request.bit_map.clone(),
+                ..DispatchRequest::default()
+            };
+            let mut cq = self.find_or_create_consume_queue(&lmq_request.topic, queue_id);
+            self.put_message_position_info_wrapper_with_cq(&mut **cq.as_mut(), &lmq_request);
+        }
+    }
 }
 
 impl ConsumeQueueStore {
+    /// Appends the units combined by the consume queue write buffers to their mapped files so
+    /// they become visible to readers, a no-op unless `consume_queue_write_buffer_units` is set.
+    pub fn flush_write_buffers(&self) -> usize {
+        self.inner
+            .write_buffer_pool
+            .as_ref()
+            .map_or(0, |pool| pool.flush_dirty())
+    }
+
+    /// Remaps the cold files of every consume queue, keeping the newest `reserve_num` files of
+    /// each queue untouched.
+    pub fn swap_map(
+        &self,
+        reserve_num: i32,
+        force_swap_interval_ms: i64,
+        normal_swap_interval_ms: i64,
+    ) {
+        let cloned = self.inner.consume_queue_table.lock().clone();
+        for consume_queue_table in cloned.values() {
+            for logic in consume_queue_table.values() {
+                logic.swap_map(reserve_num, force_swap_interval_ms, normal_swap_interval_ms);
+            }
+        }
+    }
+
+    /// Releases the mappings left behind by [`ConsumeQueueStore::swap_map`].
+    pub fn clean_swapped_map(&self, force_clean_swap_interval_ms: i64) {
+        let cloned = self.inner.consume_queue_table.lock().clone();
+        for consume_queue_table in cloned.values() {
+            for logic in consume_queue_table.values() {
+                logic.clean_swapped_map(force_clean_swap_interval_ms);
+            }
+        }
+    }
+
     fn create_consume_queue_by_type(
         &self,
         topic: &CheetahString,
@@ -509,7 +681,7 @@ impl ConsumeQueueStore {
     ) -> Box<dyn ConsumeQueueTrait> {
         match cq_type {
             CQType::SimpleCQ => {
-                let consume_queue = ConsumeQueue::new(
+                let mut consume_queue = ConsumeQueue::new(
                     topic.clone(),
                     queue_id,
                     store_path,
@@ -520,6 +692,9 @@ impl ConsumeQueueStore {
                     self.running_flags.clone(),
                     self.store_checkpoint.clone(),
                 );
+                if let Some(pool) = self.inner.write_buffer_pool.as_ref() {
+                    consume_queue.set_write_buffer_pool(pool.clone());
+                }
                 Box::new(consume_queue)
             }
             CQType::BatchCQ => {
@@ -545,3 +720,37 @@ impl ConsumeQueueStore {
         self.find_or_create_consume_queue(topic, queue_id)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clean_expired_removes_idle_queue_directories() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path().to_string_lossy().to_string();
+        let idle_queue_dir = PathBuf::from(get_store_path_consume_queue(&root))
+            .join("TopicTest")
+            .join("0");
+        fs::create_dir_all(&idle_queue_dir).unwrap();
+        let mut consume_queue_store = ConsumeQueueStore::new(
+            Arc::new(MessageStoreConfig {
+                store_path_root_dir: root.into(),
+                ..MessageStoreConfig::default()
+            }),
+            Arc::new(BrokerConfig::default()),
+            Arc::new(parking_lot::Mutex::new(HashMap::new())),
+            Arc::new(RunningFlags::new()),
+            Arc::new(StoreCheckpoint::new(temp_dir.path().join("checkpoint")).unwrap()),
+        );
+        assert!(consume_queue_store.load());
+
+        let topic = CheetahString::from_static_str("TopicTest");
+        assert_eq!(consume_queue_store.get_max_offset_in_queue(&topic, 1), 0);
+        assert!(consume_queue_store.find_consume_queue(&topic, 1).is_none());
+
+        consume_queue_store.clean_expired(0);
+        assert!(!idle_queue_dir.exists());
+        assert!(consume_queue_store.find_consume_queue(&topic, 0).is_some());
+    }
+}
@@ -0,0 +1,210 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Light message queue (LMQ) support, see RIP-28.
+//!
+//! A message carrying `INNER_MULTI_DISPATCH` is written once to the commit log but shows up in
+//! every light queue named by that property. The offset the message takes in each of those queues
+//! is assigned under the topic queue lock and recorded in `INNER_MULTI_QUEUE_OFFSET`, so the
+//! dispatcher can rebuild the light consume queues from the commit log alone.
+
+use std::collections::HashMap;
+
+use cheetah_string::CheetahString;
+use rocketmq_common::common::message::MessageConst;
+use rocketmq_common::common::mix_all;
+use rocketmq_common::common::topic::TopicValidator;
+
+use crate::config::message_store_config::MessageStoreConfig;
+
+/// Light message queues always live in queue 0.
+pub const LMQ_QUEUE_ID: i32 = 0;
+
+/// Key of a light message queue in the queue offset table.
+#[inline]
+pub fn lmq_queue_key(queue_name: &str) -> CheetahString {
+    CheetahString::from_string(format!("{}-{}", queue_name, LMQ_QUEUE_ID))
+}
+
+/// Whether messages of `topic` may be fanned out to light message queues.
+pub fn is_need_handle_multi_dispatch(
+    message_store_config: &MessageStoreConfig,
+    topic: &str,
+) -> bool {
+    message_store_config.enable_multi_dispatch
+        && !topic.starts_with(mix_all::RETRY_GROUP_TOPIC_PREFIX)
+        && !topic.starts_with(TopicValidator::SYSTEM_TOPIC_PREFIX)
+        && topic != TopicValidator::RMQ_SYS_SCHEDULE_TOPIC
+}
+
+/// Whether `queue_name` is a light message queue the store manages offsets for.
+#[inline]
+pub fn is_lmq_queue(message_store_config: &MessageStoreConfig, queue_name: &str) -> bool {
+    message_store_config.enable_lmq && mix_all::is_lmq(Some(queue_name))
+}
+
+/// Splits the `INNER_MULTI_DISPATCH` property into queue names, `None` if it is blank.
+pub fn split_multi_dispatch_queues(multi_dispatch_queue: &str) -> Option<Vec<&str>> {
+    if multi_dispatch_queue.trim().is_empty() {
+        return None;
+    }
+    Some(
+        multi_dispatch_queue
+            .split(mix_all::MULTI_DISPATCH_QUEUE_SPLITTER)
+            .collect(),
+    )
+}
+
+/// Joins the offsets assigned to each light queue into the `INNER_MULTI_QUEUE_OFFSET` property.
+///
+/// Queues that are not light message queues keep an empty slot so the positions line up with
+/// `INNER_MULTI_DISPATCH`.
+pub fn join_multi_queue_offsets(queue_offsets: &[Option<i64>]) -> CheetahString {
+    let joined = queue_offsets
+        .iter()
+        .map(|offset| offset.map_or_else(String::new, |offset| offset.to_string()))
+        .collect::<Vec<_>>()
+        .join(mix_all::MULTI_DISPATCH_QUEUE_SPLITTER);
+    CheetahString::from_string(joined)
+}
+
+/// Pairs every queue of `INNER_MULTI_DISPATCH` with its offset from `INNER_MULTI_QUEUE_OFFSET`.
+///
+/// Returns `None` when either property is missing or blank, and `Err` when the two lists disagree
+/// or an offset cannot be parsed.
+pub fn parse_multi_dispatch(
+    properties: &HashMap<CheetahString, CheetahString>,
+) -> Option<Result<Vec<(CheetahString, i64)>, String>> {
+    let multi_dispatch_queue = properties.get(MessageConst::PROPERTY_INNER_MULTI_DISPATCH)?;
+    let multi_queue_offset = properties.get(MessageConst::PROPERTY_INNER_MULTI_QUEUE_OFFSET)?;
+    let queues = split_multi_dispatch_queues(multi_dispatch_queue)?;
+    if multi_queue_offset.trim().is_empty() {
+        return None;
+    }
+    let queue_offsets = multi_queue_offset
+        .split(mix_all::MULTI_DISPATCH_QUEUE_SPLITTER)
+        .collect::<Vec<_>>();
+    if queues.len() != queue_offsets.len() {
+        return Some(Err(format!(
+            "queues.length({}) != queueOffsets.length({})",
+            queues.len(),
+            queue_offsets.len()
+        )));
+    }
+    let mut result = Vec::with_capacity(queues.len());
+    for (queue, offset) in queues.into_iter().zip(queue_offsets) {
+        if offset.is_empty() {
+            continue;
+        }
+        match offset.parse::<i64>() {
+            Ok(offset) => result.push((CheetahString::from_slice(queue), offset)),
+            Err(_) => {
+                return Some(Err(format!(
+                    "illegal queue offset {} of queue {}",
+                    offset, queue
+                )))
+            }
+        }
+    }
+    Some(Ok(result))
+}
+
+/// Whether the dispatch of `topic` with `properties` must also fan out to light queues.
+pub fn check_multi_dispatch_queue(
+    message_store_config: &MessageStoreConfig,
+    topic: &str,
+    properties: Option<&HashMap<CheetahString, CheetahString>>,
+) -> bool {
+    if !is_need_handle_multi_dispatch(message_store_config, topic) {
+        return false;
+    }
+    let Some(properties) = properties else {
+        return false;
+    };
+    let not_blank = |key: &str| properties.get(key).is_some_and(|v| !v.trim().is_empty());
+    not_blank(MessageConst::PROPERTY_INNER_MULTI_DISPATCH)
+        && not_blank(MessageConst::PROPERTY_INNER_MULTI_QUEUE_OFFSET)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn properties(queues: &str, offsets: &str) -> HashMap<CheetahString, CheetahString> {
+        let mut properties = HashMap::new();
+        properties.insert(
+            CheetahString::from_static_str(MessageConst::PROPERTY_INNER_MULTI_DISPATCH),
+            CheetahString::from_slice(queues),
+        );
+        properties.insert(
+            CheetahString::from_static_str(MessageConst::PROPERTY_INNER_MULTI_QUEUE_OFFSET),
+            CheetahString::from_slice(offsets),
+        );
+        properties
+    }
+
+    #[test]
+    fn parse_multi_dispatch_pairs_queues_with_offsets() {
+        let parsed = parse_multi_dispatch(&properties("%LMQ%a,%LMQ%b", "3,7"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            parsed,
+            vec![
+                (CheetahString::from_static_str("%LMQ%a"), 3),
+                (CheetahString::from_static_str("%LMQ%b"), 7)
+            ]
+        );
+        assert_eq!(
+            join_multi_queue_offsets(&[Some(3), None, Some(7)]).as_str(),
+            "3,,7"
+        );
+        let parsed = parse_multi_dispatch(&properties("%LMQ%a,plain,%LMQ%b", "3,,7"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(parsed.len(), 2);
+        assert_eq!(lmq_queue_key("%LMQ%a").as_str(), "%LMQ%a-0");
+    }
+
+    #[test]
+    fn parse_multi_dispatch_rejects_mismatched_or_blank_properties() {
+        assert!(parse_multi_dispatch(&properties("%LMQ%a,%LMQ%b", "3"))
+            .unwrap()
+            .is_err());
+        assert!(parse_multi_dispatch(&properties("%LMQ%a", "x"))
+            .unwrap()
+            .is_err());
+        assert!(parse_multi_dispatch(&properties(" ", "3")).is_none());
+        assert!(parse_multi_dispatch(&HashMap::new()).is_none());
+
+        let config = MessageStoreConfig {
+            enable_multi_dispatch: true,
+            ..MessageStoreConfig::default()
+        };
+        let props = properties("%LMQ%a", "0");
+        assert!(check_multi_dispatch_queue(&config, "TopicA", Some(&props)));
+        assert!(!check_multi_dispatch_queue(
+            &config,
+            "%RETRY%group",
+            Some(&props)
+        ));
+        assert!(!check_multi_dispatch_queue(
+            &MessageStoreConfig::default(),
+            "TopicA",
+            Some(&props)
+        ));
+    }
+}
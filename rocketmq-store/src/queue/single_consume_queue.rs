@@ -27,6 +27,8 @@ use cheetah_string::CheetahString;
 use rocketmq_common::common::attribute::cq_type::CQType;
 use rocketmq_common::common::boundary_type::BoundaryType;
 use rocketmq_common::common::message::message_ext_broker_inner::MessageExtBrokerInner;
+use rocketmq_common::common::message::MessageConst;
+use rocketmq_common::common::message::MessageTrait;
 use tracing::debug;
 use tracing::error;
 use tracing::info;
@@ -44,6 +46,9 @@ use crate::filter::MessageFilter;
 use crate::log_file::mapped_file::default_mapped_file_impl::DefaultMappedFile;
 use crate::log_file::mapped_file::MappedFile;
 use crate::queue::consume_queue_ext::ConsumeQueueExt;
+use crate::queue::consume_queue_write_buffer::ConsumeQueueBufferPool;
+use crate::queue::consume_queue_write_buffer::ConsumeQueueWriteBuffer;
+use crate::queue::multi_dispatch;
 use crate::queue::queue_offset_operator::QueueOffsetOperator;
 use crate::queue::ConsumeQueueTrait;
 use crate::queue::CqUnit;
@@ -79,6 +84,7 @@ pub struct ConsumeQueue {
     consume_queue_ext: Option<ConsumeQueueExt>,
     running_flags: Arc<RunningFlags>,
     store_checkpoint: Arc<StoreCheckpoint>,
+    write_buffer: Option<Arc<ConsumeQueueWriteBuffer>>,
 }
 
 impl ConsumeQueue {
@@ -94,11 +100,18 @@ impl ConsumeQueue {
         let queue_dir = PathBuf::from(store_path.as_str())
             .join(topic.as_str())
             .join(queue_id.to_string());
-        let mapped_file_queue = MappedFileQueue::new(
+        let mut mapped_file_queue = MappedFileQueue::new(
             queue_dir.to_string_lossy().to_string(),
             mapped_file_size as u64,
             None,
         );
+        mapped_file_queue.set_write_without_mmap(message_store_config.write_without_mmap);
+        mapped_file_queue
+            .set_write_buffer_size(message_store_config.write_without_mmap_buffer_size);
+        let cold_mapped_file_size = message_store_config.get_mapped_file_size_consume_queue_cold();
+        if cold_mapped_file_size > 0 && cold_mapped_file_size % mapped_file_size == 0 {
+            mapped_file_queue.set_cold_mapped_file_size(cold_mapped_file_size as u64);
+        }
         let consume_queue_ext = if message_store_config.enable_consume_queue_ext {
             Some(ConsumeQueueExt::new(
                 topic.clone(),
@@ -124,8 +137,14 @@ impl ConsumeQueue {
             consume_queue_ext,
             running_flags,
             store_checkpoint,
+            write_buffer: None,
         }
     }
+
+    /// Combines the units written to this queue in buffers borrowed from `pool`.
+    pub fn set_write_buffer_pool(&mut self, pool: Arc<ConsumeQueueBufferPool>) {
+        self.write_buffer = Some(Arc::new(ConsumeQueueWriteBuffer::new(pool)));
+    }
 }
 
 impl ConsumeQueue {
@@ -135,16 +154,19 @@ impl ConsumeQueue {
     }
 
     pub fn truncate_dirty_logic_files_handler(&mut self, phy_offset: i64, delete_file: bool) {
+        if let Some(write_buffer) = self.write_buffer.as_ref() {
+            write_buffer.flush();
+        }
         self.set_max_physic_offset(phy_offset);
         let mut max_ext_addr = 1i64;
         let mut should_delete_file = false;
-        let mapped_file_size = self.mapped_file_size;
         loop {
             let mapped_file_option = self.mapped_file_queue.get_last_mapped_file();
             if mapped_file_option.is_none() {
                 break;
             }
             let mapped_file = mapped_file_option.unwrap();
+            let mapped_file_size = mapped_file.get_file_size() as i32;
             mapped_file.set_wrote_position(0);
             mapped_file.set_committed_position(0);
             mapped_file.set_flushed_position(0);
@@ -207,7 +229,7 @@ impl ConsumeQueue {
         }
         if self.is_ext_read_enable() {
             self.consume_queue_ext
-                .as_ref()
+                .as_mut()
                 .unwrap()
                 .truncate_by_max_address(max_ext_addr);
         }
@@ -248,6 +270,13 @@ impl ConsumeQueue {
         bytes.put_i64(tags_code);
 
         let expect_logic_offset = cq_offset * CQ_STORE_UNIT_SIZE as i64;
+        if let Some(write_buffer) = self.write_buffer.as_ref() {
+            if write_buffer.append(expect_logic_offset, &bytes) {
+                self.set_max_physic_offset(offset + size as i64);
+                return true;
+            }
+            write_buffer.flush();
+        }
         if let Some(mapped_file) = self
             .mapped_file_queue
             .get_last_mapped_file_mut_start_offset(expect_logic_offset as u64, true)
@@ -300,7 +329,14 @@ impl ConsumeQueue {
                 }
             }
             self.set_max_physic_offset(offset + size as i64);
-            mapped_file.append_message_bytes(&bytes.freeze())
+            let current_logic_offset =
+                mapped_file.get_wrote_position() as i64 + mapped_file.get_file_from_offset() as i64;
+            match self.write_buffer.as_ref() {
+                Some(write_buffer) if expect_logic_offset == current_logic_offset => {
+                    write_buffer.open(mapped_file, expect_logic_offset, &bytes)
+                }
+                _ => mapped_file.append_message_bytes(&bytes.freeze()),
+            }
         } else {
             false
         }
@@ -313,22 +349,22 @@ impl ConsumeQueue {
         bytes_mut.put_i32(i32::MAX);
         bytes_mut.put_i64(0);
         let bytes = bytes_mut.freeze();
-        let until = (until_where % self.mapped_file_queue.mapped_file_size as i64) as i32
-            / CQ_STORE_UNIT_SIZE;
+        let until =
+            (until_where - mapped_file.get_file_from_offset() as i64) as i32 / CQ_STORE_UNIT_SIZE;
         for n in 0..until {
             mapped_file.append_message_bytes(&bytes);
         }
     }
 
     pub fn get_index_buffer(&self, start_index: i64) -> Option<SelectMappedBufferResult> {
-        let mapped_file_size = self.mapped_file_size;
         let offset = start_index * CQ_STORE_UNIT_SIZE as i64;
         if offset >= self.get_min_logic_offset() {
             if let Some(mapped_file) = self
                 .mapped_file_queue
                 .find_mapped_file_by_offset(offset, false)
             {
-                return mapped_file.select_mapped_buffer((offset % mapped_file_size as i64) as i32);
+                let pos = offset - mapped_file.get_file_from_offset() as i64;
+                return mapped_file.select_mapped_buffer(pos as i32);
             }
         }
         None
@@ -361,12 +397,13 @@ impl FileQueueLifeCycle for ConsumeQueue {
             index = 0;
         }
         let mut index = index as usize;
-        let mapped_file_size_logics = self.mapped_file_size;
         let mut mapped_file = mapped_files.get(index).unwrap();
         let mut process_offset = mapped_file.get_file_from_offset();
         let mut mapped_file_offset = 0i64;
         let mut max_ext_addr = 1i64;
         loop {
+            // cold files are larger than the configured size, always go by the file itself
+            let mapped_file_size_logics = mapped_file.get_file_size() as i32;
             for index in 0..(mapped_file_size_logics / CQ_STORE_UNIT_SIZE) {
                 let bytes_option = mapped_file.get_bytes(
                     (index * CQ_STORE_UNIT_SIZE) as usize,
@@ -445,10 +482,23 @@ impl FileQueueLifeCycle for ConsumeQueue {
     }
 
     fn flush(&self, flush_least_pages: i32) -> bool {
-        todo!()
+        let mut result = self.mapped_file_queue.flush(flush_least_pages);
+        if self.is_ext_read_enable() {
+            result &= self
+                .consume_queue_ext
+                .as_ref()
+                .unwrap()
+                .flush(flush_least_pages);
+        }
+        self.mapped_file_queue
+            .merge_cold_files(self.message_store_config.consume_queue_hot_file_num);
+        result
     }
 
     fn destroy(&mut self) {
+        if let Some(write_buffer) = self.write_buffer.as_ref() {
+            write_buffer.discard();
+        }
         self.set_max_physic_offset(-1);
         self.min_logic_offset.store(0, Ordering::SeqCst);
         self.mapped_file_queue.destroy();
@@ -485,11 +535,16 @@ impl Swappable for ConsumeQueue {
         force_swap_interval_ms: i64,
         normal_swap_interval_ms: i64,
     ) {
-        todo!()
+        self.mapped_file_queue.swap_map(
+            reserve_num,
+            force_swap_interval_ms,
+            normal_swap_interval_ms,
+        );
     }
 
-    fn clean_swapped_map(&self, _force_clean_swap_interval_ms: i64) {
-        todo!()
+    fn clean_swapped_map(&self, force_clean_swap_interval_ms: i64) {
+        self.mapped_file_queue
+            .clean_swapped_map(force_clean_swap_interval_ms);
     }
 }
 
@@ -504,10 +559,9 @@ impl ConsumeQueueTrait for ConsumeQueue {
     }
 
     fn get(&self, index: i64) -> Option<CqUnit> {
-        match self.iterate_from(index) {
-            None => None,
-            Some(value) => None,
-        }
+        let mut cq_unit = self.iterate_from(index)?.next()?;
+        cq_unit.queue_offset = index;
+        Some(cq_unit)
     }
 
     fn get_cq_unit_and_store_time(&self, index: i64) -> Option<(CqUnit, i64)> {
@@ -527,7 +581,20 @@ impl ConsumeQueueTrait for ConsumeQueue {
     }
 
     fn get_last_offset(&self) -> i64 {
-        todo!()
+        let mut last_offset = -1;
+        if let Some(mapped_file) = self.mapped_file_queue.get_last_mapped_file() {
+            let position = (mapped_file.get_wrote_position() - CQ_STORE_UNIT_SIZE).max(0);
+            if let Some(mut byte_buffer) =
+                mapped_file.get_bytes(position as usize, CQ_STORE_UNIT_SIZE as usize)
+            {
+                let offset = byte_buffer.get_i64();
+                let size = byte_buffer.get_i32();
+                if offset >= 0 && size > 0 {
+                    last_offset = offset + size as i64;
+                }
+            }
+        }
+        last_offset
     }
 
     fn get_min_offset_in_queue(&self) -> i64 {
@@ -722,7 +789,7 @@ impl ConsumeQueueTrait for ConsumeQueue {
         while i < max_retries && can_write {
             let mut tags_code = request.tags_code;
             if self.is_ext_write_enable() {
-                let ext_addr = self.consume_queue_ext.as_ref().unwrap().put(CqExtUnit::new(
+                let ext_addr = self.consume_queue_ext.as_mut().unwrap().put(CqExtUnit::new(
                     tags_code,
                     request.store_timestamp,
                     request.bit_map.clone(),
@@ -751,9 +818,6 @@ impl ConsumeQueueTrait for ConsumeQueue {
                 }
                 self.store_checkpoint
                     .set_logics_msg_timestamp(request.store_timestamp as u64);
-                //if (MultiDispatchUtils.checkMultiDispatchQueue(this.messageStore.
-                // getMessageStoreConfig(), request)) {
-                // multiDispatchLmqQueue(request, maxRetries);                 }
                 return;
             } else {
                 warn!(
@@ -780,6 +844,27 @@ impl ConsumeQueueTrait for ConsumeQueue {
             CheetahString::from_string(format!("{}-{}", msg.topic(), msg.queue_id())),
             message_num,
         );
+        // Each light message queue the message is fanned out to takes exactly one slot.
+        if !multi_dispatch::is_need_handle_multi_dispatch(
+            &self.message_store_config,
+            msg.topic().as_str(),
+        ) {
+            return;
+        }
+        let Some(multi_dispatch_queue) = msg.property(MessageConst::PROPERTY_INNER_MULTI_DISPATCH)
+        else {
+            return;
+        };
+        let Some(queues) =
+            multi_dispatch::split_multi_dispatch_queues(multi_dispatch_queue.as_str())
+        else {
+            return;
+        };
+        for queue in queues {
+            if multi_dispatch::is_lmq_queue(&self.message_store_config, queue) {
+                queue_offset_assigner.increase_lmq_offset(&multi_dispatch::lmq_queue_key(queue), 1);
+            }
+        }
     }
 
     fn assign_queue_offset(
@@ -791,6 +876,36 @@ impl ConsumeQueueTrait for ConsumeQueue {
             format!("{}-{}", msg.topic(), msg.queue_id()),
         ));
         msg.message_ext_inner.queue_offset = queue_offset;
+
+        // Light message queues (RIP-28) are built from the message properties, so the offset the
+        // message takes in each of them is recorded alongside the message.
+        if !multi_dispatch::is_need_handle_multi_dispatch(
+            &self.message_store_config,
+            msg.topic().as_str(),
+        ) {
+            return;
+        }
+        let Some(multi_dispatch_queue) = msg.property(MessageConst::PROPERTY_INNER_MULTI_DISPATCH)
+        else {
+            return;
+        };
+        let Some(queues) =
+            multi_dispatch::split_multi_dispatch_queues(multi_dispatch_queue.as_str())
+        else {
+            return;
+        };
+        let queue_offsets = queues
+            .into_iter()
+            .map(|queue| {
+                multi_dispatch::is_lmq_queue(&self.message_store_config, queue).then(|| {
+                    queue_offset_operator.get_lmq_offset(&multi_dispatch::lmq_queue_key(queue))
+                })
+            })
+            .collect::<Vec<_>>();
+        msg.put_property(
+            CheetahString::from_static_str(MessageConst::PROPERTY_INNER_MULTI_QUEUE_OFFSET),
+            multi_dispatch::join_multi_queue_offsets(&queue_offsets),
+        );
     }
 
     fn estimate_message_count(&self, from: i64, to: i64, filter: &dyn MessageFilter) -> i64 {
@@ -826,7 +941,7 @@ struct ConsumeQueueIterator {
 }
 
 impl ConsumeQueueIterator {
-    fn get_ext(&self, offset: i64, cq_ext_unit: &CqExtUnit) -> bool {
+    fn get_ext(&self, offset: i64, cq_ext_unit: &mut CqExtUnit) -> bool {
         match self.consume_queue_ext.as_ref() {
             None => false,
             Some(value) => value.get(offset, cq_ext_unit),
@@ -844,12 +959,20 @@ impl Iterator for ConsumeQueueIterator {
                 if self.counter * CQ_STORE_UNIT_SIZE >= value.size {
                     return None;
                 }
-                let mmp = value.mapped_file.as_ref().unwrap().get_mapped_file();
+                let mapped_file = value.mapped_file.as_ref().unwrap();
+                // start_offset is a logic offset, the unit is read relative to its file
                 let start =
                     value.start_offset as usize + (self.counter * CQ_STORE_UNIT_SIZE) as usize;
                 self.counter += 1;
-                let end = start + CQ_STORE_UNIT_SIZE as usize;
-                let mut bytes = Bytes::copy_from_slice(&mmp[start..end]);
+                let relative_start = start - mapped_file.get_file_from_offset() as usize;
+                let relative_end = relative_start + CQ_STORE_UNIT_SIZE as usize;
+                let mut bytes = if mapped_file.is_write_without_mmap() {
+                    mapped_file.get_bytes(relative_start, CQ_STORE_UNIT_SIZE as usize)?
+                } else {
+                    Bytes::copy_from_slice(
+                        &mapped_file.get_mapped_file()[relative_start..relative_end],
+                    )
+                };
                 let pos = bytes.get_i64();
                 let size = bytes.get_i32();
                 let tags_code = bytes.get_i64();
@@ -862,8 +985,8 @@ impl Iterator for ConsumeQueueIterator {
                 };
 
                 if ConsumeQueueExt::is_ext_addr(cq_unit.tags_code) {
-                    let cq_ext_unit = CqExtUnit::default();
-                    let ext_ret = self.get_ext(cq_unit.tags_code, &cq_ext_unit);
+                    let mut cq_ext_unit = CqExtUnit::default();
+                    let ext_ret = self.get_ext(cq_unit.tags_code, &mut cq_ext_unit);
                     if ext_ret {
                         cq_unit.tags_code = cq_ext_unit.tags_code();
                         cq_unit.cq_ext_unit = Some(cq_ext_unit);
@@ -880,3 +1003,106 @@ impl Iterator for ConsumeQueueIterator {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn get_returns_unit_at_index() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path().to_string_lossy().to_string();
+        let message_store_config = Arc::new(MessageStoreConfig {
+            store_path_root_dir: root.clone().into(),
+            ..MessageStoreConfig::default()
+        });
+        let store_checkpoint =
+            Arc::new(StoreCheckpoint::new(temp_dir.path().join("checkpoint")).unwrap());
+        let mut consume_queue = ConsumeQueue::new(
+            CheetahString::from_static_str("TopicTest"),
+            0,
+            CheetahString::from_string(root),
+            CQ_STORE_UNIT_SIZE * 10,
+            message_store_config,
+            Arc::new(RunningFlags::new()),
+            store_checkpoint,
+        );
+        for i in 0..3i64 {
+            assert!(consume_queue.put_message_position_info(100 * (i + 1), 10, 0, i));
+        }
+
+        let cq_unit = consume_queue.get(1).unwrap();
+        assert_eq!(cq_unit.queue_offset, 1);
+        assert_eq!(cq_unit.pos, 200);
+        assert_eq!(cq_unit.size, 10);
+        assert!(consume_queue.get(3).is_none());
+    }
+
+    #[test]
+    fn get_last_offset_points_past_last_message() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path().to_string_lossy().to_string();
+        let message_store_config = Arc::new(MessageStoreConfig {
+            store_path_root_dir: root.clone().into(),
+            ..MessageStoreConfig::default()
+        });
+        let store_checkpoint =
+            Arc::new(StoreCheckpoint::new(temp_dir.path().join("checkpoint")).unwrap());
+        let mut consume_queue = ConsumeQueue::new(
+            CheetahString::from_static_str("TopicTest"),
+            0,
+            CheetahString::from_string(root),
+            CQ_STORE_UNIT_SIZE * 10,
+            message_store_config,
+            Arc::new(RunningFlags::new()),
+            store_checkpoint,
+        );
+        assert_eq!(consume_queue.get_last_offset(), -1);
+
+        assert!(consume_queue.put_message_position_info(100, 10, 0, 0));
+        assert!(consume_queue.put_message_position_info(110, 20, 0, 1));
+        assert_eq!(consume_queue.get_last_offset(), 130);
+    }
+
+    #[test]
+    fn iterate_from_reads_units_of_later_files() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path().to_string_lossy().to_string();
+        let message_store_config = Arc::new(MessageStoreConfig {
+            store_path_root_dir: root.clone().into(),
+            ..MessageStoreConfig::default()
+        });
+        let store_checkpoint =
+            Arc::new(StoreCheckpoint::new(temp_dir.path().join("checkpoint")).unwrap());
+        let mut consume_queue = ConsumeQueue::new(
+            CheetahString::from_static_str("TopicTest"),
+            0,
+            CheetahString::from_string(root),
+            CQ_STORE_UNIT_SIZE * 4,
+            message_store_config,
+            Arc::new(RunningFlags::new()),
+            store_checkpoint,
+        );
+        for i in 0..6i64 {
+            assert!(consume_queue.put_message_position_info(100 * (i + 1), 10, 0, i));
+        }
+
+        let units: Vec<CqUnit> = consume_queue.iterate_from(1).unwrap().collect();
+        assert_eq!(
+            units
+                .iter()
+                .map(|unit| unit.queue_offset)
+                .collect::<Vec<_>>(),
+            vec![1, 2, 3]
+        );
+
+        let units: Vec<CqUnit> = consume_queue.iterate_from(4).unwrap().collect();
+        assert_eq!(
+            units
+                .iter()
+                .map(|unit| (unit.queue_offset, unit.pos))
+                .collect::<Vec<_>>(),
+            vec![(4, 500), (5, 600)]
+        );
+    }
+}
@@ -1,2 +0,0 @@
-#[derive(Default, Clone)]
-pub struct AllocateMappedFileService;
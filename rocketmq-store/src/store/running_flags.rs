@@ -137,6 +137,16 @@ impl RunningFlags {
         result
     }
 
+    pub fn is_disk_full(&self) -> bool {
+        let flags = self.flag_bits.load(Ordering::Acquire);
+        flags & DISK_FULL_BIT != 0
+    }
+
+    pub fn is_logic_disk_full(&self) -> bool {
+        let flags = self.flag_bits.load(Ordering::Acquire);
+        flags & LOGIC_DISK_FULL_BIT != 0
+    }
+
     pub fn get_and_make_disk_ok(&self) -> bool {
         (self.flag_bits.fetch_and(!(DISK_FULL_BIT), Ordering::AcqRel)) == 0
     }
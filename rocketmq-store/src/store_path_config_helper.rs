@@ -38,6 +38,14 @@ pub fn get_store_path_batch_consume_queue(root_dir: &str) -> String {
         .into_owned()
 }
 
+pub fn get_store_path_compaction_log(root_dir: &str) -> String {
+    PathBuf::from(root_dir)
+        .join("compaction")
+        .join("compactionLog")
+        .to_string_lossy()
+        .into_owned()
+}
+
 pub fn get_store_path_index(root_dir: &str) -> String {
     PathBuf::from(root_dir)
         .join("index")
@@ -66,6 +74,21 @@ pub fn get_lock_file(root_dir: &str) -> String {
         .into_owned()
 }
 
+pub fn get_recovery_progress_path(root_dir: &str) -> String {
+    PathBuf::from(root_dir)
+        .join("config")
+        .join("recoveryProgress.json")
+        .to_string_lossy()
+        .into_owned()
+}
+
+pub fn get_epoch_file_path(root_dir: &str) -> String {
+    PathBuf::from(root_dir)
+        .join("epochFileCheckpoint")
+        .to_string_lossy()
+        .into_owned()
+}
+
 pub fn get_delay_offset_store_path(root_dir: &str) -> String {
     PathBuf::from(root_dir)
         .join("config")
@@ -74,6 +97,28 @@ pub fn get_delay_offset_store_path(root_dir: &str) -> String {
         .into_owned()
 }
 
+pub fn get_timer_log_path(root_dir: &str) -> String {
+    PathBuf::from(root_dir)
+        .join("timerlog")
+        .to_string_lossy()
+        .into_owned()
+}
+
+pub fn get_timer_wheel_path(root_dir: &str) -> String {
+    PathBuf::from(root_dir)
+        .join("timerwheel")
+        .to_string_lossy()
+        .into_owned()
+}
+
+pub fn get_timer_check_path(root_dir: &str) -> String {
+    PathBuf::from(root_dir)
+        .join("config")
+        .join("timercheck")
+        .to_string_lossy()
+        .into_owned()
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -140,5 +185,13 @@ mod tests {
                 .to_string_lossy()
                 .into_owned()
         );
+        assert_eq!(
+            get_timer_check_path(root_dir),
+            PathBuf::from(root_dir)
+                .join("config")
+                .join("timercheck")
+                .to_string_lossy()
+                .into_owned()
+        );
     }
 }
@@ -0,0 +1,178 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::Read;
+use std::io::Seek;
+use std::io::SeekFrom;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+
+use bytes::Bytes;
+
+use crate::tiered::tiered_store_provider::TieredObject;
+use crate::tiered::tiered_store_provider::TieredStoreProvider;
+
+const TMP_SUFFIX: &str = ".tmp";
+
+/// Keeps tiered objects as files under a local directory, typically a mounted network file
+/// system.
+pub struct PosixProvider {
+    root_dir: PathBuf,
+}
+
+impl PosixProvider {
+    pub fn new(root_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            root_dir: root_dir.into(),
+        }
+    }
+
+    fn path_of(&self, key: &str) -> PathBuf {
+        self.root_dir.join(key)
+    }
+
+    fn collect(&self, dir: &Path, prefix: &str, objects: &mut Vec<TieredObject>) -> io::Result<()> {
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(err) => return Err(err),
+        };
+        for entry in entries {
+            let entry = entry?;
+            let path = entry.path();
+            let metadata = entry.metadata()?;
+            if metadata.is_dir() {
+                self.collect(&path, prefix, objects)?;
+                continue;
+            }
+            let Ok(relative) = path.strip_prefix(&self.root_dir) else {
+                continue;
+            };
+            let key = relative
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            if key.starts_with(prefix) && !key.ends_with(TMP_SUFFIX) {
+                objects.push(TieredObject {
+                    key,
+                    size: metadata.len(),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+impl TieredStoreProvider for PosixProvider {
+    fn name(&self) -> &'static str {
+        "posix"
+    }
+
+    fn put(&self, key: &str, data: &[u8]) -> io::Result<()> {
+        let path = self.path_of(key);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        // written aside and renamed so a crash never leaves a truncated object behind
+        let tmp_path = PathBuf::from(format!("{}{}", path.display(), TMP_SUFFIX));
+        let mut file = File::create(&tmp_path)?;
+        file.write_all(data)?;
+        file.sync_all()?;
+        fs::rename(&tmp_path, &path)
+    }
+
+    fn read(&self, key: &str, position: u64, len: usize) -> io::Result<Bytes> {
+        let mut file = File::open(self.path_of(key))?;
+        file.seek(SeekFrom::Start(position))?;
+        let mut buf = vec![0u8; len];
+        file.read_exact(&mut buf)?;
+        Ok(Bytes::from(buf))
+    }
+
+    fn list(&self, prefix: &str) -> io::Result<Vec<TieredObject>> {
+        // only the directory the prefix points into has to be walked
+        let dir = match prefix.rfind('/') {
+            Some(index) => self.path_of(&prefix[..index]),
+            None => self.root_dir.clone(),
+        };
+        let mut objects = Vec::new();
+        self.collect(&dir, prefix, &mut objects)?;
+        objects.sort_by(|a, b| a.key.cmp(&b.key));
+        Ok(objects)
+    }
+
+    fn delete(&self, key: &str) -> io::Result<()> {
+        match fs::remove_file(self.path_of(key)) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn put_read_list_and_delete() {
+        let dir = tempfile::tempdir().unwrap();
+        let provider = PosixProvider::new(dir.path());
+        provider
+            .put("commitlog/00000000000000001024", b"0123456789")
+            .unwrap();
+        provider
+            .put("commitlog/00000000000000000000", b"abc")
+            .unwrap();
+        provider
+            .put("consumequeue/TopicTest/0/00000000000000000000", b"x")
+            .unwrap();
+
+        assert_eq!(
+            provider
+                .read("commitlog/00000000000000001024", 3, 4)
+                .unwrap()
+                .as_ref(),
+            b"3456"
+        );
+        let objects = provider.list("commitlog/").unwrap();
+        assert_eq!(
+            objects,
+            vec![
+                TieredObject {
+                    key: "commitlog/00000000000000000000".to_string(),
+                    size: 3
+                },
+                TieredObject {
+                    key: "commitlog/00000000000000001024".to_string(),
+                    size: 10
+                },
+            ]
+        );
+        assert_eq!(provider.list("consumequeue/").unwrap().len(), 1);
+
+        provider.delete("commitlog/00000000000000000000").unwrap();
+        provider.delete("commitlog/00000000000000000000").unwrap();
+        assert_eq!(provider.list("commitlog/").unwrap().len(), 1);
+        assert!(provider
+            .read("commitlog/00000000000000001024", 8, 4)
+            .is_err());
+    }
+}
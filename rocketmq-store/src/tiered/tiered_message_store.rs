@@ -0,0 +1,539 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::io;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use bytes::Buf;
+use bytes::BufMut;
+use bytes::Bytes;
+use bytes::BytesMut;
+use cheetah_string::CheetahString;
+use parking_lot::RwLock;
+use rocketmq_common::common::message::MessageConst;
+use rocketmq_common::common::sys_flag::message_sys_flag::MessageSysFlag;
+use tracing::error;
+use tracing::info;
+use tracing::warn;
+
+use crate::base::decode_failure_stats::DecodeFailureStats;
+use crate::base::get_message_result::GetMessageResult;
+use crate::base::message_status_enum::GetMessageStatus;
+use crate::base::select_result::SelectMappedBufferResult;
+use crate::config::message_store_config::MessageStoreConfig;
+use crate::log_file::commit_log;
+use crate::log_file::commit_log::CommitLog;
+use crate::log_file::mapped_file::MappedFile;
+use crate::tiered::tiered_store_provider::TieredStoreProvider;
+
+const COMMIT_LOG_PREFIX: &str = "commitlog/";
+const CONSUME_QUEUE_PREFIX: &str = "consumequeue/";
+const INDEX_PREFIX: &str = "index/";
+/// queue offset, commit log offset and size of a message
+const CQ_UNIT_SIZE: usize = 20;
+
+fn commit_log_key(base_offset: i64) -> String {
+    format!("{}{:020}", COMMIT_LOG_PREFIX, base_offset)
+}
+
+fn consume_queue_key(topic: &str, queue_id: i32, base_offset: i64) -> String {
+    format!(
+        "{}{}/{}/{:020}",
+        CONSUME_QUEUE_PREFIX, topic, queue_id, base_offset
+    )
+}
+
+fn index_key(base_offset: i64) -> String {
+    format!("{}{:020}", INDEX_PREFIX, base_offset)
+}
+
+/// The units one commit log segment contributed to a queue.
+#[derive(Debug, Clone)]
+struct QueueSegment {
+    key: String,
+    min_queue_offset: i64,
+    units: usize,
+}
+
+impl QueueSegment {
+    fn max_queue_offset(&self) -> i64 {
+        self.min_queue_offset + self.units as i64
+    }
+}
+
+#[derive(Default)]
+struct TieredMetadata {
+    /// Base offset to size of every uploaded commit log segment.
+    commit_log: BTreeMap<i64, u64>,
+    /// Per queue, the uploaded segments keyed by their first queue offset.
+    queues: HashMap<(CheetahString, i32), BTreeMap<i64, QueueSegment>>,
+}
+
+/// Moves sealed commit log segments to a [`TieredStoreProvider`] and serves reads of messages
+/// that already expired locally from there.
+///
+/// Every commit log segment is uploaded together with the consume queue units and the key
+/// index of the messages it holds, both rebuilt from the segment itself. The commit log
+/// object goes up last, a segment whose commit log object is listed is complete.
+pub struct TieredMessageStore {
+    message_store_config: Arc<MessageStoreConfig>,
+    provider: Arc<dyn TieredStoreProvider>,
+    metadata: RwLock<TieredMetadata>,
+    decode_failure_stats: DecodeFailureStats,
+    uploaded_segments: AtomicU64,
+    read_messages: AtomicU64,
+}
+
+impl TieredMessageStore {
+    pub fn new(
+        message_store_config: Arc<MessageStoreConfig>,
+        provider: Arc<dyn TieredStoreProvider>,
+    ) -> Self {
+        Self {
+            message_store_config,
+            provider,
+            metadata: RwLock::new(TieredMetadata::default()),
+            decode_failure_stats: DecodeFailureStats::default(),
+            uploaded_segments: AtomicU64::new(0),
+            read_messages: AtomicU64::new(0),
+        }
+    }
+
+    /// Rebuilds the segment metadata from what the provider holds.
+    pub fn load(&self) -> bool {
+        match self.load_metadata() {
+            Ok(metadata) => {
+                info!(
+                    "load tiered store from {} provider, {} commit log segments",
+                    self.provider.name(),
+                    metadata.commit_log.len()
+                );
+                *self.metadata.write() = metadata;
+                true
+            }
+            Err(err) => {
+                error!("load tiered store failed: {}", err);
+                false
+            }
+        }
+    }
+
+    fn load_metadata(&self) -> io::Result<TieredMetadata> {
+        let mut metadata = TieredMetadata::default();
+        for object in self.provider.list(COMMIT_LOG_PREFIX)? {
+            if let Ok(base_offset) = object.key[COMMIT_LOG_PREFIX.len()..].parse::<i64>() {
+                metadata.commit_log.insert(base_offset, object.size);
+            }
+        }
+        for object in self.provider.list(CONSUME_QUEUE_PREFIX)? {
+            let mut parts = object.key[CONSUME_QUEUE_PREFIX.len()..].rsplitn(3, '/');
+            let (Some(base_offset), Some(queue_id), Some(topic)) =
+                (parts.next(), parts.next(), parts.next())
+            else {
+                continue;
+            };
+            let (Ok(base_offset), Ok(queue_id)) =
+                (base_offset.parse::<i64>(), queue_id.parse::<i32>())
+            else {
+                continue;
+            };
+            // units of a segment whose commit log upload did not finish are uploaded again
+            if !metadata.commit_log.contains_key(&base_offset) || object.size == 0 {
+                continue;
+            }
+            let min_queue_offset = self.provider.read(&object.key, 0, 8)?.get_i64();
+            metadata
+                .queues
+                .entry((CheetahString::from(topic), queue_id))
+                .or_default()
+                .insert(
+                    min_queue_offset,
+                    QueueSegment {
+                        key: object.key,
+                        min_queue_offset,
+                        units: object.size as usize / CQ_UNIT_SIZE,
+                    },
+                );
+        }
+        Ok(metadata)
+    }
+
+    /// Uploads the sealed commit log segments not in the tier yet, oldest first, and returns
+    /// how many were uploaded.
+    pub fn upload_sealed_segments(&self, commit_log: &CommitLog) -> usize {
+        let confirm_offset = commit_log.get_confirm_offset();
+        let mut uploaded = 0;
+        for mapped_file in commit_log.sealed_mapped_files() {
+            let base_offset = mapped_file.get_file_from_offset() as i64;
+            if self.metadata.read().commit_log.contains_key(&base_offset) {
+                continue;
+            }
+            if base_offset + mapped_file.get_file_size() as i64 > confirm_offset {
+                break;
+            }
+            let max_offset = self.get_max_offset();
+            if max_offset >= 0 && max_offset < base_offset {
+                warn!(
+                    "commit log [{}, {}) expired before it was uploaded to the tiered store",
+                    max_offset, base_offset
+                );
+            }
+            let Some(data) = mapped_file.get_data(0, mapped_file.get_read_position() as usize)
+            else {
+                break;
+            };
+            if let Err(err) = self.upload_segment(base_offset, data) {
+                error!(
+                    "upload commit log segment {} to the tiered store failed: {}",
+                    mapped_file.get_file_name(),
+                    err
+                );
+                break;
+            }
+            uploaded += 1;
+        }
+        uploaded
+    }
+
+    fn upload_segment(&self, base_offset: i64, data: Bytes) -> io::Result<()> {
+        let mut queue_units: HashMap<(CheetahString, i32), BytesMut> = HashMap::new();
+        let mut index = BytesMut::new();
+        let mut pos = 0;
+        while pos + 8 <= data.len() {
+            let total_size = (&data[pos..pos + 4]).get_i32();
+            if total_size <= 0 || pos + total_size as usize > data.len() {
+                break;
+            }
+            let mut message = data.slice(pos..pos + total_size as usize);
+            let request = commit_log::check_message_and_return_size(
+                &mut message,
+                false,
+                false,
+                false,
+                &self.message_store_config,
+                &self.decode_failure_stats,
+            );
+            // a blank message pads the end of the file
+            if !request.success || request.msg_size <= 0 {
+                break;
+            }
+            pos += total_size as usize;
+            let tran_type = MessageSysFlag::get_transaction_value(request.sys_flag);
+            if tran_type != MessageSysFlag::TRANSACTION_NOT_TYPE
+                && tran_type != MessageSysFlag::TRANSACTION_COMMIT_TYPE
+            {
+                continue;
+            }
+            let units = queue_units
+                .entry((request.topic.clone(), request.queue_id))
+                .or_default();
+            units.put_i64(request.consume_queue_offset);
+            units.put_i64(request.commit_log_offset);
+            units.put_i32(request.msg_size);
+            let keys = request
+                .keys
+                .split(MessageConst::KEY_SEPARATOR)
+                .chain(request.uniq_key.as_ref().map(|key| key.as_str()));
+            for key in keys.filter(|key| !key.is_empty()) {
+                index.put_u16(request.topic.len() as u16);
+                index.put_slice(request.topic.as_bytes());
+                index.put_u16(key.len() as u16);
+                index.put_slice(key.as_bytes());
+                index.put_i64(request.commit_log_offset);
+                index.put_i32(request.msg_size);
+            }
+        }
+
+        let mut segments = Vec::with_capacity(queue_units.len());
+        for ((topic, queue_id), units) in queue_units {
+            let key = consume_queue_key(topic.as_str(), queue_id, base_offset);
+            self.provider.put(&key, &units)?;
+            segments.push((
+                (topic, queue_id),
+                QueueSegment {
+                    key,
+                    min_queue_offset: (&units[..8]).get_i64(),
+                    units: units.len() / CQ_UNIT_SIZE,
+                },
+            ));
+        }
+        if !index.is_empty() {
+            self.provider.put(&index_key(base_offset), &index)?;
+        }
+        self.provider.put(&commit_log_key(base_offset), &data)?;
+
+        let mut metadata = self.metadata.write();
+        metadata.commit_log.insert(base_offset, data.len() as u64);
+        for (queue, segment) in segments {
+            metadata
+                .queues
+                .entry(queue)
+                .or_default()
+                .insert(segment.min_queue_offset, segment);
+        }
+        self.uploaded_segments.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Reads up to `max_msg_nums` messages of the queue starting at `offset` from the tier,
+    /// `None` when the tier does not hold `offset`.
+    pub fn get_message(
+        &self,
+        topic: &CheetahString,
+        queue_id: i32,
+        offset: i64,
+        max_msg_nums: i32,
+        max_total_msg_size: i32,
+    ) -> Option<GetMessageResult> {
+        let (segment, min_offset) = {
+            let metadata = self.metadata.read();
+            let segments = metadata.queues.get(&(topic.clone(), queue_id))?;
+            let (_, segment) = segments.range(..=offset).next_back()?;
+            if offset >= segment.max_queue_offset() {
+                return None;
+            }
+            (segment.clone(), *segments.keys().next()?)
+        };
+        let skip = (offset - segment.min_queue_offset) as usize;
+        let count = (segment.units - skip).min(max_msg_nums.max(1) as usize);
+        let units = match self.provider.read(
+            &segment.key,
+            (skip * CQ_UNIT_SIZE) as u64,
+            count * CQ_UNIT_SIZE,
+        ) {
+            Ok(units) => units,
+            Err(err) => {
+                error!("read tiered consume queue {} failed: {}", segment.key, err);
+                return None;
+            }
+        };
+
+        let mut result = GetMessageResult::new();
+        let mut next_begin_offset = offset;
+        for mut unit in units.chunks(CQ_UNIT_SIZE) {
+            let queue_offset = unit.get_i64();
+            let commit_log_offset = unit.get_i64();
+            let size = unit.get_i32();
+            if result.buffer_total_size() > 0
+                && result.buffer_total_size() + size > max_total_msg_size
+            {
+                break;
+            }
+            let Some(base_offset) = self
+                .metadata
+                .read()
+                .commit_log
+                .range(..=commit_log_offset)
+                .next_back()
+                .map(|(base_offset, _)| *base_offset)
+            else {
+                break;
+            };
+            let data = match self.provider.read(
+                &commit_log_key(base_offset),
+                (commit_log_offset - base_offset) as u64,
+                size as usize,
+            ) {
+                Ok(data) => data,
+                Err(err) => {
+                    error!(
+                        "read tiered commit log at {} failed: {}",
+                        commit_log_offset, err
+                    );
+                    break;
+                }
+            };
+            result.add_message(
+                SelectMappedBufferResult::from_bytes(commit_log_offset as u64, data),
+                queue_offset as u64,
+                1,
+            );
+            next_begin_offset = queue_offset + 1;
+        }
+        if result.message_count() == 0 {
+            return None;
+        }
+        self.read_messages
+            .fetch_add(result.message_count() as u64, Ordering::Relaxed);
+        result.set_status(Some(GetMessageStatus::Found));
+        result.set_next_begin_offset(next_begin_offset);
+        result.set_min_offset(min_offset);
+        Some(result)
+    }
+
+    /// Messages of `topic` whose key or unique key is `key` found in the uploaded indexes,
+    /// newest segments first.
+    pub fn query_message(&self, topic: &str, key: &str, max_num: usize) -> Vec<Bytes> {
+        let mut messages = Vec::new();
+        let objects = match self.provider.list(INDEX_PREFIX) {
+            Ok(objects) => objects,
+            Err(err) => {
+                error!("list tiered indexes failed: {}", err);
+                return messages;
+            }
+        };
+        for object in objects.iter().rev() {
+            let Ok(mut index) = self.provider.read(&object.key, 0, object.size as usize) else {
+                continue;
+            };
+            let base_offset = object.key[INDEX_PREFIX.len()..].parse::<i64>().unwrap_or(0);
+            while index.remaining() > 2 && messages.len() < max_num {
+                let topic_len = index.get_u16() as usize;
+                let entry_topic = index.split_to(topic_len);
+                let key_len = index.get_u16() as usize;
+                let entry_key = index.split_to(key_len);
+                let commit_log_offset = index.get_i64();
+                let size = index.get_i32();
+                if entry_topic != topic.as_bytes() || entry_key != key.as_bytes() {
+                    continue;
+                }
+                if let Ok(data) = self.provider.read(
+                    &commit_log_key(base_offset),
+                    (commit_log_offset - base_offset) as u64,
+                    size as usize,
+                ) {
+                    messages.push(data);
+                }
+            }
+            if messages.len() >= max_num {
+                break;
+            }
+        }
+        messages
+    }
+
+    /// Smallest commit log offset held by the tier, `-1` when it is empty.
+    pub fn get_min_offset(&self) -> i64 {
+        self.metadata
+            .read()
+            .commit_log
+            .keys()
+            .next()
+            .copied()
+            .unwrap_or(-1)
+    }
+
+    /// End of the uploaded commit log, `-1` when the tier is empty.
+    pub fn get_max_offset(&self) -> i64 {
+        self.metadata
+            .read()
+            .commit_log
+            .iter()
+            .next_back()
+            .map(|(base_offset, size)| base_offset + *size as i64)
+            .unwrap_or(-1)
+    }
+
+    pub fn uploaded_segments(&self) -> u64 {
+        self.uploaded_segments.load(Ordering::Relaxed)
+    }
+
+    pub fn read_messages(&self) -> u64 {
+        self.read_messages.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rocketmq_common::common::message::message_ext::MessageExt;
+    use rocketmq_common::MessageDecoder;
+
+    use super::*;
+    use crate::log_file::commit_log::BLANK_MAGIC_CODE;
+    use crate::tiered::posix_provider::PosixProvider;
+
+    const BASE_OFFSET: i64 = 1024;
+
+    fn append(segment: &mut BytesMut, queue_id: i32, queue_offset: i64, keys: &str) {
+        let mut message = MessageExt::default();
+        message.set_topic(CheetahString::from_static_str("TieredTopic"));
+        message.set_queue_id(queue_id);
+        message.set_queue_offset(queue_offset);
+        message.set_commit_log_offset(BASE_OFFSET + segment.len() as i64);
+        message.set_body(Bytes::from(format!("body-{}-{}", queue_id, queue_offset)));
+        message.put_property(
+            CheetahString::from_static_str(MessageConst::PROPERTY_KEYS),
+            CheetahString::from(keys),
+        );
+        message.store_size = MessageDecoder::encode(&message, false).unwrap().len() as i32;
+        segment.extend_from_slice(&MessageDecoder::encode(&message, false).unwrap());
+    }
+
+    fn bodies(result: &GetMessageResult) -> Vec<Bytes> {
+        result
+            .message_mapped_list()
+            .iter()
+            .filter_map(|buffer| {
+                let mut data = buffer.get_bytes()?;
+                MessageDecoder::decode(&mut data, true, false, false, false, false)?
+                    .get_body()
+                    .cloned()
+            })
+            .collect()
+    }
+
+    fn assert_reads(store: &TieredMessageStore) {
+        let topic = CheetahString::from_static_str("TieredTopic");
+        let result = store.get_message(&topic, 0, 10, 32, i32::MAX).unwrap();
+        assert_eq!(result.status(), Some(GetMessageStatus::Found));
+        assert_eq!(result.next_begin_offset(), 12);
+        assert_eq!(result.min_offset(), 10);
+        assert_eq!(
+            bodies(&result),
+            vec![Bytes::from("body-0-10"), Bytes::from("body-0-11")]
+        );
+        let result = store.get_message(&topic, 0, 11, 32, i32::MAX).unwrap();
+        assert_eq!(result.message_queue_offset(), &[11]);
+        assert!(store.get_message(&topic, 0, 12, 32, i32::MAX).is_none());
+        assert!(store.get_message(&topic, 0, 9, 32, i32::MAX).is_none());
+        let result = store.get_message(&topic, 1, 0, 32, i32::MAX).unwrap();
+        assert_eq!(bodies(&result), vec![Bytes::from("body-1-0")]);
+
+        assert_eq!(store.query_message("TieredTopic", "k1", 32).len(), 2);
+        assert_eq!(store.query_message("TieredTopic", "k3", 32).len(), 1);
+        assert!(store.query_message("OtherTopic", "k1", 32).is_empty());
+        assert_eq!(store.get_min_offset(), BASE_OFFSET);
+    }
+
+    #[test]
+    fn uploaded_segment_serves_reads_after_reload() {
+        let dir = tempfile::tempdir().unwrap();
+        let provider: Arc<dyn TieredStoreProvider> = Arc::new(PosixProvider::new(dir.path()));
+        let config = Arc::new(MessageStoreConfig::default());
+        let store = TieredMessageStore::new(config.clone(), provider.clone());
+
+        let mut segment = BytesMut::new();
+        append(&mut segment, 0, 10, "k1");
+        append(&mut segment, 1, 0, "k2");
+        append(&mut segment, 0, 11, "k1 k3");
+        // blank message padding the end of the file
+        segment.put_i32(64);
+        segment.put_i32(BLANK_MAGIC_CODE);
+        segment.resize(segment.len() + 56, 0);
+        let segment_size = segment.len() as i64;
+        store.upload_segment(BASE_OFFSET, segment.freeze()).unwrap();
+        assert_eq!(store.get_max_offset(), BASE_OFFSET + segment_size);
+        assert_reads(&store);
+
+        let reloaded = TieredMessageStore::new(config, provider);
+        assert!(reloaded.load());
+        assert_reads(&reloaded);
+    }
+}
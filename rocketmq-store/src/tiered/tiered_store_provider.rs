@@ -0,0 +1,63 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::io;
+use std::sync::Arc;
+
+use bytes::Bytes;
+
+use crate::config::message_store_config::MessageStoreConfig;
+use crate::tiered::posix_provider::PosixProvider;
+
+/// An object kept by a tiered storage backend.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TieredObject {
+    pub key: String,
+    pub size: u64,
+}
+
+/// Object storage the tiered store uploads sealed segments to.
+///
+/// Keys are `/` separated paths. Objects are written once and never modified, `put` must not
+/// make a partially written object visible, so a segment listed by `list` is always complete.
+pub trait TieredStoreProvider: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    fn put(&self, key: &str, data: &[u8]) -> io::Result<()>;
+
+    /// Reads `len` bytes of `key` starting at `position`.
+    fn read(&self, key: &str, position: u64, len: usize) -> io::Result<Bytes>;
+
+    /// Every object whose key starts with `prefix`, in key order.
+    fn list(&self, prefix: &str) -> io::Result<Vec<TieredObject>>;
+
+    fn delete(&self, key: &str) -> io::Result<()>;
+}
+
+/// Creates the provider named by `tiered_store_provider`.
+pub fn create_provider(
+    message_store_config: &MessageStoreConfig,
+) -> io::Result<Arc<dyn TieredStoreProvider>> {
+    match message_store_config.tiered_store_provider.as_str() {
+        "posix" => Ok(Arc::new(PosixProvider::new(
+            message_store_config.get_tiered_store_path(),
+        ))),
+        other => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("unsupported tiered store provider {}", other),
+        )),
+    }
+}
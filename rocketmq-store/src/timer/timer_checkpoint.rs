@@ -0,0 +1,106 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::fs::OpenOptions;
+use std::path::Path;
+use std::sync::atomic::AtomicI64;
+use std::sync::atomic::Ordering;
+
+use memmap2::MmapMut;
+use parking_lot::Mutex;
+use rocketmq_common::UtilAll::ensure_dir_ok;
+use tracing::info;
+
+const CHECKPOINT_SIZE: u64 = 24;
+
+/// Progress of the timer message store persisted by the flush task, everything before these
+/// positions is known to be durable.
+pub struct TimerCheckpoint {
+    mmap: Mutex<MmapMut>,
+    last_read_time_ms: AtomicI64,
+    last_timer_log_flush_pos: AtomicI64,
+    last_timer_queue_offset: AtomicI64,
+}
+
+impl TimerCheckpoint {
+    pub fn new<P: AsRef<Path>>(path: P) -> std::io::Result<Self> {
+        if let Some(parent) = path.as_ref().parent() {
+            ensure_dir_ok(parent.to_string_lossy().as_ref());
+        }
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path.as_ref())?;
+        if file.metadata()?.len() < CHECKPOINT_SIZE {
+            file.set_len(CHECKPOINT_SIZE)?;
+        }
+        let mmap = unsafe { MmapMut::map_mut(&file)? };
+        let last_read_time_ms = i64::from_be_bytes(mmap[0..8].try_into().unwrap());
+        let last_timer_log_flush_pos = i64::from_be_bytes(mmap[8..16].try_into().unwrap());
+        let last_timer_queue_offset = i64::from_be_bytes(mmap[16..24].try_into().unwrap());
+        info!(
+            "timer checkpoint {}, lastReadTimeMs: {}, lastTimerLogFlushPos: {}, \
+             lastTimerQueueOffset: {}",
+            path.as_ref().display(),
+            last_read_time_ms,
+            last_timer_log_flush_pos,
+            last_timer_queue_offset
+        );
+        Ok(TimerCheckpoint {
+            mmap: Mutex::new(mmap),
+            last_read_time_ms: AtomicI64::new(last_read_time_ms),
+            last_timer_log_flush_pos: AtomicI64::new(last_timer_log_flush_pos),
+            last_timer_queue_offset: AtomicI64::new(last_timer_queue_offset),
+        })
+    }
+
+    pub fn flush(&self) -> std::io::Result<()> {
+        let mut mmap = self.mmap.lock();
+        mmap[0..8].copy_from_slice(&self.last_read_time_ms().to_be_bytes());
+        mmap[8..16].copy_from_slice(&self.last_timer_log_flush_pos().to_be_bytes());
+        mmap[16..24].copy_from_slice(&self.last_timer_queue_offset().to_be_bytes());
+        mmap.flush()
+    }
+
+    pub fn last_read_time_ms(&self) -> i64 {
+        self.last_read_time_ms.load(Ordering::Relaxed)
+    }
+
+    pub fn set_last_read_time_ms(&self, last_read_time_ms: i64) {
+        self.last_read_time_ms
+            .store(last_read_time_ms, Ordering::Relaxed);
+    }
+
+    pub fn last_timer_log_flush_pos(&self) -> i64 {
+        self.last_timer_log_flush_pos.load(Ordering::Relaxed)
+    }
+
+    pub fn set_last_timer_log_flush_pos(&self, last_timer_log_flush_pos: i64) {
+        self.last_timer_log_flush_pos
+            .store(last_timer_log_flush_pos, Ordering::Relaxed);
+    }
+
+    pub fn last_timer_queue_offset(&self) -> i64 {
+        self.last_timer_queue_offset.load(Ordering::Relaxed)
+    }
+
+    pub fn set_last_timer_queue_offset(&self, last_timer_queue_offset: i64) {
+        self.last_timer_queue_offset
+            .store(last_timer_queue_offset, Ordering::Relaxed);
+    }
+}
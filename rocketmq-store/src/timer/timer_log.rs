@@ -0,0 +1,241 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::sync::Arc;
+
+use bytes::Buf;
+use bytes::BufMut;
+use bytes::Bytes;
+use bytes::BytesMut;
+use tracing::info;
+
+use crate::consume_queue::mapped_file_queue::MappedFileQueue;
+use crate::log_file::mapped_file::default_mapped_file_impl::DefaultMappedFile;
+use crate::log_file::mapped_file::MappedFile;
+
+/// Size of one timer log unit, the layout matches the Java broker:
+/// `size(4) prev_pos(8) magic(4) curr_write_time(8) delayed_time(4) offset_py(8) size_py(4)
+/// hash_code_of_real_topic(4) reserved(8)`.
+pub const UNIT_SIZE: i32 = 52;
+
+/// One timer message linked into a wheel slot. Units of the same slot form a list through
+/// `prev_pos`, which is `-1` for the first unit of a slot.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TimerLogUnit {
+    pub prev_pos: i64,
+    pub magic: i32,
+    pub curr_write_time_ms: i64,
+    /// Delay relative to `curr_write_time_ms`.
+    pub delayed_time_ms: i32,
+    pub offset_py: i64,
+    pub size_py: i32,
+    pub hash_code_of_real_topic: i32,
+}
+
+impl TimerLogUnit {
+    pub fn delayed_at_ms(&self) -> i64 {
+        self.curr_write_time_ms + self.delayed_time_ms as i64
+    }
+
+    fn encode(&self) -> Bytes {
+        let mut buffer = BytesMut::with_capacity(UNIT_SIZE as usize);
+        buffer.put_i32(UNIT_SIZE);
+        buffer.put_i64(self.prev_pos);
+        buffer.put_i32(self.magic);
+        buffer.put_i64(self.curr_write_time_ms);
+        buffer.put_i32(self.delayed_time_ms);
+        buffer.put_i64(self.offset_py);
+        buffer.put_i32(self.size_py);
+        buffer.put_i32(self.hash_code_of_real_topic);
+        buffer.put_i64(0);
+        buffer.freeze()
+    }
+
+    fn decode(mut bytes: Bytes) -> Option<Self> {
+        if bytes.len() < UNIT_SIZE as usize || bytes.get_i32() != UNIT_SIZE {
+            return None;
+        }
+        Some(TimerLogUnit {
+            prev_pos: bytes.get_i64(),
+            magic: bytes.get_i32(),
+            curr_write_time_ms: bytes.get_i64(),
+            delayed_time_ms: bytes.get_i32(),
+            offset_py: bytes.get_i64(),
+            size_py: bytes.get_i32(),
+            hash_code_of_real_topic: bytes.get_i32(),
+        })
+    }
+}
+
+/// Append only log of timer units, units never span two files.
+pub struct TimerLog {
+    mapped_file_queue: MappedFileQueue,
+    file_size: u64,
+}
+
+impl TimerLog {
+    pub fn new(store_path: String, file_size: usize) -> Self {
+        // whole units only, the tail of a file that cannot hold one more unit stays unused
+        let file_size = file_size as u64;
+        TimerLog {
+            mapped_file_queue: MappedFileQueue::new(store_path, file_size, None),
+            file_size,
+        }
+    }
+
+    pub fn load(&mut self) -> bool {
+        let result = self.mapped_file_queue.load();
+        info!("load timer log {}", if result { "OK" } else { "Failed" });
+        result
+    }
+
+    /// Appends `unit` and returns its position.
+    pub fn append(&mut self, unit: &TimerLogUnit) -> Option<i64> {
+        let mut mapped_file = self
+            .mapped_file_queue
+            .get_last_mapped_file_mut_start_offset(0, true)?;
+        if mapped_file.get_wrote_position() as u64 + UNIT_SIZE as u64 > self.file_size {
+            mapped_file.set_wrote_position(self.file_size as i32);
+            mapped_file = self
+                .mapped_file_queue
+                .get_last_mapped_file_mut_start_offset(0, true)?;
+        }
+        let pos =
+            mapped_file.get_file_from_offset() as i64 + mapped_file.get_wrote_position() as i64;
+        if mapped_file.append_message_bytes(&unit.encode()) {
+            Some(pos)
+        } else {
+            None
+        }
+    }
+
+    pub fn get_unit(&self, pos: i64) -> Option<TimerLogUnit> {
+        if pos < self.min_offset() || pos + UNIT_SIZE as i64 > self.max_offset() {
+            return None;
+        }
+        let mapped_file = self
+            .mapped_file_queue
+            .find_mapped_file_by_offset(pos, false)?;
+        let relative_pos = (pos - mapped_file.get_file_from_offset() as i64) as usize;
+        TimerLogUnit::decode(mapped_file.get_bytes(relative_pos, UNIT_SIZE as usize)?)
+    }
+
+    pub fn min_offset(&self) -> i64 {
+        self.mapped_file_queue
+            .get_first_mapped_file()
+            .map_or(0, |mapped_file| mapped_file.get_file_from_offset() as i64)
+    }
+
+    pub fn max_offset(&self) -> i64 {
+        self.mapped_file_queue.get_max_offset()
+    }
+
+    /// Flushes everything appended so far and returns the flushed position.
+    pub fn flush(&self) -> i64 {
+        // each round flushes a single file
+        loop {
+            let flushed_where = self.mapped_file_queue.get_flushed_where();
+            if flushed_where >= self.max_offset() {
+                return flushed_where;
+            }
+            self.mapped_file_queue.flush(0);
+            if self.mapped_file_queue.get_flushed_where() == flushed_where {
+                return flushed_where;
+            }
+        }
+    }
+
+    /// Drops everything written at or after `offset`.
+    pub fn truncate(&mut self, offset: i64) {
+        self.mapped_file_queue.truncate_dirty_files(offset);
+        self.mapped_file_queue.set_flushed_where(offset);
+        self.mapped_file_queue.set_committed_where(offset);
+    }
+
+    /// Deletes the files that lie entirely before `offset`, the last file is always kept.
+    pub fn delete_files_before(&mut self, offset: i64) -> usize {
+        let expired: Vec<Arc<DefaultMappedFile>> = {
+            let mapped_files = self.mapped_file_queue.get_mapped_files();
+            let mapped_files = mapped_files.read();
+            let keep_from = mapped_files.len().saturating_sub(1);
+            mapped_files[..keep_from]
+                .iter()
+                .filter(|mapped_file| {
+                    (mapped_file.get_file_from_offset() + mapped_file.get_file_size()) as i64
+                        <= offset
+                })
+                .cloned()
+                .collect()
+        };
+        for mapped_file in &expired {
+            mapped_file.destroy(1000);
+            info!(
+                "delete expired timer log file {}",
+                mapped_file.get_file_name()
+            );
+        }
+        let deleted = expired.len();
+        if deleted > 0 {
+            self.mapped_file_queue.delete_expired_file(expired);
+        }
+        deleted
+    }
+
+    pub fn destroy(&mut self) {
+        self.mapped_file_queue.destroy();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use super::*;
+
+    fn unit(prev_pos: i64, offset_py: i64) -> TimerLogUnit {
+        TimerLogUnit {
+            prev_pos,
+            magic: 1,
+            curr_write_time_ms: 1_000,
+            delayed_time_ms: 2_000,
+            offset_py,
+            size_py: 100,
+            hash_code_of_real_topic: 7,
+        }
+    }
+
+    #[test]
+    fn append_skips_file_tail_that_cannot_hold_a_unit() {
+        let dir = tempdir().unwrap();
+        // room for two units, the remaining 20 bytes are left unused
+        let mut timer_log = TimerLog::new(
+            dir.path().to_string_lossy().to_string(),
+            UNIT_SIZE as usize * 2 + 20,
+        );
+        assert_eq!(timer_log.append(&unit(-1, 0)), Some(0));
+        assert_eq!(timer_log.append(&unit(0, 100)), Some(UNIT_SIZE as i64));
+        let third = timer_log.append(&unit(UNIT_SIZE as i64, 200)).unwrap();
+        assert_eq!(third, UNIT_SIZE as i64 * 2 + 20);
+
+        assert_eq!(timer_log.get_unit(third), Some(unit(UNIT_SIZE as i64, 200)));
+        assert_eq!(timer_log.get_unit(0).unwrap().delayed_at_ms(), 3_000);
+        assert!(timer_log.get_unit(third + UNIT_SIZE as i64).is_none());
+
+        timer_log.truncate(third);
+        assert!(timer_log.get_unit(third).is_none());
+        assert_eq!(timer_log.max_offset(), third);
+    }
+}
@@ -14,15 +14,43 @@
  * See the License for the specific language governing permissions and
  * limitations under the License.
  */
+use std::sync::atomic::AtomicBool;
 use std::sync::atomic::AtomicI64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
 
 use cheetah_string::CheetahString;
+use parking_lot::Mutex;
+use rocketmq_common::common::message::message_ext::MessageExt;
+use rocketmq_common::common::message::message_ext_broker_inner::MessageExtBrokerInner;
 use rocketmq_common::common::message::MessageConst;
+use rocketmq_common::common::message::MessageTrait;
+use rocketmq_common::common::sys_flag::message_sys_flag::MessageSysFlag;
 use rocketmq_common::common::system_clock::SystemClock;
+use rocketmq_common::MessageAccessor::MessageAccessor;
+use rocketmq_common::MessageDecoder::message_properties_to_string;
+use rocketmq_common::TimeUtils::get_current_millis;
 use rocketmq_rust::ArcMut;
+use tracing::error;
+use tracing::info;
+use tracing::warn;
 
+use crate::base::message_result::PutMessageResult;
+use crate::base::message_status_enum::PutMessageStatus;
+use crate::config::message_store_config::MessageStoreConfig;
 use crate::log_file::MessageStore;
 use crate::message_store::default_message_store::DefaultMessageStore;
+use crate::store_path_config_helper::get_timer_check_path;
+use crate::store_path_config_helper::get_timer_log_path;
+use crate::store_path_config_helper::get_timer_wheel_path;
+use crate::timer::timer_checkpoint::TimerCheckpoint;
+use crate::timer::timer_log::TimerLog;
+use crate::timer::timer_log::TimerLogUnit;
+use crate::timer::timer_metrics::TimerMetrics;
+use crate::timer::timer_metrics::TimerStatus;
+use crate::timer::timer_wheel::Slot;
+use crate::timer::timer_wheel::TimerWheel;
 
 pub const TIMER_TOPIC: &str = concat!("rmq_sys_", "wheel_timer");
 pub const TIMER_OUT_MS: &str = MessageConst::PROPERTY_TIMER_OUT_MS;
@@ -45,37 +73,577 @@ pub const MAGIC_DEFAULT: i32 = 1;
 pub const MAGIC_ROLL: i32 = 1 << 1;
 pub const MAGIC_DELETE: i32 = 1 << 2;
 
-pub struct TimerMessageStore {
-    pub curr_read_time_ms: AtomicI64,
-    pub curr_queue_offset: AtomicI64,
-    pub default_message_store: Option<ArcMut<DefaultMessageStore>>,
+const ENQUEUE_BATCH_SIZE: usize = 32;
+const IDLE_WAIT_MS: u64 = 100;
+const PUT_RETRY_WAIT_MS: u64 = 50;
+// the flush task cleans expired timer log files once every so many rounds
+const CLEAN_TIMER_LOG_INTERVAL: u64 = 60;
+
+/// Timer log, wheel and checkpoint, only present once the store is loaded.
+struct TimerState {
+    timer_log: TimerLog,
+    timer_wheel: TimerWheel,
+    timer_checkpoint: TimerCheckpoint,
+    precision_ms: i64,
+    roll_window_ms: i64,
 }
 
-impl Clone for TimerMessageStore {
-    fn clone(&self) -> Self {
-        Self {
-            curr_read_time_ms: AtomicI64::new(
-                self.curr_read_time_ms
-                    .load(std::sync::atomic::Ordering::Relaxed),
-            ),
-            curr_queue_offset: AtomicI64::new(
-                self.curr_queue_offset
-                    .load(std::sync::atomic::Ordering::Relaxed),
-            ),
-            default_message_store: self.default_message_store.clone(),
+impl TimerState {
+    /// Links a message due at `delayed_ms` into its slot. Messages due beyond the roll window
+    /// are parked in the last slot of the window and rolled over when it is read.
+    fn enqueue(
+        &mut self,
+        read_time_ms: i64,
+        delayed_ms: i64,
+        offset_py: i64,
+        size_py: i32,
+        hash_code_of_real_topic: i32,
+    ) -> bool {
+        let mut magic = MAGIC_DEFAULT;
+        // the slot at the read time may already be taken by the dequeue task
+        let mut target_ms = delayed_ms.max(read_time_ms + self.precision_ms);
+        if target_ms >= read_time_ms + self.roll_window_ms {
+            target_ms = read_time_ms + self.roll_window_ms - self.precision_ms;
+            magic |= MAGIC_ROLL;
+        }
+        target_ms = target_ms / self.precision_ms * self.precision_ms;
+
+        let now = get_current_millis() as i64;
+        let slot = self.timer_wheel.get_slot(target_ms);
+        let unit = TimerLogUnit {
+            prev_pos: slot.last_pos,
+            magic,
+            curr_write_time_ms: now,
+            delayed_time_ms: (target_ms - now) as i32,
+            offset_py,
+            size_py,
+            hash_code_of_real_topic,
+        };
+        let Some(pos) = self.timer_log.append(&unit) else {
+            error!("append timer log failed, offsetPy: {}", offset_py);
+            return false;
+        };
+        self.timer_wheel.put_slot(&Slot {
+            time_ms: target_ms,
+            first_pos: if slot.first_pos < 0 {
+                pos
+            } else {
+                slot.first_pos
+            },
+            last_pos: pos,
+            num: slot.num + 1,
+            magic: slot.magic,
+        });
+        true
+    }
+
+    /// Units of the slot due at `time_ms`, in enqueue order.
+    fn collect_slot(&self, time_ms: i64) -> Vec<TimerLogUnit> {
+        let slot = self.timer_wheel.get_slot(time_ms);
+        let mut units = Vec::with_capacity(slot.num.max(0) as usize);
+        let mut pos = slot.last_pos;
+        while pos >= 0 && units.len() < slot.num as usize {
+            match self.timer_log.get_unit(pos) {
+                Some(unit) => {
+                    pos = unit.prev_pos;
+                    units.push(unit);
+                }
+                None => {
+                    error!(
+                        "timer log unit at {} of slot {} is unreadable, {} of {} units recovered",
+                        pos,
+                        time_ms,
+                        units.len(),
+                        slot.num
+                    );
+                    break;
+                }
+            }
         }
+        units.reverse();
+        units
+    }
+
+    /// Unlinks the units written after the last flushed position from the wheel and truncates
+    /// the timer log there, they are enqueued again from the checkpointed queue offset.
+    fn recover(&mut self) {
+        let flush_pos = self.timer_checkpoint.last_timer_log_flush_pos();
+        for mut slot in self.timer_wheel.live_slots() {
+            if slot.last_pos < flush_pos {
+                continue;
+            }
+            let mut pos = slot.last_pos;
+            while pos >= flush_pos {
+                match self.timer_log.get_unit(pos) {
+                    Some(unit) => {
+                        pos = unit.prev_pos;
+                        slot.num -= 1;
+                    }
+                    None => {
+                        error!(
+                            "timer log unit at {} of slot {} is unreadable, drop the slot",
+                            pos, slot.time_ms
+                        );
+                        pos = -1;
+                        slot.num = 0;
+                    }
+                }
+            }
+            if pos < 0 || slot.num <= 0 {
+                slot = Slot::empty(slot.time_ms);
+            } else {
+                slot.last_pos = pos;
+            }
+            self.timer_wheel.put_slot(&slot);
+        }
+        self.timer_log.truncate(flush_pos);
+    }
+
+    /// Smallest timer log position still referenced by a slot due at or after `read_time_ms`.
+    fn min_live_pos(&self, read_time_ms: i64) -> i64 {
+        self.timer_wheel
+            .live_slots()
+            .iter()
+            .filter(|slot| slot.time_ms >= read_time_ms)
+            .map(|slot| slot.first_pos)
+            .min()
+            .unwrap_or_else(|| self.timer_log.max_offset())
     }
 }
 
+/// Timer wheel store of `rmq_sys_wheel_timer`, delivering messages at the time set by
+/// `TIMER_DELIVER_MS`, `TIMER_DELAY_SEC` or `TIMER_DELAY_MS`.
+///
+/// Timer messages are first written to the timer topic. The enqueue task reads them from its
+/// consume queue into the timer log and links them into the wheel slot of their delivery
+/// time, the dequeue task puts the messages of every elapsed slot back to their real topic.
+#[derive(Clone)]
+pub struct TimerMessageStore {
+    pub curr_read_time_ms: Arc<AtomicI64>,
+    pub curr_queue_offset: Arc<AtomicI64>,
+    pub default_message_store: Option<ArcMut<DefaultMessageStore>>,
+    timer_metrics: Arc<TimerMetrics>,
+    state: Arc<Mutex<Option<TimerState>>>,
+    running: Arc<AtomicBool>,
+}
+
 impl TimerMessageStore {
-    pub fn load(&mut self) -> bool {
+    pub fn load(&self) -> bool {
+        let Some(store) = self.default_message_store.as_ref() else {
+            return true;
+        };
+        let config = store.message_store_config();
+        let root_dir = config.store_path_root_dir.as_str();
+        let precision_ms = config.timer_precision_ms.max(1) as i64;
+        let roll_window_slot = config.timer_roll_window_slot.max(2) as i64;
+
+        let mut timer_log = TimerLog::new(
+            get_timer_log_path(root_dir),
+            config.mapped_file_size_timer_log as usize,
+        );
+        if !timer_log.load() {
+            return false;
+        }
+        let timer_wheel = match TimerWheel::new(
+            get_timer_wheel_path(root_dir),
+            (roll_window_slot * 2) as usize,
+            precision_ms as u64,
+        ) {
+            Ok(timer_wheel) => timer_wheel,
+            Err(e) => {
+                error!("load timer wheel failed: {}", e);
+                return false;
+            }
+        };
+        let timer_checkpoint = match TimerCheckpoint::new(get_timer_check_path(root_dir)) {
+            Ok(timer_checkpoint) => timer_checkpoint,
+            Err(e) => {
+                error!("load timer checkpoint failed: {}", e);
+                return false;
+            }
+        };
+
+        let mut state = TimerState {
+            timer_log,
+            timer_wheel,
+            timer_checkpoint,
+            precision_ms,
+            roll_window_ms: roll_window_slot * precision_ms,
+        };
+        state.recover();
+
+        let now = get_current_millis() as i64 / precision_ms * precision_ms;
+        // slots older than one turn of the wheel have been overwritten already
+        let oldest = now - state.timer_wheel.slots_total() as i64 * precision_ms;
+        let mut read_time_ms = state.timer_checkpoint.last_read_time_ms();
+        if read_time_ms <= 0 {
+            read_time_ms = now;
+        } else if read_time_ms < oldest {
+            warn!(
+                "timer read time {} is older than the wheel, messages before {} are lost",
+                read_time_ms, oldest
+            );
+            read_time_ms = oldest;
+        }
+        self.curr_read_time_ms
+            .store(read_time_ms, Ordering::Relaxed);
+        self.curr_queue_offset.store(
+            state.timer_checkpoint.last_timer_queue_offset(),
+            Ordering::Relaxed,
+        );
+        info!(
+            "timer message store loaded, currReadTimeMs: {}, currQueueOffset: {}, timerLog: [{}, \
+             {})",
+            read_time_ms,
+            state.timer_checkpoint.last_timer_queue_offset(),
+            state.timer_log.min_offset(),
+            state.timer_log.max_offset()
+        );
+        *self.state.lock() = Some(state);
         true
     }
 
-    pub fn start(&mut self) {}
+    pub fn start(&self) {
+        let Some(store) = self.default_message_store.as_ref() else {
+            return;
+        };
+        if self.state.lock().is_none() {
+            warn!("timer message store is not loaded, skip start");
+            return;
+        }
+        if self.running.swap(true, Ordering::AcqRel) {
+            return;
+        }
+        let task_spawner = store.task_spawner().clone();
+        let flush_interval_ms = store.message_store_config().timer_flush_interval_ms.max(1) as u64;
 
-    pub fn is_reject(&self, _deliver_ms: u64) -> bool {
-        false
+        let this = self.clone();
+        task_spawner.spawn("timer-enqueue", async move {
+            while this.is_running() {
+                if this.enqueue() {
+                    tokio::task::yield_now().await;
+                } else {
+                    tokio::time::sleep(Duration::from_millis(IDLE_WAIT_MS)).await;
+                }
+            }
+        });
+        let this = self.clone();
+        task_spawner.spawn("timer-dequeue", async move {
+            while this.is_running() {
+                if this.dequeue().await {
+                    tokio::task::yield_now().await;
+                } else {
+                    tokio::time::sleep(Duration::from_millis(IDLE_WAIT_MS)).await;
+                }
+            }
+        });
+        let this = self.clone();
+        task_spawner.spawn("timer-flush", async move {
+            let mut interval = tokio::time::interval(Duration::from_millis(flush_interval_ms));
+            let mut rounds = 0u64;
+            while this.is_running() {
+                interval.tick().await;
+                this.flush();
+                rounds += 1;
+                if rounds % CLEAN_TIMER_LOG_INTERVAL == 0 {
+                    this.clean_expired_timer_log();
+                }
+            }
+        });
+        info!("timer message store started");
+    }
+
+    pub fn shutdown(&self) {
+        if self.running.swap(false, Ordering::AcqRel) {
+            self.flush();
+            info!("timer message store shutdown");
+        }
+    }
+
+    fn is_running(&self) -> bool {
+        self.running.load(Ordering::Acquire)
+    }
+
+    /// Moves the next batch of messages of the timer topic into the wheel, returns whether any
+    /// message was read.
+    fn enqueue(&self) -> bool {
+        let Some(store) = self.default_message_store.as_ref() else {
+            return false;
+        };
+        if store.message_store_config().timer_stop_enqueue {
+            return false;
+        }
+        let offset = self.curr_queue_offset.load(Ordering::Relaxed);
+        let units = match store.examine_consume_queue(
+            &CheetahString::from_static_str(TIMER_TOPIC),
+            0,
+            offset,
+            ENQUEUE_BATCH_SIZE,
+        ) {
+            Some(units) if !units.is_empty() => units,
+            _ => return false,
+        };
+
+        let mut guard = self.state.lock();
+        let Some(state) = guard.as_mut() else {
+            return false;
+        };
+        for unit in units {
+            // read under the lock, the dequeue task advances it under the same lock
+            let read_time_ms = self.curr_read_time_ms.load(Ordering::Relaxed);
+            match store.look_message_by_offset_with_size(unit.pos, unit.size) {
+                Some(msg) => match parse_timer_out_ms(&msg) {
+                    Some(delayed_ms) => {
+                        let real_topic = msg
+                            .get_property(&CheetahString::from_static_str(
+                                MessageConst::PROPERTY_REAL_TOPIC,
+                            ))
+                            .unwrap_or_default();
+                        if !state.enqueue(
+                            read_time_ms,
+                            delayed_ms,
+                            unit.pos,
+                            unit.size,
+                            java_hash_code(real_topic.as_str()),
+                        ) {
+                            // retried from the same offset in the next round
+                            return true;
+                        }
+                    }
+                    None => warn!(
+                        "timer message without a valid {} property, offsetPy: {}",
+                        TIMER_OUT_MS, unit.pos
+                    ),
+                },
+                None => warn!(
+                    "timer message not found in commit log, offsetPy: {}, size: {}",
+                    unit.pos, unit.size
+                ),
+            }
+            self.curr_queue_offset
+                .store(unit.queue_offset + 1, Ordering::Relaxed);
+        }
+        true
+    }
+
+    /// Delivers the slot at the current read time once it is due, returns whether the read
+    /// time advanced.
+    async fn dequeue(&self) -> bool {
+        let Some(mut store) = self.default_message_store.clone() else {
+            return false;
+        };
+        if store.message_store_config().timer_stop_dequeue {
+            return false;
+        }
+        let read_time_ms = self.curr_read_time_ms.load(Ordering::Relaxed);
+        if read_time_ms > get_current_millis() as i64 {
+            return false;
+        }
+        let (units, precision_ms) = match self.state.lock().as_ref() {
+            Some(state) => (state.collect_slot(read_time_ms), state.precision_ms),
+            None => return false,
+        };
+
+        for unit in units {
+            let Some(msg) = store.look_message_by_offset_with_size(unit.offset_py, unit.size_py)
+            else {
+                warn!(
+                    "timer message not found in commit log, offsetPy: {}, size: {}",
+                    unit.offset_py, unit.size_py
+                );
+                continue;
+            };
+            let roll = unit.magic & MAGIC_ROLL != 0;
+            let inner = convert_message(&msg, roll, unit.curr_write_time_ms);
+            loop {
+                let result = store.put_message(inner.clone()).await;
+                match put_result_code(&result) {
+                    PUT_OK => break,
+                    PUT_NO_RETRY => {
+                        warn!(
+                            "deliver timer message failed without retry, topic: {}, status: {:?}",
+                            inner.topic(),
+                            result.put_message_status()
+                        );
+                        break;
+                    }
+                    _ => {
+                        // the slot is delivered again after a restart, duplicates are acceptable
+                        if !self.is_running() {
+                            return false;
+                        }
+                        tokio::time::sleep(Duration::from_millis(PUT_RETRY_WAIT_MS)).await;
+                    }
+                }
+            }
+            if !roll {
+                if let Some(delayed_ms) = parse_timer_out_ms(&msg) {
+                    self.timer_metrics
+                        .on_dequeue((delayed_ms - msg.store_timestamp()).max(0) as u64);
+                }
+            }
+        }
+
+        if let Some(state) = self.state.lock().as_ref() {
+            state.timer_wheel.put_slot(&Slot::empty(read_time_ms));
+            self.curr_read_time_ms
+                .store(read_time_ms + precision_ms, Ordering::Relaxed);
+        }
+        true
+    }
+
+    /// Flushes the timer log and wheel, then persists the progress they reflect.
+    pub fn flush(&self) {
+        let guard = self.state.lock();
+        let Some(state) = guard.as_ref() else {
+            return;
+        };
+        let flush_pos = state.timer_log.flush();
+        if let Err(e) = state.timer_wheel.flush() {
+            error!("flush timer wheel failed: {}", e);
+            return;
+        }
+        let checkpoint = &state.timer_checkpoint;
+        checkpoint.set_last_read_time_ms(self.curr_read_time_ms.load(Ordering::Relaxed));
+        checkpoint.set_last_timer_log_flush_pos(flush_pos);
+        checkpoint.set_last_timer_queue_offset(self.curr_queue_offset.load(Ordering::Relaxed));
+        if let Err(e) = checkpoint.flush() {
+            error!("flush timer checkpoint failed: {}", e);
+        }
+    }
+
+    fn clean_expired_timer_log(&self) {
+        let mut guard = self.state.lock();
+        let Some(state) = guard.as_mut() else {
+            return;
+        };
+        let min_live_pos = state.min_live_pos(self.curr_read_time_ms.load(Ordering::Relaxed));
+        let deleted = state.timer_log.delete_files_before(min_live_pos);
+        if deleted > 0 {
+            info!(
+                "deleted {} expired timer log files before {}",
+                deleted, min_live_pos
+            );
+        }
+    }
+
+    /// Redirects a timer message to the timer topic, the real topic and queue are kept in its
+    /// properties. Returns the result to answer with when the message is rejected.
+    pub fn handle_timer_message(
+        &self,
+        message_store_config: &MessageStoreConfig,
+        msg: &mut MessageExtBrokerInner,
+    ) -> Option<PutMessageResult> {
+        let tran_type = MessageSysFlag::get_transaction_value(msg.sys_flag());
+        if tran_type != MessageSysFlag::TRANSACTION_NOT_TYPE
+            && tran_type != MessageSysFlag::TRANSACTION_COMMIT_TYPE
+        {
+            return None;
+        }
+        if TIMER_TOPIC == msg.topic() || !Self::is_timer_message(msg) {
+            return None;
+        }
+        if !message_store_config.timer_wheel_enable {
+            return Some(PutMessageResult::new_default(
+                PutMessageStatus::WheelTimerNotEnable,
+            ));
+        }
+
+        let now = get_current_millis();
+        let deliver_ms = match parse_deliver_ms(msg, now) {
+            Some(deliver_ms) => deliver_ms,
+            None => {
+                return Some(PutMessageResult::new_default(
+                    PutMessageStatus::WheelTimerMsgIllegal,
+                ))
+            }
+        };
+        if deliver_ms <= now {
+            if msg.property(TIMER_DELETE_UNIQUE_KEY).is_some() {
+                return Some(PutMessageResult::new_default(
+                    PutMessageStatus::WheelTimerMsgIllegal,
+                ));
+            }
+            // already due, delivered as a normal message
+            return None;
+        }
+        if deliver_ms - now > message_store_config.timer_max_delay_sec * 1000 {
+            return Some(PutMessageResult::new_default(
+                PutMessageStatus::WheelTimerMsgIllegal,
+            ));
+        }
+
+        let timer_precision_ms = message_store_config.timer_precision_ms.max(1);
+        let deliver_ms = if deliver_ms % timer_precision_ms == 0 {
+            deliver_ms - timer_precision_ms
+        } else {
+            deliver_ms / timer_precision_ms * timer_precision_ms
+        };
+        if self.is_reject(deliver_ms) {
+            return Some(PutMessageResult::new_default(
+                PutMessageStatus::WheelTimerFlowControl,
+            ));
+        }
+
+        let properties = &mut msg.message_ext_inner.message.properties;
+        properties.insert(
+            CheetahString::from_static_str(TIMER_OUT_MS),
+            CheetahString::from_string(deliver_ms.to_string()),
+        );
+        properties.insert(
+            CheetahString::from_static_str(MessageConst::PROPERTY_REAL_TOPIC),
+            msg.message_ext_inner.message.topic.clone(),
+        );
+        properties.insert(
+            CheetahString::from_static_str(MessageConst::PROPERTY_REAL_QUEUE_ID),
+            CheetahString::from_string(msg.message_ext_inner.queue_id.to_string()),
+        );
+        msg.properties_string =
+            message_properties_to_string(&msg.message_ext_inner.message.properties);
+        msg.message_ext_inner.message.topic = CheetahString::from_static_str(TIMER_TOPIC);
+        msg.message_ext_inner.queue_id = 0;
+        self.timer_metrics
+            .on_enqueue(deliver_ms.saturating_sub(now));
+        None
+    }
+
+    /// Whether `msg` asks for a delivery time. Delay level messages go to the schedule topic
+    /// instead, their timer properties are dropped.
+    pub fn is_timer_message(msg: &mut MessageExtBrokerInner) -> bool {
+        let delay_level = msg.message_ext_inner.message.get_delay_time_level();
+        let timer_topic = TIMER_TOPIC == msg.topic();
+        let properties = &mut msg.message_ext_inner.message.properties;
+        if delay_level > 0 {
+            properties.remove(MessageConst::PROPERTY_TIMER_DELIVER_MS);
+            properties.remove(MessageConst::PROPERTY_TIMER_DELAY_SEC);
+            properties.remove(MessageConst::PROPERTY_TIMER_DELAY_MS);
+            return false;
+        }
+        if timer_topic || properties.contains_key(TIMER_OUT_MS) {
+            return false;
+        }
+        properties.contains_key(MessageConst::PROPERTY_TIMER_DELIVER_MS)
+            || properties.contains_key(MessageConst::PROPERTY_TIMER_DELAY_MS)
+            || properties.contains_key(MessageConst::PROPERTY_TIMER_DELAY_SEC)
+    }
+
+    /// Rejects new timer messages for a slot already holding `timer_congest_num_each_slot`.
+    pub fn is_reject(&self, deliver_ms: u64) -> bool {
+        let Some(store) = self.default_message_store.as_ref() else {
+            return false;
+        };
+        let congest_num_each_slot = store.message_store_config().timer_congest_num_each_slot;
+        if congest_num_each_slot == 0 {
+            return false;
+        }
+        match self.state.lock().as_ref() {
+            Some(state) => {
+                let slot_time_ms = deliver_ms as i64 / state.precision_ms * state.precision_ms;
+                state.timer_wheel.get_slot(slot_time_ms).num.max(0) as usize
+                    >= congest_num_each_slot
+            }
+            None => false,
+        }
     }
 
     pub fn get_dequeue_behind(&self) -> i64 {
@@ -83,16 +651,11 @@ impl TimerMessageStore {
     }
 
     pub fn get_dequeue_behind_millis(&self) -> i64 {
-        (SystemClock::now() as i64)
-            - self
-                .curr_read_time_ms
-                .load(std::sync::atomic::Ordering::Relaxed)
+        (SystemClock::now() as i64) - self.curr_read_time_ms.load(Ordering::Relaxed)
     }
 
     pub fn get_enqueue_behind_messages(&self) -> i64 {
-        let temp_queue_offset = self
-            .curr_queue_offset
-            .load(std::sync::atomic::Ordering::Relaxed);
+        let temp_queue_offset = self.curr_queue_offset.load(Ordering::Relaxed);
         let consume_queue = self
             .default_message_store
             .as_ref()
@@ -117,20 +680,54 @@ impl TimerMessageStore {
         0.0
     }
 
+    pub fn timer_metrics(&self) -> &Arc<TimerMetrics> {
+        &self.timer_metrics
+    }
+
+    /// Whether delayed messages are being delivered later than
+    /// `timer_congest_dequeue_behind_ms`, or any slot of the wheel is congested.
+    pub fn is_congested(&self) -> bool {
+        let congest_dequeue_behind_ms = match self.default_message_store.as_ref() {
+            Some(store) => store.message_store_config().timer_congest_dequeue_behind_ms,
+            None => return false,
+        };
+        self.get_all_congest_num() > 0
+            || self.get_dequeue_behind_millis() > congest_dequeue_behind_ms as i64
+    }
+
+    pub fn timer_status(&self) -> TimerStatus {
+        let timer_wheel_enable = self
+            .default_message_store
+            .as_ref()
+            .is_some_and(|store| store.message_store_config().timer_wheel_enable);
+        if !timer_wheel_enable {
+            return TimerStatus::default();
+        }
+        TimerStatus {
+            timer_wheel_enable,
+            dequeue_behind_millis: self.get_dequeue_behind_millis(),
+            enqueue_behind_messages: self.get_enqueue_behind_messages(),
+            congest_num: self.get_all_congest_num(),
+            congested: self.is_congested(),
+            enqueue_tps: self.get_enqueue_tps(),
+            dequeue_tps: self.get_dequeue_tps(),
+            metrics: self.timer_metrics.snapshot(),
+        }
+    }
+
     pub fn new(default_message_store: Option<ArcMut<DefaultMessageStore>>) -> Self {
         Self {
-            curr_read_time_ms: AtomicI64::new(0),
-            curr_queue_offset: AtomicI64::new(0),
+            curr_read_time_ms: Arc::new(AtomicI64::new(0)),
+            curr_queue_offset: Arc::new(AtomicI64::new(0)),
             default_message_store,
+            timer_metrics: Arc::new(TimerMetrics::new()),
+            state: Arc::new(Mutex::new(None)),
+            running: Arc::new(AtomicBool::new(false)),
         }
     }
 
     pub fn new_empty() -> Self {
-        Self {
-            curr_read_time_ms: AtomicI64::new(0),
-            curr_queue_offset: AtomicI64::new(0),
-            default_message_store: None,
-        }
+        Self::new(None)
     }
 
     pub fn set_default_message_store(
@@ -140,3 +737,201 @@ impl TimerMessageStore {
         self.default_message_store = default_message_store;
     }
 }
+
+fn parse_timer_out_ms(msg: &MessageExt) -> Option<i64> {
+    msg.get_property(&CheetahString::from_static_str(TIMER_OUT_MS))?
+        .parse::<i64>()
+        .ok()
+}
+
+fn parse_deliver_ms(msg: &MessageExtBrokerInner, now: u64) -> Option<u64> {
+    if let Some(delay_sec) = msg.property(MessageConst::PROPERTY_TIMER_DELAY_SEC) {
+        let delay_sec = delay_sec.parse::<u64>().ok()?;
+        return now.checked_add(delay_sec.checked_mul(1000)?);
+    }
+    if let Some(delay_ms) = msg.property(MessageConst::PROPERTY_TIMER_DELAY_MS) {
+        return now.checked_add(delay_ms.parse::<u64>().ok()?);
+    }
+    msg.property(MessageConst::PROPERTY_TIMER_DELIVER_MS)?
+        .parse::<u64>()
+        .ok()
+}
+
+/// Rebuilds the message to put back, rolled messages stay on the timer topic.
+fn convert_message(msg: &MessageExt, roll: bool, enqueue_ms: i64) -> MessageExtBrokerInner {
+    let mut inner = MessageExtBrokerInner::default();
+    if let Some(body) = msg.get_body() {
+        inner.set_body(body.clone());
+    }
+    inner.set_flag(msg.get_flag());
+    let mut properties = msg.get_properties().clone();
+    if roll {
+        inner.set_topic(msg.get_topic().clone());
+        inner.message_ext_inner.queue_id = msg.queue_id;
+        let roll_times = properties
+            .get(TIMER_ROLL_TIMES)
+            .and_then(|roll_times| roll_times.parse::<i32>().ok())
+            .unwrap_or(0);
+        properties.insert(
+            CheetahString::from_static_str(TIMER_ROLL_TIMES),
+            CheetahString::from_string((roll_times + 1).to_string()),
+        );
+    } else {
+        let real_topic = properties
+            .remove(MessageConst::PROPERTY_REAL_TOPIC)
+            .unwrap_or_else(|| msg.get_topic().clone());
+        let real_queue_id = properties
+            .remove(MessageConst::PROPERTY_REAL_QUEUE_ID)
+            .and_then(|queue_id| queue_id.parse::<i32>().ok())
+            .unwrap_or(0);
+        inner.set_topic(real_topic);
+        inner.message_ext_inner.queue_id = real_queue_id;
+        properties.insert(
+            CheetahString::from_static_str(TIMER_ENQUEUE_MS),
+            CheetahString::from_string(enqueue_ms.to_string()),
+        );
+        properties.insert(
+            CheetahString::from_static_str(TIMER_DEQUEUE_MS),
+            CheetahString::from_string(get_current_millis().to_string()),
+        );
+    }
+    inner.properties_string = message_properties_to_string(&properties);
+    MessageAccessor::set_properties(&mut inner, properties);
+    inner.tags_code = MessageExtBrokerInner::tags_string_to_tags_code(
+        inner.get_tags().unwrap_or_default().as_str(),
+    );
+    inner.message_ext_inner.sys_flag = msg.sys_flag();
+    inner.message_ext_inner.born_timestamp = msg.born_timestamp;
+    inner.message_ext_inner.born_host = msg.born_host;
+    inner.message_ext_inner.store_host = msg.store_host;
+    inner.message_ext_inner.reconsume_times = msg.reconsume_times();
+    inner
+}
+
+fn put_result_code(result: &PutMessageResult) -> i32 {
+    match result.put_message_status() {
+        PutMessageStatus::PutOk
+        | PutMessageStatus::FlushDiskTimeout
+        | PutMessageStatus::FlushSlaveTimeout
+        | PutMessageStatus::SlaveNotAvailable => PUT_OK,
+        PutMessageStatus::MessageIllegal
+        | PutMessageStatus::PropertiesSizeExceeded
+        | PutMessageStatus::WheelTimerMsgIllegal
+        | PutMessageStatus::WheelTimerNotEnable => PUT_NO_RETRY,
+        _ => PUT_NEED_RETRY,
+    }
+}
+
+/// Same value as Java's `String#hashCode`, kept in the timer log for compatibility.
+fn java_hash_code(value: &str) -> i32 {
+    value
+        .encode_utf16()
+        .fold(0i32, |hash, c| hash.wrapping_mul(31).wrapping_add(c as i32))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use tempfile::tempdir;
+
+    use super::*;
+
+    const PRECISION_MS: i64 = 1000;
+    const ROLL_WINDOW_SLOT: i64 = 8;
+
+    fn timer_state(root_dir: &Path) -> TimerState {
+        let root_dir = root_dir.to_string_lossy();
+        let mut timer_log = TimerLog::new(get_timer_log_path(&root_dir), 1024);
+        assert!(timer_log.load());
+        TimerState {
+            timer_log,
+            timer_wheel: TimerWheel::new(
+                get_timer_wheel_path(&root_dir),
+                (ROLL_WINDOW_SLOT * 2) as usize,
+                PRECISION_MS as u64,
+            )
+            .unwrap(),
+            timer_checkpoint: TimerCheckpoint::new(get_timer_check_path(&root_dir)).unwrap(),
+            precision_ms: PRECISION_MS,
+            roll_window_ms: ROLL_WINDOW_SLOT * PRECISION_MS,
+        }
+    }
+
+    #[test]
+    fn enqueue_links_units_of_a_slot_in_order() {
+        let dir = tempdir().unwrap();
+        let mut state = timer_state(dir.path());
+        let read_time_ms = 100_000;
+        assert!(state.enqueue(read_time_ms, 103_500, 10, 100, 1));
+        assert!(state.enqueue(read_time_ms, 103_000, 20, 100, 1));
+        // already due, goes to the next slot instead of the one being read
+        assert!(state.enqueue(read_time_ms, 90_000, 30, 100, 1));
+        // beyond the window, parked in its last slot
+        assert!(state.enqueue(read_time_ms, 500_000, 40, 100, 1));
+
+        let offsets =
+            |units: Vec<TimerLogUnit>| units.iter().map(|unit| unit.offset_py).collect::<Vec<_>>();
+        assert_eq!(offsets(state.collect_slot(103_000)), vec![10, 20]);
+        assert_eq!(offsets(state.collect_slot(101_000)), vec![30]);
+        let rolled = state.collect_slot(107_000);
+        assert_eq!(offsets(rolled.clone()), vec![40]);
+        assert_ne!(rolled[0].magic & MAGIC_ROLL, 0);
+        assert!(state.collect_slot(100_000).is_empty());
+    }
+
+    #[test]
+    fn recover_unlinks_units_written_after_the_checkpoint() {
+        let dir = tempdir().unwrap();
+        {
+            let mut state = timer_state(dir.path());
+            assert!(state.enqueue(100_000, 103_000, 10, 100, 1));
+            assert!(state.enqueue(100_000, 104_000, 20, 100, 1));
+            let flush_pos = state.timer_log.flush();
+            state
+                .timer_checkpoint
+                .set_last_timer_log_flush_pos(flush_pos);
+            state.timer_checkpoint.flush().unwrap();
+            assert!(state.enqueue(100_000, 103_000, 30, 100, 1));
+            assert!(state.enqueue(100_000, 105_000, 40, 100, 1));
+            state.timer_log.flush();
+            state.timer_wheel.flush().unwrap();
+        }
+
+        let mut state = timer_state(dir.path());
+        state.recover();
+        let slot = state.timer_wheel.get_slot(103_000);
+        assert_eq!(slot.num, 1);
+        assert_eq!(state.collect_slot(103_000)[0].offset_py, 10);
+        assert_eq!(state.collect_slot(104_000)[0].offset_py, 20);
+        assert_eq!(state.timer_wheel.get_slot(105_000), Slot::empty(105_000));
+        assert_eq!(
+            state.timer_log.max_offset(),
+            state.timer_checkpoint.last_timer_log_flush_pos()
+        );
+    }
+
+    #[test]
+    fn delay_level_wins_over_timer_properties() {
+        let mut msg = MessageExtBrokerInner::default();
+        msg.set_topic(CheetahString::from_static_str("TopicA"));
+        msg.put_property(
+            CheetahString::from_static_str(MessageConst::PROPERTY_TIMER_DELAY_SEC),
+            CheetahString::from_static_str("10"),
+        );
+        assert!(TimerMessageStore::is_timer_message(&mut msg));
+
+        msg.message_ext_inner.message.set_delay_time_level(3);
+        assert!(!TimerMessageStore::is_timer_message(&mut msg));
+        assert!(msg
+            .property(MessageConst::PROPERTY_TIMER_DELAY_SEC)
+            .is_none());
+    }
+
+    #[test]
+    fn java_hash_code_matches_string_hash_code() {
+        assert_eq!(java_hash_code(""), 0);
+        assert_eq!(java_hash_code("abc"), 96354);
+        assert_eq!(java_hash_code("TopicTest"), -1_902_610_879);
+    }
+}
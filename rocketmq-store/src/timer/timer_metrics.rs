@@ -0,0 +1,149 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::sync::atomic::AtomicI64;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+/// Upper bounds, in seconds, of the delay buckets pending timer messages are counted in. Messages
+/// delayed longer than the last bound are counted in an extra overflow bucket.
+pub const TIMER_DIST_SECS: [u64; 8] = [5, 60, 300, 900, 3600, 14400, 28800, 86400];
+
+const BUCKET_NUM: usize = TIMER_DIST_SECS.len() + 1;
+
+/// Counts pending timer messages per delay bucket.
+#[derive(Default)]
+pub struct TimerMetrics {
+    pending: [AtomicI64; BUCKET_NUM],
+    enqueued_total: AtomicU64,
+    dequeued_total: AtomicU64,
+}
+
+impl TimerMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a message accepted into the timer wheel with the given delay.
+    pub fn on_enqueue(&self, delay_ms: u64) {
+        self.pending[bucket_index(delay_ms)].fetch_add(1, Ordering::Relaxed);
+        self.enqueued_total.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Records a message delivered from the timer wheel, `delay_ms` being the delay it was
+    /// enqueued with.
+    pub fn on_dequeue(&self, delay_ms: u64) {
+        let _ = self.pending[bucket_index(delay_ms)].fetch_update(
+            Ordering::Relaxed,
+            Ordering::Relaxed,
+            |pending| Some((pending - 1).max(0)),
+        );
+        self.dequeued_total.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn pending_total(&self) -> i64 {
+        self.pending
+            .iter()
+            .map(|pending| pending.load(Ordering::Relaxed))
+            .sum()
+    }
+
+    pub fn snapshot(&self) -> TimerMetricsSnapshot {
+        let buckets = self
+            .pending
+            .iter()
+            .enumerate()
+            .map(|(index, pending)| TimerBucket {
+                upper_bound_secs: TIMER_DIST_SECS.get(index).copied(),
+                pending: pending.load(Ordering::Relaxed),
+            })
+            .collect::<Vec<_>>();
+        TimerMetricsSnapshot {
+            pending_total: buckets.iter().map(|bucket| bucket.pending).sum(),
+            enqueued_total: self.enqueued_total.load(Ordering::Relaxed),
+            dequeued_total: self.dequeued_total.load(Ordering::Relaxed),
+            buckets,
+        }
+    }
+}
+
+fn bucket_index(delay_ms: u64) -> usize {
+    TIMER_DIST_SECS
+        .iter()
+        .position(|upper_bound_secs| delay_ms <= upper_bound_secs * 1000)
+        .unwrap_or(TIMER_DIST_SECS.len())
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimerBucket {
+    /// `None` for the overflow bucket.
+    pub upper_bound_secs: Option<u64>,
+    pub pending: i64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimerMetricsSnapshot {
+    pub pending_total: i64,
+    pub enqueued_total: u64,
+    pub dequeued_total: u64,
+    pub buckets: Vec<TimerBucket>,
+}
+
+/// Delivery status of the timer message store, answered by `GET_TIMER_METRICS`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimerStatus {
+    pub timer_wheel_enable: bool,
+    pub dequeue_behind_millis: i64,
+    pub enqueue_behind_messages: i64,
+    pub congest_num: i64,
+    pub congested: bool,
+    pub enqueue_tps: f32,
+    pub dequeue_tps: f32,
+    pub metrics: TimerMetricsSnapshot,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pending_counts_follow_delay_buckets() {
+        let metrics = TimerMetrics::new();
+        metrics.on_enqueue(1000);
+        metrics.on_enqueue(5000);
+        metrics.on_enqueue(10 * 60 * 1000);
+        metrics.on_enqueue(2 * 86400 * 1000);
+
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.buckets.len(), TIMER_DIST_SECS.len() + 1);
+        assert_eq!(snapshot.buckets[0].pending, 2);
+        assert_eq!(snapshot.buckets[3].pending, 1);
+        assert_eq!(snapshot.buckets[8].upper_bound_secs, None);
+        assert_eq!(snapshot.buckets[8].pending, 1);
+        assert_eq!(snapshot.pending_total, 4);
+
+        metrics.on_dequeue(1000);
+        metrics.on_dequeue(60 * 1000);
+        assert_eq!(metrics.pending_total(), 3);
+        assert_eq!(metrics.snapshot().dequeued_total, 2);
+    }
+}
@@ -0,0 +1,178 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::fs::OpenOptions;
+use std::path::Path;
+
+use memmap2::MmapMut;
+use parking_lot::Mutex;
+use rocketmq_common::UtilAll::ensure_dir_ok;
+use tracing::info;
+
+/// Size of one slot: `time_ms(8) first_pos(8) last_pos(8) num(4) magic(4)`.
+pub const SLOT_SIZE: usize = 32;
+
+/// Head of the timer log list of the messages due at `time_ms`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Slot {
+    pub time_ms: i64,
+    pub first_pos: i64,
+    pub last_pos: i64,
+    pub num: i32,
+    pub magic: i32,
+}
+
+impl Slot {
+    pub fn empty(time_ms: i64) -> Self {
+        Slot {
+            time_ms,
+            first_pos: -1,
+            last_pos: -1,
+            num: 0,
+            magic: 0,
+        }
+    }
+}
+
+/// Fixed size ring of slots mapped from one file, a slot is addressed by
+/// `(time_ms / precision_ms) % slots_total` and only valid while its `time_ms` matches.
+pub struct TimerWheel {
+    slots_total: usize,
+    precision_ms: i64,
+    mmap: Mutex<MmapMut>,
+}
+
+impl TimerWheel {
+    pub fn new<P: AsRef<Path>>(
+        path: P,
+        slots_total: usize,
+        precision_ms: u64,
+    ) -> std::io::Result<Self> {
+        if let Some(parent) = path.as_ref().parent() {
+            ensure_dir_ok(parent.to_string_lossy().as_ref());
+        }
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path.as_ref())?;
+        let wheel_size = (slots_total * SLOT_SIZE) as u64;
+        let file_size = file.metadata()?.len();
+        if file_size != 0 && file_size != wheel_size {
+            // the wheel cannot be rehashed, a resized wheel starts empty
+            info!(
+                "timer wheel size changed from {} to {}, reset {}",
+                file_size,
+                wheel_size,
+                path.as_ref().display()
+            );
+            file.set_len(0)?;
+        }
+        file.set_len(wheel_size)?;
+        let mmap = unsafe { MmapMut::map_mut(&file)? };
+        Ok(TimerWheel {
+            slots_total,
+            precision_ms: precision_ms.max(1) as i64,
+            mmap: Mutex::new(mmap),
+        })
+    }
+
+    pub fn slots_total(&self) -> usize {
+        self.slots_total
+    }
+
+    fn slot_index(&self, time_ms: i64) -> usize {
+        ((time_ms / self.precision_ms) as usize) % self.slots_total
+    }
+
+    /// Returns the slot of `time_ms`, an empty slot if the stored one belongs to another round.
+    pub fn get_slot(&self, time_ms: i64) -> Slot {
+        let slot = self.get_raw_slot(self.slot_index(time_ms));
+        if slot.time_ms == time_ms {
+            slot
+        } else {
+            Slot::empty(time_ms)
+        }
+    }
+
+    pub fn put_slot(&self, slot: &Slot) {
+        let start = self.slot_index(slot.time_ms) * SLOT_SIZE;
+        let mut mmap = self.mmap.lock();
+        let buffer = &mut mmap[start..start + SLOT_SIZE];
+        buffer[0..8].copy_from_slice(&slot.time_ms.to_be_bytes());
+        buffer[8..16].copy_from_slice(&slot.first_pos.to_be_bytes());
+        buffer[16..24].copy_from_slice(&slot.last_pos.to_be_bytes());
+        buffer[24..28].copy_from_slice(&slot.num.to_be_bytes());
+        buffer[28..32].copy_from_slice(&slot.magic.to_be_bytes());
+    }
+
+    pub fn get_raw_slot(&self, index: usize) -> Slot {
+        let start = index * SLOT_SIZE;
+        let mmap = self.mmap.lock();
+        let buffer = &mmap[start..start + SLOT_SIZE];
+        Slot {
+            time_ms: i64::from_be_bytes(buffer[0..8].try_into().unwrap()),
+            first_pos: i64::from_be_bytes(buffer[8..16].try_into().unwrap()),
+            last_pos: i64::from_be_bytes(buffer[16..24].try_into().unwrap()),
+            num: i32::from_be_bytes(buffer[24..28].try_into().unwrap()),
+            magic: i32::from_be_bytes(buffer[28..32].try_into().unwrap()),
+        }
+    }
+
+    /// Every slot currently holding messages.
+    pub fn live_slots(&self) -> Vec<Slot> {
+        (0..self.slots_total)
+            .map(|index| self.get_raw_slot(index))
+            .filter(|slot| slot.num > 0 && slot.last_pos >= 0)
+            .collect()
+    }
+
+    pub fn flush(&self) -> std::io::Result<()> {
+        self.mmap.lock().flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::tempdir;
+
+    use super::*;
+
+    #[test]
+    fn slot_is_only_valid_for_its_own_round() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("timerwheel");
+        let wheel = TimerWheel::new(&path, 8, 1000).unwrap();
+        let slot = Slot {
+            time_ms: 3_000,
+            first_pos: 0,
+            last_pos: 104,
+            num: 3,
+            magic: 0,
+        };
+        wheel.put_slot(&slot);
+        assert_eq!(wheel.get_slot(3_000), slot);
+        // same index, next round
+        assert_eq!(wheel.get_slot(11_000), Slot::empty(11_000));
+        wheel.flush().unwrap();
+        drop(wheel);
+
+        let wheel = TimerWheel::new(&path, 8, 1000).unwrap();
+        assert_eq!(wheel.get_slot(3_000), slot);
+        assert_eq!(wheel.live_slots(), vec![slot]);
+    }
+}
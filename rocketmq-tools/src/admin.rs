@@ -15,6 +15,7 @@
  * limitations under the License.
  */
 pub mod common;
+pub mod consume_queue_formatter;
 pub mod default_mq_admin_ext;
 pub mod default_mq_admin_ext_impl;
 pub mod mq_admin_ext;
@@ -0,0 +1,89 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::fmt::Write;
+
+use rocketmq_remoting::protocol::body::examine_consume_queue_body::ExamineConsumeQueueBody;
+
+/// Renders the result of `examine_consume_queue` as a table, one consume queue unit per line.
+pub fn format_consume_queue_units(body: &ExamineConsumeQueueBody) -> String {
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "Topic: {}, QueueId: {}, MinOffset: {}, MaxOffset: {}",
+        body.topic, body.queue_id, body.min_offset, body.max_offset
+    );
+    let _ = writeln!(
+        out,
+        "{:<16}  {:<20}  {:<10}  {:<20}",
+        "#QueueOffset", "#PhyOffset", "#Size", "#TagsCode"
+    );
+    for unit in &body.units {
+        let _ = writeln!(
+            out,
+            "{:<16}  {:<20}  {:<10}  {:<20}",
+            unit.queue_offset, unit.phy_offset, unit.size, unit.tags_code
+        );
+    }
+    if body.units.is_empty() {
+        let _ = writeln!(out, "No consume queue unit in the requested range");
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use cheetah_string::CheetahString;
+    use rocketmq_remoting::protocol::body::examine_consume_queue_body::ConsumeQueueUnitData;
+
+    use super::*;
+
+    #[test]
+    fn format_consume_queue_units_prints_one_line_per_unit() {
+        let body = ExamineConsumeQueueBody {
+            topic: CheetahString::from_static_str("TopicA"),
+            queue_id: 2,
+            min_offset: 0,
+            max_offset: 8,
+            units: vec![
+                ConsumeQueueUnitData {
+                    queue_offset: 5,
+                    phy_offset: 1024,
+                    size: 180,
+                    tags_code: 7,
+                },
+                ConsumeQueueUnitData {
+                    queue_offset: 6,
+                    phy_offset: 1204,
+                    size: 96,
+                    tags_code: 0,
+                },
+            ],
+        };
+        let output = format_consume_queue_units(&body);
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(
+            lines[0],
+            "Topic: TopicA, QueueId: 2, MinOffset: 0, MaxOffset: 8"
+        );
+        assert!(lines[1].starts_with("#QueueOffset"));
+        assert_eq!(
+            lines[2].split_whitespace().collect::<Vec<_>>(),
+            ["5", "1024", "180", "7"]
+        );
+    }
+}
@@ -30,6 +30,7 @@ use rocketmq_remoting::protocol::body::broker_body::cluster_info::ClusterInfo;
 use rocketmq_remoting::protocol::body::consume_message_directly_result::ConsumeMessageDirectlyResult;
 use rocketmq_remoting::protocol::body::consumer_connection::ConsumerConnection;
 use rocketmq_remoting::protocol::body::consumer_running_info::ConsumerRunningInfo;
+use rocketmq_remoting::protocol::body::examine_consume_queue_body::ExamineConsumeQueueBody;
 use rocketmq_remoting::protocol::body::group_list::GroupList;
 use rocketmq_remoting::protocol::body::kv_table::KVTable;
 use rocketmq_remoting::protocol::body::producer_connection::ProducerConnection;
@@ -509,6 +510,19 @@ impl MQAdminExt for DefaultMQAdminExt {
         todo!()
     }
 
+    async fn examine_consume_queue(
+        &self,
+        broker_addr: CheetahString,
+        topic: CheetahString,
+        queue_id: i32,
+        index: i64,
+        count: i32,
+    ) -> crate::Result<ExamineConsumeQueueBody> {
+        self.default_mqadmin_ext_impl
+            .examine_consume_queue(broker_addr, topic, queue_id, index, count)
+            .await
+    }
+
     async fn resume_check_half_message(
         &self,
         topic: CheetahString,
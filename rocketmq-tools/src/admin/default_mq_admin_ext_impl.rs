@@ -35,11 +35,13 @@ use rocketmq_remoting::protocol::body::broker_body::cluster_info::ClusterInfo;
 use rocketmq_remoting::protocol::body::consume_message_directly_result::ConsumeMessageDirectlyResult;
 use rocketmq_remoting::protocol::body::consumer_connection::ConsumerConnection;
 use rocketmq_remoting::protocol::body::consumer_running_info::ConsumerRunningInfo;
+use rocketmq_remoting::protocol::body::examine_consume_queue_body::ExamineConsumeQueueBody;
 use rocketmq_remoting::protocol::body::group_list::GroupList;
 use rocketmq_remoting::protocol::body::kv_table::KVTable;
 use rocketmq_remoting::protocol::body::producer_connection::ProducerConnection;
 use rocketmq_remoting::protocol::body::topic::topic_list::TopicList;
 use rocketmq_remoting::protocol::body::topic_info_wrapper::TopicConfigSerializeWrapper;
+use rocketmq_remoting::protocol::header::examine_consume_queue_request_header::ExamineConsumeQueueRequestHeader;
 use rocketmq_remoting::protocol::heartbeat::subscription_data::SubscriptionData;
 use rocketmq_remoting::protocol::route::topic_route_data::TopicRouteData;
 use rocketmq_remoting::protocol::static_topic::topic_queue_mapping_detail::TopicQueueMappingDetail;
@@ -537,6 +539,31 @@ impl MQAdminExt for DefaultMQAdminExtImpl {
         todo!()
     }
 
+    async fn examine_consume_queue(
+        &self,
+        broker_addr: CheetahString,
+        topic: CheetahString,
+        queue_id: i32,
+        index: i64,
+        count: i32,
+    ) -> crate::Result<ExamineConsumeQueueBody> {
+        let mq_client_api = self
+            .client_instance
+            .as_ref()
+            .unwrap()
+            .get_mq_client_api_impl();
+        let request_header = ExamineConsumeQueueRequestHeader {
+            topic,
+            queue_id,
+            index,
+            count,
+        };
+        mq_client_api
+            .examine_consume_queue(&broker_addr, request_header, self.timeout_millis)
+            .await
+            .map_err(crate::tools_error::ToolsError::MQClientError)
+    }
+
     async fn resume_check_half_message(
         &self,
         topic: CheetahString,
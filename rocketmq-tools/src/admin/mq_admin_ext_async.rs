@@ -28,6 +28,7 @@ use rocketmq_remoting::protocol::body::broker_body::cluster_info::ClusterInfo;
 use rocketmq_remoting::protocol::body::consume_message_directly_result::ConsumeMessageDirectlyResult;
 use rocketmq_remoting::protocol::body::consumer_connection::ConsumerConnection;
 use rocketmq_remoting::protocol::body::consumer_running_info::ConsumerRunningInfo;
+use rocketmq_remoting::protocol::body::examine_consume_queue_body::ExamineConsumeQueueBody;
 use rocketmq_remoting::protocol::body::group_list::GroupList;
 use rocketmq_remoting::protocol::body::kv_table::KVTable;
 use rocketmq_remoting::protocol::body::producer_connection::ProducerConnection;
@@ -444,6 +445,17 @@ pub trait MQAdminExtLocal: Sync {
         name_servers: Vec<CheetahString>,
     ) -> Result<HashMap<CheetahString, HashMap<CheetahString, CheetahString>>>;
 
+    /// Reads raw consume queue units (physical offset, size and tags code) of one queue,
+    /// starting at queue offset `index`.
+    async fn examine_consume_queue(
+        &self,
+        broker_addr: CheetahString,
+        topic: CheetahString,
+        queue_id: i32,
+        index: i64,
+        count: i32,
+    ) -> Result<ExamineConsumeQueueBody>;
+
     /*async fn query_consume_queue(
         &self,
         broker_addr: CheetahString,
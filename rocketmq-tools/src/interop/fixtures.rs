@@ -0,0 +1,81 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::env;
+use std::path::PathBuf;
+
+use rocketmq_common::common::message::message_decoder;
+use rocketmq_common::TimeUtils::get_current_millis;
+
+pub const PRODUCER_GROUP: &str = "interop_producer_group";
+pub const CONSUMER_GROUP: &str = "interop_consumer_group";
+pub const MESSAGE_TAG: &str = "InteropTag";
+pub const MESSAGE_KEYS: &str = "interop_key";
+pub const MESSAGE_COUNT: usize = 16;
+pub const WRITE_QUEUE_NUMS: i32 = 4;
+
+/// Where the two clusters of the suite run, read from the environment:
+///
+/// - `ROCKETMQ_HOME`: a Java RocketMQ distribution, required, the suite is skipped without it
+/// - `INTEROP_JAVA_NAMESRV`: name server of the Java cluster, `127.0.0.1:9876` by default
+/// - `INTEROP_RUST_NAMESRV`: name server of the Rust cluster, `127.0.0.1:9877` by default
+/// - `INTEROP_CLUSTER`: cluster name topics are created in, `DefaultCluster` by default
+#[derive(Debug, Clone)]
+pub struct InteropConfig {
+    pub rocketmq_home: PathBuf,
+    pub java_namesrv_addr: String,
+    pub rust_namesrv_addr: String,
+    pub cluster_name: String,
+}
+
+impl InteropConfig {
+    pub fn from_env() -> Option<Self> {
+        let rocketmq_home = env::var_os("ROCKETMQ_HOME")?;
+        Some(Self {
+            rocketmq_home: PathBuf::from(rocketmq_home),
+            java_namesrv_addr: env::var("INTEROP_JAVA_NAMESRV")
+                .unwrap_or_else(|_| "127.0.0.1:9876".to_string()),
+            rust_namesrv_addr: env::var("INTEROP_RUST_NAMESRV")
+                .unwrap_or_else(|_| "127.0.0.1:9877".to_string()),
+            cluster_name: env::var("INTEROP_CLUSTER")
+                .unwrap_or_else(|_| "DefaultCluster".to_string()),
+        })
+    }
+}
+
+/// A topic nobody used before, so offsets of a run start at zero.
+pub fn unique_topic(prefix: &str) -> String {
+    format!("{}_{}", prefix, get_current_millis())
+}
+
+pub fn message_body(index: usize) -> String {
+    format!("interop message {}", index)
+}
+
+/// A unique message id as generated by the client: 32 upper case hex digits.
+pub fn is_unique_msg_id(msg_id: &str) -> bool {
+    msg_id.len() == 32
+        && msg_id
+            .chars()
+            .all(|c| c.is_ascii_digit() || ('A'..='F').contains(&c))
+}
+
+/// An offset message id as generated by the broker: the store host followed by the commit log
+/// offset, `None` when `msg_id` is not one.
+pub fn decode_offset_msg_id(msg_id: &str) -> Option<(String, i64)> {
+    message_decoder::try_decode_message_id(msg_id)
+        .map(|message_id| (message_id.address.to_string(), message_id.offset))
+}
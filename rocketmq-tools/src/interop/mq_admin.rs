@@ -0,0 +1,177 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::io;
+use std::path::PathBuf;
+use std::process::Command;
+
+use rocketmq_remoting::protocol::route::topic_route_data::TopicRouteData;
+use rocketmq_remoting::protocol::RemotingDeserializable;
+
+/// One row of `mqadmin sendMessage`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SentMessage {
+    pub broker_name: String,
+    pub queue_id: i32,
+    pub send_status: String,
+    pub msg_id: String,
+}
+
+/// One row of `mqadmin topicStatus`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueueStatus {
+    pub broker_name: String,
+    pub queue_id: i32,
+    pub min_offset: i64,
+    pub max_offset: i64,
+}
+
+/// Drives the Java client through the `mqadmin` script of a RocketMQ distribution.
+pub struct MqAdmin {
+    script: PathBuf,
+}
+
+impl MqAdmin {
+    pub fn new(rocketmq_home: impl Into<PathBuf>) -> Self {
+        Self {
+            script: rocketmq_home.into().join("bin").join("mqadmin"),
+        }
+    }
+
+    /// Runs `mqadmin <command> -n <namesrv_addr> <args>` and returns what it printed.
+    pub fn run(&self, command: &str, namesrv_addr: &str, args: &[&str]) -> io::Result<String> {
+        let output = Command::new(&self.script)
+            .arg(command)
+            .arg("-n")
+            .arg(namesrv_addr)
+            .args(args)
+            .output()?;
+        let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+        if !output.status.success() {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                format!(
+                    "mqadmin {} exited with {}: {}{}",
+                    command,
+                    output.status,
+                    stdout,
+                    String::from_utf8_lossy(&output.stderr)
+                ),
+            ));
+        }
+        Ok(stdout)
+    }
+
+    pub fn update_topic(
+        &self,
+        namesrv_addr: &str,
+        cluster_name: &str,
+        topic: &str,
+        write_queue_nums: i32,
+    ) -> io::Result<()> {
+        let queue_nums = write_queue_nums.to_string();
+        self.run(
+            "updateTopic",
+            namesrv_addr,
+            &[
+                "-c",
+                cluster_name,
+                "-t",
+                topic,
+                "-r",
+                &queue_nums,
+                "-w",
+                &queue_nums,
+            ],
+        )
+        .map(|_| ())
+    }
+
+    pub fn send_message(
+        &self,
+        namesrv_addr: &str,
+        topic: &str,
+        body: &str,
+        tags: &str,
+        keys: &str,
+    ) -> io::Result<SentMessage> {
+        let output = self.run(
+            "sendMessage",
+            namesrv_addr,
+            &["-t", topic, "-p", body, "-c", tags, "-k", keys],
+        )?;
+        parse_send_message(&output).ok_or_else(|| invalid_output("sendMessage", &output))
+    }
+
+    pub fn topic_status(&self, namesrv_addr: &str, topic: &str) -> io::Result<Vec<QueueStatus>> {
+        let output = self.run("topicStatus", namesrv_addr, &["-t", topic])?;
+        Ok(parse_topic_status(&output))
+    }
+
+    pub fn topic_route(&self, namesrv_addr: &str, topic: &str) -> io::Result<TopicRouteData> {
+        let output = self.run("topicRoute", namesrv_addr, &["-t", topic])?;
+        let json = output
+            .find('{')
+            .map(|start| &output[start..])
+            .ok_or_else(|| invalid_output("topicRoute", &output))?;
+        TopicRouteData::decode(json.as_bytes()).map_err(|_| invalid_output("topicRoute", &output))
+    }
+}
+
+fn invalid_output(command: &str, output: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("unexpected mqadmin {} output: {}", command, output),
+    )
+}
+
+/// Rows follow a header starting with `#Broker Name`, columns are separated by blanks.
+fn table_rows(output: &str) -> impl Iterator<Item = Vec<&str>> {
+    output
+        .lines()
+        .skip_while(|line| !line.trim_start().starts_with("#Broker Name"))
+        .skip(1)
+        .map(|line| line.split_whitespace().collect::<Vec<_>>())
+        .filter(|columns| !columns.is_empty())
+}
+
+/// `#Broker Name  #QID  #Send Result  #MsgId`
+pub fn parse_send_message(output: &str) -> Option<SentMessage> {
+    let columns = table_rows(output).next()?;
+    if columns.len() < 4 {
+        return None;
+    }
+    Some(SentMessage {
+        broker_name: columns[0].to_string(),
+        queue_id: columns[1].parse().ok()?,
+        send_status: columns[2].to_string(),
+        msg_id: columns[3].to_string(),
+    })
+}
+
+/// `#Broker Name  #QID  #Min Offset  #Max Offset  #Last Updated`
+pub fn parse_topic_status(output: &str) -> Vec<QueueStatus> {
+    table_rows(output)
+        .filter_map(|columns| {
+            Some(QueueStatus {
+                broker_name: columns.first()?.to_string(),
+                queue_id: columns.get(1)?.parse().ok()?,
+                min_offset: columns.get(2)?.parse().ok()?,
+                max_offset: columns.get(3)?.parse().ok()?,
+            })
+        })
+        .collect()
+}
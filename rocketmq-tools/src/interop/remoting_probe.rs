@@ -0,0 +1,156 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+use std::sync::Arc;
+
+use cheetah_string::CheetahString;
+use rocketmq_common::common::message::message_decoder;
+use rocketmq_common::common::message::message_ext::MessageExt;
+use rocketmq_common::common::mix_all;
+use rocketmq_remoting::clients::rocketmq_default_impl::RocketmqDefaultClient;
+use rocketmq_remoting::clients::RemotingClient;
+use rocketmq_remoting::code::request_code::RequestCode;
+use rocketmq_remoting::code::response_code::ResponseCode;
+use rocketmq_remoting::protocol::header::client_request_header::GetRouteInfoRequestHeader;
+use rocketmq_remoting::protocol::header::pull_message_request_header::PullMessageRequestHeader;
+use rocketmq_remoting::protocol::header::pull_message_response_header::PullMessageResponseHeader;
+use rocketmq_remoting::protocol::remoting_command::RemotingCommand;
+use rocketmq_remoting::protocol::route::topic_route_data::TopicRouteData;
+use rocketmq_remoting::protocol::RemotingDeserializable;
+use rocketmq_remoting::remoting::RemotingService;
+use rocketmq_remoting::request_processor::default_request_processor::DefaultRemotingRequestProcessor;
+use rocketmq_remoting::runtime::config::client_config::TokioClientConfig;
+use rocketmq_rust::ArcMut;
+
+use crate::interop::fixtures::CONSUMER_GROUP;
+
+const TIMEOUT_MILLIS: u64 = 5000;
+
+/// What a broker answered to a pull, the header fields decoded as the Rust client does.
+pub struct PullResponse {
+    pub code: ResponseCode,
+    pub header: PullMessageResponseHeader,
+    pub messages: Vec<MessageExt>,
+}
+
+/// Talks to a name server and its brokers with raw remoting commands, so the suite asserts
+/// what is on the wire rather than what the client makes of it.
+pub struct RemotingProbe {
+    remoting_client: ArcMut<RocketmqDefaultClient<DefaultRemotingRequestProcessor>>,
+}
+
+impl RemotingProbe {
+    pub async fn new(namesrv_addr: &str) -> Self {
+        let remoting_client = ArcMut::new(RocketmqDefaultClient::new(
+            Arc::new(TokioClientConfig::default()),
+            DefaultRemotingRequestProcessor,
+        ));
+        remoting_client
+            .update_name_server_address_list(vec![CheetahString::from(namesrv_addr)])
+            .await;
+        remoting_client
+            .start(ArcMut::downgrade(&remoting_client))
+            .await;
+        Self { remoting_client }
+    }
+
+    async fn invoke(
+        &self,
+        addr: Option<&CheetahString>,
+        request: RemotingCommand,
+    ) -> Result<RemotingCommand, String> {
+        self.remoting_client
+            .invoke_async(addr, request, TIMEOUT_MILLIS)
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    pub async fn topic_route(&self, topic: &str) -> Result<TopicRouteData, String> {
+        let header = GetRouteInfoRequestHeader {
+            topic: CheetahString::from(topic),
+            ..Default::default()
+        };
+        let request =
+            RemotingCommand::create_request_command(RequestCode::GetRouteinfoByTopic, header);
+        let response = self.invoke(None, request).await?;
+        if ResponseCode::from(response.code()) != ResponseCode::Success {
+            return Err(format!(
+                "get route of {} failed, code: {}",
+                topic,
+                response.code()
+            ));
+        }
+        response
+            .body()
+            .as_ref()
+            .and_then(|body| TopicRouteData::decode(body).ok())
+            .ok_or_else(|| format!("undecodable route of {}", topic))
+    }
+
+    /// Master address of `broker_name` in the route of `topic`.
+    pub async fn master_addr(&self, topic: &str, broker_name: &str) -> Result<String, String> {
+        let route = self.topic_route(topic).await?;
+        route
+            .broker_datas
+            .iter()
+            .find(|broker_data| broker_data.broker_name().as_str() == broker_name)
+            .and_then(|broker_data| broker_data.broker_addrs().get(&mix_all::MASTER_ID))
+            .map(|addr| addr.to_string())
+            .ok_or_else(|| format!("no master of {} serves {}", broker_name, topic))
+    }
+
+    /// Pulls without suspending and without committing an offset.
+    pub async fn pull(
+        &self,
+        broker_addr: &str,
+        topic: &str,
+        queue_id: i32,
+        queue_offset: i64,
+        max_msg_nums: i32,
+    ) -> Result<PullResponse, String> {
+        let header = PullMessageRequestHeader {
+            consumer_group: CheetahString::from_static_str(CONSUMER_GROUP),
+            topic: CheetahString::from(topic),
+            queue_id,
+            queue_offset,
+            max_msg_nums,
+            sys_flag: 0,
+            commit_offset: 0,
+            suspend_timeout_millis: 0,
+            subscription: Some(CheetahString::from_static_str("*")),
+            sub_version: 0,
+            expression_type: None,
+            max_msg_bytes: None,
+            request_source: None,
+            proxy_forward_client_id: None,
+            projected_properties: None,
+            topic_request: None,
+        };
+        let request = RemotingCommand::create_request_command(RequestCode::PullMessage, header);
+        let response = self
+            .invoke(Some(&CheetahString::from(broker_addr)), request)
+            .await?;
+        let header = response
+            .decode_command_custom_header::<PullMessageResponseHeader>()
+            .map_err(|e| format!("undecodable pull response header: {}", e))?;
+        let mut body = response.body().clone().unwrap_or_default();
+        Ok(PullResponse {
+            code: ResponseCode::from(response.code()),
+            header,
+            messages: message_decoder::decodes_batch(&mut body, true, true),
+        })
+    }
+}
@@ -17,6 +17,8 @@
 use crate::tools_error::ToolsError;
 
 pub mod admin;
+#[cfg(feature = "interop")]
+pub mod interop;
 pub mod tools_error;
 
 pub type Result<T> = std::result::Result<T, ToolsError>;
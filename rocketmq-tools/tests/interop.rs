@@ -0,0 +1,274 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one or more
+ * contributor license agreements.  See the NOTICE file distributed with
+ * this work for additional information regarding copyright ownership.
+ * The ASF licenses this file to You under the Apache License, Version 2.0
+ * (the "License"); you may not use this file except in compliance with
+ * the License.  You may obtain a copy of the License at
+ *
+ *     http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing, software
+ * distributed under the License is distributed on an "AS IS" BASIS,
+ * WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+ * See the License for the specific language governing permissions and
+ * limitations under the License.
+ */
+//! Wire-level conformance against the Java implementation: the Rust client against a Java
+//! broker and the Java tools against a Rust broker. Both clusters have to be running, see
+//! `InteropConfig` for where they are expected.
+//!
+//! ```shell
+//! ROCKETMQ_HOME=/opt/rocketmq cargo test -p rocketmq-tools --features interop --test interop
+//! ```
+
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+
+use cheetah_string::CheetahString;
+use rocketmq_client_rust::producer::default_mq_producer::DefaultMQProducer;
+use rocketmq_client_rust::producer::mq_producer::MQProducer;
+use rocketmq_client_rust::producer::send_status::SendStatus;
+use rocketmq_common::common::message::message_single::Message;
+use rocketmq_common::common::message::MessageConst;
+use rocketmq_common::common::message::MessageTrait;
+use rocketmq_remoting::code::response_code::ResponseCode;
+use rocketmq_remoting::protocol::route::topic_route_data::TopicRouteData;
+use rocketmq_tools::interop::fixtures::decode_offset_msg_id;
+use rocketmq_tools::interop::fixtures::is_unique_msg_id;
+use rocketmq_tools::interop::fixtures::message_body;
+use rocketmq_tools::interop::fixtures::unique_topic;
+use rocketmq_tools::interop::fixtures::InteropConfig;
+use rocketmq_tools::interop::fixtures::MESSAGE_COUNT;
+use rocketmq_tools::interop::fixtures::MESSAGE_KEYS;
+use rocketmq_tools::interop::fixtures::MESSAGE_TAG;
+use rocketmq_tools::interop::fixtures::PRODUCER_GROUP;
+use rocketmq_tools::interop::fixtures::WRITE_QUEUE_NUMS;
+use rocketmq_tools::interop::mq_admin::MqAdmin;
+use rocketmq_tools::interop::remoting_probe::RemotingProbe;
+
+/// A message as the producer saw it after sending.
+struct Sent {
+    unique_msg_id: String,
+    /// Only the Rust producer reports it.
+    offset_msg_id: Option<String>,
+    body: String,
+}
+
+/// Sent messages per `(brokerName, queueId)`, in send order.
+type SentByQueue = BTreeMap<(String, i32), Vec<Sent>>;
+
+fn config() -> Option<InteropConfig> {
+    let config = InteropConfig::from_env();
+    if config.is_none() {
+        eprintln!("ROCKETMQ_HOME is not set, skipping the interop suite");
+    }
+    config
+}
+
+/// Pulls every queue of `sent` back and checks offsets, ids, properties and bodies.
+async fn assert_pulled(probe: &RemotingProbe, topic: &str, sent: &SentByQueue) {
+    for ((broker_name, queue_id), expected) in sent {
+        let broker_addr = probe.master_addr(topic, broker_name).await.unwrap();
+        let count = expected.len() as i64;
+        let response = probe
+            .pull(&broker_addr, topic, *queue_id, 0, MESSAGE_COUNT as i32)
+            .await
+            .unwrap();
+        assert_eq!(response.code, ResponseCode::Success);
+        assert_eq!(response.header.min_offset, 0);
+        assert_eq!(response.header.max_offset, count);
+        assert_eq!(response.header.next_begin_offset, count);
+        assert_eq!(response.messages.len(), expected.len());
+
+        for (queue_offset, (msg, sent)) in response.messages.iter().zip(expected).enumerate() {
+            assert_eq!(msg.queue_offset(), queue_offset as i64);
+            assert_eq!(msg.queue_id(), *queue_id);
+            assert_eq!(msg.get_topic().as_str(), topic);
+            assert_eq!(
+                msg.get_property(&CheetahString::from_static_str(
+                    MessageConst::PROPERTY_UNIQ_CLIENT_MESSAGE_ID_KEYIDX
+                ))
+                .unwrap_or_default()
+                .as_str(),
+                sent.unique_msg_id
+            );
+            let (_, commit_log_offset) = decode_offset_msg_id(msg.msg_id()).unwrap();
+            assert_eq!(commit_log_offset, msg.commit_log_offset());
+            if let Some(offset_msg_id) = sent.offset_msg_id.as_ref() {
+                assert_eq!(msg.msg_id().as_str(), offset_msg_id);
+            }
+            assert_eq!(msg.get_tags().unwrap_or_default().as_str(), MESSAGE_TAG);
+            assert_eq!(msg.get_keys().unwrap_or_default().as_str(), MESSAGE_KEYS);
+            assert_eq!(msg.body().unwrap().as_ref(), sent.body.as_bytes());
+        }
+
+        // pulling at the max offset is answered with the same offsets by both brokers
+        let response = probe
+            .pull(&broker_addr, topic, *queue_id, count, MESSAGE_COUNT as i32)
+            .await
+            .unwrap();
+        assert_eq!(response.code, ResponseCode::PullNotFound);
+        assert_eq!(response.header.next_begin_offset, count);
+        assert!(response.messages.is_empty());
+    }
+}
+
+fn broker_addrs(route: &TopicRouteData) -> BTreeMap<String, HashMap<u64, CheetahString>> {
+    route
+        .broker_datas
+        .iter()
+        .map(|broker_data| {
+            (
+                broker_data.broker_name().to_string(),
+                broker_data.broker_addrs().clone(),
+            )
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn rust_client_against_java_broker() {
+    let Some(config) = config() else {
+        return;
+    };
+    let admin = MqAdmin::new(&config.rocketmq_home);
+    let topic = unique_topic("RustToJava");
+    admin
+        .update_topic(
+            &config.java_namesrv_addr,
+            &config.cluster_name,
+            &topic,
+            WRITE_QUEUE_NUMS,
+        )
+        .unwrap();
+
+    let mut producer = DefaultMQProducer::builder()
+        .producer_group(PRODUCER_GROUP)
+        .name_server_addr(config.java_namesrv_addr.as_str())
+        .build();
+    producer.start().await.unwrap();
+    let mut sent = SentByQueue::new();
+    for index in 0..MESSAGE_COUNT {
+        let body = message_body(index);
+        let message =
+            Message::with_keys(topic.as_str(), MESSAGE_TAG, MESSAGE_KEYS, body.as_bytes());
+        let result = producer.send_with_timeout(message, 3000).await.unwrap();
+        assert_eq!(result.send_status, SendStatus::SendOk);
+        let unique_msg_id = result.msg_id.clone().unwrap().to_string();
+        assert!(is_unique_msg_id(&unique_msg_id), "{}", unique_msg_id);
+        let offset_msg_id = result.offset_msg_id.clone().unwrap();
+        assert!(
+            decode_offset_msg_id(&offset_msg_id).is_some(),
+            "{}",
+            offset_msg_id
+        );
+
+        let message_queue = result.message_queue.clone().unwrap();
+        let queue = sent
+            .entry((
+                message_queue.get_broker_name().to_string(),
+                message_queue.get_queue_id(),
+            ))
+            .or_default();
+        assert_eq!(result.queue_offset, queue.len() as u64);
+        queue.push(Sent {
+            unique_msg_id,
+            offset_msg_id: Some(offset_msg_id),
+            body,
+        });
+    }
+    producer.shutdown().await;
+
+    let probe = RemotingProbe::new(&config.java_namesrv_addr).await;
+    assert_pulled(&probe, &topic, &sent).await;
+}
+
+#[tokio::test]
+async fn java_client_against_rust_broker() {
+    let Some(config) = config() else {
+        return;
+    };
+    let admin = MqAdmin::new(&config.rocketmq_home);
+    let topic = unique_topic("JavaToRust");
+    admin
+        .update_topic(
+            &config.rust_namesrv_addr,
+            &config.cluster_name,
+            &topic,
+            WRITE_QUEUE_NUMS,
+        )
+        .unwrap();
+
+    let mut sent = SentByQueue::new();
+    for index in 0..MESSAGE_COUNT {
+        let body = message_body(index);
+        let result = admin
+            .send_message(
+                &config.rust_namesrv_addr,
+                &topic,
+                &body,
+                MESSAGE_TAG,
+                MESSAGE_KEYS,
+            )
+            .unwrap();
+        assert_eq!(result.send_status, "SEND_OK");
+        assert!(is_unique_msg_id(&result.msg_id), "{}", result.msg_id);
+        sent.entry((result.broker_name, result.queue_id))
+            .or_default()
+            .push(Sent {
+                unique_msg_id: result.msg_id,
+                offset_msg_id: None,
+                body,
+            });
+    }
+
+    let probe = RemotingProbe::new(&config.rust_namesrv_addr).await;
+    assert_pulled(&probe, &topic, &sent).await;
+
+    let status = admin
+        .topic_status(&config.rust_namesrv_addr, &topic)
+        .unwrap();
+    for ((broker_name, queue_id), messages) in &sent {
+        let queue = status
+            .iter()
+            .find(|queue| &queue.broker_name == broker_name && queue.queue_id == *queue_id)
+            .unwrap();
+        assert_eq!(queue.min_offset, 0);
+        assert_eq!(queue.max_offset, messages.len() as i64);
+    }
+}
+
+#[tokio::test]
+async fn routes_decode_the_same_on_both_sides() {
+    let Some(config) = config() else {
+        return;
+    };
+    let admin = MqAdmin::new(&config.rocketmq_home);
+    for namesrv_addr in [&config.java_namesrv_addr, &config.rust_namesrv_addr] {
+        let topic = unique_topic("InteropRoute");
+        admin
+            .update_topic(namesrv_addr, &config.cluster_name, &topic, WRITE_QUEUE_NUMS)
+            .unwrap();
+
+        let java_route = admin.topic_route(namesrv_addr, &topic).unwrap();
+        let rust_route = RemotingProbe::new(namesrv_addr)
+            .await
+            .topic_route(&topic)
+            .await
+            .unwrap();
+        assert_eq!(java_route.queue_datas.len(), rust_route.queue_datas.len());
+        for queue_data in &rust_route.queue_datas {
+            assert_eq!(queue_data.write_queue_nums, WRITE_QUEUE_NUMS as u32);
+            let java_queue_data = java_route
+                .queue_datas
+                .iter()
+                .find(|java| java.broker_name == queue_data.broker_name)
+                .unwrap();
+            assert_eq!(java_queue_data.read_queue_nums, queue_data.read_queue_nums);
+            assert_eq!(java_queue_data.perm, queue_data.perm);
+            assert_eq!(java_queue_data.topic_sys_flag, queue_data.topic_sys_flag);
+        }
+        assert_eq!(broker_addrs(&java_route), broker_addrs(&rust_route));
+    }
+}